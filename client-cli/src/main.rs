@@ -6,20 +6,37 @@ mod table_print;
 mod utils;
 
 use crate::simple_db_cli::SimpleDbCli;
+use crate::table_print::OutputFormat;
 use std::env;
 
 fn main() {
-    let (address, password) = get_database_args();
-    let mut app = SimpleDbCli::create(address, password);
+    let (address, password, output_format, max_column_width) = get_database_args();
+    let mut app = SimpleDbCli::create(address, password, output_format, max_column_width);
     app.start()
 }
 
-//Address, Password
-fn get_database_args() -> (String, String) {
+//Address, Password, Output format, Max column width
+fn get_database_args() -> (String, String, OutputFormat, Option<usize>) {
     let args: Vec<String> = env::args().collect();
     if args.len() < 3 {
-        panic!("Invalid args. Expect <address> <password>")
+        panic!("Invalid args. Expect <address> <password> [--format table|csv|json] [--max-column-width <width>]")
     }
 
-    (args[1].clone(), args[2].clone())
+    let output_format = match args.iter().position(|arg| arg == "--format") {
+        Some(format_index) => match args.get(format_index + 1).map(|format| format.as_str()) {
+            Some("table") => OutputFormat::Table,
+            Some("csv") => OutputFormat::Csv,
+            Some("json") => OutputFormat::Json,
+            _ => panic!("Invalid args. Expect --format table|csv|json")
+        },
+        None => OutputFormat::Table,
+    };
+
+    let max_column_width = args.iter().position(|arg| arg == "--max-column-width")
+        .map(|width_index| match args.get(width_index + 1).and_then(|width| width.parse::<usize>().ok()) {
+            Some(max_column_width) => max_column_width,
+            None => panic!("Invalid args. Expect --max-column-width <width>")
+        });
+
+    (args[1].clone(), args[2].clone(), output_format, max_column_width)
 }
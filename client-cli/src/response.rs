@@ -1,6 +1,6 @@
 use bytes::Bytes;
 use shared::connection::Connection;
-use shared::{utils, ColumnId, ErrorTypeId};
+use shared::{timestamp, utils, ColumnId, ErrorTypeId};
 use std::collections::HashMap;
 
 pub enum IndexType {
@@ -21,6 +21,7 @@ pub enum StatementResponse {
     Tables(Vec<String>),
     Describe(Vec<ColumnDescriptor>),
     Indexes(Vec<(String, IndexType)>),
+    Explain(Vec<String>), //One line per plan step
 }
 
 pub struct QueryDataResponse {
@@ -55,6 +56,7 @@ pub enum ColumnType {
     String,
     Date,
     Blob,
+    Timestamp,
 }
 
 impl Response {
@@ -68,6 +70,7 @@ impl Response {
                     4 => StatementResponse::Tables(Self::deserialize_string_vec(connection)),
                     5 => StatementResponse::Describe(Self::deserialize_column_dec(connection)),
                     6 => StatementResponse::Indexes(Self::deserialize_indexes(connection)),
+                    7 => StatementResponse::Explain(Self::deserialize_string_vec(connection)),
                     _ => panic!("Invalid statement response type Id")
                 })
             },
@@ -184,6 +187,7 @@ impl ColumnType {
             12 => ColumnType::String,
             13 => ColumnType::Date,
             14 => ColumnType::Blob,
+            15 => ColumnType::Timestamp,
             _ => panic!("Cannot deserialize column type ID")
         }
     }
@@ -204,6 +208,7 @@ impl ColumnType {
             ColumnType::String => String::from_utf8(value.to_vec()).unwrap(),
             ColumnType::Date => todo!(),
             ColumnType::Blob => format!("{:02X?}", value.to_vec()),
+            ColumnType::Timestamp => timestamp::format_iso8601(utils::bytes_to_i64_le(value)),
         }
     }
 
@@ -223,6 +228,22 @@ impl ColumnType {
             ColumnType::String => "String",
             ColumnType::Date => "Date",
             ColumnType::Blob => "Blob",
+            ColumnType::Timestamp => "Timestamp",
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::response::ColumnType;
+    use bytes::Bytes;
+
+    #[test]
+    fn bytes_to_string_boolean_timestamp() {
+        assert_eq!(ColumnType::deserialize(11).bytes_to_string(&Bytes::from(vec![0x01])), "true");
+        assert_eq!(ColumnType::deserialize(11).bytes_to_string(&Bytes::from(vec![0x00])), "false");
+
+        let timestamp = Bytes::copy_from_slice(&1672531200000i64.to_le_bytes());
+        assert_eq!(ColumnType::deserialize(15).bytes_to_string(&timestamp), "2023-01-01T00:00:00Z");
+        assert_eq!(ColumnType::deserialize(15).to_string(), "Timestamp");
+    }
+}
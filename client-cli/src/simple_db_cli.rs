@@ -1,7 +1,7 @@
 use crate::request::Request;
 use crate::response::{ColumnDescriptor, IndexType, QueryDataResponse, Response, StatementResponse};
 use crate::simpledb_server::SimpleDbServer;
-use crate::table_print::TablePrint;
+use crate::table_print::{OutputFormat, TablePrint};
 use std::cmp::Ordering;
 use std::io;
 use std::io::{stdout, Write};
@@ -14,16 +14,22 @@ pub struct SimpleDbCli {
     server: SimpleDbServer,
     password: String,
     is_standalone: bool,
+    output_format: OutputFormat,
+    max_column_width: Option<usize>,
 }
 
 impl SimpleDbCli {
     pub fn create(
         address: String,
         password: String,
+        output_format: OutputFormat,
+        max_column_width: Option<usize>,
     ) -> SimpleDbCli {
         SimpleDbCli {
             server: SimpleDbServer::create(address),
             is_standalone: true,
+            output_format,
+            max_column_width,
             password
         }
     }
@@ -70,6 +76,7 @@ impl SimpleDbCli {
                     StatementResponse::Tables(tables) => self.print_vec_string_as_table("Tables", tables, duration),
                     StatementResponse::Describe(desc) => self.print_table_describe(&desc, duration),
                     StatementResponse::Indexes(indexes) => self.print_show_indexes(indexes, duration),
+                    StatementResponse::Explain(lines) => self.print_vec_string_as_table("Plan", lines, duration),
                 };
             }
             Response::Error(error_type_id) => {
@@ -80,11 +87,13 @@ impl SimpleDbCli {
             }
         };
 
-        print!("\n");
+        if self.output_format == OutputFormat::Table {
+            print!("\n");
+        }
     }
 
     fn print_show_indexes(&self, mut indexes: Vec<(String, IndexType)>, duration: Duration) {
-        let mut table = TablePrint::create(2);
+        let mut table = self.create_table(2);
         table.add_header("Field");
         table.add_header("Type");
 
@@ -110,7 +119,7 @@ impl SimpleDbCli {
                 return Ordering::Equal
             }
         });
-        let mut query_data_table = TablePrint::create(columns_desc.len());
+        let mut query_data_table = self.create_table(columns_desc.len());
 
         for current_column_desc in &columns_desc {
             query_data_table.add_header(current_column_desc.column_name.as_str());
@@ -121,7 +130,7 @@ impl SimpleDbCli {
                 if let Some(column_value) = row.columns.get(&current_column_desc.column_id) {
                     query_data_table.add_column_value(current_column_desc.column_type.bytes_to_string(column_value));
                 } else {
-                    query_data_table.add_column_value("N/A".to_string());
+                    query_data_table.add_null_value();
                 }
             }
         }
@@ -134,7 +143,7 @@ impl SimpleDbCli {
         columns_desc: &Vec<ColumnDescriptor>,
         duration: Duration
     ) {
-        let mut table = TablePrint::create(4);
+        let mut table = self.create_table(4);
         table.add_header("Field");
         table.add_header("Type");
         table.add_header("Primary");
@@ -168,7 +177,7 @@ impl SimpleDbCli {
         vec: Vec<String>,
         duration: Duration
     ) {
-        let mut table = TablePrint::create(1);
+        let mut table = self.create_table(1);
         table.add_header(table_header_name);
         for item in vec {
             table.add_column_value(item);
@@ -176,6 +185,14 @@ impl SimpleDbCli {
         table.print(duration);
     }
 
+    fn create_table(&self, n_columns: usize) -> TablePrint {
+        let mut table = TablePrint::create(n_columns, self.output_format);
+        if let Some(max_column_width) = self.max_column_width {
+            table.set_max_column_width(max_column_width);
+        }
+        table
+    }
+
     fn exit_command(&mut self) {
         self.server.send_request(Request::Close(self.password.clone()));
 
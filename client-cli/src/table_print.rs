@@ -1,17 +1,45 @@
-use std::cmp::max;
+use std::borrow::Cow;
+use std::cmp::{max, min};
 use std::time::Duration;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 use crate::utils::duration_to_string;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutputFormat {
+    Table,
+    //RFC-4180. The header is the first record. No summary line is printed
+    Csv,
+    //Array of objects keyed by column name. No summary line is printed
+    Json,
+}
+
+//How null values are printed in tables & CSV
+const NULL_VALUE: &str = "N/A";
+
+const DEFAULT_MAX_COLUMN_WIDTH: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Alignment {
+    Left,
+    Right,
+}
+
 pub struct TablePrint {
     header: Vec<String>,
-    rows: Vec<Vec<String>>,
+    rows: Vec<Vec<Option<String>>>, //None if the value is null
+    //Width of the values, without the space added at each side of the cells. At most max_column_width
     columns_width: Vec<usize>,
+    //Longer values are truncated with an ellipsis when printed as a table
+    max_column_width: usize,
+    //Columns whose values are all numbers are aligned to the right. None if the column has no values yet
+    columns_alignment: Vec<Option<Alignment>>,
 
     n_columns: usize,
+    output_format: OutputFormat,
 }
 
 impl TablePrint {
-    pub fn create(n_columns: usize) -> TablePrint {
+    pub fn create(n_columns: usize, output_format: OutputFormat) -> TablePrint {
         let mut columns_width = Vec::new();
         for _ in 0..n_columns {
             columns_width.push(0);
@@ -21,22 +49,45 @@ impl TablePrint {
             header: Vec::new(),
             rows: Vec::new(),
             columns_width,
-            n_columns
+            max_column_width: DEFAULT_MAX_COLUMN_WIDTH,
+            columns_alignment: vec![None; n_columns],
+            n_columns,
+            output_format,
         }
     }
 
     pub fn add_header(&mut self, header: &str) {
-        let header = Self::format_cell(header);
-
         let column_index = self.header.len();
         self.header.push(header.to_string());
 
-        self.columns_width[column_index] = max(self.columns_width[column_index], header.len());
+        self.update_column_width(column_index, header);
+    }
+
+    pub fn set_max_column_width(&mut self, max_column_width: usize) {
+        //Room for at least the ellipsis
+        self.max_column_width = max(max_column_width, 1);
+
+        self.columns_width = vec![0; self.n_columns];
+        for column_index in 0..self.header.len() {
+            self.update_column_width(column_index, &self.header[column_index].clone());
+        }
+        for row_index in 0..self.rows.len() {
+            for column_index in 0..self.rows[row_index].len() {
+                let value = self.rows[row_index][column_index].clone();
+                self.update_column_width(column_index, value.as_deref().unwrap_or(NULL_VALUE));
+            }
+        }
     }
 
     pub fn add_column_value(&mut self, value: String) {
-        let value = Self::format_cell(value.as_str());
+        self.add_cell(Some(value));
+    }
+
+    pub fn add_null_value(&mut self) {
+        self.add_cell(None);
+    }
 
+    fn add_cell(&mut self, value: Option<String>) {
         if self.rows.is_empty() {
             self.rows.push(Vec::new());
         }
@@ -51,82 +102,399 @@ impl TablePrint {
         }
 
         let n_column_index = row_vec.len();
-        let value_width = value.len();
+        //Nulls don't change the alignment of the column
+        if let Some(value) = &value {
+            let is_number = value.parse::<f64>().is_ok_and(|number| number.is_finite());
+            self.columns_alignment[n_column_index] = match self.columns_alignment[n_column_index] {
+                None | Some(Alignment::Right) if is_number => Some(Alignment::Right),
+                _ => Some(Alignment::Left),
+            };
+        }
 
-        row_vec.push(value);
+        row_vec.push(value.clone());
 
-        self.columns_width[n_column_index] = max(self.columns_width[n_column_index], value_width);
+        self.update_column_width(n_column_index, value.as_deref().unwrap_or(NULL_VALUE));
+    }
+
+    fn update_column_width(&mut self, column_index: usize, value: &str) {
+        let value_width = min(Self::display_width(value), self.max_column_width);
+        self.columns_width[column_index] = max(self.columns_width[column_index], value_width);
+    }
+
+    //Number of terminal columns. Wide characters (CJK, emojis) take two and combining marks none
+    fn display_width(value: &str) -> usize {
+        UnicodeWidthStr::width(value)
+    }
+
+    //Values wider than max_column_width keep their first characters followed by an ellipsis
+    fn truncate<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if Self::display_width(value) <= self.max_column_width {
+            return Cow::Borrowed(value);
+        }
+
+        let mut truncated = String::new();
+        let mut truncated_width = 0;
+        for char in value.chars() {
+            let char_width = UnicodeWidthChar::width(char).unwrap_or(0);
+            //Room for the ellipsis
+            if truncated_width + char_width > self.max_column_width - 1 {
+                break;
+            }
+            truncated.push(char);
+            truncated_width += char_width;
+        }
+        truncated.push('…');
+        Cow::Owned(truncated)
     }
 
     pub fn print(&self, duration: Duration) {
-        self.print_horizontal_line();
+        match self.output_format {
+            OutputFormat::Table => self.print_table(duration),
+            OutputFormat::Csv => self.print_csv(),
+            OutputFormat::Json => self.print_json(),
+        }
+    }
 
-        self.print_header_row();
-        self.print_rows();
+    fn print_json(&self) {
+        println!("{}", self.to_json());
+    }
 
-        self.print_horizontal_line();
+    //Values of columns whose values are all numbers are emitted as JSON numbers, the rest as strings
+    fn to_json(&self) -> String {
+        let numeric_columns: Vec<bool> = (0..self.n_columns)
+            .map(|column_index| self.is_numeric_column(column_index))
+            .collect();
 
-        self.print_resume(duration);
+        let json_rows: Vec<String> = self.rows.iter()
+            .map(|row| {
+                let fields: Vec<String> = row.iter().enumerate()
+                    .map(|(column_index, cell)| {
+                        let value = match cell {
+                            None => String::from("null"),
+                            Some(cell) if numeric_columns[column_index] => cell.clone(),
+                            Some(cell) => Self::format_json_string(cell),
+                        };
+                        format!("{}: {}", Self::format_json_string(&self.header[column_index]), value)
+                    })
+                    .collect();
+                format!("  {{{}}}", fields.join(", "))
+            })
+            .collect();
+
+        if json_rows.is_empty() {
+            String::from("[]")
+        } else {
+            format!("[\n{}\n]", json_rows.join(",\n"))
+        }
     }
 
-    fn print_resume(&self, duration: Duration) {
-        println!("{} rows in set ({})", self.rows.len(), duration_to_string(duration));
+    fn is_numeric_column(&self, column_index: usize) -> bool {
+        self.rows.iter()
+            .filter_map(|row| row.get(column_index).and_then(|cell| cell.as_ref()))
+            .all(|cell| Self::is_json_number(cell))
     }
 
-    fn print_rows(&self) {
-        for (_, row) in self.rows.iter().enumerate() {
-            for (column_index, cell) in row.iter().enumerate() {
-                let column_width: usize = self.columns_width[column_index];
+    //JSON grammar: -? (0 | [1-9][0-9]*) (.[0-9]+)? ([eE][+-]?[0-9]+)?
+    fn is_json_number(value: &str) -> bool {
+        let bytes = value.as_bytes();
+        let mut index = if bytes.first() == Some(&b'-') { 1 } else { 0 };
 
-                print!("|");
+        let n_integer_digits = Self::count_digits(&bytes[index..]);
+        if n_integer_digits == 0 || (n_integer_digits > 1 && bytes[index] == b'0') {
+            return false;
+        }
+        index += n_integer_digits;
 
-                print!("{}", cell);
-                for _ in 0..(column_width - cell.len()) {
-                    print!(" ");
-                }
+        if bytes.get(index) == Some(&b'.') {
+            let n_fraction_digits = Self::count_digits(&bytes[index + 1..]);
+            if n_fraction_digits == 0 {
+                return false;
+            }
+            index += 1 + n_fraction_digits;
+        }
 
-                if column_index + 1 == self.n_columns {
-                    print!("|");
-                    print!("\n");
-                }
+        if matches!(bytes.get(index), Some(b'e') | Some(b'E')) {
+            index += 1;
+            if matches!(bytes.get(index), Some(b'+') | Some(b'-')) {
+                index += 1;
+            }
+            let n_exponent_digits = Self::count_digits(&bytes[index..]);
+            if n_exponent_digits == 0 {
+                return false;
             }
+            index += n_exponent_digits;
         }
+
+        index == bytes.len()
+    }
+
+    fn count_digits(bytes: &[u8]) -> usize {
+        bytes.iter().take_while(|byte| byte.is_ascii_digit()).count()
     }
 
-    fn print_header_row(&self) {
-        print!("|");
-        for (column_index, header) in self.header.iter().enumerate() {
-            print!("{}", header);
-            let column_width: usize = self.columns_width[column_index];
-            for _ in 0..(column_width - header.len()) {
-                print!(" ");
+    fn format_json_string(value: &str) -> String {
+        let mut json_string = String::with_capacity(value.len() + 2);
+        json_string.push('"');
+        for char in value.chars() {
+            match char {
+                '"' => json_string.push_str("\\\""),
+                '\\' => json_string.push_str("\\\\"),
+                '\n' => json_string.push_str("\\n"),
+                '\r' => json_string.push_str("\\r"),
+                '\t' => json_string.push_str("\\t"),
+                char if (char as u32) < 0x20 => json_string.push_str(&format!("\\u{:04x}", char as u32)),
+                char => json_string.push(char),
             }
+        }
+        json_string.push('"');
+        json_string
+    }
+
+    fn print_csv(&self) {
+        print!("{}", self.to_csv());
+    }
+
+    fn to_csv(&self) -> String {
+        let header: Vec<&str> = self.header.iter().map(|header| header.as_str()).collect();
+        let records = self.rows.iter()
+            .map(|row| row.iter().map(|cell| cell.as_deref().unwrap_or(NULL_VALUE)).collect());
+
+        let mut csv = String::new();
+        for record in std::iter::once(header).chain(records) {
+            let fields: Vec<String> = record.iter()
+                .map(|field| Self::format_csv_field(field))
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+
+        csv
+    }
+
+    //Fields containing commas, quotes or line breaks are enclosed in quotes. Quotes inside them are doubled
+    fn format_csv_field(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn print_table(&self, duration: Duration) {
+        for line in self.format_table() {
+            println!("{}", line);
+        }
+
+        self.print_resume(duration);
+    }
+
+    fn print_resume(&self, duration: Duration) {
+        println!("{} rows in set ({})", self.rows.len(), duration_to_string(duration));
+    }
+
+    fn format_table(&self) -> Vec<String> {
+        let horizontal_line = self.format_horizontal_line();
+        let mut lines = vec![horizontal_line.clone()];
+
+        lines.push(self.format_row(self.header.iter().map(|header| header.as_str())));
+        lines.push(horizontal_line.clone());
+        for row in &self.rows {
+            lines.push(self.format_row(row.iter().map(|cell| cell.as_deref().unwrap_or(NULL_VALUE))));
+        }
+        lines.push(horizontal_line);
+
+        lines
+    }
 
-            print!("|");
+    fn format_row<'a>(&self, values: impl Iterator<Item = &'a str>) -> String {
+        let cells: Vec<String> = values.enumerate()
+            .map(|(column_index, value)| self.format_cell(column_index, value))
+            .collect();
+        format!("|{}|", cells.join("|"))
+    }
+
+    //Pads the value to the width of the column, on the side given by the alignment of the column.
+    //Headers follow the alignment of their column
+    fn format_cell(&self, column_index: usize, value: &str) -> String {
+        let value = self.truncate(value);
+        let padding = " ".repeat(self.columns_width[column_index] - Self::display_width(&value));
+        match self.columns_alignment[column_index].unwrap_or(Alignment::Left) {
+            Alignment::Left => format!(" {}{} ", value, padding),
+            Alignment::Right => format!(" {}{} ", padding, value),
         }
-        print!("\n");
-        self.print_horizontal_line();
     }
 
-    fn print_horizontal_line(&self) {
-        print!("+");
-        print!("{}", "-".repeat(self.total_width()));
-        print!("+\n");
+    fn format_horizontal_line(&self) -> String {
+        format!("+{}+", "-".repeat(self.total_width()))
     }
 
     fn total_width(&self) -> usize {
         let mut total_width = 0;
         for column_max_width in &self.columns_width {
-            total_width = column_max_width + total_width;
+            total_width = column_max_width + 2 + total_width;
         }
 
         total_width + (self.n_columns - 1)
     }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::table_print::{OutputFormat, TablePrint};
+
+    #[test]
+    fn csv_field_with_comma() {
+        let table = create_csv_table("Madrid, Spain");
+        assert_eq!(table.to_csv(), "Id,City\r\n1,\"Madrid, Spain\"\r\n");
+    }
+
+    #[test]
+    fn csv_field_with_quote() {
+        let table = create_csv_table("The \"old\" town");
+        assert_eq!(table.to_csv(), "Id,City\r\n1,\"The \"\"old\"\" town\"\r\n");
+    }
+
+    #[test]
+    fn csv_field_with_newline() {
+        let table = create_csv_table("First line\nSecond line");
+        assert_eq!(table.to_csv(), "Id,City\r\n1,\"First line\nSecond line\"\r\n");
+    }
+
+    #[test]
+    fn json() {
+        let mut table = TablePrint::create(4, OutputFormat::Json);
+        table.add_header("Id");
+        table.add_header("Name");
+        table.add_header("Balance");
+        table.add_header("Code");
+        table.add_column_value("1".to_string());
+        table.add_column_value("Jaime \"J\"\\n".to_string());
+        table.add_column_value("-10.5".to_string());
+        table.add_column_value("007".to_string());
+        table.add_column_value("2".to_string());
+        table.add_null_value();
+        table.add_null_value();
+        table.add_column_value("1e3".to_string());
+
+        let json: serde_json::Value = serde_json::from_str(&table.to_json()).unwrap();
+        let rows = json.as_array().unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["Id"], serde_json::json!(1));
+        assert_eq!(rows[0]["Name"], serde_json::json!("Jaime \"J\"\\n"));
+        assert_eq!(rows[0]["Balance"], serde_json::json!(-10.5));
+        //Not every value of the column is a JSON number
+        assert_eq!(rows[0]["Code"], serde_json::json!("007"));
+        assert_eq!(rows[1]["Id"], serde_json::json!(2));
+        assert!(rows[1]["Name"].is_null());
+        assert!(rows[1]["Balance"].is_null());
+        assert_eq!(rows[1]["Code"], serde_json::json!("1e3"));
+    }
+
+    #[test]
+    fn json_empty() {
+        let mut table = TablePrint::create(1, OutputFormat::Json);
+        table.add_header("Id");
+
+        let json: serde_json::Value = serde_json::from_str(&table.to_json()).unwrap();
+        assert_eq!(json, serde_json::json!([]));
+    }
+
+    #[test]
+    fn numeric_columns_right_aligned() {
+        let mut table = TablePrint::create(2, OutputFormat::Table);
+        table.add_header("Balance");
+        table.add_header("Name");
+        table.add_column_value("1".to_string());
+        table.add_column_value("Jaime".to_string());
+        table.add_column_value("-100.5".to_string());
+        table.add_column_value("Al".to_string());
+        table.add_null_value();
+        table.add_column_value("12".to_string());
+
+        assert_eq!(table.format_cell(0, "Balance"), " Balance ");
+        assert_eq!(table.format_cell(0, "1"), "       1 ");
+        assert_eq!(table.format_cell(0, "-100.5"), "  -100.5 ");
+        assert_eq!(table.format_cell(0, "N/A"), "     N/A ");
+        //Not every value is a number
+        assert_eq!(table.format_cell(1, "Name"), " Name  ");
+        assert_eq!(table.format_cell(1, "Al"), " Al    ");
+        assert_eq!(table.format_cell(1, "12"), " 12    ");
+    }
+
+    #[test]
+    fn truncate_ascii() {
+        let mut table = TablePrint::create(1, OutputFormat::Table);
+        table.add_header("Blob");
+        table.add_column_value("a".repeat(100));
+        table.add_column_value("short".to_string());
+
+        let truncated = format!("{}…", "a".repeat(63));
+        assert_eq!(table.columns_width[0], 64);
+        assert_eq!(table.format_cell(0, &"a".repeat(100)), format!(" {} ", truncated));
+        assert_eq!(table.format_cell(0, "short"), format!(" short{} ", " ".repeat(59)));
+        //Not truncated in other formats
+        assert_eq!(table.to_csv(), format!("Blob\r\n{}\r\nshort\r\n", "a".repeat(100)));
+    }
+
+    #[test]
+    fn truncate_multibyte() {
+        let mut table = TablePrint::create(1, OutputFormat::Table);
+        table.add_header("Name");
+        //The 4th character starts at byte 6 and ends at byte 8
+        table.add_column_value("ñáéíóú".to_string());
+        table.set_max_column_width(4);
+
+        assert_eq!(table.columns_width[0], 4);
+        assert_eq!(table.format_cell(0, "ñáéíóú"), " ñáé… ");
+        assert_eq!(table.format_cell(0, "Name"), " Name ");
+    }
+
+    #[test]
+    fn unicode_width() {
+        let mut table = TablePrint::create(2, OutputFormat::Table);
+        table.add_header("Name");
+        table.add_header("Pet");
+        table.add_column_value("Café".to_string());
+        table.add_column_value("🦀".to_string());
+        //e followed by a combining acute accent
+        table.add_column_value("Cafe\u{301}".to_string());
+        table.add_column_value("猫".to_string());
+        table.add_column_value("日本語".to_string());
+        table.add_column_value("🐈🐕".to_string());
+
+        assert_eq!(table.columns_width, vec![6, 4]);
+
+        let lines = table.format_table();
+        let header_borders = line_borders(&lines[1]);
+        assert_eq!(header_borders, vec![0, 9, 16]);
+        for row_line in &lines[3..lines.len() - 1] {
+            assert_eq!(line_borders(row_line), header_borders, "Misaligned row {}", row_line);
+        }
+        for horizontal_line in [&lines[0], &lines[2], &lines[lines.len() - 1]] {
+            assert_eq!(line_borders(horizontal_line), vec![0, 16]);
+        }
+    }
+
+    //Display columns of the cell borders
+    fn line_borders(line: &str) -> Vec<usize> {
+        let mut borders = Vec::new();
+        let mut column = 0;
+        for char in line.chars() {
+            if char == '|' || char == '+' {
+                borders.push(column);
+            }
+            column += unicode_width::UnicodeWidthChar::width(char).unwrap_or(0);
+        }
+        borders
+    }
 
-    fn format_cell(value: &str) -> String {
-        let mut value = value.to_string();
-        value.push(' ');
-        value.insert(0, ' ');
-        value
+    fn create_csv_table(city: &str) -> TablePrint {
+        let mut table = TablePrint::create(2, OutputFormat::Csv);
+        table.add_header("Id");
+        table.add_header("City");
+        table.add_column_value("1".to_string());
+        table.add_column_value(city.to_string());
+        table
     }
 }
\ No newline at end of file
@@ -1,11 +1,14 @@
 use crate::database::database_descriptor::DatabaseDescriptor;
-use crate::sql::statement::CreateTableStatement;
+use crate::sql::statement::{CreateTableColumn, CreateTableStatement};
 use crate::table::table::Table;
-use crate::value::Type;
 use crossbeam_skiplist::SkipMap;
 use shared::SimpleDbError::{CannotCreateDatabaseFolder, PrimaryColumnNotIncluded, TableAlreadyExists};
-use shared::{utils, SimpleDbError, SimpleDbOptions};
+use shared::{utils, Collation, SimpleDbError, SimpleDbOptions};
 use std::sync::{Arc, LockResult, Mutex, RwLock, RwLockWriteGuard};
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::atomic::Ordering::Relaxed;
+use shared::TxnId;
+use crate::sql::query_cache::QueryCache;
 use storage::Storage;
 use storage::transactions::transaction::Transaction;
 
@@ -19,6 +22,18 @@ pub struct Database {
 
     //See self::lock_rollbacks() method docks
     rollback_lock: RwLock<()>,
+
+    pub(crate) query_cache: QueryCache,
+    //Incremented every time a transaction with writes commits or rollbacks
+    epoch: AtomicU64,
+    //Transactions started with start_transaction() that haven't committed or rolledback yet
+    active_transactions: SkipMap<TxnId, ActiveTransaction>,
+}
+
+struct ActiveTransaction {
+    //Epoch when the transaction was started
+    epoch: u64,
+    has_writes: AtomicBool,
 }
 
 impl Database {
@@ -34,6 +49,9 @@ impl Database {
             database_descriptor: Mutex::new(DatabaseDescriptor::create(options, &database_name.to_string())?),
             storage: Arc::new(Storage::create(options.clone())?),
             rollback_lock: RwLock::new(()),
+            query_cache: QueryCache::create(options.query_cache_size_bytes),
+            active_transactions: SkipMap::new(),
+            epoch: AtomicU64::new(0),
             name: database_name.to_string(),
             options: options.clone(),
             tables: SkipMap::new(),
@@ -55,6 +73,9 @@ impl Database {
             name: String::from(database_name),
             options: database_options.clone(),
             rollback_lock: RwLock::new(()),
+            query_cache: QueryCache::create(database_options.query_cache_size_bytes),
+            active_transactions: SkipMap::new(),
+            epoch: AtomicU64::new(0),
             storage: storage.clone(),
             tables: SkipMap::new(),
         });
@@ -69,6 +90,9 @@ impl Database {
             database_descriptor: Mutex::new(DatabaseDescriptor::mock()),
             storage: Arc::new(Storage::create_mock(&options.clone())),
             rollback_lock: RwLock::new(()),
+            query_cache: QueryCache::create(options.query_cache_size_bytes),
+            active_transactions: SkipMap::new(),
+            epoch: AtomicU64::new(0),
             name: String::from("mock"),
             tables: SkipMap::new(),
             options: options.clone()
@@ -90,13 +114,18 @@ impl Database {
     pub fn create_table(
         self: &Arc<Self>,
         table_name: &str,
-        columns: Vec<(String, Type, bool)>,
+        columns: Vec<CreateTableColumn>,
     ) -> Result<Arc<Table>, SimpleDbError> {
         let primary_column_name = columns.iter()
-            .filter(|(_, _, is_primary)| *is_primary)
-            .map(|(name,_, _)| name.clone())
+            .filter(|column| column.is_primary)
+            .map(|column| column.column_name.clone())
             .find(|_| true) //Find first
             .ok_or(PrimaryColumnNotIncluded())?;
+        //UNIQUE constraints are checked with a secondary index. Primary columns are already unique
+        let unique_columns: Vec<String> = columns.iter()
+            .filter(|column| column.is_unique && !column.is_primary)
+            .map(|column| column.column_name.clone())
+            .collect();
 
         let table = Table::create(
             table_name,
@@ -111,6 +140,9 @@ impl Database {
         database_descriptor.add_table(table_name, table.storage_keyspace_id)?;
 
         table.add_columns(columns)?;
+        for unique_column in unique_columns {
+            table.create_secondary_index(&[unique_column], true)?;
+        }
 
         self.tables.insert(table.table_name.clone(), table.clone());
 
@@ -120,12 +152,22 @@ impl Database {
     pub fn add_column(
         &self,
         table_name: &str,
-        columns_to_add: Vec<(String, Type, bool)>
+        columns_to_add: Vec<CreateTableColumn>
     ) -> Result<(), SimpleDbError> {
         let table = self.get_table_or_err(table_name)?;
         table.add_columns(columns_to_add)
     }
 
+    pub fn drop_table(&self, table_name: &str) -> Result<(), SimpleDbError> {
+        let table = self.get_table_or_err(table_name)?;
+        self.tables.remove(table_name);
+        self.database_descriptor.lock().unwrap().remove_table(table_name);
+        //A table with the same name might be created again, cached results of the dropped table shouldn't be returned
+        self.epoch.fetch_add(1, Relaxed);
+
+        table.drop_keyspaces()
+    }
+
     pub fn get_table_or_err(&self, table_name: &str) -> Result<Arc<Table>, SimpleDbError> {
         self.tables.get(table_name)
             .map(|entry| entry.value().clone())
@@ -133,22 +175,71 @@ impl Database {
     }
 
     pub fn start_transaction(&self) -> Transaction {
-        self.storage.start_transaction()
+        let transaction = self.storage.start_transaction();
+        self.active_transactions.insert(transaction.id(), ActiveTransaction {
+            epoch: self.epoch.load(Relaxed),
+            has_writes: AtomicBool::new(false),
+        });
+        transaction
     }
 
     pub fn rollback_transaction(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
         let lock = self.rollback_lock.read();
-        self.storage.rollback_transaction(transaction)
+        self.storage.rollback_transaction(transaction)?;
+        self.on_transaction_finished(transaction);
+        Ok(())
+    }
+
+    //The transaction keeps running, only the writes done after the savepoint are discarded
+    pub fn rollback_to_savepoint(&self, transaction: &Transaction, name: &str) -> Result<(), SimpleDbError> {
+        let _lock = self.rollback_lock.read();
+        self.storage.rollback_to_savepoint(transaction, name)
     }
 
     pub fn commit_transaction(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
-        self.storage.commit_transaction(transaction)
+        self.storage.commit_transaction(transaction)?;
+        self.on_transaction_finished(transaction);
+        Ok(())
+    }
+
+    pub(crate) fn on_transaction_write(&self, transaction: &Transaction) {
+        if let Some(active_transaction) = self.active_transactions.get(&transaction.id()) {
+            active_transaction.value().has_writes.store(true, Relaxed);
+        }
+    }
+
+    //Returns the epoch that should be used to read or store the query results of the transaction in QueryCache.
+    //Returns None if the transaction can see different data than a newly started transaction would see:
+    // - The transaction has done some writes, which are not visible for others.
+    // - Other transactions have committed or rolledback since the transaction started.
+    pub(crate) fn query_cache_epoch(&self, transaction: &Transaction) -> Option<u64> {
+        let active_transaction = self.active_transactions.get(&transaction.id())?;
+        let active_transaction = active_transaction.value();
+        let current_epoch = self.epoch.load(Relaxed);
+
+        if !active_transaction.has_writes.load(Relaxed) && active_transaction.epoch == current_epoch {
+            Some(current_epoch)
+        } else {
+            None
+        }
+    }
+
+    fn on_transaction_finished(&self, transaction: &Transaction) {
+        if let Some(active_transaction) = self.active_transactions.remove(&transaction.id()) {
+            if active_transaction.value().has_writes.load(Relaxed) {
+                self.epoch.fetch_add(1, Relaxed);
+            }
+        }
     }
 
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    pub(crate) fn collation(&self) -> Collation {
+        self.options.collation
+    }
+
     pub fn get_tables(&self) -> Vec<Arc<Table>> {
         let mut tables = Vec::new();
         for entry in self.tables.iter() {
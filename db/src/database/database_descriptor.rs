@@ -66,6 +66,12 @@ impl DatabaseDescriptor {
         Ok(())
     }
 
+    //Tables are loaded from the storage keyspaces, so the entry is only removed from memory.
+    //If a table with the same name is created again, its entry will override the old one.
+    pub fn remove_table(&mut self, table_name: &str) {
+        self.keyspace_id_by_table_name.remove(table_name);
+    }
+
     fn serialize_new_table_entry(&self, table_name: &str, keyspace_id: KeyspaceId) -> Vec<u8> {
         let mut serialized = Vec::new();
         serialized.put_u32_le(table_name.len() as u32);
@@ -104,17 +104,13 @@ impl Databases {
     }
 
     fn merge_storage_tables(prev: &Bytes, new: &Bytes, flag: Flag) -> StorageValueMergeResult {
+        //Deleted keys are not passed to the merger by the storage engine
         if flag.has(KEYSPACE_TABLE_USER) {
-            let tombstone = Bytes::new();
-            if prev.eq(&tombstone) || new.eq(&tombstone) {
-                StorageValueMergeResult::DiscardPreviousKeepNew
-            } else {
-                let mut prev = Record::deserialize(prev.to_vec());
-                let new = Record::deserialize(new.to_vec());
-                prev.merge(new);
+            let mut prev = Record::deserialize(prev.to_vec());
+            let new = Record::deserialize(new.to_vec());
+            prev.merge(new);
 
-                StorageValueMergeResult::Ok(Bytes::from(prev.serialize()))
-            }
+            StorageValueMergeResult::Ok(Bytes::from(prev.serialize()))
         } else if flag.has(KEYSPACE_TABLE_INDEX) {
             let prev = PostingList::deserialize(&mut prev.iter().as_slice());
             let new = PostingList::deserialize(&mut new.iter().as_slice());
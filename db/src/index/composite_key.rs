@@ -0,0 +1,65 @@
+use bytes::{BufMut, Bytes};
+use std::ops::Bound;
+
+const NULL_VALUE_LENGTH: u32 = 0xFFFFFFFF;
+
+//Keys of composite indexes. The indexed values of each column are concatenated in the index column order:
+// [ Value length (u32) | Value bytes... ]
+//Null values are encoded with length 0xFFFFFFFF and without bytes.
+//Lengths are always present, so ("ab", "c") and ("a", "bc") produce different keys, and the encoded values of the
+//first n columns are a prefix of the keys of all the rows with those values
+pub fn encode(values: &[Option<&Bytes>]) -> Bytes {
+    let mut encoded = Vec::new();
+    for value in values {
+        match value {
+            Some(value) => {
+                encoded.put_u32_le(value.len() as u32);
+                encoded.extend(value.iter());
+            },
+            None => encoded.put_u32_le(NULL_VALUE_LENGTH),
+        }
+    }
+
+    Bytes::from(encoded)
+}
+
+//Returns the bound before which all the keys starting with prefix are found
+pub fn prefix_end(prefix: &Bytes) -> Bound<Bytes> {
+    let mut end = prefix.to_vec();
+    while let Some(last_byte) = end.pop() {
+        if last_byte < 0xFF {
+            end.push(last_byte + 1);
+            return Bound::Excluded(Bytes::from(end));
+        }
+    }
+
+    Bound::Unbounded
+}
+
+#[cfg(test)]
+mod test {
+    use crate::index::composite_key::{encode, prefix_end};
+    use bytes::Bytes;
+    use std::ops::Bound;
+
+    #[test]
+    fn encode_is_not_ambiguous() {
+        let ab = Bytes::from("ab");
+        let c = Bytes::from("c");
+        let a = Bytes::from("a");
+        let bc = Bytes::from("bc");
+        let empty = Bytes::new();
+
+        assert_ne!(encode(&[Some(&ab), Some(&c)]), encode(&[Some(&a), Some(&bc)]));
+        assert_ne!(encode(&[Some(&empty), Some(&c)]), encode(&[None, Some(&c)]));
+        assert!(encode(&[Some(&ab), Some(&c)]).starts_with(&encode(&[Some(&ab)])));
+        assert!(!encode(&[Some(&ab), Some(&c)]).starts_with(&encode(&[Some(&a)])));
+    }
+
+    #[test]
+    fn prefix_end_bound() {
+        assert_eq!(prefix_end(&Bytes::from(vec![1, 2])), Bound::Excluded(Bytes::from(vec![1, 3])));
+        assert_eq!(prefix_end(&Bytes::from(vec![1, 0xFF])), Bound::Excluded(Bytes::from(vec![2])));
+        assert_eq!(prefix_end(&Bytes::from(vec![0xFF, 0xFF])), Bound::Unbounded);
+    }
+}
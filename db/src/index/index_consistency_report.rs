@@ -0,0 +1,24 @@
+use bytes::Bytes;
+use shared::ColumnId;
+
+//Returned by SecondaryIndexes::verify
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IndexConsistencyReport {
+    //Table rows whose indexed column value has no entry in the secondary index
+    pub missing_entries: Vec<IndexEntry>,
+    //Secondary index entries that point to rows that don't exist, or that have another column value
+    pub dangling_entries: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IndexEntry {
+    pub column_id: ColumnId,
+    pub indexed_value: Bytes,
+    pub primary_key: Bytes,
+}
+
+impl IndexConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_entries.is_empty() && self.dangling_entries.is_empty()
+    }
+}
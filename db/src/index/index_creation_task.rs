@@ -1,3 +1,5 @@
+use crate::index::posting_list::PostingList;
+use crate::index::secondary_indexes::SecondaryIndexes;
 use crate::table::record::Record;
 use crate::table::table::Table;
 use shared::{ColumnId, KeyspaceId, SimpleDbError};
@@ -14,9 +16,9 @@ pub struct IndexCreationTask {
     table: Arc<Table>,
     database: Arc<Database>,
 
-    indexed_column_id: ColumnId,
+    //More than one for composite indexes
+    indexed_columns_id: Vec<ColumnId>,
     index_keyspace_id: KeyspaceId,
-    table_keyspace_id: KeyspaceId,
     storage: Arc<Storage>,
 
     n_affected_rows_sender: Sender<Result<usize, SimpleDbError>>
@@ -24,9 +26,8 @@ pub struct IndexCreationTask {
 
 impl IndexCreationTask {
     pub fn create(
-        indexed_column_id: ColumnId,
+        indexed_columns_id: Vec<ColumnId>,
         index_keyspace_id: KeyspaceId,
-        table_keyspace_id: KeyspaceId,
         database: Arc<Database>,
         storage: Arc<Storage>,
         table: Arc<Table>,
@@ -36,8 +37,7 @@ impl IndexCreationTask {
         let index = IndexCreationTask {
             n_affected_rows_sender: send,
             index_keyspace_id,
-            table_keyspace_id,
-            indexed_column_id,
+            indexed_columns_id,
             database,
             storage,
             table,
@@ -48,9 +48,10 @@ impl IndexCreationTask {
 
     pub fn start(&self) {
         let mut n_affected_rows = 0;
-        let mut iterator = self.storage.scan_all_with_transaction(
+        let mut iterator = self.table.scan_storage(
             &Transaction::none(),
-            self.table_keyspace_id,
+            None,
+            &self.indexed_columns_id,
         ).unwrap();
 
         logger().info(DB(self.table.table_name.clone()), &format!(
@@ -64,28 +65,46 @@ impl IndexCreationTask {
         while iterator.next() {
             let key = iterator.key();
             let value = iterator.value();
-            let mut record = Record::deserialize(value.to_vec());
+            let record = Record::deserialize(value.to_vec());
 
-            if let Some(value_to_be_indexed) = record.take_value(self.indexed_column_id) {
+            if let Some(value_to_be_indexed) = SecondaryIndexes::get_index_key(&self.indexed_columns_id, &record) {
                 n_affected_rows += 1;
 
+                let transaction = Transaction::create(key.txn_id());
+                let entry = PostingList::create_present(key.as_bytes().clone(), &transaction)
+                    .serialize();
+
                 if let Err(error) = self.storage.set_with_transaction(
                     self.index_keyspace_id,
-                    &Transaction::create(key.txn_id()),
+                    &transaction,
                     value_to_be_indexed,
-                    key.as_bytes()
+                    &entry
                 ) {
-                    self.n_affected_rows_sender.send(Err(error)).unwrap();
+                    let _ = self.n_affected_rows_sender.send(Err(error));
                     return;
                 }
             }
         }
 
+        drop(lock);
+
+        //The index is not activated if some row couldn't be read
+        if let Some(error) = iterator.take_error() {
+            let _ = self.n_affected_rows_sender.send(Err(error));
+            return;
+        }
+
+        if let Err(error) = self.table.secondary_indexes.set_active(&self.indexed_columns_id) {
+            let _ = self.n_affected_rows_sender.send(Err(error));
+            return;
+        }
+
         logger().info(DB(self.table.table_name.clone()), &format!(
             "Created secondary index for table {} with {} entries",
             self.table.table_name.clone(), n_affected_rows
         ));
 
-        self.n_affected_rows_sender.send(Ok(n_affected_rows)).unwrap();
+        //If the index is created asynchronously, nobody will be waiting for the result
+        let _ = self.n_affected_rows_sender.send(Ok(n_affected_rows));
     }
 }
\ No newline at end of file
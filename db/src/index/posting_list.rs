@@ -19,6 +19,18 @@ impl PostingList {
         PostingList { entries: Vec::new() }
     }
 
+    pub fn create_present(
+        id: Bytes,
+        transaction: &Transaction
+    ) -> PostingList {
+        PostingList {
+            entries: vec![PostingListEntry{
+                primary_key: Key::create(id, transaction.id()),
+                is_present: true,
+            }]
+        }
+    }
+
     pub fn create_deleted(
         id: Bytes,
         transaction: &Transaction
@@ -31,6 +43,20 @@ impl PostingList {
         }
     }
 
+    pub fn create_present_all(
+        ids: Vec<Bytes>,
+        transaction: &Transaction
+    ) -> PostingList {
+        PostingList {
+            entries: ids.into_iter()
+                .map(|id| PostingListEntry {
+                    primary_key: Key::create(id, transaction.id()),
+                    is_present: true,
+                })
+                .collect()
+        }
+    }
+
     //Used for testing
     pub fn create(
         values: Vec<(&str, TxnId, bool)>
@@ -57,6 +83,10 @@ impl PostingList {
         }
 
         for current_entry_b in &b.entries {
+            if a.is_deleted_after(&current_entry_b.primary_key) {
+                continue;
+            }
+
             match final_posting_list.get_entry_by_key_bytes(&current_entry_b.primary_key) {
                 None => {
                     if current_entry_b.is_present {
@@ -70,6 +100,13 @@ impl PostingList {
         final_posting_list
     }
 
+    //Returns true if the primary key has been deleted by the same or a newer transaction than the key's one
+    fn is_deleted_after(&self, key: &Key) -> bool {
+        self.entries.iter().any(|entry| {
+            !entry.is_present && entry.primary_key.bytes_eq(key) && entry.primary_key.txn_id() >= key.txn_id()
+        })
+    }
+
     pub fn is_emtpy(&self) -> bool {
         self.entries.is_empty()
     }
@@ -155,4 +192,24 @@ mod test {
             PostingListEntry{ primary_key: Key::create_from_str("Pedro", 1), is_present: true },
         ]});
     }
+
+    #[test]
+    fn merge_deleted_entries() {
+        let posting_list_a = PostingList::create(vec![
+            ("Jaime", 2, false),
+            ("Juan", 2, false),
+        ]);
+        let posting_list_b = PostingList::create(vec![
+            ("Jaime", 2, true),
+            ("Juan", 3, true),
+            ("Pedro", 1, true),
+        ]);
+
+        let merge_result = PostingList::merge(&posting_list_a, &posting_list_b);
+
+        assert_eq!(merge_result, PostingList{entries: vec![
+            PostingListEntry{ primary_key: Key::create_from_str("Juan", 3), is_present: true },
+            PostingListEntry{ primary_key: Key::create_from_str("Pedro", 1), is_present: true },
+        ]});
+    }
 }
\ No newline at end of file
@@ -4,7 +4,8 @@ use bytes::Bytes;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::DB;
 use shared::{KeyspaceId, SimpleDbError, SimpleDbOptions};
-use std::sync::Arc;
+use std::ops::Bound;
+use std::sync::{Arc, RwLock};
 use storage::transactions::transaction::Transaction;
 use storage::{SimpleDbStorageIterator, Storage};
 
@@ -16,7 +17,8 @@ pub enum SecondaryIndexState {
 pub struct SecondaryIndex {
     keyspace_id: KeyspaceId,
     storage: Arc<storage::Storage>,
-    state: SecondaryIndexState,
+    //Indexes start in Creating state until all the existing table rows are indexed
+    state: RwLock<SecondaryIndexState>,
     table_name: String
 }
 
@@ -27,14 +29,14 @@ impl SecondaryIndex {
         keyspace_id: KeyspaceId,
         table_name: String
     ) -> SecondaryIndex {
-        SecondaryIndex { keyspace_id, storage, state, table_name }
+        SecondaryIndex { keyspace_id, storage, state: RwLock::new(state), table_name }
     }
 
     pub fn create_mock() -> SecondaryIndex {
         SecondaryIndex {
             storage: Arc::new(Storage::create_mock(&Arc::new(SimpleDbOptions::default()))),
             table_name: String::from("table_name"),
-            state: SecondaryIndexState::Active,
+            state: RwLock::new(SecondaryIndexState::Active),
             keyspace_id: 1,
         }
     }
@@ -55,7 +57,7 @@ impl SecondaryIndex {
             self.delete(transaction, old_value.clone(), primary_key.clone())?;
         }
 
-        let new_entry = PostingList::create_deleted(primary_key, transaction)
+        let new_entry = PostingList::create_present(primary_key, transaction)
             .serialize();
 
         self.storage.set_with_transaction(
@@ -66,6 +68,24 @@ impl SecondaryIndex {
         )
     }
 
+    //Indexes all the primary keys with the same column value with a single write
+    pub fn add_all(
+        &self,
+        transaction: &Transaction,
+        value: Bytes, //Column value indexed
+        primary_keys: Vec<Bytes> //Table's primary keys
+    ) -> Result<(), SimpleDbError> {
+        let new_entry = PostingList::create_present_all(primary_keys, transaction)
+            .serialize();
+
+        self.storage.set_with_transaction(
+            self.keyspace_id,
+            transaction,
+            value,
+            &new_entry
+        )
+    }
+
     pub fn scan_all(
         &self,
         transaction: &Transaction
@@ -74,6 +94,23 @@ impl SecondaryIndex {
         Ok(SecondaryIndexIterator::create(transaction, iterator))
     }
 
+    //Returns the primary keys whose indexed value is inside the range, sorted by the indexed value
+    pub fn scan_range(
+        &self,
+        transaction: &Transaction,
+        start: Bound<Bytes>,
+        end: Bound<Bytes>,
+    ) -> Result<SecondaryIndexIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        let iterator = match &start {
+            Bound::Included(start) => self.storage.scan_from_key_with_transaction(transaction, self.keyspace_id, start, true)?,
+            Bound::Excluded(start) => self.storage.scan_from_key_with_transaction(transaction, self.keyspace_id, start, false)?,
+            Bound::Unbounded => self.storage.scan_all_with_transaction(transaction, self.keyspace_id)?,
+        };
+        let mut iterator = SecondaryIndexIterator::create(transaction, iterator);
+        iterator.set_end(end);
+        Ok(iterator)
+    }
+
     pub fn delete(
         &self,
         transaction: &Transaction,
@@ -92,6 +129,10 @@ impl SecondaryIndex {
     }
 
     pub fn can_be_read(&self) -> bool {
-        matches!(self.state, SecondaryIndexState::Active)
+        matches!(*self.state.read().unwrap(), SecondaryIndexState::Active)
+    }
+
+    pub fn set_active(&self) {
+        *self.state.write().unwrap() = SecondaryIndexState::Active;
     }
 }
\ No newline at end of file
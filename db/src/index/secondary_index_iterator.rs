@@ -6,6 +6,7 @@ use shared::TxnId;
 use storage::transactions::transaction::Transaction;
 use shared::iterators::storage_iterator::StorageIterator;
 use shared::key::Key;
+use std::ops::Bound;
 
 //This iterator will return the primary keys indexed::
 //  - These primary keys are readable by the transaction
@@ -17,6 +18,9 @@ pub struct SecondaryIndexIterator<I: StorageIterator> {
     storage_iterator: I,
 
     deleted_entries: SkipSet<TxnId>,
+
+    //Indexed values after this bound are not returned
+    end: Bound<Bytes>,
 }
 
 impl<I: StorageIterator> SecondaryIndexIterator<I> {
@@ -28,7 +32,8 @@ impl<I: StorageIterator> SecondaryIndexIterator<I> {
             transaction: transaction.clone(),
             deleted_entries: SkipSet::new(),
             posting_list_iterator: None,
-            storage_iterator: iterator
+            storage_iterator: iterator,
+            end: Bound::Unbounded,
         }
     }
 
@@ -84,7 +89,7 @@ impl<I: StorageIterator> SecondaryIndexIterator<I> {
     }
 
     fn next_posting_list_iterator(&mut self) -> bool {
-        if !self.storage_iterator.next() {
+        if !self.storage_iterator.next() || self.is_past_end() {
             return false;
         }
 
@@ -97,6 +102,24 @@ impl<I: StorageIterator> SecondaryIndexIterator<I> {
     pub fn seek(&mut self, key: &Bytes, inclusive: bool) {
         self.storage_iterator.seek(key, inclusive);
     }
+
+    pub fn set_end(&mut self, end: Bound<Bytes>) {
+        self.end = end;
+    }
+
+    fn is_past_end(&self) -> bool {
+        let indexed_value = self.storage_iterator.key().as_bytes();
+        match &self.end {
+            Bound::Included(end) => indexed_value > end,
+            Bound::Excluded(end) => indexed_value >= end,
+            Bound::Unbounded => false,
+        }
+    }
+
+    //Returns the indexed column value of the last primary key returned by next()
+    pub fn indexed_value(&self) -> &Bytes {
+        self.storage_iterator.key().as_bytes()
+    }
 }
 
 #[cfg(test)]
@@ -123,8 +146,10 @@ mod test  {
         secondary_index_iterator.seek(&Bytes::from("2".as_bytes().to_vec()), true);
 
         assert_eq!(secondary_index_iterator.next(), Some(Key::create_from_str("Wili", 4)));
+        assert_eq!(secondary_index_iterator.indexed_value(), &Bytes::from("2"));
         assert_eq!(secondary_index_iterator.next(), Some(Key::create_from_str("Walo", 2)));
         assert_eq!(secondary_index_iterator.next(), Some(Key::create_from_str("Alvaro", 2)));
+        assert_eq!(secondary_index_iterator.indexed_value(), &Bytes::from("3"));
         assert_eq!(secondary_index_iterator.next(), None);
     }
 
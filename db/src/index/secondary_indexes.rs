@@ -1,3 +1,5 @@
+use crate::index::composite_key;
+use crate::index::index_consistency_report::{IndexConsistencyReport, IndexEntry};
 use crate::index::secondary_index::{SecondaryIndex, SecondaryIndexState};
 use crate::index::secondary_index_iterator::SecondaryIndexIterator;
 use crate::table::record::Record;
@@ -5,42 +7,55 @@ use crate::table::table_descriptor::TableDescriptor;
 use crate::table::table_flags::KEYSPACE_TABLE_INDEX;
 use bytes::Bytes;
 use crossbeam_skiplist::SkipMap;
+use shared::iterators::storage_iterator::StorageIterator;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::DB;
-use shared::SimpleDbError::IndexNotFound;
-use shared::{ColumnId, KeyspaceId, SimpleDbError, SimpleDbOptions};
+use shared::SimpleDbError::{IndexNotFound, TooManyIndexes};
+use shared::{ColumnId, KeyspaceId, SimpleDbError, SimpleDbOptions, TxnId};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
 use std::sync::Arc;
 use storage::transactions::transaction::Transaction;
 use storage::{SimpleDbStorageIterator, Storage};
 
 pub struct SecondaryIndexes {
     secondary_index_by_column_id: SkipMap<ColumnId, Arc<SecondaryIndex>>,
+    //Indexes on several columns. Columns are in the order in which their values are concatenated in the index key
+    composite_index_by_columns_id: SkipMap<Vec<ColumnId>, Arc<SecondaryIndex>>,
     storage: Arc<Storage>,
+    options: Arc<SimpleDbOptions>,
     table_name: String,
 }
 
 impl SecondaryIndexes {
-    pub fn create_empty(storage: Arc<Storage>, table_name: &str) -> SecondaryIndexes {
+    pub fn create_empty(options: Arc<SimpleDbOptions>, storage: Arc<Storage>, table_name: &str) -> SecondaryIndexes {
         SecondaryIndexes {
             secondary_index_by_column_id: SkipMap::new(),
+            composite_index_by_columns_id: SkipMap::new(),
             table_name: table_name.to_string(),
-            storage
+            storage,
+            options,
         }
     }
 
-    pub fn create_mock(options: Arc<SimpleDbOptions>) -> SecondaryIndexes {
-        let mut secondary_indexes = SkipMap::new();
-        secondary_indexes.insert(1, Arc::new(SecondaryIndex::create_mock()));
+    pub fn create_mock(options: Arc<SimpleDbOptions>, indexed_columns_id: Vec<ColumnId>) -> SecondaryIndexes {
+        let secondary_indexes = SkipMap::new();
+        for indexed_column_id in indexed_columns_id {
+            secondary_indexes.insert(indexed_column_id, Arc::new(SecondaryIndex::create_mock()));
+        }
 
         SecondaryIndexes {
             storage: Arc::new(Storage::create_mock(&options)),
             secondary_index_by_column_id: secondary_indexes,
+            composite_index_by_columns_id: SkipMap::new(),
             table_name: String::from(""),
+            options,
         }
     }
 
     pub fn load_secondary_indexes(
         table_descriptor: &TableDescriptor,
+        options: Arc<SimpleDbOptions>,
         storage: Arc<Storage>
     ) -> SecondaryIndexes {
         logger().info(DB(table_descriptor.table_name.clone()), "Loading secondary indexes");
@@ -60,29 +75,52 @@ impl SecondaryIndexes {
             }
         }
 
+        let composite_indexes = SkipMap::new();
+        for composite_index_descriptor in &table_descriptor.composite_indexes {
+            composite_indexes.insert(composite_index_descriptor.columns_id.clone(), Arc::new(SecondaryIndex::create(
+                storage.clone(),
+                SecondaryIndexState::Active,
+                composite_index_descriptor.keyspace_id,
+                table_descriptor.table_name.clone()
+            )));
+        }
+
         logger().info(DB(table_descriptor.table_name.clone()), &format!(
-            "Loaded {} secondary indexes", secondary_indexes.len())
+            "Loaded {} secondary indexes", secondary_indexes.len() + composite_indexes.len())
         );
 
         SecondaryIndexes {
             table_name: table_descriptor.table_name.clone(),
             secondary_index_by_column_id: secondary_indexes,
-            storage
+            composite_index_by_columns_id: composite_indexes,
+            storage,
+            options,
         }
     }
 
+    //Creates a composite index if more than one column is passed
     pub fn create_new_secondary_index(
         &self,
-        column_id: ColumnId,
+        columns_id: &[ColumnId],
     ) -> Result<KeyspaceId, SimpleDbError> {
-        let keyspace_id = self.storage.create_keyspace(KEYSPACE_TABLE_INDEX)?;
+        //Indexes loaded from disk are not checked, so lowering the limit doesn't prevent tables from being loaded
+        if self.secondary_index_by_column_id.len() + self.composite_index_by_columns_id.len() >= self.options.max_indexes_per_table {
+            return Err(TooManyIndexes(self.table_name.clone(), self.options.max_indexes_per_table));
+        }
 
-        self.secondary_index_by_column_id.insert(column_id, Arc::new(SecondaryIndex::create(
+        let keyspace_id = self.storage.create_keyspace(KEYSPACE_TABLE_INDEX)?;
+        let secondary_index = Arc::new(SecondaryIndex::create(
             self.storage.clone(),
             SecondaryIndexState::Creating,
             keyspace_id,
             self.table_name.clone()
-        )));
+        ));
+
+        if let [column_id] = columns_id {
+            self.secondary_index_by_column_id.insert(*column_id, secondary_index);
+        } else {
+            self.composite_index_by_columns_id.insert(columns_id.to_vec(), secondary_index);
+        }
 
         Ok(keyspace_id)
     }
@@ -98,6 +136,35 @@ impl SecondaryIndexes {
         }
     }
 
+    pub fn scan_range(
+        &self,
+        transaction: &Transaction,
+        column_id: ColumnId,
+        start: Bound<Bytes>,
+        end: Bound<Bytes>,
+    ) -> Result<SecondaryIndexIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        match self.secondary_index_by_column_id.get(&column_id) {
+            Some(entry) => entry.value().scan_range(transaction, start, end),
+            None => Err(IndexNotFound(column_id)),
+        }
+    }
+
+    //Returns the primary keys of the rows whose composite key starts with prefix, see composite_key::encode
+    pub fn scan_composite_prefix(
+        &self,
+        transaction: &Transaction,
+        columns_id: &[ColumnId],
+        prefix: Bytes,
+    ) -> Result<SecondaryIndexIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        match self.composite_index_by_columns_id.get(columns_id) {
+            Some(entry) => {
+                let end = composite_key::prefix_end(&prefix);
+                entry.value().scan_range(transaction, Bound::Included(prefix), end)
+            },
+            None => Err(IndexNotFound(columns_id[0])),
+        }
+    }
+
     pub fn update_all(
         &self,
         transaction: &Transaction,
@@ -118,9 +185,200 @@ impl SecondaryIndexes {
             }
         }
 
+        //Rows whose indexed columns are all null are not indexed by composite indexes
+        for entry in self.composite_index_by_columns_id.iter() {
+            let columns_id = entry.key();
+            if !columns_id.iter().any(|column_id| new_data.get_value(*column_id).is_some()) {
+                continue;
+            }
+
+            //Expect old_data to contain the old values of all the columns of the index, see get_columns_id_to_reindex
+            let old_values: Vec<Option<&Bytes>> = columns_id.iter()
+                .map(|column_id| old_data.get_value(*column_id))
+                .collect();
+            let new_values: Vec<Option<&Bytes>> = columns_id.iter()
+                .map(|column_id| new_data.get_value(*column_id).or(old_data.get_value(*column_id)))
+                .collect();
+            let old_key = if old_values.iter().any(Option::is_some) { Some(composite_key::encode(&old_values)) } else { None };
+
+            entry.value().update(
+                transaction,
+                composite_key::encode(&new_values),
+                primary_key.clone(),
+                old_key.as_ref()
+            )?;
+        }
+
         Ok(())
     }
 
+    //Returns the columns whose old values are needed by update_all() when the updated columns are changed
+    pub fn get_columns_id_to_reindex(&self, updated_columns_id: &[ColumnId]) -> Vec<ColumnId> {
+        let mut columns_id: BTreeSet<ColumnId> = updated_columns_id.iter()
+            .filter(|column_id| self.secondary_index_by_column_id.contains_key(column_id))
+            .copied()
+            .collect();
+
+        for entry in self.composite_index_by_columns_id.iter() {
+            if entry.key().iter().any(|column_id| updated_columns_id.contains(column_id)) {
+                columns_id.extend(entry.key().iter());
+            }
+        }
+
+        columns_id.into_iter().collect()
+    }
+
+    //Returns the columns whose secondary index is active
+    pub fn get_readable_columns_id(&self) -> Vec<ColumnId> {
+        self.secondary_index_by_column_id.iter()
+            .filter(|entry| entry.value().can_be_read())
+            .map(|entry| *entry.key())
+            .collect()
+    }
+
+    //Returns the columns of the active composite indexes
+    pub fn get_readable_composite_indexes(&self) -> Vec<Vec<ColumnId>> {
+        self.composite_index_by_columns_id.iter()
+            .filter(|entry| entry.value().can_be_read())
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    //Returns the columns read by the active indexes
+    pub fn get_readable_indexed_columns_id(&self) -> Vec<ColumnId> {
+        let mut columns_id: BTreeSet<ColumnId> = self.get_readable_columns_id().into_iter().collect();
+        for composite_index_columns_id in self.get_readable_composite_indexes() {
+            columns_id.extend(composite_index_columns_id);
+        }
+        columns_id.into_iter().collect()
+    }
+
+    //Scans the table rows and the active secondary indexes. Reports the table rows without index entry, and the
+    //index entries without table row. Every entry is kept in memory, so it is meant to be used for audits.
+    //Composite indexes are not verified
+    pub fn verify(
+        &self,
+        transaction: &Transaction,
+        mut table_iterator: impl StorageIterator
+    ) -> Result<IndexConsistencyReport, SimpleDbError> {
+        let indexed_columns_id = self.get_readable_columns_id();
+
+        let mut expected_entries = BTreeSet::new();
+        while table_iterator.next() {
+            let record = Record::deserialize(table_iterator.value().to_vec());
+            for column_id in &indexed_columns_id {
+                if let Some(indexed_value) = record.get_value(*column_id) {
+                    expected_entries.insert(IndexEntry {
+                        primary_key: table_iterator.key().as_bytes().clone(),
+                        indexed_value: indexed_value.clone(),
+                        column_id: *column_id,
+                    });
+                }
+            }
+        }
+        if let Some(error) = table_iterator.take_error() {
+            return Err(error);
+        }
+
+        let mut indexed_entries = BTreeSet::new();
+        for column_id in &indexed_columns_id {
+            let mut index_iterator = self.scan_all(transaction, *column_id)?;
+            while let Some(primary_key) = index_iterator.next() {
+                indexed_entries.insert(IndexEntry {
+                    indexed_value: index_iterator.indexed_value().clone(),
+                    primary_key: primary_key.as_bytes().clone(),
+                    column_id: *column_id,
+                });
+            }
+        }
+
+        Ok(IndexConsistencyReport {
+            missing_entries: expected_entries.difference(&indexed_entries).cloned().collect(),
+            dangling_entries: indexed_entries.difference(&expected_entries).cloned().collect(),
+        })
+    }
+
+    //Writes the index entries of every table row readable by the transaction in the active secondary indexes.
+    //Used to build the indexes once after loading rows without index maintenance. Entries are grouped by index key
+    //& row transaction, so each group is written with a single posting list. Returns the number of rows indexed
+    pub fn rebuild_all(
+        &self,
+        transaction: &Transaction,
+        mut table_iterator: impl StorageIterator
+    ) -> Result<usize, SimpleDbError> {
+        let mut indexes_columns_id: Vec<Vec<ColumnId>> = self.get_readable_columns_id().into_iter()
+            .map(|column_id| vec![column_id])
+            .collect();
+        indexes_columns_id.extend(self.get_readable_composite_indexes());
+
+        let mut primary_keys_by_index_key: BTreeMap<(&[ColumnId], Bytes, TxnId), Vec<Bytes>> = BTreeMap::new();
+        let mut n_indexed_rows = 0;
+        while table_iterator.next() {
+            let record = Record::deserialize(table_iterator.value().to_vec());
+            let primary_key = table_iterator.key();
+            n_indexed_rows += 1;
+
+            for index_columns_id in &indexes_columns_id {
+                if let Some(index_key) = Self::get_index_key(index_columns_id, &record) {
+                    primary_keys_by_index_key.entry((index_columns_id, index_key, primary_key.txn_id()))
+                        .or_default()
+                        .push(primary_key.as_bytes().clone());
+                }
+            }
+        }
+        if let Some(error) = table_iterator.take_error() {
+            return Err(error);
+        }
+
+        for ((index_columns_id, index_key, txn_id), primary_keys) in primary_keys_by_index_key {
+            //Like IndexCreationTask, entries are written with the row's transaction, so they are visible with the row
+            let row_transaction = if txn_id == transaction.id() { transaction.clone() } else { Transaction::create(txn_id) };
+            let secondary_index = self.get_index(index_columns_id).unwrap();
+            secondary_index.add_all(&row_transaction, index_key, primary_keys)?;
+        }
+
+        Ok(n_indexed_rows)
+    }
+
+    //Returns the key under which the row is stored in the index of the columns. None if the row is not indexed
+    pub fn get_index_key(index_columns_id: &[ColumnId], record: &Record) -> Option<Bytes> {
+        if let [column_id] = index_columns_id {
+            return record.get_value(*column_id).cloned();
+        }
+
+        let values: Vec<Option<&Bytes>> = index_columns_id.iter()
+            .map(|column_id| record.get_value(*column_id))
+            .collect();
+        if values.iter().any(Option::is_some) {
+            Some(composite_key::encode(&values))
+        } else {
+            None
+        }
+    }
+
+    //Called once all the table rows have been indexed. From now on, the index can be used by queries
+    pub fn set_active(&self, columns_id: &[ColumnId]) -> Result<(), SimpleDbError> {
+        match self.get_index(columns_id) {
+            Some(secondary_index) => {
+                secondary_index.set_active();
+                Ok(())
+            },
+            None => Err(IndexNotFound(columns_id[0])),
+        }
+    }
+
+    //Returns true if the index exists, even if it is still being created
+    pub fn exists(&self, columns_id: &[ColumnId]) -> bool {
+        self.get_index(columns_id).is_some()
+    }
+
+    fn get_index(&self, columns_id: &[ColumnId]) -> Option<Arc<SecondaryIndex>> {
+        match columns_id {
+            [column_id] => self.secondary_index_by_column_id.get(column_id).map(|entry| entry.value().clone()),
+            _ => self.composite_index_by_columns_id.get(columns_id).map(|entry| entry.value().clone()),
+        }
+    }
+
     pub fn can_be_read(&self, column_id: ColumnId) -> bool {
         if let Some(secondary_index) = self.secondary_index_by_column_id.get(&column_id) {
             secondary_index.value().can_be_read()
@@ -9,7 +9,13 @@ mod index;
 
 pub use table::table_descriptor::ColumnDescriptor;
 pub use index::index_type::IndexType;
+pub use index::index_consistency_report::{IndexConsistencyReport, IndexEntry};
 pub use simple_db::SimpleDb;
 pub use simple_db::Context;
+pub use simple_db::PreparedStatement;
+pub use value::Value;
 pub use sql::statement::*;
 pub use table::row::Row;
+pub use sql::plan::explain::Explain;
+#[cfg(feature = "arrow")]
+pub use sql::arrow::ArrowBatchIterator;
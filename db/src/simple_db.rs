@@ -1,9 +1,13 @@
 use crate::database::databases::Databases;
+use crate::index::index_consistency_report::IndexConsistencyReport;
+use crate::sql::dump::SqlDumpIterator;
 use crate::sql::executor::StatementExecutor;
 use crate::sql::parser::parser::Parser;
+use crate::sql::plan::explain::Explain;
 use crate::sql::query_iterator::QueryIterator;
 use crate::sql::statement::Statement;
 use crate::table::table_descriptor::ColumnDescriptor;
+use crate::value::Value;
 use shared::{SimpleDbError, SimpleDbOptions};
 use std::sync::Arc;
 use storage::transactions::transaction::Transaction;
@@ -22,7 +26,8 @@ pub enum StatementResult {
     Databases(Vec<String>),
     Tables(Vec<String>),
     Describe(Vec<ColumnDescriptor>),
-    Indexes(Vec<(String, IndexType)>)
+    Indexes(Vec<(String, IndexType)>),
+    Explain(Explain),
 }
 
 pub fn create(
@@ -31,12 +36,19 @@ pub fn create(
     let databases = Arc::new(Databases::create(options.clone())?);
     
     Ok(SimpleDb {
-        statement_executor: StatementExecutor::create(&databases),
+        statement_executor: StatementExecutor::create(&options, &databases),
         databases,
     })
 }
 
 
+//Statement parsed once that can be executed many times, with its placeholders (?) replaced by different parameters
+pub struct PreparedStatement<'a> {
+    simple_db: &'a SimpleDb,
+    statement: Statement,
+    n_placeholders: usize,
+}
+
 #[derive(Clone)]
 pub struct Context {
     transaction: Option<Transaction>,
@@ -61,11 +73,151 @@ impl SimpleDb {
         self.statement_executor.execute(&context, statement)
     }
 
+    //Parses a statement whose values can be passed as parameters when it is executed, for example:
+    //INSERT INTO personas (id, nombre) VALUES (?, ?);
+    pub fn prepare(
+        &self,
+        statement: &str
+    ) -> Result<PreparedStatement<'_>, SimpleDbError> {
+        let mut parser = Parser::create(statement.to_string());
+        let statement = parser.next_statement()?
+            .ok_or(SimpleDbError::MalformedQuery(String::from("Expected a statement")))?;
+
+        Ok(PreparedStatement {
+            n_placeholders: parser.n_placeholders(),
+            simple_db: self,
+            statement,
+        })
+    }
+
+    //Executes an INSERT statement. If defer_index_maintenance is true, the secondary indexes of the table are
+    //built once after inserting all the rows, which is faster than updating them for each row
+    pub fn bulk_insert(
+        &self,
+        context: &Context,
+        statement: Statement,
+        defer_index_maintenance: bool
+    ) -> Result<StatementResult, SimpleDbError> {
+        self.statement_executor.bulk_insert(context, statement, defer_index_maintenance)
+    }
+
+    //Parses and executes every statement of the script, for example a dump produced by dump_table_as_sql().
+    //If the context has no transaction, the script runs in its own transaction, which will be committed
+    //if all the statements succeed or rolled back otherwise. Returns the number of executed statements.
+    pub fn execute_script(
+        &self,
+        context: &Context,
+        script: &str
+    ) -> Result<usize, SimpleDbError> {
+        if !context.has_database() {
+            return Err(SimpleDbError::InvalidContext("A Database should be supplied"));
+        }
+
+        let database = self.databases.get_database_or_err(context.database())?;
+        let mut script_context = context.clone();
+        let owns_transaction = !context.has_transaction();
+        if owns_transaction {
+            script_context.with_transaction(database.start_transaction());
+        }
+
+        match self.execute_script_statements(&script_context, script) {
+            Ok(n_statements) => {
+                if owns_transaction {
+                    database.commit_transaction(script_context.transaction())?;
+                }
+                Ok(n_statements)
+            },
+            Err(error) => {
+                if owns_transaction {
+                    database.rollback_transaction(script_context.transaction())?;
+                }
+                Err(error)
+            }
+        }
+    }
+
+    fn execute_script_statements(
+        &self,
+        context: &Context,
+        script: &str
+    ) -> Result<usize, SimpleDbError> {
+        let mut parser = Parser::create(script.to_string());
+        let mut n_statements = 0;
+
+        loop {
+            let statement = match parser.next_statement() {
+                Ok(Some(statement)) => statement,
+                Ok(None) => return Ok(n_statements),
+                Err(error) => return Err(SimpleDbError::ScriptStatementFailed(
+                    n_statements + 1, parser.statement_line(), Box::new(error)
+                )),
+            };
+
+            n_statements += 1;
+
+            let result = if statement.terminates_transaction() || matches!(statement, Statement::StartTransaction) {
+                Err(SimpleDbError::MalformedQuery(String::from("Transaction statements are not allowed in scripts")))
+            } else {
+                self.execute(context, statement)
+            };
+
+            if let Err(error) = result {
+                return Err(SimpleDbError::ScriptStatementFailed(
+                    n_statements, parser.statement_line(), Box::new(error)
+                ));
+            }
+        }
+    }
+
+    //Returns the CREATE TABLE & CREATE INDEX statements of the table followed by one INSERT statement per row,
+    //read with the context's transaction. Values without SQL syntax (like blobs or NaN) are returned as errors.
+    pub fn dump_table_as_sql(
+        &self,
+        context: &Context,
+        table_name: &str
+    ) -> Result<impl Iterator<Item = Result<String, SimpleDbError>>, SimpleDbError> {
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_or_err(table_name)?;
+        SqlDumpIterator::create(table, context.transaction())
+    }
+
+    //Reports the rows of the table without secondary index entries, and the secondary index entries
+    //without row, read with the context's transaction.
+    pub fn verify_secondary_indexes(
+        &self,
+        context: &Context,
+        table_name: &str
+    ) -> Result<IndexConsistencyReport, SimpleDbError> {
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_or_err(table_name)?;
+        table.verify_secondary_indexes(context.transaction())
+    }
+
     pub fn get_databases(&self) -> Arc<Databases> {
         self.databases.clone()
     }
 }
 
+impl PreparedStatement<'_> {
+    //params are bound to the placeholders by position
+    pub fn execute(
+        &self,
+        context: &Context,
+        params: &[Value]
+    ) -> Result<StatementResult, SimpleDbError> {
+        if params.len() != self.n_placeholders {
+            return Err(SimpleDbError::WrongNumberOfParameters(self.n_placeholders, params.len()));
+        }
+
+        let statement = self.statement.clone().bind(params)?;
+        self.simple_db.execute(context, statement)
+    }
+
+    pub fn n_placeholders(&self) -> usize {
+        self.n_placeholders
+    }
+}
+
 impl Context {
     pub fn empty() -> Context {
         Context {
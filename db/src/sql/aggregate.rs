@@ -0,0 +1,263 @@
+use crate::sql::hyperloglog::HyperLogLog;
+use crate::sql::expression::{Expression, UnaryOperator};
+use crate::sql::scalar_function::ScalarFunction;
+use crate::sql::statement::Projection;
+use crate::table::table::Table;
+use crate::value::{Type, Value};
+use crate::ColumnDescriptor;
+use shared::{ColumnId, SimpleDbError};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+    //Estimated number of distinct not null values, see HyperLogLog
+    ApproxCountDistinct,
+}
+
+//Holds the partial result of an aggregate function for a group of rows
+pub struct Aggregator {
+    function: AggregateFunction,
+    //Number of not null values aggregated
+    count: i64,
+    value: Value,
+    //Only used by ApproxCountDistinct
+    distinct_values: Option<HyperLogLog>,
+}
+
+impl AggregateFunction {
+    pub fn from_name(name: &str) -> Option<AggregateFunction> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Some(AggregateFunction::Count),
+            "SUM" => Some(AggregateFunction::Sum),
+            "MIN" => Some(AggregateFunction::Min),
+            "MAX" => Some(AggregateFunction::Max),
+            "AVG" => Some(AggregateFunction::Avg),
+            "APPROX_COUNT_DISTINCT" => Some(AggregateFunction::ApproxCountDistinct),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            AggregateFunction::Count => "COUNT",
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::ApproxCountDistinct => "APPROX_COUNT_DISTINCT",
+        }
+    }
+
+    pub fn requires_number(&self) -> bool {
+        matches!(self, AggregateFunction::Sum | AggregateFunction::Avg)
+    }
+
+    //Type of the value produced by the function given the aggregated column type
+    //column_type is None when the function is applied to *
+    pub fn produced_type(&self, column_type: Option<&Type>) -> Type {
+        match (self, column_type) {
+            (AggregateFunction::Count, _) |
+            (AggregateFunction::ApproxCountDistinct, _) => Type::I64,
+            (AggregateFunction::Avg, _) => Type::F64,
+            (AggregateFunction::Sum, Some(column_type)) => {
+                if column_type.is_fp_number() { Type::F64 } else { Type::I64 }
+            },
+            (AggregateFunction::Min, Some(column_type)) |
+            (AggregateFunction::Max, Some(column_type)) => column_type.clone(),
+            _ => Type::Null,
+        }
+    }
+}
+
+impl Aggregator {
+    pub fn create(function: AggregateFunction) -> Aggregator {
+        Aggregator {
+            distinct_values: match function {
+                AggregateFunction::ApproxCountDistinct => Some(HyperLogLog::create()),
+                _ => None,
+            },
+            value: Value::Null,
+            count: 0,
+            function,
+        }
+    }
+
+    //Null values are ignored
+    pub fn aggregate(&mut self, value: Value) -> Result<(), SimpleDbError> {
+        if value.is_null() {
+            return Ok(());
+        }
+
+        self.count += 1;
+
+        match self.function {
+            AggregateFunction::Count => {},
+            AggregateFunction::ApproxCountDistinct => {
+                self.distinct_values.as_mut().unwrap().add(&value.serialize());
+            },
+            AggregateFunction::Sum |
+            AggregateFunction::Avg => {
+                self.value = if self.value.is_null() { value } else { self.value.add(&value)? };
+            },
+            AggregateFunction::Min => {
+                if self.value.is_null() || value.less(&self.value)?.get_boolean()? {
+                    self.value = value;
+                }
+            },
+            AggregateFunction::Max => {
+                if self.value.is_null() || value.greater(&self.value)?.get_boolean()? {
+                    self.value = value;
+                }
+            }
+        };
+
+        Ok(())
+    }
+
+    //Merges the values aggregated by other, which should have the same function
+    pub fn merge(&mut self, other: &Aggregator) -> Result<(), SimpleDbError> {
+        if other.count == 0 {
+            return Ok(());
+        }
+
+        self.count += other.count;
+
+        match self.function {
+            AggregateFunction::Count => {},
+            AggregateFunction::ApproxCountDistinct => {
+                self.distinct_values.as_mut().unwrap().merge(other.distinct_values.as_ref().unwrap());
+            },
+            AggregateFunction::Sum |
+            AggregateFunction::Avg => {
+                self.value = if self.value.is_null() { other.value.clone() } else { self.value.add(&other.value)? };
+            },
+            AggregateFunction::Min => {
+                if self.value.is_null() || other.value.less(&self.value)?.get_boolean()? {
+                    self.value = other.value.clone();
+                }
+            },
+            AggregateFunction::Max => {
+                if self.value.is_null() || other.value.greater(&self.value)?.get_boolean()? {
+                    self.value = other.value.clone();
+                }
+            }
+        };
+
+        Ok(())
+    }
+
+    pub fn result(&self) -> Result<Value, SimpleDbError> {
+        if self.value.is_null() && !matches!(self.function, AggregateFunction::Count | AggregateFunction::ApproxCountDistinct) {
+            return Ok(Value::Null);
+        }
+
+        match self.function {
+            AggregateFunction::Count => Ok(Value::I64(self.count)),
+            AggregateFunction::ApproxCountDistinct => Ok(Value::I64(self.distinct_values.as_ref().unwrap().estimate())),
+            AggregateFunction::Avg => Ok(Value::F64(self.value.get_f64()? / self.count as f64)),
+            AggregateFunction::Sum => {
+                //Sum of integers with different sizes might produce any integer type
+                if self.value.is_fp_number() {
+                    Ok(Value::F64(self.value.get_f64()?))
+                } else {
+                    Ok(Value::I64(self.value.get_i64()?))
+                }
+            },
+            AggregateFunction::Min |
+            AggregateFunction::Max => Ok(self.value.clone()),
+        }
+    }
+}
+
+//Columns descriptors of the rows produced by an aggregation or by a projection with expressions.
+//Column IDs are assigned by projection order
+pub fn columns_desc(table: &Arc<Table>, projection: &Vec<Projection>) -> Vec<ColumnDescriptor> {
+    projected_columns_desc(projection, &|column_name| table.get_column_desc(column_name))
+}
+
+//Same as columns_desc, but the projected columns are looked up with column_desc, for example, in the rows of a join
+pub fn projected_columns_desc(
+    projection: &[Projection],
+    column_desc: &dyn Fn(&str) -> Option<ColumnDescriptor>,
+) -> Vec<ColumnDescriptor> {
+    let mut columns_desc = Vec::new();
+
+    for (index, projection) in projection.iter().enumerate() {
+        let column_id = index as ColumnId;
+        let column_desc = match projection {
+            Projection::Column(column_name) => {
+                let mut column_desc = column_desc(column_name).unwrap();
+                column_desc.secondary_index_keyspace_id = None;
+                column_desc.is_primary = false;
+                column_desc.column_id = column_id;
+                column_desc
+            },
+            Projection::Aggregate(function, column_name) => {
+                let column_type = column_name.as_ref()
+                    .map(|column_name| column_desc(column_name).unwrap().column_type);
+
+                ColumnDescriptor {
+                    column_name: format!("{}({})", function.name(), column_name.as_ref().map_or("*", |it| it.as_str())),
+                    column_type: function.produced_type(column_type.as_ref()),
+                    secondary_index_keyspace_id: None,
+                    default_value: None,
+                    is_primary: false,
+                    is_not_null: false,
+                    is_unique: false,
+                    column_id,
+                }
+            },
+            Projection::Expression(expression, alias) => ColumnDescriptor {
+                column_name: alias.clone().unwrap_or_else(|| expression.to_string()),
+                column_type: expression_type(column_desc, expression),
+                secondary_index_keyspace_id: None,
+                default_value: None,
+                is_primary: false,
+                is_not_null: false,
+                is_unique: false,
+                column_id,
+            },
+        };
+
+        columns_desc.push(column_desc);
+    }
+
+    columns_desc
+}
+
+//Expected to have been validated before calling this function
+fn expression_type(column_desc: &dyn Fn(&str) -> Option<ColumnDescriptor>, expression: &Expression) -> Type {
+    match expression {
+        Expression::Binary(operator, left, right) => operator
+            .produced_type(&expression_type(column_desc, left), &expression_type(column_desc, right))
+            .map_or(Type::Null, |produced_type| computed_number_type(&produced_type)),
+        Expression::Unary(UnaryOperator::Not, _) => Type::Boolean,
+        Expression::Unary(_, expression) => computed_number_type(&expression_type(column_desc, expression)),
+        Expression::Function(name, _) => ScalarFunction::from_name(name).unwrap().produced_type(),
+        Expression::Identifier(column_name) => column_desc(column_name).unwrap().column_type,
+        Expression::Literal(value) => value.to_type(),
+        Expression::In(_, _) |
+        Expression::Between(_, _, _) |
+        Expression::IsNull(_) |
+        Expression::IsNotNull(_) |
+        Expression::Exists(_) => Type::Boolean,
+        Expression::Subquery(_) |
+        Expression::Placeholder(_) => Type::Null,
+    }
+}
+
+//Arithmetic is done with i64 or f64 values, see Value::arithmetic_op
+fn computed_number_type(produced_type: &Type) -> Type {
+    if produced_type.is_fp_number() {
+        Type::F64
+    } else if produced_type.is_integer_number() {
+        Type::I64
+    } else {
+        produced_type.clone()
+    }
+}
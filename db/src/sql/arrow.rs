@@ -0,0 +1,145 @@
+use crate::sql::query_iterator::QueryIterator;
+use crate::value::{Type, Value};
+use crate::{ColumnDescriptor, Row};
+use arrow_array::types::{Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type};
+use arrow_array::{ArrayRef, ArrowPrimitiveType, BinaryArray, BooleanArray, PrimitiveArray, RecordBatch, StringArray, TimestampMillisecondArray};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef, TimeUnit};
+use shared::SimpleDbError;
+use std::sync::Arc;
+
+//Timestamps are stored in UTC
+const TIMESTAMP_TIMEZONE: &str = "UTC";
+
+//Converts the rows returned by a QueryIterator into Arrow record batches of at most batch_size rows.
+//Every selected column is converted into an Arrow array with the type mapped from its column type.
+//Null values (columns not present in a row) are stored in the Arrow null bitmap.
+pub struct ArrowBatchIterator {
+    query_iterator: QueryIterator,
+    schema: SchemaRef,
+    batch_size: usize,
+}
+
+impl ArrowBatchIterator {
+    pub(crate) fn create(
+        query_iterator: QueryIterator,
+        batch_size: usize
+    ) -> Result<ArrowBatchIterator, SimpleDbError> {
+        let mut fields = Vec::new();
+        for column in query_iterator.columns_descriptor_selection() {
+            let data_type = arrow_data_type(&column.column_type).ok_or(SimpleDbError::MalformedQuery(format!(
+                "Column {} of type {:?} cannot be converted to Arrow", column.column_name, column.column_type
+            )))?;
+            //Primary keys are always present
+            fields.push(Field::new(&column.column_name, data_type, !column.is_primary));
+        }
+
+        Ok(ArrowBatchIterator {
+            schema: Arc::new(Schema::new(fields)),
+            batch_size: batch_size.max(1),
+            query_iterator,
+        })
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, SimpleDbError> {
+        let mut rows = Vec::with_capacity(self.batch_size);
+        while rows.len() < self.batch_size {
+            match self.query_iterator.next()? {
+                Some(row) => rows.push(row),
+                None => break,
+            }
+        }
+
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let mut columns = Vec::new();
+        for column in self.query_iterator.columns_descriptor_selection() {
+            columns.push(arrow_array(column, &rows)?);
+        }
+
+        RecordBatch::try_new(self.schema.clone(), columns)
+            .map(Some)
+            .map_err(|_: ArrowError| SimpleDbError::Internal)
+    }
+}
+
+impl Iterator for ArrowBatchIterator {
+    type Item = Result<RecordBatch, SimpleDbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+fn arrow_array(column: &ColumnDescriptor, rows: &[Row]) -> Result<ArrayRef, SimpleDbError> {
+    let mut values = Vec::with_capacity(rows.len());
+    for row in rows {
+        values.push(row.get_column_value(&column.column_name)?);
+    }
+
+    let array: ArrayRef = match column.column_type {
+        Type::I8 => primitive_array::<Int8Type>(&values, |value| match value { Value::I8(v) => Some(*v), _ => None }),
+        Type::U8 => primitive_array::<UInt8Type>(&values, |value| match value { Value::U8(v) => Some(*v), _ => None }),
+        Type::I16 => primitive_array::<Int16Type>(&values, |value| match value { Value::I16(v) => Some(*v), _ => None }),
+        Type::U16 => primitive_array::<UInt16Type>(&values, |value| match value { Value::U16(v) => Some(*v), _ => None }),
+        Type::I32 => primitive_array::<Int32Type>(&values, |value| match value { Value::I32(v) => Some(*v), _ => None }),
+        Type::U32 => primitive_array::<UInt32Type>(&values, |value| match value { Value::U32(v) => Some(*v), _ => None }),
+        Type::I64 => primitive_array::<Int64Type>(&values, |value| match value { Value::I64(v) => Some(*v), _ => None }),
+        Type::U64 => primitive_array::<UInt64Type>(&values, |value| match value { Value::U64(v) => Some(*v), _ => None }),
+        Type::F32 => primitive_array::<Float32Type>(&values, |value| match value { Value::F32(v) => Some(*v), _ => None }),
+        Type::F64 => primitive_array::<Float64Type>(&values, |value| match value { Value::F64(v) => Some(*v), _ => None }),
+        Type::Boolean => Arc::new(values.iter()
+            .map(|value| match value { Value::Boolean(v) => Some(*v), _ => None })
+            .collect::<BooleanArray>()),
+        Type::String => Arc::new(values.iter()
+            .map(|value| match value { Value::String(v) => Some(v.as_str()), _ => None })
+            .collect::<StringArray>()),
+        Type::Blob => Arc::new(values.iter()
+            .map(|value| match value { Value::Blob(v) => Some(v.as_ref()), _ => None })
+            .collect::<BinaryArray>()),
+        Type::Timestamp => Arc::new(values.iter()
+            .map(|value| match value { Value::Timestamp(v) => Some(*v), _ => None })
+            .collect::<TimestampMillisecondArray>()
+            .with_timezone(TIMESTAMP_TIMEZONE)),
+        Type::Date | Type::Null => return Err(SimpleDbError::MalformedQuery(format!(
+            "Column {} of type {:?} cannot be converted to Arrow", column.column_name, column.column_type
+        ))),
+    };
+
+    Ok(array)
+}
+
+fn primitive_array<T: ArrowPrimitiveType>(
+    values: &[Value],
+    get_value: impl Fn(&Value) -> Option<T::Native>
+) -> ArrayRef {
+    Arc::new(values.iter()
+        .map(get_value)
+        .collect::<PrimitiveArray<T>>())
+}
+
+fn arrow_data_type(column_type: &Type) -> Option<DataType> {
+    match column_type {
+        Type::I8 => Some(DataType::Int8),
+        Type::U8 => Some(DataType::UInt8),
+        Type::I16 => Some(DataType::Int16),
+        Type::U16 => Some(DataType::UInt16),
+        Type::I32 => Some(DataType::Int32),
+        Type::U32 => Some(DataType::UInt32),
+        Type::I64 => Some(DataType::Int64),
+        Type::U64 => Some(DataType::UInt64),
+        Type::F32 => Some(DataType::Float32),
+        Type::F64 => Some(DataType::Float64),
+        Type::Boolean => Some(DataType::Boolean),
+        Type::String => Some(DataType::Utf8),
+        Type::Blob => Some(DataType::Binary),
+        Type::Timestamp => Some(DataType::Timestamp(TimeUnit::Millisecond, Some(TIMESTAMP_TIMEZONE.into()))),
+        //Dates are not stored yet
+        Type::Date | Type::Null => None,
+    }
+}
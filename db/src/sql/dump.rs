@@ -0,0 +1,205 @@
+use crate::selection::Selection;
+use crate::table::table::Table;
+use crate::table::table_descriptor::ColumnDescriptor;
+use crate::table::table_iterator::TableIterator;
+use crate::value::{Type, Value};
+use shared::{timestamp, SimpleDbError};
+use std::fmt::Display;
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+use crate::table::table_storage_iterator::TableStorageIterator;
+
+//Streams the contents of a table as SQL statements: CREATE TABLE, CREATE INDEX and one INSERT per row.
+//The output can be executed again with SimpleDb to recreate the table.
+//Rows are read lazily from the storage engine, so dumping a big table doesn't load it in memory.
+pub struct SqlDumpIterator {
+    pending_statements: Vec<String>,
+    table_iterator: TableIterator<TableStorageIterator>,
+    columns: Vec<ColumnDescriptor>,
+    table: Arc<Table>,
+}
+
+impl SqlDumpIterator {
+    pub(crate) fn create(
+        table: Arc<Table>,
+        transaction: &Transaction
+    ) -> Result<SqlDumpIterator, SimpleDbError> {
+        let mut columns: Vec<ColumnDescriptor> = table.get_columns().into_values().collect();
+        columns.sort_by_key(|column| column.column_id);
+
+        for column in &columns {
+            if sql_type(&column.column_type).is_none() {
+                return Err(SimpleDbError::MalformedQuery(format!(
+                    "Column {} of type {:?} cannot be dumped as SQL", column.column_name, column.column_type
+                )));
+            }
+        }
+
+        let mut pending_statements = vec![Self::create_table_statement(&table, &columns)?];
+        //Indexes of UNIQUE columns are created by CREATE TABLE
+        for column in columns.iter().filter(|column| column.is_secondary_indexed() && !column.is_unique) {
+            pending_statements.push(format!("CREATE INDEX ON {} ({});", table.table_name, column.column_name));
+        }
+        for composite_index in table.composite_indexes.lock().unwrap().iter() {
+            let columns_names: Vec<&str> = composite_index.columns_id.iter()
+                .filter_map(|column_id| columns.iter().find(|column| column.column_id == *column_id))
+                .map(|column| column.column_name.as_str())
+                .collect();
+            pending_statements.push(format!("CREATE INDEX ON {} ({});", table.table_name, columns_names.join(", ")));
+        }
+        //Statements are popped from the back
+        pending_statements.reverse();
+
+        Ok(SqlDumpIterator {
+            table_iterator: table.scan_all(transaction, Selection::All)?,
+            pending_statements,
+            columns,
+            table,
+        })
+    }
+
+    fn create_table_statement(table: &Arc<Table>, columns: &Vec<ColumnDescriptor>) -> Result<String, SimpleDbError> {
+        let is_auto_increment = table.auto_increment.lock().unwrap().is_some();
+        let mut columns_sql = Vec::new();
+
+        for column in columns {
+            let mut column_sql = format!("{} {}", column.column_name, sql_type(&column.column_type).unwrap());
+            if column.is_primary {
+                column_sql.push_str(" PRIMARY KEY");
+                if is_auto_increment {
+                    column_sql.push_str(" AUTOINCREMENT");
+                }
+            }
+            if column.is_not_null {
+                column_sql.push_str(" NOT NULL");
+            }
+            if column.is_unique {
+                column_sql.push_str(" UNIQUE");
+            }
+            if let Some(default_value) = &column.default_value {
+                let default_value = Value::deserialize(default_value.clone(), column.column_type.clone())
+                    .map_err(|_| SimpleDbError::InvalidType(column.column_name.clone()))?;
+                column_sql.push_str(&format!(" DEFAULT {}", sql_literal(&default_value)?));
+            }
+            columns_sql.push(column_sql);
+        }
+
+        Ok(format!("CREATE TABLE {} ({});", table.table_name, columns_sql.join(", ")))
+    }
+
+    fn insert_statement(&self) -> Result<String, SimpleDbError> {
+        let row = self.table_iterator.row();
+        let mut columns_names = Vec::new();
+        let mut columns_values = Vec::new();
+
+        for column in &self.columns {
+            let value = row.get_column_value(&column.column_name).unwrap();
+            //Null values are not stored, so they are left out from the insert
+            if !value.is_null() {
+                columns_names.push(column.column_name.as_str());
+                columns_values.push(sql_literal(&value)?);
+            }
+        }
+
+        Ok(format!("INSERT INTO {} ({}) VALUES ({});",
+                self.table.table_name, columns_names.join(", "), columns_values.join(", ")))
+    }
+}
+
+impl Iterator for SqlDumpIterator {
+    type Item = Result<String, SimpleDbError>;
+
+    fn next(&mut self) -> Option<Result<String, SimpleDbError>> {
+        if let Some(statement) = self.pending_statements.pop() {
+            return Some(Ok(statement));
+        }
+
+        if self.table_iterator.next() {
+            Some(self.insert_statement())
+        } else {
+            self.table_iterator.take_error().map(Err)
+        }
+    }
+}
+
+fn sql_type(column_type: &Type) -> Option<&'static str> {
+    match column_type {
+        Type::I8 => Some("I8"),
+        Type::U8 => Some("U8"),
+        Type::I16 => Some("I16"),
+        Type::U16 => Some("U16"),
+        Type::U32 => Some("U32"),
+        Type::I32 => Some("I32"),
+        Type::U64 => Some("U64"),
+        Type::I64 => Some("I64"),
+        Type::F32 => Some("F32"),
+        Type::F64 => Some("F64"),
+        Type::Boolean => Some("BOOLEAN"),
+        Type::String => Some("VARCHAR"),
+        Type::Timestamp => Some("TIMESTAMP"),
+        //There is no literal syntax for these types
+        Type::Date | Type::Blob | Type::Null => None,
+    }
+}
+
+pub(crate) fn sql_literal(value: &Value) -> Result<String, SimpleDbError> {
+    match value {
+        Value::I8(value) => Ok(value.to_string()),
+        Value::U8(value) => Ok(value.to_string()),
+        Value::I16(value) => Ok(value.to_string()),
+        Value::U16(value) => Ok(value.to_string()),
+        Value::U32(value) => Ok(value.to_string()),
+        Value::I32(value) => Ok(value.to_string()),
+        Value::U64(value) => Ok(value.to_string()),
+        Value::I64(value) => Ok(value.to_string()),
+        Value::F32(value) => fp_literal(*value),
+        Value::F64(value) => fp_literal(*value),
+        Value::Boolean(value) => Ok(if *value { String::from("TRUE") } else { String::from("FALSE") }),
+        Value::String(value) => Ok(format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))),
+        Value::Timestamp(value) => Ok(format!("\"{}\"", timestamp::format_iso8601(*value))),
+        Value::Null => Ok(String::from("NULL")),
+        Value::Date | Value::Blob(_) => Err(SimpleDbError::MalformedQuery(format!(
+            "Value {:?} cannot be dumped as SQL", value
+        ))),
+    }
+}
+
+//Decimal point is always kept, so that the value gets parsed back as a floating point number.
+//NaN and infinity have no literal syntax
+fn fp_literal<T: Into<f64> + Display + Copy>(value: T) -> Result<String, SimpleDbError> {
+    let formatted = value.to_string();
+    if !value.into().is_finite() {
+        Err(SimpleDbError::MalformedQuery(format!("Value {} cannot be dumped as SQL", formatted)))
+    } else if formatted.contains('.') {
+        Ok(formatted)
+    } else {
+        Ok(format!("{}.0", formatted))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::dump::{fp_literal, sql_literal};
+    use crate::value::Value;
+    use bytes::Bytes;
+
+    #[test]
+    fn literals() {
+        assert_eq!(sql_literal(&Value::I64(-10)).unwrap(), "-10");
+        assert_eq!(sql_literal(&Value::F64(10.0)).unwrap(), "10.0");
+        assert_eq!(sql_literal(&Value::F64(-0.5)).unwrap(), "-0.5");
+        assert_eq!(sql_literal(&Value::F32(1.5)).unwrap(), "1.5");
+        assert_eq!(sql_literal(&Value::Boolean(true)).unwrap(), "TRUE");
+        assert_eq!(sql_literal(&Value::String(String::from(r#"Say "hi" \o/"#))).unwrap(), r#""Say \"hi\" \\o/""#);
+        assert_eq!(fp_literal(1.0).unwrap(), "1.0");
+    }
+
+    #[test]
+    fn literals_without_sql_syntax() {
+        assert!(sql_literal(&Value::F64(f64::NAN)).is_err());
+        assert!(sql_literal(&Value::F64(f64::INFINITY)).is_err());
+        assert!(sql_literal(&Value::F32(f32::NEG_INFINITY)).is_err());
+        assert!(sql_literal(&Value::Blob(Bytes::from(vec![1]))).is_err());
+        assert!(sql_literal(&Value::Date).is_err());
+    }
+}
@@ -1,18 +1,19 @@
 use crate::database::databases::Databases;
-use crate::selection::Selection;
 use crate::simple_db::{Context, StatementResult};
 use crate::sql::expression::Expression;
 use crate::sql::expression_evaluator::{evaluate_constant_expressions, evaluate_expression};
+use crate::sql::join;
 use crate::sql::plan::planner::Planner;
+use crate::sql::plan::steps::query_cache_step::{CachedRowsStep, QueryCacheStoreStep};
 use crate::sql::query_iterator::QueryIterator;
 use crate::sql::statement::{CreateTableStatement, DeleteStatement, InsertStatement, SelectStatement, Statement, UpdateStatement};
 use crate::sql::validator::StatementValidator;
 use crate::table::table::Table;
 use crate::value::Value;
-use crate::{ColumnDescriptor, CreateIndexStatement};
+use crate::{AlterTableStatement, CreateIndexStatement};
 use bytes::Bytes;
-use shared::SimpleDbError::MalformedQuery;
-use shared::SimpleDbError;
+use shared::SimpleDbError::{InvalidType, MalformedQuery, ScalarSubqueryReturnedMoreThanOneRow, UnknownColumn};
+use shared::{Collation, SimpleDbError, SimpleDbOptions};
 use std::sync::Arc;
 use storage::transactions::transaction::Transaction;
 
@@ -20,15 +21,20 @@ pub struct StatementExecutor {
     databases: Arc<Databases>,
 
     validator: StatementValidator,
-    planner: Planner
+    planner: Planner,
+    collation: Collation,
 }
 
 impl StatementExecutor {
-    pub fn create(databases: &Arc<Databases>) -> StatementExecutor {
+    pub fn create(
+        options: &Arc<SimpleDbOptions>,
+        databases: &Arc<Databases>
+    ) -> StatementExecutor {
         StatementExecutor {
             validator: StatementValidator::create(databases),
-            planner: Planner::create(),
-            databases: databases.clone()
+            planner: Planner::create(options.clone()),
+            databases: databases.clone(),
+            collation: options.collation,
         }
     }
 
@@ -38,23 +44,31 @@ impl StatementExecutor {
         statement: Statement,
     ) -> Result<StatementResult, SimpleDbError> {
         self.validator.validate(context, &statement)?;
+        let statement = self.materialize_subqueries(context, statement)?;
         let statement = self.evaluate_constant_expressions(statement)?;
 
         match statement {
-            Statement::Select(select_statement) => self.select(context.database(), context.transaction(), select_statement),
+            Statement::Select(select_statement) => Ok(StatementResult::Data(
+                self.select(context.database(), context.transaction(), select_statement)?
+            )),
             Statement::Update(update_statement) => self.update(context.database(), context.transaction(), update_statement),
             Statement::Delete(delete_statement) => self.delete(context.database(), context.transaction(), delete_statement),
             Statement::Insert(insert_statement) => self.insert(context.database(), context.transaction(), insert_statement),
             Statement::CreateTable(create_table_statement) => self.create_table(context.database(), create_table_statement),
             Statement::CreateIndex(statement) => self.create_secondary_index(context.database(), statement),
+            Statement::DropTable(table_name) => self.drop_table(context.database(), &table_name),
+            Statement::AlterTable(statement) => self.alter_table(context.database(), statement),
             Statement::Rollback => self.rollback_transaction(context.database(), context.transaction()),
             Statement::Commit => self.commit_transaction(context.database(), context.transaction()),
+            Statement::Savepoint(name) => self.savepoint(context.transaction(), name),
+            Statement::RollbackToSavepoint(name) => self.rollback_to_savepoint(context.database(), context.transaction(), name),
             Statement::CreateDatabase(database_name) => self.create_database(database_name),
             Statement::Describe(table_name) => self.describe_table(&table_name, context),
             Statement::StartTransaction => self.start_transaction(context.database()),
             Statement::ShowIndexes(table_name) => self.show_indexes(table_name, context),
             Statement::ShowTables => self.show_tables(&context),
             Statement::ShowDatabases => self.show_databases(),
+            Statement::Explain(statement) => self.explain(context, *statement),
         }
     }
 
@@ -63,13 +77,72 @@ impl StatementExecutor {
         database_name: &String,
         transaction: &Transaction,
         select_statement: SelectStatement,
-    ) -> Result<StatementResult, SimpleDbError> {
+    ) -> Result<QueryIterator, SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(&select_statement.table_name)?;
-        let columns_desc = self.get_column_desc_by_selection(&select_statement.selection, &table);
-        let select_plan = self.planner.plan_select(&table, select_statement, transaction)?;
+        let select_statement = match &select_statement.join {
+            Some(join) => {
+                let join_table = database.get_table_or_err(&join.table_name)?;
+                join::qualify_identifiers(select_statement, &table, &join_table)?
+            },
+            None => select_statement,
+        };
+        let columns_desc = self.planner.select_columns_desc(&table, &select_statement)?;
+
+        let query_cache = &database.query_cache;
+        //Cached results are only invalidated by writes to the table, correlated subqueries and joins also read other tables
+        let has_correlated_subqueries = select_statement.where_expr.as_ref()
+            .is_some_and(|where_expr| where_expr.contains_exists());
+        let query_cache_epoch = if query_cache.is_enabled() && !has_correlated_subqueries && select_statement.join.is_none() {
+            database.query_cache_epoch(transaction)
+        } else {
+            None
+        };
+
+        let select_plan = match query_cache_epoch {
+            Some(epoch) => {
+                //Read the version before planning, so writes done while the query runs invalidate the results
+                let table_version = table.version();
+                let query_cache_key = format!("{:?}", select_statement);
+
+                match query_cache.get(&query_cache_key, table_version, epoch) {
+                    Some(cached_rows) => CachedRowsStep::create(cached_rows),
+                    None => {
+                        let select_plan = self.planner.plan_select(&table, select_statement, transaction)?;
+                        QueryCacheStoreStep::create(&database, query_cache_key, table_version, epoch, select_plan)
+                    }
+                }
+            },
+            None => self.planner.plan_select(&table, select_statement, transaction)?
+        };
+
+        Ok(QueryIterator::create(select_plan, columns_desc))
+    }
+
+    //Subqueries are run, so the plan is the same one that would be used when running the statement
+    fn explain(
+        &self,
+        context: &Context,
+        statement: Statement,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let statement = self.materialize_subqueries(context, statement)?;
+        let statement = self.evaluate_constant_expressions(statement)?;
+        let database = self.databases.get_database_or_err(context.database())?;
+
+        let explain = match &statement {
+            Statement::Select(select) => {
+                self.planner.explain_select(&database.get_table_or_err(&select.table_name)?, select)?
+            },
+            Statement::Update(update) => {
+                self.planner.explain_update(&database.get_table_or_err(&update.table_name)?, update)?
+            },
+            Statement::Delete(delete) => {
+                self.planner.explain_delete(&database.get_table_or_err(&delete.table_name)?, delete)?
+            },
+            _ => return Err(MalformedQuery(String::from("Only SELECT, UPDATE and DELETE can be explained"))),
+        };
 
-        Ok(StatementResult::Data(QueryIterator::create(select_plan, columns_desc)))
+        Ok(StatementResult::Explain(explain))
     }
 
     fn update(
@@ -83,16 +156,30 @@ impl StatementExecutor {
         let mut update_plan = self.planner.plan_update(&table, &update_statement, transaction)?;
         let mut updated_rows = 0;
 
+        //Rows are collected before being updated, so the scan doesn't see the rows that we are updating
+        let mut rows_to_update = Vec::new();
         while let Some(row_to_update) = update_plan.next()? {
+            rows_to_update.push(row_to_update);
+        }
+
+        //New values of every row are calculated before updating anything, so constraints are checked for all rows
+        let mut new_values_by_id = Vec::new();
+        for row_to_update in rows_to_update {
             let id = row_to_update.get_primary_column_value().clone();
             let mut new_values = Vec::new();
 
             for (updated_column_name, new_value_expr) in &update_statement.updated_values {
-                let new_value_bytes = match evaluate_expression(&row_to_update, new_value_expr)? {
+                let new_value_bytes = match evaluate_expression(&row_to_update, new_value_expr, self.collation)? {
                     Expression::Literal(updated_value) => {
                         if !updated_value.is_null() {
-                            updated_value.serialize()
+                            let column_type = table.get_column_desc(updated_column_name)
+                                .ok_or_else(|| UnknownColumn(updated_column_name.clone()))?
+                                .column_type;
+                            updated_value.cast(&column_type)
+                                .map_err(|_| InvalidType(updated_column_name.clone()))?
+                                .serialize()
                         } else {
+                            table.validate_null_value(updated_column_name)?;
                             continue
                         }
                     },
@@ -102,7 +189,16 @@ impl StatementExecutor {
                 new_values.push((updated_column_name.clone(), new_value_bytes));
             }
 
-            table.update(transaction, id, &new_values)?;
+            new_values_by_id.push((id, new_values));
+        }
+
+        let rows: Vec<(Bytes, &Vec<(String, Bytes)>)> = new_values_by_id.iter()
+            .map(|(id, new_values)| (id.clone(), new_values))
+            .collect();
+        table.validate_unique_values(transaction, &rows)?;
+
+        for (id, new_values) in &new_values_by_id {
+            table.update(transaction, id.clone(), new_values)?;
             updated_rows += 1;
         }
 
@@ -137,9 +233,47 @@ impl StatementExecutor {
     ) -> Result<StatementResult, SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(insert_statement.table_name.as_str())?;
-        let mut inserted_values = self.serialize_column_values(&insert_statement.values);
-        table.insert(transaction, &mut inserted_values)?;
-        Ok(StatementResult::Ok(1))
+        let mut inserted_rows = 0;
+
+        let rows_values = self.serialize_rows_values(&table, transaction, &insert_statement)?;
+        for mut inserted_values in rows_values {
+            table.clone().insert(transaction, &mut inserted_values)?;
+            inserted_rows += 1;
+        }
+
+        Ok(StatementResult::Ok(inserted_rows))
+    }
+
+    //Inserts the rows of the INSERT statement. If defer_index_maintenance is true, the secondary indexes are not
+    //updated for each row, they are rebuilt once all the rows have been inserted
+    pub fn bulk_insert(
+        &self,
+        context: &Context,
+        statement: Statement,
+        defer_index_maintenance: bool,
+    ) -> Result<StatementResult, SimpleDbError> {
+        if !matches!(statement, Statement::Insert(_)) {
+            return Err(MalformedQuery(String::from("Only INSERT statements can be bulk inserted")));
+        }
+        if !defer_index_maintenance {
+            return self.execute(context, statement);
+        }
+
+        self.validator.validate(context, &statement)?;
+        let insert_statement = match self.evaluate_constant_expressions(statement)? {
+            Statement::Insert(insert_statement) => insert_statement,
+            _ => unreachable!()
+        };
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_or_err(insert_statement.table_name.as_str())?;
+
+        let rows_values = self.serialize_rows_values(&table, context.transaction(), &insert_statement)?;
+        for mut inserted_values in rows_values {
+            table.insert_without_indexing(context.transaction(), &mut inserted_values)?;
+        }
+        table.rebuild_secondary_indexes(context.transaction())?;
+
+        Ok(StatementResult::Ok(insert_statement.values.len()))
     }
 
     fn create_table(
@@ -152,6 +286,31 @@ impl StatementExecutor {
         Ok(StatementResult::Ok(0))
     }
 
+    fn alter_table(
+        &self,
+        database_name: &String,
+        statement: AlterTableStatement,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        let table = database.get_table_or_err(&statement.table_name)?;
+        table.add_column_with_default(
+            &statement.column_name,
+            statement.column_type,
+            statement.default_value
+        )?;
+        Ok(StatementResult::Ok(0))
+    }
+
+    fn drop_table(
+        &self,
+        database_name: &String,
+        table_name: &str,
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        database.drop_table(table_name)?;
+        Ok(StatementResult::Ok(0))
+    }
+
     fn create_secondary_index(
         &self,
         database_name: &String,
@@ -161,7 +320,7 @@ impl StatementExecutor {
         let table = database.get_table_or_err(&statement.table_name)?;
 
         let n_affected_rows = table.create_secondary_index(
-            &statement.column_name, statement.wait
+            &statement.columns_names, statement.wait
         )?;
 
         Ok(StatementResult::Ok(n_affected_rows))
@@ -186,6 +345,26 @@ impl StatementExecutor {
         Ok(StatementResult::Ok(0))
     }
 
+    fn savepoint(
+        &self,
+        transaction: &Transaction,
+        name: String
+    ) -> Result<StatementResult, SimpleDbError> {
+        transaction.savepoint(&name);
+        Ok(StatementResult::Ok(0))
+    }
+
+    fn rollback_to_savepoint(
+        &self,
+        database_name: &String,
+        transaction: &Transaction,
+        name: String
+    ) -> Result<StatementResult, SimpleDbError> {
+        let database = self.databases.get_database_or_err(database_name)?;
+        database.rollback_to_savepoint(transaction, &name)?;
+        Ok(StatementResult::Ok(0))
+    }
+
     fn commit_transaction(
         &self,
         database_name: &String,
@@ -236,37 +415,204 @@ impl StatementExecutor {
         Ok(StatementResult::Describe(column_descriptors))
     }
 
+    //Omitted columns get their default value, and the primary column its AUTOINCREMENT id. Checks the UNIQUE constraints of all the rows before any of them is inserted
+    fn serialize_rows_values(
+        &self,
+        table: &Arc<Table>,
+        transaction: &Transaction,
+        insert_statement: &InsertStatement,
+    ) -> Result<Vec<Vec<(String, Bytes)>>, SimpleDbError> {
+        let rows_values: Vec<Vec<(String, Bytes)>> = insert_statement.rows_values()?.iter()
+            .map(|row_values| {
+                let mut serialized_values = self.serialize_column_values(table, row_values)?;
+                let inserted_columns: Vec<&String> = row_values.iter().map(|(column_name, _)| column_name).collect();
+                table.add_default_values(&mut serialized_values, &inserted_columns);
+                table.add_auto_increment_id(&mut serialized_values)?;
+                Ok(serialized_values)
+            })
+            .collect::<Result<_, SimpleDbError>>()?;
+        let primary_column_name = &table.primary_column_name;
+        let rows: Vec<(Bytes, &Vec<(String, Bytes)>)> = rows_values.iter()
+            .map(|row_values| {
+                let id = row_values.iter()
+                    .find(|(column_name, _)| column_name == primary_column_name)
+                    .map(|(_, id)| id.clone())
+                    .unwrap();
+                (id, row_values)
+            })
+            .collect();
+        table.validate_unique_values(transaction, &rows)?;
+
+        Ok(rows_values)
+    }
+
     fn serialize_column_values(
         &self,
+        table: &Arc<Table>,
         values: &Vec<(String, Value)>
-    ) -> Vec<(String, Bytes)>{
+    ) -> Result<Vec<(String, Bytes)>, SimpleDbError> {
         let mut formatted_values = Vec::new();
-        for (column_name, column_value) in values {
+        //Null values are not stored, missing columns are read as null
+        for (column_name, column_value) in values.iter().filter(|(_, value)| !value.is_null()) {
+            let column_type = table.get_column_desc(column_name)
+                .ok_or_else(|| UnknownColumn(column_name.clone()))?
+                .column_type;
+            let column_value = column_value.clone().cast(&column_type)
+                .map_err(|_| InvalidType(column_name.clone()))?;
             formatted_values.push((column_name.clone(), column_value.serialize()));
         }
 
-        formatted_values
+        Ok(formatted_values)
+    }
+
+    //Subqueries are uncorrelated, so they are run once before the statement and replaced with the values they return
+    fn materialize_subqueries(&self, context: &Context, statement: Statement) -> Result<Statement, SimpleDbError> {
+        match statement {
+            //Identifiers of joins are qualified when the statement is run, see join::qualify_identifiers
+            Statement::Select(mut select) if select.join.is_some() => {
+                select.where_expr = select.where_expr
+                    .map(|where_expr| self.materialize_expression_subqueries(context, where_expr))
+                    .transpose()?;
+                Ok(Statement::Select(select))
+            },
+            Statement::Select(mut select) => {
+                select.where_expr = self.materialize_where_subqueries(context, &select.table_name, select.where_expr)?;
+                Ok(Statement::Select(select))
+            },
+            Statement::Update(mut update) => {
+                update.where_expr = self.materialize_where_subqueries(context, &update.table_name, update.where_expr)?;
+                Ok(Statement::Update(update))
+            },
+            Statement::Delete(mut delete) => {
+                delete.where_expr = self.materialize_where_subqueries(context, &delete.table_name, delete.where_expr)?;
+                Ok(Statement::Delete(delete))
+            },
+            _ => Ok(statement)
+        }
+    }
+
+    //Identifiers qualified with the statement table name are also unqualified, so that they can be read from the rows
+    fn materialize_where_subqueries(
+        &self,
+        context: &Context,
+        table_name: &str,
+        where_expr: Option<Expression>,
+    ) -> Result<Option<Expression>, SimpleDbError> {
+        where_expr.map(|where_expr| self.materialize_expression_subqueries(context, where_expr.unqualify_identifiers(table_name)))
+            .transpose()
+    }
+
+    //IN (SELECT ...) is replaced with the list of returned values. EXISTS is replaced with a boolean, unless it is
+    //correlated, in that case it is run by the planner for every row. Any other subquery is a scalar, it is replaced
+    //with the only value returned or with null if it doesn't return rows
+    fn materialize_expression_subqueries(
+        &self,
+        context: &Context,
+        expression: Expression,
+    ) -> Result<Expression, SimpleDbError> {
+        match expression {
+            Expression::Binary(operator, left, right) => Ok(Expression::Binary(
+                operator,
+                Box::new(self.materialize_expression_subqueries(context, *left)?),
+                Box::new(self.materialize_expression_subqueries(context, *right)?),
+            )),
+            Expression::Unary(operator, expression) => Ok(Expression::Unary(
+                operator,
+                Box::new(self.materialize_expression_subqueries(context, *expression)?),
+            )),
+            Expression::In(expression, list) => {
+                let mut materialized_list = Vec::new();
+                for item in list {
+                    match item {
+                        Expression::Subquery(subquery) => materialized_list.extend(
+                            self.run_subquery(context, *subquery)?.into_iter().map(Expression::Literal)
+                        ),
+                        item => materialized_list.push(self.materialize_expression_subqueries(context, item)?),
+                    }
+                }
+
+                let expression = self.materialize_expression_subqueries(context, *expression)?;
+                Ok(Expression::In(Box::new(expression), materialized_list))
+            },
+            Expression::Between(expression, low, high) => Ok(Expression::Between(
+                Box::new(self.materialize_expression_subqueries(context, *expression)?),
+                Box::new(self.materialize_expression_subqueries(context, *low)?),
+                Box::new(self.materialize_expression_subqueries(context, *high)?),
+            )),
+            Expression::IsNull(expression) => Ok(Expression::IsNull(
+                Box::new(self.materialize_expression_subqueries(context, *expression)?)
+            )),
+            Expression::IsNotNull(expression) => Ok(Expression::IsNotNull(
+                Box::new(self.materialize_expression_subqueries(context, *expression)?)
+            )),
+            Expression::Function(name, args) => Ok(Expression::Function(
+                name,
+                args.into_iter()
+                    .map(|arg| self.materialize_expression_subqueries(context, arg))
+                    .collect::<Result<Vec<Expression>, SimpleDbError>>()?,
+            )),
+            Expression::Subquery(subquery) => {
+                let mut values = self.run_subquery(context, *subquery)?;
+                if values.len() > 1 {
+                    return Err(ScalarSubqueryReturnedMoreThanOneRow);
+                }
+
+                Ok(Expression::Literal(values.pop().unwrap_or(Value::Null)))
+            },
+            Expression::Exists(mut subquery) => {
+                self.prepare_subquery(context, &mut subquery)?;
+                if subquery.is_correlated() {
+                    return Ok(Expression::Exists(subquery));
+                }
+
+                let exists = self.select(context.database(), context.transaction(), *subquery)?.next()?.is_some();
+                Ok(Expression::Literal(Value::Boolean(exists)))
+            },
+            Expression::Identifier(_) |
+            Expression::Literal(_) |
+            Expression::Placeholder(_) => Ok(expression),
+        }
+    }
+
+    //Returns the values of the only column returned by the subquery. It is run in the same transaction as the statement
+    fn run_subquery(&self, context: &Context, mut subquery: SelectStatement) -> Result<Vec<Value>, SimpleDbError> {
+        self.prepare_subquery(context, &mut subquery)?;
+
+        let mut query_iterator = self.select(context.database(), context.transaction(), subquery)?;
+        let column_name = query_iterator.columns_descriptor_selection()[0].column_name.clone();
+        query_iterator.all()?.iter()
+            .map(|row| row.get_column_value(&column_name))
+            .collect()
+    }
+
+    fn prepare_subquery(&self, context: &Context, subquery: &mut SelectStatement) -> Result<(), SimpleDbError> {
+        if let Some(where_expr) = subquery.where_expr.take() {
+            let where_expr = self.materialize_where_subqueries(context, &subquery.table_name, Some(where_expr))?.unwrap();
+            subquery.where_expr = Some(evaluate_constant_expressions(where_expr, self.collation)?);
+        }
+
+        Ok(())
     }
 
     fn evaluate_constant_expressions(&self, statement: Statement) -> Result<Statement, SimpleDbError> {
         match statement {
             Statement::Select(mut select) => {
                 if let Some(where_expr) = select.where_expr {
-                    select.where_expr = Some(evaluate_constant_expressions(where_expr)?);
+                    select.where_expr = Some(evaluate_constant_expressions(where_expr, self.collation)?);
                 }
 
                 Ok(Statement::Select(select))
             }
             Statement::Update(mut update) => {
                 if let Some(where_expr) = update.where_expr {
-                    update.where_expr = Some(evaluate_constant_expressions(where_expr)?);
+                    update.where_expr = Some(evaluate_constant_expressions(where_expr, self.collation)?);
                 }
 
                 Ok(Statement::Update(update))
             }
             Statement::Delete(mut delete) => {
                 if let Some(where_expr) = delete.where_expr {
-                    delete.where_expr = Some(evaluate_constant_expressions(where_expr)?);
+                    delete.where_expr = Some(evaluate_constant_expressions(where_expr, self.collation)?);
                 }
 
                 Ok(Statement::Delete(delete))
@@ -274,25 +620,1320 @@ impl StatementExecutor {
             _ => Ok(statement)
         }
     }
+}
+#[cfg(test)]
+mod test {
+    use crate::index::index_consistency_report::IndexEntry;
+    use crate::sql::expression::Expression;
+    use crate::sql::plan::explain::{Explain, ExplainScan};
+    use crate::simple_db::{Context, SimpleDb, StatementResult};
+    use crate::value::Value;
+    use crate::Row;
+    use bytes::Bytes;
+    use crate::selection::Selection;
+    use shared::{RowFormat, SimpleDbError, SimpleDbOptionsBuilder};
 
-    fn get_column_desc_by_selection(
-        &self,
-        selection: &Selection,
-        table: &Arc<Table>,
-    ) -> Vec<ColumnDescriptor> {
-        match selection {
-            Selection::Some(columns) => {
-                let mut columns_desc = Vec::new();
-                for column_name in columns {
-                    columns_desc.push(table.get_column_desc(column_name).unwrap().clone());
+    #[test]
+    fn delete_where_some_rows() {
+        let (simple_db, context) = create_personas_db("delete_where_some_rows");
+
+        assert_rows_affected(execute(&simple_db, &context, "DELETE FROM personas WHERE dinero >= 50;"), 5);
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn delete_where_no_rows() {
+        let (simple_db, context) = create_personas_db("delete_where_no_rows");
+
+        assert_rows_affected(execute(&simple_db, &context, "DELETE FROM personas WHERE dinero > 1000;"), 0);
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn delete_where_all_rows() {
+        let (simple_db, context) = create_personas_db("delete_where_all_rows");
+
+        assert_rows_affected(execute(&simple_db, &context, "DELETE FROM personas WHERE dinero >= 0;"), 10);
+        assert_eq!(select_ids(&simple_db, &context), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn insert_multiple_rows() {
+        let (simple_db, context) = create_personas_db("insert_multiple_rows");
+
+        assert_rows_affected(execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (10, 100), (11, 110), (12, 120);"), 3);
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+    }
+
+    #[test]
+    fn select_is_null() {
+        let (simple_db, context) = create_db("select_is_null");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, nombre VARCHAR);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, dinero, nombre) VALUES (1, 0, "a"), (2, 5, "a");"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (3, "a");"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, dinero, nombre) VALUES (4, NULL, "b");"#);
+
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero IS NULL"), vec![3, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero IS NOT NULL"), vec![1, 2]);
+        //Comparing with null never matches
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 5"), vec![2]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero != 5"), vec![1]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = NULL"), Vec::<i64>::new());
+        assert_eq!(select_ids_where(&simple_db, &context, "NOT dinero = 5"), vec![1]);
+        //null OR true -> true, null AND true -> null
+        assert_eq!(select_ids_where(&simple_db, &context, r#"dinero = 5 OR nombre = "b""#), vec![2, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"dinero = 0 AND nombre = "a""#), vec![1]);
+
+        let statement = simple_db.parse("INSERT INTO personas (id, dinero) VALUES (NULL, 1);").unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::PrimaryColumnNotIncluded())));
+    }
+
+    #[test]
+    fn select_where_in_subquery() {
+        let (simple_db, context) = create_personas_db("select_where_in_subquery");
+        execute(&simple_db, &context, "CREATE TABLE deudas (id I64 PRIMARY KEY, persona_id I64, cantidad I64);");
+        execute(&simple_db, &context, "INSERT INTO deudas (id, persona_id, cantidad) VALUES (1, 2, 5), (2, 7, 50), (3, 4, 100), (4, 7, 20);");
+
+        assert_eq!(select_ids_where(&simple_db, &context, "id IN (SELECT persona_id FROM deudas)"), vec![2, 4, 7]);
+        assert_eq!(select_ids_where(&simple_db, &context, "id IN (SELECT persona_id FROM deudas WHERE cantidad > 10)"), vec![4, 7]);
+        assert_eq!(select_ids_where(&simple_db, &context, "id IN (SELECT persona_id FROM deudas WHERE cantidad > 1000)"), Vec::<i64>::new());
+        //Subqueries can contain other subqueries
+        assert_eq!(select_ids_where(&simple_db, &context,
+            "id IN (SELECT persona_id FROM deudas WHERE cantidad = (SELECT MAX(cantidad) FROM deudas))"), vec![4]);
+        assert_eq!(select_ids_where(&simple_db, &context, "id NOT IN (SELECT persona_id FROM deudas) AND id < 4"), vec![0, 1, 3]);
+
+        execute(&simple_db, &context, "UPDATE personas SET dinero = 0 WHERE id IN (SELECT persona_id FROM deudas WHERE cantidad = 20);");
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 0"), vec![0, 7]);
+
+        execute(&simple_db, &context, "DELETE FROM personas WHERE id IN (SELECT persona_id FROM deudas WHERE cantidad < 10);");
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn select_where_scalar_subquery() {
+        let (simple_db, context) = create_personas_db("select_where_scalar_subquery");
+        execute(&simple_db, &context, "CREATE TABLE deudas (id I64 PRIMARY KEY, persona_id I64, cantidad I64);");
+        execute(&simple_db, &context, "INSERT INTO deudas (id, persona_id, cantidad) VALUES (1, 2, 5), (2, 7, 50);");
+
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = (SELECT MAX(dinero) FROM personas)"), vec![9]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero > (SELECT AVG(dinero) FROM personas)"), vec![5, 6, 7, 8, 9]);
+        assert_eq!(select_ids_where(&simple_db, &context, "id = (SELECT persona_id FROM deudas WHERE id = 2)"), vec![7]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero > (SELECT cantidad FROM deudas WHERE id = 2) + 10"), vec![7, 8, 9]);
+        //A subquery without rows produces null, which never matches
+        assert_eq!(select_ids_where(&simple_db, &context, "id = (SELECT persona_id FROM deudas WHERE id = 3)"), Vec::<i64>::new());
+
+        let statement = simple_db.parse("SELECT id FROM personas WHERE id = (SELECT persona_id FROM deudas);").unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::ScalarSubqueryReturnedMoreThanOneRow)));
+        let statement = simple_db.parse("SELECT id FROM personas WHERE id = (SELECT * FROM deudas WHERE id = 1);").unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn select_where_exists() {
+        let (simple_db, context) = create_personas_db("select_where_exists");
+        execute(&simple_db, &context, "CREATE TABLE deudas (id I64 PRIMARY KEY, persona_id I64, cantidad I64);");
+        execute(&simple_db, &context, "CREATE INDEX ON deudas (persona_id);");
+        execute(&simple_db, &context, "INSERT INTO deudas (id, persona_id, cantidad) VALUES (1, 2, 5), (2, 7, 50), (3, 4, 100), (4, 7, 20);");
+
+        //Correlated, the subquery is run for every row of personas using the index on deudas.persona_id
+        assert_eq!(select_ids_where(&simple_db, &context, "EXISTS (SELECT 1 FROM deudas WHERE deudas.persona_id = personas.id)"), vec![2, 4, 7]);
+        assert_eq!(select_ids_where(&simple_db, &context, "EXISTS (SELECT * FROM deudas WHERE persona_id = personas.id AND cantidad > 10)"), vec![4, 7]);
+        assert_eq!(select_ids_where(&simple_db, &context, "NOT EXISTS (SELECT 1 FROM deudas WHERE deudas.persona_id = personas.id)"), vec![0, 1, 3, 5, 6, 8, 9]);
+        assert_eq!(select_ids_where(&simple_db, &context, "personas.id < 5 AND EXISTS (SELECT 1 FROM deudas WHERE deudas.persona_id = personas.id)"), vec![2, 4]);
+        //Correlated on a column without index
+        assert_eq!(select_ids_where(&simple_db, &context, "EXISTS (SELECT 1 FROM deudas WHERE deudas.cantidad = personas.dinero)"), vec![2, 5]);
+
+        //Uncorrelated, the subquery is run once
+        assert_eq!(select_ids_where(&simple_db, &context, "EXISTS (SELECT 1 FROM deudas WHERE cantidad > 1000)"), Vec::<i64>::new());
+        assert_eq!(select_ids_where(&simple_db, &context, "NOT EXISTS (SELECT 1 FROM deudas WHERE cantidad > 1000)").len(), 10);
+        assert_eq!(select_ids_where(&simple_db, &context, "id < 2 AND EXISTS (SELECT 1 FROM deudas)"), vec![0, 1]);
+
+        execute(&simple_db, &context, "DELETE FROM personas WHERE NOT EXISTS (SELECT 1 FROM deudas WHERE deudas.persona_id = personas.id);");
+        assert_eq!(select_ids(&simple_db, &context), vec![2, 4, 7]);
+
+        //Columns of the outer table must be qualified
+        let statement = simple_db.parse("SELECT id FROM personas WHERE EXISTS (SELECT 1 FROM deudas WHERE persona_id = dinero);").unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::UnknownColumn(_))));
+    }
+
+    #[test]
+    fn explain() {
+        let (simple_db, context) = create_personas_db("explain");
+        execute(&simple_db, &context, "CREATE INDEX ON personas (dinero);");
+
+        let explain = explain_statement(&simple_db, &context, "EXPLAIN SELECT * FROM personas WHERE dinero = 10 + 10;");
+        assert!(matches!(explain.scan(), ExplainScan::ExactSecondary(_, column_name, Expression::Literal(Value::I64(20))) if column_name == "dinero"));
+        assert_eq!(explain.to_lines()[1], "Filter: dinero = 20");
+
+        let explain = explain_statement(&simple_db, &context, "EXPLAIN SELECT * FROM personas WHERE dinero > 10;");
+        assert!(matches!(explain.scan(), ExplainScan::SecondaryRange(_, _)));
+        assert!(explain.to_lines()[0].starts_with("Secondary index range scan 10 < dinero (keyspace"));
+        assert_eq!(explain.to_lines()[1], "Filter: dinero > 10");
+
+        let explain = explain_statement(&simple_db, &context, "EXPLAIN SELECT * FROM personas WHERE id = 1;");
+        assert!(matches!(explain.scan(), ExplainScan::ExactPrimary(_, Expression::Literal(Value::I64(1)))));
+
+        let explain = explain_statement(&simple_db, &context, "EXPLAIN SELECT COUNT(*) FROM personas WHERE id >= 2 AND id < 5 LIMIT 1;");
+        assert!(matches!(explain.scan(), ExplainScan::Range(_, _)));
+        assert!(explain.to_lines()[0].starts_with("Range scan of table personas 2 <= id < 5"));
+        assert_eq!(explain.to_lines()[2..], [String::from("Aggregate all rows"), String::from("Limit: 1 Offset: 0")]);
+
+        let explain = explain_statement(&simple_db, &context, "EXPLAIN UPDATE personas SET dinero = 0 WHERE id = 1 OR dinero = 50;");
+        assert!(matches!(explain.scan(), ExplainScan::Union(_, _)));
+
+        //Explained statements are not run
+        explain_statement(&simple_db, &context, "EXPLAIN DELETE FROM personas;");
+        assert_eq!(select_ids(&simple_db, &context).len(), 10);
+    }
+
+    #[test]
+    fn insert_multiple_rows_type_mismatch() {
+        let (simple_db, context) = create_personas_db("insert_multiple_rows_type_mismatch");
+
+        let statement = simple_db.parse(r#"INSERT INTO personas (id, dinero) VALUES (10, 100), (11, 110), (12, "120");"#).unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::InvalidType(_))));
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn rollback_to_savepoint() {
+        let (simple_db, context) = create_personas_db("rollback_to_savepoint");
+
+        execute(&simple_db, &context, "SAVEPOINT before_changes;");
+        execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (10, 100);");
+        execute(&simple_db, &context, "UPDATE personas SET dinero = 0 WHERE id = 1;");
+        execute(&simple_db, &context, "DELETE FROM personas WHERE id = 2;");
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+        execute(&simple_db, &context, "ROLLBACK TO SAVEPOINT before_changes;");
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 10"), vec![1]);
+
+        //The savepoint is kept after rolling back to it
+        execute(&simple_db, &context, "DELETE FROM personas WHERE id = 0;");
+        execute(&simple_db, &context, "ROLLBACK TO before_changes;");
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        let statement = simple_db.parse("ROLLBACK TO unknown;").unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::SavepointNotFound(_))));
+    }
+
+    #[test]
+    fn update_self_referential() {
+        let (simple_db, context) = create_personas_db("update_self_referential");
+
+        assert_rows_affected(execute(&simple_db, &context, "UPDATE personas SET dinero = dinero - 10, id = id WHERE dinero >= 50;"), 5);
+
+        let mut query_iterator = execute(&simple_db, &context, "SELECT id, dinero FROM personas;").data();
+        let mut rows = Vec::new();
+        while let Some(row) = query_iterator.next().unwrap() {
+            rows.push((row.get_column_value("id").unwrap().get_i64().unwrap(), row.get_column_value("dinero").unwrap().get_i64().unwrap()));
+        }
+        rows.sort();
+
+        assert_eq!(rows, vec![(0, 0), (1, 10), (2, 20), (3, 30), (4, 40), (5, 40), (6, 50), (7, 60), (8, 70), (9, 80)]);
+    }
+
+    #[test]
+    fn update_indexed_column() {
+        let (simple_db, context) = create_db("update_indexed_column");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        execute(&simple_db, &context, "CREATE INDEX ON personas (nombre);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (1, "a");"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (2, "b");"#);
+
+        assert_rows_affected(execute(&simple_db, &context, r#"UPDATE personas SET nombre = "z" WHERE id = 1;"#), 1);
+
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        let indexed_ids: Vec<Bytes> = table.scan_all_secondary_index(context.transaction(), "nombre").unwrap()
+            .next_all()
+            .iter()
+            .map(|key| key.as_bytes().clone())
+            .collect();
+
+        //Index is sorted by nombre: "a" no longer points to 1, "b" -> 2, "z" -> 1
+        assert_eq!(indexed_ids, vec![Value::I64(2).serialize(), Value::I64(1).serialize()]);
+    }
+
+    #[test]
+    fn max_indexes_per_table() {
+        let (simple_db, context) = create_db_with_options("max_indexes_per_table", |options| {
+            options.max_indexes_per_table(2);
+        });
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64, edad I64);");
+        execute(&simple_db, &context, "CREATE INDEX ON personas (nombre);");
+        execute(&simple_db, &context, "CREATE INDEX ON personas (dinero);");
+
+        let statement = simple_db.parse("CREATE INDEX ON personas (edad);").unwrap();
+        assert!(matches!(
+            simple_db.execute(&context, statement),
+            Err(SimpleDbError::TooManyIndexes(table_name, 2)) if table_name == "personas"
+        ));
+    }
+
+    #[test]
+    fn verify_secondary_index_missing_entry() {
+        let (simple_db, context) = create_db("verify_secondary_index_missing_entry");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        execute(&simple_db, &context, "CREATE INDEX ON personas (dinero);");
+        for id in 0..5 {
+            execute(&simple_db, &context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id * 10));
+        }
+        assert_rows_affected(execute(&simple_db, &context, "UPDATE personas SET dinero = 100 WHERE id = 1;"), 1);
+
+        assert!(simple_db.verify_secondary_indexes(&context, "personas").unwrap().is_consistent());
+
+        //Corrupt the index by removing the entry of the row with id 3
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        let dinero = table.get_column_desc("dinero").unwrap();
+        table.storage.delete_with_transaction(
+            dinero.secondary_index_keyspace_id.unwrap(),
+            context.transaction(),
+            Value::I64(30).serialize()
+        ).unwrap();
+
+        let report = simple_db.verify_secondary_indexes(&context, "personas").unwrap();
+        assert_eq!(report.missing_entries, vec![IndexEntry {
+            column_id: dinero.column_id,
+            indexed_value: Value::I64(30).serialize(),
+            primary_key: Value::I64(3).serialize(),
+        }]);
+        assert!(report.dangling_entries.is_empty());
+    }
+
+    #[test]
+    fn bulk_insert_deferring_index_maintenance() {
+        let (simple_db, context) = create_db("bulk_insert_deferring_index_maintenance");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        execute(&simple_db, &context, "CREATE INDEX ON personas (dinero);");
+        //Indexed before the bulk insert
+        execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (1000, 3);");
+
+        let values: Vec<String> = (0..500).map(|id| format!("({}, {})", id, id % 10)).collect();
+        let statement = simple_db.parse(&format!("INSERT INTO personas (id, dinero) VALUES {};", values.join(", "))).unwrap();
+        assert_rows_affected(simple_db.bulk_insert(&context, statement, true).unwrap(), 500);
+
+        assert!(simple_db.verify_secondary_indexes(&context, "personas").unwrap().is_consistent());
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        let mut iterator = table.scan_from_key_secondary_index(&Value::I64(3).serialize(), context.transaction(), "dinero").unwrap();
+        let mut indexed_ids = Vec::new();
+        while let Some(primary_key) = iterator.next() {
+            if iterator.indexed_value() != &Value::I64(3).serialize() {
+                break;
+            }
+            indexed_ids.push(primary_key.as_bytes().clone());
+        }
+        indexed_ids.sort();
+        let mut expected_ids: Vec<Bytes> = (0..500).filter(|id| id % 10 == 3)
+            .chain(vec![1000])
+            .map(|id| Value::I64(id).serialize())
+            .collect();
+        expected_ids.sort();
+        assert_eq!(indexed_ids, expected_ids);
+    }
+
+    #[test]
+    fn column_chunked_projection() {
+        let (row_db, row_context) = create_db_with_options("column_chunked_projection_row", |options| {
+            options.row_format(RowFormat::Row);
+        });
+        let (chunked_db, chunked_context) = create_db_with_options("column_chunked_projection_chunked", |options| {
+            options.row_format(RowFormat::ColumnChunked);
+        });
+        let bytes_read_row = insert_wide_rows_and_scan_two_columns(&row_db, &row_context);
+        let bytes_read_chunked = insert_wide_rows_and_scan_two_columns(&chunked_db, &chunked_context);
+        assert!(bytes_read_chunked * 10 < bytes_read_row);
+
+        //Updates, deletes & secondary indexes work on column chunked tables
+        assert_rows_affected(execute(&chunked_db, &chunked_context, "UPDATE personas SET dinero = 1000 WHERE id = 3;"), 1);
+        assert_rows_affected(execute(&chunked_db, &chunked_context, "DELETE FROM personas WHERE id >= 10;"), 40);
+        assert_rows_affected(execute(&chunked_db, &chunked_context, "CREATE INDEX ON personas (dinero);"), 10);
+        assert_eq!(select_ids_where(&chunked_db, &chunked_context, "dinero = 1000"), vec![3]);
+        assert!(chunked_db.verify_secondary_indexes(&chunked_context, "personas").unwrap().is_consistent());
+
+        let mut query_iterator = execute(&chunked_db, &chunked_context, "SELECT * FROM personas WHERE id = 4;").data();
+        let row = query_iterator.next().unwrap().unwrap();
+        assert_eq!(row.get_column_value("dinero").unwrap(), Value::I64(40));
+        assert_eq!(row.get_column_value("c9").unwrap(), Value::String("a".repeat(100)));
+        assert!(query_iterator.next().unwrap().is_none());
+    }
+
+    //Creates table personas with 10 wide columns & 50 rows. Returns the bytes read by a scan of id & dinero
+    fn insert_wide_rows_and_scan_two_columns(simple_db: &SimpleDb, context: &Context) -> usize {
+        let wide_columns: Vec<String> = (0..10).map(|n| format!("c{}", n)).collect();
+        execute(simple_db, context, &format!("CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, {});",
+            wide_columns.iter().map(|column| format!("{} VARCHAR", column)).collect::<Vec<String>>().join(", ")));
+        let wide_values = vec![format!("\"{}\"", "a".repeat(100)); wide_columns.len()].join(", ");
+        for id in 0..50 {
+            execute(simple_db, context, &format!("INSERT INTO personas (id, dinero, {}) VALUES ({}, {}, {});",
+                wide_columns.join(", "), id, id * 10, wide_values));
+        }
+
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        let selection = Selection::Some(vec![String::from("id"), String::from("dinero")]);
+        let mut iterator = table.scan_all(context.transaction(), selection).unwrap();
+        let mut dinero = Vec::new();
+        while iterator.next() {
+            dinero.push(iterator.row().get_column_value("dinero").unwrap().get_i64().unwrap());
+        }
+        assert_eq!(dinero, (0..50).map(|id| id * 10).collect::<Vec<i64>>());
+
+        iterator.n_bytes_read()
+    }
+
+    #[test]
+    fn create_index_on_populated_table() {
+        let (simple_db, mut context) = create_personas_db("create_index_on_populated_table");
+        execute(&simple_db, &context, "COMMIT;");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+
+        assert_rows_affected(execute(&simple_db, &context, "CREATE INDEX idx_dinero ON personas(dinero);"), 10);
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        assert!(table.is_secondary_indexed("dinero"));
+
+        //Rows inserted after the index has been created are indexed too
+        execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (10, 50);");
+
+        let mut query_iterator = execute(&simple_db, &context, "SELECT id, dinero FROM personas WHERE dinero = 50;").data();
+        let mut ids = Vec::new();
+        while let Some(row) = query_iterator.next().unwrap() {
+            ids.push(row.get_column_value("id").unwrap().get_i64().unwrap());
+        }
+        ids.sort();
+        assert_eq!(ids, vec![5, 10]);
+    }
+
+    #[test]
+    fn select_where_secondary_index_range() {
+        let (simple_db, mut context) = create_db("select_where_secondary_index_range");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, nombre VARCHAR);");
+        for id in 0..20 {
+            execute(&simple_db, &context, &format!(
+                "INSERT INTO personas (id, dinero, nombre) VALUES ({}, {}, \"{}\");", id, (19 - id) * 20, (b'a' + id as u8) as char
+            ));
+        }
+        execute(&simple_db, &context, "COMMIT;");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        execute(&simple_db, &context, "CREATE INDEX idx_dinero ON personas(dinero);");
+        execute(&simple_db, &context, "CREATE INDEX idx_nombre ON personas(nombre);");
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        let n_full_scans = table.n_full_scans();
+
+        //Values bigger than 255 are not sorted by their serialized bytes
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero BETWEEN 200 AND 300"), vec![4, 5, 6, 7, 8, 9]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero > 300"), vec![0, 1, 2, 3]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero <= 20"), vec![18, 19]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero >= 100 AND dinero < 140"), vec![13, 14]);
+        assert_eq!(select_ids_where(&simple_db, &context, "nombre >= \"c\" AND nombre < \"f\""), vec![2, 3, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, "nombre > \"q\""), vec![17, 18, 19]);
+        assert_eq!(table.n_secondary_index_scans(), 6);
+        assert_eq!(table.n_full_scans(), n_full_scans);
+
+        let explain = explain_statement(&simple_db, &context, "EXPLAIN SELECT * FROM personas WHERE dinero > 300;");
+        assert!(matches!(explain.scan(), ExplainScan::SecondaryRange(_, _)));
+    }
+
+    #[test]
+    fn select_where_merge_scans() {
+        let (simple_db, context) = create_db("select_where_merge_scans");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 250..262 {
+            execute(&simple_db, &context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id % 3));
+        }
+        execute(&simple_db, &context, "CREATE INDEX idx_dinero ON personas(dinero);");
+
+        //Primary keys bigger than 255 are not sorted by their serialized bytes
+        assert_eq!(select_ids_where(&simple_db, &context, "id >= 254 AND id < 258"), vec![254, 255, 256, 257]);
+        //Rows returned by both sides of an OR are returned once
+        assert_eq!(select_ids_where(&simple_db, &context, "id = 252 OR dinero = 0"), vec![252, 255, 258, 261]);
+        assert_eq!(select_ids_where(&simple_db, &context, "id > 255 AND (id = 256 OR id = 300)"), vec![256]);
+        assert_eq!(select_ids_where(&simple_db, &context, "id < 256 AND dinero > 1"), vec![251, 254]);
+    }
+
+    #[test]
+    fn select_where_secondary_index_equal() {
+        let (simple_db, mut context) = create_db("select_where_secondary_index_equal");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, edad I64);");
+        for id in 0..20 {
+            execute(&simple_db, &context, &format!("INSERT INTO personas (id, dinero, edad) VALUES ({}, {}, {});", id, id % 4, id % 3));
+        }
+        execute(&simple_db, &context, "COMMIT;");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        execute(&simple_db, &context, "CREATE INDEX idx_dinero ON personas(dinero);");
+
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        //Index that is still being created, it cannot be read yet
+        let edad_column_id = table.get_column_desc("edad").unwrap().column_id;
+        table.secondary_indexes.create_new_secondary_index(&[edad_column_id]).unwrap();
+        assert!(!table.is_secondary_indexed("edad"));
+
+        let n_full_scans = table.n_full_scans();
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 2"), vec![2, 6, 10, 14, 18]);
+        assert_eq!(select_ids_where(&simple_db, &context, "2 = dinero"), vec![2, 6, 10, 14, 18]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 5"), Vec::<i64>::new());
+        assert_eq!(table.n_secondary_index_scans(), 3);
+        assert_eq!(table.n_full_scans(), n_full_scans);
+
+        //Non active and non indexed columns fall back to a full scan
+        assert_eq!(select_ids_where(&simple_db, &context, "edad = 1"), vec![1, 4, 7, 10, 13, 16, 19]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero + 1 = 3"), vec![2, 6, 10, 14, 18]);
+        assert_eq!(table.n_secondary_index_scans(), 3);
+        assert_eq!(table.n_full_scans(), n_full_scans + 2);
+    }
+
+    #[test]
+    fn select_where_composite_index() {
+        let (simple_db, mut context) = create_db("select_where_composite_index");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, apellido VARCHAR, nombre VARCHAR, edad I64);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, apellido, nombre, edad) VALUES (1, "Garcia", "Ana", 20);"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, apellido, nombre, edad) VALUES (2, "Garcia", "Luis", 30);"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, apellido, nombre, edad) VALUES (3, "ab", "c", 40);"#);
+        execute(&simple_db, &context, "COMMIT;");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        execute(&simple_db, &context, "CREATE INDEX ON personas(apellido, nombre);");
+        //Rows inserted & updated once the index exists
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, apellido, nombre, edad) VALUES (4, "Garcia", "Ana", 50);"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, apellido, nombre, edad) VALUES (5, "a", "bc", 60);"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, apellido, edad) VALUES (6, "Perez", 70);"#);
+        execute(&simple_db, &context, r#"UPDATE personas SET nombre = "Eva" WHERE id = 2;"#);
+
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        let n_full_scans = table.n_full_scans();
+
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "Garcia" AND nombre = "Ana""#), vec![1, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"nombre = "Eva" AND apellido = "Garcia""#), vec![2]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "Garcia" AND nombre = "Luis""#), Vec::<i64>::new());
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "ab" AND nombre = "c""#), vec![3]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "a" AND nombre = "bc""#), vec![5]);
+        assert_eq!(table.n_secondary_index_scans(), 5);
+
+        //Leading prefix only
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "Garcia""#), vec![1, 2, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "Garcia" AND edad > 25"#), vec![2, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "Perez""#), vec![6]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "a""#), vec![5]);
+        assert_eq!(table.n_secondary_index_scans(), 9);
+        assert_eq!(table.n_full_scans(), n_full_scans);
+
+        //Not a leading prefix of the index
+        assert_eq!(select_ids_where(&simple_db, &context, r#"nombre = "Ana""#), vec![1, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "Garcia" OR nombre = "c""#), vec![1, 2, 3, 4]);
+        assert_eq!(table.n_secondary_index_scans(), 9);
+        assert_eq!(table.n_full_scans(), n_full_scans + 2);
+
+        //Composite indexes are persisted in the table descriptor
+        execute(&simple_db, &context, "COMMIT;");
+        drop(table);
+        drop(simple_db);
+        let simple_db = open_db_with_options("select_where_composite_index", |_| {});
+        let mut context = Context::create_with_database("test");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        assert_eq!(select_ids_where(&simple_db, &context, r#"apellido = "Garcia" AND nombre = "Ana""#), vec![1, 4]);
+        assert_eq!(table.n_secondary_index_scans(), 1);
+        assert!(table.get_indexed_columns().iter()
+            .any(|(columns_names, _)| columns_names == "apellido, nombre"));
+    }
+
+    #[test]
+    fn not_null_constraint() {
+        let (simple_db, context) = create_db("not_null_constraint");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR NOT NULL, dinero I64);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (1, "Jaime");"#);
+
+        let missing = simple_db.parse("INSERT INTO personas (id, dinero) VALUES (2, 10);").unwrap();
+        assert!(matches!(simple_db.execute(&context, missing), Err(SimpleDbError::NotNullViolation(column)) if column == "nombre"));
+        let null = simple_db.parse(r#"INSERT INTO personas (id, nombre) VALUES (2, NULL);"#).unwrap();
+        assert!(matches!(simple_db.execute(&context, null), Err(SimpleDbError::NotNullViolation(_))));
+        //None of the rows is inserted
+        let multiple_rows = simple_db.parse(r#"INSERT INTO personas (id, nombre) VALUES (3, "Pedro"), (4, NULL);"#).unwrap();
+        assert!(matches!(simple_db.execute(&context, multiple_rows), Err(SimpleDbError::NotNullViolation(_))));
+        let update = simple_db.parse("UPDATE personas SET nombre = NULL WHERE id = 1;").unwrap();
+        assert!(matches!(simple_db.execute(&context, update), Err(SimpleDbError::NotNullViolation(_))));
+
+        assert_eq!(select_ids_where(&simple_db, &context, r#"nombre = "Jaime""#), vec![1]);
+        assert_eq!(select_ids_where(&simple_db, &context, "id > 0"), vec![1]);
+    }
+
+    #[test]
+    fn unique_constraint() {
+        let (simple_db, context) = create_db("unique_constraint");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64 UNIQUE, edad I64);");
+        execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (1, 10), (2, 20);");
+        //Null values are not duplicated
+        execute(&simple_db, &context, "INSERT INTO personas (id, edad) VALUES (3, 30), (4, 30);");
+
+        let duplicated = simple_db.parse("INSERT INTO personas (id, dinero) VALUES (5, 10);").unwrap();
+        assert!(matches!(simple_db.execute(&context, duplicated), Err(SimpleDbError::UniqueViolation(column, _)) if column == "dinero"));
+        //None of the rows is inserted
+        let duplicated_in_statement = simple_db.parse("INSERT INTO personas (id, dinero) VALUES (6, 60), (7, 60);").unwrap();
+        assert!(matches!(simple_db.execute(&context, duplicated_in_statement), Err(SimpleDbError::UniqueViolation(_, _))));
+        let update = simple_db.parse("UPDATE personas SET dinero = 20 WHERE id = 1;").unwrap();
+        assert!(matches!(simple_db.execute(&context, update), Err(SimpleDbError::UniqueViolation(_, _))));
+        let update_multiple_rows = simple_db.parse("UPDATE personas SET dinero = 40 WHERE id > 2;").unwrap();
+        assert!(matches!(simple_db.execute(&context, update_multiple_rows), Err(SimpleDbError::UniqueViolation(_, _))));
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 40 OR dinero = 60"), Vec::<i64>::new());
+
+        //Rows keeping its value, or moving it to other row of the statement
+        execute(&simple_db, &context, "UPDATE personas SET dinero = 10 WHERE id = 1;");
+        execute(&simple_db, &context, "UPDATE personas SET dinero = dinero + 10 WHERE id < 3;");
+        execute(&simple_db, &context, "DELETE FROM personas WHERE id = 2;");
+        execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (5, 30);");
+
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 20"), vec![1]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 30"), vec![5]);
+        let table = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("personas").unwrap();
+        assert!(table.is_secondary_indexed("dinero"));
+    }
+
+    #[test]
+    fn insert_default_values() {
+        let (simple_db, context) = create_db("insert_default_values");
+        execute(&simple_db, &context, r#"CREATE TABLE personas (id I64 PRIMARY KEY, estado VARCHAR DEFAULT "nuevo", dinero I64 NOT NULL DEFAULT 10);"#);
+        execute(&simple_db, &context, "INSERT INTO personas (id) VALUES (1);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, estado, dinero) VALUES (2, "viejo", 5);"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, estado) VALUES (3, "viejo");"#);
+
+        assert_eq!(select_ids_where(&simple_db, &context, r#"estado = "nuevo""#), vec![1]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"estado = "viejo""#), vec![2, 3]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 10"), vec![1, 3]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 5"), vec![2]);
+
+        let invalid_default = simple_db.parse(r#"CREATE TABLE deudas (id I64 PRIMARY KEY, cantidad I64 DEFAULT "a");"#).unwrap();
+        assert!(matches!(simple_db.execute(&context, invalid_default), Err(SimpleDbError::InvalidType(_))));
+    }
+
+    #[test]
+    fn string_functions() {
+        let (simple_db, context) = create_db("string_functions");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (1, "Jaime"), (2, "Begoña"), (3, "ana");"#);
+        execute(&simple_db, &context, "INSERT INTO personas (id) VALUES (4);");
+
+        assert_eq!(select_ids_where(&simple_db, &context, r#"UPPER(nombre) = "JAIME""#), vec![1]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"LOWER(nombre) = "ana""#), vec![3]);
+        //"ñ" takes 2 bytes
+        assert_eq!(select_ids_where(&simple_db, &context, "LENGTH(nombre) = 6"), vec![2]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"SUBSTR(nombre, 2, 3) = "ego""#), vec![2]);
+        //Out of range start & negative or zero length produce empty strings
+        assert_eq!(select_ids_where(&simple_db, &context, r#"SUBSTR(nombre, 10, 2) = """#), vec![1, 2, 3]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"SUBSTR(nombre, 1, 0) = """#), vec![1, 2, 3]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"SUBSTR(nombre, 1, -1) = """#), vec![1, 2, 3]);
+
+        let mut query_iterator = execute(&simple_db, &context,
+            "SELECT id, UPPER(nombre), LENGTH(nombre), SUBSTR(nombre, 1, 3) FROM personas WHERE id <= 2;").data();
+        let columns_name: Vec<String> = query_iterator.columns_descriptor_selection().iter()
+            .map(|column_desc| column_desc.column_name.clone())
+            .collect();
+        assert_eq!(columns_name, vec!["id", "UPPER(nombre)", "LENGTH(nombre)", r#"SUBSTR(nombre, 1, 3)"#]);
+        let rows = query_iterator.all().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].get_column_value("UPPER(nombre)").unwrap(), Value::String(String::from("BEGOÑA")));
+        assert_eq!(rows[1].get_column_value("LENGTH(nombre)").unwrap(), Value::I64(6));
+        assert_eq!(rows[1].get_column_value("SUBSTR(nombre, 1, 3)").unwrap(), Value::String(String::from("Beg")));
+
+        let rows = execute(&simple_db, &context, "SELECT UPPER(nombre) FROM personas WHERE id = 4;").data().all().unwrap();
+        assert_eq!(rows[0].get_column_value("UPPER(nombre)").unwrap(), Value::Null);
+
+        let wrong_args = simple_db.parse("SELECT id FROM personas WHERE LENGTH(id) = 1;").unwrap();
+        assert!(matches!(simple_db.execute(&context, wrong_args), Err(SimpleDbError::MalformedQuery(_))));
+        let unknown_function = simple_db.parse("SELECT REVERSE(nombre) FROM personas;").unwrap();
+        assert!(matches!(simple_db.execute(&context, unknown_function), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn select_expression_projection() {
+        let (simple_db, context) = create_db("select_expression_projection");
+        execute(&simple_db, &context, "CREATE TABLE pedidos (id I64 PRIMARY KEY, nombre VARCHAR, precio F64, cantidad I32);");
+        execute(&simple_db, &context, r#"INSERT INTO pedidos (id, nombre, precio, cantidad) VALUES (1, "pan", 1.5, 4), (2, "leche", 2.0, 3);"#);
+
+        //Arithmetic over a single column
+        let rows = execute(&simple_db, &context, "SELECT id, cantidad * 2 FROM pedidos;").data().all().unwrap();
+        assert_eq!(rows[0].get_column_value("cantidad * 2").unwrap(), Value::I64(8));
+        assert_eq!(rows[1].get_column_value("cantidad * 2").unwrap(), Value::I64(6));
+
+        //Projection referencing two columns with an alias
+        let mut query_iterator = execute(&simple_db, &context,
+            "SELECT precio * cantidad AS total, nombre, id + 1 AS siguiente FROM pedidos WHERE id = 1;").data();
+        let columns_name: Vec<String> = query_iterator.columns_descriptor_selection().iter()
+            .map(|column_desc| column_desc.column_name.clone())
+            .collect();
+        assert_eq!(columns_name, vec!["total", "nombre", "siguiente"]);
+        let rows = query_iterator.all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("total").unwrap(), Value::F64(6.0));
+        assert_eq!(rows[0].get_column_value("nombre").unwrap(), Value::String(String::from("pan")));
+        assert_eq!(rows[0].get_column_value("siguiente").unwrap(), Value::I64(2));
+
+        //Columns can also be renamed
+        let rows = execute(&simple_db, &context, "SELECT nombre AS producto FROM pedidos WHERE id = 2;").data().all().unwrap();
+        assert_eq!(rows[0].get_column_value("producto").unwrap(), Value::String(String::from("leche")));
+
+        let wrong_type = simple_db.parse("SELECT nombre * 2 FROM pedidos;").unwrap();
+        assert!(matches!(simple_db.execute(&context, wrong_type), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn select_distinct() {
+        let (simple_db, context) = create_db("select_distinct");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, ciudad VARCHAR);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre, ciudad) VALUES (1, "Jaime", "Madrid"), (2, "Ana", "Madrid"),
+            (3, "Jaime", "Sevilla"), (4, "Jaime", "Madrid"), (5, "Ana", "Madrid");"#);
+        execute(&simple_db, &context, "INSERT INTO personas (id) VALUES (6), (7);");
+
+        //Null values are also deduplicated
+        let rows = execute(&simple_db, &context, "SELECT DISTINCT nombre FROM personas;").data().all().unwrap();
+        assert_eq!(sorted_strings(&rows, "nombre"), vec!["", "Ana", "Jaime"]);
+
+        let rows = execute(&simple_db, &context, "SELECT DISTINCT nombre, ciudad FROM personas WHERE id <= 5;").data().all().unwrap();
+        let mut values: Vec<String> = rows.iter()
+            .map(|row| format!("{} {}", string_value(row, "nombre"), string_value(row, "ciudad")))
+            .collect();
+        values.sort();
+        assert_eq!(values, vec!["Ana Madrid", "Jaime Madrid", "Jaime Sevilla"]);
+
+        let rows = execute(&simple_db, &context, r#"SELECT DISTINCT ciudad FROM personas WHERE nombre = "Jaime" LIMIT 1;"#).data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn select_distinct_spill() {
+        //Every distinct value doesn't fit in memory, so they are written to disk
+        let (simple_db, context) = create_db_with_options("select_distinct_spill", |options| {
+            options.memtable_max_size_bytes(128);
+        });
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 0..200 {
+            execute(&simple_db, &context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id % 50));
+        }
+
+        let rows = execute(&simple_db, &context, "SELECT DISTINCT dinero FROM personas;").data().all().unwrap();
+        let mut values: Vec<i64> = rows.iter()
+            .map(|row| row.get_column_value("dinero").unwrap().get_i64().unwrap())
+            .collect();
+        values.sort();
+        assert_eq!(values, (0..50).collect::<Vec<i64>>());
+        //Spill files are removed once they have been read
+        let base_path = std::env::temp_dir().join("simpledb_executor_select_distinct_spill");
+        assert!(!std::fs::read_dir(base_path).unwrap()
+            .any(|file| file.unwrap().file_name().to_str().unwrap().ends_with(".spill")));
+    }
+
+    #[test]
+    fn select_join() {
+        let (simple_db, context) = create_db("select_join");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        execute(&simple_db, &context, "CREATE TABLE pedidos (id I64 PRIMARY KEY, persona_id I64, producto VARCHAR);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (1, "Jaime"), (2, "Ana"), (3, "Pedro");"#);
+        execute(&simple_db, &context, r#"INSERT INTO pedidos (id, persona_id, producto) VALUES (10, 1, "pan"), (11, 1, "leche"), (12, 2, "huevos");"#);
+
+        let mut query_iterator = execute(&simple_db, &context,
+            "SELECT personas.nombre, pedidos.producto FROM personas JOIN pedidos ON personas.id = pedidos.persona_id;").data();
+        let columns_name: Vec<String> = query_iterator.columns_descriptor_selection().iter()
+            .map(|column_desc| column_desc.column_name.clone())
+            .collect();
+        assert_eq!(columns_name, vec!["personas.nombre", "pedidos.producto"]);
+        let rows = query_iterator.all().unwrap();
+        let mut values: Vec<String> = rows.iter()
+            .map(|row| format!("{} {}", string_value(row, "personas.nombre"), string_value(row, "pedidos.producto")))
+            .collect();
+        values.sort();
+        assert_eq!(values, vec!["Ana huevos", "Jaime leche", "Jaime pan"]);
+
+        //Unqualified columns belong to the only table that has them, WHERE is applied to the joined rows
+        let rows = execute(&simple_db, &context,
+            r#"SELECT nombre, producto FROM personas INNER JOIN pedidos ON personas.id = persona_id WHERE producto = "pan" OR pedidos.id = 12;"#)
+            .data().all().unwrap();
+        assert_eq!(sorted_strings(&rows, "nombre"), vec!["Ana", "Jaime"]);
+
+        //* returns the columns of both tables
+        let rows = execute(&simple_db, &context,
+            "SELECT * FROM pedidos JOIN personas ON pedidos.persona_id = personas.id WHERE personas.id = 2;").data().all().unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_column_value("pedidos.id").unwrap(), Value::I64(12));
+        assert_eq!(rows[0].get_column_value("personas.nombre").unwrap(), Value::String(String::from("Ana")));
+
+        let ambiguous = simple_db.parse("SELECT id FROM personas JOIN pedidos ON personas.id = pedidos.persona_id;").unwrap();
+        assert!(matches!(simple_db.execute(&context, ambiguous), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    #[test]
+    fn select_join_no_rows() {
+        let (simple_db, context) = create_db("select_join_no_rows");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        execute(&simple_db, &context, "CREATE TABLE pedidos (id I64 PRIMARY KEY, persona_id I64);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (1, "Jaime"), (2, "Ana");"#);
+        execute(&simple_db, &context, "INSERT INTO pedidos (id, persona_id) VALUES (10, 3), (11, 4);");
+        execute(&simple_db, &context, "INSERT INTO pedidos (id) VALUES (12);");
+
+        let rows = execute(&simple_db, &context,
+            "SELECT nombre FROM personas JOIN pedidos ON personas.id = pedidos.persona_id;").data().all().unwrap();
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn select_join_inner_index() {
+        let (simple_db, mut context) = create_db("select_join_inner_index");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        execute(&simple_db, &context, "CREATE TABLE pedidos (id I64 PRIMARY KEY, persona_id I64);");
+        for id in 0..10 {
+            execute(&simple_db, &context, &format!("INSERT INTO personas (id, nombre) VALUES ({}, \"{}\");", id, id));
+            execute(&simple_db, &context, &format!("INSERT INTO pedidos (id, persona_id) VALUES ({}, {});", id * 2, id));
+            execute(&simple_db, &context, &format!("INSERT INTO pedidos (id, persona_id) VALUES ({}, {});", id * 2 + 1, id));
+        }
+        execute(&simple_db, &context, "COMMIT;");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        execute(&simple_db, &context, "CREATE INDEX idx_persona_id ON pedidos(persona_id);");
+        let pedidos = simple_db.get_databases().get_database_or_err("test").unwrap()
+            .get_table_or_err("pedidos").unwrap();
+        let n_full_scans = pedidos.n_full_scans();
+
+        let rows = execute(&simple_db, &context,
+            "SELECT pedidos.id FROM personas JOIN pedidos ON personas.id = pedidos.persona_id WHERE personas.id < 3;").data().all().unwrap();
+        let mut ids: Vec<i64> = rows.iter()
+            .map(|row| row.get_column_value("pedidos.id").unwrap().get_i64().unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![0, 1, 2, 3, 4, 5]);
+        //The matching rows of every persona are read with the index
+        assert_eq!(pedidos.n_secondary_index_scans(), 3);
+        assert_eq!(pedidos.n_full_scans(), n_full_scans);
+    }
+
+    #[test]
+    fn boolean_and_timestamp_columns() {
+        let (simple_db, context) = create_db("boolean_and_timestamp_columns");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, flag BOOLEAN, ts TIMESTAMP);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, flag, ts) VALUES (1, true, "2022-06-15T10:30:00Z"),
+            (2, false, "2023-01-01T00:00:00Z"), (3, true, '2023-03-01T12:00:00.500+02:00'), (4, false, "2024-02-29");"#);
+
+        assert_eq!(select_ids_where(&simple_db, &context, "flag = true"), vec![1, 3]);
+        assert_eq!(select_ids_where(&simple_db, &context, "flag != true"), vec![2, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, "flag < true"), vec![2, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, "ts > '2023-01-01T00:00:00Z'"), vec![3, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"ts >= "2023-01-01T02:00:00+02:00""#), vec![2, 3, 4]);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"ts = "2024-02-29T00:00:00Z" OR flag = true"#), vec![1, 3, 4]);
+
+        let rows = execute(&simple_db, &context, "SELECT MAX(ts) FROM personas;").data().all().unwrap();
+        assert_eq!(rows[0].get_column_value("MAX(ts)").unwrap(), Value::Timestamp(1709164800000));
+
+        let rows = execute(&simple_db, &context, "SELECT * FROM personas WHERE id = 3;").data().all().unwrap();
+        assert_eq!(rows[0].get_column_value("flag").unwrap(), Value::Boolean(true));
+        assert_eq!(rows[0].get_column_value("ts").unwrap(), Value::Timestamp(1677664800500));
+        assert_eq!(rows[0].to_string(), "Row [flag = true, id = 3, ts = 2023-03-01T10:00:00.500Z]");
+
+        execute(&simple_db, &context, r#"UPDATE personas SET ts = "2021-01-01" WHERE id = 3;"#);
+        assert_eq!(select_ids_where(&simple_db, &context, r#"ts < "2022-01-01""#), vec![3]);
+
+        for query in [
+            r#"INSERT INTO personas (id, flag) VALUES (5, "true");"#,
+            "INSERT INTO personas (id, flag) VALUES (5, 1);",
+            "INSERT INTO personas (id, ts) VALUES (5, 1672531200000);",
+            r#"INSERT INTO personas (id, ts) VALUES (5, "2023-02-30");"#,
+            r#"INSERT INTO personas (id, ts) VALUES (5, "hola");"#,
+        ] {
+            let statement = simple_db.parse(query).unwrap();
+            assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::InvalidType(_))), "{}", query);
+        }
+
+        let statement = simple_db.parse(r#"UPDATE personas SET ts = "hola" WHERE id = 1;"#).unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::InvalidType(_))));
+    }
+
+    #[test]
+    fn timestamp_primary_key() {
+        let (simple_db, context) = create_db("timestamp_primary_key");
+        execute(&simple_db, &context, "CREATE TABLE eventos (ts TIMESTAMP PRIMARY KEY, nombre VARCHAR);");
+        execute(&simple_db, &context, r#"INSERT INTO eventos (ts, nombre) VALUES ("2023-01-01", "a"), ("2023-01-02T00:00:00Z", "b");"#);
+
+        let rows = execute(&simple_db, &context, r#"SELECT nombre FROM eventos WHERE ts = "2023-01-02";"#).data().all().unwrap();
+        assert_eq!(sorted_strings(&rows, "nombre"), vec!["b"]);
+        let rows = execute(&simple_db, &context, r#"SELECT nombre FROM eventos WHERE ts IN ("2023-01-01T00:00:00Z", "2023-01-03");"#).data().all().unwrap();
+        assert_eq!(sorted_strings(&rows, "nombre"), vec!["a"]);
+    }
+
+    #[test]
+    fn prepared_statements() {
+        let (simple_db, context) = create_db("prepared_statements");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero I64);");
+
+        let insert = simple_db.prepare("INSERT INTO personas (id, nombre, dinero) VALUES (?, ?, ?);").unwrap();
+        assert_eq!(insert.n_placeholders(), 3);
+        insert.execute(&context, &[Value::I64(1), Value::String(String::from("Jaime")), Value::I64(100)]).unwrap();
+        //Values that would need escaping in SQL are inserted as they are
+        insert.execute(&context, &[Value::I64(2), Value::String(String::from("O'Brien\"); DROP TABLE personas;")), Value::Null]).unwrap();
+
+        let rows = execute(&simple_db, &context, "SELECT * FROM personas;").data().all().unwrap();
+        assert_eq!(sorted_strings(&rows, "nombre"), vec!["Jaime", "O'Brien\"); DROP TABLE personas;"]);
+
+        let select = simple_db.prepare("SELECT id FROM personas WHERE dinero >= ? OR id IN (SELECT id FROM personas WHERE id = ?);").unwrap();
+        let ids = |rows: Vec<Row>| rows.iter().map(|row| row.get_column_value("id").unwrap().get_i64().unwrap()).collect::<Vec<i64>>();
+        assert_eq!(ids(select.execute(&context, &[Value::I64(50), Value::I64(0)]).unwrap().data().all().unwrap()), vec![1]);
+        assert_eq!(ids(select.execute(&context, &[Value::I64(500), Value::I64(2)]).unwrap().data().all().unwrap()), vec![2]);
+
+        let update = simple_db.prepare("UPDATE personas SET dinero = ? WHERE id = ?;").unwrap();
+        update.execute(&context, &[Value::I64(7), Value::I64(2)]).unwrap();
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 7"), vec![2]);
+
+        assert!(matches!(insert.execute(&context, &[Value::I64(3)]), Err(SimpleDbError::WrongNumberOfParameters(3, 1))));
+        //Placeholders can only be used with prepared statements
+        let statement = simple_db.parse("SELECT * FROM personas WHERE id = ?;").unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::MalformedQuery(_))));
+        let statement = simple_db.parse("INSERT INTO personas (id) VALUES (?);").unwrap();
+        assert!(matches!(simple_db.execute(&context, statement), Err(SimpleDbError::MalformedQuery(_))));
+    }
+
+    fn sorted_strings(rows: &[Row], column_name: &str) -> Vec<String> {
+        let mut values: Vec<String> = rows.iter()
+            .map(|row| string_value(row, column_name))
+            .collect();
+        values.sort();
+        values
+    }
+
+    //Null values are returned as empty strings
+    fn string_value(row: &Row, column_name: &str) -> String {
+        match row.get_column_value(column_name).unwrap() {
+            Value::Null => String::new(),
+            value => value.get_string().unwrap().clone(),
+        }
+    }
+
+    #[test]
+    fn auto_increment_primary_key() {
+        let (simple_db, context) = create_db("auto_increment_primary_key");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY AUTOINCREMENT, dinero I64);");
+        execute(&simple_db, &context, "INSERT INTO personas (dinero) VALUES (10), (20);");
+        execute(&simple_db, &context, "INSERT INTO personas (dinero) VALUES (30);");
+        assert_eq!(select_ids(&simple_db, &context), vec![1, 2, 3]);
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 30"), vec![3]);
+
+        //Ids are gap free with a single writer, also when more ids have to be reserved
+        for _ in 0..100 {
+            execute(&simple_db, &context, "INSERT INTO personas (dinero) VALUES (0);");
+        }
+        assert_eq!(select_ids(&simple_db, &context), (1..=103).collect::<Vec<i64>>());
+
+        //Next ids are greater than the ids inserted explicitly
+        execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (200, 0);");
+        execute(&simple_db, &context, "INSERT INTO personas (dinero) VALUES (40);");
+        assert_eq!(select_ids_where(&simple_db, &context, "dinero = 40"), vec![201]);
+
+        let not_integer = simple_db.parse("CREATE TABLE deudas (id VARCHAR PRIMARY KEY AUTOINCREMENT);").unwrap();
+        assert!(matches!(simple_db.execute(&context, not_integer), Err(SimpleDbError::MalformedQuery(_))));
+
+        //The counter is persisted, ids are not reused after reopening the database
+        execute(&simple_db, &context, "COMMIT;");
+        drop(simple_db);
+        let simple_db = open_db_with_options("auto_increment_primary_key", |_| {});
+        let mut context = Context::create_with_database("test");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        execute(&simple_db, &context, "INSERT INTO personas (dinero) VALUES (50);");
+
+        let ids = select_ids_where(&simple_db, &context, "dinero = 50");
+        assert_eq!(ids.len(), 1);
+        assert!(ids[0] > 201);
+        assert_eq!(select_ids(&simple_db, &context).len(), 106);
+    }
+
+    #[test]
+    fn auto_increment_non_i64_primary_key() {
+        let (simple_db, context) = create_db("auto_increment_non_i64_primary_key");
+        for column_type in ["I32", "U8", "U64"] {
+            let create_table = simple_db.parse(&format!("CREATE TABLE deudas (id {} PRIMARY KEY AUTOINCREMENT);", column_type)).unwrap();
+            assert!(matches!(simple_db.execute(&context, create_table), Err(SimpleDbError::MalformedQuery(_))));
+        }
+
+        //Explicit ids are not generated again
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY AUTOINCREMENT, dinero I64);");
+        execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (1, 10), (2, 20);");
+        execute(&simple_db, &context, "INSERT INTO personas (dinero) VALUES (30);");
+        assert_eq!(select_ids(&simple_db, &context), vec![1, 2, 3]);
+        assert_eq!(select_ids_where(&simple_db, &context, "id = 3"), vec![3]);
+    }
+
+    #[test]
+    #[cfg(feature = "arrow")]
+    fn to_arrow_batches() {
+        use arrow_array::{Array, BooleanArray, Float64Array, Int64Array, StringArray};
+        use arrow_schema::DataType;
+
+        let (simple_db, context) = create_db("to_arrow_batches");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero F64, activo BOOLEAN);");
+        for id in 0..300 {
+            //Odd rows don't have nombre
+            if id % 2 == 0 {
+                execute(&simple_db, &context, &format!(
+                    r#"INSERT INTO personas (id, nombre, dinero, activo) VALUES ({}, "persona {}", {}.5, TRUE);"#, id, id, id
+                ));
+            } else {
+                execute(&simple_db, &context, &format!(
+                    "INSERT INTO personas (id, dinero, activo) VALUES ({}, {}.5, FALSE);", id, id
+                ));
+            }
+        }
+
+        let batches: Vec<_> = execute(&simple_db, &context, "SELECT * FROM personas;").data()
+            .to_arrow_batches(128).unwrap()
+            .map(|batch| batch.unwrap())
+            .collect();
+
+        assert_eq!(batches.iter().map(|batch| batch.num_rows()).collect::<Vec<usize>>(), vec![128, 128, 44]);
+        let schema = batches[0].schema();
+        let id_field = schema.field_with_name("id").unwrap();
+        assert_eq!(id_field.data_type(), &DataType::Int64);
+        assert!(!id_field.is_nullable());
+        assert_eq!(schema.field_with_name("nombre").unwrap().data_type(), &DataType::Utf8);
+        assert_eq!(schema.field_with_name("dinero").unwrap().data_type(), &DataType::Float64);
+        assert_eq!(schema.field_with_name("activo").unwrap().data_type(), &DataType::Boolean);
+
+        let mut n_rows = 0;
+        for batch in &batches {
+            let id = batch.column_by_name("id").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+            let nombre = batch.column_by_name("nombre").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+            let dinero = batch.column_by_name("dinero").unwrap().as_any().downcast_ref::<Float64Array>().unwrap();
+            let activo = batch.column_by_name("activo").unwrap().as_any().downcast_ref::<BooleanArray>().unwrap();
+
+            for i in 0..batch.num_rows() {
+                let current_id = id.value(i);
+                assert_eq!(dinero.value(i), current_id as f64 + 0.5);
+                assert_eq!(activo.value(i), current_id % 2 == 0);
+                if current_id % 2 == 0 {
+                    assert_eq!(nombre.value(i), format!("persona {}", current_id));
+                } else {
+                    assert!(nombre.is_null(i));
                 }
-                columns_desc
-            },
-            Selection::All => {
-                table.get_columns().values()
-                    .map(|it| it.clone())
-                    .collect()
+                n_rows += 1;
             }
         }
+        assert_eq!(n_rows, 300);
+    }
+
+    #[test]
+    fn alter_table_add_column() {
+        let (simple_db, context) = create_db("alter_table_add_column");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (1, "Jaime"), (2, "Pedro");"#);
+        execute(&simple_db, &context, "ALTER TABLE personas ADD COLUMN dinero I64 DEFAULT 100;");
+        execute(&simple_db, &context, "ALTER TABLE personas ADD COLUMN activo BOOLEAN;");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre, dinero, activo) VALUES (3, "Juan", 5, TRUE);"#);
+
+        assert_eq!(select_personas(&simple_db, &context), vec![
+            vec![Value::I64(3), Value::String(String::from("Juan")), Value::I64(5), Value::Boolean(true)],
+            vec![Value::I64(2), Value::String(String::from("Pedro")), Value::I64(100), Value::Null],
+            vec![Value::I64(1), Value::String(String::from("Jaime")), Value::I64(100), Value::Null],
+        ]);
+
+        //The default value can be used in the where clause
+        let mut query_iterator = execute(&simple_db, &context, "SELECT id, dinero FROM personas WHERE dinero = 100;").data();
+        let mut ids = Vec::new();
+        while let Some(row) = query_iterator.next().unwrap() {
+            ids.push(row.get_column_value("id").unwrap().get_i64().unwrap());
+        }
+        ids.sort();
+        assert_eq!(ids, vec![1, 2]);
+
+        //Default value is persisted in the table descriptor
+        execute(&simple_db, &context, "COMMIT;");
+        drop(simple_db);
+        let simple_db = open_db_with_options("alter_table_add_column", |_| {});
+        let mut context = Context::create_with_database("test");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        assert_eq!(select_personas(&simple_db, &context)[2],
+                   vec![Value::I64(1), Value::String(String::from("Jaime")), Value::I64(100), Value::Null]);
+    }
+
+    #[test]
+    fn drop_table() {
+        let (simple_db, context) = create_personas_db("drop_table");
+        execute(&simple_db, &context, "CREATE INDEX ON personas (dinero);");
+        execute(&simple_db, &context, "COMMIT;");
+        execute(&simple_db, &Context::create_with_database("test"), "DROP TABLE personas;");
+
+        let error = simple_db.execute(&Context::create_with_database("test"), simple_db.parse("DESCRIBE personas;").unwrap());
+        assert!(matches!(error, Err(SimpleDbError::TableNotFound(_))));
+        drop(simple_db);
+
+        let simple_db = open_db_with_options("drop_table", |_| {});
+        let database = simple_db.get_databases().get_database_or_err("test").unwrap();
+        assert!(database.get_tables().is_empty());
+
+        //Keyspace IDs of the dropped table might be reused
+        let mut context = Context::create_with_database("test");
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR);");
+        assert_eq!(select_ids(&simple_db, &context), Vec::<i64>::new());
+        let describe = match execute(&simple_db, &context, "DESCRIBE personas;") {
+            StatementResult::Describe(columns) => columns,
+            _ => panic!("Expected StatementResult::Describe"),
+        };
+        assert_eq!(describe.len(), 2);
+    }
+
+    #[test]
+    fn approx_count_distinct() {
+        //Aggregations are scanned in parallel, so the estimations of each thread are merged
+        let (simple_db, context) = create_db_with_options("approx_count_distinct", |options| {
+            options.query_scan_parallelism(4);
+        });
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64, edad I64);");
+        for chunk in 0..50 {
+            let values: Vec<String> = (chunk * 100..(chunk + 1) * 100)
+                .map(|id| format!("({}, {}, {})", id, id % 3000, id % 2))
+                .collect();
+            execute(&simple_db, &context, &format!("INSERT INTO personas (id, dinero, edad) VALUES {};", values.join(", ")));
+        }
+        execute(&simple_db, &context, "INSERT INTO personas (id, edad) VALUES (5000, 0);");
+
+        //Exact number of distinct values, one group per value
+        let mut query_iterator = execute(&simple_db, &context, "SELECT dinero, COUNT(*) FROM personas WHERE dinero >= 0 GROUP BY dinero;").data();
+        let mut n_distinct_dinero = 0;
+        while query_iterator.next().unwrap().is_some() {
+            n_distinct_dinero += 1;
+        }
+        assert_eq!(n_distinct_dinero, 3000);
+
+        let mut query_iterator = execute(&simple_db, &context, "SELECT APPROX_COUNT_DISTINCT(dinero) FROM personas;").data();
+        let approx_distinct_dinero = query_iterator.next().unwrap().unwrap()
+            .get_column_value("APPROX_COUNT_DISTINCT(dinero)").unwrap()
+            .get_i64().unwrap();
+        assert!((approx_distinct_dinero - n_distinct_dinero).abs() <= n_distinct_dinero / 20, "Estimate: {}", approx_distinct_dinero);
+
+        //Nulls are not counted
+        let mut query_iterator = execute(&simple_db, &context, "SELECT edad, APPROX_COUNT_DISTINCT(dinero) FROM personas GROUP BY edad;").data();
+        while let Some(row) = query_iterator.next().unwrap() {
+            let approx_distinct_dinero = row.get_column_value("APPROX_COUNT_DISTINCT(dinero)").unwrap().get_i64().unwrap();
+            assert!((approx_distinct_dinero - 1500).abs() <= 75, "Estimate: {}", approx_distinct_dinero);
+        }
+    }
+
+    #[test]
+    fn query_cache() {
+        let (simple_db, mut context) = create_db_with_options("query_cache", |options| {
+            options.query_cache_size_bytes(1024 * 1024);
+        });
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        execute(&simple_db, &context, "INSERT INTO personas (id, dinero) VALUES (1, 10), (2, 20);");
+        execute(&simple_db, &context, "COMMIT;");
+        let query_cache = &simple_db.get_databases().get_database_or_err("test").unwrap().query_cache;
+
+        let mut select_ids_in_new_transaction = || {
+            context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+            let ids = select_ids(&simple_db, &context);
+            execute(&simple_db, &context, "COMMIT;");
+            ids
+        };
+
+        assert_eq!(select_ids_in_new_transaction(), vec![1, 2]);
+        assert_eq!((query_cache.n_hits(), query_cache.n_misses()), (0, 1));
+        assert_eq!(select_ids_in_new_transaction(), vec![1, 2]);
+        assert_eq!((query_cache.n_hits(), query_cache.n_misses()), (1, 1));
+
+        let mut write_context = Context::create_with_database("test");
+        write_context.with_transaction(execute(&simple_db, &write_context, "START_TRANSACTION;").get_transaction());
+        execute(&simple_db, &write_context, "INSERT INTO personas (id, dinero) VALUES (3, 30);");
+
+        //Write not committed yet
+        assert_eq!(select_ids_in_new_transaction(), vec![1, 2]);
+        assert_eq!((query_cache.n_hits(), query_cache.n_misses()), (1, 2));
+
+        execute(&simple_db, &write_context, "COMMIT;");
+
+        assert_eq!(select_ids_in_new_transaction(), vec![1, 2, 3]);
+        assert_eq!((query_cache.n_hits(), query_cache.n_misses()), (1, 3));
+        assert_eq!(select_ids_in_new_transaction(), vec![1, 2, 3]);
+        assert_eq!((query_cache.n_hits(), query_cache.n_misses()), (2, 3));
+    }
+
+    #[test]
+    fn dump_table_and_reimport() {
+        let (simple_db, context) = create_db("dump_table_and_reimport_source");
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY AUTOINCREMENT, nombre VARCHAR NOT NULL UNIQUE, dinero F64 DEFAULT 1.5, activo BOOLEAN);");
+        execute(&simple_db, &context, "CREATE INDEX ON personas (activo, dinero);");
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre, dinero, activo) VALUES (1, "Jaime", 10.5, TRUE);"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre, dinero, activo) VALUES (2, "Say \"hi\" \\o/", -3.0, FALSE);"#);
+        execute(&simple_db, &context, r#"INSERT INTO personas (id, nombre) VALUES (-3, "Sin dinero");"#);
+
+        let dump: Vec<String> = simple_db.dump_table_as_sql(&context, "personas").unwrap()
+            .collect::<Result<Vec<String>, SimpleDbError>>()
+            .unwrap();
+        assert_eq!(dump.len(), 5);
+        assert_eq!(dump[0], "CREATE TABLE personas (id I64 PRIMARY KEY AUTOINCREMENT, nombre VARCHAR NOT NULL UNIQUE, dinero F64 DEFAULT 1.5, activo BOOLEAN);");
+        assert_eq!(dump[1], "CREATE INDEX ON personas (activo, dinero);");
+
+        let (imported_simple_db, imported_context) = create_db("dump_table_and_reimport_destination");
+        for statement in &dump {
+            execute(&imported_simple_db, &imported_context, statement);
+        }
+
+        let expected = select_personas(&simple_db, &context);
+        assert_eq!(expected.len(), 3);
+        assert_eq!(expected[0][1], Value::String(String::from(r#"Say "hi" \o/"#)));
+        assert_eq!(expected[2][2], Value::F64(1.5));
+        assert_eq!(expected, select_personas(&imported_simple_db, &imported_context));
+        let imported_dump: Vec<String> = imported_simple_db.dump_table_as_sql(&imported_context, "personas").unwrap()
+            .collect::<Result<Vec<String>, SimpleDbError>>()
+            .unwrap();
+        assert_eq!(imported_dump, dump);
+
+        //Constraints are kept by the imported table
+        execute(&imported_simple_db, &imported_context, r#"INSERT INTO personas (nombre) VALUES ("Sin id");"#);
+        assert_eq!(select_personas(&imported_simple_db, &imported_context)[0][0], Value::I64(3));
+        let missing_nombre = imported_simple_db.parse("INSERT INTO personas (id, activo) VALUES (4, TRUE);").unwrap();
+        assert!(matches!(imported_simple_db.execute(&imported_context, missing_nombre), Err(SimpleDbError::NotNullViolation(_))));
+        let duplicated_nombre = imported_simple_db.parse(r#"INSERT INTO personas (id, nombre) VALUES (4, "Jaime");"#).unwrap();
+        assert!(matches!(imported_simple_db.execute(&imported_context, duplicated_nombre), Err(SimpleDbError::UniqueViolation(_, _))));
+    }
+
+    #[test]
+    fn execute_script() {
+        let (simple_db, context) = create_db("execute_script");
+        let mut script = String::from("CREATE TABLE personas (id I64 PRIMARY KEY, nombre VARCHAR, dinero F64, activo BOOLEAN);\n");
+        for id in 0..300 {
+            script.push_str(&format!(
+                "INSERT INTO personas (id, nombre, dinero, activo) VALUES ({}, \"Persona \\\"{}\\\"\", {}.5, {});\n",
+                id, id, id, if id % 2 == 0 { "TRUE" } else { "FALSE" }
+            ));
+        }
+
+        let script_context = Context::create_with_database("test");
+        assert_eq!(simple_db.execute_script(&script_context, &script).unwrap(), 301);
+
+        let mut context = context.clone();
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        let rows = select_personas(&simple_db, &context);
+        assert_eq!(rows.len(), 300);
+        assert_eq!(rows[0], vec![Value::I64(299), Value::String(String::from("Persona \"299\"")), Value::F64(299.5), Value::Boolean(false)]);
+        assert_eq!(rows[299], vec![Value::I64(0), Value::String(String::from("Persona \"0\"")), Value::F64(0.5), Value::Boolean(true)]);
+    }
+
+    #[test]
+    fn execute_script_failure() {
+        let (simple_db, context) = create_personas_db("execute_script_failure");
+        execute(&simple_db, &context, "COMMIT;");
+        let script = "INSERT INTO personas (id, dinero) VALUES (10, 100);\n\
+            INSERT INTO personas (id, dinero) VALUES (11, 110);\n\
+            INSERT INTO desconocida (id, dinero) VALUES (12, 120);\n";
+
+        let script_context = Context::create_with_database("test");
+        match simple_db.execute_script(&script_context, script) {
+            Err(SimpleDbError::ScriptStatementFailed(statement_number, line, error)) => {
+                assert_eq!(statement_number, 3);
+                assert_eq!(line, 3);
+                assert!(matches!(*error, SimpleDbError::TableNotFound(_)));
+            },
+            _ => panic!("Expected ScriptStatementFailed"),
+        };
+
+        //Inserts done by the script were rolled back
+        let mut context = context.clone();
+        context.with_transaction(execute(&simple_db, &context, "START_TRANSACTION;").get_transaction());
+        assert_eq!(select_ids(&simple_db, &context), vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    fn select_personas(simple_db: &SimpleDb, context: &Context) -> Vec<Vec<Value>> {
+        let mut query_iterator = execute(simple_db, context, "SELECT * FROM personas;").data();
+        let mut rows = Vec::new();
+        while let Some(row) = query_iterator.next().unwrap() {
+            rows.push(["id", "nombre", "dinero", "activo"].iter()
+                .map(|column| row.get_column_value(column).unwrap())
+                .collect::<Vec<Value>>());
+        }
+        rows.sort_by_key(|row| -row[0].get_i64().unwrap());
+        rows
+    }
+
+    fn explain_statement(simple_db: &SimpleDb, context: &Context, query: &str) -> Explain {
+        match execute(simple_db, context, query) {
+            StatementResult::Explain(explain) => explain,
+            _ => panic!("Expected StatementResult::Explain"),
+        }
+    }
+
+    fn assert_rows_affected(result: StatementResult, expected: usize) {
+        match result {
+            StatementResult::Ok(n_rows) => assert_eq!(n_rows, expected),
+            _ => panic!("Expected StatementResult::Ok"),
+        }
+    }
+
+    fn select_ids(simple_db: &SimpleDb, context: &Context) -> Vec<i64> {
+        let mut query_iterator = execute(simple_db, context, "SELECT id FROM personas;").data();
+        let mut ids = Vec::new();
+        while let Some(row) = query_iterator.next().unwrap() {
+            ids.push(row.get_column_value("id").unwrap().get_i64().unwrap());
+        }
+        ids.sort();
+        ids
+    }
+
+    fn select_ids_where(simple_db: &SimpleDb, context: &Context, where_expr: &str) -> Vec<i64> {
+        let mut query_iterator = execute(simple_db, context, &format!("SELECT id FROM personas WHERE {};", where_expr)).data();
+        let mut ids = Vec::new();
+        while let Some(row) = query_iterator.next().unwrap() {
+            ids.push(row.get_column_value("id").unwrap().get_i64().unwrap());
+        }
+        ids.sort();
+        ids
+    }
+
+    //Creates table personas (id, dinero) with 10 rows: id from 0 to 9 & dinero = id * 10
+    fn create_personas_db(test_name: &str) -> (SimpleDb, Context) {
+        let (simple_db, context) = create_db(test_name);
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 0..10 {
+            execute(&simple_db, &context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id * 10));
+        }
+
+        (simple_db, context)
+    }
+
+    //Creates database test and starts a transaction
+    fn create_db(test_name: &str) -> (SimpleDb, Context) {
+        create_db_with_options(test_name, |_| {})
+    }
+
+    fn create_db_with_options(test_name: &str, options_fn: impl Fn(&mut SimpleDbOptionsBuilder)) -> (SimpleDb, Context) {
+        let path = std::env::temp_dir().join(format!("simpledb_executor_{}", test_name));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        let simple_db = open_db_with_options(test_name, options_fn);
+
+        let mut context = Context::empty();
+        execute(&simple_db, &context, "CREATE DATABASE test;");
+        context.with_database("test");
+        let transaction = execute(&simple_db, &context, "START_TRANSACTION;").get_transaction();
+        context.with_transaction(transaction);
+
+        (simple_db, context)
     }
-}
\ No newline at end of file
+
+    //Opens the SimpleDb previously created by create_db with the same test name
+    fn open_db_with_options(test_name: &str, options_fn: impl Fn(&mut SimpleDbOptionsBuilder)) -> SimpleDb {
+        let path = std::env::temp_dir().join(format!("simpledb_executor_{}", test_name));
+        let mut options_builder = shared::start_simpledb_options_builder();
+        options_builder.base_path(path.to_str().unwrap());
+        options_fn(&mut options_builder);
+        let options = options_builder.build_arc();
+        shared::logger::Logger::init(options.clone());
+        crate::simple_db::create(options).unwrap()
+    }
+
+    fn execute(simple_db: &SimpleDb, context: &Context, query: &str) -> StatementResult {
+        let statement = simple_db.parse(query).unwrap();
+        match simple_db.execute(context, statement) {
+            Ok(result) => result,
+            Err(error) => panic!("Cannot execute {}: {:?}", query, error),
+        }
+    }
+}
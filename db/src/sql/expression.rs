@@ -1,21 +1,39 @@
-use crate::value::Value;
+use crate::sql::dump::sql_literal;
+use crate::sql::statement::SelectStatement;
+use crate::value::{Type, Value};
 use bytes::Bytes;
 use shared::SimpleDbError;
 use std::cmp::PartialEq;
+use std::fmt;
+use std::fmt::Formatter;
 use SimpleDbError::MalformedQuery;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expression {
     Binary(BinaryOperator, Box<Expression>, Box<Expression>),
     Unary(UnaryOperator, Box<Expression>),
+    In(Box<Expression>, Vec<Expression>),
+    //Value, low, high. Both ends are inclusive
+    Between(Box<Expression>, Box<Expression>, Box<Expression>),
+    IsNull(Box<Expression>),
+    IsNotNull(Box<Expression>),
     Identifier(String),
     Literal(Value),
+    //Scalar function call, the name is uppercase. See ScalarFunction
+    Function(String, Vec<Expression>),
+    //(SELECT ...) returning a single column. Replaced with its results before the statement is planned
+    Subquery(Box<SelectStatement>),
+    //EXISTS (SELECT ...). The subquery can reference columns of the outer table qualified with its name: <table>.<column>
+    Exists(Box<SelectStatement>),
+    //? of a prepared statement, replaced with the parameter at that position before the statement is executed
+    Placeholder(usize),
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum UnaryOperator {
     Plus,
     Minus,
+    Not,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -24,6 +42,12 @@ pub enum BinaryOperator {
     Subtract,
     Multiply,
     Divide,
+    Modulo,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
     And,
     Or,
     NotEqual,
@@ -32,6 +56,7 @@ pub enum BinaryOperator {
     GreaterEqual,
     Less,
     LessEqual,
+    Like,
 }
 
 impl Expression {
@@ -85,8 +110,20 @@ impl Expression {
                 left.is_constant_expression() && right.is_constant_expression()
             },
             Expression::Unary(_, expr) => expr.is_constant_expression(),
+            Expression::In(expr, list) => {
+                expr.is_constant_expression() && list.iter().all(|item| item.is_constant_expression())
+            },
+            Expression::Between(expr, low, high) => {
+                expr.is_constant_expression() && low.is_constant_expression() && high.is_constant_expression()
+            },
+            Expression::IsNull(expr) |
+            Expression::IsNotNull(expr) => expr.is_constant_expression(),
+            Expression::Function(_, args) => args.iter().all(|arg| arg.is_constant_expression()),
             Expression::Literal(_) => true,
-            Expression::Identifier(_) => false,
+            Expression::Identifier(_) |
+            Expression::Subquery(_) |
+            Expression::Exists(_) |
+            Expression::Placeholder(_) => false,
         }
     }
 
@@ -94,6 +131,111 @@ impl Expression {
         matches!(self, Expression::Literal(_))
     }
 
+    //Adds the column names referenced by the expression that are not already in identifiers
+    pub fn collect_identifiers(&self, identifiers: &mut Vec<String>) {
+        match self {
+            Expression::Binary(_, left, right) => {
+                left.collect_identifiers(identifiers);
+                right.collect_identifiers(identifiers);
+            },
+            Expression::Unary(_, expr) |
+            Expression::IsNull(expr) |
+            Expression::IsNotNull(expr) => expr.collect_identifiers(identifiers),
+            Expression::In(expr, list) => {
+                expr.collect_identifiers(identifiers);
+                list.iter().for_each(|item| item.collect_identifiers(identifiers));
+            },
+            Expression::Between(expr, low, high) => {
+                expr.collect_identifiers(identifiers);
+                low.collect_identifiers(identifiers);
+                high.collect_identifiers(identifiers);
+            },
+            Expression::Function(_, args) => args.iter().for_each(|arg| arg.collect_identifiers(identifiers)),
+            Expression::Identifier(identifier) => {
+                if !identifiers.contains(identifier) {
+                    identifiers.push(identifier.clone());
+                }
+            },
+            //Identifiers of the subquery belong to its own table
+            Expression::Literal(_) |
+            Expression::Subquery(_) |
+            Expression::Exists(_) |
+            Expression::Placeholder(_) => {},
+        }
+    }
+
+    //After subqueries have been materialized, only the correlated EXISTS subqueries remain
+    pub fn contains_exists(&self) -> bool {
+        match self {
+            Expression::Binary(_, left, right) => left.contains_exists() || right.contains_exists(),
+            Expression::Unary(_, expr) |
+            Expression::IsNull(expr) |
+            Expression::IsNotNull(expr) => expr.contains_exists(),
+            Expression::In(expr, list) => expr.contains_exists() || list.iter().any(|item| item.contains_exists()),
+            Expression::Between(expr, low, high) => {
+                expr.contains_exists() || low.contains_exists() || high.contains_exists()
+            },
+            Expression::Function(_, args) => args.iter().any(|arg| arg.contains_exists()),
+            Expression::Exists(_) => true,
+            Expression::Identifier(_) |
+            Expression::Literal(_) |
+            Expression::Subquery(_) |
+            Expression::Placeholder(_) => false,
+        }
+    }
+
+    //Replaces every leaf of the expression (identifiers, literals, subqueries & placeholders) with the expression returned by mapper.
+    //Subqueries are not visited, their identifiers belong to the subquery table
+    pub fn map_leaves<F>(self, mapper: &mut F) -> Result<Expression, SimpleDbError>
+    where
+        F: FnMut(Expression) -> Result<Expression, SimpleDbError>
+    {
+        match self {
+            Expression::Binary(operator, left, right) => Ok(Expression::Binary(
+                operator,
+                Box::new(left.map_leaves(mapper)?),
+                Box::new(right.map_leaves(mapper)?),
+            )),
+            Expression::Unary(operator, expr) => Ok(Expression::Unary(operator, Box::new(expr.map_leaves(mapper)?))),
+            Expression::In(expr, list) => Ok(Expression::In(
+                Box::new(expr.map_leaves(mapper)?),
+                list.into_iter()
+                    .map(|item| item.map_leaves(mapper))
+                    .collect::<Result<Vec<Expression>, SimpleDbError>>()?,
+            )),
+            Expression::Between(expr, low, high) => Ok(Expression::Between(
+                Box::new(expr.map_leaves(mapper)?),
+                Box::new(low.map_leaves(mapper)?),
+                Box::new(high.map_leaves(mapper)?),
+            )),
+            Expression::IsNull(expr) => Ok(Expression::IsNull(Box::new(expr.map_leaves(mapper)?))),
+            Expression::IsNotNull(expr) => Ok(Expression::IsNotNull(Box::new(expr.map_leaves(mapper)?))),
+            Expression::Function(name, args) => Ok(Expression::Function(
+                name,
+                args.into_iter()
+                    .map(|arg| arg.map_leaves(mapper))
+                    .collect::<Result<Vec<Expression>, SimpleDbError>>()?,
+            )),
+            Expression::Identifier(_) |
+            Expression::Literal(_) |
+            Expression::Subquery(_) |
+            Expression::Exists(_) |
+            Expression::Placeholder(_) => mapper(self),
+        }
+    }
+
+    //<table_name>.<column> -> <column>
+    pub fn unqualify_identifiers(self, table_name: &str) -> Expression {
+        let prefix = format!("{}.", table_name);
+        self.map_leaves(&mut |leaf| match leaf {
+            Expression::Identifier(identifier) => match identifier.strip_prefix(&prefix) {
+                Some(column_name) => Ok(Expression::Identifier(column_name.to_string())),
+                None => Ok(Expression::Identifier(identifier)),
+            },
+            leaf => Ok(leaf),
+        }).unwrap()
+    }
+
     pub fn add(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
         self.arithmetic_op(other, |a, b| a.add(b))
     }
@@ -110,15 +252,49 @@ impl Expression {
         self.arithmetic_op(other, |a, b| a.divide(b))
     }
 
+    pub fn modulo(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        self.arithmetic_op(other, |a, b| a.modulo(b))
+    }
+
+    pub fn bitwise_and(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        self.arithmetic_op(other, |a, b| a.bitwise_and(b))
+    }
+
+    pub fn bitwise_or(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        self.arithmetic_op(other, |a, b| a.bitwise_or(b))
+    }
+
+    pub fn bitwise_xor(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        self.arithmetic_op(other, |a, b| a.bitwise_xor(b))
+    }
+
+    pub fn shift_left(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        self.arithmetic_op(other, |a, b| a.shift_left(b))
+    }
+
+    pub fn shift_right(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        self.arithmetic_op(other, |a, b| a.shift_right(b))
+    }
+
     pub fn or(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        //null OR true -> true, null OR false -> null
+        if self.is_null() || other.is_null() {
+            let some_true = [self, other].iter()
+                .any(|expression| matches!(expression, Expression::Literal(Value::Boolean(true))));
+            return Ok(Expression::Literal(if some_true { Value::Boolean(true) } else { Value::Null }));
+        }
+
         let value_self = self.get_value()?;
         let value_other = other.get_value()?;
         Ok(Expression::Literal(value_self.or(&value_other)?))
     }
 
     pub fn and(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
+        //null AND false -> false, null AND true -> null
         if self.is_null() || other.is_null() {
-            return Ok(Expression::Literal(Value::Null));
+            let some_false = [self, other].iter()
+                .any(|expression| matches!(expression, Expression::Literal(Value::Boolean(false))));
+            return Ok(Expression::Literal(if some_false { Value::Boolean(false) } else { Value::Null }));
         }
 
         let value_self = self.get_value()?;
@@ -127,57 +303,27 @@ impl Expression {
     }
 
     pub fn greater(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
-        self.comparation_op(
-            other,
-            Expression::Literal(Value::Null),
-            Expression::Literal(Value::Null),
-            |a, b| a.greater(b)
-        )
+        self.comparation_op(other, |a, b| a.greater(b))
     }
 
     pub fn greater_equal(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
-        self.comparation_op(
-            other,
-            Expression::Literal(Value::Null),
-            Expression::Literal(Value::Null),
-            |a, b| a.greater_equal(b)
-        )
+        self.comparation_op(other, |a, b| a.greater_equal(b))
     }
 
     pub fn less(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
-        self.comparation_op(
-            other,
-            Expression::Literal(Value::Null),
-            Expression::Literal(Value::Null),
-            |a, b| a.less(b)
-        )
+        self.comparation_op(other, |a, b| a.less(b))
     }
 
     pub fn less_equal(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
-        self.comparation_op(
-            other,
-            Expression::Literal(Value::Null),
-            Expression::Literal(Value::Null),
-            |a, b| a.less_equal(b),
-        )
+        self.comparation_op(other, |a, b| a.less_equal(b))
     }
 
     pub fn equal(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
-        self.comparation_op(
-            other,
-            Expression::Literal(Value::Boolean(true)),
-            Expression::Literal(Value::Boolean(false)),
-            |a, b| a.equal(b),
-        )
+        self.comparation_op(other, |a, b| a.equal(b))
     }
 
     pub fn not_equal(&self, other: &Expression) -> Result<Expression, SimpleDbError> {
-        self.comparation_op(
-            other,
-            Expression::Literal(Value::Boolean(false)),
-            Expression::Literal(Value::Boolean(true)),
-            |a, b| a.not_equal(b),
-        )
+        self.comparation_op(other, |a, b| a.not_equal(b))
     }
 
     pub fn get_value(&self) -> Result<Value, SimpleDbError> {
@@ -187,21 +333,17 @@ impl Expression {
         }
     }
 
+    //Comparing with null produces null, even if both are null
     fn comparation_op<Op>(
         &self,
         other: &Expression,
-        null_null_return_value: Expression,
-        null_some_return_value: Expression,
         op: Op
     ) -> Result<Expression, SimpleDbError>
     where
         Op: Fn(&Value, &Value) -> Result<Value, SimpleDbError>
     {
-        if self.is_null() && other.is_null() {
-            return Ok(null_null_return_value);
-        }
         if self.is_null() || other.is_null() {
-            return Ok(null_some_return_value);
+            return Ok(Expression::Literal(Value::Null));
         }
 
         match &self {
@@ -229,7 +371,138 @@ impl Expression {
     }
 }
 
+//Nested binary expressions are wrapped in parentheses, so the precedence doesn't need to be known to read them
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Binary(operator, left, right) => {
+                write_operand(f, left)?;
+                write!(f, " {} ", operator.symbol())?;
+                write_operand(f, right)
+            },
+            Expression::Unary(UnaryOperator::Plus, expr) => write!(f, "+{}", expr),
+            Expression::Unary(UnaryOperator::Minus, expr) => write!(f, "-{}", expr),
+            Expression::Unary(UnaryOperator::Not, expr) => write!(f, "NOT ({})", expr),
+            Expression::In(expr, list) => {
+                write_operand(f, expr)?;
+                write!(f, " IN (")?;
+                for (index, item) in list.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            },
+            Expression::Between(expr, low, high) => {
+                write_operand(f, expr)?;
+                write!(f, " BETWEEN ")?;
+                write_operand(f, low)?;
+                write!(f, " AND ")?;
+                write_operand(f, high)
+            },
+            Expression::IsNull(expr) => {
+                write_operand(f, expr)?;
+                write!(f, " IS NULL")
+            },
+            Expression::IsNotNull(expr) => {
+                write_operand(f, expr)?;
+                write!(f, " IS NOT NULL")
+            },
+            Expression::Function(name, args) => {
+                write!(f, "{}(", name)?;
+                for (index, arg) in args.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            },
+            Expression::Identifier(identifier) => write!(f, "{}", identifier),
+            Expression::Literal(value) => match sql_literal(value) {
+                Ok(literal) => write!(f, "{}", literal),
+                //Values without SQL syntax
+                Err(_) => write!(f, "{:?}", value),
+            },
+            Expression::Subquery(subquery) => write!(f, "(SELECT ... FROM {})", subquery.table_name),
+            Expression::Exists(subquery) => write!(f, "EXISTS (SELECT ... FROM {})", subquery.table_name),
+            Expression::Placeholder(_) => write!(f, "?"),
+        }
+    }
+}
+
+fn write_operand(f: &mut Formatter<'_>, operand: &Expression) -> fmt::Result {
+    match operand {
+        Expression::Binary(_, _, _) |
+        Expression::Between(_, _, _) |
+        Expression::In(_, _) => write!(f, "({})", operand),
+        _ => write!(f, "{}", operand),
+    }
+}
+
 impl BinaryOperator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::BitwiseAnd => "&",
+            BinaryOperator::BitwiseOr => "|",
+            BinaryOperator::BitwiseXor => "^",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Equal => "=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Like => "LIKE",
+        }
+    }
+
+    //Returns None if the operator cannot be applied to the types
+    pub fn produced_type(&self, type_left: &Type, type_right: &Type) -> Option<Type> {
+        if self.is_logical() &&
+            matches!(type_left, Type::Boolean) &&
+            matches!(type_right, Type::Boolean) {
+            Some(Type::Boolean)
+        } else if self.is_arithmetic() &&
+            type_left.is_number() &&
+            type_right.is_number() {
+
+            if type_left.is_fp_number() || type_right.is_fp_number() {
+                Some(Type::F64)
+            } else if type_left.is_signed_integer_number() || type_right.is_signed_integer_number() {
+                Some(Type::I64)
+            } else {
+                Some(Type::U64)
+            }
+        } else if self.is_integer_arithmetic() &&
+            type_left.is_integer_number() &&
+            type_right.is_integer_number() {
+
+            if type_left.is_signed_integer_number() || type_right.is_signed_integer_number() {
+                Some(Type::I64)
+            } else {
+                Some(Type::U64)
+            }
+        } else if self.is_comparation() && type_left.is_comparable(type_right) {
+            Some(Type::Boolean)
+        } else if matches!(self, BinaryOperator::Like) &&
+            matches!(type_left, Type::String) &&
+            matches!(type_right, Type::String) {
+            Some(Type::Boolean)
+        } else {
+            None
+        }
+    }
+
     //Takes booleans, Produces boolean
     pub fn is_logical(&self) -> bool {
         match self {
@@ -246,6 +519,13 @@ impl BinaryOperator {
             BinaryOperator::Subtract |
             BinaryOperator::Multiply |
             BinaryOperator::Divide => true,
+            BinaryOperator::Modulo |
+            BinaryOperator::BitwiseAnd |
+            BinaryOperator::BitwiseOr |
+            BinaryOperator::BitwiseXor |
+            BinaryOperator::ShiftLeft |
+            BinaryOperator::ShiftRight |
+            BinaryOperator::Like |
             BinaryOperator::And |
             BinaryOperator::Or |
             BinaryOperator::NotEqual |
@@ -257,13 +537,26 @@ impl BinaryOperator {
         }
     }
 
+    //Takes integers, Produces integer
+    pub fn is_integer_arithmetic(&self) -> bool {
+        matches!(self, BinaryOperator::Modulo | BinaryOperator::BitwiseAnd | BinaryOperator::BitwiseOr |
+            BinaryOperator::BitwiseXor | BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight)
+    }
+
     //Takes comparable args, Produces boolean
     pub fn is_comparation(&self) -> bool {
         match self {
             BinaryOperator::Add |
             BinaryOperator::Subtract |
             BinaryOperator::Multiply |
-            BinaryOperator::Divide => false,
+            BinaryOperator::Divide |
+            BinaryOperator::Modulo |
+            BinaryOperator::BitwiseAnd |
+            BinaryOperator::BitwiseOr |
+            BinaryOperator::BitwiseXor |
+            BinaryOperator::ShiftLeft |
+            BinaryOperator::ShiftRight |
+            BinaryOperator::Like => false,
             BinaryOperator::And |
             BinaryOperator::Or |
             BinaryOperator::NotEqual |
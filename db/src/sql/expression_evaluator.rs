@@ -1,17 +1,19 @@
 use crate::sql::expression::Expression::Binary;
 use crate::sql::expression::{BinaryOperator, Expression, UnaryOperator};
+use crate::sql::scalar_function::ScalarFunction;
 use crate::value::Value;
 use crate::Row;
-use shared::SimpleDbError;
+use shared::{Collation, SimpleDbError};
 use SimpleDbError::MalformedQuery;
 
 //expression is expected to have been passed to evaluate_constant_expressions() before calling this function
 //If the row returns null, we will return false
 pub fn evaluate_where_expression(
     row: &Row,
-    expression: &Expression
+    expression: &Expression,
+    collation: Collation,
 ) -> Result<bool, SimpleDbError> {
-    match evaluate_expression(row, expression)? {
+    match evaluate_expression(row, expression, collation)? {
         Expression::Literal(value_produced) => {
             match value_produced {
                 Value::Boolean(boolean_produced) => Ok(boolean_produced),
@@ -26,40 +28,98 @@ pub fn evaluate_where_expression(
 //If the row returns a null value, we will propagate the null value, the function will return a null expression
 pub fn evaluate_expression(
     row: &Row,
-    expression: &Expression
+    expression: &Expression,
+    collation: Collation,
 ) -> Result<Expression, SimpleDbError> {
     match expression {
         Expression::Binary(operation, left, right) => {
-            let left = evaluate_expression(row, &*left.clone())?;
-            let right = evaluate_expression(row, &*right.clone())?;
-            evaluate_constant_binary_op(left, right, operation.clone())
+            let left = evaluate_expression(row, &*left.clone(), collation)?;
+            let right = evaluate_expression(row, &*right.clone(), collation)?;
+            evaluate_constant_binary_op(left, right, operation.clone(), collation)
         },
         Expression::Unary(operation, unary_expr) => {
-            let unary_expr = evaluate_expression(row, &*unary_expr.clone())?;
+            let unary_expr = evaluate_expression(row, &*unary_expr.clone(), collation)?;
             evaluate_constant_unary_op(unary_expr, operation.clone())
         },
+        Expression::In(expression, list) => {
+            let expression = evaluate_expression(row, expression, collation)?;
+            let list = list.iter()
+                .map(|item| evaluate_expression(row, item, collation))
+                .collect::<Result<Vec<Expression>, SimpleDbError>>()?;
+            evaluate_constant_in(expression, list, collation)
+        },
+        Expression::Between(expression, low, high) => {
+            let expression = evaluate_expression(row, expression, collation)?;
+            let low = evaluate_expression(row, low, collation)?;
+            let high = evaluate_expression(row, high, collation)?;
+            evaluate_constant_between(expression, low, high, collation)
+        },
+        Expression::IsNull(expression) => {
+            evaluate_constant_is_null(evaluate_expression(row, expression, collation)?, false)
+        },
+        Expression::IsNotNull(expression) => {
+            evaluate_constant_is_null(evaluate_expression(row, expression, collation)?, true)
+        },
+        Expression::Function(name, args) => {
+            let args = args.iter()
+                .map(|arg| evaluate_expression(row, arg, collation))
+                .collect::<Result<Vec<Expression>, SimpleDbError>>()?;
+            evaluate_constant_function(name.clone(), args)
+        },
         Expression::Identifier(column_name) => {
             let value = row.get_column_value(column_name)?;
             Ok(Expression::Literal(value))
         },
         Expression::Literal(value) => Ok(Expression::Literal(value.clone())),
+        Expression::Subquery(_) |
+        Expression::Exists(_) => Err(MalformedQuery(String::from("Subqueries should be materialized before being evaluated"))),
+        Expression::Placeholder(_) => Err(MalformedQuery(String::from("Placeholders should be bound before being evaluated"))),
     }
 }
 
 pub fn evaluate_constant_expressions(
-    expression: Expression
+    expression: Expression,
+    collation: Collation,
 ) -> Result<Expression, SimpleDbError> {
     match expression {
         Expression::Binary(operator, left, right) => {
-            let left = evaluate_constant_expressions(*left)?;
-            let right = evaluate_constant_expressions(*right)?;
-            evaluate_constant_binary_op(left, right, operator)
+            let left = evaluate_constant_expressions(*left, collation)?;
+            let right = evaluate_constant_expressions(*right, collation)?;
+            evaluate_constant_binary_op(left, right, operator, collation)
         },
         Expression::Unary(operator, expression) => {
-            let expression = evaluate_constant_expressions(*expression)?;
+            let expression = evaluate_constant_expressions(*expression, collation)?;
             evaluate_constant_unary_op(expression, operator)
         },
-        Expression::Identifier(_) => Ok(expression),
+        Expression::In(expression, list) => {
+            let expression = evaluate_constant_expressions(*expression, collation)?;
+            let list = list.into_iter()
+                .map(|item| evaluate_constant_expressions(item, collation))
+                .collect::<Result<Vec<Expression>, SimpleDbError>>()?;
+            evaluate_constant_in(expression, list, collation)
+        },
+        Expression::Between(expression, low, high) => {
+            let expression = evaluate_constant_expressions(*expression, collation)?;
+            let low = evaluate_constant_expressions(*low, collation)?;
+            let high = evaluate_constant_expressions(*high, collation)?;
+            evaluate_constant_between(expression, low, high, collation)
+        },
+        Expression::IsNull(expression) => {
+            evaluate_constant_is_null(evaluate_constant_expressions(*expression, collation)?, false)
+        },
+        Expression::IsNotNull(expression) => {
+            evaluate_constant_is_null(evaluate_constant_expressions(*expression, collation)?, true)
+        },
+        Expression::Function(name, args) => {
+            let args = args.into_iter()
+                .map(|arg| evaluate_constant_expressions(arg, collation))
+                .collect::<Result<Vec<Expression>, SimpleDbError>>()?;
+            evaluate_constant_function(name, args)
+        },
+        Expression::Identifier(_) |
+        Expression::Subquery(_) |
+        Expression::Exists(_) |
+        Expression::Placeholder(_) => Ok(expression),
         Expression::Literal(value) => Ok(Expression::Literal(value)),
     }
 }
@@ -69,7 +129,7 @@ fn evaluate_constant_unary_op(
     operator: UnaryOperator,
 ) -> Result<Expression, SimpleDbError> {
     if !expression.is_constant() {
-        return Ok(expression);
+        return Ok(Expression::Unary(operator, Box::new(expression)));
     }
 
     match operator {
@@ -85,6 +145,12 @@ fn evaluate_constant_unary_op(
             } else {
                 Err(MalformedQuery(String::from("Cannot apply unary operator")))
             }
+        },
+        //NOT null -> null
+        UnaryOperator::Not => match expression.get_value()? {
+            Value::Boolean(value) => Ok(Expression::Literal(Value::Boolean(!value))),
+            Value::Null => Ok(Expression::Literal(Value::Null)),
+            _ => Err(MalformedQuery(String::from("NOT should be applied to a boolean"))),
         }
     }
 }
@@ -93,16 +159,28 @@ fn evaluate_constant_binary_op(
     left: Expression,
     right: Expression,
     operator: BinaryOperator,
+    collation: Collation,
 ) -> Result<Expression, SimpleDbError> {
     if !left.is_constant_expression() || !right.is_constant_expression() {
         return Ok(Binary(operator, Box::new(left), Box::new(right)));
     }
+    let (left, right) = if operator.is_comparation() || matches!(operator, BinaryOperator::Like) {
+        (collate(left, collation), collate(right, collation))
+    } else {
+        (left, right)
+    };
 
     match operator {
         BinaryOperator::Add => left.add(&right),
         BinaryOperator::Subtract => left.substract(&right),
         BinaryOperator::Multiply => left.multiply(&right),
         BinaryOperator::Divide => left.divide(&right),
+        BinaryOperator::Modulo => left.modulo(&right),
+        BinaryOperator::BitwiseAnd => left.bitwise_and(&right),
+        BinaryOperator::BitwiseOr => left.bitwise_or(&right),
+        BinaryOperator::BitwiseXor => left.bitwise_xor(&right),
+        BinaryOperator::ShiftLeft => left.shift_left(&right),
+        BinaryOperator::ShiftRight => left.shift_right(&right),
         BinaryOperator::And => left.and(&right),
         BinaryOperator::Or => left.or(&right),
         BinaryOperator::NotEqual => left.not_equal(&right),
@@ -111,7 +189,183 @@ fn evaluate_constant_binary_op(
         BinaryOperator::GreaterEqual => left.greater_equal(&right),
         BinaryOperator::Less => left.less(&right),
         BinaryOperator::LessEqual => left.less_equal(&right),
+        BinaryOperator::Like => evaluate_like(left, right),
+    }
+}
+
+//Returns null if the value is null, or if it is not found and the list contains a null
+fn evaluate_constant_in(
+    expression: Expression,
+    list: Vec<Expression>,
+    collation: Collation,
+) -> Result<Expression, SimpleDbError> {
+    if !expression.is_constant() || !list.iter().all(|item| item.is_constant()) {
+        return Ok(Expression::In(Box::new(expression), list));
+    }
+    let expression = collate(expression, collation);
+    let list: Vec<Expression> = list.into_iter()
+        .map(|item| collate(item, collation))
+        .collect();
+    if expression.is_null() {
+        return Ok(Expression::Literal(Value::Null));
     }
+
+    let value = expression.get_value()?;
+    for item in &list {
+        let item_value = item.get_value()?;
+        if !item_value.is_null() && !value.is_comparable(&item_value) {
+            return Err(MalformedQuery(format!("IN value {:?} cannot be compared with {:?}", item_value, value)));
+        }
+    }
+
+    for item in list.iter().filter(|item| !item.is_null()) {
+        if expression.equal(item)?.get_boolean()? {
+            return Ok(Expression::Literal(Value::Boolean(true)));
+        }
+    }
+
+    if list.iter().any(|item| item.is_null()) {
+        Ok(Expression::Literal(Value::Null))
+    } else {
+        Ok(Expression::Literal(Value::Boolean(false)))
+    }
+}
+
+fn evaluate_constant_between(
+    expression: Expression,
+    low: Expression,
+    high: Expression,
+    collation: Collation,
+) -> Result<Expression, SimpleDbError> {
+    if !expression.is_constant() || !low.is_constant() || !high.is_constant() {
+        return Ok(Expression::Between(Box::new(expression), Box::new(low), Box::new(high)));
+    }
+    let expression = collate(expression, collation);
+    let low = collate(low, collation);
+    let high = collate(high, collation);
+
+    for bound in [&low, &high] {
+        if !bound.is_null() && !expression.is_null() && !expression.get_value()?.is_comparable(&bound.get_value()?) {
+            return Err(MalformedQuery(format!(
+                "BETWEEN bound {:?} cannot be compared with {:?}", bound.get_value()?, expression.get_value()?
+            )));
+        }
+    }
+
+    expression.greater_equal(&low)?
+        .and(&expression.less_equal(&high)?)
+}
+
+fn evaluate_constant_is_null(
+    expression: Expression,
+    negated: bool,
+) -> Result<Expression, SimpleDbError> {
+    if !expression.is_constant() {
+        return Ok(if negated {
+            Expression::IsNotNull(Box::new(expression))
+        } else {
+            Expression::IsNull(Box::new(expression))
+        });
+    }
+
+    Ok(Expression::Literal(Value::Boolean(expression.is_null() != negated)))
+}
+
+fn evaluate_constant_function(
+    name: String,
+    args: Vec<Expression>,
+) -> Result<Expression, SimpleDbError> {
+    if !args.iter().all(|arg| arg.is_constant()) {
+        return Ok(Expression::Function(name, args));
+    }
+
+    let function = ScalarFunction::from_name(&name)
+        .ok_or(MalformedQuery(format!("Unknown function {}", name)))?;
+    let args = args.iter()
+        .map(|arg| arg.get_value())
+        .collect::<Result<Vec<Value>, SimpleDbError>>()?;
+    Ok(Expression::Literal(function.evaluate(&args)?))
+}
+
+//Returns the string literal in the form in which it is compared under the collation
+fn collate(expression: Expression, collation: Collation) -> Expression {
+    match (collation, expression) {
+        (Collation::CaseInsensitive, Expression::Literal(Value::String(string))) => {
+            Expression::Literal(Value::String(string.to_lowercase()))
+        },
+        (_, expression) => expression,
+    }
+}
+
+fn evaluate_like(left: Expression, right: Expression) -> Result<Expression, SimpleDbError> {
+    if left.is_null() || right.is_null() {
+        return Ok(Expression::Literal(Value::Null));
+    }
+
+    match (left.get_value()?, right.get_value()?) {
+        (Value::String(value), Value::String(pattern)) => Ok(Expression::Literal(Value::Boolean(
+            like_pattern_matches(&value.chars().collect::<Vec<char>>(), &parse_like_pattern(&pattern))
+        ))),
+        _ => Err(MalformedQuery(String::from("LIKE can only be applied to strings")))
+    }
+}
+
+enum LikePatternElement {
+    AnySequence, // "%"
+    AnyChar, // "_"
+    Char(char),
+}
+
+//"%" and "_" can be matched literally by escaping them with a backslash
+fn parse_like_pattern(pattern: &str) -> Vec<LikePatternElement> {
+    let mut elements = Vec::new();
+    let mut chars = pattern.chars();
+
+    while let Some(char) = chars.next() {
+        elements.push(match char {
+            '%' => LikePatternElement::AnySequence,
+            '_' => LikePatternElement::AnyChar,
+            '\\' => LikePatternElement::Char(chars.next().unwrap_or('\\')),
+            other => LikePatternElement::Char(other),
+        });
+    }
+
+    elements
+}
+
+//Greedy matching. When a char doesn't match, we backtrack to the last "%" and make it consume one more char
+fn like_pattern_matches(value: &[char], pattern: &[LikePatternElement]) -> bool {
+    let mut value_index = 0;
+    let mut pattern_index = 0;
+    //(Pattern index after the last "%", value index where the "%" match ends)
+    let mut last_any_sequence: Option<(usize, usize)> = None;
+
+    while value_index < value.len() {
+        match pattern.get(pattern_index) {
+            Some(LikePatternElement::AnySequence) => {
+                pattern_index += 1;
+                last_any_sequence = Some((pattern_index, value_index));
+            },
+            Some(LikePatternElement::AnyChar) => {
+                pattern_index += 1;
+                value_index += 1;
+            },
+            Some(LikePatternElement::Char(char)) if *char == value[value_index] => {
+                pattern_index += 1;
+                value_index += 1;
+            },
+            _ => match last_any_sequence {
+                Some((any_sequence_pattern_index, any_sequence_value_index)) => {
+                    pattern_index = any_sequence_pattern_index;
+                    value_index = any_sequence_value_index + 1;
+                    last_any_sequence = Some((any_sequence_pattern_index, value_index));
+                },
+                None => return false,
+            }
+        }
+    }
+
+    pattern[pattern_index..].iter().all(|element| matches!(element, LikePatternElement::AnySequence))
 }
 
 #[cfg(test)]
@@ -122,15 +376,16 @@ mod test {
     use crate::sql::expression::{BinaryOperator, Expression};
     use crate::sql::expression_evaluator::{evaluate_constant_expressions, evaluate_where_expression};
     use crate::sql::parser::parser::Parser;
+    use crate::sql::statement::CreateTableColumn;
     use crate::table::record::Record;
     use crate::table::table::Table;
     use crate::value::{Type, Value};
     use crate::Row;
     use bytes::Bytes;
     use crossbeam_skiplist::SkipMap;
-    use shared::{SimpleDbFile, SimpleDbFileWrapper, SimpleDbOptions};
+    use shared::{Collation, RowFormat, SimpleDbError, SimpleDbFile, SimpleDbFileWrapper, SimpleDbOptions};
     use std::cell::UnsafeCell;
-    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::{AtomicU64, AtomicUsize};
     use std::sync::{Arc, Mutex};
     use storage::Storage;
 
@@ -140,7 +395,7 @@ mod test {
         let mut parser = Parser::create(String::from("id == 10 OR dinero > 100"));
         let expression = parser.parse_expression().unwrap();
         let row = id_dinero_nombre_row(11, Some(110), None);
-        let result = evaluate_where_expression(&row, &expression);
+        let result = evaluate_where_expression(&row, &expression, Collation::Binary);
 
         assert!(result.unwrap());
     }
@@ -151,7 +406,7 @@ mod test {
         let mut parser = Parser::create(String::from("id == 10 AND (dinero > 100 OR nombre == \"Jaime\")"));
         let expression = parser.parse_expression().unwrap();
         let row = id_dinero_nombre_row(10, Some(110), None);
-        let result = evaluate_where_expression(&row, &expression);
+        let result = evaluate_where_expression(&row, &expression, Collation::Binary);
 
         assert!(result.unwrap());
     }
@@ -161,7 +416,7 @@ mod test {
         let mut parser = Parser::create(String::from("id == 10 AND (dinero > 100 AND nombre == \"Jaime\")"));
         let expression = parser.parse_expression().unwrap();
         let row = id_dinero_nombre_row(10, Some(110), None);
-        let result = evaluate_where_expression(&row, &expression);
+        let result = evaluate_where_expression(&row, &expression, Collation::Binary);
 
         assert!(!result.unwrap());
     }
@@ -170,7 +425,7 @@ mod test {
     fn constant_mixed() {
         let mut parser = Parser::create(String::from("dinero > (1 + 20) OR id > 10"));
         let expression = parser.parse_expression().unwrap();
-        let result = evaluate_constant_expressions(expression);
+        let result = evaluate_constant_expressions(expression, Collation::Binary);
 
         assert!(result.is_ok());
         let result = result.unwrap();
@@ -193,19 +448,90 @@ mod test {
     fn constant_arithmetic_operations() {
         let mut parser = Parser::create(String::from("(1 + 2) + (3.1 + -(4 * 2))"));
         let expression = parser.parse_expression().unwrap();
-        let result = evaluate_constant_expressions(expression);
+        let result = evaluate_constant_expressions(expression, Collation::Binary);
 
         assert!(result.is_ok());
         let result = result.unwrap();
-        assert!(matches!(result, Expression::Literal(Value::I64(_))));
+        assert!(matches!(result, Expression::Literal(Value::F64(_))));
         assert_eq!(result.get_f64().unwrap(), (1 + 2) as f64 + (3.1 + -(4 * 2) as f64));
     }
 
+    #[test]
+    fn constant_integer_operations() {
+        //Both numbers are the same when represented as f64
+        assert_constant_expression("9007199254740993 = 9007199254740993", Value::Boolean(true));
+        assert_constant_expression("9007199254740993 = 9007199254740992", Value::Boolean(false));
+        assert_constant_expression("9007199254740993 + 2", Value::I64(9007199254740995));
+        assert_constant_expression("7 / 2", Value::I64(3));
+        assert_constant_expression("7 / 2.0", Value::F64(3.5));
+        assert_constant_expression("1 < 1.5", Value::Boolean(true));
+    }
+
+    #[test]
+    fn modulo() {
+        assert_constant_expression("7 % 3", Value::I64(1));
+        assert_constant_expression("-7 % 3", Value::I64(-1));
+        assert_constant_expression("1 + 7 % 3", Value::I64(2));
+        assert_constant_expression("NULL % 3", Value::Null);
+        assert!(matches!(evaluate_constant_expressions(parse("7 % 0"), Collation::Binary), Err(SimpleDbError::DivisionByZero)));
+        assert!(evaluate_constant_expressions(parse("7.5 % 2"), Collation::Binary).is_err());
+        assert!(evaluate_constant_expressions(parse("\"a\" % 2"), Collation::Binary).is_err());
+
+        let row = id_dinero_nombre_row(1, Some(0), None);
+        assert!(matches!(evaluate_where_expression(&row, &parse("id % dinero = 0"), Collation::Binary), Err(SimpleDbError::DivisionByZero)));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert!(matches!(evaluate_constant_expressions(parse("7 / 0"), Collation::Binary), Err(SimpleDbError::DivisionByZero)));
+        assert!(matches!(evaluate_constant_expressions(parse("7.5 / 0"), Collation::Binary), Err(SimpleDbError::DivisionByZero)));
+        assert!(matches!(evaluate_constant_expressions(parse("7 / 0.0"), Collation::Binary), Err(SimpleDbError::DivisionByZero)));
+        assert!(matches!(evaluate_constant_expressions(parse("7.5 / -0.0"), Collation::Binary), Err(SimpleDbError::DivisionByZero)));
+        assert_constant_expression("NULL / 0", Value::Null);
+        assert_constant_expression("7.5 / 0.5", Value::F64(15.0));
+
+        let row = id_dinero_nombre_row(1, Some(0), None);
+        assert!(matches!(evaluate_where_expression(&row, &parse("id / dinero = 1"), Collation::Binary), Err(SimpleDbError::DivisionByZero)));
+    }
+
+    #[test]
+    fn integer_overflow() {
+        assert!(matches!(evaluate_constant_expressions(parse("9223372036854775807 + 1"), Collation::Binary), Err(SimpleDbError::IntegerOverflow)));
+        assert!(matches!(evaluate_constant_expressions(parse("-9223372036854775807 - 2"), Collation::Binary), Err(SimpleDbError::IntegerOverflow)));
+        assert!(matches!(evaluate_constant_expressions(parse("4611686018427387904 * 2"), Collation::Binary), Err(SimpleDbError::IntegerOverflow)));
+        assert!(matches!(evaluate_constant_expressions(parse("(-9223372036854775807 - 1) / -1"), Collation::Binary), Err(SimpleDbError::IntegerOverflow)));
+        assert_constant_expression("9223372036854775806 + 1", Value::I64(i64::MAX));
+        assert_constant_expression("9223372036854775807 + 1.0", Value::F64(9223372036854775808.0));
+
+        let row = id_dinero_nombre_row(9223372036854775807, Some(1), None);
+        assert!(matches!(evaluate_where_expression(&row, &parse("id + dinero > 0"), Collation::Binary), Err(SimpleDbError::IntegerOverflow)));
+    }
+
+    #[test]
+    fn bitwise() {
+        assert_constant_expression("6 & 3", Value::I64(2));
+        assert_constant_expression("6 | 3", Value::I64(7));
+        assert_constant_expression("6 ^ 3", Value::I64(5));
+        assert_constant_expression("1 << 4", Value::I64(16));
+        assert_constant_expression("-16 >> 2", Value::I64(-4));
+        //| < ^ < & < shifts < + -
+        assert_constant_expression("1 | 2 ^ 3 & 1 << 1 + 1", Value::I64(3));
+        assert_constant_expression("(6 & 3) = 2", Value::Boolean(true));
+        assert_constant_expression("NULL & 1", Value::Null);
+        assert!(evaluate_constant_expressions(parse("1 << 64"), Collation::Binary).is_err());
+        assert!(evaluate_constant_expressions(parse("1 >> -1"), Collation::Binary).is_err());
+        assert!(evaluate_constant_expressions(parse("1.0 & 1"), Collation::Binary).is_err());
+        assert!(evaluate_constant_expressions(parse("1 | \"a\""), Collation::Binary).is_err());
+
+        let row = id_dinero_nombre_row(10, Some(5), None);
+        assert!(evaluate_where_expression(&row, &parse("id & 2 = 2 AND dinero | 2 = 7"), Collation::Binary).unwrap());
+    }
+
     #[test]
     fn constant_comparation_logical_operations() {
         let mut parser = Parser::create(String::from("((1 > 2) OR (1 <= 2)) AND (1 == 1)"));
         let expression = parser.parse_expression().unwrap();
-        let result = evaluate_constant_expressions(expression);
+        let result = evaluate_constant_expressions(expression, Collation::Binary);
 
         assert!(result.is_ok());
         let result = result.unwrap();
@@ -213,6 +539,189 @@ mod test {
         assert_eq!(result.get_boolean().unwrap(), ((1 > 2) || (1 <= 2)) && (1 == 1));
     }
 
+    #[test]
+    fn like() {
+        //Prefix
+        assert_constant_expression(r#""foobar" LIKE "foo%""#, Value::Boolean(true));
+        assert_constant_expression(r#""barfoo" LIKE "foo%""#, Value::Boolean(false));
+        //Suffix
+        assert_constant_expression(r#""foobar" LIKE "%bar""#, Value::Boolean(true));
+        assert_constant_expression(r#""barfoo" LIKE "%bar""#, Value::Boolean(false));
+        //Contains
+        assert_constant_expression(r#""abxcd" LIKE "%x%""#, Value::Boolean(true));
+        assert_constant_expression(r#""x" LIKE "%x%""#, Value::Boolean(true));
+        assert_constant_expression(r#""abcd" LIKE "%x%""#, Value::Boolean(false));
+        assert_constant_expression(r#""aab" LIKE "%ab""#, Value::Boolean(true));
+        //Single char
+        assert_constant_expression(r#""foo" LIKE "f_o""#, Value::Boolean(true));
+        assert_constant_expression(r#""fo" LIKE "f_o""#, Value::Boolean(false));
+        assert_constant_expression(r#""fooo" LIKE "f_o""#, Value::Boolean(false));
+        //Escaped literal percent
+        assert_constant_expression(r#""100%" LIKE "100\%""#, Value::Boolean(true));
+        assert_constant_expression(r#""1000" LIKE "100\%""#, Value::Boolean(false));
+        assert_constant_expression(r#""a_b" LIKE "a\_b""#, Value::Boolean(true));
+        assert_constant_expression(r#""axb" LIKE "a\_b""#, Value::Boolean(false));
+    }
+
+    #[test]
+    fn where_like() {
+        let mut parser = Parser::create(String::from("nombre LIKE \"Jai%\""));
+        let expression = parser.parse_expression().unwrap();
+
+        assert!(evaluate_where_expression(&id_dinero_nombre_row(1, None, Some("Jaime")), &expression, Collation::Binary).unwrap());
+        assert!(!evaluate_where_expression(&id_dinero_nombre_row(1, None, Some("Pedro")), &expression, Collation::Binary).unwrap());
+        assert!(!evaluate_where_expression(&id_dinero_nombre_row(1, None, None), &expression, Collation::Binary).unwrap());
+    }
+
+    #[test]
+    fn three_valued_logic() {
+        assert_constant_expression("NULL AND FALSE", Value::Boolean(false));
+        assert_constant_expression("FALSE AND NULL", Value::Boolean(false));
+        assert_constant_expression("NULL AND TRUE", Value::Null);
+        assert_constant_expression("NULL AND NULL", Value::Null);
+        assert_constant_expression("NULL OR TRUE", Value::Boolean(true));
+        assert_constant_expression("FALSE OR NULL", Value::Null);
+        assert_constant_expression("NULL OR NULL", Value::Null);
+        //Comparations with null
+        assert_constant_expression("1 = NULL", Value::Null);
+        assert_constant_expression("NULL = NULL", Value::Null);
+        assert_constant_expression("1 != NULL", Value::Null);
+        assert_constant_expression("NULL > 1", Value::Null);
+        assert_constant_expression("1 + NULL", Value::Null);
+        assert_constant_expression("(1 = NULL) OR 1 = 1", Value::Boolean(true));
+    }
+
+    #[test]
+    fn not() {
+        assert_constant_expression("NOT TRUE", Value::Boolean(false));
+        assert_constant_expression("NOT (1 = 1)", Value::Boolean(false));
+        assert_constant_expression("NOT NULL", Value::Null);
+        assert_constant_expression("NOT (1 = NULL)", Value::Null);
+        assert_constant_expression("NOT NULL IS NULL", Value::Boolean(false));
+        //NOT binds tighter than AND & OR
+        assert_constant_expression("NOT FALSE AND FALSE", Value::Boolean(false));
+        assert_constant_expression("NOT TRUE OR TRUE", Value::Boolean(true));
+        assert_constant_expression("NOT NOT TRUE", Value::Boolean(true));
+
+        let row_without_dinero = id_dinero_nombre_row(1, None, None);
+        let row_with_dinero = id_dinero_nombre_row(1, Some(5), None);
+        assert!(!evaluate_where_expression(&row_with_dinero, &parse("NOT (dinero = 5)"), Collation::Binary).unwrap());
+        assert!(evaluate_where_expression(&row_with_dinero, &parse("NOT dinero = 4"), Collation::Binary).unwrap());
+        assert!(evaluate_where_expression(&row_with_dinero, &parse("NOT dinero IS NULL"), Collation::Binary).unwrap());
+        assert!(!evaluate_where_expression(&row_without_dinero, &parse("NOT dinero IS NULL"), Collation::Binary).unwrap());
+        //NOT null -> null, rows are not returned
+        assert!(!evaluate_where_expression(&row_without_dinero, &parse("NOT (dinero = 5)"), Collation::Binary).unwrap());
+        assert!(evaluate_constant_expressions(parse("NOT 1"), Collation::Binary).is_err());
+    }
+
+    #[test]
+    fn is_null() {
+        assert_constant_expression("NULL IS NULL", Value::Boolean(true));
+        assert_constant_expression("1 IS NULL", Value::Boolean(false));
+        assert_constant_expression("NULL IS NOT NULL", Value::Boolean(false));
+        assert_constant_expression("1 IS NOT NULL", Value::Boolean(true));
+        assert_constant_expression("(1 = NULL) IS NULL", Value::Boolean(true));
+    }
+
+    #[test]
+    fn where_null_column() {
+        let row_without_dinero = id_dinero_nombre_row(1, None, None);
+        let row_with_dinero = id_dinero_nombre_row(1, Some(5), None);
+
+        assert!(!evaluate_where_expression(&row_without_dinero, &parse("dinero = 5"), Collation::Binary).unwrap());
+        assert!(!evaluate_where_expression(&row_without_dinero, &parse("dinero != 5"), Collation::Binary).unwrap());
+        assert!(evaluate_where_expression(&row_without_dinero, &parse("dinero IS NULL"), Collation::Binary).unwrap());
+        assert!(!evaluate_where_expression(&row_without_dinero, &parse("dinero IS NOT NULL"), Collation::Binary).unwrap());
+        assert!(evaluate_where_expression(&row_with_dinero, &parse("dinero = 5"), Collation::Binary).unwrap());
+        assert!(!evaluate_where_expression(&row_with_dinero, &parse("dinero IS NULL"), Collation::Binary).unwrap());
+        //null AND false -> false, null OR true -> true
+        assert!(!evaluate_where_expression(&row_without_dinero, &parse("dinero = 5 AND id = 2"), Collation::Binary).unwrap());
+        assert!(evaluate_where_expression(&row_without_dinero, &parse("dinero = 5 OR id = 1"), Collation::Binary).unwrap());
+    }
+
+    #[test]
+    fn in_list() {
+        assert_constant_expression("2 IN (1, 2, 3)", Value::Boolean(true));
+        assert_constant_expression("4 IN (1, 2, 3)", Value::Boolean(false));
+        assert_constant_expression("2 IN (1.0, 2.0)", Value::Boolean(true));
+        assert_constant_expression(r#""b" IN ("a", "b")"#, Value::Boolean(true));
+        assert_constant_expression("4 IN (1, NULL)", Value::Null);
+        assert_constant_expression("1 IN (1, NULL)", Value::Boolean(true));
+        assert_constant_expression("NULL IN (1, 2)", Value::Null);
+    }
+
+    #[test]
+    fn between() {
+        assert_constant_expression("15 BETWEEN 10 AND 20", Value::Boolean(true));
+        assert_constant_expression("10 BETWEEN 10 AND 20", Value::Boolean(true));
+        assert_constant_expression("20 BETWEEN 10 AND 20", Value::Boolean(true));
+        assert_constant_expression("21 BETWEEN 10 AND 20", Value::Boolean(false));
+        assert_constant_expression("9.5 BETWEEN 10 AND 20", Value::Boolean(false));
+        assert_constant_expression("15 BETWEEN 10 AND 20 AND 1 == 1", Value::Boolean(true));
+    }
+
+    #[test]
+    fn in_between_type_mismatch() {
+        let mut parser = Parser::create(String::from(r#"1 IN (1, "a")"#));
+        assert!(evaluate_constant_expressions(parser.parse_expression().unwrap(), Collation::Binary).is_err());
+        let mut parser = Parser::create(String::from(r#"1 IN ("a", 1)"#));
+        assert!(evaluate_constant_expressions(parser.parse_expression().unwrap(), Collation::Binary).is_err());
+        let mut parser = Parser::create(String::from(r#"1 BETWEEN "a" AND 2"#));
+        assert!(evaluate_constant_expressions(parser.parse_expression().unwrap(), Collation::Binary).is_err());
+    }
+
+    #[test]
+    fn where_in_between() {
+        let mut parser = Parser::create(String::from("dinero IN (100, 200) OR id BETWEEN 5 AND 10"));
+        let expression = parser.parse_expression().unwrap();
+
+        assert!(evaluate_where_expression(&id_dinero_nombre_row(1, Some(200), None), &expression, Collation::Binary).unwrap());
+        assert!(!evaluate_where_expression(&id_dinero_nombre_row(1, Some(300), None), &expression, Collation::Binary).unwrap());
+        assert!(evaluate_where_expression(&id_dinero_nombre_row(5, Some(300), None), &expression, Collation::Binary).unwrap());
+        assert!(evaluate_where_expression(&id_dinero_nombre_row(10, None, None), &expression, Collation::Binary).unwrap());
+        assert!(!evaluate_where_expression(&id_dinero_nombre_row(11, None, None), &expression, Collation::Binary).unwrap());
+    }
+
+    #[test]
+    fn case_insensitive_collation() {
+        let collate = |expression: &str, collation: Collation| {
+            evaluate_constant_expressions(parse(expression), collation).unwrap().get_boolean().unwrap()
+        };
+
+        assert!(!collate("\"Apple\" = \"apple\"", Collation::Binary));
+        assert!(collate("\"Apple\" = \"apple\"", Collation::CaseInsensitive));
+        assert!(collate("\"Apple\" != \"apple\"", Collation::Binary));
+        assert!(!collate("\"Apple\" != \"apple\"", Collation::CaseInsensitive));
+        //Uppercase letters go before lowercase ones in byte order
+        assert!(!collate("\"apple\" < \"Banana\"", Collation::Binary));
+        assert!(collate("\"apple\" < \"Banana\"", Collation::CaseInsensitive));
+        assert!(collate("\"Zebra\" < \"apple\"", Collation::Binary));
+        assert!(!collate("\"Zebra\" < \"apple\"", Collation::CaseInsensitive));
+        assert!(!collate("\"b\" BETWEEN \"A\" AND \"C\"", Collation::Binary));
+        assert!(collate("\"b\" BETWEEN \"A\" AND \"C\"", Collation::CaseInsensitive));
+        assert!(!collate("\"APPLE\" IN (\"apple\", \"banana\")", Collation::Binary));
+        assert!(collate("\"APPLE\" IN (\"apple\", \"banana\")", Collation::CaseInsensitive));
+        assert!(!collate("\"APPLE\" LIKE \"app%\"", Collation::Binary));
+        assert!(collate("\"APPLE\" LIKE \"app%\"", Collation::CaseInsensitive));
+
+        let row = id_dinero_nombre_row(1, None, Some("Jaime"));
+        assert!(!evaluate_where_expression(&row, &parse("nombre = \"JAIME\""), Collation::Binary).unwrap());
+        assert!(evaluate_where_expression(&row, &parse("nombre = \"JAIME\""), Collation::CaseInsensitive).unwrap());
+    }
+
+    fn parse(expression: &str) -> Expression {
+        let mut parser = Parser::create(String::from(expression));
+        parser.parse_expression().unwrap()
+    }
+
+    fn assert_constant_expression(expression: &str, expected: Value) {
+        let mut parser = Parser::create(String::from(expression));
+        let expression = parser.parse_expression().unwrap();
+        let result = evaluate_constant_expressions(expression, Collation::Binary).unwrap();
+
+        assert_eq!(result, Expression::Literal(expected));
+    }
+
     fn id_dinero_nombre_row(
         id: usize, //0
         dinero: Option<usize>, //1
@@ -235,22 +744,29 @@ mod test {
             table_name: String::from("personas"),
             next_column_id: AtomicUsize::new(0),
             storage_keyspace_id: 1,
+            row_format: RowFormat::Row,
             columns_by_name: SkipMap::new(),
             columns_by_id: SkipMap::new(),
-            secondary_indexes: SecondaryIndexes::create_mock(Arc::new(SimpleDbOptions::default())),
-            database: Database::create_mock(&Arc::new(SimpleDbOptions::default()))
+            secondary_indexes: SecondaryIndexes::create_mock(Arc::new(SimpleDbOptions::default()), Vec::new()),
+            composite_indexes: Mutex::new(Vec::new()),
+            auto_increment: Mutex::new(None),
+            database: Database::create_mock(&Arc::new(SimpleDbOptions::default())),
+            version: AtomicU64::new(0),
+            n_full_scans: AtomicUsize::new(0),
+            n_secondary_index_scans: AtomicUsize::new(0),
         };
 
         table.add_columns(vec![
-            (String::from("id"), Type::I64, true),
-            (String::from("dinero"), Type::I64, false),
-            (String::from("nombre"), Type::String, false),
+            CreateTableColumn { column_name: String::from("id"), column_type: Type::I64, is_primary: true, is_auto_increment: false, is_not_null: false, is_unique: false, default_value: None },
+            CreateTableColumn { column_name: String::from("dinero"), column_type: Type::I64, is_primary: false, is_auto_increment: false, is_not_null: false, is_unique: false, default_value: None },
+            CreateTableColumn { column_name: String::from("nombre"), column_type: Type::String, is_primary: false, is_auto_increment: false, is_not_null: false, is_unique: false, default_value: None },
         ]);
 
         Row {
             key_bytes: Bytes::copy_from_slice(id.to_le_bytes().as_slice()),
             storage_engine_record: record.build(),
             table: Arc::new(table),
+            columns_desc: None,
         }
     }
 }
\ No newline at end of file
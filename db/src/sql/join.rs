@@ -0,0 +1,140 @@
+use crate::sql::aggregate;
+use crate::sql::expression::{BinaryOperator, Expression};
+use crate::sql::statement::{Projection, SelectStatement};
+use crate::table::table::Table;
+use crate::ColumnDescriptor;
+use shared::{ColumnId, SimpleDbError};
+use std::sync::Arc;
+
+//Columns of the joined rows: the columns of table followed by the columns of join_table, named <table>.<column>
+//Column IDs are assigned by that order
+pub fn joined_columns_desc(table: &Arc<Table>, join_table: &Arc<Table>) -> Vec<ColumnDescriptor> {
+    let mut columns_desc = Vec::new();
+
+    for joined_table in [table, join_table] {
+        for mut column_desc in sorted_columns_desc(joined_table) {
+            column_desc.column_name = format!("{}.{}", joined_table.table_name, column_desc.column_name);
+            column_desc.column_id = columns_desc.len() as ColumnId;
+            column_desc.secondary_index_keyspace_id = None;
+            column_desc.is_primary = false;
+            columns_desc.push(column_desc);
+        }
+    }
+
+    columns_desc
+}
+
+//Columns returned by a select statement with a join. Expects the identifiers to have been qualified
+pub fn columns_desc(
+    table: &Arc<Table>,
+    join_table: &Arc<Table>,
+    select_statement: &SelectStatement,
+) -> Vec<ColumnDescriptor> {
+    let joined_columns_desc = joined_columns_desc(table, join_table);
+    if select_statement.projection.is_empty() {
+        return joined_columns_desc;
+    }
+
+    aggregate::projected_columns_desc(&select_statement.projection, &|column_name| {
+        joined_columns_desc.iter()
+            .find(|column_desc| column_desc.column_name == column_name)
+            .cloned()
+    })
+}
+
+//Sorted by column ID, so the order of the joined columns is always the same
+pub fn sorted_columns_desc(table: &Arc<Table>) -> Vec<ColumnDescriptor> {
+    let mut columns_desc: Vec<ColumnDescriptor> = table.get_columns().into_values().collect();
+    columns_desc.sort_by_key(|column_desc| column_desc.column_id);
+    columns_desc
+}
+
+//Unqualified identifiers are qualified with the only table that has the column. Projected columns keep the
+//name used in the query
+pub fn qualify_identifiers(
+    mut select_statement: SelectStatement,
+    table: &Arc<Table>,
+    join_table: &Arc<Table>,
+) -> Result<SelectStatement, SimpleDbError> {
+    let mut qualify = |expression: Expression| expression.map_leaves(&mut |leaf| match leaf {
+        Expression::Identifier(identifier) => Ok(Expression::Identifier(qualify_identifier(&identifier, table, join_table)?)),
+        leaf => Ok(leaf),
+    });
+
+    if let Some(join) = select_statement.join.as_mut() {
+        join.on = qualify(join.on.clone())?;
+    }
+    select_statement.where_expr = select_statement.where_expr.map(&mut qualify).transpose()?;
+    select_statement.projection = select_statement.projection.into_iter()
+        .map(|projection| match projection {
+            Projection::Column(column_name) => {
+                let qualified_name = qualify_identifier(&column_name, table, join_table)?;
+                if qualified_name == column_name {
+                    Ok(Projection::Column(column_name))
+                } else {
+                    Ok(Projection::Expression(Expression::Identifier(qualified_name), Some(column_name)))
+                }
+            },
+            Projection::Expression(expression, alias) => {
+                let alias = alias.unwrap_or_else(|| expression.to_string());
+                Ok(Projection::Expression(qualify(expression)?, Some(alias)))
+            },
+            Projection::Aggregate(_, _) => Err(SimpleDbError::MalformedQuery(
+                String::from("Aggregations cannot be used with JOIN")
+            )),
+        })
+        .collect::<Result<Vec<Projection>, SimpleDbError>>()?;
+
+    Ok(select_statement)
+}
+
+fn qualify_identifier(
+    identifier: &str,
+    table: &Arc<Table>,
+    join_table: &Arc<Table>,
+) -> Result<String, SimpleDbError> {
+    match identifier.split_once('.') {
+        Some((table_name, column_name)) => {
+            let has_column = [table, join_table].iter()
+                .any(|it| it.table_name == table_name && it.get_column_desc(column_name).is_some());
+            if has_column {
+                Ok(identifier.to_string())
+            } else {
+                Err(SimpleDbError::UnknownColumn(identifier.to_string()))
+            }
+        },
+        None => match (table.get_column_desc(identifier), join_table.get_column_desc(identifier)) {
+            (Some(_), Some(_)) => Err(SimpleDbError::MalformedQuery(format!("Column {} is ambiguous", identifier))),
+            (Some(_), None) => Ok(format!("{}.{}", table.table_name, identifier)),
+            (None, Some(_)) => Ok(format!("{}.{}", join_table.table_name, identifier)),
+            (None, None) => Err(SimpleDbError::UnknownColumn(identifier.to_string())),
+        },
+    }
+}
+
+//Conditions joined with AND in where_expr that only use columns of table. They can be applied before joining the rows
+//Expects the identifiers to have been qualified, the returned expression is unqualified
+pub fn table_filter(where_expr: &Expression, table: &Arc<Table>) -> Option<Expression> {
+    let prefix = format!("{}.", table.table_name);
+    let mut conditions = Vec::new();
+    collect_and_conditions(where_expr, &mut conditions);
+
+    conditions.into_iter()
+        .filter(|condition| {
+            let mut identifiers = Vec::new();
+            condition.collect_identifiers(&mut identifiers);
+            identifiers.iter().all(|identifier| identifier.starts_with(&prefix))
+        })
+        .map(|condition| condition.clone().unqualify_identifiers(&table.table_name))
+        .reduce(|left, right| Expression::Binary(BinaryOperator::And, Box::new(left), Box::new(right)))
+}
+
+fn collect_and_conditions<'a>(expression: &'a Expression, conditions: &mut Vec<&'a Expression>) {
+    match expression {
+        Expression::Binary(BinaryOperator::And, left, right) => {
+            collect_and_conditions(left, conditions);
+            collect_and_conditions(right, conditions);
+        },
+        expression => conditions.push(expression),
+    }
+}
@@ -3,7 +3,15 @@ pub mod statement;
 pub mod validator;
 pub mod parser;
 pub mod query_iterator;
+pub mod aggregate;
+pub mod scalar_function;
+mod join;
+mod hyperloglog;
+pub mod dump;
+pub mod query_cache;
+#[cfg(feature = "arrow")]
+pub mod arrow;
 
 mod expression;
-mod plan;
+pub(crate) mod plan;
 mod expression_evaluator;
@@ -2,39 +2,61 @@ use crate::selection::Selection;
 use crate::sql::expression::{BinaryOperator, Expression, UnaryOperator};
 use crate::sql::parser::token::Token;
 use crate::sql::parser::tokenizer::Tokenizer;
-use crate::sql::statement::{CreateTableStatement, DeleteStatement, InsertStatement, Limit, SelectStatement, Statement, UpdateStatement};
+use crate::sql::aggregate::AggregateFunction;
+use crate::sql::statement::{AlterTableStatement, CreateTableColumn, CreateTableStatement, DeleteStatement, InsertStatement, Join, Limit, Projection, SelectStatement, Statement, UpdateStatement};
 use crate::value::{Type, Value};
 use shared::SimpleDbError;
 use shared::SimpleDbError::IllegalToken;
 use crate::CreateIndexStatement;
 
 const MAX_PRECEDENCE: u8 = u8::MAX;
+//NOT binds tighter than AND, and looser than comparations: NOT a = 1 AND b is (NOT (a = 1)) AND b
+const NOT_PRECEDENCE: u8 = 2;
 
 pub struct Parser {
     tokenizer: Tokenizer,
+    //Line where the last statement returned by next_statement() starts
+    statement_line: usize,
+    //Number of placeholders (?) in the last statement returned by next_statement()
+    n_placeholders: usize,
 }
 
 impl Parser {
     pub fn create(query: String) -> Parser {
         Parser {
             tokenizer: Tokenizer::create(query),
+            statement_line: 1,
+            n_placeholders: 0,
         }
     }
 
     pub fn next_statement(
         &mut self,
     ) -> Result<Option<Statement>, SimpleDbError> {
-        let query = match self.tokenizer.next_token()? {
-            Token::Select => self.select(),
+        //After parsing a statement, the first token of the next one has already been read when consuming ;
+        let first_token = if self.tokenizer.has_last_token() {
+            self.tokenizer.last_token().clone()
+        } else {
+            self.tokenizer.next_token()?
+        };
+        self.statement_line = self.tokenizer.current_location().line;
+        self.n_placeholders = 0;
+
+        let query = match first_token {
+            Token::Select => Ok(Statement::Select(self.select()?)),
             Token::Update => self.update(),
             Token::Delete => self.delete(),
             Token::Insert => self.insert(),
             Token::StartTransaction => self.start_transaction(),
             Token::Create => self.create_some(),
+            Token::Drop => self.drop_table(),
+            Token::Alter => self.alter_table(),
             Token::Rollback => self.rollback(),
             Token::Describe => self.describe(),
             Token::Commit => self.commit(),
+            Token::Savepoint => self.savepoint(),
             Token::Show => self.show(),
+            Token::Explain => self.explain(),
             Token::EOF => return Ok(None),
             _ => Err(IllegalToken(self.tokenizer.current_location(), String::from("Unknown keyword")))
         }?;
@@ -42,40 +64,192 @@ impl Parser {
         Ok(Some(query))
     }
 
+    pub fn statement_line(&self) -> usize {
+        self.statement_line
+    }
+
+    pub fn n_placeholders(&self) -> usize {
+        self.n_placeholders
+    }
+
     //Only used for testing
     pub(crate) fn parse_expression(&mut self) -> Result<Expression, SimpleDbError> {
         self.tokenizer.next_token()?;
         self.expression(0)
     }
 
-    fn select(&mut self) -> Result<Statement, SimpleDbError> {
+    fn select(&mut self) -> Result<SelectStatement, SimpleDbError> {
         self.advance()?;
-        let selection = self.selection()?;
+        let distinct = self.maybe_expect_token(Token::Distinct)?;
+        let projection = self.projection()?;
+        let mut select_statement = self.select_from(projection)?;
+        select_statement.distinct = distinct;
+        Ok(select_statement)
+    }
+
+    //EXISTS (SELECT ...). The returned columns are not used, so SELECT 1 is also accepted
+    fn exists_subquery(&mut self) -> Result<Expression, SimpleDbError> {
+        self.expect_token(Token::OpenParen)?;
+        self.expect_token(Token::Select)?;
+        let projection = if matches!(self.tokenizer.last_token(), Token::NumberI64(_)) {
+            self.advance()?;
+            Vec::new()
+        } else {
+            self.projection()?
+        };
+        let subquery = self.select_from(projection)?;
+        self.expect_token(Token::CloseParen)?;
+
+        Ok(Expression::Exists(Box::new(subquery)))
+    }
+
+    //FROM <table> [[INNER] JOIN <table> ON ...] [WHERE ...] [GROUP BY ...] [LIMIT ...]
+    fn select_from(&mut self, projection: Vec<Projection>) -> Result<SelectStatement, SimpleDbError> {
         self.expect_token(Token::From)?;
         let table_name = self.identifier()?;
+        let join = self.join()?;
         let mut limit = Limit::None;
+        let mut offset = 0;
         let mut expression = None;
+        let mut group_by = Vec::new();
 
         if self.maybe_expect_token(Token::Limit)? {
-            limit = self.limit()?;
+            (limit, offset) = self.limit_offset()?;
         }
         if self.maybe_expect_token(Token::Where)? {
             expression = Some(self.expression(0)?);
         }
+        if self.maybe_expect_token(Token::Group)? {
+            self.expect_token(Token::By)?;
+            group_by = self.group_by()?;
+        }
         if self.maybe_expect_token(Token::Limit)? {
-            limit = self.limit()?;
+            (limit, offset) = self.limit_offset()?;
         }
 
-        Ok(Statement::Select(SelectStatement {
+        Ok(SelectStatement {
+            selection: self.projection_to_selection(&projection, &group_by),
             where_expr: expression,
             table_name,
-            selection,
+            projection,
+            group_by,
+            distinct: false,
+            offset,
+            join,
             limit
-        }))
+        })
+    }
+
+    fn join(&mut self) -> Result<Option<Join>, SimpleDbError> {
+        if self.maybe_expect_token(Token::Inner)? {
+            self.expect_token(Token::Join)?;
+        } else if !self.maybe_expect_token(Token::Join)? {
+            return Ok(None);
+        }
+
+        let table_name = self.identifier()?;
+        self.expect_token(Token::On)?;
+        let on = self.expression(0)?;
+
+        Ok(Some(Join { table_name, on }))
+    }
+
+    //Returns an empty vec if all columns are selected with *
+    fn projection(&mut self) -> Result<Vec<Projection>, SimpleDbError> {
+        if self.maybe_expect_token(Token::Star)? {
+            return Ok(Vec::new());
+        }
+
+        let mut projection = Vec::new();
+        while !self.check_last_token(Token::From) {
+            let name = match self.tokenizer.last_token() {
+                Token::Identifier(_) => Some(self.identifier()?),
+                _ => None,
+            };
+            let aggregate_function = name.as_ref()
+                .filter(|_| self.check_last_token(Token::OpenParen))
+                .and_then(|name| AggregateFunction::from_name(name));
+
+            if let Some(function) = aggregate_function {
+                self.expect_token(Token::OpenParen)?;
+                let column_name = if self.maybe_expect_token(Token::Star)? {
+                    None
+                } else {
+                    Some(self.identifier()?)
+                };
+                self.expect_token(Token::CloseParen)?;
+
+                projection.push(Projection::Aggregate(function, column_name));
+            } else {
+                let expression = match name {
+                    Some(name) if self.check_last_token(Token::OpenParen) => {
+                        let function_call = self.function_call(name)?;
+                        self.infix_expression(0, function_call)?
+                    },
+                    Some(name) => self.infix_expression(0, Expression::Identifier(name))?,
+                    None => self.expression(0)?,
+                };
+                let alias = if self.maybe_expect_token(Token::As)? {
+                    Some(self.identifier()?)
+                } else {
+                    None
+                };
+
+                projection.push(match (expression, alias) {
+                    (Expression::Identifier(column_name), None) => Projection::Column(column_name),
+                    (expression, alias) => Projection::Expression(expression, alias),
+                });
+            }
+
+            if !self.check_last_token(Token::From) {
+                self.expect_token(Token::Comma)?;
+            }
+        }
+
+        Ok(projection)
+    }
+
+    fn group_by(&mut self) -> Result<Vec<String>, SimpleDbError> {
+        let mut group_by = vec![self.identifier()?];
+        while self.maybe_expect_token(Token::Comma)? {
+            group_by.push(self.identifier()?);
+        }
+
+        Ok(group_by)
+    }
+
+    //Columns that will need to be read from the table to produce the projection
+    fn projection_to_selection(&self, projection: &Vec<Projection>, group_by: &Vec<String>) -> Selection {
+        if projection.is_empty() {
+            return Selection::All;
+        }
+
+        let mut columns: Vec<String> = Vec::new();
+        let mut projection_columns = Vec::new();
+        for projection in projection {
+            match projection {
+                Projection::Column(column_name) => projection_columns.push(column_name.clone()),
+                Projection::Aggregate(_, column_name) => projection_columns.extend(column_name.clone()),
+                Projection::Expression(expression, _) => expression.collect_identifiers(&mut projection_columns),
+            }
+        }
+
+        for column_name in projection_columns.iter().chain(group_by.iter()) {
+            if !columns.contains(column_name) {
+                columns.push(column_name.clone());
+            }
+        }
+
+        Selection::Some(columns)
     }
 
     fn expression(&mut self, precedence: u8) -> Result<Expression, SimpleDbError> {
-        let mut expression = self.parse_prefix()?;
+        let prefix = self.parse_prefix()?;
+        self.infix_expression(precedence, prefix)
+    }
+
+    //Parses the operators that follow the already parsed left side of the expression
+    fn infix_expression(&mut self, precedence: u8, mut expression: Expression) -> Result<Expression, SimpleDbError> {
         let mut next_precedence = self.get_precedence(self.tokenizer.last_token());
 
         while precedence < next_precedence {
@@ -88,10 +262,14 @@ impl Parser {
 
     fn parse_infix(&mut self, precedence: u8, left: Expression) -> Result<Expression, SimpleDbError> {
         let binary_operator = match self.advance()? {
+            Token::In => return self.in_list(left),
+            Token::Not => return self.not_infix(precedence, left),
+            Token::Between => return self.between(precedence, left),
+            Token::Is => return self.is_null(left),
             Token::And => BinaryOperator::And,
             Token::Or => BinaryOperator::Or,
             Token::NotEqual => BinaryOperator::NotEqual,
-            Token::EqualEqual => BinaryOperator::Equal,
+            Token::EqualEqual | Token::Equal => BinaryOperator::Equal,
             Token::Less => BinaryOperator::Less,
             Token::LessEqual => BinaryOperator::LessEqual,
             Token::Greater => BinaryOperator::Greater,
@@ -100,6 +278,13 @@ impl Parser {
             Token::Slash => BinaryOperator::Divide,
             Token::Star => BinaryOperator::Multiply,
             Token::Minus => BinaryOperator::Subtract,
+            Token::Percent => BinaryOperator::Modulo,
+            Token::Ampersand => BinaryOperator::BitwiseAnd,
+            Token::Pipe => BinaryOperator::BitwiseOr,
+            Token::Caret => BinaryOperator::BitwiseXor,
+            Token::ShiftLeft => BinaryOperator::ShiftLeft,
+            Token::ShiftRight => BinaryOperator::ShiftRight,
+            Token::Like => BinaryOperator::Like,
             _ => return Err(IllegalToken(
                 self.tokenizer.current_location(), String::from("Cannot use it as a binary operator")
             ))
@@ -109,18 +294,95 @@ impl Parser {
         Ok(Expression::Binary(binary_operator, Box::new(left), Box::new(right)))
     }
 
+    //<left> IN (<expr>, <expr>...) or <left> IN (SELECT ...)
+    fn in_list(&mut self, left: Expression) -> Result<Expression, SimpleDbError> {
+        self.expect_token(Token::OpenParen)?;
+        if self.check_last_token(Token::Select) {
+            let subquery = Expression::Subquery(Box::new(self.select()?));
+            self.expect_token(Token::CloseParen)?;
+            return Ok(Expression::In(Box::new(left), vec![subquery]));
+        }
+
+        let mut list = vec![self.expression(0)?];
+        while self.maybe_expect_token(Token::Comma)? {
+            list.push(self.expression(0)?);
+        }
+        self.expect_token(Token::CloseParen)?;
+
+        Ok(Expression::In(Box::new(left), list))
+    }
+
+    //<left> NOT IN (...), <left> NOT BETWEEN ... AND ... and <left> NOT LIKE ... are parsed as NOT (<left> ...)
+    fn not_infix(&mut self, precedence: u8, left: Expression) -> Result<Expression, SimpleDbError> {
+        let expression = match self.advance()? {
+            Token::In => self.in_list(left)?,
+            Token::Between => self.between(precedence, left)?,
+            Token::Like => Expression::Binary(BinaryOperator::Like, Box::new(left), Box::new(self.expression(precedence)?)),
+            _ => return Err(IllegalToken(
+                self.tokenizer.current_location(), String::from("Expected IN, BETWEEN or LIKE after NOT")
+            ))
+        };
+
+        Ok(Expression::Unary(UnaryOperator::Not, Box::new(expression)))
+    }
+
+    //<left> BETWEEN <low> AND <high>. Bounds are parsed with BETWEEN precedence, so they stop at AND
+    fn between(&mut self, precedence: u8, left: Expression) -> Result<Expression, SimpleDbError> {
+        let low = self.expression(precedence)?;
+        self.expect_token(Token::And)?;
+        let high = self.expression(precedence)?;
+
+        Ok(Expression::Between(Box::new(left), Box::new(low), Box::new(high)))
+    }
+
+    //<name>(<expr>, <expr>...). The name has already been consumed
+    fn function_call(&mut self, name: String) -> Result<Expression, SimpleDbError> {
+        self.expect_token(Token::OpenParen)?;
+        let mut args = Vec::new();
+        if !self.check_last_token(Token::CloseParen) {
+            args.push(self.expression(0)?);
+            while self.maybe_expect_token(Token::Comma)? {
+                args.push(self.expression(0)?);
+            }
+        }
+        self.expect_token(Token::CloseParen)?;
+
+        Ok(Expression::Function(name.to_uppercase(), args))
+    }
+
+    //<left> IS [NOT] NULL
+    fn is_null(&mut self, left: Expression) -> Result<Expression, SimpleDbError> {
+        let negated = self.maybe_expect_token(Token::Not)?;
+        self.expect_token(Token::Null)?;
+
+        if negated {
+            Ok(Expression::IsNotNull(Box::new(left)))
+        } else {
+            Ok(Expression::IsNull(Box::new(left)))
+        }
+    }
+
     fn parse_prefix(&mut self) -> Result<Expression, SimpleDbError> {
         match self.advance()? {
             Token::False => Ok(Expression::Literal(Value::Boolean(false))),
             Token::True => Ok(Expression::Literal(Value::Boolean(true))),
+            Token::Null => Ok(Expression::Literal(Value::Null)),
             Token::NumberF64(num) => Ok(Expression::Literal(Value::F64(num))),
             Token::NumberI64(num) => Ok(Expression::Literal(Value::I64(num))),
             Token::String(string) => Ok(Expression::Literal(Value::String(string))),
+            Token::Identifier(identifier) if self.check_last_token(Token::OpenParen) => self.function_call(identifier),
             Token::Identifier(identifier) => Ok(Expression::Identifier(identifier)),
             Token::Minus => Ok(Expression::Unary(UnaryOperator::Minus, Box::new(self.expression(MAX_PRECEDENCE)?))),
             Token::Plus => Ok(Expression::Unary(UnaryOperator::Plus, Box::new(self.expression(MAX_PRECEDENCE)?))),
+            Token::Not => Ok(Expression::Unary(UnaryOperator::Not, Box::new(self.expression(NOT_PRECEDENCE)?))),
+            Token::Exists => self.exists_subquery(),
+            Token::Placeholder => Ok(self.placeholder()),
             Token::OpenParen => {
-                let result = self.expression(0)?;
+                let result = if self.check_last_token(Token::Select) {
+                    Expression::Subquery(Box::new(self.select()?))
+                } else {
+                    self.expression(0)?
+                };
                 self.expect_token(Token::CloseParen)?;
                 Ok(result)
             },
@@ -128,18 +390,27 @@ impl Parser {
         }
     }
 
+    //Placeholders are numbered by the order in which they appear in the statement
+    fn placeholder(&mut self) -> Expression {
+        self.n_placeholders += 1;
+        Expression::Placeholder(self.n_placeholders - 1)
+    }
+
     fn limit(&mut self) -> Result<Limit, SimpleDbError> {
         let limit_value = self.number_i64()?;
         Ok(Limit::Some(limit_value as usize))
     }
 
-    fn selection(&mut self) -> Result<Selection, SimpleDbError> {
-        if !self.check_last_token(Token::Star) {
-            Ok(Selection::Some(self.column_names(&Token::From)?))
+    //LIMIT <n> [OFFSET <m>]
+    fn limit_offset(&mut self) -> Result<(Limit, usize), SimpleDbError> {
+        let limit = self.limit()?;
+        let offset = if self.maybe_expect_token(Token::Offset)? {
+            self.number_i64()? as usize
         } else {
-            self.advance()?; //Consume *
-            Ok(Selection::All)
-        }
+            0
+        };
+
+        Ok((limit, offset))
     }
 
     fn update(&mut self) -> Result<Statement, SimpleDbError> {
@@ -159,14 +430,18 @@ impl Parser {
         }))
     }
 
+    //SET <column> = <expr>, <column> = <expr>...
+    //Repeating SET after each comma is also allowed
     fn updated_values(&mut self) -> Result<Vec<(String, Expression)>, SimpleDbError> {
         let mut updated_values = Vec::new();
+        self.expect_token(Token::Set)?;
+
         while !self.check_last_token(Token::Where) && !self.check_last_token(Token::Semicolon) {
             if updated_values.len() > 0 {
                 self.expect_token(Token::Comma)?;
+                self.maybe_expect_token(Token::Set)?;
             }
 
-            self.expect_token(Token::Set)?;
             let column_name = self.identifier()?;
             self.expect_token(Token::Equal)?;
             let new_value_expression = self.expression(0)?;
@@ -209,9 +484,20 @@ impl Parser {
 
     fn rollback(&mut self) -> Result<Statement, SimpleDbError> {
         self.advance()?;
+        //ROLLBACK TO [SAVEPOINT] <name>
+        if self.maybe_expect_token(Token::To)? {
+            self.maybe_expect_token(Token::Savepoint)?;
+            return Ok(Statement::RollbackToSavepoint(self.identifier()?));
+        }
+
         Ok(Statement::Rollback)
     }
 
+    fn savepoint(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        Ok(Statement::Savepoint(self.identifier()?))
+    }
+
     fn start_transaction(&mut self) -> Result<Statement, SimpleDbError> {
         self.advance()?;
         Ok(Statement::StartTransaction)
@@ -227,13 +513,22 @@ impl Parser {
                 let column_names = self.column_names(&Token::CloseParen)?;
                 self.expect_token(Token::CloseParen)?;
                 self.expect_token(Token::Values)?;
-                self.expect_token(Token::OpenParen)?;
-                let column_values = self.column_values(&Token::CloseParen)?;
-                self.expect_token(Token::CloseParen)?;
-                let column_name_values = self.create_insert_statement_values(column_names, column_values)?;
+
+                //VALUES (<row 1 values>), (<row 2 values>)...
+                let mut rows = Vec::new();
+                loop {
+                    self.expect_token(Token::OpenParen)?;
+                    let column_values = self.column_values(&Token::CloseParen)?;
+                    self.expect_token(Token::CloseParen)?;
+                    rows.push(self.create_insert_statement_values(column_names.clone(), column_values)?);
+
+                    if !self.maybe_expect_token(Token::Comma)? {
+                        break;
+                    }
+                }
 
                 Ok(Statement::Insert(InsertStatement {
-                    values: column_name_values,
+                    values: rows,
                     table_name,
                 }))
             },
@@ -244,8 +539,8 @@ impl Parser {
     fn create_insert_statement_values(
         &self,
         mut column_names: Vec<String>,
-        mut column_values_tokens: Vec<Value>
-    ) -> Result<Vec<(String, Value)>, SimpleDbError> {
+        mut column_values_tokens: Vec<Expression>
+    ) -> Result<Vec<(String, Expression)>, SimpleDbError> {
         if column_names.len() != column_values_tokens.len() {
             return Err(SimpleDbError::MalformedQuery(String::from(
                 "Insert statements should have the same number of columns and values"
@@ -281,17 +576,15 @@ impl Parser {
         Ok(column_names)
     }
 
-    fn column_values(&mut self, terminator_token: &Token) -> Result<Vec<Value>, SimpleDbError> {
+    //Values are literals or placeholders
+    fn column_values(&mut self, terminator_token: &Token) -> Result<Vec<Expression>, SimpleDbError> {
         let mut column_values = Vec::new();
         while !self.check_last_token(terminator_token.clone()) {
-            let token = self.advance()?;
-            let value = token.serialize()
-                .map_err(|_| IllegalToken(
-                    self.tokenizer.current_location(),
-                    String::from("Value cannot be inserted into a row"))
-                )?;
-
-            column_values.push(value);
+            if self.maybe_expect_token(Token::Placeholder)? {
+                column_values.push(self.placeholder());
+            } else {
+                column_values.push(Expression::Literal(self.literal_value()?));
+            }
 
             if !self.check_last_token(terminator_token.clone()) {
                 self.expect_token(Token::Comma)?;
@@ -301,6 +594,23 @@ impl Parser {
         Ok(column_values)
     }
 
+    fn literal_value(&mut self) -> Result<Value, SimpleDbError> {
+        let token = match self.advance()? {
+            Token::Minus => match self.advance()? {
+                Token::NumberI64(number) => Token::NumberI64(-number),
+                Token::NumberF64(number) => Token::NumberF64(-number),
+                _ => return Err(IllegalToken(self.tokenizer.current_location(), String::from("Expect number after -")))
+            },
+            token => token
+        };
+
+        token.serialize()
+            .map_err(|_| IllegalToken(
+                self.tokenizer.current_location(),
+                String::from("Value cannot be inserted into a row"))
+            )
+    }
+
     fn create_some(&mut self) -> Result<Statement, SimpleDbError> {
         self.advance()?;
         match self.advance()? {
@@ -311,17 +621,63 @@ impl Parser {
         }
     }
 
+    fn alter_table(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        self.expect_token(Token::Table)?;
+        let table_name = self.identifier()?;
+        self.expect_token(Token::Add)?;
+        self.expect_token(Token::Column)?;
+        let column_name = self.identifier()?;
+        let column_type = self.column_type()?;
+        let default_value = self.default_value()?;
+
+        Ok(Statement::AlterTable(AlterTableStatement {
+            table_name,
+            column_name,
+            column_type,
+            default_value,
+        }))
+    }
+
+    //[DEFAULT <literal value>]. DEFAULT NULL is the same as not having a default value
+    fn default_value(&mut self) -> Result<Option<Value>, SimpleDbError> {
+        if !self.maybe_expect_token(Token::Default)? {
+            return Ok(None);
+        }
+
+        match self.literal_value()? {
+            Value::Null => Ok(None),
+            default_value => Ok(Some(default_value)),
+        }
+    }
+
+    fn drop_table(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+        self.expect_token(Token::Table)?;
+        let table_name = self.identifier()?;
+        Ok(Statement::DropTable(table_name))
+    }
+
     fn create_index(&mut self) -> Result<Statement, SimpleDbError> {
+        let index_name = match self.tokenizer.last_token() {
+            Token::Identifier(_) => Some(self.identifier()?),
+            _ => None,
+        };
+
         match self.advance()? {
             Token::On => {
                 let table_name = self.identifier()?;
                 self.expect_token(Token::OpenParen)?;
-                let column_name = self.identifier()?;
+                let mut columns_names = vec![self.identifier()?];
+                while self.maybe_expect_token(Token::Comma)? {
+                    columns_names.push(self.identifier()?);
+                }
                 self.expect_token(Token::CloseParen)?;
                 let is_async = self.maybe_expect_token(Token::Async)?;
 
                 Ok(Statement::CreateIndex(CreateIndexStatement {
-                    column_name,
+                    index_name,
+                    columns_names,
                     table_name,
                     wait: !is_async
                 }))
@@ -352,15 +708,20 @@ impl Parser {
         }
     }
 
-    fn create_table_columns(&mut self) -> Result<Vec<(String, Type, bool)>, SimpleDbError> {
+    fn create_table_columns(&mut self) -> Result<Vec<CreateTableColumn>, SimpleDbError> {
         let mut columns = Vec::new();
 
         while !self.maybe_expect_token(Token::CloseParen)? {
-            let table_name = self.identifier()?;
+            let column_name = self.identifier()?;
             let column_type = self.column_type()?;
             let is_primary = self.is_primary_key()?;
+            let is_auto_increment = is_primary && self.maybe_expect_token(Token::AutoIncrement)?;
+            let (is_not_null, is_unique) = self.column_constraints()?;
+            let default_value = self.default_value()?;
 
-            columns.push((table_name, column_type, is_primary));
+            columns.push(CreateTableColumn {
+                column_name, column_type, is_primary, is_auto_increment, is_not_null, is_unique, default_value
+            });
 
             if !self.check_last_token(Token::CloseParen) {
                 self.expect_token(Token::Comma)?;
@@ -370,6 +731,20 @@ impl Parser {
         Ok(columns)
     }
 
+    //EXPLAIN <SELECT | UPDATE | DELETE statement>
+    fn explain(&mut self) -> Result<Statement, SimpleDbError> {
+        self.advance()?;
+
+        let statement = match self.tokenizer.last_token() {
+            Token::Select => Statement::Select(self.select()?),
+            Token::Update => self.update()?,
+            Token::Delete => self.delete()?,
+            _ => return Err(IllegalToken(self.tokenizer.current_location(), String::from("Only SELECT, UPDATE and DELETE can be explained")))
+        };
+
+        Ok(Statement::Explain(Box::new(statement)))
+    }
+
     fn show(&mut self) -> Result<Statement, SimpleDbError> {
         self.advance()?;
 
@@ -407,6 +782,23 @@ impl Parser {
         Ok(is_primary)
     }
 
+    //[NOT NULL] [UNIQUE] in any order. Returns (is not null, is unique)
+    fn column_constraints(&mut self) -> Result<(bool, bool), SimpleDbError> {
+        let mut is_not_null = false;
+        let mut is_unique = false;
+
+        loop {
+            if self.maybe_expect_token(Token::Not)? {
+                self.expect_token(Token::Null)?;
+                is_not_null = true;
+            } else if self.maybe_expect_token(Token::Unique)? {
+                is_unique = true;
+            } else {
+                return Ok((is_not_null, is_unique));
+            }
+        }
+    }
+
     fn column_type(&mut self) -> Result<Type, SimpleDbError> {
         match self.advance()? {
             Token::ColumnType(column_type) => Ok(column_type),
@@ -466,9 +858,14 @@ impl Parser {
             Token::NumberI64(_) | Token::NumberF64(_) | Token::Identifier(_) | Token::String(_) => 0,
             Token::Or => 1,
             Token::And => 2,
-            Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual | Token::EqualEqual | Token::NotEqual => 3,
-            Token::Plus | Token::Minus => 4,
-            Token::Slash | Token::Star => 5,
+            Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual | Token::EqualEqual | Token::Equal | Token::NotEqual | Token::Like | Token::In | Token::Between | Token::Is | Token::Not => 3,
+            //Bitwise operators bind looser than arithmetic ones and tighter than comparations: | < ^ < & < shifts
+            Token::Pipe => 4,
+            Token::Caret => 5,
+            Token::Ampersand => 6,
+            Token::ShiftLeft | Token::ShiftRight => 7,
+            Token::Plus | Token::Minus => 8,
+            Token::Slash | Token::Star | Token::Percent => 9,
             _ => 0
         }
     }
@@ -477,9 +874,10 @@ impl Parser {
 #[cfg(test)]
 mod test {
     use crate::selection::Selection;
-    use crate::sql::expression::{BinaryOperator, Expression};
+    use crate::sql::expression::{BinaryOperator, Expression, UnaryOperator};
     use crate::sql::parser::parser::Parser;
-    use crate::sql::statement::{Limit, Statement};
+    use crate::sql::aggregate::AggregateFunction;
+    use crate::sql::statement::{CreateTableColumn, Join, Limit, Projection, Statement};
     use crate::value::{Type, Value};
 
     #[test]
@@ -499,6 +897,26 @@ mod test {
         assert_eq!(update_statement.where_expr, None);
     }
 
+    #[test]
+    fn update_single_set() {
+        let mut parser = Parser::create(String::from("UPDATE personas SET dinero = dinero - 10, nombre = \"Jaime\" WHERE id = 1;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let update_statement = match statement {
+            Statement::Update(u) => u, _ => panic!(),
+        };
+
+        assert_eq!(update_statement.updated_values.len(), 2);
+        assert_eq!(update_statement.updated_values[0].0, "dinero");
+        assert_eq!(update_statement.updated_values[0].1, Expression::Binary(
+            BinaryOperator::Subtract,
+            Box::new(Expression::Identifier(String::from("dinero"))),
+            Box::new(Expression::Literal(Value::I64(10))))
+        );
+        assert_eq!(update_statement.updated_values[1].0, "nombre");
+        assert_eq!(update_statement.updated_values[1].1, Expression::Literal(Value::String(String::from("Jaime"))));
+        assert!(update_statement.where_expr.is_some());
+    }
+
     #[test]
     fn update() {
         let mut parser = Parser::create(String::from("UPDATE personas \
@@ -566,6 +984,41 @@ mod test {
         assert_eq!(select_statement.table_name, "personas");
     }
 
+    #[test]
+    fn select_with_limit_offset() {
+        let mut parser = Parser::create(String::from("SELECT nombre FROM personas WHERE dinero > 10 LIMIT 10 OFFSET 20;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert!(matches!(select_statement.limit, Limit::Some(10)));
+        assert_eq!(select_statement.offset, 20);
+        assert_eq!(select_statement.table_name, "personas");
+    }
+
+    #[test]
+    fn select_group_by() {
+        let mut parser = Parser::create(String::from("SELECT region, SUM(ventas), COUNT(*) FROM tiendas GROUP BY region;"));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.table_name, "tiendas");
+        assert_eq!(select_statement.group_by, vec![String::from("region")]);
+        assert_eq!(select_statement.projection, vec![
+            Projection::Column(String::from("region")),
+            Projection::Aggregate(AggregateFunction::Sum, Some(String::from("ventas"))),
+            Projection::Aggregate(AggregateFunction::Count, None),
+        ]);
+        match &select_statement.selection {
+            Selection::Some(columns) => assert_eq!(*columns, vec![String::from("region"), String::from("ventas")]),
+            Selection::All => panic!(),
+        };
+        assert!(select_statement.is_aggregation());
+    }
+
     #[test]
     fn delete_with_expression() {
         let mut parser = Parser::create(String::from(
@@ -586,6 +1039,289 @@ mod test {
         ));
     }
 
+    #[test]
+    fn select_where_like() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas WHERE nombre LIKE \"J_i%\" AND id > 1;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::And,
+            Box::new(Expression::Binary(
+                BinaryOperator::Like,
+                Box::new(Expression::Identifier(String::from("nombre"))),
+                Box::new(Expression::Literal(Value::String(String::from("J_i%")))),
+            )),
+            Box::new(Expression::Binary(
+                BinaryOperator::Greater,
+                Box::new(Expression::Identifier(String::from("id"))),
+                Box::new(Expression::Literal(Value::I64(1))),
+            )),
+        ));
+    }
+
+    #[test]
+    fn select_projection_aliases() {
+        let mut parser = Parser::create(String::from(
+            "SELECT max, precio * cantidad AS total, MAX(precio), -precio FROM pedidos;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.projection, vec![
+            Projection::Column(String::from("max")),
+            Projection::Expression(Expression::Binary(
+                BinaryOperator::Multiply,
+                Box::new(Expression::Identifier(String::from("precio"))),
+                Box::new(Expression::Identifier(String::from("cantidad"))),
+            ), Some(String::from("total"))),
+            Projection::Aggregate(AggregateFunction::Max, Some(String::from("precio"))),
+            Projection::Expression(Expression::Unary(
+                UnaryOperator::Minus, Box::new(Expression::Identifier(String::from("precio")))
+            ), None),
+        ]);
+    }
+
+    #[test]
+    fn select_join() {
+        let mut parser = Parser::create(String::from(
+            "SELECT personas.nombre, producto FROM personas INNER JOIN pedidos ON personas.id = pedidos.persona_id WHERE id > 1;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.table_name, "personas");
+        assert_eq!(select_statement.join, Some(Join {
+            table_name: String::from("pedidos"),
+            on: Expression::Binary(
+                BinaryOperator::Equal,
+                Box::new(Expression::Identifier(String::from("personas.id"))),
+                Box::new(Expression::Identifier(String::from("pedidos.persona_id"))),
+            ),
+        }));
+        assert_eq!(select_statement.projection, vec![
+            Projection::Column(String::from("personas.nombre")),
+            Projection::Column(String::from("producto")),
+        ]);
+        assert!(select_statement.where_expr.is_some());
+    }
+
+    #[test]
+    fn select_functions() {
+        let mut parser = Parser::create(String::from(
+            "SELECT id, upper(nombre) FROM personas WHERE SUBSTR(nombre, 1, 2) = LOWER(apellido);"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.projection, vec![
+            Projection::Column(String::from("id")),
+            Projection::Expression(Expression::Function(String::from("UPPER"), vec![Expression::Identifier(String::from("nombre"))]), None),
+        ]);
+        assert_eq!(select_statement.selection, Selection::Some(vec![String::from("id"), String::from("nombre")]));
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Equal,
+            Box::new(Expression::Function(String::from("SUBSTR"), vec![
+                Expression::Identifier(String::from("nombre")),
+                Expression::Literal(Value::I64(1)),
+                Expression::Literal(Value::I64(2)),
+            ])),
+            Box::new(Expression::Function(String::from("LOWER"), vec![Expression::Identifier(String::from("apellido"))])),
+        ));
+    }
+
+    #[test]
+    fn select_where_in_between() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas WHERE id IN (1, 2 + 1) AND dinero BETWEEN 10 AND 20;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::And,
+            Box::new(Expression::In(
+                Box::new(Expression::Identifier(String::from("id"))),
+                vec![
+                    Expression::Literal(Value::I64(1)),
+                    Expression::Binary(
+                        BinaryOperator::Add,
+                        Box::new(Expression::Literal(Value::I64(2))),
+                        Box::new(Expression::Literal(Value::I64(1))),
+                    ),
+                ],
+            )),
+            Box::new(Expression::Between(
+                Box::new(Expression::Identifier(String::from("dinero"))),
+                Box::new(Expression::Literal(Value::I64(10))),
+                Box::new(Expression::Literal(Value::I64(20))),
+            )),
+        ));
+    }
+
+    #[test]
+    fn select_where_not_in_between_like() {
+        let mut parser = Parser::create(String::from(
+            r#"SELECT * FROM personas WHERE id NOT IN (SELECT persona_id FROM deudas) AND dinero NOT BETWEEN 10 AND 20 OR nombre NOT LIKE "J%";"#
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        let (and, not_like) = match select_statement.where_expr.unwrap() {
+            Expression::Binary(BinaryOperator::Or, left, right) => (*left, *right),
+            _ => panic!(),
+        };
+        let (not_in, not_between) = match and {
+            Expression::Binary(BinaryOperator::And, left, right) => (*left, *right),
+            _ => panic!(),
+        };
+
+        assert!(matches!(not_in, Expression::Unary(UnaryOperator::Not, expression) if matches!(*expression, Expression::In(_, _))));
+        assert_eq!(not_between, Expression::Unary(UnaryOperator::Not, Box::new(Expression::Between(
+            Box::new(Expression::Identifier(String::from("dinero"))),
+            Box::new(Expression::Literal(Value::I64(10))),
+            Box::new(Expression::Literal(Value::I64(20))),
+        ))));
+        assert_eq!(not_like, Expression::Unary(UnaryOperator::Not, Box::new(Expression::Binary(
+            BinaryOperator::Like,
+            Box::new(Expression::Identifier(String::from("nombre"))),
+            Box::new(Expression::Literal(Value::String(String::from("J%")))),
+        ))));
+    }
+
+    #[test]
+    fn select_where_subquery() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas WHERE id IN (SELECT persona_id FROM deudas WHERE cantidad > 10) OR dinero = (SELECT MAX(dinero) FROM personas);"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        let (in_subquery, scalar_subquery) = match select_statement.where_expr.unwrap() {
+            Expression::Binary(BinaryOperator::Or, left, right) => (*left, *right),
+            _ => panic!(),
+        };
+
+        let in_subquery = match in_subquery {
+            Expression::In(expression, mut list) => {
+                assert_eq!(*expression, Expression::Identifier(String::from("id")));
+                assert_eq!(list.len(), 1);
+                match list.remove(0) { Expression::Subquery(subquery) => subquery, _ => panic!() }
+            },
+            _ => panic!(),
+        };
+        assert_eq!(in_subquery.table_name, "deudas");
+        assert_eq!(in_subquery.projection, vec![Projection::Column(String::from("persona_id"))]);
+        assert_eq!(in_subquery.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Greater,
+            Box::new(Expression::Identifier(String::from("cantidad"))),
+            Box::new(Expression::Literal(Value::I64(10))),
+        ));
+
+        let scalar_subquery = match scalar_subquery {
+            Expression::Binary(BinaryOperator::Equal, left, right) => {
+                assert_eq!(*left, Expression::Identifier(String::from("dinero")));
+                match *right { Expression::Subquery(subquery) => subquery, _ => panic!() }
+            },
+            _ => panic!(),
+        };
+        assert_eq!(scalar_subquery.table_name, "personas");
+        assert_eq!(scalar_subquery.projection, vec![Projection::Aggregate(AggregateFunction::Max, Some(String::from("dinero")))]);
+        assert!(scalar_subquery.where_expr.is_none());
+    }
+
+    #[test]
+    fn select_where_exists() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas WHERE NOT EXISTS (SELECT 1 FROM deudas WHERE deudas.persona_id = personas.id);"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        let subquery = match select_statement.where_expr.unwrap() {
+            Expression::Unary(UnaryOperator::Not, expr) => match *expr {
+                Expression::Exists(subquery) => subquery,
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+
+        assert_eq!(subquery.table_name, "deudas");
+        assert!(subquery.projection.is_empty());
+        assert_eq!(subquery.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Equal,
+            Box::new(Expression::Identifier(String::from("deudas.persona_id"))),
+            Box::new(Expression::Identifier(String::from("personas.id"))),
+        ));
+    }
+
+    #[test]
+    fn select_where_is_null() {
+        let mut parser = Parser::create(String::from(
+            "SELECT * FROM personas WHERE nombre IS NULL OR dinero IS NOT NULL;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        let select_statement = match statement {
+            Statement::Select(s) => s, _ => panic!(),
+        };
+        assert_eq!(select_statement.where_expr.unwrap(), Expression::Binary(
+            BinaryOperator::Or,
+            Box::new(Expression::IsNull(Box::new(Expression::Identifier(String::from("nombre"))))),
+            Box::new(Expression::IsNotNull(Box::new(Expression::Identifier(String::from("dinero"))))),
+        ));
+    }
+
+    #[test]
+    fn select_where_not() {
+        assert_eq!(parse_where("NOT (dinero = 1)"), Expression::Unary(
+            UnaryOperator::Not,
+            Box::new(Expression::Binary(
+                BinaryOperator::Equal,
+                Box::new(Expression::Identifier(String::from("dinero"))),
+                Box::new(Expression::Literal(Value::I64(1))),
+            )),
+        ));
+        assert_eq!(parse_where("NOT nombre IS NULL"), Expression::Unary(
+            UnaryOperator::Not,
+            Box::new(Expression::IsNull(Box::new(Expression::Identifier(String::from("nombre"))))),
+        ));
+        assert_eq!(parse_where("NOT true"), Expression::Unary(
+            UnaryOperator::Not,
+            Box::new(Expression::Literal(Value::Boolean(true))),
+        ));
+        //NOT binds tighter than AND
+        assert_eq!(parse_where("NOT dinero = 1 AND activo"), Expression::Binary(
+            BinaryOperator::And,
+            Box::new(Expression::Unary(
+                UnaryOperator::Not,
+                Box::new(Expression::Binary(
+                    BinaryOperator::Equal,
+                    Box::new(Expression::Identifier(String::from("dinero"))),
+                    Box::new(Expression::Literal(Value::I64(1))),
+                )),
+            )),
+            Box::new(Expression::Identifier(String::from("activo"))),
+        ));
+    }
+
+    fn parse_where(where_expr: &str) -> Expression {
+        let mut parser = Parser::create(format!("SELECT * FROM personas WHERE {};", where_expr));
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(select_statement) => select_statement.where_expr.unwrap(),
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn select_with_expression_without_limit() {
         let mut parser = Parser::create(String::from(
@@ -640,6 +1376,19 @@ mod test {
         assert!(matches!(statement, Statement::Rollback));
     }
 
+    #[test]
+    fn savepoint() {
+        let mut parser = Parser::create(String::from(
+            "SAVEPOINT before_update; ROLLBACK TO SAVEPOINT before_update; ROLLBACK TO before_update;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::Savepoint(name) if name == "before_update"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::RollbackToSavepoint(name) if name == "before_update"));
+        let statement = parser.next_statement().unwrap().unwrap();
+        assert!(matches!(statement, Statement::RollbackToSavepoint(name) if name == "before_update"));
+    }
+
     #[test]
     fn commit() {
         let mut parser = Parser::create(String::from(
@@ -660,13 +1409,78 @@ mod test {
         match statement {
             Statement::Insert(insert_statement) => {
                 assert_eq!(insert_statement.table_name, String::from("personas"));
+                assert_eq!(insert_statement.values.len(), 1);
+                assert_eq!(insert_statement.values[0].len(), 3);
+                assert_eq!(insert_statement.values[0][2], (String::from("id"), Expression::Literal(Value::I64(1))));
+                assert_eq!(insert_statement.values[0][1], (String::from("nombre"), Expression::Literal(Value::String(String::from("Jaime")))));
+                assert_eq!(insert_statement.values[0][0], (String::from("dinero"), Expression::Literal(Value::F64(10.2))));
+            }
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn insert_multiple_rows() {
+        let mut parser = Parser::create(String::from(
+            "INSERT INTO personas (id, dinero) VALUES (1, 10), (2, -20), (3, 30);"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::Insert(insert_statement) => {
                 assert_eq!(insert_statement.values.len(), 3);
-                assert_eq!(insert_statement.values[2], (String::from("id"), Value::I64(1)));
-                assert_eq!(insert_statement.values[1], (String::from("nombre"), Value::String(String::from("Jaime"))));
-                assert_eq!(insert_statement.values[0], (String::from("dinero"), Value::F64(10.2)));
+                assert_eq!(insert_statement.values[1], vec![
+                    (String::from("dinero"), Expression::Literal(Value::I64(-20))),
+                    (String::from("id"), Expression::Literal(Value::I64(2))),
+                ]);
+                assert_eq!(insert_statement.values[2][1], (String::from("id"), Expression::Literal(Value::I64(3))));
+            }
+            _ => panic!()
+        }
+    }
+
+    #[test]
+    fn placeholders() {
+        let mut parser = Parser::create(String::from(
+            "INSERT INTO personas (id, dinero) VALUES (?, 10), (2, ?); SELECT * FROM personas WHERE id = ? AND dinero > ?;"
+        ));
+
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Insert(insert_statement) => {
+                assert_eq!(insert_statement.values[0][1], (String::from("id"), Expression::Placeholder(0)));
+                assert_eq!(insert_statement.values[1][0], (String::from("dinero"), Expression::Placeholder(1)));
             }
             _ => panic!()
         }
+        assert_eq!(parser.n_placeholders(), 2);
+
+        //Placeholders are numbered again in every statement
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Select(select_statement) => assert_eq!(select_statement.where_expr, Some(Expression::Binary(
+                BinaryOperator::And,
+                Box::new(Expression::Binary(BinaryOperator::Equal, Box::new(Expression::Identifier(String::from("id"))), Box::new(Expression::Placeholder(0)))),
+                Box::new(Expression::Binary(BinaryOperator::Greater, Box::new(Expression::Identifier(String::from("dinero"))), Box::new(Expression::Placeholder(1)))),
+            ))),
+            _ => panic!()
+        }
+        assert_eq!(parser.n_placeholders(), 2);
+    }
+
+    #[test]
+    fn explain() {
+        let mut parser = Parser::create(String::from(
+            "EXPLAIN SELECT * FROM personas WHERE id = 1; EXPLAIN DELETE FROM personas; EXPLAIN INSERT INTO personas (id) VALUES (1);"
+        ));
+
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Explain(statement) => assert!(matches!(*statement, Statement::Select(_))),
+            _ => panic!("")
+        }
+        match parser.next_statement().unwrap().unwrap() {
+            Statement::Explain(statement) => assert!(matches!(*statement, Statement::Delete(_))),
+            _ => panic!("")
+        }
+        assert!(parser.next_statement().is_err());
     }
 
     #[test]
@@ -698,9 +1512,10 @@ mod test {
     fn create_table() {
         let mut parser = Parser::create(String::from(
             r#"CREATE TABLE personas (
-                id i64 PRIMARY KEY,
-                nombre VARCHAR,
-                dinero f64
+                id i64 PRIMARY KEY AUTOINCREMENT,
+                nombre VARCHAR NOT NULL UNIQUE,
+                dinero f64 UNIQUE NOT NULL,
+                edad i64 DEFAULT -1
                );"#
         ));
         let statement = parser.next_statement().unwrap().unwrap();
@@ -709,10 +1524,20 @@ mod test {
         match statement {
             Statement::CreateTable(createStatement) => {
                 assert_eq!(createStatement.table_name, String::from("personas"));
-                assert_eq!(createStatement.columns.len(), 3);
-                assert_eq!(createStatement.columns[0], (String::from("id"), Type::I64, true));
-                assert_eq!(createStatement.columns[1], (String::from("nombre"), Type::String, false));
-                assert_eq!(createStatement.columns[2], (String::from("dinero"), Type::F64, false));
+                assert_eq!(createStatement.columns.len(), 4);
+                assert_eq!(createStatement.columns[0], CreateTableColumn {
+                    column_name: String::from("id"), column_type: Type::I64, is_primary: true, is_auto_increment: true, is_not_null: false, is_unique: false, default_value: None
+                });
+                assert_eq!(createStatement.columns[1], CreateTableColumn {
+                    column_name: String::from("nombre"), column_type: Type::String, is_primary: false, is_auto_increment: false, is_not_null: true, is_unique: true, default_value: None
+                });
+                assert_eq!(createStatement.columns[2], CreateTableColumn {
+                    column_name: String::from("dinero"), column_type: Type::F64, is_primary: false, is_auto_increment: false, is_not_null: true, is_unique: true, default_value: None
+                });
+                assert_eq!(createStatement.columns[3], CreateTableColumn {
+                    column_name: String::from("edad"), column_type: Type::I64, is_primary: false, is_auto_increment: false, is_not_null: false, is_unique: false,
+                    default_value: Some(Value::I64(-1))
+                });
             },
             _ => panic!()
         }
@@ -741,8 +1566,75 @@ mod test {
         match statement {
             Statement::CreateIndex(statement) => {
                 assert_eq!(statement.table_name, String::from("personas"));
-                assert_eq!(statement.column_name, String::from("dinero"));
+                assert_eq!(statement.columns_names, vec![String::from("dinero")]);
                 assert_eq!(statement.wait, false);
+                assert_eq!(statement.index_name, None);
+            }
+            _ => panic!("")
+        }
+    }
+
+    #[test]
+    fn alter_table() {
+        let mut parser = Parser::create(String::from(
+            "ALTER TABLE personas ADD COLUMN dinero I64 DEFAULT -10;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::AlterTable(statement) => {
+                assert_eq!(statement.table_name, String::from("personas"));
+                assert_eq!(statement.column_name, String::from("dinero"));
+                assert_eq!(statement.column_type, Type::I64);
+                assert_eq!(statement.default_value, Some(Value::I64(-10)));
+            }
+            _ => panic!("")
+        }
+    }
+
+    #[test]
+    fn drop_table() {
+        let mut parser = Parser::create(String::from(
+            "DROP TABLE personas;"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::DropTable(table_name) => assert_eq!(table_name, String::from("personas")),
+            _ => panic!("")
+        }
+    }
+
+    #[test]
+    fn create_named_index() {
+        let mut parser = Parser::create(String::from(
+            "CREATE INDEX idx_dinero ON personas(dinero);"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::CreateIndex(statement) => {
+                assert_eq!(statement.index_name, Some(String::from("idx_dinero")));
+                assert_eq!(statement.table_name, String::from("personas"));
+                assert_eq!(statement.columns_names, vec![String::from("dinero")]);
+                assert_eq!(statement.wait, true);
+            }
+            _ => panic!("")
+        }
+    }
+
+    #[test]
+    fn create_composite_index() {
+        let mut parser = Parser::create(String::from(
+            "CREATE INDEX ON personas(apellido, nombre);"
+        ));
+        let statement = parser.next_statement().unwrap().unwrap();
+
+        match statement {
+            Statement::CreateIndex(statement) => {
+                assert_eq!(statement.table_name, String::from("personas"));
+                assert_eq!(statement.columns_names, vec![String::from("apellido"), String::from("nombre")]);
+                assert_eq!(statement.index_name, None);
             }
             _ => panic!("")
         }
@@ -9,6 +9,12 @@ pub enum Token {
     Star, // "*"
     Minus, // "-"
     Slash, // "/"
+    Percent, // "%"
+    Ampersand, // "&"
+    Pipe, // "|"
+    Caret, // "^"
+    ShiftLeft, // "<<"
+    ShiftRight, // ">>"
     Less, // "<"
     Equal, // "="
     EqualEqual, // "=="
@@ -16,6 +22,7 @@ pub enum Token {
     GreaterEqual, // ">="
     LessEqual, // "<="
     NotEqual, // "!="
+    Placeholder, // "?"
     Semicolon,
 
     True, //15
@@ -37,19 +44,42 @@ pub enum Token {
     Set,
     Primary,
     Key,
+    Unique,
+    AutoIncrement, // "AUTOINCREMENT"
     StartTransaction, // "START_TRANSACTION"
     Rollback, // "ROLLBACK"
     Commit, // "COMMIT"
+    Savepoint, // "SAVEPOINT"
+    To,
     Database,
     Show,
     Tables,
     Databases,
     Describe,
+    Explain,
+    Exists,
     Index,
     Async,
+    As,
     On,
+    Group,
+    By,
+    Offset,
+    Drop,
+    Alter,
+    Add,
+    Column,
+    Default,
+    Distinct,
+    Join,
+    Inner,
+    Like,
+    In,
+    Between,
+    Is,
+    Not,
 
-    Identifier(String), //Ohter identifier, like table or column names
+    Identifier(String), //Ohter identifier, like table or column names. Columns can be qualified: <table>.<column>
     ColumnType(Type),
     String(String), // "some text"
     NumberI64(i64), // any number
@@ -5,7 +5,8 @@ use crate::sql::parser::token::Token;
 use crate::value::Type;
 
 pub struct Tokenizer {
-    string: String,
+    //Chars are stored separately, so that they can be accessed by index in O(1)
+    chars: Vec<char>,
     //This will point to the next character to steps before calling next_token()
     next: usize,
 
@@ -19,7 +20,11 @@ impl Tokenizer {
     pub fn create(
         string: String,
     ) -> Tokenizer {
-        Tokenizer { string, next: 0, current_line: 1, current_column_index: 0, last_token: None }
+        Tokenizer { chars: string.chars().collect(), next: 0, current_line: 1, current_column_index: 0, last_token: None }
+    }
+
+    pub fn has_last_token(&self) -> bool {
+        self.last_token.is_some()
     }
 
     pub fn last_token(&self) -> &Token {
@@ -53,8 +58,16 @@ impl Tokenizer {
             '-' => Ok(Token::Minus),
             '*' => Ok(Token::Star),
             '/' => Ok(Token::Slash),
+            '%' => Ok(Token::Percent),
+            '&' => Ok(Token::Ampersand),
+            '|' => Ok(Token::Pipe),
+            '^' => Ok(Token::Caret),
             ';' => Ok(Token::Semicolon),
-            '"' => self.string(),
+            '?' => Ok(Token::Placeholder),
+            '"' => self.string('"'),
+            '\'' => self.string('\''),
+            '>' if self.advance_if_next_char_eq('>') => Ok(Token::ShiftRight),
+            '<' if self.advance_if_next_char_eq('<') => Ok(Token::ShiftLeft),
             '>' => self.match_char_or('=', Token::GreaterEqual, Token::Greater),
             '<' => self.match_char_or('=', Token::LessEqual, Token::Less),
             '=' => self.match_char_or('=', Token::EqualEqual, Token::Equal),
@@ -63,18 +76,24 @@ impl Tokenizer {
         }
     }
 
-    fn string(&mut self) -> Result<Token, shared::SimpleDbError> {
+    //Strings can be quoted with " or '
+    fn string(&mut self, quote: char) -> Result<Token, shared::SimpleDbError> {
         let mut string = String::new();
 
-        while !self.end_reached() && self.current() != '"' {
+        while !self.end_reached() && self.current() != quote {
+            //Escaped chars: \<quote> and \\. Other backslashes are kept, so that they can be used in LIKE patterns
+            if self.current() == '\\' && matches!(self.chars.get(self.next + 1), Some(c) if *c == quote || *c == '\\') {
+                self.advance();
+            }
+
             string.push(self.advance());
         }
 
-        if self.end_reached() || self.current() != '"' {
-            return Err(IllegalToken(self.current_location(), String::from("Strings should end with '\"\'")));
+        if self.end_reached() || self.current() != quote {
+            return Err(IllegalToken(self.current_location(), format!("Strings should end with '{}'", quote)));
         }
 
-        //Get rid of "
+        //Get rid of the quote
         self.advance();
 
         Ok(Token::String(string))
@@ -87,6 +106,14 @@ impl Tokenizer {
                     Ok(Token::And)
                 } else if self.advance_if_next_string_eq("SYNC") {
                     Ok(Token::Async)
+                } else if self.advance_if_next_string_eq("LTER") {
+                    Ok(Token::Alter)
+                } else if self.advance_if_next_string_eq("DD") {
+                    Ok(Token::Add)
+                } else if self.advance_if_next_string_eq("UTOINCREMENT") {
+                    Ok(Token::AutoIncrement)
+                } else if self.advance_if_next_string_eq("S") {
+                    Ok(Token::As)
                 } else {
                     self.next -= 1;
                     Ok(self.other_identifier())
@@ -97,6 +124,10 @@ impl Tokenizer {
                     Ok(Token::ColumnType(Type::Boolean))
                 } else if self.advance_if_next_string_eq("LOB") {
                     Ok(Token::ColumnType(Type::Blob))
+                } else if self.advance_if_next_string_eq("Y") {
+                    Ok(Token::By)
+                } else if self.advance_if_next_string_eq("ETWEEN") {
+                    Ok(Token::Between)
                 } else {
                     //Adjust, so that next points to the first char of the indentifier
                     self.next -= 1;
@@ -104,9 +135,23 @@ impl Tokenizer {
                 }
             },
             'K' => self.match_string_or_other_identifier("EY", Token::Key, 1),
+            'E' => {
+                if self.advance_if_next_string_eq("XPLAIN") {
+                    Ok(Token::Explain)
+                } else if self.advance_if_next_string_eq("XISTS") {
+                    Ok(Token::Exists)
+                } else {
+                    self.next -= 1;
+                    Ok(self.other_identifier())
+                }
+            },
+            'G' => self.match_string_or_other_identifier("ROUP", Token::Group, 1),
+            'J' => self.match_string_or_other_identifier("OIN", Token::Join, 1),
             'P' => self.match_string_or_other_identifier("RIMARY", Token::Primary, 1),
             'O' => {
-                if self.advance_if_next_string_eq("R") {
+                if self.advance_if_next_string_eq("FFSET") {
+                    Ok(Token::Offset)
+                } else if self.advance_if_next_string_eq("R") {
                     Ok(Token::Or)
                 } else if self.advance_if_next_string_eq("N") {
                     Ok(Token::On)
@@ -115,7 +160,16 @@ impl Tokenizer {
                     Ok(self.other_identifier())
                 }
             },
-            'N' => self.match_string_or_other_identifier("ULL", Token::Null, 1),
+            'N' => {
+                if self.advance_if_next_string_eq("ULL") {
+                    Ok(Token::Null)
+                } else if self.advance_if_next_string_eq("OT") {
+                    Ok(Token::Not)
+                } else {
+                    self.next -= 1;
+                    Ok(self.other_identifier())
+                }
+            },
             'R' => {
                 if self.advance_if_next_string_eq("OLLBACK") {
                     Ok(Token::Rollback)
@@ -133,6 +187,8 @@ impl Tokenizer {
                     Ok(Token::StartTransaction)
                 } else if self.advance_if_next_string_eq("ET") {
                     Ok(Token::Set)
+                } else if self.advance_if_next_string_eq("AVEPOINT") {
+                    Ok(Token::Savepoint)
                 } else {
                     self.next -= 1;
                     Ok(self.other_identifier())
@@ -158,6 +214,8 @@ impl Tokenizer {
                     Ok(Token::Create)
                 } else if self.advance_if_next_string_eq("OMMIT") {
                     Ok(Token::Commit)
+                } else if self.advance_if_next_string_eq("OLUMN") {
+                    Ok(Token::Column)
                 } else {
                     self.next -= 1;
                     Ok(self.other_identifier())
@@ -170,15 +228,30 @@ impl Tokenizer {
                     Ok(Token::Table)
                 } else if self.advance_if_next_string_eq("RUE") {
                     Ok(Token::True)
+                } else if self.advance_if_next_string_eq("IMESTAMP") {
+                    Ok(Token::ColumnType(Type::Timestamp))
+                } else if self.advance_if_next_string_eq("O") {
+                    Ok(Token::To)
+                } else {
+                    self.next -= 1;
+                    Ok(self.other_identifier())
+                }
+            },
+            'L' => {
+                if self.advance_if_next_string_eq("IMIT") {
+                    Ok(Token::Limit)
+                } else if self.advance_if_next_string_eq("IKE") {
+                    Ok(Token::Like)
                 } else {
                     self.next -= 1;
                     Ok(self.other_identifier())
                 }
             },
-            'L' => self.match_string_or_other_identifier("IMIT", Token::Limit, 1),
             'U' => {
                 if self.advance_if_next_string_eq("PDATE") {
                     Ok(Token::Update)
+                } else if self.advance_if_next_string_eq("NIQUE") {
+                    Ok(Token::Unique)
                 } else if self.advance_if_next_string_eq("8") {
                     Ok(Token::ColumnType(Type::U8))
                 } else if self.advance_if_next_string_eq("16") {
@@ -203,6 +276,12 @@ impl Tokenizer {
                     Ok(Token::Delete)
                 } else if self.advance_if_next_string_eq("ATABASE"){
                     Ok(Token::Database)
+                } else if self.advance_if_next_string_eq("ROP"){
+                    Ok(Token::Drop)
+                } else if self.advance_if_next_string_eq("EFAULT"){
+                    Ok(Token::Default)
+                } else if self.advance_if_next_string_eq("ISTINCT"){
+                    Ok(Token::Distinct)
                 } else {
                     self.next -= 1;
                     Ok(self.other_identifier())
@@ -211,10 +290,16 @@ impl Tokenizer {
             'I' => {
                 if self.advance_if_next_string_eq("NSERT") {
                     Ok(Token::Insert)
+                } else if self.advance_if_next_string_eq("NNER") {
+                    Ok(Token::Inner)
                 } else if self.advance_if_next_string_eq("NDEX") {
                     Ok(Token::Index)
                 } else if self.advance_if_next_string_eq("NTO") {
                     Ok(Token::Into)
+                } else if self.advance_if_next_string_eq("N") {
+                    Ok(Token::In)
+                } else if self.advance_if_next_string_eq("S") {
+                    Ok(Token::Is)
                 } else if self.advance_if_next_string_eq("8") {
                     Ok(Token::ColumnType(Type::I8))
                 } else if self.advance_if_next_string_eq("16") {
@@ -234,6 +319,7 @@ impl Tokenizer {
                 } else if self.advance_if_next_string_eq("ALUES") {
                     Ok(Token::Values)
                 } else {
+                    self.next -= 1;
                     Ok(self.other_identifier())
                 }
             },
@@ -248,7 +334,7 @@ impl Tokenizer {
         let mut other_identifier = String::new();
         other_identifier.push(self.advance());
 
-        while !self.end_reached() && (self.is_alpha() || self.is_number()) {
+        while !self.end_reached() && (self.is_alpha() || self.is_number() || self.current() == '_' || self.current() == '.') {
             other_identifier.push(self.advance());
         }
 
@@ -275,7 +361,8 @@ impl Tokenizer {
             }
         }
 
-        let number_string = &self.string[start_number_index..self.next];
+        let number_string: String = self.chars[start_number_index..self.next].iter().collect();
+        let number_string = number_string.as_str();
 
         if has_decimals {
             match f64::from_str(number_string) {
@@ -296,7 +383,7 @@ impl Tokenizer {
                 return;
             }
 
-            match self.string.chars().nth(self.next).unwrap() {
+            match self.chars[self.next] {
                 ' ' | '\t' | '\r' => {
                     self.current_column_index += 1;
                     self.next += 1;
@@ -369,12 +456,18 @@ impl Tokenizer {
         let start_string_index = self.next;
         let end_string_index = start_string_index + string_to_match.len();
 
-        if end_string_index >= self.string.len() {
+        if end_string_index > self.chars.len() {
             return false;
         }
 
-        let string_to_be_checked = self.string[start_string_index..end_string_index].to_string();
-        string_to_be_checked.to_uppercase() == string_to_match
+        let string_to_be_checked: String = self.chars[start_string_index..end_string_index].iter().collect();
+        //Keywords must end where the word ends, so identifiers like "address" are not tokenized as ADD + "ress"
+        let is_word_end = match self.chars.get(end_string_index) {
+            Some(next_char) => !(next_char.is_ascii_alphanumeric() || *next_char == '_'),
+            None => true,
+        };
+
+        is_word_end && string_to_be_checked.to_uppercase() == string_to_match
     }
 
     fn advance_if_next_string_eq(
@@ -407,11 +500,11 @@ impl Tokenizer {
     }
 
     fn current(&self) -> char {
-        self.string.chars().nth(self.next).unwrap()
+        self.chars[self.next]
     }
 
     fn end_reached(&self) -> bool {
-        self.next >= self.string.len()
+        self.next >= self.chars.len()
     }
 
     pub fn current_location(&self) -> TokenLocation {
@@ -488,6 +581,29 @@ mod test {
         assert!(matches!(tokenizer.get_token().unwrap(), Token::EOF));
     }
 
+    #[test]
+    fn identifiers_starting_with_keywords() {
+        let mut tokenizer = Tokenizer::create(String::from(
+            "SELECT address FROM orders;"
+        ));
+
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Select);
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Identifier(String::from("address")));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::From);
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Identifier(String::from("orders")));
+        assert_eq!(tokenizer.get_token().unwrap(), Token::Semicolon);
+    }
+
+    #[test]
+    fn escaped_string() {
+        let mut tokenizer = Tokenizer::create(String::from(
+            r#""Say \"hi\" \\o/""#
+        ));
+
+        assert_eq!(tokenizer.get_token().unwrap(), Token::String(String::from(r#"Say "hi" \o/"#)));
+        assert!(matches!(tokenizer.get_token().unwrap(), Token::EOF));
+    }
+
     #[test]
     fn delete() {
         let mut tokenizer = Tokenizer::create(String::from(
@@ -0,0 +1,122 @@
+use crate::sql::expression::Expression;
+use crate::sql::plan::scan_type::RangeScan;
+use crate::sql::statement::Limit;
+use shared::KeyspaceId;
+
+//Returned by EXPLAIN. Describes how a SELECT, UPDATE or DELETE would be run, without running it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explain {
+    pub(crate) table_name: String,
+    pub(crate) scan: ExplainScan,
+    //Number of threads reading the table
+    pub(crate) parallelism: usize,
+    //WHERE expression, evaluated on every row returned by the scan
+    pub(crate) filter: Option<Expression>,
+    pub(crate) group_by: Option<Vec<String>>,
+    pub(crate) limit: Limit,
+    pub(crate) offset: usize,
+}
+
+//How rows are read from the storage
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExplainScan {
+    //Table keyspace ID
+    Full(KeyspaceId),
+    //Table keyspace ID, primary key
+    ExactPrimary(KeyspaceId, Expression),
+    //Index keyspace ID, indexed column name, secondary key
+    ExactSecondary(KeyspaceId, String, Expression),
+    //Index keyspace ID, indexed columns names, values of the first indexed columns
+    ExactComposite(KeyspaceId, Vec<String>, Vec<Expression>),
+    //Table keyspace ID, range of primary keys
+    Range(KeyspaceId, RangeScan),
+    //Index keyspace ID, range of indexed values
+    SecondaryRange(KeyspaceId, RangeScan),
+    Union(Box<ExplainScan>, Box<ExplainScan>),
+    Intersection(Box<ExplainScan>, Box<ExplainScan>),
+}
+
+impl Explain {
+    //One line per step, in the order in which rows go through them
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        self.scan.add_lines(&self.table_name, 0, &mut lines);
+        if self.parallelism > 1 {
+            lines.push(format!("Parallel scan with {} threads", self.parallelism));
+        }
+        if let Some(filter) = &self.filter {
+            lines.push(format!("Filter: {}", filter));
+        }
+        if let Some(group_by) = &self.group_by {
+            if group_by.is_empty() {
+                lines.push(String::from("Aggregate all rows"));
+            } else {
+                lines.push(format!("Group by: {}", group_by.join(", ")));
+            }
+        }
+        if let Limit::Some(limit) = self.limit {
+            lines.push(format!("Limit: {} Offset: {}", limit, self.offset));
+        } else if self.offset > 0 {
+            lines.push(format!("Offset: {}", self.offset));
+        }
+
+        lines
+    }
+
+    pub fn scan(&self) -> &ExplainScan {
+        &self.scan
+    }
+}
+
+impl ExplainScan {
+    fn add_lines(&self, table_name: &str, depth: usize, lines: &mut Vec<String>) {
+        let indentation = "  ".repeat(depth);
+
+        match self {
+            ExplainScan::Full(keyspace_id) => {
+                lines.push(format!("{}Full scan of table {} (keyspace {})", indentation, table_name, keyspace_id));
+            },
+            ExplainScan::ExactPrimary(keyspace_id, key) => {
+                lines.push(format!("{}Primary key lookup of table {} key = {} (keyspace {})", indentation, table_name, key, keyspace_id));
+            },
+            ExplainScan::ExactSecondary(keyspace_id, column_name, key) => {
+                lines.push(format!("{}Secondary index lookup {} = {} (keyspace {})", indentation, column_name, key, keyspace_id));
+            },
+            ExplainScan::ExactComposite(keyspace_id, columns_names, leading_values) => {
+                let leading_values: Vec<String> = leading_values.iter().map(|value| value.to_string()).collect();
+                lines.push(format!("{}Composite index lookup ({}) = ({}) (keyspace {})", indentation, columns_names[..leading_values.len()].join(", "),
+                                   leading_values.join(", "), keyspace_id));
+            },
+            ExplainScan::Range(keyspace_id, range) => {
+                lines.push(format!("{}Range scan of table {} {} (keyspace {})", indentation, table_name, range_to_string(range), keyspace_id));
+            },
+            ExplainScan::SecondaryRange(keyspace_id, range) => {
+                lines.push(format!("{}Secondary index range scan {} (keyspace {})", indentation, range_to_string(range), keyspace_id));
+            },
+            ExplainScan::Union(left, right) => {
+                lines.push(format!("{}Union of", indentation));
+                left.add_lines(table_name, depth + 1, lines);
+                right.add_lines(table_name, depth + 1, lines);
+            },
+            ExplainScan::Intersection(left, right) => {
+                lines.push(format!("{}Intersection of", indentation));
+                left.add_lines(table_name, depth + 1, lines);
+                right.add_lines(table_name, depth + 1, lines);
+            },
+        }
+    }
+}
+
+//Example: 10 <= id < 20
+fn range_to_string(range: &RangeScan) -> String {
+    let mut string = String::new();
+    if let Some(start) = &range.start {
+        string.push_str(&format!("{} {} ", start, if range.start_inclusive { "<=" } else { "<" }));
+    }
+    string.push_str(&range.column_name);
+    if let Some(end) = &range.end {
+        string.push_str(&format!(" {} {}", if range.end_inclusive { "<=" } else { "<" }, end));
+    }
+    string
+}
@@ -1,6 +1,7 @@
 pub mod planner;
 pub mod plan_step;
+pub mod explain;
 
 mod scan_type;
-mod steps;
+pub(crate) mod steps;
 mod scan_type_analyzer;
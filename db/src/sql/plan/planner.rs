@@ -1,28 +1,46 @@
 use crate::selection::Selection;
+use crate::sql::aggregate;
 use crate::sql::expression::Expression;
+use crate::sql::join;
+use crate::sql::plan::explain::{Explain, ExplainScan};
 use crate::sql::plan::plan_step::Plan;
 use crate::sql::plan::scan_type::ScanType;
 use crate::sql::plan::scan_type_analyzer::ScanTypeAnalyzer;
+use crate::sql::plan::steps::composite_scan_step::CompositeScanStep;
+use crate::sql::plan::steps::distinct_step::DistinctStep;
+use crate::sql::plan::steps::join_step::JoinStep;
+use crate::sql::plan::steps::correlated_filter_step::CorrelatedFilterStep;
 use crate::sql::plan::steps::filter_step::FilterStep;
 use crate::sql::plan::steps::full_scan_step::FullScanStep;
+use crate::sql::plan::steps::group_by_step::GroupByStep;
 use crate::sql::plan::steps::limit_step::LimitStep;
 use crate::sql::plan::steps::merge_intersection_scan_type::MergeIntersectionScanType;
 use crate::sql::plan::steps::merge_union_scan_step::MergeUnionScanStep;
+use crate::sql::plan::steps::parallel_scan_step::ParallelScanStep;
 use crate::sql::plan::steps::primary_exact_scan_step::PrimaryExactScanStep;
+use crate::sql::plan::steps::projection_step::ProjectionStep;
 use crate::sql::plan::steps::range_scan_step::RangeScanStep;
+use crate::sql::plan::steps::secondary_range_scan_step::SecondaryRangeScanStep;
 use crate::sql::plan::steps::secondary_scan_type::SecondaryExactScanType;
-use crate::sql::statement::{DeleteStatement, Limit, SelectStatement, UpdateStatement};
+use crate::sql::statement::{DeleteStatement, Join, Limit, SelectStatement, UpdateStatement};
 use crate::table::table::Table;
-use shared::SimpleDbError;
+use crate::ColumnDescriptor;
+use shared::{Collation, RowFormat, SimpleDbError, SimpleDbOptions};
 use std::sync::Arc;
 use storage::transactions::transaction::Transaction;
 
+#[derive(Clone)]
 pub struct Planner {
+    options: Arc<SimpleDbOptions>,
 }
 
 impl Planner {
-    pub fn create() -> Planner {
-        Planner {}
+    pub fn create(options: Arc<SimpleDbOptions>) -> Planner {
+        Planner { options }
+    }
+
+    pub fn collation(&self) -> Collation {
+        self.options.collation
     }
 
     pub fn plan_select(
@@ -31,20 +49,197 @@ impl Planner {
         select_statement: SelectStatement,
         transaction: &Transaction
     ) -> Result<Plan, SimpleDbError> {
+        if let Some(join) = select_statement.join.clone() {
+            return self.plan_join_select(table, join, select_statement, transaction);
+        }
+
         let scan_type = self.get_scan_type(
             &select_statement.where_expr,
             table,
         )?;
-        let mut last_step = self.build_scan_step(scan_type, transaction, select_statement.selection, table)?;
+        let is_aggregation = select_statement.is_aggregation();
+        //Aggregations don't contain expressions, so the projection is only used by one of the steps
+        let expression_projection = select_statement.has_expression_projection()
+            .then(|| select_statement.projection.clone());
+        let distinct_columns_desc = select_statement.distinct
+            .then(|| self.select_columns_desc(table, &select_statement))
+            .transpose()?;
+        let uses_parallel_scan = self.uses_parallel_scan(table, &scan_type, &select_statement);
+        let selection = match select_statement.selection {
+            //COUNT(*) without any other column, we still need to read something
+            Selection::Some(columns) if columns.is_empty() => Selection::Some(vec![table.get_primary_column_data().unwrap().column_name]),
+            selection => selection,
+        };
+        //Column chunked tables only read the selected columns, so the columns used by the filter need to be read too
+        let selection = match (selection, &select_statement.where_expr) {
+            //Correlated subqueries might reference any column of the row
+            (Selection::Some(_), Some(where_expr)) if where_expr.contains_exists() => Selection::All,
+            (Selection::Some(mut columns), Some(where_expr)) => {
+                where_expr.collect_identifiers(&mut columns);
+                Selection::Some(columns)
+            },
+            (selection, _) => selection,
+        };
+
+        let mut last_step = if uses_parallel_scan {
+            let split_keys = table.storage.get_split_keys(table.storage_keyspace_id, self.options.query_scan_parallelism)?;
+
+            if is_aggregation {
+                GroupByStep::create_parallel(table.clone(), select_statement.projection, select_statement.group_by,
+                                             selection, select_statement.where_expr, transaction, split_keys)?
+            } else {
+                ParallelScanStep::create(table.clone(), selection, select_statement.where_expr, transaction, split_keys)?
+            }
+        } else {
+            let mut last_step = self.build_scan_step(scan_type, transaction, selection, table)?;
+
+            if let Some(where_expr) = select_statement.where_expr {
+                last_step = self.filter_step(where_expr, table, transaction, last_step);
+            }
+            if is_aggregation {
+                last_step = GroupByStep::create(table.clone(), select_statement.projection, select_statement.group_by, last_step);
+            }
 
+            last_step
+        };
+
+        if let Some(expression_projection) = expression_projection {
+            let columns_desc = aggregate::columns_desc(table, &expression_projection);
+            last_step = ProjectionStep::create(table.clone(), expression_projection, columns_desc, self.options.collation, last_step);
+        }
+
+        Ok(self.result_steps(table, distinct_columns_desc, select_statement.limit, select_statement.offset, last_step))
+    }
+
+    //Expects the identifiers to have been qualified, see join::qualify_identifiers
+    fn plan_join_select(
+        &self,
+        table: &Arc<Table>,
+        join: Join,
+        select_statement: SelectStatement,
+        transaction: &Transaction,
+    ) -> Result<Plan, SimpleDbError> {
+        let join_table = table.database.get_table_or_err(&join.table_name)?;
+        let columns_desc = self.select_columns_desc(table, &select_statement)?;
+        //Conditions that only use columns of the outer table are used to scan it, so fewer rows are joined
+        let table_filter = select_statement.where_expr.as_ref()
+            .and_then(|where_expr| join::table_filter(where_expr, table));
+        let scan_type = self.get_scan_type(&table_filter, table)?;
+
+        let mut last_step = self.build_scan_step(scan_type, transaction, Selection::All, table)?;
+        if let Some(table_filter) = table_filter {
+            last_step = FilterStep::create(table_filter, self.options.collation, last_step);
+        }
+        last_step = JoinStep::create(table.clone(), join_table, join.on, self, transaction, last_step);
         if let Some(where_expr) = select_statement.where_expr {
-            last_step = FilterStep::create(where_expr, last_step);
+            last_step = FilterStep::create(where_expr, self.options.collation, last_step);
         }
-        if !matches!(select_statement.limit, Limit::None) {
-            last_step = LimitStep::create(select_statement.limit, last_step);
+        if !select_statement.projection.is_empty() {
+            last_step = ProjectionStep::create(table.clone(), select_statement.projection, columns_desc.clone(), self.options.collation, last_step);
         }
 
-        Ok(last_step)
+        let distinct_columns_desc = select_statement.distinct.then_some(columns_desc);
+        Ok(self.result_steps(table, distinct_columns_desc, select_statement.limit, select_statement.offset, last_step))
+    }
+
+    //DISTINCT, LIMIT and OFFSET are applied to the rows returned by the query
+    fn result_steps(
+        &self,
+        table: &Arc<Table>,
+        distinct_columns_desc: Option<Vec<ColumnDescriptor>>,
+        limit: Limit,
+        offset: usize,
+        mut last_step: Plan,
+    ) -> Plan {
+        if let Some(distinct_columns_desc) = distinct_columns_desc {
+            last_step = DistinctStep::create(table.clone(), distinct_columns_desc, self.options.clone(), last_step);
+        }
+        if !matches!(limit, Limit::None) || offset > 0 {
+            last_step = LimitStep::create(limit, offset, last_step);
+        }
+
+        last_step
+    }
+
+    //Columns returned by the select statement
+    pub fn select_columns_desc(
+        &self,
+        table: &Arc<Table>,
+        select_statement: &SelectStatement,
+    ) -> Result<Vec<ColumnDescriptor>, SimpleDbError> {
+        if let Some(join) = &select_statement.join {
+            let join_table = table.database.get_table_or_err(&join.table_name)?;
+            return Ok(join::columns_desc(table, &join_table, select_statement));
+        }
+        if select_statement.is_aggregation() || select_statement.has_expression_projection() {
+            return Ok(aggregate::columns_desc(table, &select_statement.projection));
+        }
+
+        match &select_statement.selection {
+            Selection::Some(columns) => Ok(columns.iter()
+                .map(|column_name| table.get_column_desc(column_name).unwrap())
+                .collect()),
+            Selection::All => Ok(table.get_columns().into_values().collect()),
+        }
+    }
+
+    pub fn explain_select(
+        &self,
+        table: &Arc<Table>,
+        select_statement: &SelectStatement,
+    ) -> Result<Explain, SimpleDbError> {
+        let scan_type = self.get_scan_type(&select_statement.where_expr, table)?;
+        let parallelism = if self.uses_parallel_scan(table, &scan_type, select_statement) {
+            self.options.query_scan_parallelism
+        } else {
+            1
+        };
+
+        Ok(Explain {
+            table_name: table.table_name.clone(),
+            scan: self.explain_scan(scan_type, table),
+            filter: select_statement.where_expr.clone(),
+            group_by: if select_statement.is_aggregation() { Some(select_statement.group_by.clone()) } else { None },
+            limit: select_statement.limit.clone(),
+            offset: select_statement.offset,
+            parallelism,
+        })
+    }
+
+    pub fn explain_update(
+        &self,
+        table: &Arc<Table>,
+        update_statement: &UpdateStatement,
+    ) -> Result<Explain, SimpleDbError> {
+        let scan_type = self.get_scan_type(&update_statement.where_expr, table)?;
+
+        Ok(Explain {
+            table_name: table.table_name.clone(),
+            scan: self.explain_scan(scan_type, table),
+            filter: update_statement.where_expr.clone(),
+            group_by: None,
+            limit: Limit::None,
+            offset: 0,
+            parallelism: 1,
+        })
+    }
+
+    pub fn explain_delete(
+        &self,
+        table: &Arc<Table>,
+        delete_statement: &DeleteStatement,
+    ) -> Result<Explain, SimpleDbError> {
+        let scan_type = self.get_scan_type(&delete_statement.where_expr, table)?;
+
+        Ok(Explain {
+            table_name: table.table_name.clone(),
+            scan: self.explain_scan(scan_type, table),
+            filter: delete_statement.where_expr.clone(),
+            group_by: None,
+            limit: delete_statement.limit.clone(),
+            offset: 0,
+            parallelism: 1,
+        })
     }
 
     pub fn plan_update(
@@ -57,11 +252,11 @@ impl Planner {
             &update_statement.where_expr,
             table
         )?;
-        let updated_values = update_statement.get_updated_values();
-        let mut last_step = self.build_scan_step(scan_type, transaction, updated_values, table)?;
+        //SET expressions & WHERE might reference any column of the row
+        let mut last_step = self.build_scan_step(scan_type, transaction, Selection::All, table)?;
 
         if let Some(where_expr) = &update_statement.where_expr {
-            last_step = FilterStep::create(where_expr.clone(), last_step);
+            last_step = self.filter_step(where_expr.clone(), table, transaction, last_step);
         }
 
         Ok(last_step)
@@ -80,10 +275,10 @@ impl Planner {
         let mut last_step = self.build_scan_step(scan_type, transaction, Selection::All, table)?;
 
         if let Some(where_expr) = select_statement.where_expr {
-            last_step = FilterStep::create(where_expr, last_step);
+            last_step = self.filter_step(where_expr, table, transaction, last_step);
         }
         if !matches!(select_statement.limit, Limit::None) {
-            last_step = LimitStep::create(select_statement.limit, last_step);
+            last_step = LimitStep::create(select_statement.limit, 0, last_step);
         }
 
         Ok(last_step)
@@ -100,12 +295,21 @@ impl Planner {
             ScanType::ExactSecondary(column, exact_id_expr) => {
                 SecondaryExactScanType::create(table.clone(), &column, exact_id_expr.serialize(), transaction, selection)
             },
+            ScanType::ExactComposite(columns_names, leading_values_expr) => {
+                let leading_values = leading_values_expr.iter()
+                    .map(|leading_value_expr| leading_value_expr.serialize())
+                    .collect();
+                CompositeScanStep::create(table.clone(), &columns_names, leading_values, transaction, selection)
+            },
             ScanType::ExactPrimary(exact_id_expr) => {
                 PrimaryExactScanStep::create(table.clone(), exact_id_expr.serialize(), selection, transaction)
             },
-            ScanType::Range(range) => {
+            ScanType::Range(range) if range.column_name == table.primary_column_name => {
                 RangeScanStep::create(table.clone(), selection, transaction, range)
             },
+            ScanType::Range(range) => {
+                SecondaryRangeScanStep::create(table.clone(), range, transaction, selection)
+            },
             ScanType::Full => {
                 FullScanStep::create(table.clone(), selection, transaction)
             },
@@ -122,6 +326,75 @@ impl Planner {
         }
     }
 
+    fn explain_scan(&self, scan_type: ScanType, table: &Arc<Table>) -> ExplainScan {
+        match scan_type {
+            ScanType::Full => ExplainScan::Full(table.storage_keyspace_id),
+            ScanType::ExactPrimary(key) => ExplainScan::ExactPrimary(table.storage_keyspace_id, key),
+            ScanType::ExactSecondary(column_name, key) => {
+                //Secondary scan types are only produced for indexed columns
+                let index_keyspace_id = table.get_column_desc(&column_name)
+                    .and_then(|column_desc| column_desc.secondary_index_keyspace_id)
+                    .unwrap();
+                ExplainScan::ExactSecondary(index_keyspace_id, column_name, key)
+            },
+            ScanType::ExactComposite(columns_names, leading_values) => {
+                //Composite scan types are only produced for indexed columns
+                let index_keyspace_id = table.get_composite_index_keyspace_id(&columns_names).unwrap();
+                ExplainScan::ExactComposite(index_keyspace_id, columns_names, leading_values)
+            },
+            ScanType::Range(range) if range.column_name == table.primary_column_name => {
+                ExplainScan::Range(table.storage_keyspace_id, range)
+            },
+            ScanType::Range(range) => {
+                let index_keyspace_id = table.get_column_desc(&range.column_name)
+                    .and_then(|column_desc| column_desc.secondary_index_keyspace_id)
+                    .unwrap();
+                ExplainScan::SecondaryRange(index_keyspace_id, range)
+            },
+            ScanType::MergeUnion(left, right) => ExplainScan::Union(
+                Box::new(self.explain_scan(*left, table)),
+                Box::new(self.explain_scan(*right, table)),
+            ),
+            ScanType::MergeIntersection(left, right) => ExplainScan::Intersection(
+                Box::new(self.explain_scan(*left, table)),
+                Box::new(self.explain_scan(*right, table)),
+            ),
+        }
+    }
+
+    fn filter_step(
+        &self,
+        where_expr: Expression,
+        table: &Arc<Table>,
+        transaction: &Transaction,
+        source: Plan,
+    ) -> Plan {
+        if where_expr.contains_exists() {
+            CorrelatedFilterStep::create(where_expr, self, table, transaction, source)
+        } else {
+            FilterStep::create(where_expr, self.options.collation, source)
+        }
+    }
+
+    fn uses_parallel_scan(
+        &self,
+        table: &Arc<Table>,
+        scan_type: &ScanType,
+        select_statement: &SelectStatement,
+    ) -> bool {
+        //Limits are applied after the rows have been returned, so parallel scans are only used if all rows will be read
+        let all_rows_read = select_statement.is_aggregation() ||
+            (matches!(select_statement.limit, Limit::None) && select_statement.offset == 0);
+        //Split keys are taken from the table keyspace, which are not primary keys in column chunked tables
+        let can_be_split = table.row_format == RowFormat::Row;
+        //Parallel scans evaluate the filter themselves, they cannot run correlated subqueries
+        let has_correlated_subqueries = select_statement.where_expr.as_ref()
+            .is_some_and(|where_expr| where_expr.contains_exists());
+
+        self.options.query_scan_parallelism > 1 && matches!(scan_type, ScanType::Full) && all_rows_read && can_be_split &&
+            !has_correlated_subqueries
+    }
+
     fn get_scan_type(
         &self,
         expression: &Option<Expression>,
@@ -131,7 +404,8 @@ impl Planner {
             Some(expression) => {
                 let scan_type_analyzer = ScanTypeAnalyzer::create(
                     table.clone(),
-                    expression.clone()
+                    expression.clone(),
+                    self.options.collation,
                 );
                 scan_type_analyzer.analyze()
             },
@@ -1,6 +1,8 @@
 use crate::sql::expression::Expression;
+use crate::value::Value;
 use bytes::Bytes;
 use shared::SimpleDbError;
+use std::ops::Bound;
 use SimpleDbError::MalformedQuery;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -12,6 +14,9 @@ pub enum ScanType {
     //String: Should
     //Expression: Should produce the literal value which will be the secondary key
     ExactSecondary(String, Expression),
+    //Strings: Columns of the composite index
+    //Expressions: Values of the first columns of the index
+    ExactComposite(Vec<String>, Vec<Expression>),
 
     MergeUnion(Box<ScanType>, Box<ScanType>),
     MergeIntersection(Box<ScanType>, Box<ScanType>),
@@ -103,6 +108,25 @@ impl RangeScan {
         Ok(())
     }
 
+    //Compares values instead of serialized keys, see Type::is_serialized_in_order
+    pub fn contains(&self, value: Value) -> Result<bool, SimpleDbError> {
+        let value = Expression::Literal(value);
+        if let Some(start) = self.start.as_ref() {
+            let is_above_start = if self.start_inclusive { value.greater_equal(start)? } else { value.greater(start)? };
+            if !is_above_start.get_boolean()? {
+                return Ok(false);
+            }
+        }
+        if let Some(end) = self.end.as_ref() {
+            let is_bellow_end = if self.end_inclusive { value.less_equal(end)? } else { value.less(end)? };
+            if !is_bellow_end.get_boolean()? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
     pub fn same_column(&self, other: &RangeScan) -> bool {
         self.column_name.eq(&other.column_name)
     }
@@ -128,6 +152,22 @@ impl RangeScan {
         RangeKeyPosition::Inside
     }
 
+    pub fn start_bound(&self) -> Bound<Bytes> {
+        Self::to_bound(self.start.as_ref(), self.start_inclusive)
+    }
+
+    pub fn end_bound(&self) -> Bound<Bytes> {
+        Self::to_bound(self.end.as_ref(), self.end_inclusive)
+    }
+
+    fn to_bound(key: Option<&Expression>, inclusive: bool) -> Bound<Bytes> {
+        match key {
+            Some(key) if inclusive => Bound::Included(key.serialize()),
+            Some(key) => Bound::Excluded(key.serialize()),
+            None => Bound::Unbounded,
+        }
+    }
+
     pub fn is_start_inclusive(&self) -> bool {
         self.start_inclusive
     }
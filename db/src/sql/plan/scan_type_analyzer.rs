@@ -1,13 +1,15 @@
-use crate::sql::expression::{BinaryOperator, Expression};
+use crate::sql::expression::{BinaryOperator, Expression, UnaryOperator};
 use crate::sql::plan::scan_type::{RangeScan, ScanType};
 use crate::table::table::Table;
-use shared::SimpleDbError;
+use crate::value::Value;
+use shared::{Collation, SimpleDbError};
 use shared::SimpleDbError::MalformedQuery;
 use std::sync::Arc;
 
 pub struct ScanTypeAnalyzer {
     table: Arc<Table>,
     expression: Expression,
+    collation: Collation,
 }
 
 impl ScanTypeAnalyzer {
@@ -15,23 +17,82 @@ impl ScanTypeAnalyzer {
     pub fn create(
         table: Arc<Table>,
         expression: Expression,
+        collation: Collation,
     ) -> ScanTypeAnalyzer {
         ScanTypeAnalyzer {
             expression,
+            collation,
             table,
         }
     }
 
     pub fn analyze(&self) -> Result<ScanType, SimpleDbError> {
+        let scan_type = self.analyze_expression()?;
+
+        //Composite indexes are only used for the whole expression, so their scans are never merged
+        match (self.get_composite_scan_type(), &scan_type) {
+            (None, _) |
+            (_, ScanType::ExactPrimary(_)) => Ok(scan_type),
+            (Some(ScanType::ExactComposite(_, values)), ScanType::ExactSecondary(_, _)) if values.len() == 1 => Ok(scan_type),
+            (Some(composite_scan_type), _) => Ok(composite_scan_type),
+        }
+    }
+
+    fn analyze_expression(&self) -> Result<ScanType, SimpleDbError> {
         match &self.expression {
             Expression::Binary(operator, left, right) => {
                 self.get_scan_type_binary_expr(*operator, &left, &right)
             },
+            Expression::Unary(UnaryOperator::Not, _) => Ok(ScanType::Full),
             Expression::Unary(_, _) => Err(MalformedQuery(String::from("Illegal unary expression"))),
+            Expression::In(expression, list) => self.get_scan_type_in_expr(expression, list),
+            Expression::Between(expression, low, high) => self.get_scan_type_between_expr(expression, low, high),
+            Expression::IsNull(_) |
+            Expression::IsNotNull(_) |
+            Expression::Exists(_) => Ok(ScanType::Full),
+            //Produced by WHERE TRUE or by an uncorrelated EXISTS. The filter will decide if the rows are returned
+            Expression::Literal(Value::Boolean(_)) => Ok(ScanType::Full),
             _ => Err(MalformedQuery(String::from("Illegal literal expression"))),
         }
     }
 
+    //Returns the composite index with the most leading columns compared with = in the expression
+    fn get_composite_scan_type(&self) -> Option<ScanType> {
+        let mut equalities = Vec::new();
+        self.collect_equalities(&self.expression, &mut equalities);
+
+        self.table.get_readable_composite_indexes().into_iter()
+            .map(|columns_names| {
+                let leading_values: Vec<Expression> = columns_names.iter()
+                    .map_while(|column_name| equalities.iter()
+                        .find(|(equality_column_name, _)| equality_column_name == column_name)
+                        .map(|(_, value)| value.clone()))
+                    .collect();
+                (columns_names, leading_values)
+            })
+            .filter(|(_, leading_values)| !leading_values.is_empty())
+            .max_by_key(|(_, leading_values)| leading_values.len())
+            .map(|(columns_names, leading_values)| ScanType::ExactComposite(columns_names, leading_values))
+    }
+
+    //Collects the <column> = <constant> expressions that have to be true for the whole expression to be true
+    fn collect_equalities(&self, expression: &Expression, equalities: &mut Vec<(String, Expression)>) {
+        match expression {
+            Expression::Binary(BinaryOperator::And, left, right) => {
+                self.collect_equalities(left, equalities);
+                self.collect_equalities(right, equalities);
+            },
+            Expression::Binary(BinaryOperator::Equal, left, right) => match (left.as_ref(), right.as_ref()) {
+                (Expression::Identifier(column_name), value) |
+                (value, Expression::Identifier(column_name)) if self.can_be_compared_with_keys(value) => {
+                    equalities.push((column_name.clone(), value.clone()));
+                },
+                _ => {},
+            },
+            _ => {},
+        }
+    }
+
     fn get_scan_type_binary_expr(
         &self,
         operator: BinaryOperator,
@@ -48,24 +109,42 @@ impl ScanTypeAnalyzer {
             BinaryOperator::Add |
             BinaryOperator::Subtract |
             BinaryOperator::Multiply |
-            BinaryOperator::Divide => {
+            BinaryOperator::Divide |
+            BinaryOperator::Modulo |
+            BinaryOperator::BitwiseAnd |
+            BinaryOperator::BitwiseOr |
+            BinaryOperator::BitwiseXor |
+            BinaryOperator::ShiftLeft |
+            BinaryOperator::ShiftRight => {
                 Ok(ScanType::Full)
             },
             BinaryOperator::Equal => {
-                if right.is_constant() && self.table.is_secondary_indexed(&left.get_identifier()?) {
-                    Ok(ScanType::ExactSecondary(left.get_identifier()?, *right.clone()))
-                } else if right.is_constant() && left.identifier_eq(&self.table.primary_column_name) {
-                    Ok(ScanType::ExactPrimary(*right.clone()))
+                //Equality is symmetric, 5 = dinero is scanned the same way as dinero = 5
+                let (column, value) = match (left.as_ref(), right.as_ref()) {
+                    (Expression::Identifier(_), _) => (left, right),
+                    (_, Expression::Identifier(_)) => (right, left),
+                    _ => return Ok(ScanType::Full),
+                };
+                let column_name = column.get_identifier()?;
+
+                if !self.can_be_compared_with_keys(value) {
+                    Ok(ScanType::Full)
+                } else if self.table.is_secondary_indexed(&column_name) {
+                    //is_secondary_indexed() returns false while the index is being created
+                    Ok(ScanType::ExactSecondary(column_name.clone(), self.cast_to_column_type(&column_name, value)?))
+                } else if column_name == self.table.primary_column_name {
+                    Ok(ScanType::ExactPrimary(self.cast_to_column_type(&column_name, value)?))
                 } else {
                     Ok(ScanType::Full)
                 }
             }
-            BinaryOperator::NotEqual => {
+            BinaryOperator::NotEqual |
+            BinaryOperator::Like => {
                 Ok(ScanType::Full)
             },
             BinaryOperator::GreaterEqual |
             BinaryOperator::Greater => {
-                if right.is_constant() && left.identifier_eq(&self.table.primary_column_name) {
+                if self.can_be_compared_with_keys(right) && self.can_be_range_scanned(left) {
                     Ok(ScanType::Range(RangeScan{
                         column_name: left.get_identifier()?,
                         start: Some(*right.clone()),
@@ -79,7 +158,7 @@ impl ScanTypeAnalyzer {
             },
             BinaryOperator::LessEqual |
             BinaryOperator::Less => {
-                if right.is_constant() && left.identifier_eq(&self.table.primary_column_name){
+                if self.can_be_compared_with_keys(right) && self.can_be_range_scanned(left) {
                     Ok(ScanType::Range(RangeScan{
                         column_name: left.get_identifier()?,
                         start: None,
@@ -94,6 +173,49 @@ impl ScanTypeAnalyzer {
         }
     }
 
+    //Same as <expression> = <item 1> OR <expression> = <item 2>...
+    fn get_scan_type_in_expr(
+        &self,
+        expression: &Expression,
+        list: &Vec<Expression>,
+    ) -> Result<ScanType, SimpleDbError> {
+        let mut scan_type: Option<ScanType> = None;
+        //Repeated values would be scanned twice
+        for (index, item) in list.iter().enumerate() {
+            if list[..index].contains(item) {
+                continue;
+            }
+            let item_scan_type = self.analyze_sub_expression(&Expression::Binary(
+                BinaryOperator::Equal, Box::new(expression.clone()), Box::new(item.clone())
+            ))?;
+            scan_type = match scan_type {
+                Some(scan_type) => Some(self.merge_scan_types(BinaryOperator::Or, scan_type, item_scan_type)?),
+                None => Some(item_scan_type),
+            };
+        }
+
+        Ok(scan_type.unwrap_or(ScanType::Full))
+    }
+
+    fn get_scan_type_between_expr(
+        &self,
+        expression: &Expression,
+        low: &Expression,
+        high: &Expression,
+    ) -> Result<ScanType, SimpleDbError> {
+        if self.can_be_compared_with_keys(low) && self.can_be_compared_with_keys(high) && self.can_be_range_scanned(expression) {
+            Ok(ScanType::Range(RangeScan{
+                column_name: expression.get_identifier()?,
+                start: Some(low.clone()),
+                start_inclusive: true,
+                end: Some(high.clone()),
+                end_inclusive: true,
+            }))
+        } else {
+            Ok(ScanType::Full)
+        }
+    }
+
     fn get_scan_type_logical_expr(
         &self,
         binary_operator: BinaryOperator,
@@ -153,6 +275,9 @@ impl ScanTypeAnalyzer {
         b: ScanType
     ) -> Result<ScanType, SimpleDbError> {
         match (&a, &b) {
+            //Not produced by sub expressions, see analyze()
+            (ScanType::ExactComposite(_, _), _) |
+            (_, ScanType::ExactComposite(_, _)) => Ok(ScanType::Full),
             //Full rules
             (ScanType::Full, ScanType::Full) => Ok(ScanType::Full),
             (ScanType::ExactPrimary(primary_expr), ScanType::Full) |
@@ -317,13 +442,40 @@ impl ScanTypeAnalyzer {
         }
     }
 
-    fn analyze_sub_expression(&self, expression: &Expression) -> Result<ScanType, SimpleDbError> {
+    //Ranges of the primary key are scanned in the table. Ranges of columns with a readable secondary index are scanned in the index
+    fn can_be_range_scanned(&self, column: &Expression) -> bool {
+        match column {
+            Expression::Identifier(column_name) => {
+                *column_name == self.table.primary_column_name || self.table.is_secondary_indexed(column_name)
+            },
+            _ => false,
+        }
+    }
+
+    //Keys are looked up by their serialized value, timestamps written as strings have to be converted first
+    fn cast_to_column_type(&self, column_name: &str, expression: &Expression) -> Result<Expression, SimpleDbError> {
+        match (expression, self.table.get_column_desc(column_name)) {
+            (Expression::Literal(value), Some(column_desc)) => Ok(Expression::Literal(value.clone().cast(&column_desc.column_type)?)),
+            (expression, _) => Ok(expression.clone()),
+        }
+    }
+
+    //Keys are sorted by their bytes, so strings compared with other collations can't be looked up by key
+    fn can_be_compared_with_keys(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Literal(Value::String(_)) => self.collation == Collation::Binary,
+            _ => expression.is_constant(),
+        }
+    }
+
+        fn analyze_sub_expression(&self, expression: &Expression) -> Result<ScanType, SimpleDbError> {
         let analyzer = ScanTypeAnalyzer::create(
             self.table.clone(),
             expression.clone(),
+            self.collation,
         );
 
-        analyzer.analyze()
+        analyzer.analyze_expression()
     }
 }
 
@@ -331,12 +483,13 @@ impl ScanTypeAnalyzer {
 mod test {
     use crate::sql::expression::Expression;
     use crate::sql::parser::parser::Parser;
-    use crate::sql::plan::scan_type::ScanType;
+    use crate::sql::plan::scan_type::{RangeScan, ScanType};
     use crate::sql::plan::scan_type_analyzer::ScanTypeAnalyzer;
     use crate::table::table::Table;
     use crate::value::Value;
     use crate::ColumnDescriptor;
     use crate::sql::plan::scan_type::ScanType::{ExactPrimary, ExactSecondary, MergeUnion, Range};
+    use shared::Collation;
 
     #[test]
     fn compound_2() {
@@ -348,7 +501,8 @@ mod test {
                 ColumnDescriptor::create_primary("id"),
                 ColumnDescriptor::create_secondary("dinero", 1),
             ]),
-            parse("(id == 1 OR dinero == 100) AND (id == 2 OR dinero == 200)")
+            parse("(id == 1 OR dinero == 100) AND (id == 2 OR dinero == 200)"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
 
@@ -364,6 +518,60 @@ mod test {
         ));
     }
 
+    #[test]
+    fn in_primary() {
+        let analyzer = ScanTypeAnalyzer::create(
+            Table::create_mock(vec![
+                ColumnDescriptor::create_primary("id"),
+            ]),
+            parse("id IN (1, 2, 1)"),
+            Collation::Binary,
+        );
+        let result = analyzer.analyze().unwrap();
+
+        assert_eq!(result, MergeUnion(
+            Box::new(ExactPrimary(Expression::Literal(Value::I64(1)))),
+            Box::new(ExactPrimary(Expression::Literal(Value::I64(2)))),
+        ));
+    }
+
+    #[test]
+    fn case_insensitive_collation() {
+        let table = Table::create_mock(vec![
+            ColumnDescriptor::create_primary("id"),
+        ]);
+        let analyze = |expression: &str, collation: Collation| {
+            ScanTypeAnalyzer::create(table.clone(), parse(expression), collation).analyze().unwrap()
+        };
+
+        assert_eq!(analyze("id = \"a\"", Collation::Binary), ExactPrimary(Expression::Literal(Value::String(String::from("a")))));
+        assert_eq!(analyze("id = \"a\"", Collation::CaseInsensitive), ScanType::Full);
+        assert_eq!(analyze("id > \"a\"", Collation::CaseInsensitive), ScanType::Full);
+        assert_eq!(analyze("id BETWEEN \"a\" AND \"b\"", Collation::CaseInsensitive), ScanType::Full);
+        //Non string keys are not affected by the collation
+        assert_eq!(analyze("id = 1", Collation::CaseInsensitive), ExactPrimary(Expression::Literal(Value::I64(1))));
+    }
+
+    #[test]
+    fn between_primary() {
+        let analyzer = ScanTypeAnalyzer::create(
+            Table::create_mock(vec![
+                ColumnDescriptor::create_primary("id"),
+            ]),
+            parse("id BETWEEN 1 AND 10"),
+            Collation::Binary,
+        );
+        let result = analyzer.analyze().unwrap();
+
+        assert_eq!(result, Range(RangeScan{
+            column_name: String::from("id"),
+            start: Some(Expression::Literal(Value::I64(1))),
+            start_inclusive: true,
+            end: Some(Expression::Literal(Value::I64(10))),
+            end_inclusive: true,
+        }));
+    }
+
     #[test]
     fn compound_1() {
         //primary AND range -> primary
@@ -375,7 +583,8 @@ mod test {
                 ColumnDescriptor::create_secondary("dinero", 1),
                 ColumnDescriptor::create("dinero", 2)
             ]),
-            parse("(id == 1 AND dinero > 100) OR (id == 2 OR credito > 200)")
+            parse("(id == 1 AND dinero > 100) OR (id == 2 OR credito > 200)"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
 
@@ -390,7 +599,8 @@ mod test {
                 ColumnDescriptor::create_primary("id"),
                 ColumnDescriptor::create_secondary("dinero", 1)
             ]),
-            parse("id == 1 OR dinero == 100")
+            parse("id == 1 OR dinero == 100"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
 
@@ -411,7 +621,8 @@ mod test {
                 ColumnDescriptor::create_primary("id"),
                 ColumnDescriptor::create_secondary("dinero", 1)
             ]),
-            parse("id == 1 AND dinero == 100")
+            parse("id == 1 AND dinero == 100"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
 
@@ -423,7 +634,8 @@ mod test {
     fn range_or_full_range() {
         let analyzer = ScanTypeAnalyzer::create(
             Table::create_mock(vec![ColumnDescriptor::create_primary("id"), ColumnDescriptor::create("dinero", 1)]),
-            parse("id >= 1 OR dinero < 100")
+            parse("id >= 1 OR dinero < 100"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
         assert_eq!(result, ScanType::Full);
@@ -434,7 +646,8 @@ mod test {
     fn range_and_full() {
         let analyzer = ScanTypeAnalyzer::create(
             Table::create_mock(vec![ColumnDescriptor::create_primary("id"), ColumnDescriptor::create("dinero", 1)]),
-            parse("id >= 1 AND dinero < 100")
+            parse("id >= 1 AND dinero < 100"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
 
@@ -451,7 +664,8 @@ mod test {
     fn range_or_full() {
         let analyzer = ScanTypeAnalyzer::create(
             Table::create_mock(vec![ColumnDescriptor::create_primary("id"), ColumnDescriptor::create("dinero", 1)]),
-            parse("id >= 1 OR dinero == 100")
+            parse("id >= 1 OR dinero == 100"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
 
@@ -463,7 +677,8 @@ mod test {
     fn range_and_full_2() {
         let analyzer = ScanTypeAnalyzer::create(
             Table::create_mock(vec![ColumnDescriptor::create_primary("id"), ColumnDescriptor::create("dinero", 1)]),
-            parse("id >= 1 AND dinero == 100")
+            parse("id >= 1 AND dinero == 100"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
 
@@ -478,7 +693,8 @@ mod test {
     fn exact_primary_and_full() {
         let analyzer = ScanTypeAnalyzer::create(
             Table::create_mock(vec![ColumnDescriptor::create_primary("id"), ColumnDescriptor::create("dinero", 1)]),
-            parse("id == 1 AND dinero == 100")
+            parse("id == 1 AND dinero == 100"),
+            Collation::Binary,
         );
         let result = analyzer.analyze().unwrap();
         let result = match result { ScanType::ExactPrimary(value) => value, _ => panic!("") };
@@ -491,7 +707,8 @@ mod test {
     fn exact_primary_or_full() {
         let analyzer = ScanTypeAnalyzer::create(
             Table::create_mock(vec![ColumnDescriptor::create_primary("id"), ColumnDescriptor::create("dinero", 1)]),
-            parse("id == 1 OR dinero == 100")
+            parse("id == 1 OR dinero == 100"),
+            Collation::Binary,
         );
 
         let result = analyzer.analyze().unwrap();
@@ -0,0 +1,62 @@
+use crate::index::secondary_index_iterator::SecondaryIndexIterator;
+use crate::selection::Selection;
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::table::table::Table;
+use crate::Row;
+use bytes::Bytes;
+use shared::SimpleDbError;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+use storage::SimpleDbStorageIterator;
+
+//Returns the rows whose values of the first columns of a composite index are equal to the leading values
+pub struct CompositeScanStep {
+    secondary_index_iterator: SecondaryIndexIterator<SimpleDbStorageIterator>,
+    transaction: Transaction,
+    selection: Selection,
+    table: Arc<Table>,
+}
+
+impl CompositeScanStep {
+    pub fn create(
+        table: Arc<Table>,
+        columns_names: &[String],
+        leading_values: Vec<Bytes>,
+        transaction: &Transaction,
+        selection: Selection
+    ) -> Result<Plan, SimpleDbError> {
+        let secondary_index_iterator = table.scan_composite_secondary_index(
+            transaction,
+            columns_names,
+            &leading_values,
+        )?;
+        table.n_secondary_index_scans.fetch_add(1, Ordering::Relaxed);
+
+        Ok(Box::new(CompositeScanStep {
+            transaction: transaction.clone(),
+            secondary_index_iterator,
+            selection,
+            table,
+        }))
+    }
+}
+
+impl PlanStep for CompositeScanStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        while let Some(primary_key) = self.secondary_index_iterator.next() {
+            let mut primary_key_iterator = self.table.scan_from_key(
+                primary_key.as_bytes(),
+                true,
+                &self.transaction,
+                &self.selection,
+            )?;
+
+            if primary_key_iterator.next() && primary_key_iterator.row().key_bytes == primary_key.as_bytes() {
+                return Ok(Some(primary_key_iterator.row().clone()));
+            }
+        }
+
+        Ok(None)
+    }
+}
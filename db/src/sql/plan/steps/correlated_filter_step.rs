@@ -0,0 +1,78 @@
+use crate::sql::expression::Expression;
+use crate::sql::expression_evaluator::{evaluate_constant_expressions, evaluate_where_expression};
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::sql::plan::planner::Planner;
+use crate::sql::statement::SelectStatement;
+use crate::table::table::Table;
+use crate::value::Value;
+use crate::Row;
+use shared::{Collation, SimpleDbError};
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+
+//Filter with correlated EXISTS subqueries. They are run for every row, after replacing the columns of the outer table
+//with the values of the row
+pub struct CorrelatedFilterStep {
+    filter_expression: Expression,
+    collation: Collation,
+    planner: Planner,
+    table: Arc<Table>,
+    transaction: Transaction,
+    source: Plan,
+}
+
+impl CorrelatedFilterStep {
+    pub fn create(
+        filter_expression: Expression,
+        planner: &Planner,
+        table: &Arc<Table>,
+        transaction: &Transaction,
+        source: Plan,
+    ) -> Plan {
+        Box::new(CorrelatedFilterStep {
+            collation: planner.collation(),
+            planner: planner.clone(),
+            transaction: transaction.clone(),
+            table: table.clone(),
+            filter_expression,
+            source,
+        })
+    }
+
+    fn exists(&self, row: &Row, mut subquery: SelectStatement) -> Result<bool, SimpleDbError> {
+        let outer_table_prefix = format!("{}.", self.table.table_name);
+
+        if let Some(where_expr) = subquery.where_expr.take() {
+            let where_expr = where_expr.map_leaves(&mut |leaf| match leaf {
+                Expression::Identifier(identifier) => match identifier.strip_prefix(&outer_table_prefix) {
+                    Some(column_name) => Ok(Expression::Literal(row.get_column_value(column_name)?)),
+                    None => Ok(Expression::Identifier(identifier)),
+                },
+                leaf => Ok(leaf),
+            })?;
+            //Outer columns are now literals, so the subquery can be planned with an index or a primary key scan
+            subquery.where_expr = Some(evaluate_constant_expressions(where_expr, self.collation)?);
+        }
+
+        let subquery_table = self.table.database.get_table_or_err(&subquery.table_name)?;
+        let mut subquery_plan = self.planner.plan_select(&subquery_table, subquery, &self.transaction)?;
+        Ok(subquery_plan.next()?.is_some())
+    }
+}
+
+impl PlanStep for CorrelatedFilterStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        while let Some(next_row) = self.source.next()? {
+            let filter_expression = self.filter_expression.clone().map_leaves(&mut |leaf| match leaf {
+                Expression::Exists(subquery) => Ok(Expression::Literal(Value::Boolean(self.exists(&next_row, *subquery)?))),
+                leaf => Ok(leaf),
+            })?;
+
+            if evaluate_where_expression(&next_row, &filter_expression, self.collation)? {
+                return Ok(Some(next_row));
+            }
+        }
+
+        Ok(None)
+    }
+}
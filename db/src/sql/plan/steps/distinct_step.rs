@@ -0,0 +1,187 @@
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::table::record::Record;
+use crate::table::table::Table;
+use crate::{ColumnDescriptor, Row};
+use bytes::{Buf, BufMut, Bytes};
+use shared::{SimpleDbError, SimpleDbFile, SimpleDbFileMode, SimpleDbOptions};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+const N_SPILL_PARTITIONS: usize = 16;
+
+//Used to give unique names to the spill files of queries running at the same time
+static NEXT_SPILL_ID: AtomicUsize = AtomicUsize::new(0);
+
+//Removes the rows whose projected columns have already been returned. Rows are deduplicated by the
+//serialized values of the projected columns. Until the seen values take more than memtable_max_size_bytes,
+//new rows are returned as soon as they are read. After that, rows not seen before are written to spill files,
+//partitioned by hash, and each partition is deduplicated in memory once the source has been consumed.
+pub struct DistinctStep {
+    columns_desc: Arc<Vec<ColumnDescriptor>>,
+    options: Arc<SimpleDbOptions>,
+    table: Arc<Table>,
+    source: Plan,
+    source_consumed: bool,
+
+    seen: HashSet<Vec<u8>>,
+    seen_size_bytes: usize,
+
+    //Empty until the seen values don't fit in memory
+    spill_files: Vec<SimpleDbFile>,
+    spill_id: usize,
+    //Deduplicated rows of the last read spill file
+    pending_rows: VecDeque<Row>,
+}
+
+impl DistinctStep {
+    pub fn create(
+        table: Arc<Table>,
+        columns_desc: Vec<ColumnDescriptor>,
+        options: Arc<SimpleDbOptions>,
+        source: Plan,
+    ) -> Plan {
+        Box::new(DistinctStep {
+            spill_id: NEXT_SPILL_ID.fetch_add(1, Ordering::Relaxed),
+            columns_desc: Arc::new(columns_desc),
+            pending_rows: VecDeque::new(),
+            spill_files: Vec::new(),
+            source_consumed: false,
+            seen: HashSet::new(),
+            seen_size_bytes: 0,
+            options,
+            source,
+            table,
+        })
+    }
+
+    //Null values are not included in the record, the column id tells which columns have a value
+    fn distinct_key(&self, row: &Row) -> Vec<u8> {
+        let mut record = Record::builder();
+        for column_desc in self.columns_desc.iter() {
+            if let Some(value) = row.storage_engine_record.get_value(column_desc.column_id) {
+                record.add_column(column_desc.column_id, value.clone());
+            }
+        }
+
+        record.build().serialize()
+    }
+
+    // Key length (u32) | Key bytes | Primary key length (u32) | Primary key bytes |
+    fn spill(&mut self, key: Vec<u8>, row: &Row) -> Result<(), SimpleDbError> {
+        if self.spill_files.is_empty() {
+            self.create_spill_files()?;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let partition = (hasher.finish() as usize) % N_SPILL_PARTITIONS;
+
+        let primary_key = row.get_primary_column_value();
+        let mut entry = Vec::with_capacity(key.len() + primary_key.len() + 8);
+        entry.put_u32_le(key.len() as u32);
+        entry.extend(key);
+        entry.put_u32_le(primary_key.len() as u32);
+        entry.extend(primary_key);
+
+        self.spill_files[partition].write(&entry)
+            .map_err(SimpleDbError::CannotWriteSpillFile)
+    }
+
+    fn create_spill_files(&mut self) -> Result<(), SimpleDbError> {
+        for partition in 0..N_SPILL_PARTITIONS {
+            let mut spill_file = SimpleDbFile::open_in(
+                &self.options.file_system,
+                self.spill_file_path(partition).as_path(),
+                SimpleDbFileMode::AppendOnly
+            ).map_err(SimpleDbError::CannotWriteSpillFile)?;
+            //Might be left by a previous crash
+            spill_file.clear().map_err(SimpleDbError::CannotWriteSpillFile)?;
+
+            self.spill_files.push(spill_file);
+        }
+
+        Ok(())
+    }
+
+    fn spill_file_path(&self, partition: usize) -> PathBuf {
+        PathBuf::from(&self.options.base_path)
+            .join(format!("distinct-{}-{}.spill", self.spill_id, partition))
+    }
+
+    //The rows in the file have not been returned before, and rows with the same key are always written to the same file
+    fn load_spill_file(&mut self, mut spill_file: SimpleDbFile) -> Result<(), SimpleDbError> {
+        let bytes = spill_file.read(0, spill_file.size())
+            .map_err(SimpleDbError::CannotReadSpillFile)?;
+        spill_file.delete()
+            .map_err(SimpleDbError::CannotWriteSpillFile)?;
+
+        let mut seen_in_file = HashSet::new();
+        let mut current_ptr = bytes.as_slice();
+
+        while current_ptr.has_remaining() {
+            let key_length = current_ptr.get_u32_le() as usize;
+            let key = current_ptr[..key_length].to_vec();
+            current_ptr.advance(key_length);
+            let primary_key_length = current_ptr.get_u32_le() as usize;
+            let primary_key = Bytes::copy_from_slice(&current_ptr[..primary_key_length]);
+            current_ptr.advance(primary_key_length);
+
+            if seen_in_file.insert(key.clone()) {
+                let record = Record::deserialize(key);
+                self.pending_rows.push_back(Row::create_derived(record, &self.columns_desc, &self.table, primary_key));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PlanStep for DistinctStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        loop {
+            if let Some(pending_row) = self.pending_rows.pop_front() {
+                return Ok(Some(pending_row));
+            }
+
+            if self.source_consumed {
+                match self.spill_files.pop() {
+                    Some(spill_file) => self.load_spill_file(spill_file)?,
+                    None => return Ok(None),
+                }
+                continue;
+            }
+
+            match self.source.next()? {
+                Some(row) => {
+                    let key = self.distinct_key(&row);
+                    if self.seen.contains(&key) {
+                        continue;
+                    }
+
+                    let fits_in_memory = self.seen_size_bytes + key.len() <= self.options.memtable_max_size_bytes;
+                    if self.spill_files.is_empty() && fits_in_memory {
+                        self.seen_size_bytes += key.len();
+                        self.seen.insert(key);
+                        return Ok(Some(row));
+                    }
+
+                    self.spill(key, &row)?;
+                },
+                None => self.source_consumed = true,
+            }
+        }
+    }
+}
+
+impl Drop for DistinctStep {
+    //The query might not be fully consumed
+    fn drop(&mut self) {
+        for spill_file in self.spill_files.iter_mut() {
+            let _ = spill_file.delete();
+        }
+    }
+}
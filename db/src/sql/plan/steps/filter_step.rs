@@ -2,20 +2,23 @@ use crate::sql::expression::Expression;
 use crate::sql::expression_evaluator::evaluate_where_expression;
 use crate::sql::plan::plan_step::{Plan, PlanStep};
 use crate::Row;
-use shared::SimpleDbError;
+use shared::{Collation, SimpleDbError};
 
 pub struct FilterStep {
     filter_expression: Expression,
+    collation: Collation,
     source: Plan,
 }
 
 impl FilterStep {
     pub fn create(
         filter_expression: Expression,
+        collation: Collation,
         source: Plan,
     ) -> Plan {
         Box::new(FilterStep {
             filter_expression,
+            collation,
             source
         })
     }
@@ -24,7 +27,7 @@ impl FilterStep {
 impl PlanStep for FilterStep {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
         while let Some(next_row) = self.source.next()? {
-            if evaluate_where_expression(&next_row, &self.filter_expression)? {
+            if evaluate_where_expression(&next_row, &self.filter_expression, self.collation)? {
                 return Ok(Some(next_row));
             }
         }
@@ -1,15 +1,18 @@
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use shared::SimpleDbError;
-use storage::SimpleDbStorageIterator;
+use crate::table::table_storage_iterator::TableStorageIterator;
 use storage::transactions::transaction::Transaction;
 use crate::{Row};
 use crate::selection::Selection;
 use crate::sql::plan::plan_step::{Plan, PlanStep};
 use crate::table::table::Table;
 use crate::table::table_iterator::TableIterator;
+use shared::iterators::storage_iterator::StorageIterator;
 
-pub struct FullScanStep {
-    iterator: TableIterator<SimpleDbStorageIterator>,
+//Rows are read from the storage engine one at a time, as they are pulled from the step
+pub struct FullScanStep<I: StorageIterator = TableStorageIterator> {
+    iterator: TableIterator<I>,
 }
 
 impl FullScanStep {
@@ -18,18 +21,23 @@ impl FullScanStep {
         selection: Selection,
         transaction: &Transaction
     ) -> Result<Plan, SimpleDbError> {
-        Ok(Box::new(FullScanStep {
-            iterator: table.scan_all(transaction, selection)?
-        }))
+        table.n_full_scans.fetch_add(1, Ordering::Relaxed);
+        Ok(Self::create_from_iterator(table.scan_all(transaction, selection)?))
     }
 }
 
-impl PlanStep for FullScanStep {
+impl<I: StorageIterator + 'static> FullScanStep<I> {
+    pub(crate) fn create_from_iterator(iterator: TableIterator<I>) -> Plan {
+        Box::new(FullScanStep { iterator })
+    }
+}
+
+impl<I: StorageIterator> PlanStep for FullScanStep<I> {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
         if self.iterator.next() {
             Ok(Some(self.iterator.row().clone()))
         } else {
-            Ok(None)
+            self.iterator.take_error().map_or(Ok(None), Err)
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,395 @@
+use crate::selection::Selection;
+use crate::sql::aggregate;
+use crate::sql::aggregate::Aggregator;
+use crate::sql::expression::Expression;
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::sql::plan::steps::parallel_scan_step::scan_chunks;
+use crate::sql::statement::Projection;
+use crate::table::record::Record;
+use crate::table::table::Table;
+use crate::value::Value;
+use crate::{ColumnDescriptor, Row};
+use bytes::Bytes;
+use shared::{ColumnId, SimpleDbError};
+use std::collections::HashMap;
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+
+//Groups all the rows returned by source by the values of group_by columns, and applies the aggregate
+//functions of the projection to each group. If group_by is empty, all rows will belong to the same group.
+//Source will be fully consumed in the first call to next()
+pub struct GroupByStep {
+    //None if the rows have already been grouped, see GroupByStep::create_parallel()
+    source: Option<Plan>,
+    groups: Option<Groups>,
+    columns_desc: Arc<Vec<ColumnDescriptor>>,
+    table: Arc<Table>,
+
+    grouped_rows: Option<std::vec::IntoIter<Row>>,
+}
+
+//Partial results of the aggregation. Groups are stored in insertion order, so that the returned rows are deterministic
+struct Groups {
+    projection: Vec<Projection>,
+    group_by: Vec<String>,
+
+    groups: Vec<Group>,
+    group_index_by_key: HashMap<Vec<Option<Bytes>>, usize>,
+}
+
+struct Group {
+    //Serialized values of the group_by columns. None if the value is null
+    key: Vec<Option<Bytes>>,
+    //Values of the group_by columns
+    group_values: HashMap<String, Value>,
+    //Same order as projection. None if the projection is a column
+    aggregators: Vec<Option<Aggregator>>,
+}
+
+impl GroupByStep {
+    pub fn create(
+        table: Arc<Table>,
+        projection: Vec<Projection>,
+        group_by: Vec<String>,
+        source: Plan,
+    ) -> Plan {
+        Box::new(GroupByStep {
+            columns_desc: Arc::new(aggregate::columns_desc(&table, &projection)),
+            groups: Some(Groups::create(projection, group_by)),
+            source: Some(source),
+            grouped_rows: None,
+            table,
+        })
+    }
+
+    //Scans the table in key ranges defined by split_keys concurrently. Each key range is aggregated independently,
+    //and the partial results are merged at the end
+    pub fn create_parallel(
+        table: Arc<Table>,
+        projection: Vec<Projection>,
+        group_by: Vec<String>,
+        selection: Selection,
+        where_expr: Option<Expression>,
+        transaction: &Transaction,
+        split_keys: Vec<Bytes>,
+    ) -> Result<Plan, SimpleDbError> {
+        let groups_by_chunk = scan_chunks(&table, &selection, &where_expr, transaction, split_keys, |mut chunk| {
+            let mut groups = Groups::create(projection.clone(), group_by.clone());
+            while let Some(row) = chunk.next()? {
+                groups.add(&row)?;
+            }
+            Ok(groups)
+        })?;
+
+        let mut groups = Groups::create(projection.clone(), group_by);
+        for groups_chunk in groups_by_chunk {
+            groups.merge(groups_chunk)?;
+        }
+
+        Ok(Box::new(GroupByStep {
+            columns_desc: Arc::new(aggregate::columns_desc(&table, &projection)),
+            groups: Some(groups),
+            grouped_rows: None,
+            source: None,
+            table,
+        }))
+    }
+
+    fn group_rows(&mut self) -> Result<Vec<Row>, SimpleDbError> {
+        let mut groups = self.groups.take().unwrap();
+
+        if let Some(mut source) = self.source.take() {
+            while let Some(row) = source.next()? {
+                groups.add(&row)?;
+            }
+        }
+
+        groups.into_rows(&self.columns_desc, &self.table)
+    }
+}
+
+impl Groups {
+    fn create(projection: Vec<Projection>, group_by: Vec<String>) -> Groups {
+        Groups {
+            group_index_by_key: HashMap::new(),
+            groups: Vec::new(),
+            projection,
+            group_by,
+        }
+    }
+
+    fn add(&mut self, row: &Row) -> Result<(), SimpleDbError> {
+        let mut key = Vec::new();
+        let mut group_values = HashMap::new();
+        for group_by_column in &self.group_by {
+            let value = row.get_column_value(group_by_column)?;
+            key.push(if value.is_null() { None } else { Some(value.serialize()) });
+            group_values.insert(group_by_column.clone(), value);
+        }
+
+        let group_index = self.get_or_create_group(key, group_values);
+        let group = &mut self.groups[group_index];
+        for (projection, aggregator) in self.projection.iter().zip(group.aggregators.iter_mut()) {
+            if let (Projection::Aggregate(_, column_name), Some(aggregator)) = (projection, aggregator) {
+                aggregator.aggregate(match column_name {
+                    Some(column_name) => row.get_column_value(column_name)?,
+                    None => Value::Boolean(true), //COUNT(*) counts every row
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Groups) -> Result<(), SimpleDbError> {
+        for other_group in other.groups {
+            let group_index = self.get_or_create_group(other_group.key, other_group.group_values);
+            let group = &mut self.groups[group_index];
+
+            for (aggregator, other_aggregator) in group.aggregators.iter_mut().zip(other_group.aggregators.iter()) {
+                if let (Some(aggregator), Some(other_aggregator)) = (aggregator, other_aggregator) {
+                    aggregator.merge(other_aggregator)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_or_create_group(&mut self, key: Vec<Option<Bytes>>, group_values: HashMap<String, Value>) -> usize {
+        if let Some(group_index) = self.group_index_by_key.get(&key) {
+            return *group_index;
+        }
+
+        self.groups.push(self.create_group(key.clone(), group_values));
+        self.group_index_by_key.insert(key, self.groups.len() - 1);
+        self.groups.len() - 1
+    }
+
+    fn create_group(&self, key: Vec<Option<Bytes>>, group_values: HashMap<String, Value>) -> Group {
+        Group {
+            aggregators: self.projection.iter()
+                .map(|projection| match projection {
+                    Projection::Aggregate(function, _) => Some(Aggregator::create(*function)),
+                    Projection::Column(_) |
+                    Projection::Expression(_, _) => None,
+                })
+                .collect(),
+            group_values,
+            key,
+        }
+    }
+
+    fn into_rows(mut self, columns_desc: &Arc<Vec<ColumnDescriptor>>, table: &Arc<Table>) -> Result<Vec<Row>, SimpleDbError> {
+        //Aggregations without GROUP BY always return one row, even if there are no rows
+        if self.groups.is_empty() && self.group_by.is_empty() {
+            self.get_or_create_group(Vec::new(), HashMap::new());
+        }
+
+        let mut rows = Vec::new();
+        for (group_index, group) in self.groups.iter().enumerate() {
+            rows.push(self.group_to_row(group_index, group, columns_desc, table)?);
+        }
+
+        Ok(rows)
+    }
+
+    fn group_to_row(
+        &self,
+        group_index: usize,
+        group: &Group,
+        columns_desc: &Arc<Vec<ColumnDescriptor>>,
+        table: &Arc<Table>
+    ) -> Result<Row, SimpleDbError> {
+        let mut record = Record::builder();
+
+        for (index, projection) in self.projection.iter().enumerate() {
+            let value = match (projection, &group.aggregators[index]) {
+                (_, Some(aggregator)) => aggregator.result()?,
+                (Projection::Column(column_name), None) => group.group_values.get(column_name)
+                    .cloned()
+                    .unwrap_or(Value::Null),
+                _ => Value::Null,
+            };
+
+            if !value.is_null() {
+                record.add_column(index as ColumnId, value.serialize());
+            }
+        }
+
+        Ok(Row::create_derived(
+            record.build(),
+            columns_desc,
+            table,
+            Bytes::copy_from_slice(&group_index.to_le_bytes())
+        ))
+    }
+}
+
+impl PlanStep for GroupByStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        if self.grouped_rows.is_none() {
+            self.grouped_rows = Some(self.group_rows()?.into_iter());
+        }
+
+        Ok(self.grouped_rows.as_mut().unwrap().next())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::aggregate::AggregateFunction;
+    use crate::sql::plan::plan_step::{Plan, PlanStep};
+    use crate::selection::Selection;
+    use crate::simple_db::Context;
+    use crate::sql::plan::steps::full_scan_step::FullScanStep;
+    use crate::sql::plan::steps::group_by_step::GroupByStep;
+    use crate::sql::statement::Projection;
+    use crate::table::record::Record;
+    use crate::table::table::Table;
+    use crate::value::{Type, Value};
+    use crate::simple_db::StatementResult;
+    use crate::{ColumnDescriptor, Row, SimpleDb};
+    use bytes::Bytes;
+    use shared::SimpleDbError;
+    use std::sync::Arc;
+
+    struct MockPlan {
+        rows: Vec<Row>,
+    }
+
+    impl PlanStep for MockPlan {
+        fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+            if self.rows.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(self.rows.remove(0)))
+            }
+        }
+    }
+
+    //SELECT region, SUM(sales), COUNT(*) FROM ventas GROUP BY region
+    #[test]
+    fn group_by_sum() {
+        let table = ventas_table();
+        let source: Plan = Box::new(MockPlan { rows: vec![
+            ventas_row(&table, 1, "Madrid", 10),
+            ventas_row(&table, 2, "Barcelona", 5),
+            ventas_row(&table, 3, "Madrid", 20),
+            ventas_row(&table, 4, "Sevilla", 1),
+            ventas_row(&table, 5, "Barcelona", 7),
+        ]});
+
+        let mut step = GroupByStep::create(
+            table,
+            vec![
+                Projection::Column(String::from("region")),
+                Projection::Aggregate(AggregateFunction::Sum, Some(String::from("sales"))),
+                Projection::Aggregate(AggregateFunction::Count, None),
+            ],
+            vec![String::from("region")],
+            source
+        );
+
+        assert_group(step.next().unwrap().unwrap(), "Madrid", 30, 2);
+        assert_group(step.next().unwrap().unwrap(), "Barcelona", 12, 2);
+        assert_group(step.next().unwrap().unwrap(), "Sevilla", 1, 1);
+        assert!(step.next().unwrap().is_none());
+    }
+
+    //SELECT COUNT(*) FROM personas, scanned in 4 key ranges concurrently
+    #[test]
+    fn parallel_count() {
+        let path = std::env::temp_dir().join("simpledb_group_by_step_parallel_count");
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .base_path(path.to_str().unwrap())
+            .build_arc();
+        shared::logger::Logger::init(options.clone());
+        let simple_db = crate::simple_db::create(options).unwrap();
+
+        let mut context = Context::empty();
+        execute(&simple_db, &context, "CREATE DATABASE test;");
+        context.with_database("test");
+        let transaction = execute_statement(&simple_db, &context, "START_TRANSACTION;").get_transaction();
+        context.with_transaction(transaction);
+        execute(&simple_db, &context, "CREATE TABLE personas (id I64 PRIMARY KEY, dinero I64);");
+        for id in 0..100 {
+            execute(&simple_db, &context, &format!("INSERT INTO personas (id, dinero) VALUES ({}, {});", id, id));
+        }
+
+        let table = simple_db.get_databases().get_database_or_err(&String::from("test")).unwrap()
+            .get_table_or_err("personas").unwrap();
+        let selection = Selection::Some(vec![String::from("id")]);
+        let count_projection = vec![Projection::Aggregate(AggregateFunction::Count, None)];
+
+        //Use keys of the table to split it
+        let mut split_keys = Vec::new();
+        let mut full_scan = FullScanStep::create(table.clone(), selection.clone(), context.transaction()).unwrap();
+        let mut n_row = 0;
+        while let Some(row) = full_scan.next().unwrap() {
+            if n_row > 0 && n_row % 25 == 0 {
+                split_keys.push(row.get_primary_column_value().clone());
+            }
+            n_row += 1;
+        }
+
+        let mut serial_count = GroupByStep::create(
+            table.clone(),
+            count_projection.clone(),
+            Vec::new(),
+            FullScanStep::create(table.clone(), selection.clone(), context.transaction()).unwrap()
+        );
+        let mut parallel_count = GroupByStep::create_parallel(
+            table.clone(),
+            count_projection.clone(),
+            Vec::new(),
+            selection,
+            None,
+            context.transaction(),
+            split_keys
+        ).unwrap();
+
+        let serial_count = serial_count.next().unwrap().unwrap().get_column_value("COUNT(*)").unwrap();
+        let parallel_count = parallel_count.next().unwrap().unwrap().get_column_value("COUNT(*)").unwrap();
+        assert_eq!(serial_count, Value::I64(100));
+        assert_eq!(parallel_count, serial_count);
+
+        let _ = std::fs::remove_dir_all(&path);
+    }
+
+    fn execute(simple_db: &SimpleDb, context: &Context, query: &str) {
+        execute_statement(simple_db, context, query);
+    }
+
+    fn execute_statement(simple_db: &SimpleDb, context: &Context, query: &str) -> StatementResult {
+        let statement = simple_db.parse(query).unwrap();
+        match simple_db.execute(context, statement) {
+            Ok(result) => result,
+            Err(_) => panic!("Cannot execute {}", query),
+        }
+    }
+
+    fn assert_group(row: Row, expected_region: &str, expected_sum: i64, expected_count: i64) {
+        assert_eq!(row.get_column_value("region").unwrap(), Value::String(String::from(expected_region)));
+        assert_eq!(row.get_column_value("SUM(sales)").unwrap(), Value::I64(expected_sum));
+        assert_eq!(row.get_column_value("COUNT(*)").unwrap(), Value::I64(expected_count));
+    }
+
+    fn ventas_row(table: &Arc<Table>, id: i64, region: &str, sales: i64) -> Row {
+        let mut record = Record::builder();
+        record.add_column(1, Bytes::copy_from_slice(&id.to_le_bytes()));
+        record.add_column(2, Bytes::copy_from_slice(region.as_bytes()));
+        record.add_column(3, Bytes::copy_from_slice(&sales.to_le_bytes()));
+        Row::create(record.build(), table, Bytes::copy_from_slice(&id.to_le_bytes()))
+    }
+
+    fn ventas_table() -> Arc<Table> {
+        Table::create_mock(vec![
+            ColumnDescriptor{column_id: 1, column_type: Type::I64, column_name: String::from("id"), is_primary: true, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+            ColumnDescriptor{column_id: 2, column_type: Type::String, column_name: String::from("region"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+            ColumnDescriptor{column_id: 3, column_type: Type::I64, column_name: String::from("sales"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+        ])
+    }
+}
@@ -0,0 +1,124 @@
+use crate::selection::Selection;
+use crate::sql::expression::Expression;
+use crate::sql::expression_evaluator::evaluate_constant_expressions;
+use crate::sql::join;
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::sql::plan::planner::Planner;
+use crate::sql::statement::{Limit, SelectStatement};
+use crate::table::record::Record;
+use crate::table::table::Table;
+use crate::{ColumnDescriptor, Row};
+use shared::{Collation, ColumnId, SimpleDbError};
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+
+//Nested loop inner join. For every row of source, the ON condition is planned as a query on join_table after replacing
+//the columns of table with the values of the row, so the matching rows are found with an index when possible
+pub struct JoinStep {
+    on: Expression,
+    columns_desc: Arc<Vec<ColumnDescriptor>>,
+    //Column IDs of both tables, in the same order as columns_desc
+    table_columns_id: Vec<ColumnId>,
+    join_table_columns_id: Vec<ColumnId>,
+
+    collation: Collation,
+    planner: Planner,
+    table: Arc<Table>,
+    join_table: Arc<Table>,
+    transaction: Transaction,
+    source: Plan,
+
+    //Row of source being joined, and the plan that returns its matching rows of join_table
+    current: Option<(Row, Plan)>,
+}
+
+impl JoinStep {
+    pub fn create(
+        table: Arc<Table>,
+        join_table: Arc<Table>,
+        on: Expression,
+        planner: &Planner,
+        transaction: &Transaction,
+        source: Plan,
+    ) -> Plan {
+        Box::new(JoinStep {
+            columns_desc: Arc::new(join::joined_columns_desc(&table, &join_table)),
+            table_columns_id: columns_id(&table),
+            join_table_columns_id: columns_id(&join_table),
+            collation: planner.collation(),
+            transaction: transaction.clone(),
+            planner: planner.clone(),
+            current: None,
+            join_table,
+            source,
+            table,
+            on,
+        })
+    }
+
+    fn plan_matching_rows(&self, row: &Row) -> Result<Plan, SimpleDbError> {
+        let table_prefix = format!("{}.", self.table.table_name);
+        let on = self.on.clone().map_leaves(&mut |leaf| match leaf {
+            Expression::Identifier(identifier) => match identifier.strip_prefix(&table_prefix) {
+                Some(column_name) => Ok(Expression::Literal(row.get_column_value(column_name)?)),
+                None => Ok(Expression::Identifier(identifier)),
+            },
+            leaf => Ok(leaf),
+        })?;
+        let on = evaluate_constant_expressions(on.unqualify_identifiers(&self.join_table.table_name), self.collation)?;
+
+        self.planner.plan_select(&self.join_table, SelectStatement {
+            table_name: self.join_table.table_name.clone(),
+            where_expr: Some(on),
+            selection: Selection::All,
+            projection: Vec::new(),
+            group_by: Vec::new(),
+            limit: Limit::None,
+            offset: 0,
+            distinct: false,
+            join: None,
+        }, &self.transaction)
+    }
+
+    fn join_rows(&self, row: &Row, join_row: &Row) -> Row {
+        let mut record = Record::builder();
+        let joined_columns_id = self.table_columns_id.iter().map(|column_id| (row, column_id))
+            .chain(self.join_table_columns_id.iter().map(|column_id| (join_row, column_id)));
+
+        for (joined_column_id, (row, column_id)) in joined_columns_id.enumerate() {
+            if let Some(value) = row.storage_engine_record.get_value(*column_id) {
+                record.add_column(joined_column_id as ColumnId, value.clone());
+            }
+        }
+
+        Row::create_derived(record.build(), &self.columns_desc, &self.table, row.get_primary_column_value().clone())
+    }
+}
+
+impl PlanStep for JoinStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        loop {
+            if let Some((row, mut matching_rows)) = self.current.take() {
+                if let Some(join_row) = matching_rows.next()? {
+                    let joined_row = self.join_rows(&row, &join_row);
+                    self.current = Some((row, matching_rows));
+                    return Ok(Some(joined_row));
+                }
+            }
+
+            match self.source.next()? {
+                Some(row) => {
+                    let matching_rows = self.plan_matching_rows(&row)?;
+                    self.current = Some((row, matching_rows));
+                },
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+fn columns_id(table: &Arc<Table>) -> Vec<ColumnId> {
+    join::sorted_columns_desc(table).iter()
+        .map(|column_desc| column_desc.column_id)
+        .collect()
+}
@@ -3,28 +3,49 @@ use crate::Row;
 use crate::sql::plan::plan_step::{Plan, PlanStep};
 use crate::sql::statement::Limit;
 
+//Rows are pulled lazily from source, once the limit is reached, source won't be called again
 pub struct LimitStep {
     limit: Limit,
+    offset: usize,
     source: Plan,
 
-    count: usize
+    count: usize,
+    offset_skipped: bool,
 }
 
 impl LimitStep {
     pub fn create(
         limit: Limit,
+        offset: usize,
         source: Plan
     ) -> Plan {
         Box::new(LimitStep {
+            offset_skipped: false,
             count: 0,
             source,
+            offset,
             limit,
         })
     }
+
+    fn skip_offset(&mut self) -> Result<(), SimpleDbError> {
+        for _ in 0..self.offset {
+            if self.source.next()?.is_none() {
+                break;
+            }
+        }
+
+        self.offset_skipped = true;
+        Ok(())
+    }
 }
 
 impl PlanStep for LimitStep {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        if !self.offset_skipped {
+            self.skip_offset()?;
+        }
+
         match self.limit {
             Limit::Some(limit) => {
                 if (self.count + 1) > limit {
@@ -39,4 +60,83 @@ impl PlanStep for LimitStep {
             Limit::None => self.source.next(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::plan::plan_step::{Plan, PlanStep};
+    use crate::sql::plan::steps::limit_step::LimitStep;
+    use crate::sql::statement::Limit;
+    use crate::table::record::Record;
+    use crate::table::table::Table;
+    use crate::value::Type;
+    use crate::{ColumnDescriptor, Row};
+    use bytes::Bytes;
+    use shared::SimpleDbError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct MockPlan {
+        table: Arc<Table>,
+        n_rows: usize,
+        //Number of rows pulled from the plan
+        n_pulled: Arc<AtomicUsize>,
+    }
+
+    impl PlanStep for MockPlan {
+        fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+            let current = self.n_pulled.load(Ordering::Relaxed);
+            if current >= self.n_rows {
+                return Ok(None);
+            }
+
+            self.n_pulled.fetch_add(1, Ordering::Relaxed);
+            let id = Bytes::copy_from_slice(&(current as i64).to_le_bytes());
+            Ok(Some(Row::create(Record::create(vec![(1, id.clone())]), &self.table, id)))
+        }
+    }
+
+    #[test]
+    fn limit() {
+        let (mut step, n_pulled) = limit_step(Limit::Some(3), 0, 100);
+
+        assert_ids(&mut step, vec![0, 1, 2]);
+        assert_eq!(n_pulled.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn limit_offset() {
+        let (mut step, n_pulled) = limit_step(Limit::Some(3), 5, 100);
+
+        assert_ids(&mut step, vec![5, 6, 7]);
+        assert_eq!(n_pulled.load(Ordering::Relaxed), 8);
+    }
+
+    #[test]
+    fn offset_greater_than_n_rows() {
+        let (mut step, _) = limit_step(Limit::Some(3), 10, 5);
+
+        assert_ids(&mut step, vec![]);
+    }
+
+    fn assert_ids(step: &mut Plan, expected_ids: Vec<i64>) {
+        for expected_id in expected_ids {
+            let row = step.next().unwrap().unwrap();
+            assert_eq!(row.get_column_value("id").unwrap().get_i64().unwrap(), expected_id);
+        }
+        assert!(step.next().unwrap().is_none());
+    }
+
+    fn limit_step(limit: Limit, offset: usize, n_rows: usize) -> (Plan, Arc<AtomicUsize>) {
+        let n_pulled = Arc::new(AtomicUsize::new(0));
+        let source = Box::new(MockPlan {
+            table: Table::create_mock(vec![
+                ColumnDescriptor{column_id: 1, column_type: Type::I64, column_name: String::from("id"), is_primary: true, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+            ]),
+            n_pulled: n_pulled.clone(),
+            n_rows,
+        });
+
+        (LimitStep::create(limit, offset, source), n_pulled)
+    }
+}
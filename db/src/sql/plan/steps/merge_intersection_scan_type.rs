@@ -5,8 +5,10 @@ use crate::Row;
 use crate::sql::plan::plan_step::{Plan, PlanStep};
 
 pub struct MergeIntersectionScanType {
-    plans: Vec<Plan>,
-    rows_not_intersected: HashMap<Bytes, Row>,
+    //A plan is set to None once it has returned all of its rows
+    plans: Vec<Option<Plan>>,
+    //Primary key -> (index of the plan that returned the row, row)
+    rows_not_intersected: HashMap<Bytes, (usize, Row)>,
     prev_plan_index: usize,
 }
 
@@ -15,14 +17,10 @@ impl MergeIntersectionScanType {
         a: Plan,
         b: Plan,
     ) -> Result<Plan, SimpleDbError> {
-        let mut plans = Vec::new();
-        plans.push(a);
-        plans.push(b);
-
         Ok(Box::new(MergeIntersectionScanType {
             rows_not_intersected: HashMap::new(),
+            plans: vec![Some(a), Some(b)],
             prev_plan_index: 0,
-            plans,
         }))
     }
 
@@ -37,28 +35,37 @@ impl MergeIntersectionScanType {
 
 impl PlanStep for MergeIntersectionScanType {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
-        let mut current_index = self.prev_plan_index;
+        loop {
+            //Plans are read alternately, until one of them is finished
+            let mut current_index = self.get_next_index(self.prev_plan_index);
+            if self.plans[current_index].is_none() {
+                current_index = self.prev_plan_index;
+            }
+            self.prev_plan_index = current_index;
 
-        //If there are less than 2 plans, no more rows will be intersected, so we can return None
-        while self.plans.len() == 2 {
-            current_index = self.get_next_index(current_index);
-            let current_plan = &mut self.plans[current_index];
+            let current_plan = match &mut self.plans[current_index] {
+                Some(current_plan) => current_plan,
+                None => return Ok(None), //Both plans are finished
+            };
 
             match current_plan.next()? {
                 Some(current_row) => {
-                    let current_row_primary_value = current_row.get_primary_column_value();
-                    if let Some(row_intersected) = self.rows_not_intersected.remove(current_row_primary_value) {
-                        self.prev_plan_index = current_index;
-                        return Ok(Some(row_intersected)); //Found intersection
+                    let current_row_primary_value = current_row.get_primary_column_value().clone();
+                    match self.rows_not_intersected.remove(&current_row_primary_value) {
+                        //Found intersection
+                        Some((plan_index, row_intersected)) if plan_index != current_index => return Ok(Some(row_intersected)),
+                        _ => { self.rows_not_intersected.insert(current_row_primary_value, (current_index, current_row)); },
                     }
                 }
                 None => {
-                    self.plans.remove(current_index);
-                    break;
+                    self.plans[current_index] = None;
+                    //The rows returned by the other plan can no longer be intersected
+                    self.rows_not_intersected.retain(|_, (plan_index, _)| *plan_index == current_index);
+                    if self.rows_not_intersected.is_empty() {
+                        return Ok(None);
+                    }
                 },
             }
         }
-
-        Ok(None)
     }
-}
\ No newline at end of file
+}
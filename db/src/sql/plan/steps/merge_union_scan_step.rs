@@ -48,6 +48,7 @@ impl PlanStep for MergeUnionScanStep {
                     let row_primary_key = row.get_primary_column_value();
 
                     if !self.returned_rows.contains(row_primary_key) {
+                        self.returned_rows.insert(row_primary_key.clone());
                         self.prev_plan_index_returned = current_plan_index;
                         return Ok(Some(row.clone()));
                     } else {
@@ -4,5 +4,14 @@ pub mod range_scan_step;
 pub mod filter_step;
 pub mod limit_step;
 pub mod secondary_scan_type;
+pub mod secondary_range_scan_step;
+pub mod composite_scan_step;
 pub mod merge_union_scan_step;
 pub mod merge_intersection_scan_type;
+pub mod group_by_step;
+pub mod parallel_scan_step;
+pub mod query_cache_step;
+pub mod correlated_filter_step;
+pub mod projection_step;
+pub mod distinct_step;
+pub mod join_step;
@@ -0,0 +1,123 @@
+use crate::selection::Selection;
+use crate::sql::expression::Expression;
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::sql::plan::steps::filter_step::FilterStep;
+use crate::table::table::Table;
+use crate::table::table_iterator::TableIterator;
+use crate::Row;
+use bytes::Bytes;
+use shared::SimpleDbError;
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+use crate::table::table_storage_iterator::TableStorageIterator;
+
+//Full scan of a table, where the table's keyspace is split in key ranges (chunks) which are scanned & filtered
+//concurrently. All chunks are scanned with the same transaction. Rows are returned in the same order as FullScanStep
+pub struct ParallelScanStep {
+    rows: std::vec::IntoIter<Row>,
+}
+
+//Scans the rows of a table whose key is in [start_key, end_key)
+struct ChunkScanStep {
+    iterator: TableIterator<TableStorageIterator>,
+    end_key: Option<Bytes>,
+}
+
+impl ParallelScanStep {
+    pub fn create(
+        table: Arc<Table>,
+        selection: Selection,
+        where_expr: Option<Expression>,
+        transaction: &Transaction,
+        split_keys: Vec<Bytes>,
+    ) -> Result<Plan, SimpleDbError> {
+        let rows_by_chunk = scan_chunks(&table, &selection, &where_expr, transaction, split_keys, |mut chunk| {
+            let mut rows = Vec::new();
+            while let Some(row) = chunk.next()? {
+                rows.push(row);
+            }
+            Ok(rows)
+        })?;
+
+        Ok(Box::new(ParallelScanStep {
+            rows: rows_by_chunk.into_iter()
+                .flatten()
+                .collect::<Vec<Row>>()
+                .into_iter()
+        }))
+    }
+}
+
+impl PlanStep for ParallelScanStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        Ok(self.rows.next())
+    }
+}
+
+//Creates a thread per key range defined by split_keys. Each thread calls chunk_consumer with a plan that scans
+//& filters the rows of its key range. Results are returned in key range order
+pub(crate) fn scan_chunks<T, F>(
+    table: &Arc<Table>,
+    selection: &Selection,
+    where_expr: &Option<Expression>,
+    transaction: &Transaction,
+    split_keys: Vec<Bytes>,
+    chunk_consumer: F,
+) -> Result<Vec<T>, SimpleDbError>
+where
+    T: Send,
+    F: Fn(Plan) -> Result<T, SimpleDbError> + Sync
+{
+    let mut start_keys = vec![None];
+    start_keys.extend(split_keys.iter().cloned().map(Some));
+    let mut end_keys: Vec<Option<Bytes>> = split_keys.into_iter().map(Some).collect();
+    end_keys.push(None);
+
+    std::thread::scope(|scope| {
+        let chunk_consumer = &chunk_consumer;
+        let handles: Vec<_> = start_keys.into_iter().zip(end_keys)
+            .map(|(start_key, end_key)| scope.spawn(move || {
+                let mut chunk = ChunkScanStep::create(table, selection, transaction, start_key, end_key)?;
+                if let Some(where_expr) = where_expr {
+                    chunk = FilterStep::create(where_expr.clone(), table.database.collation(), chunk);
+                }
+                chunk_consumer(chunk)
+            }))
+            .collect();
+
+        handles.into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+impl ChunkScanStep {
+    fn create(
+        table: &Arc<Table>,
+        selection: &Selection,
+        transaction: &Transaction,
+        start_key: Option<Bytes>,
+        end_key: Option<Bytes>,
+    ) -> Result<Plan, SimpleDbError> {
+        let iterator = match start_key {
+            Some(start_key) => table.scan_from_key(&start_key, true, transaction, selection)?,
+            None => table.scan_all(transaction, selection.clone())?,
+        };
+
+        Ok(Box::new(ChunkScanStep { iterator, end_key }))
+    }
+}
+
+impl PlanStep for ChunkScanStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        if !self.iterator.next() {
+            return self.iterator.take_error().map_or(Ok(None), Err);
+        }
+
+        let row = self.iterator.row();
+        match &self.end_key {
+            Some(end_key) if row.get_primary_column_value() >= end_key => Ok(None),
+            _ => Ok(Some(row.clone())),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::sql::expression_evaluator::evaluate_expression;
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::sql::statement::Projection;
+use crate::table::record::Record;
+use crate::table::table::Table;
+use crate::{ColumnDescriptor, Row};
+use shared::{Collation, ColumnId, SimpleDbError};
+use std::sync::Arc;
+
+//Evaluates the projection over each row. Only used when the projection contains expressions or when joining tables,
+//otherwise the rows are returned as they are read from the table
+pub struct ProjectionStep {
+    projection: Vec<Projection>,
+    columns_desc: Arc<Vec<ColumnDescriptor>>,
+    table: Arc<Table>,
+    collation: Collation,
+    source: Plan,
+}
+
+impl ProjectionStep {
+    pub fn create(
+        table: Arc<Table>,
+        projection: Vec<Projection>,
+        columns_desc: Vec<ColumnDescriptor>,
+        collation: Collation,
+        source: Plan,
+    ) -> Plan {
+        Box::new(ProjectionStep {
+            columns_desc: Arc::new(columns_desc),
+            projection,
+            collation,
+            source,
+            table,
+        })
+    }
+
+    fn project(&self, row: &Row) -> Result<Row, SimpleDbError> {
+        let mut record = Record::builder();
+
+        for (index, projection) in self.projection.iter().enumerate() {
+            let value = match projection {
+                Projection::Column(column_name) => row.get_column_value(column_name)?,
+                Projection::Expression(expression, _) => evaluate_expression(row, expression, self.collation)?.get_value()?,
+                Projection::Aggregate(_, _) => return Err(SimpleDbError::MalformedQuery(
+                    String::from("Expressions cannot be used with aggregations")
+                )),
+            };
+
+            if !value.is_null() {
+                record.add_column(index as ColumnId, value.serialize());
+            }
+        }
+
+        Ok(Row::create_derived(record.build(), &self.columns_desc, &self.table, row.get_primary_column_value().clone()))
+    }
+}
+
+impl PlanStep for ProjectionStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        match self.source.next()? {
+            Some(row) => Ok(Some(self.project(&row)?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::database::database::Database;
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::sql::query_cache::row_size_bytes;
+use crate::Row;
+use shared::SimpleDbError;
+use std::sync::Arc;
+
+//Returns the rows of a query result stored in QueryCache
+pub struct CachedRowsStep {
+    rows: Arc<Vec<Row>>,
+    next_index: usize,
+}
+
+//Rows are returned from source as they are produced, and they are also collected.
+//Once source has no more rows, the collected rows are stored in the QueryCache.
+//If the results are bigger than the cache, rows stop being collected.
+pub struct QueryCacheStoreStep {
+    database: Arc<Database>,
+    key: String,
+    table_version: u64,
+    epoch: u64,
+    source: Plan,
+
+    rows: Option<Vec<Row>>,
+    size_bytes: usize,
+}
+
+impl CachedRowsStep {
+    pub fn create(rows: Arc<Vec<Row>>) -> Plan {
+        Box::new(CachedRowsStep { next_index: 0, rows })
+    }
+}
+
+impl PlanStep for CachedRowsStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        let row = self.rows.get(self.next_index).cloned();
+        self.next_index += 1;
+        Ok(row)
+    }
+}
+
+impl QueryCacheStoreStep {
+    pub fn create(
+        database: &Arc<Database>,
+        key: String,
+        table_version: u64,
+        epoch: u64,
+        source: Plan
+    ) -> Plan {
+        Box::new(QueryCacheStoreStep {
+            database: database.clone(),
+            rows: Some(Vec::new()),
+            size_bytes: 0,
+            table_version,
+            source,
+            epoch,
+            key,
+        })
+    }
+}
+
+impl PlanStep for QueryCacheStoreStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        match self.source.next()? {
+            Some(row) => {
+                if let Some(rows) = self.rows.as_mut() {
+                    self.size_bytes += row_size_bytes(&row);
+                    rows.push(row.clone());
+
+                    if self.size_bytes > self.database.query_cache.max_size_bytes() {
+                        self.rows = None;
+                    }
+                }
+
+                Ok(Some(row))
+            },
+            None => {
+                if let Some(rows) = self.rows.take() {
+                    self.database.query_cache.put(self.key.clone(), self.table_version, self.epoch, rows, self.size_bytes);
+                }
+
+                Ok(None)
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use shared::SimpleDbError;
-use storage::SimpleDbStorageIterator;
+use shared::SimpleDbError::CannotDecodeColumn;
+use crate::table::table_storage_iterator::TableStorageIterator;
 use storage::transactions::transaction::Transaction;
 use crate::{Row};
 use crate::selection::Selection;
@@ -8,10 +9,12 @@ use crate::sql::plan::plan_step::{Plan, PlanStep};
 use crate::sql::plan::scan_type::{RangeKeyPosition, RangeScan};
 use crate::table::table::Table;
 use crate::table::table_iterator::TableIterator;
+use crate::value::{Type, Value};
 
 pub struct RangeScanStep {
     range: RangeScan,
-    iterator: TableIterator<SimpleDbStorageIterator>
+    primary_column_type: Type,
+    iterator: TableIterator<TableStorageIterator>
 }
 
 impl RangeScanStep {
@@ -21,14 +24,20 @@ impl RangeScanStep {
         transaction: &Transaction,
         range: RangeScan
     ) -> Result<Plan, SimpleDbError> {
-        let iterator = if let Some(star_range_key_expr) = range.start() {
-            let star_range_key_bytes = star_range_key_expr.serialize();
-            table.scan_from_key(&star_range_key_bytes, range.is_start_inclusive(), transaction, &selection)
-        } else {
-            table.scan_all(transaction, selection)
+        let primary_column_type = table.get_column_desc_or_err(&range.column_name)?
+            .column_type;
+
+        //If the primary keys are not sorted like their values, all the rows have to be read, see Type::is_serialized_in_order
+        let iterator = match range.start() {
+            Some(star_range_key_expr) if primary_column_type.is_serialized_in_order() => {
+                let star_range_key_bytes = star_range_key_expr.serialize();
+                table.scan_from_key(&star_range_key_bytes, range.is_start_inclusive(), transaction, &selection)
+            },
+            _ => table.scan_all(transaction, selection),
         }?;
 
         Ok(Box::new(RangeScanStep{
+            primary_column_type,
             iterator,
             range,
         }))
@@ -37,18 +46,27 @@ impl RangeScanStep {
 
 impl PlanStep for RangeScanStep {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
-        if self.iterator.next() {
+        while self.iterator.next() {
             let current_row = self.iterator.row();
             let current_primary_column_value = current_row.get_primary_column_value();
 
-            match self.range.get_position(current_primary_column_value) {
+            if !self.primary_column_type.is_serialized_in_order() {
+                let value = Value::deserialize(current_primary_column_value.clone(), self.primary_column_type.clone())
+                    .map_err(|_| CannotDecodeColumn(self.range.column_name.clone(), current_primary_column_value.clone()))?;
+                if self.range.contains(value)? {
+                    return Ok(Some(current_row.clone()));
+                }
+                continue;
+            }
+
+            return match self.range.get_position(current_primary_column_value) {
                 RangeKeyPosition::Inside => Ok(Some(current_row.clone())),
                 RangeKeyPosition::Above => Ok(None),
                 //Not possible because, the iterator have been seeked in construction time
                 RangeKeyPosition::Bellow => panic!(""),
-            }
-        } else {
-            Ok(None)
+            };
         }
+
+        self.iterator.take_error().map_or(Ok(None), Err)
     }
-}
\ No newline at end of file
+}
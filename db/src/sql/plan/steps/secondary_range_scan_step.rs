@@ -0,0 +1,87 @@
+use crate::index::secondary_index_iterator::SecondaryIndexIterator;
+use crate::selection::Selection;
+use crate::sql::plan::plan_step::{Plan, PlanStep};
+use crate::sql::plan::scan_type::RangeScan;
+use crate::table::table::Table;
+use crate::value::{Type, Value};
+use crate::Row;
+use shared::SimpleDbError;
+use shared::SimpleDbError::CannotDecodeColumn;
+use std::ops::Bound;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use storage::transactions::transaction::Transaction;
+use storage::SimpleDbStorageIterator;
+
+//Returns the rows whose indexed column value is inside the range, sorted by the indexed value
+pub struct SecondaryRangeScanStep {
+    secondary_index_iterator: SecondaryIndexIterator<SimpleDbStorageIterator>,
+    range: RangeScan,
+    indexed_column_type: Type,
+    transaction: Transaction,
+    selection: Selection,
+    table: Arc<Table>,
+}
+
+impl SecondaryRangeScanStep {
+    pub fn create(
+        table: Arc<Table>,
+        range: RangeScan,
+        transaction: &Transaction,
+        selection: Selection
+    ) -> Result<Plan, SimpleDbError> {
+        let indexed_column_type = table.get_column_desc_or_err(&range.column_name)?
+            .column_type;
+
+        //If the index keys are not sorted like their values, the whole index has to be read, see Type::is_serialized_in_order
+        let secondary_index_iterator = if indexed_column_type.is_serialized_in_order() {
+            table.scan_range_secondary_index(transaction, &range.column_name, range.start_bound(), range.end_bound())?
+        } else {
+            table.scan_range_secondary_index(transaction, &range.column_name, Bound::Unbounded, Bound::Unbounded)?
+        };
+        table.n_secondary_index_scans.fetch_add(1, Ordering::Relaxed);
+
+        Ok(Box::new(SecondaryRangeScanStep {
+            transaction: transaction.clone(),
+            secondary_index_iterator,
+            indexed_column_type,
+            selection,
+            range,
+            table,
+        }))
+    }
+
+    fn is_indexed_value_in_range(&self) -> Result<bool, SimpleDbError> {
+        if self.indexed_column_type.is_serialized_in_order() {
+            return Ok(true);
+        }
+
+        let indexed_value = self.secondary_index_iterator.indexed_value();
+        let value = Value::deserialize(indexed_value.clone(), self.indexed_column_type.clone())
+            .map_err(|_| CannotDecodeColumn(self.range.column_name.clone(), indexed_value.clone()))?;
+        self.range.contains(value)
+    }
+}
+
+impl PlanStep for SecondaryRangeScanStep {
+    fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
+        while let Some(primary_key) = self.secondary_index_iterator.next() {
+            if !self.is_indexed_value_in_range()? {
+                continue;
+            }
+
+            let mut primary_key_iterator = self.table.scan_from_key(
+                primary_key.as_bytes(),
+                true,
+                &self.transaction,
+                &self.selection,
+            )?;
+
+            if primary_key_iterator.next() && &primary_key_iterator.row().key_bytes == primary_key.as_bytes() {
+                return Ok(Some(primary_key_iterator.row().clone()));
+            }
+        }
+
+        Ok(None)
+    }
+}
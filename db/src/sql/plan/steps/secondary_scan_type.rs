@@ -6,11 +6,13 @@ use crate::Row;
 use bytes::Bytes;
 use shared::SimpleDbError;
 use std::sync::Arc;
+use std::sync::atomic::Ordering;
 use storage::transactions::transaction::Transaction;
 use storage::SimpleDbStorageIterator;
 
 pub struct SecondaryExactScanType {
     secondary_index_iterator: SecondaryIndexIterator<SimpleDbStorageIterator>,
+    secondary_value: Bytes,
     transaction: Transaction,
     selection: Selection,
     table: Arc<Table>,
@@ -29,10 +31,12 @@ impl SecondaryExactScanType {
             transaction,
             secondary_column_name
         )?;
+        table.n_secondary_index_scans.fetch_add(1, Ordering::Relaxed);
 
         Ok(Box::new(SecondaryExactScanType {
             transaction: transaction.clone(),
             secondary_index_iterator,
+            secondary_value,
             selection,
             table,
         }))
@@ -43,16 +47,21 @@ impl SecondaryExactScanType {
 impl PlanStep for SecondaryExactScanType {
     fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
         while let Some(primary_key) = self.secondary_index_iterator.next() {
+            //Index entries are sorted by the indexed value, so there are no more matching rows
+            if self.secondary_index_iterator.indexed_value() != &self.secondary_value {
+                return Ok(None);
+            }
+
             let mut primary_key_iterator = self.table.scan_from_key(
-                &primary_key.as_bytes(),
+                primary_key.as_bytes(),
                 true,
                 &self.transaction,
                 &self.selection,
             )?;
 
-            primary_key_iterator.next();
-
-            return Ok(Some(primary_key_iterator.row().clone()));
+            if primary_key_iterator.next() && &primary_key_iterator.row().key_bytes == primary_key.as_bytes() {
+                return Ok(Some(primary_key_iterator.row().clone()));
+            }
         }
 
         Ok(None)
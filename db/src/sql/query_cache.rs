@@ -0,0 +1,160 @@
+use crate::Row;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex};
+
+//Caches the results of SELECT statements. There is one cache per database.
+//Entries are keyed by the normalized statement and they are only valid for the table version and database
+//commit epoch in which they were produced:
+// - Table version is increased on every write to the table. See Table::on_write()
+// - Database commit epoch is increased every time a transaction with writes commits or rollbacks. See Database::query_cache_epoch()
+//If any of both have changed, the entry is never returned, so stale results are never served.
+//When the cache is full, the oldest entries are evicted.
+pub struct QueryCache {
+    max_size_bytes: usize,
+    entries: Mutex<QueryCacheEntries>,
+
+    n_hits: AtomicUsize,
+    n_misses: AtomicUsize,
+}
+
+struct QueryCacheEntries {
+    by_key: HashMap<String, CachedQuery>,
+    //Keys in insertion order, used for eviction
+    insertion_order: VecDeque<String>,
+    size_bytes: usize,
+}
+
+struct CachedQuery {
+    table_version: u64,
+    epoch: u64,
+    rows: Arc<Vec<Row>>,
+    size_bytes: usize,
+}
+
+impl QueryCache {
+    pub fn create(max_size_bytes: usize) -> QueryCache {
+        QueryCache {
+            entries: Mutex::new(QueryCacheEntries {
+                by_key: HashMap::new(),
+                insertion_order: VecDeque::new(),
+                size_bytes: 0,
+            }),
+            n_hits: AtomicUsize::new(0),
+            n_misses: AtomicUsize::new(0),
+            max_size_bytes,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_size_bytes > 0
+    }
+
+    pub fn max_size_bytes(&self) -> usize {
+        self.max_size_bytes
+    }
+
+    pub fn get(&self, key: &str, table_version: u64, epoch: u64) -> Option<Arc<Vec<Row>>> {
+        let entries = self.entries.lock().unwrap();
+
+        match entries.by_key.get(key) {
+            Some(entry) if entry.table_version == table_version && entry.epoch == epoch => {
+                self.n_hits.fetch_add(1, Relaxed);
+                Some(entry.rows.clone())
+            },
+            _ => {
+                self.n_misses.fetch_add(1, Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: String, table_version: u64, epoch: u64, rows: Vec<Row>, size_bytes: usize) {
+        if size_bytes > self.max_size_bytes {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&key);
+
+        while entries.size_bytes + size_bytes > self.max_size_bytes {
+            let oldest_key = entries.insertion_order.pop_front().unwrap();
+            entries.remove(&oldest_key);
+        }
+
+        entries.size_bytes += size_bytes;
+        entries.insertion_order.push_back(key.clone());
+        entries.by_key.insert(key, CachedQuery {
+            rows: Arc::new(rows),
+            table_version,
+            size_bytes,
+            epoch,
+        });
+    }
+
+    pub fn n_hits(&self) -> usize {
+        self.n_hits.load(Relaxed)
+    }
+
+    pub fn n_misses(&self) -> usize {
+        self.n_misses.load(Relaxed)
+    }
+}
+
+impl QueryCacheEntries {
+    fn remove(&mut self, key: &str) {
+        if let Some(removed) = self.by_key.remove(key) {
+            self.size_bytes -= removed.size_bytes;
+            self.insertion_order.retain(|current_key| current_key != key);
+        }
+    }
+}
+
+pub fn row_size_bytes(row: &Row) -> usize {
+    row.key_bytes.len() + row.storage_engine_record.data_records.iter()
+        .map(|(_, value)| size_of::<shared::ColumnId>() + value.len())
+        .sum::<usize>()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::query_cache::QueryCache;
+    use crate::table::record::Record;
+    use crate::table::table::Table;
+    use crate::Row;
+    use bytes::Bytes;
+
+    #[test]
+    fn get_put() {
+        let cache = QueryCache::create(100);
+        cache.put(String::from("a"), 1, 1, vec![row()], 10);
+
+        assert_eq!(cache.get("a", 1, 1).unwrap().len(), 1);
+        //Table version or epoch changed
+        assert!(cache.get("a", 2, 1).is_none());
+        assert!(cache.get("a", 1, 2).is_none());
+        assert!(cache.get("b", 1, 1).is_none());
+        assert_eq!(cache.n_hits(), 1);
+        assert_eq!(cache.n_misses(), 3);
+    }
+
+    #[test]
+    fn evict_oldest() {
+        let cache = QueryCache::create(100);
+        cache.put(String::from("a"), 1, 1, vec![row()], 40);
+        cache.put(String::from("b"), 1, 1, vec![row()], 40);
+        cache.put(String::from("c"), 1, 1, vec![row()], 40);
+        //Bigger than the cache
+        cache.put(String::from("d"), 1, 1, vec![row()], 101);
+
+        assert!(cache.get("a", 1, 1).is_none());
+        assert!(cache.get("b", 1, 1).is_some());
+        assert!(cache.get("c", 1, 1).is_some());
+        assert!(cache.get("d", 1, 1).is_none());
+    }
+
+    fn row() -> Row {
+        Row::create(Record::create(Vec::new()), &Table::create_mock(Vec::new()), Bytes::new())
+    }
+}
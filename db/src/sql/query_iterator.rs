@@ -14,6 +14,7 @@ impl QueryIterator {
         QueryIterator { plan, columns_descriptor_selection }
     }
 
+    //Rows are pulled from the plan one at a time, so the result is never fully buffered
     pub fn next(&mut self) -> Result<Option<Row>, SimpleDbError> {
         self.plan.next()
     }
@@ -21,7 +22,7 @@ impl QueryIterator {
     pub fn next_n(&mut self, n: usize) -> Result<Vec<Row>, SimpleDbError> {
         let mut results = Vec::new();
 
-        while results.len() <= n {
+        while results.len() < n {
             match self.plan.next()? {
                 Some(row) => results.push(row),
                 None => break
@@ -44,4 +45,99 @@ impl QueryIterator {
     pub fn columns_descriptor_selection(&self) -> &Vec<ColumnDescriptor> {
         &self.columns_descriptor_selection
     }
-}
\ No newline at end of file
+
+    //Returns the remaining rows as Arrow record batches of at most batch_size rows
+    #[cfg(feature = "arrow")]
+    pub fn to_arrow_batches(
+        self,
+        batch_size: usize
+    ) -> Result<crate::sql::arrow::ArrowBatchIterator, SimpleDbError> {
+        crate::sql::arrow::ArrowBatchIterator::create(self, batch_size)
+    }
+}
+#[cfg(test)]
+mod test {
+    use crate::sql::plan::steps::full_scan_step::FullScanStep;
+    use crate::sql::plan::steps::limit_step::LimitStep;
+    use crate::sql::query_iterator::QueryIterator;
+    use crate::sql::statement::Limit;
+    use crate::table::record::Record;
+    use crate::table::table::Table;
+    use crate::table::table_iterator::TableIterator;
+    use crate::value::Type;
+    use crate::ColumnDescriptor;
+    use bytes::Bytes;
+    use shared::iterators::mock_iterator::MockIterator;
+    use shared::iterators::storage_iterator::StorageIterator;
+    use shared::key::Key;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    //Counts the entries read from the storage engine
+    struct CountingIterator {
+        inner: MockIterator,
+        n_read: Arc<AtomicUsize>,
+    }
+
+    impl StorageIterator for CountingIterator {
+        fn next(&mut self) -> bool {
+            let has_next = self.inner.next();
+            if has_next {
+                self.n_read.fetch_add(1, Ordering::Relaxed);
+            }
+            has_next
+        }
+
+        fn has_next(&self) -> bool {
+            self.inner.has_next()
+        }
+
+        fn key(&self) -> &Key {
+            self.inner.key()
+        }
+
+        fn value(&self) -> &[u8] {
+            self.inner.value()
+        }
+
+        fn seek(&mut self, key: &Bytes, inclusive: bool) {
+            self.inner.seek(key, inclusive)
+        }
+    }
+
+    #[test]
+    fn limited_query_streams_rows() {
+        let (mut query_iterator, n_read) = query_iterator(Limit::Some(10), 1000);
+
+        assert_eq!(query_iterator.next_n(4).unwrap().len(), 4);
+        assert_eq!(n_read.load(Ordering::Relaxed), 4);
+        assert_eq!(query_iterator.all().unwrap().len(), 6);
+        assert_eq!(n_read.load(Ordering::Relaxed), 10);
+        assert!(query_iterator.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn next_n() {
+        let (mut query_iterator, _) = query_iterator(Limit::None, 5);
+
+        assert_eq!(query_iterator.next_n(3).unwrap().len(), 3);
+        assert_eq!(query_iterator.next_n(3).unwrap().len(), 2);
+        assert!(query_iterator.next_n(3).unwrap().is_empty());
+    }
+
+    fn query_iterator(limit: Limit, n_rows: usize) -> (QueryIterator, Arc<AtomicUsize>) {
+        let columns = vec![
+            ColumnDescriptor{column_id: 1, column_type: Type::I64, column_name: String::from("ID"), is_primary: true, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+            ColumnDescriptor{column_id: 2, column_type: Type::I64, column_name: String::from("Money"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+        ];
+        let entries = (0..n_rows)
+            .map(|id| (id, Bytes::from(Record::create(vec![(2, Bytes::copy_from_slice(&(id as i64).to_le_bytes()))]).serialize())))
+            .collect();
+        let n_read = Arc::new(AtomicUsize::new(0));
+        let iterator = CountingIterator { inner: MockIterator::create_from_byte_entries(entries), n_read: n_read.clone() };
+        let table = Table::create_mock(columns.clone());
+        let plan = LimitStep::create(limit, 0, FullScanStep::create_from_iterator(TableIterator::create(iterator, vec![1, 2], table)));
+
+        (QueryIterator::create(plan, columns), n_read)
+    }
+}
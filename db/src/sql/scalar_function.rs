@@ -0,0 +1,156 @@
+use crate::value::{Type, Value};
+use shared::SimpleDbError;
+use shared::SimpleDbError::MalformedQuery;
+
+//Functions applied to the values of a single row, used in expressions like UPPER(name) = "JAIME"
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScalarFunction {
+    Upper,
+    Lower,
+    //Number of characters, not bytes
+    Length,
+    //SUBSTR(string, start [, length]). start is 1-based, characters outside the string are ignored
+    Substr,
+}
+
+impl ScalarFunction {
+    pub fn from_name(name: &str) -> Option<ScalarFunction> {
+        match name.to_uppercase().as_str() {
+            "UPPER" => Some(ScalarFunction::Upper),
+            "LOWER" => Some(ScalarFunction::Lower),
+            "LENGTH" => Some(ScalarFunction::Length),
+            "SUBSTR" => Some(ScalarFunction::Substr),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ScalarFunction::Upper => "UPPER",
+            ScalarFunction::Lower => "LOWER",
+            ScalarFunction::Length => "LENGTH",
+            ScalarFunction::Substr => "SUBSTR",
+        }
+    }
+
+    //Returns the type produced by the function, or an error if the arguments have the wrong number or type
+    pub fn validate_args(&self, args_type: &[Type]) -> Result<Type, SimpleDbError> {
+        let valid = match self {
+            ScalarFunction::Upper |
+            ScalarFunction::Lower |
+            ScalarFunction::Length => args_type.len() == 1 && is_string_type(&args_type[0]),
+            ScalarFunction::Substr => (args_type.len() == 2 || args_type.len() == 3) &&
+                is_string_type(&args_type[0]) &&
+                args_type[1..].iter().all(|arg_type| arg_type.is_integer_number() || matches!(arg_type, Type::Null)),
+        };
+
+        if !valid {
+            return Err(MalformedQuery(format!("Invalid arguments for function {}", self.name())));
+        }
+
+        Ok(self.produced_type())
+    }
+
+    pub fn produced_type(&self) -> Type {
+        match self {
+            ScalarFunction::Length => Type::I64,
+            ScalarFunction::Upper |
+            ScalarFunction::Lower |
+            ScalarFunction::Substr => Type::String,
+        }
+    }
+
+    //If some argument is null, null is returned
+    pub fn evaluate(&self, args: &[Value]) -> Result<Value, SimpleDbError> {
+        if args.iter().any(|arg| arg.is_null()) {
+            return Ok(Value::Null);
+        }
+
+        let string = args.first()
+            .ok_or(MalformedQuery(format!("Invalid arguments for function {}", self.name())))?
+            .get_string()?;
+
+        match self {
+            ScalarFunction::Upper => Ok(Value::String(string.to_uppercase())),
+            ScalarFunction::Lower => Ok(Value::String(string.to_lowercase())),
+            ScalarFunction::Length => Ok(Value::I64(string.chars().count() as i64)),
+            ScalarFunction::Substr => {
+                let start = args.get(1)
+                    .ok_or(MalformedQuery(String::from("SUBSTR expects a start position")))?
+                    .get_i64()?;
+                let length = match args.get(2) {
+                    Some(length) => length.get_i64()?,
+                    None => i64::MAX,
+                };
+                Ok(Value::String(substr(string, start, length)))
+            },
+        }
+    }
+}
+
+//Characters in positions [start, start + length) that are in the string. Positions start at 1
+fn substr(string: &str, start: i64, length: i64) -> String {
+    if length <= 0 {
+        return String::new();
+    }
+
+    let end = start.saturating_add(length);
+    let start = start.max(1);
+    if end <= start {
+        return String::new();
+    }
+
+    string.chars()
+        .skip((start - 1) as usize)
+        .take((end - start) as usize)
+        .collect()
+}
+
+fn is_string_type(arg_type: &Type) -> bool {
+    matches!(arg_type, Type::String | Type::Null)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sql::scalar_function::ScalarFunction;
+    use crate::value::Value;
+
+    #[test]
+    fn upper_lower() {
+        let string = vec![Value::String(String::from("Jaime Ñoño"))];
+        assert_eq!(ScalarFunction::Upper.evaluate(&string).unwrap(), Value::String(String::from("JAIME ÑOÑO")));
+        assert_eq!(ScalarFunction::Lower.evaluate(&string).unwrap(), Value::String(String::from("jaime ñoño")));
+        assert_eq!(ScalarFunction::Upper.evaluate(&[Value::Null]).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn length() {
+        //"ñ" takes 2 bytes
+        assert_eq!(ScalarFunction::Length.evaluate(&[Value::String(String::from("año"))]).unwrap(), Value::I64(3));
+        assert_eq!(ScalarFunction::Length.evaluate(&[Value::String(String::new())]).unwrap(), Value::I64(0));
+        assert_eq!(ScalarFunction::Length.evaluate(&[Value::Null]).unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn substr() {
+        assert_eq!(evaluate_substr("polidura", 1, 3), Value::String(String::from("pol")));
+        assert_eq!(evaluate_substr("polidura", 5, 100), Value::String(String::from("dura")));
+        assert_eq!(evaluate_substr("añoño", 2, 2), Value::String(String::from("ño")));
+        //Out of range start
+        assert_eq!(evaluate_substr("polidura", 20, 3), Value::String(String::new()));
+        assert_eq!(evaluate_substr("polidura", 0, 3), Value::String(String::from("po")));
+        assert_eq!(evaluate_substr("polidura", -5, 3), Value::String(String::new()));
+        //Negative or zero length
+        assert_eq!(evaluate_substr("polidura", 2, 0), Value::String(String::new()));
+        assert_eq!(evaluate_substr("polidura", 2, -1), Value::String(String::new()));
+        //Without length
+        assert_eq!(
+            ScalarFunction::Substr.evaluate(&[Value::String(String::from("polidura")), Value::I64(5)]).unwrap(),
+            Value::String(String::from("dura"))
+        );
+    }
+
+    fn evaluate_substr(string: &str, start: i64, length: i64) -> Value {
+        ScalarFunction::Substr.evaluate(&[Value::String(string.to_string()), Value::I64(start), Value::I64(length)]).unwrap()
+    }
+}
@@ -1,7 +1,10 @@
 use crate::selection::Selection;
+use crate::sql::aggregate::AggregateFunction;
 use crate::sql::expression::Expression;
 use crate::value::{Type, Value};
+use shared::SimpleDbError;
 
+#[derive(Clone)]
 pub enum Statement {
     Select(SelectStatement),
     Update(UpdateStatement),
@@ -10,56 +13,119 @@ pub enum Statement {
     CreateTable(CreateTableStatement),
     CreateIndex(CreateIndexStatement),
     CreateDatabase(String),
+    DropTable(String), //Table name
+    AlterTable(AlterTableStatement),
     Describe(String),
     StartTransaction,
     Rollback,
     Commit,
+    Savepoint(String), //Savepoint name
+    RollbackToSavepoint(String), //Savepoint name
     ShowIndexes(String), //Table name
     ShowDatabases,
     ShowTables,
+    //Only SELECT, UPDATE & DELETE statements can be explained
+    Explain(Box<Statement>),
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Limit {
     None,
     Some(usize)
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum Projection {
+    Column(String),
+    //Column name is None when using *, for example: COUNT(*)
+    Aggregate(AggregateFunction, Option<String>),
+    //Evaluated for each row, for example: price * quantity AS total. Without an alias, the expression is used as the column name
+    Expression(Expression, Option<String>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct SelectStatement {
     pub(crate) where_expr: Option<Expression>,
+    //Columns to be read from the table
     pub(crate) selection: Selection,
+    //Values to be returned. Empty if selection is Selection::All
+    pub(crate) projection: Vec<Projection>,
+    pub(crate) group_by: Vec<String>,
     pub(crate) table_name: String,
     pub(crate) limit: Limit,
+    //Number of rows to skip before returning them
+    pub(crate) offset: usize,
+    //Duplicated rows are removed from the result
+    pub(crate) distinct: bool,
+    //Inner join with another table. Columns of the joined rows are qualified with their table name: <table>.<column>
+    pub(crate) join: Option<Join>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Join {
+    pub(crate) table_name: String,
+    //Condition that a row of each table must satisfy to be joined
+    pub(crate) on: Expression,
 }
 
+#[derive(Clone)]
 pub struct UpdateStatement {
     pub(crate) table_name: String,
     pub(crate) updated_values: Vec<(String, Expression)>,
     pub(crate) where_expr: Option<Expression>
 }
 
+#[derive(Clone)]
 pub struct DeleteStatement {
     pub(crate) table_name: String,
     pub(crate) where_expr: Option<Expression>,
     pub(crate) limit: Limit
 }
 
+#[derive(Clone)]
 pub struct InsertStatement {
     pub(crate) table_name: String,
-    //Column name, Value, Value type
-    pub(crate) values: Vec<(String, Value)>,
+    //One entry per inserted row: Column name, Value. Values are literals, or placeholders in prepared statements
+    pub(crate) values: Vec<Vec<(String, Expression)>>,
 }
 
+#[derive(Clone)]
 pub struct CreateIndexStatement {
+    //Indexes are identified by their indexed columns, the name is optional and only informative
+    pub(crate) index_name: Option<String>,
     pub(crate) table_name: String,
-    pub(crate) column_name: String,
+    //More than one column creates a composite index
+    pub(crate) columns_names: Vec<String>,
     pub(crate) wait: bool,
 }
 
+//ALTER TABLE <table_name> ADD COLUMN <column_name> <column_type> [DEFAULT <default_value>]
+#[derive(Clone)]
+pub struct AlterTableStatement {
+    pub(crate) table_name: String,
+    pub(crate) column_name: String,
+    pub(crate) column_type: Type,
+    pub(crate) default_value: Option<Value>,
+}
+
+#[derive(Clone)]
 pub struct CreateTableStatement {
     pub(crate) table_name: String,
-    //Column name, Column type, is primary
-    pub(crate) columns: Vec<(String, Type, bool)>
+    pub(crate) columns: Vec<CreateTableColumn>
+}
+
+//<column_name> <column_type> [PRIMARY KEY [AUTOINCREMENT]] [NOT NULL] [UNIQUE] [DEFAULT <default_value>]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTableColumn {
+    pub(crate) column_name: String,
+    pub(crate) column_type: Type,
+    pub(crate) is_primary: bool,
+    //Inserts without primary key get the next value of a counter of the table
+    pub(crate) is_auto_increment: bool,
+    pub(crate) is_not_null: bool,
+    pub(crate) is_unique: bool,
+    //Used by inserts that don't include the column
+    pub(crate) default_value: Option<Value>,
 }
 
 enum Requirement {
@@ -101,18 +167,102 @@ impl StatementDescriptor {
     }
 }
 
-impl UpdateStatement {
-    pub fn get_updated_values(&self) -> Selection {
-        let mut column_names = Vec::new();
-        for (column_name, _) in &self.updated_values {
-            column_names.push(column_name.clone());
+impl SelectStatement {
+    //Returns true if rows have to be grouped before being returned
+    //Once the identifiers of the subquery table have been unqualified, the qualified ones reference the outer table
+    pub fn is_correlated(&self) -> bool {
+        let mut identifiers = Vec::new();
+        if let Some(where_expr) = &self.where_expr {
+            where_expr.collect_identifiers(&mut identifiers);
+        }
+
+        identifiers.iter().any(|identifier| identifier.contains('.'))
+    }
+
+    //Returns true if the returned rows are produced by evaluating the projection over the rows of the table
+    pub fn has_expression_projection(&self) -> bool {
+        self.projection.iter()
+            .any(|projection| matches!(projection, Projection::Expression(_, _)))
+    }
+
+    pub fn is_aggregation(&self) -> bool {
+        !self.group_by.is_empty() || self.projection.iter()
+            .any(|projection| matches!(projection, Projection::Aggregate(_, _)))
+    }
+
+    fn bind(mut self, params: &[Value]) -> Result<SelectStatement, SimpleDbError> {
+        self.where_expr = self.where_expr.map(|where_expr| bind_expression(where_expr, params)).transpose()?;
+        if let Some(join) = self.join.as_mut() {
+            join.on = bind_expression(join.on.clone(), params)?;
         }
+        self.projection = self.projection.into_iter()
+            .map(|projection| match projection {
+                Projection::Expression(expression, alias) => Ok(Projection::Expression(bind_expression(expression, params)?, alias)),
+                projection => Ok(projection),
+            })
+            .collect::<Result<Vec<Projection>, SimpleDbError>>()?;
+
+        Ok(self)
+    }
+}
 
-        Selection::Some(column_names)
+impl InsertStatement {
+    //Values of every row. Fails if the statement has placeholders that have not been bound
+    pub fn rows_values(&self) -> Result<Vec<Vec<(String, Value)>>, SimpleDbError> {
+        self.values.iter()
+            .map(|row_values| row_values.iter()
+                .map(|(column_name, value)| match value {
+                    Expression::Literal(value) => Ok((column_name.clone(), value.clone())),
+                    _ => Err(SimpleDbError::MalformedQuery(String::from(
+                        "Placeholders should be bound before executing the statement"
+                    ))),
+                })
+                .collect())
+            .collect()
     }
 }
 
+//Replaces the placeholders, including the ones of the subqueries, with the parameter at their position
+fn bind_expression(expression: Expression, params: &[Value]) -> Result<Expression, SimpleDbError> {
+    expression.map_leaves(&mut |leaf| match leaf {
+        Expression::Placeholder(position) => params.get(position)
+            .map(|param| Expression::Literal(param.clone()))
+            .ok_or(SimpleDbError::WrongNumberOfParameters(position + 1, params.len())),
+        Expression::Subquery(subquery) => Ok(Expression::Subquery(Box::new(subquery.bind(params)?))),
+        Expression::Exists(subquery) => Ok(Expression::Exists(Box::new(subquery.bind(params)?))),
+        leaf => Ok(leaf),
+    })
+}
+
 impl Statement {
+    //Replaces the placeholders (?) of a prepared statement with the parameters, by position
+    pub(crate) fn bind(self, params: &[Value]) -> Result<Statement, SimpleDbError> {
+        match self {
+            Statement::Select(select) => Ok(Statement::Select(select.bind(params)?)),
+            Statement::Update(mut update) => {
+                update.where_expr = update.where_expr.map(|where_expr| bind_expression(where_expr, params)).transpose()?;
+                update.updated_values = update.updated_values.into_iter()
+                    .map(|(column_name, value)| Ok((column_name, bind_expression(value, params)?)))
+                    .collect::<Result<Vec<(String, Expression)>, SimpleDbError>>()?;
+                Ok(Statement::Update(update))
+            },
+            Statement::Delete(mut delete) => {
+                delete.where_expr = delete.where_expr.map(|where_expr| bind_expression(where_expr, params)).transpose()?;
+                Ok(Statement::Delete(delete))
+            },
+            Statement::Insert(mut insert) => {
+                insert.values = insert.values.into_iter()
+                    .map(|row_values| row_values.into_iter()
+                        .map(|(column_name, value)| Ok((column_name, bind_expression(value, params)?)))
+                        .collect())
+                    .collect::<Result<Vec<Vec<(String, Expression)>>, SimpleDbError>>()?;
+                Ok(Statement::Insert(insert))
+            },
+            Statement::Explain(statement) => Ok(Statement::Explain(Box::new(statement.bind(params)?))),
+            statement => Ok(statement),
+        }
+    }
+
     pub fn terminates_transaction(&self) -> bool {
         match *self {
             Statement::Rollback | Statement::Commit => true,
@@ -130,6 +280,7 @@ impl Statement {
 
     pub fn get_descriptor(&self) -> StatementDescriptor {
         match self {
+            Statement::Explain(statement) => statement.get_descriptor(),
             Statement::Select(_) => StatementDescriptor {
                 creates_transaction: false,
                 terminates_transaction: false,
@@ -166,6 +317,18 @@ impl Statement {
                 transaction_req: Requirement::Optional,
                 database_req: Requirement::ObligatoryToNotHave
             },
+            Statement::AlterTable(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::ObligatoryToHave
+            },
+            Statement::DropTable(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::Optional,
+                database_req: Requirement::ObligatoryToHave
+            },
             Statement::Describe(_) => StatementDescriptor {
                 creates_transaction: false,
                 terminates_transaction: false,
@@ -190,6 +353,13 @@ impl Statement {
                 transaction_req: Requirement::ObligatoryToHave,
                 database_req: Requirement::ObligatoryToHave
             },
+            Statement::Savepoint(_) |
+            Statement::RollbackToSavepoint(_) => StatementDescriptor {
+                creates_transaction: false,
+                terminates_transaction: false,
+                transaction_req: Requirement::ObligatoryToHave,
+                database_req: Requirement::ObligatoryToHave
+            },
             Statement::ShowDatabases => StatementDescriptor {
                 creates_transaction: false,
                 terminates_transaction: false,
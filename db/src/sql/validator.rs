@@ -1,10 +1,15 @@
 use crate::database::databases::Databases;
 use crate::simple_db::Context;
-use crate::sql::expression::Expression;
-use crate::sql::statement::{CreateTableStatement, DeleteStatement, InsertStatement, SelectStatement, Statement, UpdateStatement};
+use crate::sql::expression::{Expression, UnaryOperator};
+use crate::sql::aggregate;
+use crate::sql::aggregate::AggregateFunction;
+use crate::sql::join;
+use crate::sql::scalar_function::ScalarFunction;
+use crate::selection::Selection;
+use crate::sql::statement::{CreateTableStatement, DeleteStatement, InsertStatement, Projection, SelectStatement, Statement, UpdateStatement};
 use crate::table::table::Table;
 use crate::value::Type;
-use crate::CreateIndexStatement;
+use crate::{AlterTableStatement, CreateIndexStatement};
 use shared::SimpleDbError::UnknownColumn;
 use shared::SimpleDbError;
 use std::sync::Arc;
@@ -39,11 +44,16 @@ impl StatementValidator {
             Statement::CreateDatabase(database_name) => self.validate_create_database(database_name),
             Statement::ShowIndexes(table_name) => self.validate_show_indexes(context.database(), table_name),
             Statement::Describe(table) => self.validate_describe(context, table),
+            Statement::DropTable(table) => self.validate_drop_table(context, table),
+            Statement::AlterTable(statement) => self.validate_alter_table(context, statement),
+            Statement::Explain(statement) => self.validate(context, statement),
             Statement::StartTransaction |
             Statement::ShowDatabases |
             Statement::ShowTables |
             Statement::Rollback |
-            Statement::Commit => Ok(()),
+            Statement::Commit |
+            Statement::Savepoint(_) |
+            Statement::RollbackToSavepoint(_) => Ok(()),
         }
     }
 
@@ -54,7 +64,7 @@ impl StatementValidator {
     ) -> Result<(), SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(&statement.table_name)?;
-        table.validate_create_index(&statement.column_name)
+        table.validate_create_index(&statement.columns_names)
     }
 
     fn validate_create_database(
@@ -74,8 +84,129 @@ impl StatementValidator {
     ) -> Result<(), SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(&statement.table_name)?;
-        self.validate_where_expression(&statement.where_expr, &table)?;
+        match &statement.join {
+            Some(join) => self.validate_join(statement, &table, &database.get_table_or_err(&join.table_name)?),
+            None => self.validate_select_on_table(statement, &table, None),
+        }
+    }
+
+    fn validate_join(
+        &self,
+        statement: &SelectStatement,
+        table: &Arc<Table>,
+        join_table: &Arc<Table>,
+    ) -> Result<(), SimpleDbError> {
+        if table.table_name == join_table.table_name {
+            return Err(SimpleDbError::MalformedQuery(String::from("A table cannot be joined with itself")));
+        }
+        if statement.is_aggregation() {
+            return Err(SimpleDbError::MalformedQuery(String::from("Aggregations cannot be used with JOIN")));
+        }
+        if statement.where_expr.as_ref().is_some_and(|where_expr| where_expr.contains_exists()) {
+            return Err(SimpleDbError::MalformedQuery(String::from("EXISTS cannot be used with JOIN")));
+        }
+
+        //Once qualified, identifiers are resolved against both tables
+        let statement = join::qualify_identifiers(statement.clone(), table, join_table)?;
+        let on = statement.join.as_ref().map(|join| join.on.clone());
+        self.validate_where_expression(&on, table, Some(join_table))?;
+        self.validate_where_expression(&statement.where_expr, table, Some(join_table))?;
+        for projection in &statement.projection {
+            if let Projection::Expression(expression, _) = projection {
+                self.validate_expression(expression, table, Some(join_table))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    //outer_table is the table of the statement that contains the EXISTS subquery
+    fn validate_select_on_table(
+        &self,
+        statement: &SelectStatement,
+        table: &Arc<Table>,
+        outer_table: Option<&Arc<Table>>,
+    ) -> Result<(), SimpleDbError> {
+        self.validate_where_expression(&statement.where_expr, table, outer_table)?;
         table.validate_selection(&statement.selection)?;
+        for projection in &statement.projection {
+            if let Projection::Expression(expression, _) = projection {
+                self.validate_expression(expression, table, outer_table)?;
+            }
+        }
+        if statement.is_aggregation() {
+            self.validate_aggregation(statement, table)?;
+        }
+        Ok(())
+    }
+
+    //Subqueries are uncorrelated, they are validated against their own table. Returns the type of the only column returned
+    fn validate_subquery(
+        &self,
+        subquery: &SelectStatement,
+        table: &Arc<Table>
+    ) -> Result<Type, SimpleDbError> {
+        let subquery_table = table.database.get_table_or_err(&subquery.table_name)?;
+        if subquery.join.is_some() {
+            return Err(SimpleDbError::MalformedQuery(String::from("JOIN cannot be used in subqueries")));
+        }
+        self.validate_select_on_table(subquery, &subquery_table, None)?;
+
+        let mut columns_type: Vec<Type> = if subquery.is_aggregation() || subquery.has_expression_projection() {
+            aggregate::columns_desc(&subquery_table, &subquery.projection).into_iter()
+                .map(|column_desc| column_desc.column_type)
+                .collect()
+        } else {
+            match &subquery.selection {
+                Selection::Some(columns) => columns.iter()
+                    .map(|column_name| subquery_table.get_column_desc(column_name).unwrap().column_type)
+                    .collect(),
+                Selection::All => subquery_table.get_columns().into_values()
+                    .map(|column_desc| column_desc.column_type)
+                    .collect(),
+            }
+        };
+
+        if columns_type.len() != 1 {
+            return Err(SimpleDbError::MalformedQuery(String::from("Subquery should return a single column")));
+        }
+
+        Ok(columns_type.remove(0))
+    }
+
+    fn validate_aggregation(
+        &self,
+        statement: &SelectStatement,
+        table: &Arc<Table>
+    ) -> Result<(), SimpleDbError> {
+        if statement.projection.is_empty() {
+            return Err(SimpleDbError::MalformedQuery(String::from("* cannot be used with GROUP BY")));
+        }
+
+        for projection in &statement.projection {
+            match projection {
+                Projection::Column(column_name) => {
+                    if !statement.group_by.contains(column_name) {
+                        return Err(SimpleDbError::ColumnNotGrouped(column_name.clone()));
+                    }
+                },
+                Projection::Aggregate(function, None) => {
+                    if !matches!(function, AggregateFunction::Count) {
+                        return Err(SimpleDbError::MalformedQuery(format!("{} cannot be applied to *", function.name())));
+                    }
+                },
+                Projection::Aggregate(function, Some(column_name)) => {
+                    let column_desc = table.get_column_desc(column_name).unwrap();
+                    if function.requires_number() && !column_desc.column_type.is_number() {
+                        return Err(SimpleDbError::MalformedQuery(format!("{} can only be applied to number columns", function.name())));
+                    }
+                },
+                Projection::Expression(_, _) => {
+                    return Err(SimpleDbError::MalformedQuery(String::from("Expressions cannot be used with aggregations")));
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -86,14 +217,14 @@ impl StatementValidator {
     ) -> Result<(), SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(&statement.table_name)?;
-        self.validate_where_expression(&statement.where_expr, &table)?;
+        self.validate_where_expression(&statement.where_expr, &table, None)?;
 
         for (updated_column_name, updated_column_value_expr) in &statement.updated_values {
             let column_data = table.get_column_desc(updated_column_name)
                 .ok_or(SimpleDbError::ColumnNotFound(table.storage_keyspace_id, updated_column_name.clone()))?;
-            let expression_type_result = self.validate_expression(updated_column_value_expr, &table)?;
+            let expression_type_result = self.validate_expression(updated_column_value_expr, &table, None)?;
 
-            if !expression_type_result.can_be_casted(&column_data.column_type) {
+            if !column_data.column_type.can_be_casted(&expression_type_result) {
                 return Err(SimpleDbError::MalformedQuery(String::from("SET expression should produce a column value")))
             }
         }
@@ -118,7 +249,12 @@ impl StatementValidator {
     ) -> Result<(), SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(statement.table_name.as_str())?;
-        table.validate_column_values(&statement.values)
+        //Every row is validated before inserting anything, so rows of the statement are either all inserted or none
+        for row_values in &statement.rows_values()? {
+            table.validate_column_values(row_values)?;
+        }
+
+        Ok(())
     }
 
     fn validate_create_table(
@@ -137,18 +273,19 @@ impl StatementValidator {
     ) -> Result<(), SimpleDbError> {
         let database = self.databases.get_database_or_err(database_name)?;
         let table = database.get_table_or_err(&statement.table_name)?;
-        self.validate_where_expression(&statement.where_expr, &table)?;
+        self.validate_where_expression(&statement.where_expr, &table, None)?;
         Ok(())
     }
 
     fn validate_where_expression(
         &self,
         expression: &Option<Expression>,
-        table: &Arc<Table>
+        table: &Arc<Table>,
+        outer_table: Option<&Arc<Table>>,
     ) -> Result<(), SimpleDbError> {
         match expression {
             Some(expression) => {
-                let type_produced = self.validate_expression(expression, &table)?;
+                let type_produced = self.validate_expression(expression, &table, outer_table)?;
                 if !matches!(type_produced, Type::Boolean) {
                     Err(SimpleDbError::MalformedQuery(String::from("Expression should produce a boolean")))
                 } else {
@@ -159,6 +296,18 @@ impl StatementValidator {
         }
     }
 
+    fn validate_alter_table(&self, context: &Context, statement: &AlterTableStatement) -> Result<(), SimpleDbError> {
+        let database = self.databases.get_database_or_err(context.database())?;
+        let table = database.get_table_or_err(&statement.table_name)?;
+        table.validate_add_column(&statement.column_name, &statement.column_type, &statement.default_value)
+    }
+
+    fn validate_drop_table(&self, context: &Context, table_name: &str) -> Result<(), SimpleDbError> {
+        let database = self.databases.get_database_or_err(context.database())?;
+        database.get_table_or_err(table_name)?;
+        Ok(())
+    }
+
     fn validate_describe(&self, context: &Context, table_name: &str) -> Result<(), SimpleDbError> {
         let database = self.databases.get_database_or_err(context.database())?;
         database.get_table_or_err(table_name)?;
@@ -168,48 +317,107 @@ impl StatementValidator {
     fn validate_expression(
         &self,
         expression: &Expression,
-        table: &Arc<Table>
+        table: &Arc<Table>,
+        outer_table: Option<&Arc<Table>>,
     ) -> Result<Type, SimpleDbError> {
         match expression {
             Expression::Binary(operator, left, right) => {
-                let type_left = self.validate_expression(left, table)?;
-                let type_right = self.validate_expression(right, table)?;
+                let type_left = self.validate_expression(left, table, outer_table)?;
+                let type_right = self.validate_expression(right, table, outer_table)?;
 
-                if operator.is_logical() &&
-                    matches!(type_left, Type::Boolean) &&
-                    matches!(type_right, Type::Boolean) {
-                    Ok(Type::Boolean)
-                } else if operator.is_arithmetic() &&
-                    type_left.is_number() &&
-                    type_right.is_number() {
-
-                    if type_left.is_fp_number() || type_right.is_fp_number() {
-                        Ok(Type::F64)
-                    } else if type_left.is_signed_integer_number() || type_right.is_signed_integer_number() {
-                        Ok(Type::I64)
-                    } else {
-                        Ok(Type::U64)
-                    }
-                } else if operator.is_comparation() && type_left.is_comparable(&type_right) {
-                    Ok(Type::Boolean)
+                operator.produced_type(&type_left, &type_right)
+                    .ok_or(SimpleDbError::MalformedQuery(String::from("Expression produces wrong type")))
+            },
+            Expression::Unary(UnaryOperator::Not, expr) => {
+                let produced_type = self.validate_expression(expr, table, outer_table)?;
+                if !matches!(produced_type, Type::Boolean | Type::Null) {
+                    Err(SimpleDbError::MalformedQuery(String::from("NOT should be applied to a boolean")))
                 } else {
-                    Err(SimpleDbError::MalformedQuery(String::from("Expression produces wrong type")))
+                    Ok(Type::Boolean)
                 }
             },
             Expression::Unary(_, expr) => {
-                let produced_type = self.validate_expression(expr, table)?;
+                let produced_type = self.validate_expression(expr, table, outer_table)?;
                 if !produced_type.is_number() {
                     Err(SimpleDbError::MalformedQuery(String::from("Expression should produce a number")))
                 } else {
                     Ok(produced_type)
                 }
             }
-            Expression::Identifier(table_name) => {
-                table.get_column_desc(table_name)
-                    .ok_or(UnknownColumn(table_name.clone()))
+            Expression::In(expr, list) => {
+                let produced_type = self.validate_expression(expr, table, outer_table)?;
+                for item in list {
+                    let item_type = self.validate_expression(item, table, outer_table)?;
+                    if !produced_type.is_comparable(&item_type) && !item_type.is_comparable(&produced_type) {
+                        return Err(SimpleDbError::MalformedQuery(format!(
+                            "IN list values should be comparable with {:?}", produced_type
+                        )));
+                    }
+                }
+                Ok(Type::Boolean)
+            },
+            Expression::Between(expr, low, high) => {
+                let produced_type = self.validate_expression(expr, table, outer_table)?;
+                for bound in [low, high] {
+                    let bound_type = self.validate_expression(bound, table, outer_table)?;
+                    if !produced_type.is_comparable(&bound_type) && !bound_type.is_comparable(&produced_type) {
+                        return Err(SimpleDbError::MalformedQuery(format!(
+                            "BETWEEN bounds should be comparable with {:?}", produced_type
+                        )));
+                    }
+                }
+                Ok(Type::Boolean)
+            },
+            Expression::IsNull(expr) |
+            Expression::IsNotNull(expr) => {
+                self.validate_expression(expr, table, outer_table)?;
+                Ok(Type::Boolean)
+            },
+            Expression::Identifier(identifier) => {
+                let (table, column_name) = self.resolve_identifier(identifier, table, outer_table)?;
+                table.get_column_desc(column_name)
+                    .ok_or(UnknownColumn(identifier.clone()))
                     .map(|it| it.column_type)
             },
+            Expression::Function(name, args) => {
+                let function = ScalarFunction::from_name(name)
+                    .ok_or(SimpleDbError::MalformedQuery(format!("Unknown function {}", name)))?;
+                let args_type = args.iter()
+                    .map(|arg| self.validate_expression(arg, table, outer_table))
+                    .collect::<Result<Vec<Type>, SimpleDbError>>()?;
+                function.validate_args(&args_type)
+            },
             Expression::Literal(value) => Ok(value.to_type()),
+            Expression::Subquery(subquery) => self.validate_subquery(subquery, table),
+            Expression::Exists(subquery) => {
+                if subquery.join.is_some() {
+                    return Err(SimpleDbError::MalformedQuery(String::from("JOIN cannot be used in subqueries")));
+                }
+                let subquery_table = table.database.get_table_or_err(&subquery.table_name)?;
+                self.validate_select_on_table(subquery, &subquery_table, Some(table))?;
+                Ok(Type::Boolean)
+            },
+            Expression::Placeholder(_) => Err(SimpleDbError::MalformedQuery(String::from(
+                "Placeholders should be bound before executing the statement"
+            ))),
+        }
+    }
+
+    //Identifiers can be qualified with the table name. Columns of the outer table can only be referenced if they are
+    //qualified, unqualified identifiers always belong to table
+    fn resolve_identifier<'a>(
+        &self,
+        identifier: &'a str,
+        table: &'a Arc<Table>,
+        outer_table: Option<&'a Arc<Table>>,
+    ) -> Result<(&'a Arc<Table>, &'a str), SimpleDbError> {
+        match identifier.split_once('.') {
+            Some((table_name, column_name)) if table_name == table.table_name => Ok((table, column_name)),
+            Some((table_name, column_name)) => match outer_table {
+                Some(outer_table) if table_name == outer_table.table_name => Ok((outer_table, column_name)),
+                _ => Err(UnknownColumn(identifier.to_string())),
+            },
+            None => Ok((table, identifier)),
         }
     }
 
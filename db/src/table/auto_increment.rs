@@ -0,0 +1,68 @@
+//Number of ids reserved each time the table descriptor is written
+const RESERVED_IDS_BATCH: i64 = 64;
+
+//Counter of AUTOINCREMENT primary columns. Ids are reserved in batches. The end of the last reserved batch is saved
+//in the table descriptor before any of its ids is used. After a restart, ids start from the end of the last batch,
+//so the ids of the batch that were not used are skipped, but ids are never returned twice
+pub struct AutoIncrement {
+    next_id: i64,
+    //Ids lower than this might have been used
+    reserved_until: i64,
+}
+
+impl AutoIncrement {
+    pub fn create(reserved_until: i64) -> AutoIncrement {
+        AutoIncrement { next_id: reserved_until.max(1), reserved_until }
+    }
+
+    //Returns the id and the new end of the reserved ids, if it has to be saved before the id is used
+    pub fn next(&mut self) -> (i64, Option<i64>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        (id, self.reserve())
+    }
+
+    //Called when a row is inserted with an explicit id. Next ids will be greater than it
+    pub fn on_id_inserted(&mut self, id: i64) -> Option<i64> {
+        if id >= self.next_id {
+            self.next_id = id + 1;
+        }
+        self.reserve()
+    }
+
+    pub fn reserved_until(&self) -> i64 {
+        self.reserved_until
+    }
+
+    fn reserve(&mut self) -> Option<i64> {
+        if self.next_id > self.reserved_until {
+            self.reserved_until = self.next_id + RESERVED_IDS_BATCH;
+            Some(self.reserved_until)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::table::auto_increment::AutoIncrement;
+
+    #[test]
+    fn next() {
+        let mut auto_increment = AutoIncrement::create(0);
+        assert_eq!(auto_increment.next(), (1, Some(66)));
+        for id in 2..=65 {
+            assert_eq!(auto_increment.next(), (id, None));
+        }
+        assert_eq!(auto_increment.next(), (66, Some(131)));
+
+        assert_eq!(auto_increment.on_id_inserted(10), None);
+        assert_eq!(auto_increment.on_id_inserted(200), Some(265));
+        assert_eq!(auto_increment.next(), (201, None));
+
+        //Restart
+        let mut auto_increment = AutoIncrement::create(auto_increment.reserved_until());
+        assert_eq!(auto_increment.next(), (265, Some(330)));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::table::record::Record;
+use bytes::{BufMut, Bytes};
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::key::Key;
+use shared::{ColumnId, SimpleDbError, TxnId};
+
+//Key of a column value in tables stored with RowFormat::ColumnChunked:
+// Column ID (u16 big endian) | Primary key bytes |
+//The values of a column are stored next to each other ordered by primary key, so SSTable blocks contain chunks of a
+//single column. The value is a Record with the column value. The primary column chunk contains an empty Record per row,
+//so rows without any other column value still exist
+pub(crate) fn column_chunk_key(column_id: ColumnId, primary_key: &Bytes) -> Bytes {
+    let mut key = Vec::with_capacity(size_of::<ColumnId>() + primary_key.len());
+    key.put_u16(column_id);
+    key.extend(primary_key);
+    Bytes::from(key)
+}
+
+//Merges the column chunks of the selected columns of a table stored with RowFormat::ColumnChunked.
+//Returns an entry per row, like tables stored with RowFormat::Row: the key is the primary key & the value is the
+//serialized Record with the selected columns of the row. Only the chunks of the selected columns are read.
+pub struct ColumnChunkedIterator<I: StorageIterator> {
+    column_chunks: Vec<ColumnChunk<I>>,
+    first_iteration: bool,
+    current_key: Option<Key>,
+    current_value: Vec<u8>,
+}
+
+struct ColumnChunk<I: StorageIterator> {
+    column_id: ColumnId,
+    iterator: I,
+    //Primary key & value of the next entry in the chunk. None if the whole chunk has been read
+    next_entry: Option<(Key, Record)>,
+    //Set if the chunk couldn't be read
+    error: Option<SimpleDbError>,
+}
+
+impl<I: StorageIterator> ColumnChunkedIterator<I> {
+    //Expect iterators to be seeked to the start of each column chunk
+    pub(crate) fn create(iterators: Vec<(ColumnId, I)>) -> ColumnChunkedIterator<I> {
+        ColumnChunkedIterator {
+            column_chunks: iterators.into_iter()
+                .map(|(column_id, iterator)| ColumnChunk { column_id, iterator, next_entry: None, error: None })
+                .collect(),
+            current_value: Vec::new(),
+            first_iteration: true,
+            current_key: None,
+        }
+    }
+
+    fn min_primary_key(&self) -> Option<Bytes> {
+        self.column_chunks.iter()
+            .filter_map(|column_chunk| column_chunk.next_entry.as_ref())
+            .map(|(primary_key, _)| primary_key.as_bytes())
+            .min()
+            .cloned()
+    }
+
+    fn has_failed(&self) -> bool {
+        self.column_chunks.iter().any(|column_chunk| column_chunk.error.is_some())
+    }
+}
+
+impl<I: StorageIterator> ColumnChunk<I> {
+    fn advance(&mut self) {
+        self.next_entry = None;
+
+        if self.iterator.next() {
+            let key = self.iterator.key().as_bytes();
+            //The next column chunk has been reached
+            if key.len() < size_of::<ColumnId>() || key[..size_of::<ColumnId>()] != self.column_id.to_be_bytes() {
+                return;
+            }
+
+            let primary_key = Key::create(key.slice(size_of::<ColumnId>()..), self.iterator.key().txn_id());
+            let record = Record::deserialize(self.iterator.value().to_vec());
+            self.next_entry = Some((primary_key, record));
+        } else {
+            self.error = self.iterator.take_error();
+        }
+    }
+
+    //Returns the column value & the txn_id that wrote it, if the next entry is from the given row
+    fn take_if_row(&mut self, primary_key: &Bytes) -> Option<(Record, TxnId)> {
+        match &self.next_entry {
+            Some((next_primary_key, _)) if next_primary_key.as_bytes() == primary_key => {
+                let (next_primary_key, record) = self.next_entry.take().unwrap();
+                self.advance();
+                Some((record, next_primary_key.txn_id()))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl<I: StorageIterator> StorageIterator for ColumnChunkedIterator<I> {
+    fn next(&mut self) -> bool {
+        if self.first_iteration {
+            for column_chunk in &mut self.column_chunks {
+                column_chunk.advance();
+            }
+            self.first_iteration = false;
+        }
+
+        let Some(primary_key) = self.min_primary_key().filter(|_| !self.has_failed()) else {
+            self.current_key = None;
+            return false;
+        };
+
+        let mut record_builder = Record::builder();
+        //The row is returned with the txn_id of its last write
+        let mut txn_id = 0;
+        for column_chunk in &mut self.column_chunks {
+            if let Some((record, column_txn_id)) = column_chunk.take_if_row(&primary_key) {
+                record_builder.add_record(record);
+                txn_id = txn_id.max(column_txn_id);
+            }
+        }
+        //Rows are not returned if some of their columns couldn't be read
+        if self.has_failed() {
+            self.current_key = None;
+            return false;
+        }
+
+        self.current_value = record_builder.build().serialize();
+        self.current_key = Some(Key::create(primary_key, txn_id));
+        true
+    }
+
+    fn has_next(&self) -> bool {
+        !self.has_failed() && (self.first_iteration || self.column_chunks.iter().any(|column_chunk| column_chunk.next_entry.is_some()))
+    }
+
+    fn key(&self) -> &Key {
+        self.current_key.as_ref().unwrap()
+    }
+
+    fn value(&self) -> &[u8] {
+        &self.current_value
+    }
+
+    fn take_error(&mut self) -> Option<SimpleDbError> {
+        self.column_chunks.iter_mut().find_map(|column_chunk| column_chunk.error.take())
+    }
+
+    //Seeks every column chunk to the given primary key
+    fn seek(&mut self, primary_key: &Bytes, inclusive: bool) {
+        for column_chunk in &mut self.column_chunks {
+            column_chunk.iterator.seek(&column_chunk_key(column_chunk.column_id, primary_key), inclusive);
+        }
+    }
+}
@@ -5,3 +5,6 @@ pub(crate) mod table_descriptor;
 pub(crate) mod record;
 pub(crate) mod table_iterator;
 pub(crate) mod table_flags;
+pub(crate) mod table_storage_iterator;
+pub(crate) mod column_chunked_iterator;
+pub(crate) mod auto_increment;
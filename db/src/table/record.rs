@@ -43,19 +43,6 @@ impl Record {
         None
     }
 
-    pub fn take_value(&mut self, column_id_lookup: ColumnId) -> Option<Bytes> {
-        for (current_index, current_entry) in self.data_records.iter().enumerate() {
-            let (current_column_id, _) = current_entry;
-
-            if *current_column_id == column_id_lookup {
-                let (_, value) =  self.data_records.remove(current_index);
-                return Some(value);
-            }
-        }
-
-        None
-    }
-
     fn get_column_id_index(&self, column_id_lookup: ColumnId) -> Option<usize> {
         let mut current_column_index = 0;
 
@@ -97,6 +84,25 @@ impl Record {
         Record { data_records }
     }
 
+    //Only the values of the given columns are copied, the rest are skipped
+    pub fn deserialize_projected(bytes: &[u8], columns_id: &[ColumnId]) -> Record {
+        let mut current_ptr = bytes;
+        let mut data_records: Vec<(ColumnId, Bytes)> = Vec::with_capacity(columns_id.len());
+
+        while current_ptr.has_remaining() {
+            let column_id = current_ptr.get_u16_le() as ColumnId;
+            let column_value_length = current_ptr.get_u32_le() as usize;
+
+            if columns_id.contains(&column_id) {
+                data_records.push((column_id, Bytes::copy_from_slice(&current_ptr[..column_value_length])));
+            }
+
+            current_ptr.advance(column_value_length);
+        }
+
+        Record { data_records }
+    }
+
     pub fn builder() -> RecordBuilder {
         RecordBuilder { data_records: Vec::new() }
     }
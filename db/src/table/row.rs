@@ -1,9 +1,11 @@
 use crate::table::record::Record;
 use crate::table::table::Table;
+use crate::table::table_descriptor::ColumnDescriptor;
 use crate::value::{Type, Value};
 use bytes::{BufMut, Bytes};
 use shared::SimpleDbError::CannotDecodeColumn;
-use shared::{utils, SimpleDbError};
+use shared::{timestamp, utils, SimpleDbError};
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Formatter;
 use std::sync::Arc;
@@ -13,7 +15,9 @@ pub struct Row {
     pub(crate) storage_engine_record: Record,
     pub(crate) key_bytes: Bytes,
 
-    pub(crate) table: Arc<Table>
+    pub(crate) table: Arc<Table>,
+    //Rows that are not read directly from the table (like aggregations) don't use the table columns
+    pub(crate) columns_desc: Option<Arc<Vec<ColumnDescriptor>>>,
 }
 
 impl Row {
@@ -23,6 +27,21 @@ impl Row {
         key_bytes: Bytes
     ) -> Row {
         Row {
+            storage_engine_record,
+            table: table.clone(),
+            columns_desc: None,
+            key_bytes
+        }
+    }
+
+    pub(crate) fn create_derived(
+        storage_engine_record: Record,
+        columns_desc: &Arc<Vec<ColumnDescriptor>>,
+        table: &Arc<Table>,
+        key_bytes: Bytes
+    ) -> Row {
+        Row {
+            columns_desc: Some(columns_desc.clone()),
             storage_engine_record,
             table: table.clone(),
             key_bytes
@@ -36,7 +55,7 @@ impl Row {
     //Expect column_name to have been validated before calling this function
     //If emtpy, value will contain Value::Null
     pub fn get_column_value(&self, column_name: &str) -> Result<Value, SimpleDbError> {
-        let column_data = self.table.get_column_desc(column_name)
+        let column_data = self.get_column_desc(column_name)
             .ok_or(SimpleDbError::ColumnNotFound(self.table.storage_keyspace_id, column_name.to_string()))?;
 
         match self.storage_engine_record.get_value(column_data.column_id) {
@@ -46,6 +65,24 @@ impl Row {
         }
     }
 
+    fn get_column_desc(&self, column_name: &str) -> Option<ColumnDescriptor> {
+        match &self.columns_desc {
+            Some(columns_desc) => columns_desc.iter()
+                .find(|column_desc| column_desc.column_name == column_name)
+                .cloned(),
+            None => self.table.get_column_desc(column_name),
+        }
+    }
+
+    fn get_columns(&self) -> HashMap<String, ColumnDescriptor> {
+        match &self.columns_desc {
+            Some(columns_desc) => columns_desc.iter()
+                .map(|column_desc| (column_desc.column_name.clone(), column_desc.clone()))
+                .collect(),
+            None => self.table.get_columns(),
+        }
+    }
+
     pub fn serialize(self) -> Vec<u8> {
         let mut serialized: Vec<u8> = Vec::new();
         serialized.put_u32_le(self.storage_engine_record.get_n_columns() as u32);
@@ -57,17 +94,17 @@ impl Row {
 impl fmt::Display for Row {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut string = String::from("Row [");
-        let n_columns = self.table.get_columns().len();
+        let columns = self.get_columns();
+        let n_columns = columns.len();
         let mut count = 0;
 
-        let columns = self.table.get_columns();
         let mut column_names: Vec<_> = columns.keys().collect();
         column_names.sort_by(|a, b| {
             (*a).cmp(b)
         });
 
         for column in column_names {
-            let column = self.table.get_column_desc(column).unwrap();
+            let column = self.get_column_desc(column).unwrap();
             if let Some(column_value) = self.storage_engine_record.get_value(column.column_id) {
                 string.push_str(&column.column_name);
                 string.push_str(" = ");
@@ -86,6 +123,7 @@ impl fmt::Display for Row {
                     Type::String => String::from_utf8(column_value.to_vec()).unwrap(),
                     Type::Date => todo!(),
                     Type::Blob => format!("Blob {} bytes long", column_value.len()),
+                    Type::Timestamp => timestamp::format_iso8601(utils::bytes_to_i64_le(column_value)),
                     Type::Null => panic!("")
                 }).as_str());
 
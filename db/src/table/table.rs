@@ -1,22 +1,29 @@
 use crate::database::database::Database;
+use crate::index::composite_key;
+use crate::index::index_consistency_report::IndexConsistencyReport;
 use crate::index::index_creation_task::IndexCreationTask;
 use crate::index::index_type::IndexType;
 use crate::index::secondary_index_iterator::SecondaryIndexIterator;
 use crate::index::secondary_indexes::SecondaryIndexes;
 use crate::selection::Selection;
+use crate::sql::statement::CreateTableColumn;
+use crate::table::auto_increment::AutoIncrement;
 use crate::table::record::Record;
 use crate::table::row::Row;
-use crate::table::table_descriptor::{ColumnDescriptor, TableDescriptor};
-use crate::table::table_flags::KEYSPACE_TABLE_USER;
+use crate::table::table_descriptor::{ColumnDescriptor, CompositeIndexDescriptor, TableDescriptor};
+use crate::table::column_chunked_iterator::{column_chunk_key, ColumnChunkedIterator};
+use crate::table::table_flags::{KEYSPACE_TABLE_COLUMN_CHUNKED, KEYSPACE_TABLE_USER};
 use crate::table::table_iterator::TableIterator;
+use crate::table::table_storage_iterator::TableStorageIterator;
 use crate::value::{Type, Value};
 use bytes::Bytes;
 use crossbeam_skiplist::SkipMap;
-use shared::SimpleDbError::{CannotWriteTableDescriptor, ColumnNameAlreadyDefined, ColumnNotFound, IndexAlreadyExists, InvalidType, OnlyOnePrimaryColumnAllowed, PrimaryColumnNotIncluded, UnknownColumn};
-use shared::{ColumnId, FlagMethods, KeyspaceId, SimpleDbError, SimpleDbFile, SimpleDbOptions};
+use shared::SimpleDbError::{CannotWriteTableDescriptor, ColumnNameAlreadyDefined, ColumnNotFound, IndexAlreadyExists, InvalidType, MalformedQuery, NotNullViolation, OnlyOnePrimaryColumnAllowed, PrimaryColumnNotIncluded, UniqueViolation, UnknownColumn};
+use shared::{ColumnId, FlagMethods, KeyspaceId, RowFormat, SimpleDbError, SimpleDbFile, SimpleDbOptions};
 use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
 use std::sync::atomic::Ordering::Relaxed;
-use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::atomic::{fence, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use storage::transactions::transaction::Transaction;
 use storage::{SimpleDbStorageIterator, Storage};
@@ -24,6 +31,7 @@ use storage::{SimpleDbStorageIterator, Storage};
 pub struct Table {
     pub(crate) storage_keyspace_id: KeyspaceId,
     pub(crate) table_name: String,
+    pub(crate) row_format: RowFormat,
 
     pub(crate) table_descriptor_file: Mutex<SimpleDbFile>,
 
@@ -35,8 +43,19 @@ pub struct Table {
     pub(crate) storage: Arc<storage::Storage>,
 
     pub(crate) secondary_indexes: SecondaryIndexes,
+    //Indexes on several columns. Single column indexes are stored in ColumnDescriptor
+    pub(crate) composite_indexes: Mutex<Vec<CompositeIndexDescriptor>>,
+    //Some if the primary column is AUTOINCREMENT
+    pub(crate) auto_increment: Mutex<Option<AutoIncrement>>,
 
-    pub(crate) database: Arc<Database>
+    pub(crate) database: Arc<Database>,
+
+    //Incremented on every write to the table. Used to invalidate cached query results
+    pub(crate) version: AtomicU64,
+
+    //Number of plans that read the table with a full scan or with a secondary index lookup
+    pub(crate) n_full_scans: AtomicUsize,
+    pub(crate) n_secondary_index_scans: AtomicUsize,
 }
 
 impl Table {
@@ -47,7 +66,10 @@ impl Table {
         primary_column_name: String,
         database: Arc<Database>
     ) -> Result<Arc<Table>, SimpleDbError> {
-        let table_keyspace_id = storage.create_keyspace(KEYSPACE_TABLE_USER)?;
+        let table_keyspace_id = storage.create_keyspace(match options.row_format {
+            RowFormat::Row => KEYSPACE_TABLE_USER,
+            RowFormat::ColumnChunked => KEYSPACE_TABLE_USER | KEYSPACE_TABLE_COLUMN_CHUNKED,
+        })?;
         let (table_descriptor, table_descriptor_file) = TableDescriptor::create(
             table_keyspace_id,
             options,
@@ -59,12 +81,18 @@ impl Table {
         Ok(Arc::new(Table {
             table_descriptor_file: Mutex::new(table_descriptor_file),
             next_column_id: AtomicUsize::new(max_column_id as usize + 1),
-            secondary_indexes: SecondaryIndexes::create_empty(storage.clone(), table_name),
+            secondary_indexes: SecondaryIndexes::create_empty(options.clone(), storage.clone(), table_name),
+            composite_indexes: Mutex::new(table_descriptor.composite_indexes),
+            auto_increment: Mutex::new(None),
             columns_by_id: table_descriptor.columns,
+            row_format: options.row_format,
             table_name: table_descriptor.table_name,
             storage_keyspace_id: table_keyspace_id,
             columns_by_name: SkipMap::new(),
             storage: storage.clone(),
+            version: AtomicU64::new(0),
+            n_full_scans: AtomicUsize::new(0),
+            n_secondary_index_scans: AtomicUsize::new(0),
             primary_column_name,
             database
         }))
@@ -83,7 +111,10 @@ impl Table {
             if flags.has(KEYSPACE_TABLE_USER) {
                 let (descriptor, descriptor_file) = TableDescriptor::load_from_disk(options, keyspace_id)?;
                 tables.push(Arc::new(Table {
-                    secondary_indexes: SecondaryIndexes::load_secondary_indexes(&descriptor, storage.clone()),
+                    secondary_indexes: SecondaryIndexes::load_secondary_indexes(&descriptor, options.clone(), storage.clone()),
+                    composite_indexes: Mutex::new(descriptor.composite_indexes.clone()),
+                    auto_increment: Mutex::new(descriptor.auto_increment_reserved_until.map(AutoIncrement::create)),
+                    row_format: if flags.has(KEYSPACE_TABLE_COLUMN_CHUNKED) { RowFormat::ColumnChunked } else { RowFormat::Row },
                     next_column_id: AtomicUsize::new(descriptor.get_max_column_id() as usize + 1),
                     columns_by_name: Self::index_column_id_by_name(&descriptor.columns),
                     primary_column_name: descriptor.get_primary_column_name(),
@@ -92,7 +123,10 @@ impl Table {
                     storage_keyspace_id: keyspace_id,
                     columns_by_id: descriptor.columns,
                     storage: storage.clone(),
-                    database: database.clone()
+                    database: database.clone(),
+                    version: AtomicU64::new(0),
+                    n_full_scans: AtomicUsize::new(0),
+                    n_secondary_index_scans: AtomicUsize::new(0),
                 }));
             }
         }
@@ -104,16 +138,22 @@ impl Table {
         let options = Arc::new(SimpleDbOptions::default());
         let columns_by_id = SkipMap::new();
         let mut primary_column_name = String::from("");
+        let mut indexed_columns_id = Vec::new();
         for column in columns {
             if column.is_primary {
                 primary_column_name = column.column_name.clone();
             }
+            if column.secondary_index_keyspace_id.is_some() {
+                indexed_columns_id.push(column.column_id);
+            }
 
             columns_by_id.insert(column.column_id, column);
         }
 
         Arc::new(Table {
-            secondary_indexes: SecondaryIndexes::create_mock(options.clone()),
+            secondary_indexes: SecondaryIndexes::create_mock(options.clone(), indexed_columns_id),
+            composite_indexes: Mutex::new(Vec::new()),
+            auto_increment: Mutex::new(None),
             columns_by_name: Self::index_column_id_by_name(&columns_by_id),
             table_descriptor_file: Mutex::new(SimpleDbFile::mock()),
             storage: Arc::new(Storage::create_mock(&options)),
@@ -121,6 +161,10 @@ impl Table {
             next_column_id: AtomicUsize::new(1),
             table_name: String::from("Mock"),
             storage_keyspace_id: 1,
+            row_format: RowFormat::Row,
+            version: AtomicU64::new(0),
+            n_full_scans: AtomicUsize::new(0),
+            n_secondary_index_scans: AtomicUsize::new(0),
             primary_column_name,
             columns_by_id,
         })
@@ -128,14 +172,55 @@ impl Table {
 
     pub fn add_columns(
         &self,
-        columns_to_add: Vec<(String, Type, bool)>,
+        columns_to_add: Vec<CreateTableColumn>,
     ) -> Result<(), SimpleDbError> {
-        for (column_name, column_type, is_primary) in columns_to_add {
-            self.add_column(&column_name, column_type, is_primary)?
+        for column_to_add in columns_to_add {
+            self.add_column(column_to_add)?
         }
         Ok(())
     }
 
+    //Adds a column to an existing table. Existing rows are not rewritten: as they don't contain the column,
+    //they will return the default value, or null if there is no default value
+    //Expect call to validate_add_column before calling this function
+    pub fn add_column_with_default(
+        &self,
+        column_name: &str,
+        column_type: Type,
+        default_value: Option<Value>,
+    ) -> Result<(), SimpleDbError> {
+        self.add_column(CreateTableColumn {
+            column_name: column_name.to_string(),
+            is_primary: false,
+            is_not_null: false,
+            is_unique: false,
+            is_auto_increment: false,
+            column_type,
+            default_value,
+        })?;
+        //Cached query results don't contain the new column
+        self.version.fetch_add(1, Relaxed);
+        Ok(())
+    }
+
+    pub fn validate_add_column(
+        &self,
+        column_name: &str,
+        column_type: &Type,
+        default_value: &Option<Value>,
+    ) -> Result<(), SimpleDbError> {
+        if self.columns_by_name.contains_key(column_name) {
+            return Err(ColumnNameAlreadyDefined(column_name.to_string()));
+        }
+        if let Some(default_value) = default_value {
+            if !default_value.can_be_stored_as(column_type) {
+                return Err(InvalidType(column_name.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn get_by_primary_column(
         self: &Arc<Self>,
         key: &Bytes,
@@ -146,18 +231,17 @@ impl Table {
             return Ok(None);
         }
 
-        let mut table_iterator = TableIterator::create(
-            self.storage.scan_from_key_with_transaction(
-                transaction,
-                self.storage_keyspace_id,
-                key,
-                true
-            )?,
-            self.selection_to_columns_id(selection)?,
-            self.clone()
-        );
+        let selection = self.selection_to_columns_id(selection)?;
+        let storage_iterator = match self.row_format {
+            //SSTables whose bloom filter rejects the key are not read
+            RowFormat::Row => TableStorageIterator::Row(Box::new(
+                self.storage.scan_key_with_transaction(transaction, self.storage_keyspace_id, key)?
+            )),
+            RowFormat::ColumnChunked => self.scan_storage(transaction, Some((key, true)), &selection)?,
+        };
+        let mut table_iterator = TableIterator::create(storage_iterator, selection, self.clone());
         if !table_iterator.next() {
-            return Ok(None);
+            return table_iterator.take_error().map_or(Ok(None), Err);
         }
 
         let row = table_iterator.row();
@@ -174,14 +258,9 @@ impl Table {
         inclusive: bool,
         transaction: &Transaction,
         selection: &Selection,
-    ) -> Result<TableIterator<SimpleDbStorageIterator>, SimpleDbError> {
+    ) -> Result<TableIterator<TableStorageIterator>, SimpleDbError> {
         let selection = self.selection_to_columns_id(&selection)?;
-        let storage_iterator = self.storage.scan_from_key_with_transaction(
-            transaction,
-            self.storage_keyspace_id,
-            key,
-            inclusive
-        )?;
+        let storage_iterator = self.scan_storage(transaction, Some((key, inclusive)), &selection)?;
 
         Ok(TableIterator::create(
             storage_iterator,
@@ -194,9 +273,9 @@ impl Table {
         self: &Arc<Self>,
         transaction: &Transaction,
         selection: Selection
-    ) -> Result<TableIterator<SimpleDbStorageIterator>, SimpleDbError> {
+    ) -> Result<TableIterator<TableStorageIterator>, SimpleDbError> {
         let selection = self.selection_to_columns_id(&selection)?;
-        let storage_iterator = self.storage.scan_all_with_transaction(transaction, self.storage_keyspace_id)?;
+        let storage_iterator = self.scan_storage(transaction, None, &selection)?;
 
         Ok(TableIterator::create(
             storage_iterator,
@@ -205,6 +284,70 @@ impl Table {
         ))
     }
 
+    //Like scan_all, but the columns are given by their ids. Values of other columns are skipped when decoding
+    //the records, so they are not copied
+    pub fn scan_all_projected(
+        self: &Arc<Self>,
+        transaction: &Transaction,
+        columns_id: Vec<ColumnId>
+    ) -> Result<TableIterator<TableStorageIterator>, SimpleDbError> {
+        for column_id in &columns_id {
+            if !self.columns_by_id.contains_key(column_id) {
+                return Err(SimpleDbError::ColumnNotFound(self.storage_keyspace_id, column_id.to_string()));
+            }
+        }
+
+        let storage_iterator = self.scan_storage(transaction, None, &columns_id)?;
+
+        Ok(TableIterator::create(
+            storage_iterator,
+            columns_id,
+            self.clone()
+        ))
+    }
+
+    //Returns an entry per row, starting from the given primary key, with at least the given columns.
+    //Tables stored with RowFormat::ColumnChunked only read the chunks of the given columns
+    pub(crate) fn scan_storage(
+        &self,
+        transaction: &Transaction,
+        start_key: Option<(&Bytes, bool)>, //Primary key & inclusive
+        columns_id: &Vec<ColumnId>,
+    ) -> Result<TableStorageIterator, SimpleDbError> {
+        match (self.row_format, start_key) {
+            (RowFormat::Row, Some((start_key, inclusive))) => Ok(TableStorageIterator::Row(Box::new(
+                self.storage.scan_from_key_with_transaction(transaction, self.storage_keyspace_id, start_key, inclusive)?
+            ))),
+            (RowFormat::Row, None) => Ok(TableStorageIterator::Row(Box::new(
+                self.storage.scan_all_with_transaction(transaction, self.storage_keyspace_id)?
+            ))),
+            (RowFormat::ColumnChunked, start_key) => {
+                let (start_key, inclusive) = start_key
+                    .map(|(start_key, inclusive)| (start_key.clone(), inclusive))
+                    .unwrap_or((Bytes::new(), true));
+                //Every row has an entry in the primary column chunk
+                let mut chunks_column_id = vec![self.get_primary_column_data().unwrap().column_id];
+                for column_id in columns_id {
+                    if !chunks_column_id.contains(column_id) {
+                        chunks_column_id.push(*column_id);
+                    }
+                }
+
+                let mut iterators = Vec::new();
+                for column_id in chunks_column_id {
+                    iterators.push((column_id, self.storage.scan_from_key_with_transaction(
+                        transaction,
+                        self.storage_keyspace_id,
+                        &column_chunk_key(column_id, &start_key),
+                        inclusive
+                    )?));
+                }
+
+                Ok(TableStorageIterator::ColumnChunked(ColumnChunkedIterator::create(iterators)))
+            }
+        }
+    }
+
     pub fn scan_from_key_secondary_index(
         self: &Arc<Self>,
         key: &Bytes,
@@ -218,6 +361,18 @@ impl Table {
         Ok(iterator)
     }
 
+    pub fn scan_range_secondary_index(
+        self: &Arc<Self>,
+        transaction: &Transaction,
+        column_name: &str,
+        start: Bound<Bytes>,
+        end: Bound<Bytes>,
+    ) -> Result<SecondaryIndexIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        let column_id = self.get_column_desc_or_err(column_name)?
+            .column_id;
+        self.secondary_indexes.scan_range(transaction, column_id, start, end)
+    }
+
     pub fn scan_all_secondary_index(
         self: &Arc<Self>,
         transaction: &Transaction,
@@ -228,25 +383,56 @@ impl Table {
         self.secondary_indexes.scan_all(transaction, column_id)
     }
 
+    //Returns the primary keys of the rows whose values of the first columns of the composite index are the
+    //serialized values passed
+    pub fn scan_composite_secondary_index(
+        self: &Arc<Self>,
+        transaction: &Transaction,
+        columns_names: &[String],
+        leading_values: &[Bytes],
+    ) -> Result<SecondaryIndexIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        let columns_id = self.get_columns_id(columns_names)?;
+        let leading_values: Vec<Option<&Bytes>> = leading_values.iter().map(Some).collect();
+        let prefix = composite_key::encode(&leading_values);
+        self.secondary_indexes.scan_composite_prefix(transaction, &columns_id, prefix)
+    }
+
+    pub fn get_composite_index_keyspace_id(&self, columns_names: &[String]) -> Option<KeyspaceId> {
+        let columns_id = self.get_columns_id(columns_names).ok()?;
+        self.composite_indexes.lock().unwrap().iter()
+            .find(|composite_index| composite_index.columns_id == columns_id)
+            .map(|composite_index| composite_index.keyspace_id)
+    }
+
+    //Returns the columns names of the composite indexes that can be used by queries
+    pub fn get_readable_composite_indexes(&self) -> Vec<Vec<String>> {
+        self.secondary_indexes.get_readable_composite_indexes().into_iter()
+            .map(|columns_id| columns_id.iter()
+                .map(|column_id| self.columns_by_id.get(column_id).unwrap().value().column_name.clone())
+                .collect())
+            .collect()
+    }
+
+    //Creates a composite index if more than one column is passed
+    //Expect call to validate_create_index before calling this function
     pub fn create_secondary_index(
         self: &Arc<Self>,
-        column_name: &str,
+        columns_names: &[String],
         wait: bool
     ) -> Result<usize, SimpleDbError> {
-        let column = self.get_column_desc(column_name).unwrap();
+        let columns_id = self.get_columns_id(columns_names)?;
 
-        if self.secondary_indexes.can_be_read(column.column_id) {
-            return Err(IndexAlreadyExists(self.storage_keyspace_id, column_name.to_string()));
+        if self.secondary_indexes.exists(&columns_id) {
+            return Err(IndexAlreadyExists(self.storage_keyspace_id, columns_names.join(", ")));
         }
 
-        let index_keyspace_id = self.secondary_indexes.create_new_secondary_index(column.column_id)?;
+        let index_keyspace_id = self.secondary_indexes.create_new_secondary_index(&columns_id)?;
         //Before we start reading all the SSTables and Memtables, make sure the new secondary index is visible for writers
         fence(Ordering::Release);
 
         let (task, receiver) = IndexCreationTask::create(
-            column.column_id,
+            columns_id.clone(),
             index_keyspace_id,
-            self.storage_keyspace_id,
             self.database.clone(),
             self.storage.clone(),
             self.clone(),
@@ -266,11 +452,31 @@ impl Table {
 
         }
 
-        self.save_column_descriptor_as_indexed(column.column_id, index_keyspace_id)?;
+        self.save_index(&columns_id, index_keyspace_id)?;
 
         Ok(n_affected_rows)
     }
 
+    //Removes all the table data, including its secondary indexes
+    //The table keyspace is dropped first, so if we crash in the middle, the table won't be loaded again
+    pub(crate) fn drop_keyspaces(&self) -> Result<(), SimpleDbError> {
+        self.storage.drop_keyspace(self.storage_keyspace_id)?;
+
+        for entry in self.columns_by_id.iter() {
+            if let Some(secondary_index_keyspace_id) = entry.value().secondary_index_keyspace_id {
+                self.storage.drop_keyspace(secondary_index_keyspace_id)?;
+            }
+        }
+        for composite_index in self.composite_indexes.lock().unwrap().iter() {
+            self.storage.drop_keyspace(composite_index.keyspace_id)?;
+        }
+
+        //If it cannot be deleted, it will be overwritten when the keyspace ID is reused
+        let _ = self.table_descriptor_file.lock().unwrap().delete();
+
+        Ok(())
+    }
+
     //Expect call to validate_insert before calling this function
     pub fn insert(
         self: Arc<Self>,
@@ -281,16 +487,53 @@ impl Table {
         self.upsert(transaction, id_value, true, to_insert_data)
     }
 
+    //Inserts the row without updating the secondary indexes.
+    //Expect call to validate_insert before calling this function, and to rebuild_secondary_indexes once the rows are inserted
+    pub fn insert_without_indexing(
+        &self,
+        transaction: &Transaction,
+        to_insert_data: &mut Vec<(String, Bytes)>
+    ) -> Result<(), SimpleDbError> {
+        let id_value = self.extract_primary_value(to_insert_data).unwrap();
+        let new_record = self.build_record(to_insert_data)?;
+        self.on_write(transaction);
+
+        self.write_record(transaction, id_value, &new_record, true)
+    }
+
+    //Returns the number of rows indexed
+    pub fn rebuild_secondary_indexes(&self, transaction: &Transaction) -> Result<usize, SimpleDbError> {
+        //Index entries are written with the transaction of each row, like in IndexCreationTask
+        let _lock = self.database.lock_rollbacks();
+        let table_iterator = self.scan_storage(transaction, None, &self.secondary_indexes.get_readable_indexed_columns_id())?;
+        self.secondary_indexes.rebuild_all(transaction, table_iterator)
+    }
+
+    pub fn verify_secondary_indexes(&self, transaction: &Transaction) -> Result<IndexConsistencyReport, SimpleDbError> {
+        let table_iterator = self.scan_storage(transaction, None, &self.secondary_indexes.get_readable_columns_id())?;
+        self.secondary_indexes.verify(transaction, table_iterator)
+    }
+
     pub fn delete(
         &self,
         transaction: &Transaction,
         id: Bytes
     ) -> Result<(), SimpleDbError> {
-        self.storage.delete_with_transaction(
-            self.storage_keyspace_id,
-            transaction,
-            id
-        )
+        self.on_write(transaction);
+
+        match self.row_format {
+            RowFormat::Row => self.storage.delete_with_transaction(self.storage_keyspace_id, transaction, id),
+            RowFormat::ColumnChunked => {
+                for entry in self.columns_by_id.iter() {
+                    self.storage.delete_with_transaction(
+                        self.storage_keyspace_id,
+                        transaction,
+                        column_chunk_key(*entry.key(), &id)
+                    )?;
+                }
+                Ok(())
+            }
+        }
     }
 
     pub fn update(
@@ -299,7 +542,7 @@ impl Table {
         id: Bytes,
         to_update_data: &Vec<(String, Bytes)>
     ) -> Result<(), SimpleDbError> {
-        self.upsert(transaction, id, true, to_update_data)
+        self.upsert(transaction, id, false, to_update_data)
     }
 
     fn upsert(
@@ -310,7 +553,7 @@ impl Table {
         to_update_data: &Vec<(String, Bytes)>
     ) -> Result<(), SimpleDbError> {
         let new_record = self.build_record(to_update_data)?;
-        let new_value = new_record.serialize();
+        self.on_write(transaction);
 
         let old_record = Record::create(if !is_new_values {
             self.get_old_data_to_invalidate_secondary_index(&id, transaction, to_update_data)?
@@ -318,12 +561,7 @@ impl Table {
             Vec::new()
         });
 
-        self.storage.set_with_transaction(
-            self.storage_keyspace_id,
-            transaction,
-            id.clone(),
-            new_value.as_slice()
-        )?;
+        self.write_record(transaction, id.clone(), &new_record, is_new_values)?;
 
         self.secondary_indexes.update_all(
             transaction,
@@ -335,6 +573,50 @@ impl Table {
         Ok(())
     }
 
+    //Rows stored with RowFormat::ColumnChunked are written as one entry per column. New rows also
+    //write an entry in the primary column chunk
+    fn write_record(
+        &self,
+        transaction: &Transaction,
+        id: Bytes,
+        record: &Record,
+        is_new_row: bool
+    ) -> Result<(), SimpleDbError> {
+        match self.row_format {
+            RowFormat::Row => self.storage.set_with_transaction(
+                self.storage_keyspace_id,
+                transaction,
+                id,
+                record.serialize().as_slice()
+            ),
+            RowFormat::ColumnChunked => {
+                if is_new_row {
+                    let primary_column_id = self.get_primary_column_data().unwrap().column_id;
+                    self.storage.set_with_transaction(self.storage_keyspace_id, transaction, column_chunk_key(primary_column_id, &id), &[])?;
+                }
+                for (column_id, column_value) in &record.data_records {
+                    let column_record = Record::create(vec![(*column_id, column_value.clone())]);
+                    self.storage.set_with_transaction(
+                        self.storage_keyspace_id,
+                        transaction,
+                        column_chunk_key(*column_id, &id),
+                        column_record.serialize().as_slice()
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version.load(Relaxed)
+    }
+
+    fn on_write(&self, transaction: &Transaction) {
+        self.version.fetch_add(1, Relaxed);
+        self.database.on_transaction_write(transaction);
+    }
+
     pub fn get_column_desc(
         &self,
         column_name: &str
@@ -358,13 +640,14 @@ impl Table {
     }
 
     pub fn validate_new_columns(
-        columns: &Vec<(String, Type, bool)>,
+        columns: &Vec<CreateTableColumn>,
     ) -> Result<(), SimpleDbError> {
         let mut primary_already_added = false;
         let mut column_names_added = HashSet::new();
 
-        for (new_column_name, _, is_primary) in columns {
-            let is_primary = *is_primary;
+        for new_column in columns {
+            let new_column_name = &new_column.column_name;
+            let is_primary = new_column.is_primary;
 
             if primary_already_added && is_primary {
                 return Err(OnlyOnePrimaryColumnAllowed());
@@ -378,6 +661,20 @@ impl Table {
             if !column_names_added.insert(new_column_name) {
                 return Err(ColumnNameAlreadyDefined(new_column_name.to_string()));
             }
+
+            //Integer values are stored as I64, so generated ids are only valid for I64 columns
+            if new_column.is_auto_increment && !matches!(new_column.column_type, Type::I64) {
+                return Err(MalformedQuery(format!("AUTOINCREMENT column {} should have type I64", new_column_name)));
+            }
+
+            if let Some(default_value) = &new_column.default_value {
+                if is_primary {
+                    return Err(MalformedQuery(format!("Primary column {} cannot have a default value", new_column_name)));
+                }
+                if !default_value.can_be_stored_as(&new_column.column_type) {
+                    return Err(InvalidType(new_column_name.to_string()));
+                }
+            }
         }
 
         if !primary_already_added {
@@ -396,6 +693,12 @@ impl Table {
                 indexed_columns.push((column_desc.column_name.clone(), IndexType::Secondary));
             }
         }
+        for composite_index in self.composite_indexes.lock().unwrap().iter() {
+            let columns_names: Vec<String> = composite_index.columns_id.iter()
+                .map(|column_id| self.columns_by_id.get(column_id).unwrap().value().column_name.clone())
+                .collect();
+            indexed_columns.push((columns_names.join(", "), IndexType::Secondary));
+        }
 
         indexed_columns
     }
@@ -420,12 +723,26 @@ impl Table {
 
     pub fn validate_create_index(
         &self,
-        column_name: &str
+        columns_names: &[String]
     ) -> Result<(), SimpleDbError> {
-        let column = self.get_column_desc_or_err(column_name)?;
+        let mut columns_id = Vec::new();
+        for column_name in columns_names {
+            let column = self.get_column_desc_or_err(column_name)?;
+            if columns_id.contains(&column.column_id) {
+                return Err(MalformedQuery(format!("Column {} is repeated in the index", column_name)));
+            }
+            //The primary key is not stored in the records, so it cannot be part of composite indexes
+            if column.is_primary && columns_names.len() > 1 {
+                return Err(MalformedQuery(format!("Primary column {} cannot be part of a composite index", column_name)));
+            }
+            if column.is_primary {
+                return Err(IndexAlreadyExists(self.storage_keyspace_id, column_name.to_string()));
+            }
+            columns_id.push(column.column_id);
+        }
 
-        if self.secondary_indexes.can_be_read(column.column_id) || column.is_primary{
-            return Err(IndexAlreadyExists(self.storage_keyspace_id, column_name.to_string()));
+        if self.secondary_indexes.exists(&columns_id) {
+            return Err(IndexAlreadyExists(self.storage_keyspace_id, columns_names.join(", ")));
         }
 
         Ok(())
@@ -435,16 +752,30 @@ impl Table {
         &self,
         to_insert_data: &Vec<(String, Value)>
     ) -> Result<(), SimpleDbError> {
-        if !self.has_primary_value(to_insert_data) {
+        if !self.has_primary_value(to_insert_data) && self.auto_increment.lock().unwrap().is_none() {
             return Err(PrimaryColumnNotIncluded())
         }
+        for entry in self.columns_by_id.iter() {
+            let column = entry.value();
+            let column_value = to_insert_data.iter()
+                .find(|(column_name, _)| *column_name == column.column_name)
+                .map(|(_, column_value)| column_value);
+            //Missing columns with default value are read as the default value
+            let is_null = match column_value {
+                Some(column_value) => column_value.is_null(),
+                None => column.default_value.is_none(),
+            };
+            if column.is_not_null && is_null {
+                return Err(NotNullViolation(column.column_name.clone()));
+            }
+        }
         for (column_name, column_value) in to_insert_data {
             match self.columns_by_name.get(column_name) {
                 Some(column) => {
                     let column = self.columns_by_id.get(column.value()).unwrap();
                     let column = column.value();
 
-                    if !column.column_type.can_be_casted(&column_value.to_type()) {
+                    if !column_value.can_be_stored_as(&column.column_type) {
                         return Err(InvalidType(column_name.clone()));
                     }
                 },
@@ -455,9 +786,140 @@ impl Table {
         Ok(())
     }
 
+    //Adds the default values of the columns not included in the inserted row
+    pub fn add_default_values(&self, to_insert_data: &mut Vec<(String, Bytes)>, inserted_columns: &[&String]) {
+        for entry in self.columns_by_id.iter() {
+            let column = entry.value();
+            if let Some(default_value) = &column.default_value {
+                if !inserted_columns.contains(&&column.column_name) {
+                    to_insert_data.push((column.column_name.clone(), default_value.clone()));
+                }
+            }
+        }
+    }
+
+    //Rows inserted without primary key get the next id of the AUTOINCREMENT primary column
+    pub fn add_auto_increment_id(&self, to_insert_data: &mut Vec<(String, Bytes)>) -> Result<(), SimpleDbError> {
+        let mut auto_increment = self.auto_increment.lock().unwrap();
+        let Some(auto_increment) = auto_increment.as_mut() else {
+            return Ok(());
+        };
+
+        let primary_value = to_insert_data.iter()
+            .find(|(column_name, _)| *column_name == self.primary_column_name)
+            .map(|(_, primary_value)| primary_value.clone());
+        let reserved_until = match primary_value {
+            Some(primary_value) => {
+                //Explicit ids have to be tracked, so that they are not generated again
+                let id = Value::deserialize(primary_value, Type::I64)
+                    .map_err(|_| InvalidType(self.primary_column_name.clone()))?
+                    .get_i64()?;
+                auto_increment.on_id_inserted(id)
+            },
+            None => {
+                let (id, reserved_until) = auto_increment.next();
+                to_insert_data.push((self.primary_column_name.clone(), Value::I64(id).serialize()));
+                reserved_until
+            }
+        };
+
+        //The lock is held until the reserved ids are saved, so other rows can't use them before
+        if let Some(reserved_until) = reserved_until {
+            let mut file = self.table_descriptor_file.lock().unwrap();
+            file.write(&TableDescriptor::serialize_auto_increment(reserved_until))
+                .and_then(|_| file.fsync())
+                .map_err(|e| CannotWriteTableDescriptor(self.storage_keyspace_id, e))?;
+        }
+
+        Ok(())
+    }
+
+    //Returns an error if a column updated to null is NOT NULL
+    pub fn validate_null_value(&self, column_name: &str) -> Result<(), SimpleDbError> {
+        match self.get_column_desc(column_name) {
+            Some(column) if column.is_not_null => Err(NotNullViolation(column.column_name)),
+            _ => Ok(())
+        }
+    }
+
+    //Returns an error if some of the new values of UNIQUE columns is already used by other row. rows contains the
+    //primary key and the new values of each row written by the statement. All rows are checked before writing any
+    //of them, so the rows of a statement are either all written or none
+    pub fn validate_unique_values(
+        self: &Arc<Self>,
+        transaction: &Transaction,
+        rows: &[(Bytes, &Vec<(String, Bytes)>)],
+    ) -> Result<(), SimpleDbError> {
+        for entry in self.columns_by_id.iter() {
+            let column = entry.value();
+            if !column.is_unique || column.is_primary {
+                continue;
+            }
+
+            //Rows of the statement that write the column. Their old values don't have to be checked
+            let written_primary_keys: HashSet<&Bytes> = rows.iter()
+                .filter(|(_, values)| values.iter().any(|(column_name, _)| *column_name == column.column_name))
+                .map(|(primary_key, _)| primary_key)
+                .collect();
+            let mut primary_key_by_new_value: HashMap<&Bytes, &Bytes> = HashMap::new();
+
+            for (primary_key, values) in rows {
+                let Some((_, new_value)) = values.iter().find(|(column_name, _)| *column_name == column.column_name) else {
+                    continue;
+                };
+                if primary_key_by_new_value.insert(new_value, primary_key).is_some_and(|other| other != primary_key) {
+                    return Err(self.unique_violation(column, new_value));
+                }
+
+                let mut index_iterator = self.secondary_indexes.scan_range(
+                    transaction,
+                    column.column_id,
+                    Bound::Included(new_value.clone()),
+                    Bound::Included(new_value.clone()),
+                )?;
+                while let Some(other_primary_key) = index_iterator.next() {
+                    let other_primary_key = other_primary_key.as_bytes();
+                    if other_primary_key != primary_key &&
+                        !written_primary_keys.contains(other_primary_key) &&
+                        self.has_value(transaction, other_primary_key, column, new_value)? {
+                        return Err(self.unique_violation(column, new_value));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    //Index entries of deleted rows are not removed, the row is read to check that it still has the value
+    fn has_value(
+        self: &Arc<Self>,
+        transaction: &Transaction,
+        primary_key: &Bytes,
+        column: &ColumnDescriptor,
+        value: &Bytes,
+    ) -> Result<bool, SimpleDbError> {
+        let selection = Selection::Some(vec![column.column_name.clone()]);
+        match self.get_by_primary_column(primary_key, transaction, &selection)? {
+            Some(row) => {
+                let value = Value::deserialize(value.clone(), column.column_type.clone()).unwrap_or(Value::Null);
+                Ok(row.get_column_value(&column.column_name)? == value)
+            },
+            None => Ok(false),
+        }
+    }
+
+    fn unique_violation(&self, column: &ColumnDescriptor, value: &Bytes) -> SimpleDbError {
+        let value = match Value::deserialize(value.clone(), column.column_type.clone()) {
+            Ok(value) => format!("{:?}", value),
+            Err(_) => format!("{:?}", value),
+        };
+        UniqueViolation(column.column_name.clone(), value)
+    }
+
     fn has_primary_value(&self, data: &Vec<(String, Value)>) -> bool {
-        for (column_name, _) in data.iter() {
-            if column_name.eq(&self.primary_column_name) {
+        for (column_name, value) in data.iter() {
+            if column_name.eq(&self.primary_column_name) && !value.is_null() {
                 return true
             }
         }
@@ -489,21 +951,30 @@ impl Table {
 
     fn add_column(
         &self,
-        column_name: &str,
-        column_type: Type,
-        is_primary: bool,
+        column: CreateTableColumn,
     ) -> Result<(), SimpleDbError> {
+        let default_value = column.default_value
+            .map(|default_value| default_value.cast(&column.column_type))
+            .transpose()?;
         let column_descriptor = ColumnDescriptor {
             column_id: self.next_column_id.fetch_add(1, Relaxed) as shared::ColumnId,
-            column_name: column_name.to_string(),
+            column_name: column.column_name,
             secondary_index_keyspace_id: None,
-            column_type,
-            is_primary,
+            column_type: column.column_type,
+            is_primary: column.is_primary,
+            is_not_null: column.is_not_null,
+            is_unique: column.is_unique,
+            default_value: default_value.map(|default_value| default_value.serialize()),
         };
 
         let mut file = self.table_descriptor_file.lock().unwrap();
         file.write(&column_descriptor.serialize())
             .map_err(|e| SimpleDbError::CannotWriteTableDescriptor(self.storage_keyspace_id, e))?;
+        if column.is_auto_increment {
+            file.write(&TableDescriptor::serialize_auto_increment(0))
+                .map_err(|e| SimpleDbError::CannotWriteTableDescriptor(self.storage_keyspace_id, e))?;
+            *self.auto_increment.lock().unwrap() = Some(AutoIncrement::create(0));
+        }
 
         self.columns_by_name.insert(column_descriptor.column_name.clone(), column_descriptor.column_id);
         self.columns_by_id.insert(column_descriptor.column_id, column_descriptor);
@@ -570,10 +1041,12 @@ impl Table {
     ) -> Result<Vec<(ColumnId, Bytes)>, SimpleDbError> {
         let mut old_data = Vec::new();
 
-        let secondary_indexed_columns_names: Vec<String> = updated_data.iter()
-            .map(|(column_name, _) | self.get_column_desc(column_name).unwrap())
-            .filter(|column| column.is_secondary_indexed())
-            .map(|column| column.column_name.clone())
+        let updated_columns_id: Vec<ColumnId> = updated_data.iter()
+            .map(|(column_name, _) | self.get_column_desc(column_name).unwrap().column_id)
+            .collect();
+        //Composite indexes need the old values of all their columns, even if they are not updated
+        let secondary_indexed_columns_names: Vec<String> = self.secondary_indexes.get_columns_id_to_reindex(&updated_columns_id).iter()
+            .map(|column_id| self.columns_by_id.get(column_id).unwrap().value().column_name.clone())
             .collect();
 
         let old_value_selection = Selection::Some(secondary_indexed_columns_names);
@@ -600,19 +1073,22 @@ impl Table {
         Ok(old_data)
     }
 
-    fn save_column_descriptor_as_indexed(
+    fn save_index(
         &self,
-        column_id_indexed: ColumnId,
+        columns_id_indexed: &[ColumnId],
         keyspace_id: KeyspaceId
     ) -> Result<(), SimpleDbError> {
+        //Same lock order as add_auto_increment_id
+        let auto_increment = self.auto_increment.lock().unwrap();
         let mut file_lock = self.table_descriptor_file.lock().unwrap();
+        let mut composite_indexes = self.composite_indexes.lock().unwrap();
 
         //Create new list of columns descriptors
         let mut new_columns = Vec::new();
         for current_entry in self.columns_by_id.iter() {
             let current_column_id = *current_entry.key();
             let current_column = current_entry.value().clone();
-            if current_column_id == column_id_indexed {
+            if columns_id_indexed == [current_column_id] {
                 let mut current_column_to_update = current_column;
                 current_column_to_update.secondary_index_keyspace_id = Some(keyspace_id);
                 new_columns.push(current_column_to_update.clone());
@@ -621,16 +1097,26 @@ impl Table {
                 new_columns.push(current_column);
             }
         }
+        if columns_id_indexed.len() > 1 {
+            composite_indexes.push(CompositeIndexDescriptor { columns_id: columns_id_indexed.to_vec(), keyspace_id });
+        }
 
         //Save new table desc with updated column
-        let serialized = TableDescriptor::serialize(new_columns, &self.table_name);
+        let auto_increment_reserved_until = auto_increment.as_ref().map(|auto_increment| auto_increment.reserved_until());
+        let serialized = TableDescriptor::serialize(new_columns, composite_indexes.clone(), auto_increment_reserved_until, &self.table_name);
         file_lock.safe_replace(&serialized)
             .map_err(|io_error| CannotWriteTableDescriptor(self.storage_keyspace_id, io_error))?;
 
         Ok(())
     }
 
-    fn get_column_desc_or_err(
+    fn get_columns_id(&self, columns_names: &[String]) -> Result<Vec<ColumnId>, SimpleDbError> {
+        columns_names.iter()
+            .map(|column_name| self.get_column_id_by_name(column_name))
+            .collect()
+    }
+
+    pub(crate) fn get_column_desc_or_err(
         &self,
         column_name: &str
     ) -> Result<ColumnDescriptor, SimpleDbError> {
@@ -640,9 +1126,16 @@ impl Table {
         }
     }
 
+    pub fn n_full_scans(&self) -> usize {
+        self.n_full_scans.load(Relaxed)
+    }
+
+    pub fn n_secondary_index_scans(&self) -> usize {
+        self.n_secondary_index_scans.load(Relaxed)
+    }
+
     pub fn is_secondary_indexed(&self, column_name: &str) -> bool {
-        let column_desc = self.get_column_desc_or_err(column_name)
-            .unwrap();
-        self.secondary_indexes.can_be_read(column_desc.column_id)
+        self.get_column_desc(column_name)
+            .is_some_and(|column_desc| self.secondary_indexes.can_be_read(column_desc.column_id))
     }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use crate::value::Type;
 use crate::value::Type::I64;
-use bytes::{Buf, BufMut};
+use bytes::{Buf, BufMut, Bytes};
 use crossbeam_skiplist::SkipMap;
 use shared::{ColumnId, KeyspaceId, SimpleDbError, SimpleDbFile};
 use std::path::PathBuf;
@@ -8,18 +8,45 @@ use std::sync::Arc;
 
 const NO_INDEX: KeyspaceId = 0xFFFFFFFFFFFFFFFF;
 
+//Records starting with this column ID describe a composite index instead of a column
+const COMPOSITE_INDEX_RECORD: ColumnId = 0xFFFF;
+//Records starting with this column ID contain the ids reserved by the AUTOINCREMENT primary column
+const AUTO_INCREMENT_RECORD: ColumnId = 0xFFFE;
+
+const COLUMN_FLAG_PRIMARY: u8 = 0x01;
+const COLUMN_FLAG_HAS_DEFAULT: u8 = 0x02;
+const COLUMN_FLAG_NOT_NULL: u8 = 0x04;
+const COLUMN_FLAG_UNIQUE: u8 = 0x08;
+
 //Maintains information about column ID with its column name, column type, is_primary etc.
 //This file is stored in binary format
 //There is one file of these for each table
 
 // Flags (u64) | Table name length (u16) | Table name bytes...
-// [ Column ID (u16) | Column type (u8) | Is primary (u8) | index keyspace ID (u64) | name length (u32) | name bytes... ]
+// [ Column ID (u16) | Column type (u8) | Column flags (u8) | index keyspace ID (u64) | name length (u32) | name bytes... |
+//   default value length (u32) | default value bytes... ]
+// Column flags: 0x01 is primary, 0x02 has default value, 0x04 is not null, 0x08 is unique. Default value length & bytes are only present if it has default value
+// Composite indexes are stored after the columns:
+// [ 0xFFFF (u16) | index keyspace ID (u64) | number of columns (u16) | column IDs (u16)... ]
+// Tables with an AUTOINCREMENT primary column have records with the end of the reserved ids. The last one is used:
+// [ 0xFFFE (u16) | reserved until (i64) ]
 pub struct TableDescriptor {
     pub(crate) columns: SkipMap<ColumnId, ColumnDescriptor>,
+    pub(crate) composite_indexes: Vec<CompositeIndexDescriptor>,
+    //Some if the primary column is AUTOINCREMENT
+    pub(crate) auto_increment_reserved_until: Option<i64>,
     pub(crate) table_name: String,
     pub(crate) primary_column_id: ColumnId,
 }
 
+//Secondary index whose key is made of the values of several columns, see index::composite_key
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompositeIndexDescriptor {
+    //In the order in which their values are concatenated in the index key
+    pub(crate) columns_id: Vec<ColumnId>,
+    pub(crate) keyspace_id: KeyspaceId,
+}
+
 #[derive(Clone, Debug, PartialOrd, PartialEq)]
 pub struct ColumnDescriptor {
     pub(crate) column_id: ColumnId,
@@ -27,6 +54,12 @@ pub struct ColumnDescriptor {
     pub(crate) column_name: String,
     pub(crate) is_primary: bool,
     pub(crate) secondary_index_keyspace_id: Option<KeyspaceId>,
+    //Serialized value returned for rows that don't have the column. For example, rows inserted
+    //before the column was added with ALTER TABLE
+    pub(crate) default_value: Option<Bytes>,
+    //Constraints checked when rows are inserted or updated
+    pub(crate) is_not_null: bool,
+    pub(crate) is_unique: bool,
 }
 
 impl TableDescriptor {
@@ -35,10 +68,13 @@ impl TableDescriptor {
         options: &Arc<shared::SimpleDbOptions>,
         table_name: &str,
     ) -> Result<(TableDescriptor, SimpleDbFile), SimpleDbError> {
-        let table_descriptor_file_bytes: Vec<u8> = Self::serialize(Vec::new(), table_name);
+        let table_descriptor_file_bytes: Vec<u8> = Self::serialize(Vec::new(), Vec::new(), None, table_name);
+        let path = Self::table_descriptor_file_path(options, keyspace_id);
+        //Keyspace IDs of dropped tables can be reused. The old descriptor might not have been removed if we crashed
+        let _ = std::fs::remove_file(path.as_path());
 
         let table_descriptor_file = SimpleDbFile::create(
-            Self::table_descriptor_file_path(options, keyspace_id).as_path(),
+            path.as_path(),
             &table_descriptor_file_bytes,
             shared::SimpleDbFileMode::AppendOnly
         ).map_err(|e| SimpleDbError::CannotCreateTableDescriptor(keyspace_id, e))?;
@@ -47,6 +83,8 @@ impl TableDescriptor {
             table_name: table_name.to_string(),
             primary_column_id: 0,
             columns: SkipMap::new(),
+            composite_indexes: Vec::new(),
+            auto_increment_reserved_until: None,
         }, table_descriptor_file))
     }
 
@@ -62,20 +100,15 @@ impl TableDescriptor {
 
         let table_descriptor_bytes = table_descriptor_file.read_all()
             .map_err(|e| SimpleDbError::CannotReadTableDescriptor(keyspace_id, e))?;
-        let (table_name, mut column_descriptors, primary_column_id) = Self::deserialize_table_descriptor_bytes(
-            keyspace_id,
-            &table_descriptor_bytes
-        )?;
+        let table_descriptor = Self::deserialize_table_descriptor_bytes(keyspace_id, &table_descriptor_bytes)?;
 
-        Ok((TableDescriptor {
-            columns: Self::index_by_column_name(&mut column_descriptors),
-            primary_column_id,
-            table_name,
-        }, table_descriptor_file))
+        Ok((table_descriptor, table_descriptor_file))
     }
 
     pub fn serialize(
         columns: Vec<ColumnDescriptor>,
+        composite_indexes: Vec<CompositeIndexDescriptor>,
+        auto_increment_reserved_until: Option<i64>,
         table_name: &str
     ) -> Vec<u8> {
         let mut serialized: Vec<u8> = Vec::new();
@@ -85,10 +118,23 @@ impl TableDescriptor {
         for column in columns {
             serialized.extend(column.serialize());
         }
+        for composite_index in composite_indexes {
+            serialized.extend(composite_index.serialize());
+        }
+        if let Some(auto_increment_reserved_until) = auto_increment_reserved_until {
+            serialized.extend(Self::serialize_auto_increment(auto_increment_reserved_until));
+        }
 
         serialized
     }
 
+    pub fn serialize_auto_increment(reserved_until: i64) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        serialized.put_u16_le(AUTO_INCREMENT_RECORD);
+        serialized.put_i64_le(reserved_until);
+        serialized
+    }
+
     pub fn get_max_column_id(&self) -> ColumnId {
         if let Some(entry) = self.columns.back() {
             *entry.key()
@@ -107,9 +153,11 @@ impl TableDescriptor {
     fn deserialize_table_descriptor_bytes(
         keyspace_id: KeyspaceId,
         bytes: &Vec<u8>,
-    ) -> Result<(String, Vec<ColumnDescriptor>, ColumnId), SimpleDbError> {
+    ) -> Result<TableDescriptor, SimpleDbError> {
         let mut current_ptr = bytes.as_slice();
         let mut columns_descriptor = Vec::new();
+        let mut composite_indexes = Vec::new();
+        let mut auto_increment_reserved_until = None;
 
         //Table name
         let table_name_length = current_ptr.get_u32_le() as usize;
@@ -119,6 +167,19 @@ impl TableDescriptor {
         let mut primary_column_id = 0;
 
         while current_ptr.has_remaining() {
+            match (&current_ptr[..]).get_u16_le() {
+                COMPOSITE_INDEX_RECORD => {
+                    composite_indexes.push(CompositeIndexDescriptor::deserialize(&mut current_ptr));
+                    continue;
+                },
+                AUTO_INCREMENT_RECORD => {
+                    current_ptr.advance(2);
+                    auto_increment_reserved_until = Some(current_ptr.get_i64_le());
+                    continue;
+                },
+                _ => {}
+            }
+
             let column_descriptor = ColumnDescriptor::deserialize(
                 keyspace_id, columns_descriptor.len(), &mut current_ptr
             )?;
@@ -130,7 +191,13 @@ impl TableDescriptor {
             columns_descriptor.push(column_descriptor);
         }
 
-        Ok((table_name, columns_descriptor, primary_column_id))
+        Ok(TableDescriptor {
+            columns: Self::index_by_column_name(&mut columns_descriptor),
+            auto_increment_reserved_until,
+            composite_indexes,
+            primary_column_id,
+            table_name,
+        })
     }
 
     fn index_by_column_name(column_descriptors: &mut Vec<ColumnDescriptor>) -> SkipMap<shared::ColumnId, ColumnDescriptor> {
@@ -154,6 +221,30 @@ impl TableDescriptor {
     }
 }
 
+impl CompositeIndexDescriptor {
+    pub fn deserialize(current_ptr: &mut &[u8]) -> CompositeIndexDescriptor {
+        current_ptr.advance(2); //COMPOSITE_INDEX_RECORD
+        let keyspace_id = current_ptr.get_u64_le() as KeyspaceId;
+        let n_columns = current_ptr.get_u16_le() as usize;
+        let columns_id = (0..n_columns)
+            .map(|_| current_ptr.get_u16_le() as ColumnId)
+            .collect();
+
+        CompositeIndexDescriptor { columns_id, keyspace_id }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        serialized.put_u16_le(COMPOSITE_INDEX_RECORD);
+        serialized.put_u64_le(self.keyspace_id as u64);
+        serialized.put_u16_le(self.columns_id.len() as u16);
+        for column_id in &self.columns_id {
+            serialized.put_u16_le(*column_id);
+        }
+        serialized
+    }
+}
+
 impl ColumnDescriptor {
     pub fn create_primary(name: &str) -> ColumnDescriptor {
         ColumnDescriptor {
@@ -161,7 +252,10 @@ impl ColumnDescriptor {
             column_type: I64,
             column_name: name.to_string(),
             is_primary: true,
-            secondary_index_keyspace_id: None
+            secondary_index_keyspace_id: None,
+            default_value: None,
+            is_not_null: false,
+            is_unique: false,
         }
     }
 
@@ -171,15 +265,21 @@ impl ColumnDescriptor {
             column_type: I64,
             column_name: name.to_string(),
             is_primary: false,
-            secondary_index_keyspace_id: Some(1)
+            secondary_index_keyspace_id: Some(1),
+            default_value: None,
+            is_not_null: false,
+            is_unique: false,
         }
     }
 
     pub fn create(name: &str, column_id: ColumnId) -> ColumnDescriptor {
         ColumnDescriptor {
             secondary_index_keyspace_id: None,
+            default_value: None,
             column_name: name.to_string(),
             is_primary: false,
+            is_not_null: false,
+            is_unique: false,
             column_type: I64,
             column_id,
         }
@@ -197,18 +297,31 @@ impl ColumnDescriptor {
                 index: n_column,
                 offset: 0,
             }))?;
-        let is_primary = current_ptr.get_u8() != 0;
+        let flags = current_ptr.get_u8();
+        let is_primary = flags & COLUMN_FLAG_PRIMARY != 0;
+        let is_not_null = flags & COLUMN_FLAG_NOT_NULL != 0;
+        let is_unique = flags & COLUMN_FLAG_UNIQUE != 0;
         let secondary_index_keyspace_id = Self::get_secondary_index_keyspace_id(current_ptr.get_u64_le());
         let column_name_bytes_length = current_ptr.get_u32_le() as usize;
         let column_bytes = &current_ptr[..column_name_bytes_length];
         let column_name = decode_string(column_bytes, keyspace_id, n_column)?;
         current_ptr.advance(column_name_bytes_length);
 
+        let mut default_value = None;
+        if flags & COLUMN_FLAG_HAS_DEFAULT != 0 {
+            let default_value_length = current_ptr.get_u32_le() as usize;
+            default_value = Some(Bytes::copy_from_slice(&current_ptr[..default_value_length]));
+            current_ptr.advance(default_value_length);
+        }
+
         Ok(ColumnDescriptor{
             secondary_index_keyspace_id,
+            default_value,
             column_name,
             column_type,
             is_primary,
+            is_not_null,
+            is_unique,
             column_id,
         })
     }
@@ -217,14 +330,35 @@ impl ColumnDescriptor {
         let mut serialized = Vec::new();
         serialized.put_u16_le(self.column_id);
         serialized.put_u8(self.column_type.serialize());
-        serialized.put_u8(self.is_primary as u8);
+        serialized.put_u8(self.flags());
         serialized.put_u64_le(self.get_index_keyspace() as u64);
         let name_bytes = self.column_name.bytes();
         serialized.put_u32_le(name_bytes.len() as u32);
         serialized.extend(name_bytes);
+        if let Some(default_value) = &self.default_value {
+            serialized.put_u32_le(default_value.len() as u32);
+            serialized.extend(default_value);
+        }
         serialized
     }
 
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.is_primary {
+            flags |= COLUMN_FLAG_PRIMARY;
+        }
+        if self.default_value.is_some() {
+            flags |= COLUMN_FLAG_HAS_DEFAULT;
+        }
+        if self.is_not_null {
+            flags |= COLUMN_FLAG_NOT_NULL;
+        }
+        if self.is_unique {
+            flags |= COLUMN_FLAG_UNIQUE;
+        }
+        flags
+    }
+
     pub fn get_secondary_index_keyspace_id(value: u64) -> Option<KeyspaceId> {
         if value as KeyspaceId != NO_INDEX {
             Some(value as KeyspaceId)
@@ -1,4 +1,5 @@
 use shared::Flag;
 
+pub const KEYSPACE_TABLE_COLUMN_CHUNKED: Flag = 0x04 as Flag; //000000100 Rows stored with RowFormat::ColumnChunked
 pub const KEYSPACE_TABLE_INDEX: Flag = 0x02 as Flag; //000000010
-pub const KEYSPACE_TABLE_USER: Flag = 0x01 as Flag; //000000001
\ No newline at end of file
+pub const KEYSPACE_TABLE_USER: Flag = 0x01 as Flag; //000000001
@@ -3,7 +3,7 @@ use crate::table::row::Row;
 use crate::table::table::Table;
 use bytes::Bytes;
 use shared::iterators::storage_iterator::StorageIterator;
-use shared::ColumnId;
+use shared::{ColumnId, SimpleDbError};
 use std::sync::Arc;
 
 //This is the iterator that will be exposed to users of the SimpleDB
@@ -21,8 +21,14 @@ struct RowReassemble {
 pub struct TableIterator<I: StorageIterator> {
     simple_db_storage_iterator: I,
     selection: Arc<Vec<ColumnId>>, //Columns ID to retrieve from storage engine
+    //Default values of the selected columns that have one. Used for rows that don't contain the column
+    default_values: Vec<(ColumnId, Bytes)>,
     rows_reassembling: Vec<RowReassemble>,
     current_row: Option<Row>,
+    //Bytes of the keys & values returned by the storage engine
+    n_bytes_read: usize,
+    //Set if the storage engine couldn't read some entry. No more rows are returned
+    error: Option<SimpleDbError>,
 
     table: Arc<Table>
 }
@@ -33,11 +39,20 @@ impl<I: StorageIterator> TableIterator<I> {
         selection: Vec<ColumnId>, //Columns ID to select
         table: Arc<Table>
     ) -> TableIterator<I> {
+        let default_values = selection.iter()
+            .filter_map(|column_id| table.columns_by_id.get(column_id)
+                .and_then(|column| column.value().default_value.clone())
+                .map(|default_value| (*column_id, default_value)))
+            .collect();
+
         TableIterator {
             selection: Arc::new(selection),
+            default_values,
             rows_reassembling: Vec::new(),
             simple_db_storage_iterator,
             current_row: None,
+            n_bytes_read: 0,
+            error: None,
             table,
         }
     }
@@ -45,19 +60,25 @@ impl<I: StorageIterator> TableIterator<I> {
     pub fn next(&mut self) -> bool {
         while self.n_reassembled_rows_that_can_be_returned() == 0 {
             if !self.simple_db_storage_iterator.next() {
+                self.error = self.simple_db_storage_iterator.take_error();
                 break;
             }
 
-            let record = Record::deserialize(self.simple_db_storage_iterator.value().to_vec());
+            let record = Record::deserialize_projected(self.simple_db_storage_iterator.value(), &self.selection);
             let key = Bytes::copy_from_slice(self.simple_db_storage_iterator.key().as_bytes());
+            self.n_bytes_read += key.len() + self.simple_db_storage_iterator.value().len();
             self.reassemble_row(key, record);
         }
 
-        if self.rows_reassembling.is_empty() {
+        if self.rows_reassembling.is_empty() || self.error.is_some() {
             return false;
         }
 
-        let row_in_reassembling = self.rows_reassembling.remove(0);
+        let mut row_in_reassembling = self.rows_reassembling.remove(0);
+        for (column_id, default_value) in &self.default_values {
+            //Only added if the row doesn't contain the column
+            row_in_reassembling.record_builder.add_column(*column_id, default_value.clone());
+        }
         let key_bytes = row_in_reassembling.key.clone();
         let row_record_reassembled = row_in_reassembling.build();
         self.current_row = Some(Row::create(row_record_reassembled, &self.table, key_bytes));
@@ -69,6 +90,15 @@ impl<I: StorageIterator> TableIterator<I> {
         self.current_row.as_ref().unwrap()
     }
 
+    pub fn n_bytes_read(&self) -> usize {
+        self.n_bytes_read
+    }
+
+    //Returns the error that made next() return false, if the rows couldn't be read
+    pub fn take_error(&mut self) -> Option<SimpleDbError> {
+        self.error.take()
+    }
+
     fn reassemble_row(&mut self, key: Bytes, record: Record) {
         let row_reassemble_index = match self.find_row_reassemble_index(&key) {
             Some(row_reassemble_index) => row_reassemble_index,
@@ -134,7 +164,7 @@ mod test {
     use crate::table::table_iterator::TableIterator;
     use bytes::Bytes;
     use shared::iterators::mock_iterator::MockIterator;
-    use shared::ColumnId;
+    use shared::{ColumnId, SimpleDbError};
     use crate::ColumnDescriptor;
     use crate::value::{Type, Value};
     //Given records:
@@ -164,10 +194,10 @@ mod test {
             ]),
             vec![2, 3],
             Table::create_mock(vec![
-                ColumnDescriptor{column_id: 1, column_type: Type::I64, column_name: String::from("ID"), is_primary: true, secondary_index_keyspace_id: None },
-                ColumnDescriptor{column_id: 2, column_type: Type::String, column_name: String::from("Money"), is_primary: false, secondary_index_keyspace_id: None },
-                ColumnDescriptor{column_id: 3, column_type: Type::String, column_name: String::from("Desc"), is_primary: false, secondary_index_keyspace_id: None },
-                ColumnDescriptor{column_id: 4, column_type: Type::String, column_name: String::from("Fecha"), is_primary: false, secondary_index_keyspace_id: None },
+                ColumnDescriptor{column_id: 1, column_type: Type::I64, column_name: String::from("ID"), is_primary: true, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+                ColumnDescriptor{column_id: 2, column_type: Type::String, column_name: String::from("Money"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+                ColumnDescriptor{column_id: 3, column_type: Type::String, column_name: String::from("Desc"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+                ColumnDescriptor{column_id: 4, column_type: Type::String, column_name: String::from("Fecha"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
             ])
         );
 
@@ -221,6 +251,63 @@ mod test {
         assert!(!iterator.next());
     }
 
+    #[test]
+    fn projected() {
+        let mut iterator = TableIterator::create(
+            MockIterator::create_from_byte_entries(vec![
+                (1, record(vec![(2, "100"), (3, "Pago"), (4, "30/30/3000")])),
+                (2, record(vec![(2, "200"), (4, "1999")])),
+                (2, record(vec![(3, "Cena")])),
+            ]),
+            vec![1, 3],
+            Table::create_mock(vec![
+                ColumnDescriptor{column_id: 1, column_type: Type::I64, column_name: String::from("ID"), is_primary: true, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+                ColumnDescriptor{column_id: 2, column_type: Type::String, column_name: String::from("Money"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+                ColumnDescriptor{column_id: 3, column_type: Type::String, column_name: String::from("Desc"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+                ColumnDescriptor{column_id: 4, column_type: Type::String, column_name: String::from("Fecha"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+            ])
+        );
+
+        for expected_desc in ["Pago", "Cena"] {
+            assert!(iterator.next());
+            let row = iterator.row();
+            let record = &row.storage_engine_record;
+
+            assert_eq!(record.get_n_columns(), 2);
+            assert!(record.get_value(1).is_some());
+            assert_eq!(record.get_value(3).unwrap(), &Bytes::from(expected_desc));
+            assert!(record.get_value(2).is_none());
+            assert!(record.get_value(4).is_none());
+        }
+
+        assert!(!iterator.next());
+    }
+
+    #[test]
+    fn storage_error() {
+        let mut storage_iterator = MockIterator::create_from_byte_entries(vec![
+            (1, record(vec![(2, "100"), (3, "Pago")])),
+            (2, record(vec![(2, "200")])),
+            (2, record(vec![(3, "Cena")])),
+        ]);
+        storage_iterator.fail_after(2, SimpleDbError::CorruptedBlock(0, 1, 0));
+        let mut iterator = TableIterator::create(
+            storage_iterator,
+            vec![2, 3],
+            Table::create_mock(vec![
+                ColumnDescriptor{column_id: 1, column_type: Type::I64, column_name: String::from("ID"), is_primary: true, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+                ColumnDescriptor{column_id: 2, column_type: Type::String, column_name: String::from("Money"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+                ColumnDescriptor{column_id: 3, column_type: Type::String, column_name: String::from("Desc"), is_primary: false, secondary_index_keyspace_id: None, default_value: None, is_not_null: false, is_unique: false },
+            ])
+        );
+
+        assert!(iterator.next());
+        assert_eq!(iterator.row().get_column_value("ID").unwrap().get_i64().unwrap(), 1);
+        //The column Desc of row 2 couldn't be read, so the row is not returned
+        assert!(!iterator.next());
+        assert!(matches!(iterator.take_error(), Some(SimpleDbError::CorruptedBlock(0, 1, 0))));
+    }
+
     fn record(rows: Vec<(i32, &str)>) -> Bytes {
         let mut record_builder = Record::builder();
         for (column_id, column_value) in rows {
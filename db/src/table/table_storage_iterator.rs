@@ -0,0 +1,57 @@
+use crate::table::column_chunked_iterator::ColumnChunkedIterator;
+use bytes::Bytes;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::key::Key;
+use shared::SimpleDbError;
+use storage::SimpleDbStorageIterator;
+
+//Iterates the rows stored in a table keyspace, regardless of the table RowFormat.
+//Each entry is a row: the key is the primary key & the value is the serialized Record of the row
+pub enum TableStorageIterator {
+    Row(Box<SimpleDbStorageIterator>),
+    ColumnChunked(ColumnChunkedIterator<SimpleDbStorageIterator>),
+}
+
+impl StorageIterator for TableStorageIterator {
+    fn next(&mut self) -> bool {
+        match self {
+            TableStorageIterator::Row(iterator) => iterator.next(),
+            TableStorageIterator::ColumnChunked(iterator) => iterator.next(),
+        }
+    }
+
+    fn has_next(&self) -> bool {
+        match self {
+            TableStorageIterator::Row(iterator) => iterator.has_next(),
+            TableStorageIterator::ColumnChunked(iterator) => iterator.has_next(),
+        }
+    }
+
+    fn key(&self) -> &Key {
+        match self {
+            TableStorageIterator::Row(iterator) => iterator.key(),
+            TableStorageIterator::ColumnChunked(iterator) => iterator.key(),
+        }
+    }
+
+    fn value(&self) -> &[u8] {
+        match self {
+            TableStorageIterator::Row(iterator) => iterator.value(),
+            TableStorageIterator::ColumnChunked(iterator) => iterator.value(),
+        }
+    }
+
+    fn seek(&mut self, key: &Bytes, inclusive: bool) {
+        match self {
+            TableStorageIterator::Row(iterator) => iterator.seek(key, inclusive),
+            TableStorageIterator::ColumnChunked(iterator) => iterator.seek(key, inclusive),
+        }
+    }
+
+    fn take_error(&mut self) -> Option<SimpleDbError> {
+        match self {
+            TableStorageIterator::Row(iterator) => iterator.take_error(),
+            TableStorageIterator::ColumnChunked(iterator) => iterator.take_error(),
+        }
+    }
+}
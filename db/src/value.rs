@@ -1,6 +1,6 @@
 use bytes::Bytes;
 use shared::SimpleDbError::MalformedQuery;
-use shared::{utils, SimpleDbError};
+use shared::{timestamp, utils, SimpleDbError};
 
 #[derive(Clone, Debug, PartialEq, PartialOrd)]
 pub enum Type {
@@ -18,6 +18,7 @@ pub enum Type {
     String,
     Date,
     Blob,
+    Timestamp,
     Null
 }
 
@@ -38,6 +39,7 @@ impl Type {
             Type::String => 12,
             Type::Date => 13,
             Type::Blob => 14,
+            Type::Timestamp => 15,
             Type::Null => panic!("Illegal code path")
         }
     }
@@ -58,6 +60,7 @@ impl Type {
             12 => Ok(Type::String),
             13 => Ok(Type::Date),
             14 => Ok(Type::Blob),
+            15 => Ok(Type::Timestamp),
             _ => Err(value)
         }
     }
@@ -70,7 +73,8 @@ impl Type {
         } else if self.is_null() || other.is_null() {
             true
         } else {
-            utils::enum_eq(self, other)
+            //Timestamps are written as ISO-8601 strings
+            matches!((self, other), (Type::Timestamp, Type::String)) || utils::enum_eq(self, other)
         }
     }
 
@@ -104,9 +108,17 @@ impl Type {
         self.is_signed_integer_number() || self.is_unsigned_integer_number() || self.is_fp_number()
     }
 
+    //Numbers are serialized in little endian, so their serialized bytes are not sorted like their values.
+    //Storage keys of these types can't be used to seek or to stop range scans
+    pub fn is_serialized_in_order(&self) -> bool {
+        matches!(self, Type::U8 | Type::Boolean | Type::String | Type::Blob)
+    }
+
     pub fn is_comparable(&self, other: &Type) -> bool {
         //Null types can always be compared
-        if (self.is_number() && other.is_number()) || matches!(other, Type::Null) {
+        //Strings are parsed as ISO-8601 timestamps
+        if (self.is_number() && other.is_number()) || self.is_null() || other.is_null() ||
+            matches!((self, other), (Type::Timestamp, Type::String) | (Type::String, Type::Timestamp)) {
             true
         } else {
             utils::enum_eq(self, &other)
@@ -130,12 +142,14 @@ pub enum Value {
     String(String),
     Date,
     Blob(Bytes),
+    //Milliseconds since the unix epoch
+    Timestamp(i64),
     Null
 }
 
 impl Value {
-    pub fn deserialize(bytes: Bytes, expected_type: Type) -> Result<Value, ()> {
-        if expected_type.is_number() && (bytes.len() > 8 || bytes.len() == 0)  {
+    pub(crate) fn deserialize(bytes: Bytes, expected_type: Type) -> Result<Value, ()> {
+        if (expected_type.is_number() || matches!(expected_type, Type::Timestamp)) && (bytes.len() > 8 || bytes.len() == 0)  {
             return Err(());
         }
 
@@ -156,6 +170,7 @@ impl Value {
                 .map(|string| Value::String(string)),
             Type::Date => todo!(),
             Type::Blob => Ok(Value::Blob(bytes)),
+            Type::Timestamp => Ok(Value::Timestamp(utils::bytes_to_i64_le(&bytes))),
             Type::Null => Ok(Value::Null)
         }
     }
@@ -182,6 +197,7 @@ impl Value {
             Value::String(value) => Bytes::copy_from_slice(value.as_bytes()),
             Value::Date => todo!(),
             Value::Blob(value) => value.clone(),
+            Value::Timestamp(value) => Bytes::copy_from_slice(value.to_le_bytes().as_slice()),
             Value::Null => Bytes::from(vec![])
         }
     }
@@ -202,6 +218,7 @@ impl Value {
             Value::String(_) => Type::String,
             Value::Date => Type::Date,
             Value::Blob(_) => Type::Blob,
+            Value::Timestamp(_) => Type::Timestamp,
             Value::Null => Type::Null,
         }
     }
@@ -219,6 +236,7 @@ impl Value {
             Value::F32(value) => Ok(*value as f64),
             Value::F64(value) => Ok(*value),
             Value::Boolean(value) => if *value { Ok(1.0) } else { Ok(0.0) },
+            Value::Timestamp(value) => Ok(*value as f64),
             Value::String(_) |
             Value::Date |
             Value::Blob(_) |
@@ -240,6 +258,7 @@ impl Value {
             Value::F32(value) => Ok(*value as i64),
             Value::F64(value) => Ok(*value as i64),
             Value::Boolean(value) => if *value { Ok(1) } else { Ok(0) },
+            Value::Timestamp(value) => Ok(*value),
             Value::String(_) |
             Value::Date |
             Value::Blob(_) |
@@ -255,6 +274,28 @@ impl Value {
         }
     }
 
+    //Strings are parsed as ISO-8601
+    pub fn get_timestamp(&self) -> Result<i64, SimpleDbError> {
+        match &self {
+            Value::Timestamp(value) => Ok(*value),
+            Value::String(value) => timestamp::parse_iso8601(value)
+                .ok_or_else(|| MalformedQuery(format!("{} is not a valid timestamp", value))),
+            _ => Err(MalformedQuery(String::from("Cannot get Timestamp")))
+        }
+    }
+
+    //Converts a value to the type of the column it is stored in. Only needed for timestamps written as strings
+    pub fn cast(self, column_type: &Type) -> Result<Value, SimpleDbError> {
+        match (self, column_type) {
+            (value @ Value::String(_), Type::Timestamp) => Ok(Value::Timestamp(value.get_timestamp()?)),
+            (value, _) => Ok(value),
+        }
+    }
+
+    pub fn can_be_stored_as(&self, column_type: &Type) -> bool {
+        column_type.can_be_casted(&self.to_type()) && self.clone().cast(column_type).is_ok()
+    }
+
     pub fn get_boolean(&self) -> Result<bool, SimpleDbError> {
         match &self {
             Value::Boolean(value) => Ok(*value),
@@ -274,6 +315,10 @@ impl Value {
         matches!(self, Value::Null)
     }
 
+    pub fn is_timestamp(&self) -> bool {
+        matches!(self, Value::Timestamp(_))
+    }
+
     pub fn is_number(&self) -> bool {
         match &self {
             Value::I8(_) |
@@ -290,6 +335,7 @@ impl Value {
             Value::String(_) |
             Value::Date |
             Value::Blob(_) |
+            Value::Timestamp(_) |
             Value::Null => false
         }
     }
@@ -317,7 +363,9 @@ impl Value {
     }
 
     pub fn is_comparable(&self, other: &Value) -> bool {
-        if self.is_number() && other.is_number() {
+        if (self.is_number() && other.is_number()) ||
+            (self.is_timestamp() && other.is_string()) ||
+            (self.is_string() && other.is_timestamp()) {
             true
         } else {
             utils::enum_eq(self, other)
@@ -365,32 +413,91 @@ impl Value {
     }
 
     pub fn add(&self, other: &Value) -> Result<Value, SimpleDbError> {
-        self.arithmetic_op(other, |a, b| a + b, |a, b| a + b)
+        self.arithmetic_op(other, |a, b| a + b, |a, b| a.checked_add(b))
     }
 
     pub fn substract(&self, other: &Value) -> Result<Value, SimpleDbError> {
-        self.arithmetic_op(other, |a, b| a - b, |a, b| a - b)
+        self.arithmetic_op(other, |a, b| a - b, |a, b| a.checked_sub(b))
     }
 
     pub fn multiply(&self, other: &Value) -> Result<Value, SimpleDbError> {
-        self.arithmetic_op(other, |a, b| a * b, |a, b| a * b)
+        self.arithmetic_op(other, |a, b| a * b, |a, b| a.checked_mul(b))
     }
 
+    //Dividing by zero is an error for both integers & floats, floats never produce infinity or NaN
     pub fn divide(&self, other: &Value) -> Result<Value, SimpleDbError> {
-        self.arithmetic_op(other, |a, b| a / b, |a, b| a / b)
+        if other.is_number() && other.get_f64()? == 0.0 {
+            return Err(SimpleDbError::DivisionByZero);
+        }
+
+        //i64::MIN / -1 overflows
+        self.arithmetic_op(other, |a, b| a / b, |a, b| a.checked_div(b))
+    }
+
+    pub fn modulo(&self, other: &Value) -> Result<Value, SimpleDbError> {
+        self.integer_op(other, |a, b| match b {
+            0 => Err(SimpleDbError::DivisionByZero),
+            //i64::MIN % -1 overflows
+            _ => Ok(a.wrapping_rem(b)),
+        })
+    }
+
+    pub fn bitwise_and(&self, other: &Value) -> Result<Value, SimpleDbError> {
+        self.integer_op(other, |a, b| Ok(a & b))
+    }
+
+    pub fn bitwise_or(&self, other: &Value) -> Result<Value, SimpleDbError> {
+        self.integer_op(other, |a, b| Ok(a | b))
+    }
+
+    pub fn bitwise_xor(&self, other: &Value) -> Result<Value, SimpleDbError> {
+        self.integer_op(other, |a, b| Ok(a ^ b))
+    }
+
+    pub fn shift_left(&self, other: &Value) -> Result<Value, SimpleDbError> {
+        self.integer_op(other, |a, b| Ok(a << Self::shift_amount(b)?))
+    }
+
+    //Arithmetic shift, the sign is kept
+    pub fn shift_right(&self, other: &Value) -> Result<Value, SimpleDbError> {
+        self.integer_op(other, |a, b| Ok(a >> Self::shift_amount(b)?))
+    }
+
+    fn shift_amount(shift: i64) -> Result<u32, SimpleDbError> {
+        if (0..i64::BITS as i64).contains(&shift) {
+            Ok(shift as u32)
+        } else {
+            Err(MalformedQuery(format!("Cannot shift by {} bits", shift)))
+        }
+    }
+
+    //Operations that are only defined for integers, like modulo & bitwise operations
+    fn integer_op<IntOp>(&self, other: &Value, int_op: IntOp) -> Result<Value, SimpleDbError>
+    where
+        IntOp: Fn(i64, i64) -> Result<i64, SimpleDbError>,
+    {
+        if !self.is_integer_number() || !other.is_integer_number() {
+            return Err(MalformedQuery(String::from("Only integers can be used in modulo and bitwise operations")));
+        }
+
+        Ok(Value::I64(int_op(self.get_i64()?, other.get_i64()?)?))
     }
 
     fn arithmetic_op<FpOp, IntOp>(&self, other: &Value, fp_op: FpOp, int_op: IntOp) -> Result<Value, SimpleDbError>
     where
-        IntOp: Fn(i64, i64) -> i64,
+        IntOp: Fn(i64, i64) -> Option<i64>,
         FpOp: Fn(f64, f64) -> f64,
     {
-        if !self.is_number() && !other.is_number() {
+        if !self.is_number() || !other.is_number() {
             return Err(MalformedQuery(String::from("Only numbers can be added")));
         }
 
+        //Integer operations are done without converting to f64, so that no precision is lost.
+        //int_op returns None if the result doesn't fit in i64
         if !self.is_fp_number() && !other.is_fp_number() {
-            Ok(Value::I64(int_op(self.get_i64()?, other.get_i64()?)))
+            int_op(self.get_i64()?, other.get_i64()?)
+                .map(Value::I64)
+                .ok_or(SimpleDbError::IntegerOverflow)
         } else {
             Ok(Value::F64(fp_op(self.get_f64()?, other.get_f64()?)))
         }
@@ -412,12 +519,17 @@ impl Value {
             return Err(SimpleDbError::MalformedQuery(String::from("Cannot compare values")));
         }
 
-        if self.is_fp_number() && other.is_fp_number() {
-            Ok(Value::Boolean(fp_op(self.get_f64()?, other.get_f64()?)))
-        } else if self.is_integer_number() && other.is_integer_number() {
+        if self.is_integer_number() && other.is_integer_number() {
             Ok(Value::Boolean(int_op(self.get_i64()?, other.get_i64()?)))
+        } else if self.is_number() && other.is_number() {
+            Ok(Value::Boolean(fp_op(self.get_f64()?, other.get_f64()?)))
         } else if self.is_string() && other.is_string() {
             Ok(Value::Boolean(str_op(self.get_string()?, other.get_string()?)))
+        } else if self.is_timestamp() || other.is_timestamp() {
+            Ok(Value::Boolean(int_op(self.get_timestamp()?, other.get_timestamp()?)))
+        } else if self.is_boolean() && other.is_boolean() {
+            //false < true
+            Ok(Value::Boolean(int_op(self.get_i64()?, other.get_i64()?)))
         } else {
             Err(SimpleDbError::MalformedQuery(String::from("Cannot compare values")))
         }
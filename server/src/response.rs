@@ -14,7 +14,8 @@ pub enum StatementResponse {
     Databases(Vec<String>),
     Tables(Vec<String>),
     Indexes(Vec<(String, IndexType)>),
-    Describe(Vec<ColumnDescriptor>)
+    Describe(Vec<ColumnDescriptor>),
+    Explain(Vec<String>), //One line per plan step
 }
 
 pub struct QueryDataResponse {
@@ -78,6 +79,7 @@ impl StatementResponse {
             StatementResponse::Indexes(indexes) => serialized.extend(Self::serialize_show_indexes(indexes)),
             StatementResponse::Data(data) => serialized.extend(Self::serialize_query_data(data)),
             StatementResponse::Tables(tables) => serialized.extend(Self::serialize_string_vec(tables)),
+            StatementResponse::Explain(lines) => serialized.extend(Self::serialize_string_vec(lines)),
             StatementResponse::Ok(n_affected_rows) => serialized.put_u64_le(*n_affected_rows as u64),
         };
 
@@ -147,6 +149,7 @@ impl StatementResponse {
             StatementResponse::Tables(_) => 4,
             StatementResponse::Describe(_) => 5,
             StatementResponse::Indexes(_) => 6,
+            StatementResponse::Explain(_) => 7,
         }
     }
 }
\ No newline at end of file
@@ -216,6 +216,12 @@ impl Server {
                 );
                 Ok(StatementResponse::Tables(tables))
             },
+            StatementResult::Explain(explain) => {
+                logger().debug(SimpleDbLayer::Server, &format!(
+                    "Executed explain request Connection ID: {} Statement: {}", connection_id, statement
+                ));
+                Ok(StatementResponse::Explain(explain.to_lines()))
+            },
             StatementResult::Ok(n) => {
                 logger().debug(SimpleDbLayer::Server, &format!(
                     "Executed statement request Connection ID: {} Rows affected {}. Statement: {}",
@@ -0,0 +1,369 @@
+use crate::SimpleDbFileMode;
+use std::collections::HashMap;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+//Abstracts the file I/O done by the storage engine (SSTables, WAL, manifest etc.)
+//Files are created if they don't exist when they are opened, unless they are opened in read only mode.
+pub trait FileSystem: Send + Sync {
+    fn open(&self, path: &Path, mode: &SimpleDbFileMode) -> Result<Box<dyn FileHandle>, Error>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<(), Error>;
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error>;
+
+    fn remove_file(&self, path: &Path) -> Result<(), Error>;
+
+    fn create_dir(&self, path: &Path) -> Result<(), Error>;
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), Error>;
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), Error>;
+
+    //Returns the paths of the files and directories inside the directory
+    fn list(&self, path: &Path) -> Result<Vec<PathBuf>, Error>;
+}
+
+pub trait FileHandle: Send + Sync {
+    //Reads from the current position until the end of the file
+    fn read_to_end(&self, buff: &mut Vec<u8>) -> Result<usize, Error>;
+
+    //Reads at the offset, without taking into account the current position
+    fn read_at(&self, buff: &mut [u8], offset: u64) -> Result<usize, Error>;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error>;
+
+    fn set_len(&mut self, size: u64) -> Result<(), Error>;
+
+    fn sync_all(&self) -> Result<(), Error>;
+
+    fn size(&self) -> Result<u64, Error>;
+}
+
+pub fn std_file_system() -> Arc<dyn FileSystem> {
+    Arc::new(StdFileSystem {})
+}
+
+//Default implementation, backed by std::fs
+pub struct StdFileSystem {}
+
+struct StdFileHandle {
+    file: File,
+}
+
+impl FileSystem for StdFileSystem {
+    fn open(&self, path: &Path, mode: &SimpleDbFileMode) -> Result<Box<dyn FileHandle>, Error> {
+        let is_append_only = matches!(mode, SimpleDbFileMode::AppendOnly);
+        let is_read_only = matches!(mode, SimpleDbFileMode::ReadOnly);
+
+        let file = OpenOptions::new()
+            .append(is_append_only)
+            .write(!is_read_only)
+            .create(!is_read_only) //Create file if it doest exist
+            .read(true)
+            .open(path)?;
+
+        Ok(Box::new(StdFileHandle { file }))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        fs::copy(from, to).map(|_| ())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        fs::rename(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), Error> {
+        fs::remove_file(path)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), Error> {
+        fs::create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), Error> {
+        fs::create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        fs::remove_dir_all(path)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(path)? {
+            paths.push(entry?.path());
+        }
+        Ok(paths)
+    }
+}
+
+impl FileHandle for StdFileHandle {
+    fn read_to_end(&self, buff: &mut Vec<u8>) -> Result<usize, Error> {
+        (&self.file).read_to_end(buff)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, buff: &mut [u8], offset: u64) -> Result<usize, Error> {
+        use std::os::windows::fs::FileExt;
+        self.file.seek_read(buff, offset)
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, buff: &mut [u8], offset: u64) -> Result<usize, Error> {
+        use std::os::unix::fs::FileExt;
+        FileExt::read_at(&self.file, buff, offset)
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.file.write_all(bytes)
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), Error> {
+        self.file.set_len(size)
+    }
+
+    fn sync_all(&self) -> Result<(), Error> {
+        self.file.sync_all()
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        Ok(self.file.metadata()?.len())
+    }
+}
+
+//Keeps all files in memory. Directories are created implicitly when a file is created inside them.
+//Useful for tests that don't want to touch the disk
+#[derive(Default)]
+pub struct InMemoryFileSystem {
+    files: RwLock<HashMap<PathBuf, Arc<RwLock<Vec<u8>>>>>,
+    dirs: RwLock<Vec<PathBuf>>,
+}
+
+struct InMemoryFileHandle {
+    data: Arc<RwLock<Vec<u8>>>,
+    position: Mutex<usize>,
+    append: bool,
+}
+
+impl InMemoryFileSystem {
+    pub fn create() -> InMemoryFileSystem {
+        InMemoryFileSystem::default()
+    }
+
+    pub fn list_files(&self) -> Vec<PathBuf> {
+        self.files.read().unwrap().keys().cloned().collect()
+    }
+
+    fn not_found(path: &Path) -> Error {
+        Error::new(ErrorKind::NotFound, format!("{:?} not found", path))
+    }
+
+    fn add_dir(&self, path: &Path) {
+        let mut dirs = self.dirs.write().unwrap();
+        if !dirs.iter().any(|dir| dir == path) {
+            dirs.push(path.to_path_buf());
+        }
+    }
+}
+
+impl FileSystem for InMemoryFileSystem {
+    fn open(&self, path: &Path, mode: &SimpleDbFileMode) -> Result<Box<dyn FileHandle>, Error> {
+        let mut files = self.files.write().unwrap();
+        if matches!(mode, SimpleDbFileMode::ReadOnly) && !files.contains_key(path) {
+            return Err(Self::not_found(path));
+        }
+        let data = files.entry(path.to_path_buf())
+            .or_default()
+            .clone();
+        drop(files);
+        if let Some(parent) = path.parent() {
+            self.add_dir(parent);
+        }
+
+        Ok(Box::new(InMemoryFileHandle {
+            append: matches!(mode, SimpleDbFileMode::AppendOnly),
+            position: Mutex::new(0),
+            data,
+        }))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.read().unwrap().contains_key(path) || self.is_dir(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.dirs.read().unwrap().iter().any(|dir| dir == path)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let mut files = self.files.write().unwrap();
+        let data = files.get(from)
+            .ok_or(Self::not_found(from))?
+            .read().unwrap()
+            .clone();
+        files.insert(to.to_path_buf(), Arc::new(RwLock::new(data)));
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        if self.is_dir(from) {
+            let mut dirs = self.dirs.write().unwrap();
+            for dir in dirs.iter_mut().filter(|dir| dir.starts_with(from)) {
+                *dir = to.join(dir.strip_prefix(from).unwrap());
+            }
+            let mut files = self.files.write().unwrap();
+            let moved: Vec<PathBuf> = files.keys()
+                .filter(|file| file.starts_with(from))
+                .cloned()
+                .collect();
+            for file in moved {
+                let data = files.remove(&file).unwrap();
+                files.insert(to.join(file.strip_prefix(from).unwrap()), data);
+            }
+            Ok(())
+        } else {
+            let mut files = self.files.write().unwrap();
+            let data = files.remove(from).ok_or(Self::not_found(from))?;
+            files.insert(to.to_path_buf(), data);
+            Ok(())
+        }
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), Error> {
+        self.files.write().unwrap()
+            .remove(path)
+            .map(|_| ())
+            .ok_or(Self::not_found(path))
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), Error> {
+        if self.is_dir(path) {
+            return Err(Error::new(ErrorKind::AlreadyExists, format!("{:?} already exists", path)));
+        }
+        self.add_dir(path);
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), Error> {
+        for ancestor in path.ancestors() {
+            self.add_dir(ancestor);
+        }
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        if !self.is_dir(path) {
+            return Err(Self::not_found(path));
+        }
+        self.dirs.write().unwrap().retain(|dir| !dir.starts_with(path));
+        self.files.write().unwrap().retain(|file, _| !file.starts_with(path));
+        Ok(())
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        if !self.is_dir(path) {
+            return Err(Self::not_found(path));
+        }
+
+        let mut paths: Vec<PathBuf> = self.files.read().unwrap().keys()
+            .filter(|file| file.parent() == Some(path))
+            .cloned()
+            .collect();
+        paths.extend(self.dirs.read().unwrap().iter()
+            .filter(|dir| dir.parent() == Some(path))
+            .cloned());
+        Ok(paths)
+    }
+}
+
+impl FileHandle for InMemoryFileHandle {
+    fn read_to_end(&self, buff: &mut Vec<u8>) -> Result<usize, Error> {
+        let data = self.data.read().unwrap();
+        let mut position = self.position.lock().unwrap();
+        let start = (*position).min(data.len());
+        buff.extend_from_slice(&data[start..]);
+        *position = data.len();
+        Ok(data.len() - start)
+    }
+
+    fn read_at(&self, buff: &mut [u8], offset: u64) -> Result<usize, Error> {
+        let data = self.data.read().unwrap();
+        let start = (offset as usize).min(data.len());
+        let n_bytes = buff.len().min(data.len() - start);
+        buff[..n_bytes].copy_from_slice(&data[start..start + n_bytes]);
+        Ok(n_bytes)
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let mut data = self.data.write().unwrap();
+        let position = self.position.get_mut().unwrap();
+        if self.append {
+            *position = data.len();
+        }
+        let end = *position + bytes.len();
+        if end > data.len() {
+            data.resize(end, 0);
+        }
+        data[*position..end].copy_from_slice(bytes);
+        *position = end;
+        Ok(())
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), Error> {
+        self.data.write().unwrap().resize(size as usize, 0);
+        Ok(())
+    }
+
+    fn sync_all(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        Ok(self.data.read().unwrap().len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file_system::{FileSystem, InMemoryFileSystem};
+    use crate::SimpleDbFileMode;
+    use std::path::Path;
+
+    #[test]
+    fn in_memory_file_system() {
+        let fs = InMemoryFileSystem::create();
+        fs.create_dir_all(Path::new("/db/1")).unwrap();
+
+        let mut file = fs.open(Path::new("/db/1/wal-1"), &SimpleDbFileMode::AppendOnly).unwrap();
+        file.write_all(&[1, 2]).unwrap();
+        file.write_all(&[3]).unwrap();
+
+        let mut buff = vec![0; 2];
+        assert_eq!(file.read_at(&mut buff, 1).unwrap(), 2);
+        assert_eq!(buff, vec![2, 3]);
+
+        fs.rename(Path::new("/db/1"), Path::new("/db/1.dropped")).unwrap();
+        assert!(!fs.exists(Path::new("/db/1/wal-1")));
+        assert_eq!(fs.list(Path::new("/db/1.dropped")).unwrap(), vec![Path::new("/db/1.dropped/wal-1").to_path_buf()]);
+
+        fs.remove_dir_all(Path::new("/db/1.dropped")).unwrap();
+        assert_eq!(fs.list(Path::new("/db")).unwrap().len(), 0);
+    }
+}
@@ -1,8 +1,12 @@
 use crate::iterators::storage_iterator::StorageIterator;
 use crate::key::Key;
+use crate::SimpleDbError;
 use bytes::Bytes;
 use std::collections::HashSet;
 
+//Iterators holding the same key (same bytes & txn_id) are deduplicated, only one entry is returned.
+//The entry of the iterator with the lowest index is returned, so sources should be passed from newest to oldest
+//(for example: memtables before SSTables), which makes the output deterministic
 pub struct MergeIterator<I: StorageIterator> {
     //We use Option so that when an iterator has not next, we can remove it by placing None
     iterators: Vec<Option<Box<I>>>,
@@ -10,6 +14,8 @@ pub struct MergeIterator<I: StorageIterator> {
     last_value_iterated: Option<Bytes>,
     last_key_iterated: Option<Key>,
     finished_iterators_indexes: HashSet<usize>,
+    //Error of the first iterator that failed. The merge finishes once an iterator fails
+    error: Option<SimpleDbError>,
 
     first_iteration: bool,
 }
@@ -30,6 +36,7 @@ impl<I: StorageIterator> MergeIterator<I> {
             last_value_iterated: None,
             last_key_iterated: None,
             first_iteration: true,
+            error: None,
         }
     }
 
@@ -51,6 +58,9 @@ impl<I: StorageIterator> MergeIterator<I> {
                 while !is_iterator_up_to_date(&iterator, &min_key_seen) {
                     if !iterator.next() { //Has not advanced
                         finished_iterators.push(current_index);
+                        if self.error.is_none() {
+                            self.error = iterator.take_error();
+                        }
                         break;
                     }
                 }
@@ -74,6 +84,9 @@ impl<I: StorageIterator> MergeIterator<I> {
             //Has not advanced
             if !iterator.next() {
                 self.finished_iterators_indexes.insert(current_index);
+                if self.error.is_none() {
+                    self.error = iterator.take_error();
+                }
             }
 
             current_index += 1;
@@ -87,6 +100,9 @@ impl<I: StorageIterator> StorageIterator for MergeIterator<I> {
             self.call_next_on_every_iterator();
             self.first_iteration = false;
         }
+        if self.error.is_some() {
+            return false;
+        }
 
         let mut min_key_seen: Option<Key> = None;
         let mut min_iterator_index = 0;
@@ -106,7 +122,8 @@ impl<I: StorageIterator> StorageIterator for MergeIterator<I> {
 
                 if !key_smaller_than_prev_iteration && !key_larger_than_min {
                     match min_key_seen {
-                        Some(_) => if current_key.le(min_key_seen.as_ref().unwrap()) {
+                        //Equal keys are ignored, so the first iterator holding the key is chosen
+                        Some(_) => if current_key.lt(min_key_seen.as_ref().unwrap()) {
                             min_key_seen = Some(current_key.clone());
                             min_iterator_index = current_index;
                         },
@@ -134,7 +151,7 @@ impl<I: StorageIterator> StorageIterator for MergeIterator<I> {
     }
 
     fn has_next(&self) -> bool {
-        self.finished_iterators_indexes.len() < self.iterators.len()
+        self.error.is_none() && self.finished_iterators_indexes.len() < self.iterators.len()
     }
 
     fn key(&self) -> &Key {
@@ -157,8 +174,16 @@ impl<I: StorageIterator> StorageIterator for MergeIterator<I> {
             }
         }
 
+        //Iterators might be moved back, before the last key returned or after they have finished
+        self.finished_iterators_indexes.clear();
+        self.last_key_iterated = None;
+        self.last_value_iterated = None;
         self.first_iteration = true;
     }
+
+    fn take_error(&mut self) -> Option<SimpleDbError> {
+        self.error.take()
+    }
 }
 
 fn is_iterator_up_to_date<I: StorageIterator>(it: &Box<I>, last_key: &Key) -> bool {
@@ -172,6 +197,7 @@ mod test {
     use crate::iterators::merge_iterator::MergeIterator;
     use crate::iterators::mock_iterator::MockIterator;
     use crate::iterators::storage_iterator::StorageIterator;
+    use crate::key::Key;
     use bytes::Bytes;
 
     #[test]
@@ -265,6 +291,61 @@ mod test {
         );
     }
 
+    /**
+    A -> B -> D
+    B -> E
+    C -> D -> E
+    */
+    #[test]
+    fn seek_back_after_finished() {
+        let mut iterator = create_merge_iterator();
+        while iterator.next() {}
+        iterator.seek(&Bytes::from("b"), true);
+
+        assert_iterator_str_seq(
+            iterator,
+            vec!["b", "c", "d", "e"]
+        );
+    }
+
+    #[test]
+    fn identical_keys() {
+        let mut memtable_iterator = MockIterator::create();
+        memtable_iterator.add_entry("a", 1, Bytes::from("memtable"));
+        memtable_iterator.add_entry("b", 1, Bytes::from("memtable"));
+        let mut sstable_iterator = MockIterator::create();
+        sstable_iterator.add_entry("a", 1, Bytes::from("sstable"));
+        sstable_iterator.add_entry("b", 1, Bytes::from("sstable"));
+        sstable_iterator.add_entry("b", 2, Bytes::from("sstable"));
+
+        let mut iterator = MergeIterator::create(vec![Box::new(memtable_iterator), Box::new(sstable_iterator)]);
+
+        assert!(iterator.next());
+        assert_eq!(iterator.key(), &Key::create_from_str("a", 1));
+        assert_eq!(iterator.value(), b"memtable");
+        assert!(iterator.next());
+        assert_eq!(iterator.key(), &Key::create_from_str("b", 1));
+        assert_eq!(iterator.value(), b"memtable");
+        assert!(iterator.next());
+        assert_eq!(iterator.key(), &Key::create_from_str("b", 2));
+        assert_eq!(iterator.value(), b"sstable");
+        assert!(!iterator.next());
+    }
+
+    #[test]
+    fn failed_iterator() {
+        let mut iterator1 = MockIterator::create_from_strs_values(vec!["a", "c", "e"]);
+        iterator1.fail_after(1, crate::SimpleDbError::CorruptedBlock(0, 1, 0));
+        let iterator2 = MockIterator::create_from_strs_values(vec!["b", "d", "f"]);
+        let mut iterator = MergeIterator::create(vec![Box::new(iterator1), Box::new(iterator2)]);
+
+        assert!(iterator.next());
+        assert_eq!(iterator.key().to_string(), "a");
+        assert!(!iterator.next());
+        assert!(!iterator.has_next());
+        assert!(matches!(iterator.take_error(), Some(crate::SimpleDbError::CorruptedBlock(0, 1, 0))));
+    }
+
     fn create_merge_iterator() -> MergeIterator<MockIterator> {
         let mut iterator1 = MockIterator::create();
         iterator1.add_entry("a", 0, Bytes::from("a"));
@@ -1,12 +1,15 @@
 use crate::iterators::storage_iterator::StorageIterator;
 use crate::key::Key;
-use crate::TxnId;
+use crate::{SimpleDbError, TxnId};
 use bytes::Bytes;
 
 pub struct MockIterator {
     entries: Vec<(Key, Bytes)>,
 
     next_index: usize,
+    //The iterator fails with the error after returning the number of entries
+    fail_after: Option<(usize, SimpleDbError)>,
+    error: Option<SimpleDbError>,
 }
 
 impl MockIterator {
@@ -36,17 +39,32 @@ impl MockIterator {
     pub fn create() -> MockIterator {
         MockIterator {
             entries: Vec::new(),
-            next_index: 0
+            next_index: 0,
+            fail_after: None,
+            error: None,
         }
     }
 
     pub fn add_entry(&mut self, key: &str, txn_id: TxnId, value: Bytes) {
         self.entries.push((Key::create_from_str(key, txn_id), value));
     }
+
+    pub fn add_key(&mut self, key: Key, value: Bytes) {
+        self.entries.push((key, value));
+    }
+
+    pub fn fail_after(&mut self, n_entries: usize, error: SimpleDbError) {
+        self.fail_after = Some((n_entries, error));
+    }
 }
 
 impl StorageIterator for MockIterator {
     fn next(&mut self) -> bool {
+        if self.fail_after.as_ref().is_some_and(|(n_entries, _)| *n_entries == self.next_index) {
+            self.error = self.fail_after.take().map(|(_, error)| error);
+            self.next_index = self.entries.len();
+            return false;
+        }
         if self.next_index < self.entries.len() {
             self.next_index += 1;
             true
@@ -56,7 +74,7 @@ impl StorageIterator for MockIterator {
     }
 
     fn has_next(&self) -> bool {
-        self.next_index < self.entries.len()
+        self.next_index < self.entries.len() || self.fail_after.is_some()
     }
 
     fn key(&self) -> &Key {
@@ -113,6 +131,10 @@ impl StorageIterator for MockIterator {
             };
         }
     }
+
+    fn take_error(&mut self) -> Option<SimpleDbError> {
+        self.error.take()
+    }
 }
 
 #[cfg(test)]
@@ -1,14 +1,19 @@
 use bytes::Bytes;
 use crate::key::Key;
+use crate::SimpleDbError;
 
 //This is the iterator interface for all iterators in simpleDb, specially in the storage engine layer
 //The iterated collection is expected:
 // - To be ordered in ascending order
 // - Items to be unique
+//Iterators don't point to any entry when they are created or after a seek. Every call to next(), including the
+//first one, moves the iterator to the next entry, so callers only need to call next() before key() & value()
 pub trait StorageIterator {
-    //Returns true if it has advanced
+    //Returns true if it has advanced. If it returns false, key() & value() shouldn't be called
     fn next(&mut self) -> bool;
 
+    //Returns true if there might be entries after the current one. Some iterators can only know if the next
+    //entries are visible by advancing, so next() might still return false
     fn has_next(&self) -> bool;
 
     //Returns current key. Expect call after next();
@@ -26,4 +31,36 @@ pub trait StorageIterator {
     //[1, 3, 5] Seek = 0, inclusive = true or false. The iterator will point to 1
     //[1, 3, 5] Seek = 2, inclusive = true or false. The iterator will point to 3
     fn seek(&mut self, key: &Bytes, inclusive: bool);
+
+    //Returns the error that finished the iterator, if any. next() cannot return errors, so when an entry cannot be
+    //read, next() returns false & the error is kept until it is taken. Iterators wrapping other iterators finish
+    //as soon as one of them fails, and return its error
+    fn take_error(&mut self) -> Option<SimpleDbError> {
+        None
+    }
+
+    //Returns the number of remaining entries
+    fn count(mut self) -> usize where Self: Sized {
+        let mut count = 0;
+        while self.next() {
+            count += 1;
+        }
+        count
+    }
+
+    //Returns the remaining keys, in the order they are iterated
+    fn collect_keys(mut self) -> Vec<Key> where Self: Sized {
+        let mut keys = Vec::new();
+        while self.next() {
+            keys.push(self.key().clone());
+        }
+        keys
+    }
+
+    //Calls f with every remaining entry
+    fn for_each<F: FnMut(&Key, &[u8])>(mut self, mut f: F) where Self: Sized {
+        while self.next() {
+            f(self.key(), self.value());
+        }
+    }
 }
\ No newline at end of file
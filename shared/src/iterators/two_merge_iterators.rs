@@ -1,88 +1,84 @@
 use crate::iterators::storage_iterator::StorageIterator;
 use bytes::Bytes;
 use crate::key::Key;
+use crate::SimpleDbError;
 
 pub struct TwoMergeIterator<A: StorageIterator, B: StorageIterator> {
     a: A,
     b: B,
 
+    //True if the current entry is taken from a
     choose_a: bool,
+    //True if the iterator points to an entry, which might not have been returned yet
+    a_valid: bool,
+    b_valid: bool,
+    //Error of the iterator that failed. The merge finishes once one of them fails
+    error: Option<SimpleDbError>,
 
     first_iteration: bool,
 }
 
 impl<A: StorageIterator, B: StorageIterator> TwoMergeIterator<A, B> {
     pub fn create(a: A, b: B) -> TwoMergeIterator<A, B> {
-        TwoMergeIterator { a, b, choose_a: false, first_iteration: true }
+        TwoMergeIterator { a, b, choose_a: false, a_valid: false, b_valid: false, error: None, first_iteration: true }
     }
 
+    //The smallest key is returned first. If both keys are equal, a is chosen
     fn choose_a(&self) -> bool {
-        if !self.a.has_next() && !self.b.has_next() {
-            //Return previous choice
-            return self.choose_a;
-        }
-        if !self.a.has_next() && self.b.has_next() {
-            return false;
-        }
-        if !self.b.has_next() && self.a.has_next() {
-            return true;
-        }
-
-        self.a.key() < self.b.key()
+        self.a_valid && (!self.b_valid || self.a.key() <= self.b.key())
     }
 
-    fn skip_b_duplicates(&mut self) {
-        while self.a.has_next() && self.b.has_next() && self.a.key() == self.b.key() {
-            self.b.next();
+    fn take_iterators_error(&mut self) {
+        if self.error.is_none() && !self.a_valid {
+            self.error = self.a.take_error();
+        }
+        if self.error.is_none() && !self.b_valid {
+            self.error = self.b.take_error();
         }
     }
 
-    fn first_iteration(&mut self) -> bool {
-        self.first_iteration = false;
-        let a_advanced = self.a.next();
-        let b_advanced = self.b.next();
-
-        if !a_advanced && !b_advanced {
-            return false;
-        }
-        if a_advanced && !b_advanced {
-            self.choose_a = true;
-        }
-        if !a_advanced && b_advanced {
-            self.choose_a = false;
-        }
-        if a_advanced && b_advanced {
-            self.skip_b_duplicates();
-            self.choose_a = self.choose_a();
+    //Identical keys are returned once, taking the entry from a
+    fn skip_b_duplicates(&mut self) {
+        while self.a_valid && self.b_valid && self.a.key() == self.b.key() {
+            self.b_valid = self.b.next();
         }
-
-        true
     }
 }
 
 impl<A: StorageIterator, B: StorageIterator> StorageIterator for TwoMergeIterator<A, B> {
     fn next(&mut self) -> bool {
-        if !self.has_next() {
-            return false;
-        }
         if self.first_iteration {
-            return self.first_iteration();
+            self.first_iteration = false;
+            self.a_valid = self.a.next();
+            self.b_valid = self.b.next();
+        } else if self.choose_a && self.a_valid {
+            self.a_valid = self.a.next();
+        } else if !self.choose_a && self.b_valid {
+            self.b_valid = self.b.next();
         }
 
-        let advanced= if self.choose_a {
-            self.a.next()
-        } else { //Choose b
-            self.b.next()
-        };
-
         self.skip_b_duplicates();
+        self.take_iterators_error();
+        if self.error.is_some() {
+            self.a_valid = false;
+            self.b_valid = false;
+        }
         self.choose_a = self.choose_a();
 
-        advanced
+        self.a_valid || self.b_valid
     }
 
+    //The iterator that has not been chosen might still point to an entry that hasn't been returned
     fn has_next(&self) -> bool {
-        self.a.has_next() || self.b.has_next()
+        if self.error.is_some() {
+            return false;
+        }
+        if self.first_iteration {
+            return self.a.has_next() || self.b.has_next();
+        }
+
+        let other_pending = if self.choose_a { self.b_valid } else { self.a_valid };
+        other_pending || self.a.has_next() || self.b.has_next()
     }
 
     fn key(&self) -> &Key {
@@ -107,6 +103,10 @@ impl<A: StorageIterator, B: StorageIterator> StorageIterator for TwoMergeIterato
         self.b.seek(key, inclusive);
         self.first_iteration = true;
     }
+
+    fn take_error(&mut self) -> Option<SimpleDbError> {
+        self.error.take()
+    }
 }
 
 #[cfg(test)]
@@ -201,6 +201,68 @@ mod test {
         );
     }
 
+    #[test]
+    fn interleaved() {
+        assertions::assert_iterator_str_seq(
+            TwoMergeIterator::create(
+                MockIterator::create_from_strs_values(vec!["a", "c", "e", "g"]),
+                MockIterator::create_from_strs_values(vec!["b", "d", "f", "h"]),
+            ),
+            vec!["a", "b", "c", "d", "e", "f", "g", "h"]
+        );
+        assertions::assert_iterator_str_seq(
+            TwoMergeIterator::create(
+                MockIterator::create_from_strs_values(vec!["b", "d", "f", "h"]),
+                MockIterator::create_from_strs_values(vec!["a", "c", "e", "g"]),
+            ),
+            vec!["a", "b", "c", "d", "e", "f", "g", "h"]
+        );
+    }
+
+    //The last entry of a is smaller than the entries left in b
+    #[test]
+    fn interleaved_one_iterator_ends_first() {
+        assertions::assert_iterator_str_seq(
+            TwoMergeIterator::create(
+                MockIterator::create_from_strs_values(vec!["b"]),
+                MockIterator::create_from_strs_values(vec!["a", "c", "d"]),
+            ),
+            vec!["a", "b", "c", "d"]
+        );
+        assertions::assert_iterator_str_seq(
+            TwoMergeIterator::create(
+                MockIterator::create_from_strs_values(vec!["a", "c", "d"]),
+                MockIterator::create_from_strs_values(vec!["b"]),
+            ),
+            vec!["a", "b", "c", "d"]
+        );
+    }
+
+    //Equal keys are taken from a
+    #[test]
+    fn interleaved_duplicates() {
+        let mut a = MockIterator::create();
+        a.add_entry("a", 0, Bytes::from(vec![1]));
+        a.add_entry("c", 0, Bytes::from(vec![1]));
+        a.add_entry("d", 0, Bytes::from(vec![1]));
+        let mut b = MockIterator::create();
+        b.add_entry("b", 0, Bytes::from(vec![2]));
+        b.add_entry("c", 0, Bytes::from(vec![2]));
+        b.add_entry("d", 0, Bytes::from(vec![2]));
+        b.add_entry("e", 0, Bytes::from(vec![2]));
+
+        let mut iterator = TwoMergeIterator::create(a, b);
+        let mut entries = Vec::new();
+        while iterator.next() {
+            entries.push((iterator.key().as_bytes().clone(), iterator.value()[0]));
+        }
+
+        assert_eq!(entries, vec![
+            (Bytes::from("a"), 1), (Bytes::from("b"), 2), (Bytes::from("c"), 1), (Bytes::from("d"), 1), (Bytes::from("e"), 2)
+        ]);
+        assert!(!iterator.has_next());
+    }
+
     // A -> B -> D
     // A -> C -> D -> F
     #[test]
@@ -217,6 +279,21 @@ mod test {
         );
     }
 
+    #[test]
+    fn failed_iterator() {
+        let iterator1 = MockIterator::create_from_strs_values(vec!["a", "c", "e"]);
+        let mut iterator2 = MockIterator::create_from_strs_values(vec!["b", "d", "f"]);
+        iterator2.fail_after(1, crate::SimpleDbError::CorruptedBlock(0, 1, 0));
+        let mut iterator = TwoMergeIterator::create(iterator1, iterator2);
+
+        assert!(iterator.next());
+        assert_eq!(iterator.key().to_string(), "a");
+        assert!(iterator.next());
+        assert_eq!(iterator.key().to_string(), "b");
+        assert!(!iterator.next());
+        assert!(matches!(iterator.take_error(), Some(crate::SimpleDbError::CorruptedBlock(0, 1, 0))));
+    }
+
     fn create_iterator() -> TwoMergeIterator<MockIterator, MockIterator> {
         let mut iterator1 = MockIterator::create();
         iterator1.add_entry("a", 0, Bytes::from(vec![1]));
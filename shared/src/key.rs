@@ -4,27 +4,78 @@ use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Formatter;
 
+//Set in the serialized flags of keys written by deletes
+const KEY_TOMBSTONE_FLAG: u8 = 0x01;
+//Set in the serialized flags of keys whose value starts with its expiration time
+const KEY_TTL_FLAG: u8 = 0x02;
+//Set in the serialized flags of WAL entries written by range deletes. The key is the start of the range & the value its end
+const KEY_RANGE_TOMBSTONE_FLAG: u8 = 0x04;
+
 #[derive(Debug)]
 pub struct Key {
     bytes: Bytes,
     txn_id: TxnId,
+    //KEY_TOMBSTONE_FLAG is set if the key has been deleted by the transaction txn_id.
+    //KEY_TTL_FLAG is set if the value expires. Flags are not used when comparing keys
+    flags: u8,
 }
 
 impl Key {
     pub fn create_from_str(string: &str, txn_id: TxnId) -> Key {
         Key {
             bytes: Bytes::from(string.to_string()),
+            flags: 0,
             txn_id
         }
     }
 
     pub fn create(bytes: Bytes, txn_id: TxnId) -> Key {
         Key {
+            flags: 0,
             bytes,
             txn_id
         }
     }
 
+    pub fn create_tombstone(bytes: Bytes, txn_id: TxnId) -> Key {
+        Key {
+            flags: KEY_TOMBSTONE_FLAG,
+            bytes,
+            txn_id
+        }
+    }
+
+    pub fn create_range_tombstone(start: Bytes, txn_id: TxnId) -> Key {
+        Key {
+            flags: KEY_TOMBSTONE_FLAG | KEY_RANGE_TOMBSTONE_FLAG,
+            bytes: start,
+            txn_id
+        }
+    }
+
+    pub fn is_range_tombstone(&self) -> bool {
+        self.flags & KEY_RANGE_TOMBSTONE_FLAG != 0
+    }
+
+    pub fn is_tombstone(&self) -> bool {
+        self.flags & KEY_TOMBSTONE_FLAG != 0
+    }
+
+    //Tombstones never expire
+    pub fn with_tombstone(mut self, tombstone: bool) -> Key {
+        self.flags = if tombstone { KEY_TOMBSTONE_FLAG } else { self.flags & !KEY_TOMBSTONE_FLAG };
+        self
+    }
+
+    pub fn has_ttl(&self) -> bool {
+        self.flags & KEY_TTL_FLAG != 0
+    }
+
+    pub fn with_ttl(mut self, ttl: bool) -> Key {
+        self.flags = if ttl { self.flags | KEY_TTL_FLAG } else { self.flags & !KEY_TTL_FLAG };
+        self
+    }
+
     pub fn len(&self) -> usize {
         self.bytes.len()
     }
@@ -67,30 +118,58 @@ impl Key {
 
     pub fn serialized_key_size(ptr: &mut &[u8]) -> usize {
         let _ = ptr.get_u64_le() as TxnId;
+        let _ = ptr.get_u8();
         let bytes_len = ptr.get_u16_le();
 
-        8 + 2 + bytes_len as usize
+        8 + 1 + 2 + bytes_len as usize
     }
 
     pub fn deserialize(ptr: &mut &[u8]) -> Key {
         let txn_id = ptr.get_u64_le() as TxnId;
+        let flags = ptr.get_u8();
         let bytes_len = ptr.get_u16_le();
         let bytes = &ptr[.. bytes_len as usize];
         ptr.advance(bytes_len as usize);
 
         Key {
             bytes: Bytes::copy_from_slice(bytes),
+            flags,
+            txn_id,
+        }
+    }
+
+    //Keys serialized before keys had flags: txn id (u64), length (u16), bytes. The key is returned without flags
+    pub fn deserialize_without_flags(ptr: &mut &[u8]) -> Key {
+        let txn_id = ptr.get_u64_le() as TxnId;
+        let bytes_len = ptr.get_u16_le();
+        let bytes = &ptr[.. bytes_len as usize];
+        ptr.advance(bytes_len as usize);
+
+        Key {
+            bytes: Bytes::copy_from_slice(bytes),
+            flags: 0,
             txn_id,
         }
     }
 
     pub fn serialized_size(&self) -> usize {
-        8 + 2 + self.bytes.len()
+        8 + 1 + 2 + self.bytes.len()
+    }
+
+    //Flags of the key, stored in SSTables & WAL entries
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn with_flags(mut self, flags: u8) -> Key {
+        self.flags = flags;
+        self
     }
 
     pub fn serialize(&self) -> Vec<u8> {
         let mut serialized = Vec::new();
         serialized.put_u64_le(self.txn_id as u64);
+        serialized.put_u8(self.flags());
         serialized.put_u16_le(self.bytes.len() as u16);
         serialized.extend(self.bytes.as_ref());
         serialized
@@ -119,13 +198,17 @@ impl Key {
     //"Juan".split(2) -> ("Ju", "an")
     pub fn split(&self, index: usize) -> (Key, Key) {
         let (h1, h2) = self.bytes.split_at(index);
-        (Key::create(Bytes::from(h1.to_vec()), self.txn_id), Key::create(Bytes::from(h2.to_vec()), self.txn_id))
+        (
+            Key::create(Bytes::from(h1.to_vec()), self.txn_id).with_flags(self.flags),
+            Key::create(Bytes::from(h2.to_vec()), self.txn_id).with_flags(self.flags)
+        )
     }
 
+    //The flags are taken from b
     pub fn merge(a: &Key, b: &Key, txn_id: TxnId) -> Key {
         let mut result = Vec::from(a.bytes.as_ref());
         result.extend(b.bytes.as_ref());
-        Key { bytes: Bytes::from(result), txn_id }
+        Key { bytes: Bytes::from(result), flags: b.flags, txn_id }
     }
 }
 
@@ -140,7 +223,7 @@ impl fmt::Display for Key {
 
 impl Default for Key {
     fn default() -> Self {
-        Key{ bytes: Bytes::from(vec![]), txn_id: 0 }
+        Key{ bytes: Bytes::from(vec![]), flags: 0, txn_id: 0 }
     }
 }
 
@@ -155,10 +238,12 @@ impl Eq for Key {}
 impl Clone for Key {
     fn clone(&self) -> Self {
         let cloned = self.bytes.clone();
-        Key { bytes: cloned, txn_id: self.txn_id }
+        Key { bytes: cloned, flags: self.flags, txn_id: self.txn_id }
     }
 }
 
+//Keys are ordered by its bytes. Versions of the same key are ordered by txn_id in ascending order,
+//so when iterating, the newest version of a key will be the last one
 impl PartialOrd for Key {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match self.bytes.partial_cmp(&other.bytes) {
@@ -180,6 +265,7 @@ impl Ord for Key {
 #[cfg(test)]
 mod test {
     use crate::key::Key;
+    use bytes::BufMut;
 
     #[test]
     fn serialize_deserialize() {
@@ -188,5 +274,39 @@ mod test {
         let deserialized = Key::deserialize(&mut serialized.as_slice());
 
         assert_eq!(deserialized, Key::create_from_str("Jaime", 1));
+        assert!(!deserialized.is_tombstone());
+
+        let key = Key::create_tombstone(bytes::Bytes::from("Jaime"), 2);
+        let deserialized = Key::deserialize(&mut key.serialize().as_slice());
+        assert_eq!(deserialized, Key::create_from_str("Jaime", 2));
+        assert!(deserialized.is_tombstone());
+        assert!(!deserialized.has_ttl());
+
+        let key = Key::create_from_str("Jaime", 3).with_ttl(true);
+        let deserialized = Key::deserialize(&mut key.serialize().as_slice());
+        assert!(deserialized.has_ttl());
+        assert!(!deserialized.is_tombstone());
+    }
+
+    #[test]
+    fn deserialize_without_flags() {
+        let mut serialized = Vec::new();
+        serialized.put_u64_le(4);
+        serialized.put_u16_le(5);
+        serialized.extend_from_slice(b"Jaime");
+        serialized.put_u8(1);
+
+        let mut ptr = serialized.as_slice();
+        let deserialized = Key::deserialize_without_flags(&mut ptr);
+        assert_eq!(deserialized, Key::create_from_str("Jaime", 4));
+        assert_eq!(deserialized.flags(), 0);
+        assert_eq!(ptr, &[1]);
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(Key::create_from_str("a", 5) < Key::create_from_str("b", 1));
+        assert!(Key::create_from_str("a", 1) < Key::create_from_str("a", 3));
+        assert!(Key::create_from_str("a", 3) < Key::create_from_str("a", 5));
     }
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 mod simpledb_file;
+mod file_system;
 mod simpledb_files;
 mod atomic_shared_ref;
 mod simpledb_options;
@@ -11,8 +12,10 @@ pub mod logger;
 pub mod assertions;
 pub mod iterators;
 pub mod key;
+pub mod timestamp;
 
 pub use simpledb_file::*;
+pub use file_system::*;
 pub use utils::*;
 pub use simpledb_files::*;
 pub use simpledb_options::*;
@@ -26,7 +26,7 @@ impl Logger {
             let mut builder = Builder::new();
             builder.filter_level(log::LevelFilter::Info);
             builder.filter_level(log::LevelFilter::Debug);
-            builder.init();
+            let _ = builder.try_init();
 
             let logger = Arc::new(Logger{ options });
             let _ = LOGGER.set(logger);
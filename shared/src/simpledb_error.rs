@@ -1,3 +1,4 @@
+use crate::key::Key;
 use crate::{types, ColumnId, KeyspaceId};
 use bytes::Bytes;
 use std::fmt::{Debug, Formatter};
@@ -9,6 +10,7 @@ pub enum DecodeErrorType {
     JsonSerdeDeserialization(serde_json::Error),
     IllegalSize(usize, usize), //Expected size, actual size
     UnknownFlag(usize), //Current flag value
+    Decompression(String), //Error returned by the codec
 }
 
 pub struct DecodeError {
@@ -21,7 +23,9 @@ pub struct DecodeError {
 pub enum SSTableCorruptedPart {
     BlockMetadata,
     BloomFilter,
+    Footer,
     Block(usize), //Block ID
+    RangeTombstones,
 }
 
 pub struct TokenLocation {
@@ -40,8 +44,16 @@ pub enum SimpleDbError {
     //DB Layer errors
     IndexAlreadyExists(KeyspaceId, String),
     IndexNotFound(ColumnId),
+    TooManyIndexes(String, usize), //Table name, max indexes per table
+    ColumnNotGrouped(String),
+    ScriptStatementFailed(usize, usize, Box<SimpleDbError>), //Statement number (starts from 1), line, error
     IllegalToken(TokenLocation, String),
     MalformedQuery(String),
+    DivisionByZero,
+    ScalarSubqueryReturnedMoreThanOneRow,
+    WrongNumberOfParameters(usize, usize), //Expected, given
+    NotNullViolation(String), //Column name
+    UniqueViolation(String, String), //Column name, duplicated value
     FullScanNotAllowed(),
     RangeScanNotAllowed(),
     InvalidContext(&'static str),
@@ -54,6 +66,8 @@ pub enum SimpleDbError {
     UnknownColumn(String),
     InvalidType(String),
     CannotDecodeColumn(String, Bytes),
+    CannotWriteSpillFile(std::io::Error),
+    CannotReadSpillFile(std::io::Error),
     DatabaseAlreadyExists(String),
     DatabaseNotFound(String),
     CannotCreateTableDescriptor(types::KeyspaceId, std::io::Error),
@@ -73,10 +87,13 @@ pub enum SimpleDbError {
     CannotCreateKeyspaceDescriptorFile(types::KeyspaceId, std::io::Error),
     CannotReadKeyspaceDescriptorFile(types::KeyspaceId, std::io::Error),
     CannotOpenKeyspaceDescriptorFile(types::KeyspaceId, std::io::Error),
+    CannotWriteKeyspaceDescriptorFile(types::KeyspaceId, std::io::Error),
     KeyspaceNotFound(types::KeyspaceId),
+    ReadOnlyKeyspace(types::KeyspaceId),
     CannotReadKeyspacesDirectories(std::io::Error),
     CannotReadKeyspaceFile(types::KeyspaceId, std::io::Error),
     CannotCreateKeyspaceDirectory(types::KeyspaceId, std::io::Error),
+    CannotDropKeyspace(types::KeyspaceId, std::io::Error),
     CannotCreateWal(types::KeyspaceId, types::MemtableId, std::io::Error),
     CannotWriteWalEntry(types::KeyspaceId, types::MemtableId, std::io::Error),
     CannotReadWalEntries(types::KeyspaceId, types::MemtableId, std::io::Error),
@@ -91,13 +108,30 @@ pub enum SimpleDbError {
     CannotReadSSTableFile(types::KeyspaceId, types::SSTableId, std::io::Error),
     CannotReadSSTablesFiles(types::KeyspaceId, std::io::Error),
     CannotDecodeSSTable(types::KeyspaceId, types::SSTableId, SSTableCorruptedPart, DecodeError),
+    CorruptedBlock(types::KeyspaceId, types::SSTableId, usize), //Block offset in the SSTable file
     CannotDeleteSSTable(types::KeyspaceId, types::SSTableId, std::io::Error),
     CannotCreateSSTableFile(types::KeyspaceId, types::SSTableId, std::io::Error),
+    CompactionVerificationFailed(types::KeyspaceId, String),
+    UnsortedSSTableInput(types::KeyspaceId, Key, Key), //Previous key, added key
     CannotCreateTransactionLog(std::io::Error),
     CannotWriteTransactionLogEntry(std::io::Error),
     CannotReadTransactionLogEntries(std::io::Error),
     CannotDecodeTransactionLogEntry(DecodeError),
     CannotResetTransactionLog(std::io::Error),
+    SerializationFailure(types::TxnId),
+    SavepointNotFound(String),
+    CannotWriteSnapshot(std::io::Error),
+    CannotReadSnapshot(std::io::Error),
+    CannotDecodeSnapshot(DecodeError),
+    InvalidOption(String, String), //Option name, constraint that it doesn't meet
+    CannotReadOptionsFile(std::io::Error),
+    CannotDecodeOptions(String), //Message of the TOML parser
+    TransactionAborted(types::TxnId),
+    SSTableNotFound(types::KeyspaceId, types::SSTableId),
+    IntegerOverflow,
+    ScanTaskStopped,
+    //Range deletes cannot be undone by savepoints, and they are not checked for serializable conflicts
+    DeleteRangeNotSupported(types::TxnId),
 
     //This error cannot be returned to the final user,
     //It will only be used internally in the storage engine code
@@ -164,6 +198,9 @@ impl Debug for SimpleDbError {
             SimpleDbError::CannotDecodeSSTable(_, sstable_id, error_part, decode_error) => {
                 write!(f, "{}", sstable_decode_error_to_message(*sstable_id, error_part.clone(), decode_error))
             }
+            SimpleDbError::CorruptedBlock(keyspace_id, sstable_id, block_offset) => {
+                write!(f, "Corrupted SSTable block at offset {}. SSTable ID: {}. Keyspace ID: {}", block_offset, sstable_id, keyspace_id)
+            }
             SimpleDbError::CannotDeleteSSTable(keyspace_id, sstable_id, io_error) => {
                 write!(f, "Cannot delete SSTable. SSTable ID: {} Error: {}. Keyspace ID: {}", sstable_id, io_error, keyspace_id)
             }
@@ -191,6 +228,12 @@ impl Debug for SimpleDbError {
             SimpleDbError::KeyspaceNotFound(keyspace_id) => {
                 write!(f, "Keyspace with ID {} not found", keyspace_id)
             },
+            SimpleDbError::SSTableNotFound(keyspace_id, sstable_id) => {
+                write!(f, "SSTable with ID {} not found. Keyspace ID: {}", sstable_id, keyspace_id)
+            },
+            SimpleDbError::ReadOnlyKeyspace(keyspace_id) => {
+                write!(f, "Keyspace with ID {} is read only", keyspace_id)
+            },
             SimpleDbError::Internal => {
                 panic!("This error shoudnt be returned to the final user!! Invalid code path");
             }
@@ -203,6 +246,42 @@ impl Debug for SimpleDbError {
             SimpleDbError::CannotCreateKeyspaceDirectory(keyspace_id, io_error) => {
                 write!(f, "Cannot create keyspace directory. IO Error: {}. Keyspace ID: {}", io_error, keyspace_id)
             }
+            SimpleDbError::CannotDropKeyspace(keyspace_id, io_error) => {
+                write!(f, "Cannot drop keyspace. IO Error: {}. Keyspace ID: {}", io_error, keyspace_id)
+            }
+            SimpleDbError::CompactionVerificationFailed(keyspace_id, reason) => {
+                write!(f, "Compaction output verification failed: {}. Keyspace ID: {}", reason, keyspace_id)
+            }
+            SimpleDbError::UnsortedSSTableInput(keyspace_id, previous_key, key) => {
+                write!(f, "Key {} added to SSTable is not greater than the previous key {}. Keyspace ID: {}", key, previous_key, keyspace_id)
+            }
+            SimpleDbError::SavepointNotFound(name) => {
+                write!(f, "Savepoint {} not found", name)
+            }
+            SimpleDbError::CannotWriteSnapshot(io_error) => {
+                write!(f, "Cannot write snapshot file. IO Error: {}", io_error)
+            }
+            SimpleDbError::CannotReadSnapshot(io_error) => {
+                write!(f, "Cannot read snapshot file. IO Error: {}", io_error)
+            }
+            SimpleDbError::InvalidOption(option_name, constraint) => {
+                write!(f, "Invalid option {}: {}", option_name, constraint)
+            }
+            SimpleDbError::CannotReadOptionsFile(io_error) => {
+                write!(f, "Cannot read options file. IO Error: {}", io_error)
+            }
+            SimpleDbError::CannotDecodeOptions(message) => {
+                write!(f, "Cannot decode options: {}", message)
+            }
+            SimpleDbError::CannotDecodeSnapshot(decode_error) => {
+                write!(f, "Cannot decode snapshot file. Error: {}", decode_error_to_message(decode_error))
+            }
+            SimpleDbError::TransactionAborted(txn_id) => {
+                write!(f, "Transaction {} aborted: it has been open for longer than the transaction timeout", txn_id)
+            }
+            SimpleDbError::SerializationFailure(txn_id) => {
+                write!(f, "Transaction {} aborted: keys read by it were modified by a concurrent transaction. It can be retried", txn_id)
+            }
             SimpleDbError::CannotCreateKeyspaceDescriptorFile(keyspace_id, io_error) => {
                 write!(f, "Cannot create keyspace descriptor. IO Error: {}, Keyspace ID: {}", io_error, keyspace_id)
             },
@@ -212,6 +291,9 @@ impl Debug for SimpleDbError {
             SimpleDbError::CannotOpenKeyspaceDescriptorFile(keyspace_id, io_error) => {
                 write!(f, "Cannot open keyspace descriptor file. IO Error: {} Keyspace ID: {}", io_error, keyspace_id)
             }
+            SimpleDbError::CannotWriteKeyspaceDescriptorFile(keyspace_id, io_error) => {
+                write!(f, "Cannot write keyspace descriptor file. IO Error: {} Keyspace ID: {}", io_error, keyspace_id)
+            }
             SimpleDbError::CannotReadDatabases(io_error) => {
                 write!(f, "Cannot list database files in base path. IO Error: {}", io_error)
             }
@@ -248,12 +330,42 @@ impl Debug for SimpleDbError {
             SimpleDbError::CannotWriteDatabaseDescriptor(io_error) => {
                 write!(f, "Cannot write to database descriptor. IO Error: {}", io_error)
             }
+            SimpleDbError::CannotWriteSpillFile(io_error) => {
+                write!(f, "Cannot write query spill file. IO Error: {}", io_error)
+            }
+            SimpleDbError::CannotReadSpillFile(io_error) => {
+                write!(f, "Cannot read query spill file. IO Error: {}", io_error)
+            }
             SimpleDbError::IllegalToken(location, message) => {
                 write!(f, "Unexpected token at line {} and index {} Message: {}", location.line, location.column_index, message)
             }
             SimpleDbError::MalformedQuery(message) => {
                 write!(f, "Malformed query: {}", message)
             }
+            SimpleDbError::DivisionByZero => {
+                write!(f, "Division by zero")
+            }
+            SimpleDbError::IntegerOverflow => {
+                write!(f, "Integer overflow")
+            }
+            SimpleDbError::ScanTaskStopped => {
+                write!(f, "Scan task stopped before creating the iterator")
+            }
+            SimpleDbError::DeleteRangeNotSupported(txn_id) => {
+                write!(f, "Transaction {} cannot delete a range: it has savepoints or is serializable", txn_id)
+            }
+            SimpleDbError::ScalarSubqueryReturnedMoreThanOneRow => {
+                write!(f, "Subquery used as a value returned more than one row")
+            }
+            SimpleDbError::WrongNumberOfParameters(expected, given) => {
+                write!(f, "Prepared statement expects {} parameters, but {} were given", expected, given)
+            }
+            SimpleDbError::NotNullViolation(column_name) => {
+                write!(f, "Column {} is NOT NULL, it must have a value", column_name)
+            }
+            SimpleDbError::UniqueViolation(column_name, value) => {
+                write!(f, "Column {} is UNIQUE, value {} already exists", column_name, value)
+            }
             SimpleDbError::DatabaseNotFound(database) => {
                 write!(f, "Database not found: {}", database)
             }
@@ -293,6 +405,15 @@ impl Debug for SimpleDbError {
             SimpleDbError::IndexNotFound(column_id) => {
                 write!(f, "Index not found on column ID: {}", column_id)
             }
+            SimpleDbError::TooManyIndexes(table_name, max_indexes) => {
+                write!(f, "Table {} cannot have more than {} secondary indexes", table_name, max_indexes)
+            }
+            SimpleDbError::ColumnNotGrouped(column_name) => {
+                write!(f, "Column {} must appear in GROUP BY or be used in an aggregate function", column_name)
+            }
+            SimpleDbError::ScriptStatementFailed(statement_number, line, error) => {
+                write!(f, "Statement {} at line {} failed: {:?}", statement_number, line, error)
+            }
         }
     }
 }
@@ -362,6 +483,35 @@ impl SimpleDbError {
             SimpleDbError::CannotOpenKeyspaceDescriptorFile(_, _) => 61,
             SimpleDbError::IndexAlreadyExists(_, _) => 62,
             SimpleDbError::IndexNotFound(_) => 63,
+            SimpleDbError::ColumnNotGrouped(_) => 64,
+            SimpleDbError::ScriptStatementFailed(_, _, _) => 65,
+            SimpleDbError::CannotDropKeyspace(_, _) => 66,
+            SimpleDbError::CompactionVerificationFailed(_, _) => 67,
+            SimpleDbError::SerializationFailure(_) => 68,
+            SimpleDbError::SavepointNotFound(_) => 69,
+            SimpleDbError::CorruptedBlock(_, _, _) => 70,
+            SimpleDbError::DivisionByZero => 71,
+            SimpleDbError::TooManyIndexes(_, _) => 72,
+            SimpleDbError::UnsortedSSTableInput(_, _, _) => 73,
+            SimpleDbError::CannotWriteKeyspaceDescriptorFile(_, _) => 74,
+            SimpleDbError::ReadOnlyKeyspace(_) => 75,
+            SimpleDbError::ScalarSubqueryReturnedMoreThanOneRow => 76,
+            SimpleDbError::NotNullViolation(_) => 77,
+            SimpleDbError::UniqueViolation(_, _) => 78,
+            SimpleDbError::CannotWriteSpillFile(_) => 79,
+            SimpleDbError::CannotReadSpillFile(_) => 80,
+            SimpleDbError::WrongNumberOfParameters(_, _) => 81,
+            SimpleDbError::CannotWriteSnapshot(_) => 82,
+            SimpleDbError::CannotReadSnapshot(_) => 83,
+            SimpleDbError::CannotDecodeSnapshot(_) => 84,
+            SimpleDbError::InvalidOption(_, _) => 85,
+            SimpleDbError::CannotReadOptionsFile(_) => 86,
+            SimpleDbError::CannotDecodeOptions(_) => 87,
+            SimpleDbError::TransactionAborted(_) => 88,
+            SimpleDbError::SSTableNotFound(_, _) => 89,
+            SimpleDbError::IntegerOverflow => 90,
+            SimpleDbError::ScanTaskStopped => 91,
+            SimpleDbError::DeleteRangeNotSupported(_) => 92,
         }
     }
 }
@@ -390,7 +540,9 @@ fn sstable_decode_error_to_message(
     let corrupted_part: String = match corrupted_part {
         SSTableCorruptedPart::BlockMetadata => "block metadata".to_string(),
         SSTableCorruptedPart::BloomFilter => "bloom filter".to_string(),
+        SSTableCorruptedPart::Footer => "footer".to_string(),
         SSTableCorruptedPart::Block(block_id) => format!("block ID {}", block_id),
+        SSTableCorruptedPart::RangeTombstones => "range tombstones".to_string(),
     };
 
     message.push_str(format!("Cannot decode SSTable {}. SSTable ID: {}. Error: {}", corrupted_part,
@@ -426,5 +578,8 @@ fn decode_error_type_to_message(decode_error_type: &DecodeErrorType) -> String {
         DecodeErrorType::UnknownFlag(unknown_flgag) => {
             format!("Unknown flag {}", unknown_flgag)
         },
+        DecodeErrorType::Decompression(codec_error) => {
+            format!("Cannot decompress: {}", codec_error)
+        },
     }
 }
\ No newline at end of file
@@ -1,9 +1,7 @@
+use crate::file_system::{std_file_system, FileHandle, FileSystem};
 use std::cell::UnsafeCell;
-use std::fs;
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Write};
-use std::os::windows::fs::FileExt;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub enum SimpleDbFileMode {
@@ -14,7 +12,8 @@ pub enum SimpleDbFileMode {
 }
 
 pub struct SimpleDbFile {
-    file: Option<File>,
+    file: Option<Box<dyn FileHandle>>,
+    file_system: Arc<dyn FileSystem>,
     path: Option<PathBuf>,
 
     size_bytes: usize,
@@ -37,6 +36,7 @@ impl SimpleDbFile {
         SimpleDbFile {
             mode: SimpleDbFileMode::Mock,
             size_bytes: 0,
+            file_system: std_file_system(),
             path: None,
             file: None,
         }
@@ -47,46 +47,58 @@ impl SimpleDbFile {
         data: &Vec<u8>,
         mode: SimpleDbFileMode
     ) -> Result<SimpleDbFile, std::io::Error> {
-        let mut file = Self::open(path, mode)?;
+        Self::create_in(&std_file_system(), path, data, mode)
+    }
+
+    pub fn create_in(
+        file_system: &Arc<dyn FileSystem>,
+        path: &Path,
+        data: &[u8],
+        mode: SimpleDbFileMode
+    ) -> Result<SimpleDbFile, std::io::Error> {
+        let mut file = Self::open_in(file_system, path, mode)?;
         file.write(data)?;
         Ok(file)
     }
 
     pub fn open(path: &Path, mode: SimpleDbFileMode) -> Result<SimpleDbFile, std::io::Error> {
+        Self::open_in(&std_file_system(), path, mode)
+    }
+
+    pub fn open_in(
+        file_system: &Arc<dyn FileSystem>,
+        path: &Path,
+        mode: SimpleDbFileMode
+    ) -> Result<SimpleDbFile, std::io::Error> {
         if !Self::is_backup_path(path) {
-            Self::recover_from_backup(path)?;
+            Self::recover_from_backup(file_system, path)?;
         }
 
-        let open_options = Self::create_open_options_from_mode(&mode);
-        let file = open_options.open(path)?;
-        let metadata = file.metadata()?;
+        let file = file_system.open(path, &mode)?;
 
         Ok(SimpleDbFile {
-            size_bytes: metadata.len() as usize,
+            size_bytes: file.size()? as usize,
+            file_system: file_system.clone(),
             path: Some(path.to_path_buf()),
             file: Some(file),
             mode,
         })
     }
 
-    fn recover_from_backup(original_file_path: &Path) -> Result<(), std::io::Error> {
+    fn recover_from_backup(file_system: &Arc<dyn FileSystem>, original_file_path: &Path) -> Result<(), std::io::Error> {
         let mut original_file = SimpleDbFile {
             path: Some(original_file_path.to_path_buf()),
             mode: SimpleDbFileMode::RandomWrites,
             size_bytes: 0,
-            file: Some(OpenOptions::new()
-                .create(true)
-                .write(true)
-                .create(true) //Create file if it doest exist
-                .read(true)
-                .open(original_file_path)?),
+            file: Some(file_system.open(original_file_path, &SimpleDbFileMode::RandomWrites)?),
+            file_system: file_system.clone(),
         };
 
         let backup_path = Self::create_file_backup_path(original_file_path);
         let backup_path = backup_path.as_path();
 
-        if backup_path.exists() {
-            let mut backup_file = SimpleDbFile::open(backup_path, SimpleDbFileMode::RandomWrites)?;
+        if file_system.exists(backup_path) {
+            let mut backup_file = SimpleDbFile::open_in(file_system, backup_path, SimpleDbFileMode::RandomWrites)?;
             let backup_contents = backup_file.read_all()?;
 
             original_file.clear()?;
@@ -127,7 +139,7 @@ impl SimpleDbFile {
                     let _ = self.file.take().unwrap();
                 }
 
-                fs::remove_file(self.path.as_ref().unwrap().as_path())
+                self.file_system.remove_file(self.path.as_ref().unwrap().as_path())
             },
         }
     }
@@ -178,8 +190,8 @@ impl SimpleDbFile {
     }
 
     pub fn copy(&self, new_path: &Path, mode: SimpleDbFileMode) -> Result<SimpleDbFile, std::io::Error> {
-        fs::copy(self.path.as_ref().unwrap().as_path(), new_path)?;
-        SimpleDbFile::open(new_path, mode)
+        self.file_system.copy(self.path.as_ref().unwrap().as_path(), new_path)?;
+        SimpleDbFile::open_in(&self.file_system, new_path, mode)
     }
 
     pub fn read(&self, offset: usize, length: usize) -> Result<Vec<u8>, std::io::Error> {
@@ -187,7 +199,15 @@ impl SimpleDbFile {
             SimpleDbFileMode::Mock => Ok(Vec::new()),
             _ => {
                 let mut result: Vec<u8> = vec![0; length];
-                self.file.as_ref().unwrap().seek_read(&mut result, offset as u64)?;
+                let file = self.file.as_ref().unwrap();
+                let mut n_bytes_read = 0;
+                //A read might return less bytes than requested
+                while n_bytes_read < length {
+                    match file.read_at(&mut result[n_bytes_read..], (offset + n_bytes_read) as u64)? {
+                        0 => break, //End of file
+                        n => n_bytes_read += n,
+                    }
+                }
                 Ok(result)
             }
         }
@@ -215,9 +235,7 @@ impl SimpleDbFile {
         match prev_monde {
             SimpleDbFileMode::Mock => Ok(()),
             prev_mode => {
-                let open_options = Self::create_open_options_from_mode(&prev_mode);
-                let file = open_options.open(self.path.as_ref().unwrap().as_path())?;
-                self.file = Some(file);
+                self.file = Some(self.file_system.open(self.path.as_ref().unwrap().as_path(), &prev_mode)?);
                 self.mode = prev_mode;
                 Ok(())
             }
@@ -230,28 +248,10 @@ impl SimpleDbFile {
         match self.mode.clone() {
             SimpleDbFileMode::Mock => Ok(SimpleDbFileMode::Mock),
             prev_mode => {
-                self.file = Some(OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .read(true)
-                    .open(self.path.as_ref().unwrap().as_path())?);
+                self.file = Some(self.file_system.open(self.path.as_ref().unwrap().as_path(), &SimpleDbFileMode::RandomWrites)?);
 
                 self.mode = SimpleDbFileMode::RandomWrites;
                 Ok(prev_mode.clone())
             }
         }
-    }
-
-    fn create_open_options_from_mode(mode: &SimpleDbFileMode) -> OpenOptions {
-        let is_append_only = matches!(mode, SimpleDbFileMode::AppendOnly);
-        let is_read_only = matches!(mode, SimpleDbFileMode::ReadOnly);
-
-        OpenOptions::new()
-            .create(true)
-            .append(is_append_only)
-            .write(!is_read_only)
-            .create(true) //Create file if it doest exist
-            .read(true)
-            .clone()
-    }
-}
\ No newline at end of file
+    }}
\ No newline at end of file
@@ -1,12 +1,16 @@
+use std::path::Path;
 use std::sync::Arc;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
-use crate::Flag;
+use crate::{std_file_system, FileSystem, Flag, SimpleDbError};
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum CompactionStrategy {
     SimpleLeveled,
     Tiered,
+    Leveled,
+    //SSTables are never compacted. For append only keyspaces, with no overwrites or deletes
+    None,
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
@@ -15,16 +19,68 @@ pub enum DurabilityLevel {
     Weak, //Writes to memtable without waiting for WAL write to complete
 }
 
+//Codec used to compress SSTable blocks. The codec is stored in each SSTable, so changing it
+//doesn't affect the SSTables already written
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub enum Compression {
+    None,
+    Lz4,
+    Snappy,
+}
+
+//How the rows of a table are laid out in its keyspace. The format is stored in the table keyspace flags,
+//so changing it only affects the tables created afterward
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub enum RowFormat {
+    Row, //Each row is stored as a single entry with all its columns
+    //The values of each column are stored next to each other, so SSTable blocks contain chunks of a single column.
+    //Scans only read the blocks of the selected columns
+    ColumnChunked,
+}
+
+//How strings are compared by query expressions. Keys are always stored & sorted by their bytes, so with a
+//collation other than Binary, string comparisons can't be used to narrow table scans
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub enum Collation {
+    Binary, //Strings are compared byte by byte
+    CaseInsensitive, //Strings are compared after being lowercased
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum ManifestFsyncPolicy {
+    EveryOperation, //Every manifest operation is fsynced once it is written
+    //Manifest operations are fsynced once the given number of operations haven't been fsynced.
+    //Compaction operations are always fsynced before the compaction starts
+    Batched(usize),
+}
+
 //a is before b, (example b has greater timestamp (txn_id))
 pub type StorageValueMergerFn = fn(a: &Bytes, b: &Bytes, keyspace_flags: Flag) -> StorageValueMergeResult;
 
+//Omitted fields take their default value when deserialized
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SimpleDbOptions {
     //Common/Shared option
-    #[serde(skip)]
+    #[serde(skip_serializing)]
     pub base_path: String,
+    //Used by the storage engine to access SSTables, WAL, manifest & keyspace files
+    #[serde(skip, default = "std_file_system")]
+    pub file_system: Arc<dyn FileSystem>,
     pub use_debug_logging: bool,
 
+    //DB layer options
+    //Number of threads used to scan a table in full scans. 1 means that scans won't be parallelized
+    pub query_scan_parallelism: usize,
+    //Max size of the results of SELECT statements that can be cached. 0 means that the cache is disabled
+    pub query_cache_size_bytes: usize,
+    //Format of the rows of the tables created
+    pub row_format: RowFormat,
+    //Used by string comparisons (=, <, LIKE, IN, BETWEEN...) in queries
+    pub collation: Collation,
+    //Every secondary index is stored in its own keyspace, with its own memtables & compaction thread
+    pub max_indexes_per_table: usize,
+
     //Server layer options
     pub server_password: String,
     pub server_port: u16,
@@ -34,18 +90,48 @@ pub struct SimpleDbOptions {
     #[serde(skip)]
     pub storage_value_merger: Option<StorageValueMergerFn>,
     pub tiered_compaction_options: TieredCompactionOptions,
+    pub leveled_compaction_options: LeveledCompactionOptions,
     pub compaction_strategy: CompactionStrategy,
     pub compaction_task_frequency_ms: usize,
+    //Max bytes per second read & written by the compactions of all keyspaces. 0 means unlimited
+    pub compaction_max_bytes_per_sec: usize,
+    //Number of threads used by simple leveled & tiered compactions. The compacted keys are split in non-overlapping
+    //ranges, merged concurrently. 1 means that compactions won't be parallelized
+    pub compaction_threads_per_keyspace: usize,
+    //Deprecated, it is ignored. Blocks are cached in a single cache shared by all SSTables, see block_cache_size_bytes
     pub n_cached_blocks_per_sstable: usize,
+    //Max size of the blocks cached by the block cache shared by all SSTables. 0 disables the cache
+    pub block_cache_size_bytes: usize,
+    //Number of blocks read ahead (with a single read) by SSTable iterators when they load a block. 0 disables read-ahead.
+    //Read-ahead blocks are stored in the block cache, so they might evict blocks that are read more often
+    pub scan_read_ahead_blocks: usize,
     pub durability_level: DurabilityLevel,
+    pub manifest_fsync_policy: ManifestFsyncPolicy,
+    //Transaction log entries written within this window share a single fsync, so concurrent commits don't
+    //fsync one at a time. Only used with DurabilityLevel::Strong. 0 means that every entry is fsynced on its own
+    pub group_commit_window_ms: usize,
+    //Transactions open for longer than this are rolledback, their next operations fail. 0 means no timeout
+    pub transaction_timeout_ms: usize,
     pub memtable_max_size_bytes: usize,
     pub max_memtables_inactive: usize,
-    pub bloom_filter_n_entries: usize,
+    //Target false positive rate of SSTable bloom filters. Filters are sized from the number of keys of each SSTable
+    pub bloom_filter_false_positive_rate: f64,
     pub block_size_bytes: usize,
     pub sst_size_bytes: usize,
+    //Adds a checksum over the block metadata & footer of SSTables, verified when the SSTable is opened
+    pub sst_index_checksum: bool,
+    //Verifies the checksum of every SSTable block read from disk
+    pub sst_block_checksum: bool,
+    //Checks that every key added to an SSTable is greater than the previous one. Always checked in tests
+    pub sst_verify_sorted_input: bool,
+    //Pads SSTable blocks so that every block starts at a 4kb page boundary. Trades disk space for reads that
+    //don't straddle pages. Only applies to SSTables written after it is enabled
+    pub sst_page_aligned_blocks: bool,
+    pub compression: Compression,
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct TieredCompactionOptions {
     pub min_levels_trigger_size_ratio: usize,
     pub max_size_amplification: usize,
@@ -53,16 +139,30 @@ pub struct TieredCompactionOptions {
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SimpleLeveledCompactionOptions {
     pub level0_file_num_compaction_trigger: usize,
     pub size_ratio_percent: usize,
     pub max_levels: usize,
 }
 
+//Levels other than level 0 don't contain overlapping SSTables. Level 1 target size is base_level_size_bytes,
+//and each next level target size is level_size_multiplier times bigger
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeveledCompactionOptions {
+    pub level0_file_num_compaction_trigger: usize,
+    pub base_level_size_bytes: usize,
+    pub level_size_multiplier: usize,
+    pub max_levels: usize,
+}
+
 pub enum StorageValueMergeResult {
     Ok(Bytes),
     DiscardPreviousKeepNew,
     DiscardPreviousAndNew,
+    //The merged key is deleted, a tombstone will be stored instead
+    Delete,
 }
 
 impl Default for SimpleDbOptions {
@@ -70,24 +170,95 @@ impl Default for SimpleDbOptions {
         SimpleDbOptions {
             simple_leveled_compaction_options: SimpleLeveledCompactionOptions::default(),
             tiered_compaction_options: TieredCompactionOptions::default(),
+            leveled_compaction_options: LeveledCompactionOptions::default(),
             compaction_strategy: CompactionStrategy::SimpleLeveled,
             durability_level: DurabilityLevel::Strong,
+            manifest_fsync_policy: ManifestFsyncPolicy::EveryOperation,
             base_path: String::from("ignored"),
+            file_system: std_file_system(),
             compaction_task_frequency_ms: 100, //100ms
+            compaction_max_bytes_per_sec: 0,
+            compaction_threads_per_keyspace: 1,
+            transaction_timeout_ms: 0,
+            group_commit_window_ms: 0,
             memtable_max_size_bytes: 1048576, //1Mb
-            n_cached_blocks_per_sstable: 8, //Expect power of two
-            bloom_filter_n_entries: 32768, //4kb of bloom filter so it fits in a page
+            n_cached_blocks_per_sstable: 8,
+            block_cache_size_bytes: 33554432, //32 MB
+            scan_read_ahead_blocks: 0,
+            bloom_filter_false_positive_rate: 0.01, //~10 bits per key
             storage_value_merger: None,
             sst_size_bytes: 268435456, //256 MB ~ 64 blocks
+            sst_index_checksum: true,
+            sst_block_checksum: true,
+            sst_verify_sorted_input: false,
+            sst_page_aligned_blocks: false,
+            compression: Compression::None,
             max_memtables_inactive: 8,
             block_size_bytes: 4096, //4kb
             use_debug_logging: true,
+            query_scan_parallelism: 1,
+            query_cache_size_bytes: 0,
+            row_format: RowFormat::Row,
+            collation: Collation::Binary,
+            max_indexes_per_table: 16,
             server_port: 8888,
             server_password: String::from("123456"),
         }
     }
 }
 
+//Blocks end with their flags (u64), number of entries (u16) & offset of the entries offsets (u16).
+//Offsets are stored as u16
+const MIN_BLOCK_SIZE_BYTES: usize = 12;
+const MAX_BLOCK_SIZE_BYTES: usize = 65536;
+
+impl SimpleDbOptions {
+    //Fields are named like the fields of SimpleDbOptions, and compaction options are tables. For example:
+    //  memtable_max_size_bytes = 1048576
+    //  compaction_strategy = "Leveled"
+    //  [leveled_compaction_options]
+    //  max_levels = 5
+    pub fn from_toml_str(string: &str) -> Result<SimpleDbOptions, SimpleDbError> {
+        let options: SimpleDbOptions = toml::from_str(string)
+            .map_err(|e| SimpleDbError::CannotDecodeOptions(e.message().to_string()))?;
+        options.validate()?;
+        Ok(options)
+    }
+
+    pub fn from_toml_file(path: &Path) -> Result<SimpleDbOptions, SimpleDbError> {
+        let string = std::fs::read_to_string(path)
+            .map_err(SimpleDbError::CannotReadOptionsFile)?;
+        Self::from_toml_str(&string)
+    }
+
+    //Checks the invariants expected by the storage engine & the query engine. Called when the storage engine starts
+    pub fn validate(&self) -> Result<(), SimpleDbError> {
+        check(self.memtable_max_size_bytes > 0, "memtable_max_size_bytes", "it must be greater than 0")?;
+        check(self.block_size_bytes > MIN_BLOCK_SIZE_BYTES && self.block_size_bytes <= MAX_BLOCK_SIZE_BYTES,
+              "block_size_bytes", format!("it must be greater than {} and not greater than {}", MIN_BLOCK_SIZE_BYTES, MAX_BLOCK_SIZE_BYTES))?;
+        check(self.block_size_bytes <= self.sst_size_bytes, "block_size_bytes", "it must not be greater than sst_size_bytes")?;
+        check(self.bloom_filter_false_positive_rate > 0.0 && self.bloom_filter_false_positive_rate < 1.0,
+              "bloom_filter_false_positive_rate", "it must be greater than 0 and less than 1")?;
+        check(self.query_scan_parallelism > 0, "query_scan_parallelism", "it must be greater than 0")?;
+        check(self.compaction_threads_per_keyspace > 0, "compaction_threads_per_keyspace", "it must be greater than 0")?;
+        check(self.simple_leveled_compaction_options.max_levels > 0,
+              "simple_leveled_compaction_options.max_levels", "it must be greater than 0")?;
+        check(self.leveled_compaction_options.max_levels > 0,
+              "leveled_compaction_options.max_levels", "it must be greater than 0")?;
+        check(self.leveled_compaction_options.level_size_multiplier > 1,
+              "leveled_compaction_options.level_size_multiplier", "it must be greater than 1")?;
+        Ok(())
+    }
+}
+
+fn check(is_valid: bool, option_name: &'static str, constraint: impl Into<String>) -> Result<(), SimpleDbError> {
+    if is_valid {
+        Ok(())
+    } else {
+        Err(SimpleDbError::InvalidOption(option_name.to_string(), constraint.into()))
+    }
+}
+
 pub fn start_simpledb_options_builder() -> SimpleDbOptionsBuilder {
     SimpleDbOptionsBuilder {
         options: SimpleDbOptions::default()
@@ -115,6 +286,11 @@ impl SimpleDbOptionsBuilder {
         self
     }
 
+    pub fn leveled_compaction_options(&mut self, value: LeveledCompactionOptions) -> &mut SimpleDbOptionsBuilder {
+        self.options.leveled_compaction_options = value;
+        self
+    }
+
     pub fn storage_value_merger(&mut self, storage_value_merger_fn: StorageValueMergerFn) -> &mut SimpleDbOptionsBuilder {
         self.options.storage_value_merger = Some(storage_value_merger_fn);
         self
@@ -125,6 +301,11 @@ impl SimpleDbOptionsBuilder {
         self
     }
 
+    pub fn manifest_fsync_policy(&mut self, value: ManifestFsyncPolicy) -> &mut SimpleDbOptionsBuilder {
+        self.options.manifest_fsync_policy = value;
+        self
+    }
+
     pub fn compaction_strategy(&mut self, value: CompactionStrategy) -> &mut SimpleDbOptionsBuilder {
         self.options.compaction_strategy = value;
         self
@@ -135,11 +316,37 @@ impl SimpleDbOptionsBuilder {
         self
     }
 
+    pub fn compaction_max_bytes_per_sec(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.compaction_max_bytes_per_sec = value;
+        self
+    }
+
+    pub fn compaction_threads_per_keyspace(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.compaction_threads_per_keyspace = value;
+        self
+    }
+
+    pub fn group_commit_window_ms(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.group_commit_window_ms = value;
+        self
+    }
+
+    pub fn transaction_timeout_ms(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.transaction_timeout_ms = value;
+        self
+    }
+
+    #[deprecated(note = "blocks are cached in a single cache shared by all SSTables, use block_cache_size_bytes")]
     pub fn n_cached_blocks_per_sstable(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
         self.options.n_cached_blocks_per_sstable = value;
         self
     }
 
+    pub fn block_cache_size_bytes(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.block_cache_size_bytes = value;
+        self
+    }
+
     pub fn memtable_max_size_bytes(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
         self.options.memtable_max_size_bytes = value;
         self
@@ -150,8 +357,13 @@ impl SimpleDbOptionsBuilder {
         self
     }
 
-    pub fn bloom_filter_n_entries(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
-        self.options.bloom_filter_n_entries = value;
+    pub fn bloom_filter_false_positive_rate(&mut self, value: f64) -> &mut SimpleDbOptionsBuilder {
+        self.options.bloom_filter_false_positive_rate = value;
+        self
+    }
+
+    pub fn scan_read_ahead_blocks(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.scan_read_ahead_blocks = value;
         self
     }
 
@@ -165,11 +377,66 @@ impl SimpleDbOptionsBuilder {
         self
     }
 
+    pub fn query_scan_parallelism(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.query_scan_parallelism = value;
+        self
+    }
+
+    pub fn query_cache_size_bytes(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.query_cache_size_bytes = value;
+        self
+    }
+
+    pub fn row_format(&mut self, value: RowFormat) -> &mut SimpleDbOptionsBuilder {
+        self.options.row_format = value;
+        self
+    }
+
+    pub fn collation(&mut self, value: Collation) -> &mut SimpleDbOptionsBuilder {
+        self.options.collation = value;
+        self
+    }
+
+    pub fn max_indexes_per_table(&mut self, value: usize) -> &mut SimpleDbOptionsBuilder {
+        self.options.max_indexes_per_table = value;
+        self
+    }
+
+    pub fn sst_index_checksum(&mut self, value: bool) -> &mut SimpleDbOptionsBuilder {
+        self.options.sst_index_checksum = value;
+        self
+    }
+
+    pub fn sst_block_checksum(&mut self, value: bool) -> &mut SimpleDbOptionsBuilder {
+        self.options.sst_block_checksum = value;
+        self
+    }
+
+    pub fn sst_verify_sorted_input(&mut self, value: bool) -> &mut SimpleDbOptionsBuilder {
+        self.options.sst_verify_sorted_input = value;
+        self
+    }
+
+    pub fn sst_page_aligned_blocks(&mut self, value: bool) -> &mut SimpleDbOptionsBuilder {
+        self.options.sst_page_aligned_blocks = value;
+        self
+    }
+
+    pub fn compression(&mut self, value: Compression) -> &mut SimpleDbOptionsBuilder {
+        self.options.compression = value;
+        self
+    }
+
     pub fn base_path(&mut self, value: &str) -> &mut SimpleDbOptionsBuilder {
         self.options.base_path = value.to_string();
         self
     }
 
+    pub fn file_system(&mut self, value: Arc<dyn FileSystem>) -> &mut SimpleDbOptionsBuilder {
+        self.options.file_system = value;
+        self
+    }
+
     pub fn build_arc(&self) -> Arc<SimpleDbOptions> {
         Arc::new(self.options.clone())
     }
@@ -177,6 +444,12 @@ impl SimpleDbOptionsBuilder {
     pub fn build(&self) -> SimpleDbOptions {
         self.options.clone()
     }
+
+    //Like build_arc, but returns an error if the options are not valid, see SimpleDbOptions::validate
+    pub fn try_build_arc(&self) -> Result<Arc<SimpleDbOptions>, SimpleDbError> {
+        self.options.validate()?;
+        Ok(self.build_arc())
+    }
 }
 
 impl Default for TieredCompactionOptions {
@@ -189,6 +462,17 @@ impl Default for TieredCompactionOptions {
     }
 }
 
+impl Default for LeveledCompactionOptions {
+    fn default() -> Self {
+        LeveledCompactionOptions {
+            level0_file_num_compaction_trigger: 4,
+            base_level_size_bytes: 1073741824, //1 GB ~ 4 SSTables
+            level_size_multiplier: 10,
+            max_levels: 7,
+        }
+    }
+}
+
 impl Default for SimpleLeveledCompactionOptions {
     fn default() -> Self {
         SimpleLeveledCompactionOptions {
@@ -197,4 +481,91 @@ impl Default for SimpleLeveledCompactionOptions {
             max_levels: 8,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::{start_simpledb_options_builder, CompactionStrategy, Compression, DurabilityLevel, LeveledCompactionOptions, ManifestFsyncPolicy, SimpleDbError, SimpleDbOptions, SimpleLeveledCompactionOptions};
+
+    #[test]
+    fn valid_options() {
+        assert!(start_simpledb_options_builder().try_build_arc().is_ok());
+        assert!(start_simpledb_options_builder()
+            .memtable_max_size_bytes(128)
+            .block_size_bytes(64)
+            .sst_size_bytes(64)
+            .bloom_filter_false_positive_rate(0.5)
+            .query_scan_parallelism(4)
+            .try_build_arc()
+            .is_ok());
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert_invalid_option(start_simpledb_options_builder().memtable_max_size_bytes(0).try_build_arc(), "memtable_max_size_bytes");
+        assert_invalid_option(start_simpledb_options_builder().block_size_bytes(12).try_build_arc(), "block_size_bytes");
+        assert_invalid_option(start_simpledb_options_builder().block_size_bytes(65537).try_build_arc(), "block_size_bytes");
+        assert_invalid_option(start_simpledb_options_builder().block_size_bytes(4096).sst_size_bytes(1024).try_build_arc(), "block_size_bytes");
+        assert_invalid_option(start_simpledb_options_builder().bloom_filter_false_positive_rate(0.0).try_build_arc(), "bloom_filter_false_positive_rate");
+        assert_invalid_option(start_simpledb_options_builder().bloom_filter_false_positive_rate(1.0).try_build_arc(), "bloom_filter_false_positive_rate");
+        assert_invalid_option(start_simpledb_options_builder().query_scan_parallelism(0).try_build_arc(), "query_scan_parallelism");
+        assert_invalid_option(start_simpledb_options_builder().compaction_threads_per_keyspace(0).try_build_arc(), "compaction_threads_per_keyspace");
+        assert_invalid_option(start_simpledb_options_builder()
+            .simple_leveled_compaction_options(SimpleLeveledCompactionOptions { max_levels: 0, ..SimpleLeveledCompactionOptions::default() })
+            .try_build_arc(), "simple_leveled_compaction_options.max_levels");
+        assert_invalid_option(start_simpledb_options_builder()
+            .leveled_compaction_options(LeveledCompactionOptions { max_levels: 0, ..LeveledCompactionOptions::default() })
+            .try_build_arc(), "leveled_compaction_options.max_levels");
+        assert_invalid_option(start_simpledb_options_builder()
+            .leveled_compaction_options(LeveledCompactionOptions { level_size_multiplier: 1, ..LeveledCompactionOptions::default() })
+            .try_build_arc(), "leveled_compaction_options.level_size_multiplier");
+    }
+
+    #[test]
+    fn from_toml_str() {
+        let options = SimpleDbOptions::from_toml_str(r#"
+            base_path = "/var/lib/simpledb"
+            memtable_max_size_bytes = 65536
+            block_size_bytes = 8192
+            bloom_filter_false_positive_rate = 0.05
+            compaction_strategy = "Leveled"
+            durability_level = "Weak"
+            compression = "Lz4"
+            manifest_fsync_policy = { Batched = 16 }
+
+            [leveled_compaction_options]
+            max_levels = 5
+        "#).unwrap();
+
+        assert_eq!(options.base_path, "/var/lib/simpledb");
+        assert_eq!(options.memtable_max_size_bytes, 65536);
+        assert_eq!(options.block_size_bytes, 8192);
+        assert_eq!(options.bloom_filter_false_positive_rate, 0.05);
+        assert!(matches!(options.compaction_strategy, CompactionStrategy::Leveled));
+        assert!(matches!(options.durability_level, DurabilityLevel::Weak));
+        assert_eq!(options.compression, Compression::Lz4);
+        assert!(matches!(options.manifest_fsync_policy, ManifestFsyncPolicy::Batched(16)));
+        assert_eq!(options.leveled_compaction_options.max_levels, 5);
+        //Omitted fields take their default value
+        let default_options = SimpleDbOptions::default();
+        assert_eq!(options.sst_size_bytes, default_options.sst_size_bytes);
+        assert_eq!(options.leveled_compaction_options.level_size_multiplier, default_options.leveled_compaction_options.level_size_multiplier);
+
+        let round_tripped = SimpleDbOptions::from_toml_str(&toml::to_string(&options).unwrap()).unwrap();
+        assert_eq!(serde_json::to_value(&round_tripped).unwrap(), serde_json::to_value(&options).unwrap());
+    }
+
+    #[test]
+    fn from_toml_str_errors() {
+        assert!(matches!(SimpleDbOptions::from_toml_str("memtable_max_size = 10"), Err(SimpleDbError::CannotDecodeOptions(_))));
+        assert!(matches!(SimpleDbOptions::from_toml_str("[tiered_compaction_options]\nratio = 2"), Err(SimpleDbError::CannotDecodeOptions(_))));
+        assert!(matches!(SimpleDbOptions::from_toml_str("compaction_strategy = \"Unknown\""), Err(SimpleDbError::CannotDecodeOptions(_))));
+        assert!(matches!(SimpleDbOptions::from_toml_str("memtable_max_size_bytes = 0"), Err(SimpleDbError::InvalidOption(_, _))));
+    }
+
+    fn assert_invalid_option<T>(result: Result<T, SimpleDbError>, expected_option_name: &str) {
+        match result {
+            Err(SimpleDbError::InvalidOption(option_name, _)) => assert_eq!(option_name, expected_option_name),
+            _ => panic!("Expected {} to be invalid", expected_option_name),
+        }
+    }
+}
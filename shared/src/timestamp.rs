@@ -0,0 +1,157 @@
+//Timestamps are stored as milliseconds since the unix epoch in UTC, and written as ISO-8601 strings:
+//YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or YYYY-MM-DDTHH:MM:SS.mmm, optionally followed by Z or a +HH:MM/-HH:MM offset
+
+const MILLIS_PER_SECOND: i64 = 1000;
+const MILLIS_PER_MINUTE: i64 = 60 * MILLIS_PER_SECOND;
+const MILLIS_PER_HOUR: i64 = 60 * MILLIS_PER_MINUTE;
+const MILLIS_PER_DAY: i64 = 24 * MILLIS_PER_HOUR;
+
+//Returns None if the string is not a valid ISO-8601 timestamp
+pub fn parse_iso8601(string: &str) -> Option<i64> {
+    let bytes = string.as_bytes();
+    let year = parse_digits(bytes, 0, 4)?;
+    expect_byte(bytes, 4, b'-')?;
+    let month = parse_digits(bytes, 5, 2)?;
+    expect_byte(bytes, 7, b'-')?;
+    let day = parse_digits(bytes, 8, 2)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut millis = days_from_civil(year, month, day) * MILLIS_PER_DAY;
+    if bytes.len() == 10 {
+        return Some(millis);
+    }
+
+    if !matches!(bytes[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    let hour = parse_digits(bytes, 11, 2)?;
+    expect_byte(bytes, 13, b':')?;
+    let minute = parse_digits(bytes, 14, 2)?;
+    expect_byte(bytes, 16, b':')?;
+    let second = parse_digits(bytes, 17, 2)?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    millis += hour * MILLIS_PER_HOUR + minute * MILLIS_PER_MINUTE + second * MILLIS_PER_SECOND;
+
+    let mut current = 19;
+    if bytes.get(current) == Some(&b'.') {
+        millis += parse_digits(bytes, current + 1, 3)?;
+        current += 4;
+    }
+
+    match &bytes[current..] {
+        [] | [b'Z'] | [b'z'] => Some(millis),
+        [sign @ (b'+' | b'-'), ..] if bytes.len() == current + 6 => {
+            let offset_hour = parse_digits(bytes, current + 1, 2)?;
+            expect_byte(bytes, current + 3, b':')?;
+            let offset_minute = parse_digits(bytes, current + 4, 2)?;
+            if offset_hour > 23 || offset_minute > 59 {
+                return None;
+            }
+            let offset = offset_hour * MILLIS_PER_HOUR + offset_minute * MILLIS_PER_MINUTE;
+            //A time ahead of UTC happened before the same time in UTC
+            Some(if *sign == b'+' { millis - offset } else { millis + offset })
+        },
+        _ => None,
+    }
+}
+
+//Formats the timestamp in UTC. Milliseconds are only written when they are not zero
+pub fn format_iso8601(millis: i64) -> String {
+    let days = millis.div_euclid(MILLIS_PER_DAY);
+    let millis_of_day = millis.rem_euclid(MILLIS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let hour = millis_of_day / MILLIS_PER_HOUR;
+    let minute = (millis_of_day % MILLIS_PER_HOUR) / MILLIS_PER_MINUTE;
+    let second = (millis_of_day % MILLIS_PER_MINUTE) / MILLIS_PER_SECOND;
+    let millis_of_second = millis_of_day % MILLIS_PER_SECOND;
+
+    if millis_of_second == 0 {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+    } else {
+        format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, hour, minute, second, millis_of_second)
+    }
+}
+
+fn parse_digits(bytes: &[u8], start: usize, n_digits: usize) -> Option<i64> {
+    let digits = bytes.get(start..start + n_digits)?;
+    digits.iter().try_fold(0, |value, digit| {
+        if digit.is_ascii_digit() {
+            Some(value * 10 + (digit - b'0') as i64)
+        } else {
+            None
+        }
+    })
+}
+
+fn expect_byte(bytes: &[u8], index: usize, expected: u8) -> Option<()> {
+    if bytes.get(index) == Some(&expected) { Some(()) } else { None }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+//Number of days since 1970-01-01 of a date in the proleptic gregorian calendar
+//See http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::timestamp::{format_iso8601, parse_iso8601};
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_iso8601("1970-01-01"), Some(0));
+        assert_eq!(parse_iso8601("2023-01-01T00:00:00Z"), Some(1672531200000));
+        assert_eq!(parse_iso8601("2023-01-01T00:00:00"), Some(1672531200000));
+        assert_eq!(parse_iso8601("2023-01-01T00:00:00.250Z"), Some(1672531200250));
+        assert_eq!(parse_iso8601("2023-01-01T02:00:00+02:00"), Some(1672531200000));
+        assert_eq!(parse_iso8601("2022-12-31T22:00:00-02:00"), Some(1672531200000));
+        assert_eq!(parse_iso8601("2024-02-29"), Some(1709164800000));
+        assert_eq!(parse_iso8601("1969-12-31T23:59:59Z"), Some(-1000));
+
+        assert_eq!(parse_iso8601("2023-02-29"), None);
+        assert_eq!(parse_iso8601("2023-13-01"), None);
+        assert_eq!(parse_iso8601("2023-01-01T24:00:00Z"), None);
+        assert_eq!(parse_iso8601("2023-01-01T00:00:00X"), None);
+        assert_eq!(parse_iso8601("2023-01-01T00:00"), None);
+        assert_eq!(parse_iso8601("hola"), None);
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_iso8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_iso8601(1672531200250), "2023-01-01T00:00:00.250Z");
+        assert_eq!(format_iso8601(-1000), "1969-12-31T23:59:59Z");
+        assert_eq!(format_iso8601(parse_iso8601("2024-02-29T13:45:10Z").unwrap()), "2024-02-29T13:45:10Z");
+    }
+}
@@ -1,7 +1,6 @@
 use bytes::Bytes;
 use std::fs;
-use std::fs::DirEntry;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub fn bytes_to_f64_le(bytes: &Bytes) -> f64 {
     let byte_array: [u8; 8] = bytes[..8].try_into().expect("Slice must be 8 bytes long");
@@ -118,11 +117,11 @@ pub fn u8_vec_to_u16_vec(u8_vec: &Vec<u8>) -> Vec<u16> {
 }
 
 pub fn extract_number_from_file_name(
-    file: &DirEntry,
+    file: &Path,
     separator: &str
 ) -> Result<usize, ()> {
-    let file_name = file.file_name();
-    let split = file_name
+    let split = file.file_name()
+        .ok_or(())?
         .to_str()
         .unwrap()
         .split(separator)
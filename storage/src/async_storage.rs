@@ -0,0 +1,301 @@
+use crate::transactions::transaction::Transaction;
+use crate::transactions::transaction_manager::IsolationLevel;
+use crate::{SimpleDbStorageIterator, Storage};
+use bytes::Bytes;
+use futures_core::Stream;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::{KeyspaceId, SimpleDbError};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot};
+
+//Max number of entries read ahead by a scan that haven't been consumed by its stream
+const SCAN_STREAM_BUFFER: usize = 64;
+
+//Async version of the Storage API. Storage operations are blocking, so they are run in the tokio blocking pool
+//(see tokio::task::spawn_blocking). It has to be used from inside a tokio runtime
+#[derive(Clone)]
+pub struct AsyncStorage {
+    storage: Arc<Storage>,
+}
+
+//Key & value of the entries returned by a scan. The iterator is consumed by a task of the blocking pool,
+//which stops when the stream is dropped. If the iterator fails, the error is the last item of the stream
+pub struct ScanStream {
+    receiver: mpsc::Receiver<Result<(Bytes, Bytes), SimpleDbError>>,
+}
+
+impl AsyncStorage {
+    pub fn create(storage: Arc<Storage>) -> AsyncStorage {
+        AsyncStorage { storage }
+    }
+
+    pub fn storage(&self) -> &Arc<Storage> {
+        &self.storage
+    }
+
+    pub async fn get(&self, keyspace_id: KeyspaceId, key: Bytes) -> Result<Option<Bytes>, SimpleDbError> {
+        self.run_blocking(move |storage| storage.get(keyspace_id, &key)).await
+    }
+
+    pub async fn get_with_transaction(
+        &self,
+        keyspace_id: KeyspaceId,
+        transaction: &Transaction,
+        key: Bytes,
+    ) -> Result<Option<Bytes>, SimpleDbError> {
+        let transaction = transaction.clone();
+        self.run_blocking(move |storage| storage.get_with_transaction(keyspace_id, &transaction, &key)).await
+    }
+
+    pub async fn set(&self, keyspace_id: KeyspaceId, key: Bytes, value: Bytes) -> Result<(), SimpleDbError> {
+        self.run_blocking(move |storage| storage.set(keyspace_id, key, &value)).await
+    }
+
+    pub async fn set_with_transaction(
+        &self,
+        keyspace_id: KeyspaceId,
+        transaction: &Transaction,
+        key: Bytes,
+        value: Bytes,
+    ) -> Result<(), SimpleDbError> {
+        let transaction = transaction.clone();
+        self.run_blocking(move |storage| storage.set_with_transaction(keyspace_id, &transaction, key, &value)).await
+    }
+
+    pub async fn delete(&self, keyspace_id: KeyspaceId, key: Bytes) -> Result<(), SimpleDbError> {
+        self.run_blocking(move |storage| storage.delete(keyspace_id, key)).await
+    }
+
+    pub async fn delete_with_transaction(
+        &self,
+        keyspace_id: KeyspaceId,
+        transaction: &Transaction,
+        key: Bytes,
+    ) -> Result<(), SimpleDbError> {
+        let transaction = transaction.clone();
+        self.run_blocking(move |storage| storage.delete_with_transaction(keyspace_id, &transaction, key)).await
+    }
+
+    //Starting a transaction doesn't block, so it is run in the caller's task
+    pub fn start_transaction(&self) -> Transaction {
+        self.storage.start_transaction()
+    }
+
+    pub fn start_transaction_with_isolation(&self, isolation_level: IsolationLevel) -> Transaction {
+        self.storage.start_transaction_with_isolation(isolation_level)
+    }
+
+    pub async fn commit_transaction(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
+        let transaction = transaction.clone();
+        self.run_blocking(move |storage| storage.commit_transaction(&transaction)).await
+    }
+
+    pub async fn rollback_transaction(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
+        let transaction = transaction.clone();
+        self.run_blocking(move |storage| storage.rollback_transaction(&transaction)).await
+    }
+
+    pub async fn scan_all(&self, keyspace_id: KeyspaceId) -> Result<ScanStream, SimpleDbError> {
+        self.scan(move |storage| storage.scan_all(keyspace_id)).await
+    }
+
+    pub async fn scan_from(
+        &self,
+        keyspace_id: KeyspaceId,
+        key: Bytes,
+        inclusive: bool,
+    ) -> Result<ScanStream, SimpleDbError> {
+        self.scan(move |storage| storage.scan_from(keyspace_id, &key, inclusive)).await
+    }
+
+    pub async fn scan_all_with_transaction(
+        &self,
+        transaction: &Transaction,
+        keyspace_id: KeyspaceId,
+    ) -> Result<ScanStream, SimpleDbError> {
+        let transaction = transaction.clone();
+        self.scan(move |storage| storage.scan_all_with_transaction(&transaction, keyspace_id)).await
+    }
+
+    //The iterator is created & consumed by the same blocking task. Errors creating it are returned
+    //before the stream is returned
+    async fn scan<F>(&self, create_iterator: F) -> Result<ScanStream, SimpleDbError>
+    where
+        F: FnOnce(&Storage) -> Result<SimpleDbStorageIterator, SimpleDbError> + Send + 'static
+    {
+        let (entries_sender, entries_receiver) = mpsc::channel(SCAN_STREAM_BUFFER);
+        let (created_sender, created_receiver) = oneshot::channel();
+        let storage = self.storage.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut iterator = match create_iterator(&storage) {
+                Ok(iterator) => iterator,
+                Err(error) => {
+                    let _ = created_sender.send(Err(error));
+                    return;
+                }
+            };
+            let _ = created_sender.send(Ok(()));
+
+            while iterator.next() {
+                let entry = (iterator.key().as_bytes().clone(), Bytes::copy_from_slice(iterator.value()));
+                //The stream has been dropped
+                if entries_sender.blocking_send(Ok(entry)).is_err() {
+                    return;
+                }
+            }
+            if let Some(error) = iterator.take_error() {
+                let _ = entries_sender.blocking_send(Err(error));
+            }
+        });
+
+        match created_receiver.await {
+            Ok(result) => result.map(|_| ScanStream { receiver: entries_receiver }),
+            //The task panicked before creating the iterator
+            Err(_) => Err(SimpleDbError::ScanTaskStopped),
+        }
+    }
+
+    async fn run_blocking<T, F>(&self, operation: F) -> Result<T, SimpleDbError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Storage) -> Result<T, SimpleDbError> + Send + 'static
+    {
+        let storage = self.storage.clone();
+        match tokio::task::spawn_blocking(move || operation(&storage)).await {
+            Ok(result) => result,
+            Err(join_error) => std::panic::resume_unwind(join_error.into_panic()),
+        }
+    }
+}
+
+impl Stream for ScanStream {
+    type Item = Result<(Bytes, Bytes), SimpleDbError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::async_storage::{AsyncStorage, ScanStream};
+    use crate::Storage;
+    use bytes::Bytes;
+    use shared::logger::Logger;
+    use crate::sst::sstables_files::is_sstable_file;
+    use shared::{FileSystem, InMemoryFileSystem, SimpleDbError, SimpleDbFile, SimpleDbFileMode, SimpleDbOptionsBuilder};
+    use std::future::poll_fn;
+    use std::path::Path;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use futures_core::Stream;
+
+    #[test]
+    fn set_get() {
+        let async_storage = create_async_storage();
+        runtime().block_on(async {
+            let keyspace_id = async_storage.storage().create_keyspace(0).unwrap();
+            async_storage.set(keyspace_id, Bytes::from("a"), Bytes::from("1")).await.unwrap();
+            assert_eq!(async_storage.get(keyspace_id, Bytes::from("a")).await.unwrap(), Some(Bytes::from("1")));
+
+            let transaction = async_storage.start_transaction();
+            async_storage.delete_with_transaction(keyspace_id, &transaction, Bytes::from("a")).await.unwrap();
+            async_storage.commit_transaction(&transaction).await.unwrap();
+            assert_eq!(async_storage.get(keyspace_id, Bytes::from("a")).await.unwrap(), None);
+
+            assert!(matches!(async_storage.get(100, Bytes::from("a")).await, Err(SimpleDbError::KeyspaceNotFound(100))));
+        });
+    }
+
+    #[test]
+    fn scan_stream() {
+        let async_storage = create_async_storage();
+        runtime().block_on(async {
+            let keyspace_id = async_storage.storage().create_keyspace(0).unwrap();
+            for i in 0..200 {
+                async_storage.set(keyspace_id, key(i), Bytes::from(format!("value-{}", i))).await.unwrap();
+            }
+
+            let entries = collect(async_storage.scan_all(keyspace_id).await.unwrap()).await.unwrap();
+            assert_eq!(entries.len(), 200);
+            for (i, (key_read, value_read)) in entries.iter().enumerate() {
+                assert_eq!(key_read, &key(i));
+                assert_eq!(value_read, &Bytes::from(format!("value-{}", i)));
+            }
+
+            let entries = collect(async_storage.scan_from(keyspace_id, key(150), false).await.unwrap()).await.unwrap();
+            assert_eq!(entries.len(), 49);
+            assert_eq!(entries[0].0, key(151));
+
+            //Dropping the stream before it is consumed stops the scan
+            drop(async_storage.scan_all(keyspace_id).await.unwrap());
+            assert!(async_storage.scan_all(100).await.is_err());
+        });
+    }
+
+    #[test]
+    fn scan_stream_error() {
+        let file_system: Arc<dyn FileSystem> = Arc::new(InMemoryFileSystem::create());
+        let async_storage = create_async_storage_with_options(file_system.clone(), |options| {
+            options.memtable_max_size_bytes(128).max_memtables_inactive(1);
+        });
+        runtime().block_on(async {
+            let keyspace_id = async_storage.storage().create_keyspace(0).unwrap();
+            for i in 0..30 {
+                async_storage.set(keyspace_id, key(i), Bytes::from(format!("value-{}", i))).await.unwrap();
+            }
+
+            //Flip a byte of the first block of an SSTable
+            let sstable_path = file_system.list(&Path::new("/simpledb").join(keyspace_id.to_string())).unwrap()
+                .into_iter()
+                .find(|path| is_sstable_file(path))
+                .unwrap();
+            let mut bytes = SimpleDbFile::open_in(&file_system, &sstable_path, SimpleDbFileMode::ReadOnly).unwrap()
+                .read_all()
+                .unwrap();
+            bytes[2] ^= 0xFF;
+            SimpleDbFile::create_in(&file_system, &sstable_path, &bytes, SimpleDbFileMode::RandomWrites).unwrap();
+
+            let result = collect(async_storage.scan_all(keyspace_id).await.unwrap()).await;
+            assert!(matches!(result, Err(SimpleDbError::CorruptedBlock(_, _, _))));
+        });
+    }
+
+    async fn collect(mut stream: ScanStream) -> Result<Vec<(Bytes, Bytes)>, SimpleDbError> {
+        let mut entries = Vec::new();
+        while let Some(entry) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            entries.push(entry?);
+        }
+        Ok(entries)
+    }
+
+    fn create_async_storage() -> AsyncStorage {
+        create_async_storage_with_options(Arc::new(InMemoryFileSystem::create()), |_| {})
+    }
+
+    fn create_async_storage_with_options(
+        file_system: Arc<dyn FileSystem>,
+        options_fn: impl Fn(&mut SimpleDbOptionsBuilder)
+    ) -> AsyncStorage {
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let mut options_builder = shared::start_simpledb_options_builder();
+        options_builder.file_system(file_system).base_path("/simpledb");
+        options_fn(&mut options_builder);
+        let options = options_builder.build_arc();
+        Logger::init(options.clone());
+        AsyncStorage::create(Arc::new(Storage::create(options).unwrap()))
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+    }
+
+    fn key(i: usize) -> Bytes {
+        Bytes::from(format!("key-{:03}", i))
+    }
+}
@@ -0,0 +1,30 @@
+use std::env;
+use std::process::exit;
+use storage::Manifest;
+
+//Prints every operation of a keyspace's manifest log, in the order they were appended
+//Usage: manifest_dump <simpledb base path> <keyspace id>
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} <base path> <keyspace id>", args[0]);
+        exit(1);
+    }
+    let keyspace_id = args[2].parse::<shared::KeyspaceId>()
+        .expect("Invalid keyspace id");
+    let options = shared::start_simpledb_options_builder()
+        .base_path(args[1].as_str())
+        .build_arc();
+
+    let manifest = Manifest::create(options, keyspace_id)
+        .expect("Cannot open manifest");
+    let operations = manifest.iter_operations()
+        .expect("Cannot read manifest");
+
+    for (operation_id, content, is_completed) in operations {
+        let status = if is_completed { "COMPLETED" } else { "PENDING" };
+        let content = serde_json::to_string(&content)
+            .expect("Cannot serialize manifest operation");
+        println!("{} {} {}", operation_id, status, content);
+    }
+}
@@ -1,15 +1,23 @@
 use crate::compaction::simple_leveled::{create_simple_level_compaction_task, start_simple_leveled_compaction, SimpleLeveledCompactionTask};
 use crate::compaction::tiered::{create_tiered_compaction_task, start_tiered_compaction, TieredCompactionTask};
+use crate::compaction::leveled::{create_leveled_compaction_task, start_leveled_compaction, LeveledCompactionTask};
+use crate::compaction::full::{get_full_compaction_level, start_full_compaction, FullCompactionTask};
 use serde::{Deserialize, Serialize};
 use crate::sst::sstables::SSTables;
-use std::time::Duration;
-use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::atomic::Ordering::Relaxed;
 use shared::Flag;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::StorageKeyspace;
+use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+use crate::compaction::compaction_stats::CompactionStats;
 use crate::manifest::manifest::{Manifest, ManifestOperationContent};
 use crate::transactions::transaction_manager::TransactionManager;
 
+const MAX_WAIT_NEXT_CYCLE_SLEEP: Duration = Duration::from_millis(10);
+
 pub struct Compaction {
     transaction_manager: Arc<TransactionManager>,
     options: Arc<shared::SimpleDbOptions>,
@@ -18,6 +26,23 @@ pub struct Compaction {
 
     keyspace_id: shared::KeyspaceId,
     keyspace_flags: Flag,
+
+    //Set when the keyspace is dropped, the compaction thread will exit
+    stopped: Arc<AtomicBool>,
+    //Set while the keyspace is read only, the compaction thread won't compact until it is unset
+    paused: Arc<AtomicBool>,
+    //Shared with the compaction thread. Initially SimpleDbOptions::compaction_task_frequency_ms
+    task_frequency_ms: Arc<AtomicUsize>,
+    //Shared with the compaction thread
+    stats: Arc<Mutex<CompactionStats>>,
+    //Shared with the compactions of other keyspaces
+    rate_limiter: Arc<CompactionRateLimiter>,
+    //Shared with the compaction thread. It can be changed with migrate_strategy
+    strategy: Arc<RwLock<shared::CompactionStrategy>>,
+    //Held while compacting, so that the compaction thread and strategy migrations don't compact the same SSTables
+    compaction_lock: Arc<Mutex<()>>,
+    //Set by start_compaction_thread, even if the thread is not started because compaction is disabled
+    thread_requested: AtomicBool,
 }
 
 struct CompactionThread {
@@ -28,12 +53,22 @@ struct CompactionThread {
 
     keyspace_id: shared::KeyspaceId,
     keyspace_flags: Flag,
+
+    stopped: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    task_frequency_ms: Arc<AtomicUsize>,
+    stats: Arc<Mutex<CompactionStats>>,
+    rate_limiter: Arc<CompactionRateLimiter>,
+    strategy: Arc<RwLock<shared::CompactionStrategy>>,
+    compaction_lock: Arc<Mutex<()>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
 pub enum CompactionTask {
     SimpleLeveled(SimpleLeveledCompactionTask),
     Tiered(TieredCompactionTask),
+    Leveled(LeveledCompactionTask),
+    Full(FullCompactionTask),
 }
 
 impl Compaction {
@@ -44,18 +79,33 @@ impl Compaction {
         manifest: Arc<Manifest>,
         keyspace_id: shared::KeyspaceId,
         keyspace_flags: Flag,
+        rate_limiter: Arc<CompactionRateLimiter>,
     ) -> Arc<Compaction> {
         Arc::new(Compaction {
             transaction_manager: transaction_manager.clone(),
             options: options.clone(),
             sstables: sstables.clone(),
             manifest: manifest.clone(),
+            stopped: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+            task_frequency_ms: Arc::new(AtomicUsize::new(options.compaction_task_frequency_ms)),
+            stats: Arc::new(Mutex::new(CompactionStats::default())),
+            rate_limiter,
             keyspace_flags,
-            keyspace_id
+            keyspace_id,
+            strategy: Arc::new(RwLock::new(options.compaction_strategy)),
+            compaction_lock: Arc::new(Mutex::new(())),
+            thread_requested: AtomicBool::new(false),
         })
     }
 
     pub fn start_compaction_thread(&self) {
+        self.thread_requested.store(true, Relaxed);
+        if matches!(self.strategy(), shared::CompactionStrategy::None) {
+            logger().info(StorageKeyspace(self.keyspace_id), "Compaction is disabled");
+            return;
+        }
+
         logger().info(StorageKeyspace(self.keyspace_id), "Starting compaction thread");
 
         let compaction_thread = CompactionThread {
@@ -65,6 +115,13 @@ impl Compaction {
             manifest: self.manifest.clone(),
             keyspace_id: self.keyspace_id,
             options: self.options.clone(),
+            stopped: self.stopped.clone(),
+            paused: self.paused.clone(),
+            task_frequency_ms: self.task_frequency_ms.clone(),
+            stats: self.stats.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            strategy: self.strategy.clone(),
+            compaction_lock: self.compaction_lock.clone(),
         };
 
         std::thread::spawn(move || {
@@ -72,39 +129,122 @@ impl Compaction {
         });
     }
 
-    pub fn compact(&self, compaction_task: CompactionTask) -> Result<(), shared::SimpleDbError> {
-        match compaction_task {
-            CompactionTask::SimpleLeveled(simple_leveled_task) => start_simple_leveled_compaction(
-                simple_leveled_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_id, self.keyspace_flags
-            ),
-            CompactionTask::Tiered(tiered_task) => start_tiered_compaction(
-                tiered_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_id, self.keyspace_flags
-            ),
+    pub fn stop_compaction_thread(&self) {
+        logger().info(StorageKeyspace(self.keyspace_id), "Stopping compaction thread");
+        self.stopped.store(true, Relaxed);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Relaxed);
+    }
+
+    //Used from the next compaction cycle
+    pub fn set_task_frequency_ms(&self, task_frequency_ms: usize) {
+        self.task_frequency_ms.store(task_frequency_ms, Relaxed);
+    }
+
+    //Only used before starting the compaction thread. Use migrate_strategy to change the strategy of a running keyspace
+    pub(crate) fn set_strategy(&self, strategy: shared::CompactionStrategy) {
+        *self.strategy.write().unwrap() = strategy;
+    }
+
+    pub fn strategy(&self) -> shared::CompactionStrategy {
+        *self.strategy.read().unwrap()
+    }
+
+    //Compacts all the SSTables into the level expected by the new strategy, which will be used by the next compactions.
+    //The full compaction is written to the manifest, so it is restarted after a crash
+    pub fn migrate_strategy(&self, new_strategy: shared::CompactionStrategy) -> Result<(), shared::SimpleDbError> {
+        let _compaction_lock = self.compaction_lock.lock().unwrap();
+
+        if let Some(level) = get_full_compaction_level(&self.options, new_strategy) {
+            let compaction_task = CompactionTask::Full(FullCompactionTask { level });
+            let operation_id = self.manifest.append_operation(ManifestOperationContent::Compaction(compaction_task))
+                .and_then(|operation_id| self.manifest.sync().map(|_| operation_id))?;
+            *self.stats.lock().unwrap() += run_compaction_task(compaction_task, &self.transaction_manager, &self.options,
+                &self.sstables, self.keyspace_id, self.keyspace_flags, &self.rate_limiter)?;
+            let _ = self.manifest.mark_as_completed(operation_id);
         }
+
+        let prev_strategy = std::mem::replace(&mut *self.strategy.write().unwrap(), new_strategy);
+        logger().info(StorageKeyspace(self.keyspace_id), "Migrated compaction strategy");
+
+        //The compaction thread was not started, because compaction was disabled
+        if matches!(prev_strategy, shared::CompactionStrategy::None) && self.thread_requested.load(Relaxed) {
+            self.start_compaction_thread();
+        }
+
+        Ok(())
+    }
+
+    pub fn compact(&self, compaction_task: CompactionTask) -> Result<(), shared::SimpleDbError> {
+        let _compaction_lock = self.compaction_lock.lock().unwrap();
+        //Failed compactions are not added to the stats
+        *self.stats.lock().unwrap() += run_compaction_task(compaction_task, &self.transaction_manager, &self.options,
+            &self.sstables, self.keyspace_id, self.keyspace_flags, &self.rate_limiter)?;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> CompactionStats {
+        *self.stats.lock().unwrap()
     }
 }
 
 impl CompactionThread {
-    fn start_compactions(&self) -> ! {
+    fn start_compactions(&self) {
         loop {
-            std::thread::sleep(Duration::from_millis(self.options.compaction_task_frequency_ms as u64));
+            self.wait_next_cycle();
+
+            if self.stopped.load(Relaxed) {
+                return;
+            }
+            if self.paused.load(Relaxed) {
+                continue;
+            }
 
+            let _compaction_lock = self.compaction_lock.lock().unwrap();
+            if matches!(*self.strategy.read().unwrap(), shared::CompactionStrategy::None) {
+                //Compaction has been disabled by a strategy migration
+                return;
+            }
             if let Some(compaction_task) = self.create_compaction_task() {
-                let operation_id = self.manifest.append_operation(ManifestOperationContent::Compaction(compaction_task));
+                //The operation is fsynced before the compaction writes anything. Otherwise, if the operation was lost
+                //after a crash, the compaction outputs would be loaded along with its inputs
+                let operation_id = self.manifest.append_operation(ManifestOperationContent::Compaction(compaction_task))
+                    .and_then(|operation_id| self.manifest.sync().map(|_| operation_id));
+                let operation_id = match operation_id {
+                    Ok(operation_id) => operation_id,
+                    Err(manifest_error) => {
+                        logger().error(StorageKeyspace(self.keyspace_id), &format!("Cannot write compaction to manifest: {:?}", manifest_error));
+                        continue;
+                    }
+                };
 
                 if let Err(compaction_error) = self.compact(compaction_task) {
                     logger().error(StorageKeyspace(self.keyspace_id), &format!("Error while compacting: {:?}", compaction_error));
                 }
 
-                if let Ok(operation_id) = operation_id {
-                    let _ = self.manifest.mark_as_completed(operation_id);
-                }
+                let _ = self.manifest.mark_as_completed(operation_id);
+            }
+        }
+    }
+
+    //The frequency is read again while waiting, so that lowering it doesn't wait for the previous frequency to elapse
+    fn wait_next_cycle(&self) {
+        let start = Instant::now();
+        loop {
+            let task_frequency = Duration::from_millis(self.task_frequency_ms.load(Relaxed) as u64);
+            let elapsed = start.elapsed();
+            if elapsed >= task_frequency || self.stopped.load(Relaxed) {
+                return;
             }
+            std::thread::sleep((task_frequency - elapsed).min(MAX_WAIT_NEXT_CYCLE_SLEEP));
         }
     }
 
     pub fn create_compaction_task(&self) -> Option<CompactionTask> {
-        match self.options.compaction_strategy {
+        let strategy = *self.strategy.read().unwrap();
+        match strategy {
             shared::CompactionStrategy::SimpleLeveled => {
                 if let Some(compaction_task) = create_simple_level_compaction_task(
                     self.options.simple_leveled_compaction_options, &self.sstables
@@ -119,19 +259,52 @@ impl CompactionThread {
                     return Some(CompactionTask::Tiered(compaction_task));
                 }
             },
+            shared::CompactionStrategy::Leveled => {
+                if let Some(compaction_task) = create_leveled_compaction_task(
+                    self.options.leveled_compaction_options, &self.sstables
+                ) {
+                    return Some(CompactionTask::Leveled(compaction_task));
+                }
+            },
+            shared::CompactionStrategy::None => {},
         }
 
         None
     }
 
     fn compact(&self, compaction_task: CompactionTask) -> Result<(), shared::SimpleDbError> {
-        match compaction_task {
-            CompactionTask::SimpleLeveled(simple_leveled_task) => start_simple_leveled_compaction(
-                simple_leveled_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_id, self.keyspace_flags
-            ),
-            CompactionTask::Tiered(tiered_task) => start_tiered_compaction(
-                tiered_task, &self.transaction_manager, &self.options, &self.sstables, self.keyspace_id, self.keyspace_flags,
-            ),
-        }
+        //Failed compactions are not added to the stats
+        *self.stats.lock().unwrap() += run_compaction_task(compaction_task, &self.transaction_manager, &self.options,
+            &self.sstables, self.keyspace_id, self.keyspace_flags, &self.rate_limiter)?;
+        Ok(())
     }
+}
+
+fn run_compaction_task(
+    compaction_task: CompactionTask,
+    transaction_manager: &Arc<TransactionManager>,
+    options: &Arc<shared::SimpleDbOptions>,
+    sstables: &Arc<SSTables>,
+    keyspace_id: shared::KeyspaceId,
+    keyspace_flags: Flag,
+    rate_limiter: &CompactionRateLimiter,
+) -> Result<CompactionStats, shared::SimpleDbError> {
+    let start = Instant::now();
+    let mut compaction_stats = match compaction_task {
+        CompactionTask::SimpleLeveled(simple_leveled_task) => start_simple_leveled_compaction(
+            simple_leveled_task, transaction_manager, options, sstables, keyspace_id, keyspace_flags, rate_limiter
+        ),
+        CompactionTask::Tiered(tiered_task) => start_tiered_compaction(
+            tiered_task, transaction_manager, options, sstables, keyspace_id, keyspace_flags, rate_limiter
+        ),
+        CompactionTask::Leveled(leveled_task) => start_leveled_compaction(
+            leveled_task, transaction_manager, options, sstables, keyspace_id, keyspace_flags, rate_limiter
+        ),
+        CompactionTask::Full(full_task) => start_full_compaction(
+            full_task, transaction_manager, options, sstables, keyspace_id, keyspace_flags, rate_limiter
+        ),
+    }?;
+    compaction_stats.total_time = start.elapsed();
+
+    Ok(compaction_stats)
 }
\ No newline at end of file
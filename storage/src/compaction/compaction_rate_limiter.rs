@@ -0,0 +1,113 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use crate::utils::clock::{Clock, SystemClock};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+//Token bucket that limits the bytes read & written by compactions. A single one is shared by the compaction
+//threads of all keyspaces, so the aggregated throughput is limited. Callers go into debt when they acquire more
+//bytes than available, and sleep until it is paid. The lock is held while sleeping, so other callers wait too.
+pub struct CompactionRateLimiter {
+    //It can be changed with set_max_bytes_per_sec
+    max_bytes_per_sec: AtomicUsize,
+    clock: Arc<dyn Clock>,
+    bucket: Mutex<TokenBucket>,
+}
+
+struct TokenBucket {
+    //Bytes multiplied by NANOS_PER_SEC, so refills are exact. Negative when in debt.
+    //At most max_bytes_per_sec bytes can be accumulated
+    available: i128,
+    last_refill: Instant,
+}
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+impl CompactionRateLimiter {
+    pub(crate) fn create(options: &Arc<shared::SimpleDbOptions>) -> CompactionRateLimiter {
+        Self::create_with_clock(options.compaction_max_bytes_per_sec, Arc::new(SystemClock {}))
+    }
+
+    pub(crate) fn create_with_clock(max_bytes_per_sec: usize, clock: Arc<dyn Clock>) -> CompactionRateLimiter {
+        CompactionRateLimiter {
+            bucket: Mutex::new(TokenBucket {
+                available: max_bytes_per_sec as i128 * NANOS_PER_SEC,
+                last_refill: clock.now(),
+            }),
+            max_bytes_per_sec: AtomicUsize::new(max_bytes_per_sec),
+            clock,
+        }
+    }
+
+    //0 means unlimited
+    pub(crate) fn set_max_bytes_per_sec(&self, max_bytes_per_sec: usize) {
+        self.max_bytes_per_sec.store(max_bytes_per_sec, Relaxed);
+    }
+
+    //Blocks until n_bytes can be read or written
+    pub(crate) fn acquire(&self, n_bytes: usize) {
+        let max_bytes_per_sec = self.max_bytes_per_sec.load(Relaxed) as i128;
+        if max_bytes_per_sec == 0 {
+            return;
+        }
+
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = self.clock.now();
+        let elapsed_nanos = now.duration_since(bucket.last_refill).as_nanos() as i128;
+        bucket.available = (bucket.available + elapsed_nanos * max_bytes_per_sec).min(max_bytes_per_sec * NANOS_PER_SEC);
+        bucket.last_refill = now;
+        bucket.available -= n_bytes as i128 * NANOS_PER_SEC;
+
+        if bucket.available < 0 {
+            let debt_nanos = (-bucket.available + max_bytes_per_sec - 1) / max_bytes_per_sec;
+            self.clock.sleep(Duration::from_nanos(debt_nanos as u64));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+    use crate::utils::clock::MockClock;
+    use std::time::Duration;
+
+    #[test]
+    fn acquire() {
+        let clock = MockClock::create();
+        let rate_limiter = CompactionRateLimiter::create_with_clock(100, clock.clone());
+
+        //The bucket starts full
+        rate_limiter.acquire(60);
+        rate_limiter.acquire(40);
+        assert!(clock.take_sleeps().is_empty());
+
+        //Budget exhausted
+        rate_limiter.acquire(50);
+        assert_eq!(clock.take_sleeps(), vec![Duration::from_millis(500)]);
+        rate_limiter.acquire(200);
+        assert_eq!(clock.take_sleeps(), vec![Duration::from_secs(2)]);
+
+        //Refilled after some time
+        clock.advance(Duration::from_millis(300));
+        rate_limiter.acquire(30);
+        assert!(clock.take_sleeps().is_empty());
+
+        //At most 1 second of bytes is accumulated
+        clock.advance(Duration::from_secs(10));
+        rate_limiter.acquire(100);
+        assert!(clock.take_sleeps().is_empty());
+        rate_limiter.acquire(10);
+        assert_eq!(clock.take_sleeps(), vec![Duration::from_millis(100)]);
+    }
+
+    #[test]
+    fn acquire_unlimited() {
+        let clock = MockClock::create();
+        let rate_limiter = CompactionRateLimiter::create_with_clock(0, clock.clone());
+
+        for _ in 0..100 {
+            rate_limiter.acquire(1024 * 1024);
+        }
+        assert!(clock.take_sleeps().is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+use crate::sst::sstable::SSTable;
+use crate::sst::sstables::SSTables;
+use std::ops::AddAssign;
+use std::sync::Arc;
+use std::time::Duration;
+
+//Counters of the compactions completed in a keyspace since it was loaded. Bytes are SSTable file sizes,
+//so bytes_written / bytes_read gives an idea of how much space compactions reclaim
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CompactionStats {
+    pub n_compactions: usize,
+    //Number of input SSTables
+    pub n_sstables_merged: usize,
+    pub bytes_read: usize,
+    pub bytes_written: usize,
+    pub total_time: Duration,
+}
+
+impl CompactionStats {
+    //Expect to be called before the compaction starts, as the input SSTables are deleted once it completes
+    pub(crate) fn create_from_input(sstables: &Arc<SSTables>, levels_id: &[usize]) -> CompactionStats {
+        let input_sstables: Vec<_> = levels_id.iter()
+            .flat_map(|level_id| sstables.get_sstables(*level_id))
+            .collect();
+
+        Self::create_from_sstables(input_sstables.iter())
+    }
+
+    pub(crate) fn create_from_sstables<'a>(input_sstables: impl Iterator<Item = &'a Arc<SSTable>>) -> CompactionStats {
+        let mut stats = CompactionStats { n_compactions: 1, ..CompactionStats::default() };
+        for input_sstable in input_sstables {
+            stats.bytes_read += input_sstable.size();
+            stats.n_sstables_merged += 1;
+        }
+
+        stats
+    }
+
+    pub(crate) fn add_output(&mut self, sstables: &Arc<SSTables>, level_id: usize, new_sstables_id: &[shared::SSTableId]) {
+        self.bytes_written += sstables.get_sstables(level_id).iter()
+            .filter(|sstable| new_sstables_id.contains(&sstable.sstable_id))
+            .map(|sstable| sstable.size())
+            .sum::<usize>();
+    }
+}
+
+impl AddAssign for CompactionStats {
+    fn add_assign(&mut self, other: CompactionStats) {
+        self.n_compactions += other.n_compactions;
+        self.n_sstables_merged += other.n_sstables_merged;
+        self.bytes_read += other.bytes_read;
+        self.bytes_written += other.bytes_written;
+        self.total_time += other.total_time;
+    }
+}
@@ -0,0 +1,98 @@
+use crate::sst::block::block_iterator::BlockIterator;
+use crate::sst::sstable::SSTable;
+use crate::sst::sstables::SSTables;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::key::Key;
+use std::sync::Arc;
+
+//Verifies that the SSTables written by a compaction contain the entries produced by the merge, before the
+//compaction inputs get deleted. The verifier keeps the number of entries & a checksum of the entries given to it,
+//and compares them with the entries read back from the new SSTables, which must be readable & sorted.
+pub(crate) struct CompactionVerifier {
+    keyspace_id: shared::KeyspaceId,
+    n_entries: usize,
+    hasher: crc32fast::Hasher,
+}
+
+impl CompactionVerifier {
+    pub(crate) fn create(keyspace_id: shared::KeyspaceId) -> CompactionVerifier {
+        CompactionVerifier {
+            hasher: crc32fast::Hasher::new(),
+            n_entries: 0,
+            keyspace_id,
+        }
+    }
+
+    //Expect to be called in the same order as the entries are added to the SSTable builders
+    pub(crate) fn add_entry(&mut self, key: &Key, value: &[u8]) {
+        hash_entry(&mut self.hasher, key, value);
+        self.n_entries += 1;
+    }
+
+    //new_sstables_id is expected to be ordered by creation
+    pub(crate) fn verify(
+        &self,
+        sstables: &Arc<SSTables>,
+        level: usize,
+        new_sstables_id: &Vec<shared::SSTableId>,
+    ) -> Result<(), shared::SimpleDbError> {
+        let sstables_in_level = sstables.get_sstables(level);
+        let mut hasher = crc32fast::Hasher::new();
+        let mut n_entries = 0;
+        let mut prev_key: Option<Key> = None;
+
+        for new_sstable_id in new_sstables_id {
+            let sstable = sstables_in_level.iter()
+                .find(|sstable| sstable.sstable_id == *new_sstable_id)
+                .ok_or_else(|| self.verification_failed(format!("SSTable {} not found in level {}", new_sstable_id, level)))?;
+
+            for (key, value) in self.read_entries(sstable)? {
+                if prev_key.as_ref().is_some_and(|prev_key| prev_key > &key) {
+                    return Err(self.verification_failed(format!("Keys are not sorted in SSTable {}", new_sstable_id)));
+                }
+
+                hash_entry(&mut hasher, &key, &value);
+                n_entries += 1;
+                prev_key = Some(key);
+            }
+        }
+
+        if n_entries != self.n_entries {
+            return Err(self.verification_failed(format!("Expected {} entries, but {} were found", self.n_entries, n_entries)));
+        }
+        if hasher.finalize() != self.hasher.clone().finalize() {
+            return Err(self.verification_failed(String::from("Checksum of the entries doesn't match")));
+        }
+
+        Ok(())
+    }
+
+    //Blocks are loaded directly, so decode errors are reported as verification failures
+    fn read_entries(&self, sstable: &Arc<SSTable>) -> Result<Vec<(Key, Vec<u8>)>, shared::SimpleDbError> {
+        let mut entries = Vec::new();
+
+        for block_id in 0..sstable.block_metadata.len() {
+            let block = match sstable.load_block(block_id) {
+                Err(shared::SimpleDbError::CorruptedBlock(_, sstable_id, offset)) => return Err(self.verification_failed(
+                    format!("Corrupted block at offset {} in SSTable {}", offset, sstable_id))),
+                other => other?,
+            };
+            let mut block_iterator = BlockIterator::create(block);
+            while block_iterator.next() {
+                entries.push((block_iterator.key().clone(), block_iterator.value().to_vec()));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn verification_failed(&self, reason: String) -> shared::SimpleDbError {
+        shared::SimpleDbError::CompactionVerificationFailed(self.keyspace_id, reason)
+    }
+}
+
+fn hash_entry(hasher: &mut crc32fast::Hasher, key: &Key, value: &[u8]) {
+    hasher.update(&key.serialize());
+    hasher.update(&(value.len() as u32).to_le_bytes());
+    hasher.update(value);
+}
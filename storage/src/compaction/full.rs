@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use shared::Flag;
+use shared::iterators::merge_iterator::MergeIterator;
+use crate::sst::sstable::SSTable;
+use crate::sst::sstable_builder::SSTableBuilder;
+use crate::sst::sstables::SSTables;
+use crate::sst::ssttable_iterator::SSTableIterator;
+use crate::transactions::transaction::Transaction;
+use crate::transactions::transaction_manager::TransactionManager;
+use crate::utils::storage_engine_iterator::StorageEngineIterator;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::logger::logger;
+use shared::logger::SimpleDbLayer::StorageKeyspace;
+use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+use crate::compaction::compaction_stats::CompactionStats;
+use crate::compaction::compaction_verifier::CompactionVerifier;
+use crate::compaction::tombstone_gc_iterator::TombstoneGcIterator;
+
+//Merges the SSTables of all levels into non overlapping SSTables in a single level.
+//Used when the compaction strategy of a keyspace is changed, to lay out the SSTables the way the new strategy expects
+#[derive(Serialize, Deserialize, Copy, Clone)]
+pub struct FullCompactionTask {
+    pub(crate) level: usize,
+}
+
+//Returns the level in which the new strategy expects all the data to be after a full compaction
+pub(crate) fn get_full_compaction_level(
+    options: &shared::SimpleDbOptions,
+    strategy: shared::CompactionStrategy,
+) -> Option<usize> {
+    match strategy {
+        shared::CompactionStrategy::Leveled => Some(options.leveled_compaction_options.max_levels - 1),
+        shared::CompactionStrategy::SimpleLeveled => Some(options.simple_leveled_compaction_options.max_levels - 1),
+        //A single tier, below the one memtables are flushed to
+        shared::CompactionStrategy::Tiered => Some(1),
+        shared::CompactionStrategy::None => None,
+    }
+}
+
+pub(crate) fn start_full_compaction(
+    compaction_task: FullCompactionTask,
+    transaction_manager: &Arc<TransactionManager>,
+    options: &Arc<shared::SimpleDbOptions>,
+    sstables: &Arc<SSTables>,
+    keyspace_id: shared::KeyspaceId,
+    keyspace_flags: Flag,
+    rate_limiter: &CompactionRateLimiter,
+) -> Result<CompactionStats, shared::SimpleDbError> {
+    let new_level = compaction_task.level;
+    //Memtables might be flushed while compacting, so only the SSTables taken here are deleted
+    let sstables_to_compact: Vec<(usize, Vec<Arc<SSTable>>)> = sstables.get_non_empty_levels().into_iter()
+        .map(|level| (level, sstables.get_sstables(level)))
+        .collect();
+    if sstables_to_compact.is_empty() {
+        return Ok(CompactionStats::default());
+    }
+
+    let mut stats = CompactionStats::create_from_sstables(sstables_to_compact.iter()
+        .flat_map(|(_, sstables_in_level)| sstables_in_level.iter()));
+    //Newer SSTables go first
+    let mut iterator = StorageEngineIterator::create(
+        keyspace_flags,
+        options,
+        MergeIterator::create(sstables_to_compact.iter()
+            .flat_map(|(_, sstables_in_level)| sstables_in_level.iter().rev())
+            .map(|sstable| Box::new(SSTableIterator::create(sstable.clone(), &Transaction::none())))
+            .collect()),
+    );
+    //Expired entries are not returned by the iterator, so they are dropped from the new SSTables
+    iterator.keep_expiration_time();
+    //Older versions might still be read by running transactions
+    iterator.keep_all_versions();
+    //Same for the entries covered by range tombstones. Range tombstones are moved to the first new SSTable,
+    //as they might cover keys in SSTables flushed while compacting
+    let range_tombstones: Vec<_> = sstables_to_compact.iter()
+        .flat_map(|(_, sstables_in_level)| sstables_in_level.iter())
+        .flat_map(|sstable| sstable.range_tombstones.iter().cloned())
+        .collect();
+    iterator.set_range_tombstones(range_tombstones.clone());
+    //All the SSTables are merged, so there are no older versions of the keys in other levels
+    let mut iterator = TombstoneGcIterator::create(iterator, transaction_manager.oldest_read_txn_id());
+    let mut new_sstable_builder = SSTableBuilder::create(options.clone(), keyspace_id, new_level as u32);
+    for range_tombstone in range_tombstones {
+        new_sstable_builder.add_range_tombstone(range_tombstone);
+    }
+
+    let mut new_sstables_id = Vec::new();
+    let mut verifier = CompactionVerifier::create(keyspace_id);
+    let mut prev_key: Option<Bytes> = None;
+
+    while iterator.next() {
+        let key = iterator.key().clone();
+        rate_limiter.acquire(key.len() + iterator.value().len());
+
+        if transaction_manager.on_write_key(&key).is_err() {
+            continue;
+        }
+
+        //All the versions of a key are kept in the same SSTable, so the new SSTables don't overlap
+        let is_new_key = prev_key.as_ref().is_none_or(|prev_key| prev_key != key.as_bytes());
+        if is_new_key && new_sstable_builder.n_entries() > 0 && new_sstable_builder.estimated_size_bytes() > options.sst_size_bytes {
+            let full_sstable_builder = std::mem::replace(
+                &mut new_sstable_builder, SSTableBuilder::create(options.clone(), keyspace_id, new_level as u32)
+            );
+            new_sstables_id.push(sstables.flush_to_disk(full_sstable_builder)?);
+        }
+
+        prev_key = Some(key.as_bytes().clone());
+        verifier.add_entry(&key, iterator.value());
+        new_sstable_builder.add_entry(key, Bytes::copy_from_slice(iterator.value()))?;
+    }
+
+    //An input couldn't be read (ex: corrupted block), so the compaction inputs are kept
+    if let Some(error) = iterator.take_error() {
+        sstables.delete_sstables(new_level, new_sstables_id)?;
+        return Err(error);
+    }
+
+    if new_sstable_builder.n_entries() > 0 || new_sstable_builder.has_range_tombstones() {
+        new_sstables_id.push(sstables.flush_to_disk(new_sstable_builder)?);
+    }
+
+    //If the new SSTables don't contain what has been merged, we keep the compaction inputs
+    if let Err(verification_error) = verifier.verify(sstables, new_level, &new_sstables_id) {
+        sstables.delete_sstables(new_level, new_sstables_id)?;
+        return Err(verification_error);
+    }
+    stats.add_output(sstables, new_level, &new_sstables_id);
+
+    logger().info(StorageKeyspace(keyspace_id), &format!(
+        "Compacted all SSTables into level {}. Created SSTables {:?}", new_level, new_sstables_id,
+    ));
+
+    for (level, sstables_in_level) in sstables_to_compact {
+        sstables.delete_sstables(level, sstables_in_level.iter().map(|sstable| sstable.sstable_id).collect())?;
+    }
+
+    Ok(stats)
+}
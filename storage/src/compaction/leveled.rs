@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use shared::Flag;
+use shared::iterators::merge_iterator::MergeIterator;
+use crate::sst::sstable::SSTable;
+use crate::sst::sstable_builder::SSTableBuilder;
+use crate::sst::sstables::SSTables;
+use crate::sst::ssttable_iterator::SSTableIterator;
+use crate::transactions::transaction::Transaction;
+use crate::transactions::transaction_manager::TransactionManager;
+use crate::utils::storage_engine_iterator::StorageEngineIterator;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::logger::logger;
+use shared::logger::SimpleDbLayer::StorageKeyspace;
+use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+use crate::compaction::compaction_stats::CompactionStats;
+use crate::compaction::compaction_verifier::CompactionVerifier;
+use crate::compaction::tombstone_gc_iterator::TombstoneGcIterator;
+
+//Pushes SSTables from level to level + 1. Level 0 SSTables might overlap, so all of them are compacted at once.
+//In other levels, SSTables don't overlap, the one with the most overlapping data in level + 1 is compacted.
+//Only the SSTables in level + 1 that overlap with the compacted ones are merged & rewritten.
+//SSTables containing keys of hot ranges are pushed to level + 1 only when all the SSTables of the level contain hot keys.
+#[derive(Serialize, Deserialize, Copy, Clone)]
+pub struct LeveledCompactionTask {
+    pub(crate) level: usize,
+}
+
+pub(crate) fn start_leveled_compaction(
+    compaction_task: LeveledCompactionTask,
+    transaction_manager: &Arc<TransactionManager>,
+    options: &Arc<shared::SimpleDbOptions>,
+    sstables: &Arc<SSTables>,
+    keyspace_id: shared::KeyspaceId,
+    keyspace_flags: Flag,
+    rate_limiter: &CompactionRateLimiter,
+) -> Result<CompactionStats, shared::SimpleDbError> {
+    let level_to_compact = compaction_task.level;
+    let new_level = level_to_compact + 1;
+
+    if new_level >= options.leveled_compaction_options.max_levels {
+        return Ok(CompactionStats::default());
+    }
+
+    let sstables_to_compact = pick_sstables_to_compact(sstables, level_to_compact);
+    if sstables_to_compact.is_empty() {
+        return Ok(CompactionStats::default());
+    }
+    let (first_key, last_key) = get_key_range(&sstables_to_compact);
+    let overlapping_sstables = get_overlapping_sstables(sstables, new_level, &first_key, &last_key);
+    let sstables_id_to_compact: Vec<shared::SSTableId> = sstables_to_compact.iter().map(|sstable| sstable.sstable_id).collect();
+    let overlapping_sstables_id: Vec<shared::SSTableId> = overlapping_sstables.iter().map(|sstable| sstable.sstable_id).collect();
+    let is_new_level_last_level = is_last_non_empty_level(sstables, new_level);
+
+    let mut stats = CompactionStats::create_from_sstables(sstables_to_compact.iter().chain(overlapping_sstables.iter()));
+    //Newer SSTables go first
+    let mut iterator = StorageEngineIterator::create(
+        keyspace_flags,
+        options,
+        MergeIterator::create(sstables_to_compact.iter().rev()
+            .chain(overlapping_sstables.iter().rev())
+            .map(|sstable| Box::new(SSTableIterator::create(sstable.clone(), &Transaction::none())))
+            .collect()),
+    );
+    //Expired entries are not returned by the iterator, so they are dropped from the new SSTables
+    iterator.keep_expiration_time();
+    //Older versions might still be read by running transactions
+    iterator.keep_all_versions();
+    //Same for the entries covered by range tombstones. Range tombstones are never dropped, they are moved
+    //to the first new SSTable, as they might cover keys in the SSTables not being compacted
+    let range_tombstones: Vec<_> = sstables_to_compact.iter()
+        .chain(overlapping_sstables.iter())
+        .flat_map(|sstable| sstable.range_tombstones.iter().cloned())
+        .collect();
+    iterator.set_range_tombstones(range_tombstones.clone());
+    //Tombstones are only removed in the last level, lower levels might contain older versions of their keys
+    let oldest_read_txn_id = if is_new_level_last_level { transaction_manager.oldest_read_txn_id() } else { 0 };
+    let mut iterator = TombstoneGcIterator::create(iterator, oldest_read_txn_id);
+    let mut new_sstable_builder = SSTableBuilder::create(options.clone(), keyspace_id, new_level as u32);
+    for range_tombstone in range_tombstones {
+        new_sstable_builder.add_range_tombstone(range_tombstone);
+    }
+
+    let mut new_sstables_id = Vec::new();
+    let mut verifier = CompactionVerifier::create(keyspace_id);
+    let mut prev_key: Option<Bytes> = None;
+
+    while iterator.next() {
+        let key = iterator.key().clone();
+        rate_limiter.acquire(key.len() + iterator.value().len());
+
+        if transaction_manager.on_write_key(&key).is_err() {
+            continue;
+        }
+
+        //All the versions of a key are kept in the same SSTable, so SSTables in the new level don't overlap
+        let is_new_key = prev_key.as_ref().is_none_or(|prev_key| prev_key != key.as_bytes());
+        if is_new_key && new_sstable_builder.n_entries() > 0 && new_sstable_builder.estimated_size_bytes() > options.sst_size_bytes {
+            let full_sstable_builder = std::mem::replace(
+                &mut new_sstable_builder, SSTableBuilder::create(options.clone(), keyspace_id, new_level as u32)
+            );
+            new_sstables_id.push(sstables.flush_to_disk(full_sstable_builder)?);
+        }
+
+        prev_key = Some(key.as_bytes().clone());
+        rate_limiter.acquire(key.len() + iterator.value().len());
+        verifier.add_entry(&key, iterator.value());
+        new_sstable_builder.add_entry(key, Bytes::copy_from_slice(iterator.value()))?;
+    }
+
+    //An input couldn't be read (ex: corrupted block), so the compaction inputs are kept
+    if let Some(error) = iterator.take_error() {
+        sstables.delete_sstables(new_level, new_sstables_id)?;
+        return Err(error);
+    }
+
+    if new_sstable_builder.n_entries() > 0 || new_sstable_builder.has_range_tombstones() {
+        new_sstables_id.push(sstables.flush_to_disk(new_sstable_builder)?);
+    }
+
+    //If the new SSTables don't contain what has been merged, we keep the compaction inputs
+    if let Err(verification_error) = verifier.verify(sstables, new_level, &new_sstables_id) {
+        sstables.delete_sstables(new_level, new_sstables_id)?;
+        return Err(verification_error);
+    }
+    stats.add_output(sstables, new_level, &new_sstables_id);
+
+    logger().info(StorageKeyspace(keyspace_id), &format!(
+        "Compacted SSTables: {:?} in level {} with SSTables {:?} in level {}. Created SSTables {:?}",
+        sstables_id_to_compact, level_to_compact, overlapping_sstables_id, new_level, new_sstables_id,
+    ));
+
+    sstables.delete_sstables(new_level, overlapping_sstables_id)?;
+    sstables.delete_sstables(level_to_compact, sstables_id_to_compact)?;
+
+    Ok(stats)
+}
+
+pub(crate) fn create_leveled_compaction_task(
+    options: shared::LeveledCompactionOptions,
+    sstables: &Arc<SSTables>
+) -> Option<LeveledCompactionTask> {
+    //Trigger l0 to l1 compaction
+    if sstables.get_n_sstables(0) > options.level0_file_num_compaction_trigger {
+        return Some(LeveledCompactionTask { level: 0 });
+    }
+
+    //The level that exceeds the most its target size is compacted. The last level has no target size
+    (1..options.max_levels.saturating_sub(1))
+        .map(|level| (level, get_level_size(sstables, level) as f64 / get_target_level_size(options, level) as f64))
+        .filter(|(_, score)| *score > 1.0)
+        .max_by(|(_, score_a), (_, score_b)| score_a.total_cmp(score_b))
+        .map(|(level, _)| LeveledCompactionTask { level })
+}
+
+fn pick_sstables_to_compact(sstables: &Arc<SSTables>, level: usize) -> Vec<Arc<SSTable>> {
+    let sstables_in_level = sstables.get_sstables(level);
+    if level == 0 {
+        return sstables_in_level;
+    }
+
+    sstables_in_level.into_iter()
+        .max_by_key(|sstable| {
+            let is_cold = !sstables.contains_hot_keys(sstable);
+            let overlapping_size = get_overlapping_sstables(sstables, level + 1, sstable.first_key.as_bytes(), sstable.last_key.as_bytes())
+                .iter()
+                .map(|overlapping_sstable| overlapping_sstable.size())
+                .sum::<usize>();
+            (is_cold, overlapping_size)
+        })
+        .into_iter()
+        .collect()
+}
+
+//Both ends are inclusive
+fn get_overlapping_sstables(
+    sstables: &Arc<SSTables>,
+    level: usize,
+    first_key: &Bytes,
+    last_key: &Bytes
+) -> Vec<Arc<SSTable>> {
+    sstables.get_sstables(level).into_iter()
+        .filter(|sstable| sstable.first_key.as_bytes() <= last_key && sstable.last_key.as_bytes() >= first_key)
+        .collect()
+}
+
+//Returns the smallest & biggest key of the SSTables
+fn get_key_range(sstables: &[Arc<SSTable>]) -> (Bytes, Bytes) {
+    let first_key = sstables.iter().map(|sstable| sstable.first_key.as_bytes()).min().unwrap();
+    let last_key = sstables.iter().map(|sstable| sstable.last_key.as_bytes()).max().unwrap();
+    (first_key.clone(), last_key.clone())
+}
+
+fn is_last_non_empty_level(sstables: &Arc<SSTables>, level: usize) -> bool {
+    let mut current_level = level;
+    while !sstables.is_last_level(current_level) {
+        current_level += 1;
+        if sstables.get_n_sstables(current_level) > 0 {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn get_level_size(sstables: &Arc<SSTables>, level: usize) -> usize {
+    sstables.get_sstables(level).iter()
+        .map(|sstable| sstable.size())
+        .sum()
+}
+
+fn get_target_level_size(options: shared::LeveledCompactionOptions, level: usize) -> usize {
+    options.level_size_multiplier
+        .saturating_pow((level - 1) as u32)
+        .saturating_mul(options.base_level_size_bytes)
+}
@@ -0,0 +1,152 @@
+use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+use crate::compaction::compaction_verifier::CompactionVerifier;
+use crate::compaction::tombstone_gc_iterator::TombstoneGcIterator;
+use crate::sst::sstable_builder::SSTableBuilder;
+use crate::sst::sstables::SSTables;
+use crate::transactions::transaction_manager::TransactionManager;
+use crate::utils::storage_engine_iterator::StorageEngineIterator;
+use crate::utils::tombstone::RangeTombstone;
+use bytes::Bytes;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::{Flag, TxnId};
+use std::sync::Arc;
+
+//Merges the SSTables of levels_id into new SSTables in new_level. With compaction_threads_per_keyspace > 1, the keys
+//are split in non-overlapping ranges, each one merged by its own thread. Either all the new SSTables are returned,
+//or none of them are kept: if a range fails, the SSTables written by the other ranges are deleted. The caller
+//deletes the compaction inputs, so a crash before that restarts the whole compaction from the manifest
+pub(crate) struct LevelsMerge<'a> {
+    pub(crate) levels_id: &'a [usize],
+    pub(crate) new_level: usize,
+    pub(crate) transaction_manager: &'a Arc<TransactionManager>,
+    pub(crate) options: &'a Arc<shared::SimpleDbOptions>,
+    pub(crate) sstables: &'a Arc<SSTables>,
+    pub(crate) keyspace_id: shared::KeyspaceId,
+    pub(crate) keyspace_flags: Flag,
+    pub(crate) rate_limiter: &'a CompactionRateLimiter,
+}
+
+impl LevelsMerge<'_> {
+    pub(crate) fn merge(&self) -> Result<Vec<shared::SSTableId>, shared::SimpleDbError> {
+        //Tombstones are only removed in the last level, lower levels might contain older versions of their keys
+        let oldest_read_txn_id = if self.sstables.is_last_level(self.new_level) { self.transaction_manager.oldest_read_txn_id() } else { 0 };
+        //Entries covered by range tombstones are dropped. Range tombstones are never dropped, they are moved
+        //to the first new SSTable, as they might cover keys in the levels not being compacted
+        let range_tombstones = self.sstables.get_range_tombstones_in_levels(&self.levels_id.to_vec());
+        let split_keys = self.sstables.get_split_keys_in_levels(self.levels_id, self.options.compaction_threads_per_keyspace);
+        let mut start_keys = vec![None];
+        start_keys.extend(split_keys.iter().cloned().map(Some));
+        let mut end_keys: Vec<Option<Bytes>> = split_keys.into_iter().map(Some).collect();
+        end_keys.push(None);
+
+        let results: Vec<(Vec<shared::SSTableId>, Result<(), shared::SimpleDbError>)> = std::thread::scope(|scope| {
+            let range_tombstones = &range_tombstones;
+            let handles: Vec<_> = start_keys.into_iter().zip(end_keys).enumerate()
+                .map(|(index, (start_key, end_key))| scope.spawn(move || {
+                    let mut new_sstables_id = Vec::new();
+                    let result = self.merge_key_range(start_key, end_key, range_tombstones, index == 0,
+                        oldest_read_txn_id, &mut new_sstables_id);
+                    (new_sstables_id, result)
+                }))
+                .collect();
+
+            handles.into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        let mut new_sstables_id = Vec::new();
+        let mut first_error = None;
+        for (new_sstables_id_range, result) in results {
+            new_sstables_id.extend(new_sstables_id_range);
+            if let Err(error) = result {
+                first_error.get_or_insert(error);
+            }
+        }
+
+        match first_error {
+            Some(error) => {
+                self.sstables.delete_sstables(self.new_level, new_sstables_id)?;
+                Err(error)
+            },
+            None => Ok(new_sstables_id),
+        }
+    }
+
+    //Merges the keys in [start_key, end_key). The ids of the SSTables written are added to new_sstables_id,
+    //even if it fails, so that they can be deleted
+    fn merge_key_range(
+        &self,
+        start_key: Option<Bytes>,
+        end_key: Option<Bytes>,
+        range_tombstones: &[RangeTombstone],
+        add_range_tombstones: bool,
+        oldest_read_txn_id: TxnId,
+        new_sstables_id: &mut Vec<shared::SSTableId>,
+    ) -> Result<(), shared::SimpleDbError> {
+        let mut iterator = StorageEngineIterator::create(
+            self.keyspace_flags,
+            self.options,
+            self.sstables.scan_from_level(&self.levels_id.to_vec()),
+        );
+        //Expired entries are not returned by the iterator, so they are dropped from the new SSTables
+        iterator.keep_expiration_time();
+        //Older versions might still be read by running transactions
+        iterator.keep_all_versions();
+        iterator.set_range_tombstones(range_tombstones.to_vec());
+        if let Some(start_key) = &start_key {
+            iterator.seek(start_key, true);
+        }
+        let mut iterator = TombstoneGcIterator::create(iterator, oldest_read_txn_id);
+        let mut new_sstable_builder = Some(self.new_sstable_builder());
+        if add_range_tombstones {
+            for range_tombstone in range_tombstones {
+                new_sstable_builder.as_mut().unwrap().add_range_tombstone(range_tombstone.clone());
+            }
+        }
+
+        let mut verifier = CompactionVerifier::create(self.keyspace_id);
+        let first_new_sstable_index = new_sstables_id.len();
+
+        while iterator.next() {
+            let key = iterator.key().clone();
+            if end_key.as_ref().is_some_and(|end_key| key.as_bytes() >= end_key) {
+                break;
+            }
+            self.rate_limiter.acquire(key.len() + iterator.value().len());
+
+            if self.transaction_manager.on_write_key(&key).is_err() {
+                continue;
+            }
+
+            let value = iterator.value();
+            self.rate_limiter.acquire(key.len() + value.len());
+            verifier.add_entry(&key, value);
+            new_sstable_builder.as_mut().unwrap().add_entry(
+                key, Bytes::copy_from_slice(iterator.value())
+            )?;
+
+            if new_sstable_builder.as_ref().unwrap().estimated_size_bytes() > self.options.sst_size_bytes {
+                new_sstables_id.push(self.sstables.flush_to_disk(new_sstable_builder.take().unwrap())?);
+                new_sstable_builder = Some(self.new_sstable_builder());
+            }
+        }
+
+        //An input couldn't be read (ex: corrupted block), the new SSTables will be deleted by merge()
+        if let Some(error) = iterator.take_error() {
+            return Err(error);
+        }
+
+        let new_sstable_builder_ref = new_sstable_builder.as_ref().unwrap();
+        if new_sstable_builder_ref.n_entries() > 0 || new_sstable_builder_ref.has_range_tombstones() {
+            new_sstables_id.push(self.sstables.flush_to_disk(new_sstable_builder.take().unwrap())?);
+        }
+
+        //If the new SSTables don't contain what has been merged, we keep the compaction inputs
+        verifier.verify(self.sstables, self.new_level, &new_sstables_id[first_new_sstable_index..].to_vec())
+    }
+
+    fn new_sstable_builder(&self) -> SSTableBuilder {
+        SSTableBuilder::create(self.options.clone(), self.keyspace_id, self.new_level as u32)
+    }
+}
@@ -1,3 +1,10 @@
 pub mod compaction;
 pub mod simple_leveled;
 pub mod tiered;
+pub mod leveled;
+pub mod full;
+pub mod compaction_stats;
+pub mod compaction_rate_limiter;
+mod compaction_verifier;
+mod tombstone_gc_iterator;
+mod levels_merge;
@@ -1,13 +1,11 @@
 use std::sync::Arc;
-use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use shared::Flag;
-use crate::sst::sstable_builder::SSTableBuilder;
 use crate::sst::sstables::SSTables;
 use crate::transactions::transaction_manager::TransactionManager;
-use crate::utils::storage_engine_iterator::StorageEngineIterator;
-use shared::iterators::storage_iterator::StorageIterator;
-use crate::utils::tombstone::TOMBSTONE;
+use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+use crate::compaction::compaction_stats::CompactionStats;
+use crate::compaction::levels_merge::LevelsMerge;
 
 #[derive(Serialize, Deserialize, Copy, Clone)]
 pub enum TieredCompactionTask {
@@ -21,14 +19,15 @@ pub(crate) fn start_tiered_compaction(
     options: &Arc<shared::SimpleDbOptions>,
     sstables: &Arc<SSTables>,
     keyspace_id: shared::KeyspaceId,
-    keyspace_flags: Flag
-) -> Result<(), shared::SimpleDbError> {
+    keyspace_flags: Flag,
+    rate_limiter: &CompactionRateLimiter,
+) -> Result<CompactionStats, shared::SimpleDbError> {
     match task {
         TieredCompactionTask::AmplificationRatioTrigger => {
-            do_tiered_compaction(options, sstables, sstables.get_n_levels() - 1, transaction_manager, keyspace_id, keyspace_flags)
+            do_tiered_compaction(options, sstables, sstables.get_n_levels() - 1, transaction_manager, keyspace_id, keyspace_flags, rate_limiter)
         },
         TieredCompactionTask::SizeRatioTrigger(level_id) => {
-            do_tiered_compaction(options, sstables, level_id, transaction_manager, keyspace_id, keyspace_flags)
+            do_tiered_compaction(options, sstables, level_id, transaction_manager, keyspace_id, keyspace_flags, rate_limiter)
         },
     }
 }
@@ -39,58 +38,28 @@ fn do_tiered_compaction(
     max_level_id_to_compact: usize, //Compact from level 0 to max_level_id_to_compact (inclusive, inclusive)
     transaction_manager: &Arc<TransactionManager>,
     keyspace_id: shared::KeyspaceId,
-    keyspace_flags: Flag
-) -> Result<(), shared::SimpleDbError> {
+    keyspace_flags: Flag,
+    rate_limiter: &CompactionRateLimiter,
+) -> Result<CompactionStats, shared::SimpleDbError> {
     let new_level = max_level_id_to_compact + 1;
-    let is_new_level_last_level = sstables.is_last_level(new_level);
     let levels_id_to_compact: Vec<usize> = (0..max_level_id_to_compact).into_iter().collect();
-    let mut iterator = StorageEngineIterator::create(
-        keyspace_flags,
+    let mut stats = CompactionStats::create_from_input(sstables, &levels_id_to_compact);
+    let new_sstables_id = LevelsMerge {
+        levels_id: &levels_id_to_compact,
+        new_level,
+        transaction_manager,
         options,
-        sstables.scan_from_level(&levels_id_to_compact),
-    );
-    let mut new_sstable_builder = Some(SSTableBuilder::create(
-        options.clone(), keyspace_id, new_level as u32
-    ));
-
-    while iterator.has_next() {
-        iterator.next();
-
-        let key = iterator.key().clone();
-        match transaction_manager.on_write_key(&key) {
-            Ok(_) => {
-                let value = iterator.value();
-                let is_tombstone = value.eq(TOMBSTONE.as_ref());
-
-                if is_new_level_last_level && is_tombstone {
-                    //We remove tombstones in the last levels compactions
-                    continue;
-                }
-
-                new_sstable_builder.as_mut().unwrap().add_entry(
-                    key, Bytes::copy_from_slice(iterator.value())
-                );
-
-                if new_sstable_builder.as_ref().unwrap().estimated_size_bytes() > options.sst_size_bytes {
-                    sstables.flush_to_disk(new_sstable_builder.take().unwrap())?;
-
-                    new_sstable_builder = Some(
-                        SSTableBuilder::create(options.clone(), keyspace_id, new_level as u32)
-                    );
-                }
-            },
-            Err(_) => {}
-        }
-    }
-
-    if new_sstable_builder.as_ref().unwrap().n_entries() > 0 {
-        sstables.flush_to_disk(new_sstable_builder.take().unwrap())?;
-    }
+        sstables,
+        keyspace_id,
+        keyspace_flags,
+        rate_limiter,
+    }.merge()?;
+    stats.add_output(sstables, new_level, &new_sstables_id);
 
     levels_id_to_compact.iter()
         .for_each(|level_id| sstables.delete_all_sstables(*level_id));
 
-    Ok(())
+    Ok(stats)
 }
 
 pub(crate) fn create_tiered_compaction_task(
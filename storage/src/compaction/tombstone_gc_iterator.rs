@@ -0,0 +1,142 @@
+use bytes::Bytes;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::key::Key;
+use shared::TxnId;
+use std::collections::VecDeque;
+
+//Used by compactions into the last level. A tombstone written by a transaction older than oldest_read_txn_id
+//(see TransactionManager::oldest_read_txn_id) is visible to every transaction, so the tombstone and the older
+//versions of the key that it hides are not returned. Expects the versions of a key in ascending order
+pub(crate) struct TombstoneGcIterator<I: StorageIterator> {
+    inner_iterator: I,
+    oldest_read_txn_id: TxnId,
+
+    //Versions of the current key that haven't been returned yet
+    versions: VecDeque<(Key, Bytes)>,
+    //Entry of the inner iterator that belongs to the next key
+    pending: Option<(Key, Bytes)>,
+
+    current_key: Option<Key>,
+    current_value: Option<Bytes>,
+}
+
+impl<I: StorageIterator> TombstoneGcIterator<I> {
+    pub(crate) fn create(inner_iterator: I, oldest_read_txn_id: TxnId) -> TombstoneGcIterator<I> {
+        TombstoneGcIterator {
+            versions: VecDeque::new(),
+            current_value: None,
+            current_key: None,
+            pending: None,
+            oldest_read_txn_id,
+            inner_iterator,
+        }
+    }
+
+    fn next_inner_entry(&mut self) -> Option<(Key, Bytes)> {
+        if self.inner_iterator.next() {
+            Some((self.inner_iterator.key().clone(), Bytes::copy_from_slice(self.inner_iterator.value())))
+        } else {
+            None
+        }
+    }
+
+    //Returns false if there are no more keys
+    fn read_next_key_versions(&mut self) -> bool {
+        let first_version = match self.pending.take().or_else(|| self.next_inner_entry()) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        self.versions.push_back(first_version);
+
+        while let Some(entry) = self.next_inner_entry() {
+            if entry.0.bytes_eq_bytes(self.versions[0].0.as_bytes()) {
+                self.versions.push_back(entry);
+            } else {
+                self.pending = Some(entry);
+                break;
+            }
+        }
+
+        let last_collectable_tombstone = self.versions.iter()
+            .rposition(|(key, _)| key.is_tombstone() && key.txn_id() < self.oldest_read_txn_id);
+        if let Some(last_collectable_tombstone) = last_collectable_tombstone {
+            self.versions.drain(..=last_collectable_tombstone);
+        }
+
+        true
+    }
+}
+
+impl<I: StorageIterator> StorageIterator for TombstoneGcIterator<I> {
+    fn next(&mut self) -> bool {
+        loop {
+            if let Some((key, value)) = self.versions.pop_front() {
+                self.current_key = Some(key);
+                self.current_value = Some(value);
+                return true;
+            }
+            if !self.read_next_key_versions() {
+                return false;
+            }
+        }
+    }
+
+    //The remaining versions might be collected
+    fn has_next(&self) -> bool {
+        !self.versions.is_empty() || self.pending.is_some() || self.inner_iterator.has_next()
+    }
+
+    fn key(&self) -> &Key {
+        self.current_key.as_ref().unwrap()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.current_value.as_ref().unwrap()
+    }
+
+    fn seek(&mut self, key: &Bytes, inclusive: bool) {
+        self.versions.clear();
+        self.pending = None;
+        self.inner_iterator.seek(key, inclusive);
+    }
+
+    fn take_error(&mut self) -> Option<shared::SimpleDbError> {
+        self.inner_iterator.take_error()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compaction::tombstone_gc_iterator::TombstoneGcIterator;
+    use bytes::Bytes;
+    use shared::iterators::mock_iterator::MockIterator;
+    use shared::iterators::storage_iterator::StorageIterator;
+    use shared::key::Key;
+
+    #[test]
+    fn collects_old_tombstones() {
+        let mut inner_iterator = MockIterator::create();
+        inner_iterator.add_entry("a", 1, Bytes::from("1"));
+        inner_iterator.add_entry("a", 2, Bytes::from("2"));
+        inner_iterator.add_entry("b", 1, Bytes::from("1"));
+        inner_iterator.add_key(Key::create_from_str("b", 3).with_tombstone(true), Bytes::new());
+        inner_iterator.add_entry("b", 6, Bytes::from("6"));
+        inner_iterator.add_entry("c", 2, Bytes::from("2"));
+        inner_iterator.add_key(Key::create_from_str("c", 5).with_tombstone(true), Bytes::new());
+        let mut iterator = TombstoneGcIterator::create(inner_iterator, 5);
+
+        let mut entries = Vec::new();
+        while iterator.next() {
+            entries.push((iterator.key().clone(), iterator.key().is_tombstone(), Bytes::copy_from_slice(iterator.value())));
+        }
+
+        //The tombstone of c might be read beneath by a transaction older than 5
+        assert_eq!(entries, vec![
+            (Key::create_from_str("a", 1), false, Bytes::from("1")),
+            (Key::create_from_str("a", 2), false, Bytes::from("2")),
+            (Key::create_from_str("b", 6), false, Bytes::from("6")),
+            (Key::create_from_str("c", 2), false, Bytes::from("2")),
+            (Key::create_from_str("c", 5), true, Bytes::new()),
+        ]);
+    }
+}
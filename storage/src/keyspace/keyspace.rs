@@ -1,20 +1,29 @@
 use crate::compaction::compaction::{Compaction, CompactionTask};
+use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+use crate::sst::block_cache::BlockCache;
+use crate::compaction::compaction_stats::CompactionStats;
+use crate::keyspace::keyspace_info::KeyspaceInfo;
+use crate::keyspace::runtime_options::RuntimeOptions;
 use crate::keyspace::keyspace_descriptor::KeyspaceDescriptor;
 use crate::manifest::manifest::{Manifest, ManifestOperationContent, MemtableFlushManifestOperation};
 use crate::memtables::memtable::MemTable;
 use crate::memtables::memtables::Memtables;
+use crate::memtables::memtable_stats::MemtableStats;
 use crate::sst::sstable_builder::SSTableBuilder;
+use crate::sst::sstable_meta::SSTableMeta;
 use crate::sst::sstables::SSTables;
-use crate::transactions::transaction::Transaction;
+use crate::transactions::transaction::{Transaction, UndoEntry};
 use crate::transactions::transaction_manager::{IsolationLevel, TransactionManager};
 use crate::utils::storage_engine_iterator::StorageEngineIterator;
+use crate::utils::tombstone::{is_range_deleted, RangeTombstone};
+use crate::utils::ttl;
 use crate::SimpleDbStorageIterator;
 use bytes::Bytes;
 use shared::iterators::storage_iterator::StorageIterator;
 use shared::iterators::two_merge_iterators::TwoMergeIterator;
 use shared::Flag;
-use std::fs;
 use std::sync::Arc;
+use std::time::Duration;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::StorageKeyspace;
 
@@ -35,27 +44,35 @@ impl Keyspace {
         keyspace_id: shared::KeyspaceId,
         transaction_manager: Arc<TransactionManager>,
         options: Arc<shared::SimpleDbOptions>,
-        flags: Flag
+        flags: Flag,
+        rate_limiter: Arc<CompactionRateLimiter>,
+        block_cache: Arc<BlockCache>,
     ) -> Result<Arc<Keyspace>, shared::SimpleDbError> {
         let path = shared::get_directory_usize(&options.base_path, keyspace_id);
-        fs::create_dir(path.as_path())
+        options.file_system.create_dir(path.as_path())
             .map_err(|e| shared::SimpleDbError::CannotCreateKeyspaceDirectory(keyspace_id, e))?;
-        KeyspaceDescriptor::create(flags, path.clone(), keyspace_id)?;
-        Self::create_and_load(keyspace_id, transaction_manager, options)
+        KeyspaceDescriptor::create(&options, flags, path.clone(), keyspace_id)?;
+        Self::create_and_load(keyspace_id, transaction_manager, options, rate_limiter, block_cache)
     }
 
     pub fn create_and_load(
         keyspace_id: shared::KeyspaceId,
         transaction_manager: Arc<TransactionManager>,
-        options: Arc<shared::SimpleDbOptions>
+        options: Arc<shared::SimpleDbOptions>,
+        rate_limiter: Arc<CompactionRateLimiter>,
+        block_cache: Arc<BlockCache>,
     ) -> Result<Arc<Keyspace>, shared::SimpleDbError> {
         let path = shared::get_directory_usize(&options.base_path, keyspace_id);
-        let descriptor = KeyspaceDescriptor::load_from_disk(keyspace_id, path)?;
+        let descriptor = KeyspaceDescriptor::load_from_disk(&options, keyspace_id, path)?;
         let manifest = Arc::new(Manifest::create(options.clone(), keyspace_id)?);
-        let sstables = Arc::new(SSTables::open(options.clone(), keyspace_id, manifest.clone())?);
+        let sstables = Arc::new(SSTables::open(options.clone(), keyspace_id, manifest.clone(), block_cache)?);
         let memtables = Memtables::create_and_recover_from_wal(options.clone(), keyspace_id, descriptor.flags)?;
         let compaction = Compaction::create(transaction_manager.clone(), options.clone(),
-                                            sstables.clone(), manifest.clone(), keyspace_id, descriptor.flags);
+                                            sstables.clone(), manifest.clone(), keyspace_id, descriptor.flags, rate_limiter);
+        compaction.set_paused(descriptor.is_read_only());
+        if let Some(compaction_strategy) = descriptor.compaction_strategy() {
+            compaction.set_strategy(compaction_strategy);
+        }
 
         Ok(Arc::new(Keyspace {
             transaction_manager,
@@ -75,15 +92,34 @@ impl Keyspace {
         key: &Bytes,
         inclusive: bool,
     ) -> SimpleDbStorageIterator {
+        let mut iterator = self.scan_from_key(transaction, key, inclusive);
+        if transaction.is_serializable() {
+            iterator.track_reads(transaction, self.keyspace_id);
+        }
+        iterator
+    }
+
+    //Iterator positioned before the versions of the key. SSTables that cannot contain the key are not scanned,
+    //so entries after the key might be missing
+    pub fn scan_key_with_transaction(
+        &self,
+        transaction: &Transaction,
+        key: &Bytes,
+    ) -> SimpleDbStorageIterator {
+        let statement_transaction = self.transaction_manager.statement_transaction(transaction);
         let mut iterator = StorageEngineIterator::create(
             self.descriptor.flags,
             &self.options,
             TwoMergeIterator::create(
-                self.memtables.scan_all(&transaction),
-                self.sstables.scan_all(&transaction),
-            ),
+                self.memtables.scan_all(&statement_transaction),
+                self.sstables.scan_key(key, &statement_transaction),
+            )
         );
-        iterator.seek(key, inclusive);
+        iterator.set_range_tombstones(self.get_range_tombstones(&statement_transaction));
+        iterator.seek(key, true);
+        if transaction.is_serializable() {
+            iterator.track_reads(transaction, self.keyspace_id);
+        }
         iterator
     }
 
@@ -91,14 +127,20 @@ impl Keyspace {
         &self,
         transaction: &Transaction
     ) -> SimpleDbStorageIterator {
-        StorageEngineIterator::create(
+        let statement_transaction = self.transaction_manager.statement_transaction(transaction);
+        let mut iterator = StorageEngineIterator::create(
             self.descriptor.flags,
             &self.options,
             TwoMergeIterator::create(
-                self.memtables.scan_all(&transaction),
-                self.sstables.scan_all(&transaction),
+                self.memtables.scan_all(&statement_transaction),
+                self.sstables.scan_all(&statement_transaction),
             )
-        )
+        );
+        iterator.set_range_tombstones(self.get_range_tombstones(&statement_transaction));
+        if transaction.is_serializable() {
+            iterator.track_reads(transaction, self.keyspace_id);
+        }
+        iterator
     }
 
     pub fn get_with_transaction(
@@ -106,10 +148,17 @@ impl Keyspace {
         transaction: &Transaction,
         key: &Bytes,
     ) -> Result<Option<Bytes>, shared::SimpleDbError> {
-        match self.memtables.get(&key, transaction) {
-            Some(value_from_memtable) => Ok(Some(value_from_memtable)),
-            None => self.sstables.get(&key, &transaction),
-        }
+        transaction.on_read(self.keyspace_id, key);
+        let transaction = self.transaction_manager.statement_transaction(transaction);
+        let entry = match self.memtables.get(&key, &transaction) {
+            Some(entry_from_memtable) => Some(entry_from_memtable),
+            None => self.sstables.get(&key, &transaction)?,
+        };
+
+        Ok(entry
+            .filter(|(key, _)| !key.is_tombstone())
+            .filter(|(key, _)| !is_range_deleted(&self.get_range_tombstones(&transaction), key))
+            .map(|(key, value)| ttl::user_value(&key, value)))
     }
 
     pub fn set_with_transaction(
@@ -118,8 +167,60 @@ impl Keyspace {
         key: Bytes,
         value: &[u8],
     ) -> Result<(), shared::SimpleDbError> {
+        self.check_writable()?;
         self.transaction_manager.mark_write(transaction)?;
-        match self.memtables.set(key, value, transaction) {
+        transaction.on_write(self.keyspace_id, &key);
+        self.save_undo_entry(transaction, &key);
+        match self.memtables.set(key, value, transaction)? {
+            Some(memtable_to_flush) => self.flush_memtable(memtable_to_flush),
+            None => Ok(())
+        }
+    }
+
+    //The key is treated as absent once ttl has elapsed, and it is removed by compactions
+    pub fn set_with_ttl_with_transaction(
+        &self,
+        transaction: &Transaction,
+        key: Bytes,
+        value: &[u8],
+        ttl: Duration,
+    ) -> Result<(), shared::SimpleDbError> {
+        self.set_with_expire_at_with_transaction(transaction, key, value, ttl::expire_at_from_ttl(ttl))
+    }
+
+    //expire_at is in millis since the epoch
+    pub(crate) fn set_with_expire_at_with_transaction(
+        &self,
+        transaction: &Transaction,
+        key: Bytes,
+        value: &[u8],
+        expire_at: u64,
+    ) -> Result<(), shared::SimpleDbError> {
+        self.check_writable()?;
+        self.transaction_manager.mark_write(transaction)?;
+        transaction.on_write(self.keyspace_id, &key);
+        self.save_undo_entry(transaction, &key);
+        match self.memtables.set_with_ttl(key, value, expire_at, transaction)? {
+            Some(memtable_to_flush) => self.flush_memtable(memtable_to_flush),
+            None => Ok(())
+        }
+    }
+
+    //Deletes the keys in [start, end) with a single range tombstone. The tombstone cannot be undone by
+    //Storage::rollback_to_savepoint nor checked for serializable conflicts, so transactions with savepoints
+    //or serializable isolation get SimpleDbError::DeleteRangeNotSupported
+    pub fn delete_range_with_transaction(
+        &self,
+        transaction: &Transaction,
+        start: Bytes,
+        end: Bytes,
+    ) -> Result<(), shared::SimpleDbError> {
+        if transaction.has_savepoints() || transaction.is_serializable() {
+            return Err(shared::SimpleDbError::DeleteRangeNotSupported(transaction.txn_id));
+        }
+        self.check_writable()?;
+        self.transaction_manager.mark_write(transaction)?;
+        match self.memtables.delete_range(start, end, transaction)? {
             Some(memtable_to_flush) => self.flush_memtable(memtable_to_flush),
             None => Ok(())
         }
@@ -138,17 +239,85 @@ impl Keyspace {
         transaction: &Transaction,
         key: Bytes,
     ) -> Result<(), shared::SimpleDbError> {
+        self.check_writable()?;
         self.transaction_manager.mark_write(transaction)?;
-        match self.memtables.delete(key, transaction) {
+        transaction.on_write(self.keyspace_id, &key);
+        self.save_undo_entry(transaction, &key);
+        match self.memtables.delete(key, transaction)? {
             Some(memtable_to_flush) => self.flush_memtable(memtable_to_flush),
             None => Ok(()),
         }
     }
 
+    //Called when rolling back to a savepoint. The version of the key written by the transaction is replaced
+    //with the value visible before the savepoint, a tombstone is written if the key wasn't present
+    pub(crate) fn restore(
+        &self,
+        transaction: &Transaction,
+        key: Bytes,
+        previous_value: Option<Bytes>,
+    ) -> Result<(), shared::SimpleDbError> {
+        self.check_writable()?;
+        self.transaction_manager.mark_write(transaction)?;
+        match self.memtables.replace(key, previous_value.as_deref(), transaction)? {
+            Some(memtable_to_flush) => self.flush_memtable(memtable_to_flush),
+            None => Ok(()),
+        }
+    }
+
+    fn save_undo_entry(&self, transaction: &Transaction, key: &Bytes) {
+        if transaction.has_savepoints() {
+            transaction.on_savepoint_write(UndoEntry {
+                previous_value: self.get_visible_value(transaction, key),
+                keyspace_id: self.keyspace_id,
+                key: key.clone(),
+            });
+        }
+    }
+
+    //Value of the key seen by the transaction, after merging its versions. Reads are not tracked
+    fn get_visible_value(&self, transaction: &Transaction, key: &Bytes) -> Option<Bytes> {
+        let mut iterator = self.scan_from_key(transaction, key, true);
+        let mut value = None;
+
+        while iterator.next() && iterator.key().bytes_eq_bytes(key) {
+            value = if iterator.key().is_tombstone() { None } else { Some(Bytes::copy_from_slice(iterator.value())) };
+        }
+
+        value
+    }
+
+    fn scan_from_key(
+        &self,
+        transaction: &Transaction,
+        key: &Bytes,
+        inclusive: bool,
+    ) -> SimpleDbStorageIterator {
+        let transaction = self.transaction_manager.statement_transaction(transaction);
+        let mut iterator = StorageEngineIterator::create(
+            self.descriptor.flags,
+            &self.options,
+            TwoMergeIterator::create(
+                self.memtables.scan_all(&transaction),
+                self.sstables.scan_all(&transaction),
+            ),
+        );
+        iterator.set_range_tombstones(self.get_range_tombstones(&transaction));
+        iterator.seek(key, inclusive);
+        iterator
+    }
+
+    fn get_range_tombstones(&self, transaction: &Transaction) -> Vec<RangeTombstone> {
+        let mut range_tombstones = self.memtables.get_range_tombstones(transaction);
+        range_tombstones.extend(self.sstables.get_range_tombstones(transaction));
+        range_tombstones
+    }
+
     fn flush_memtable(&self, memtable: Arc<MemTable>) -> Result<(), shared::SimpleDbError> {
-        let sstable_builder_ready: SSTableBuilder = memtable.to_sst(&self.transaction_manager);
+        let sstable_builder_ready: SSTableBuilder = memtable.to_sst(&self.transaction_manager)?;
         let sstable_id = self.sstables.flush_memtable_to_disk(sstable_builder_ready)?;
         memtable.set_flushed();
+        self.memtables.on_memtable_flushed(&memtable);
 
         logger().info(StorageKeyspace(self.keyspace_id), &format!(
             "Flushed Memtable ID: {} To SSTable ID {}", memtable.get_id(), sstable_id
@@ -161,6 +330,14 @@ impl Keyspace {
         self.compaction.start_compaction_thread();
     }
 
+    pub fn stop_compaction_thread(&self) {
+        self.compaction.stop_compaction_thread();
+    }
+
+    pub fn get_split_keys(&self, n_chunks: usize) -> Vec<Bytes> {
+        self.sstables.get_split_keys(n_chunks)
+    }
+
     pub fn keyspace_id(&self) -> shared::KeyspaceId {
         self.keyspace_id
     }
@@ -169,6 +346,65 @@ impl Keyspace {
         self.descriptor.flags
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.descriptor.is_read_only()
+    }
+
+    //Compactions are paused while the keyspace is read only
+    pub(crate) fn apply_runtime_options(&self, runtime_options: &RuntimeOptions) {
+        self.compaction.set_task_frequency_ms(runtime_options.compaction_task_frequency_ms());
+        self.memtables.set_max_memtables_inactive(runtime_options.max_memtables_inactive());
+    }
+
+    pub fn set_read_only(&self, read_only: bool) -> Result<(), shared::SimpleDbError> {
+        self.descriptor.set_read_only(read_only)?;
+        self.compaction.set_paused(read_only);
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<(), shared::SimpleDbError> {
+        if self.descriptor.is_read_only() {
+            return Err(shared::SimpleDbError::ReadOnlyKeyspace(self.keyspace_id));
+        }
+
+        Ok(())
+    }
+
+    pub fn memtable_stats(&self) -> MemtableStats {
+        self.memtables.stats()
+    }
+
+    pub fn compaction_stats(&self) -> CompactionStats {
+        self.compaction.stats()
+    }
+
+    pub fn info(&self) -> KeyspaceInfo {
+        KeyspaceInfo {
+            keyspace_id: self.keyspace_id,
+            flags: self.flags(),
+            n_sstables_per_level: self.sstables.get_n_sstables_per_level(),
+            sstables_bytes: self.sstables.get_sstables_size(),
+            n_memtables: 1 + self.memtables.stats().inactive_count,
+        }
+    }
+
+    pub fn describe_sstable(&self, sstable_id: shared::SSTableId) -> Result<SSTableMeta, shared::SimpleDbError> {
+        match self.sstables.get_sstable(sstable_id) {
+            Some(sstable) => Ok(sstable.describe()),
+            None => Err(shared::SimpleDbError::SSTableNotFound(self.keyspace_id, sstable_id)),
+        }
+    }
+
+    pub fn pin_hot_range(&self, start: Bytes, end: Bytes) {
+        self.sstables.pin_hot_range(start, end);
+    }
+
+    //The new strategy is persisted once the existing SSTables have been compacted to the layout it expects
+    pub fn migrate_compaction_strategy(&self, new_strategy: shared::CompactionStrategy) -> Result<(), shared::SimpleDbError> {
+        self.compaction.migrate_strategy(new_strategy)?;
+        self.descriptor.set_compaction_strategy(new_strategy)
+    }
+
     //TODO If lsm engine crash during recovering from manifest, we will likely lose some operations
     pub fn recover_from_manifest(&self) {
         let manifest_operations = self.manifest.read_uncompleted_operations()
@@ -202,4 +438,661 @@ impl Keyspace {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::compaction::compaction::CompactionTask;
+    use crate::compaction::simple_leveled::SimpleLeveledCompactionTask;
+    use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+    use crate::sst::block_cache::BlockCache;
+    use crate::compaction::compaction_stats::CompactionStats;
+    use crate::compaction::tiered::TieredCompactionTask;
+    use crate::compaction::leveled::LeveledCompactionTask;
+    use crate::compaction::full::FullCompactionTask;
+    use crate::keyspace::keyspace::Keyspace;
+    use crate::sst::sstable::SSTable;
+    use crate::sst::sstables_files::to_sstable_file_name;
+    use crate::transactions::transaction_manager::{IsolationLevel, TransactionManager};
+    use crate::utils::faulty_file_system::FaultyFileSystem;
+    use bytes::Bytes;
+    use shared::iterators::storage_iterator::StorageIterator;
+    use shared::logger::Logger;
+    use shared::{CompactionStrategy, FileSystem, SimpleDbError};
+    use std::path::Path;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn compaction_verification_keeps_inputs() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..30 {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+        let sstables_id_l0 = keyspace.sstables.get_sstables_id(0);
+        assert!(!sstables_id_l0.is_empty());
+
+        //The data written by the compaction is lost
+        file_system.zero_writes(true);
+        let result = keyspace.compaction.compact(CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 0 }));
+        file_system.zero_writes(false);
+
+        assert!(matches!(result, Err(SimpleDbError::CompactionVerificationFailed(_, _))));
+        assert_eq!(keyspace.sstables.get_sstables_id(0), sstables_id_l0);
+        assert!(keyspace.sstables.get_sstables_id(1).is_empty());
+        assert_keys_readable(&keyspace, &transaction_manager);
+
+        keyspace.compaction.compact(CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 0 })).unwrap();
+        assert!(keyspace.sstables.get_sstables_id(0).is_empty());
+        assert!(!keyspace.sstables.get_sstables_id(1).is_empty());
+        assert_keys_readable(&keyspace, &transaction_manager);
+    }
+
+    #[test]
+    fn compaction_with_corrupted_block_keeps_inputs() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..30 {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+        let sstables_id_l0 = keyspace.sstables.get_sstables_id(0);
+        assert!(!sstables_id_l0.is_empty());
+
+        //Flip a byte of the first block of an SSTable
+        let sstable_path = shared::get_file_usize(&options.base_path, 0, &to_sstable_file_name(sstables_id_l0[0]));
+        let mut bytes = shared::SimpleDbFile::open_in(&options.file_system, &sstable_path, shared::SimpleDbFileMode::ReadOnly)
+            .unwrap()
+            .read_all()
+            .unwrap();
+        bytes[2] ^= 0xFF;
+        shared::SimpleDbFile::create_in(&options.file_system, &sstable_path, &bytes, shared::SimpleDbFileMode::RandomWrites).unwrap();
+
+        for task in [
+            CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 0 }),
+            CompactionTask::Leveled(LeveledCompactionTask { level: 0 }),
+            CompactionTask::Full(FullCompactionTask { level: 1 }),
+        ] {
+            let result = keyspace.compaction.compact(task);
+            assert!(matches!(result, Err(SimpleDbError::CorruptedBlock(0, sstable_id, 0)) if sstable_id == sstables_id_l0[0]));
+            assert_eq!(keyspace.sstables.get_sstables_id(0), sstables_id_l0);
+            assert!(keyspace.sstables.get_sstables_id(1).is_empty());
+        }
+    }
+
+    #[test]
+    fn compaction_drops_expired_keys() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..30 {
+            let ttl = if i % 2 == 0 { Duration::ZERO } else { Duration::from_secs(3600) };
+            keyspace.set_with_ttl_with_transaction(&transaction, key(i), &value(i), ttl).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+        assert!(!keyspace.sstables.get_sstables_id(0).is_empty());
+
+        keyspace.compaction.compact(CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 0 })).unwrap();
+
+        let mut iterator = keyspace.sstables.scan_from_level(&vec![1]);
+        let mut keys_in_sstables = Vec::new();
+        while iterator.next() {
+            keys_in_sstables.push(iterator.key().as_bytes().clone());
+        }
+        //Some of the keys might still be in memtables
+        assert!(!keys_in_sstables.is_empty());
+        assert!((0..30).filter(|i| i % 2 == 0).all(|i| !keys_in_sstables.contains(&key(i))));
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..30 {
+            let expected = if i % 2 == 0 { None } else { Some(Bytes::from(value(i))) };
+            assert_eq!(keyspace.get_with_transaction(&transaction, &key(i)).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn compaction_drops_range_deleted_keys() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..30 {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        keyspace.delete_range_with_transaction(&transaction, key(0), key(10)).unwrap();
+        transaction_manager.commit(&transaction).unwrap();
+        //The range tombstone is flushed to an SSTable
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 30..60 {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+
+        keyspace.compaction.compact(CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 0 })).unwrap();
+
+        let mut iterator = keyspace.sstables.scan_from_level(&vec![1]);
+        let mut keys_in_sstables = Vec::new();
+        while iterator.next() {
+            keys_in_sstables.push(iterator.key().as_bytes().clone());
+        }
+        assert!(keys_in_sstables.contains(&key(10)));
+        assert!((0..10).all(|i| !keys_in_sstables.contains(&key(i))));
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..60 {
+            let expected = if i < 10 { None } else { Some(Bytes::from(value(i))) };
+            assert_eq!(keyspace.get_with_transaction(&transaction, &key(i)).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn delete_range_rejected_with_savepoints_or_serializable() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system)
+            .base_path("/simpledb")
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        keyspace.set_with_transaction(&transaction, key(1), &value(1)).unwrap();
+        transaction.savepoint("a");
+        assert!(matches!(
+            keyspace.delete_range_with_transaction(&transaction, key(0), key(10)),
+            Err(SimpleDbError::DeleteRangeNotSupported(_))
+        ));
+        transaction_manager.commit(&transaction).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::Serializable);
+        assert!(matches!(
+            keyspace.delete_range_with_transaction(&transaction, key(0), key(10)),
+            Err(SimpleDbError::DeleteRangeNotSupported(_))
+        ));
+        transaction_manager.commit(&transaction).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        assert_eq!(keyspace.get_with_transaction(&transaction, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+    }
+
+    #[test]
+    fn compaction_stats() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+        assert_eq!(keyspace.compaction_stats(), CompactionStats::default());
+
+        //The same keys are written multiple times, so the SSTables overlap
+        write_overlapping_keys(&keyspace, &transaction_manager);
+        keyspace.compaction.compact(CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 0 })).unwrap();
+        let stats_after_leveled = keyspace.compaction_stats();
+        assert_eq!(stats_after_leveled.n_compactions, 1);
+        assert!(stats_after_leveled.n_sstables_merged > 1);
+        assert!(stats_after_leveled.bytes_written > 0);
+        assert!(stats_after_leveled.bytes_written <= stats_after_leveled.bytes_read);
+
+        write_overlapping_keys(&keyspace, &transaction_manager);
+        keyspace.compaction.compact(CompactionTask::Tiered(TieredCompactionTask::SizeRatioTrigger(1))).unwrap();
+        let stats = keyspace.compaction_stats();
+        assert_eq!(stats.n_compactions, 2);
+        assert!(stats.n_sstables_merged > stats_after_leveled.n_sstables_merged);
+        assert!(stats.bytes_read > stats_after_leveled.bytes_read);
+        assert!(stats.bytes_written > stats_after_leveled.bytes_written);
+        assert!(stats.bytes_written <= stats.bytes_read);
+        assert!(stats.total_time >= stats_after_leveled.total_time);
+        assert_keys_readable(&keyspace, &transaction_manager);
+    }
+
+    #[test]
+    fn leveled_compaction_non_overlapping_levels() {
+        let (keyspace, transaction_manager) = create_leveled_keyspace();
+
+        write_keys(&keyspace, &transaction_manager, 0..30);
+        keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 0 })).unwrap();
+        for _ in 0..3 {
+            write_keys(&keyspace, &transaction_manager, 10..20);
+        }
+        keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 0 })).unwrap();
+
+        assert!(keyspace.sstables.get_sstables_id(0).is_empty());
+        assert!(keyspace.sstables.get_n_sstables(1) > 1);
+        assert_level_non_overlapping(&keyspace, 1);
+        assert_keys_readable(&keyspace, &transaction_manager);
+
+        //A single SSTable is pushed from L1 to L2
+        let n_sstables_l1 = keyspace.sstables.get_n_sstables(1);
+        keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 1 })).unwrap();
+        keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 1 })).unwrap();
+
+        assert_eq!(keyspace.sstables.get_n_sstables(1), n_sstables_l1 - 2);
+        assert!(keyspace.sstables.get_n_sstables(2) > 0);
+        assert_level_non_overlapping(&keyspace, 1);
+        assert_level_non_overlapping(&keyspace, 2);
+        assert_keys_readable(&keyspace, &transaction_manager);
+    }
+
+    #[test]
+    fn leveled_compaction_picks_overlapping_sstables() {
+        let (keyspace, transaction_manager) = create_leveled_keyspace();
+        //Keys 10..12 are rewritten until the rest of the keys are flushed to L0
+        write_keys(&keyspace, &transaction_manager, 0..30);
+        for _ in 0..20 {
+            write_keys(&keyspace, &transaction_manager, 10..12);
+        }
+        keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 0 })).unwrap();
+        let sstables_l1 = keyspace.sstables.get_sstables(1);
+        assert!(sstables_l1.len() > 2);
+
+        //Only the L1 SSTables containing keys 10..12 are rewritten
+        for _ in 0..20 {
+            write_keys(&keyspace, &transaction_manager, 10..12);
+        }
+        keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 0 })).unwrap();
+
+        let sstables_id_l1 = keyspace.sstables.get_sstables_id(1);
+        let (overlapping, non_overlapping): (Vec<_>, Vec<_>) = sstables_l1.iter()
+            .partition(|sstable| sstable.first_key.as_bytes() <= &key(11) && sstable.last_key.as_bytes() >= &key(10));
+        assert!(!overlapping.is_empty());
+        assert!(!non_overlapping.is_empty());
+        assert!(overlapping.iter().all(|sstable| !sstables_id_l1.contains(&sstable.sstable_id)));
+        assert!(non_overlapping.iter().all(|sstable| sstables_id_l1.contains(&sstable.sstable_id)));
+        assert_level_non_overlapping(&keyspace, 1);
+        assert_keys_readable(&keyspace, &transaction_manager);
+    }
+
+    #[test]
+    fn leveled_compaction_keeps_hot_ranges() {
+        let (keyspace, transaction_manager) = create_leveled_keyspace();
+        write_keys(&keyspace, &transaction_manager, 0..30);
+        keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 0 })).unwrap();
+        keyspace.pin_hot_range(key(0), key(10));
+
+        let is_hot = |sstable: &Arc<SSTable>| sstable.first_key.as_bytes() < &key(10);
+        let n_cold_sstables_l1 = keyspace.sstables.get_sstables(1).iter()
+            .filter(|sstable| !is_hot(sstable))
+            .count();
+        assert!(n_cold_sstables_l1 > 0);
+        for _ in 0..n_cold_sstables_l1 {
+            keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 1 })).unwrap();
+        }
+
+        //Only the cold SSTables have been pushed to L2
+        assert!(!keyspace.sstables.get_sstables(1).is_empty());
+        assert!(keyspace.sstables.get_sstables(1).iter().all(is_hot));
+        assert!(!keyspace.sstables.get_sstables(2).is_empty());
+        assert!(keyspace.sstables.get_sstables(2).iter().all(|sstable| !is_hot(sstable)));
+        assert_keys_readable(&keyspace, &transaction_manager);
+
+        //Hot SSTables are pushed once there are no cold SSTables left in the level
+        keyspace.compaction.compact(CompactionTask::Leveled(LeveledCompactionTask { level: 1 })).unwrap();
+        assert!(keyspace.sstables.get_sstables(2).iter().any(is_hot));
+        assert_keys_readable(&keyspace, &transaction_manager);
+    }
+
+    #[test]
+    fn no_compaction() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .sst_size_bytes(128)
+            .compaction_task_frequency_ms(1)
+            .compaction_strategy(CompactionStrategy::None)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+        keyspace.start_compaction_thread();
+
+        write_overlapping_keys(&keyspace, &transaction_manager);
+        let n_sstables = keyspace.sstables.get_sstables(0).len();
+        assert!(n_sstables > 4);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        assert_eq!(keyspace.sstables.get_sstables(0).len(), n_sstables);
+        assert!(keyspace.sstables.get_sstables(1).is_empty());
+        assert_eq!(keyspace.compaction.stats().n_compactions, 0);
+        assert_keys_readable(&keyspace, &transaction_manager);
+    }
+
+    #[test]
+    fn migrate_tiered_to_leveled() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .sst_size_bytes(128)
+            .compaction_strategy(CompactionStrategy::Tiered)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        //Tiers in level 0 & 2
+        write_overlapping_keys(&keyspace, &transaction_manager);
+        keyspace.compaction.compact(CompactionTask::Tiered(TieredCompactionTask::SizeRatioTrigger(1))).unwrap();
+        write_overlapping_keys(&keyspace, &transaction_manager);
+        assert!(keyspace.sstables.get_n_sstables(0) > 0);
+        assert!(keyspace.sstables.get_n_sstables(2) > 0);
+
+        keyspace.migrate_compaction_strategy(CompactionStrategy::Leveled).unwrap();
+
+        let last_level = options.leveled_compaction_options.max_levels - 1;
+        assert_eq!(keyspace.sstables.get_non_empty_levels(), vec![last_level]);
+        assert!(keyspace.sstables.get_n_sstables(last_level) > 1);
+        assert_level_non_overlapping(&keyspace, last_level);
+        assert_keys_readable(&keyspace, &transaction_manager);
+        assert!(matches!(keyspace.compaction.strategy(), CompactionStrategy::Leveled));
+
+        //The new strategy is kept after restarting
+        let keyspace = Keyspace::create_and_load(0, transaction_manager.clone(), options.clone(), Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+        assert!(matches!(keyspace.compaction.strategy(), CompactionStrategy::Leveled));
+        assert_keys_readable(&keyspace, &transaction_manager);
+    }
+
+    fn create_leveled_keyspace() -> (Arc<Keyspace>, Arc<TransactionManager>) {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .sst_size_bytes(128)
+            .compaction_strategy(CompactionStrategy::Leveled)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+        (keyspace, transaction_manager)
+    }
+
+    fn assert_level_non_overlapping(keyspace: &Arc<Keyspace>, level: usize) {
+        let mut sstables = keyspace.sstables.get_sstables(level);
+        sstables.sort_by(|a, b| a.first_key.as_bytes().cmp(b.first_key.as_bytes()));
+        for pair in sstables.windows(2) {
+            assert!(pair[0].last_key.as_bytes() < pair[1].first_key.as_bytes());
+        }
+    }
+
+    fn write_keys(keyspace: &Arc<Keyspace>, transaction_manager: &Arc<TransactionManager>, keys: std::ops::Range<usize>) {
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in keys {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+    }
+
+    fn write_overlapping_keys(keyspace: &Arc<Keyspace>, transaction_manager: &Arc<TransactionManager>) {
+        for _ in 0..3 {
+            let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+            for i in 0..30 {
+                keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+            }
+            transaction_manager.commit(&transaction).unwrap();
+        }
+    }
+
+    #[test]
+    fn scan_key_skips_sstables() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(0)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        //Every SSTable contains keys spread over the whole key range
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for round in 0..3 {
+            for i in 0..10 {
+                keyspace.set_with_transaction(&transaction, key(i * 3 + round), &value(i * 3 + round)).unwrap();
+            }
+        }
+        transaction_manager.commit(&transaction).unwrap();
+        let sstables = keyspace.sstables.get_sstables(0);
+        assert!(sstables.len() > 2);
+        assert!(sstables.iter().all(|sstable| sstable.first_key.as_bytes() < &key(10) && sstable.last_key.as_bytes() > &key(19)));
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        let mut iterator = keyspace.scan_key_with_transaction(&transaction, &key(15));
+        assert!(iterator.next());
+        assert_eq!(iterator.key().as_bytes(), &key(15));
+        assert_eq!(iterator.value(), &value(15));
+        assert_eq!(n_sstables_read(&sstables), 1);
+
+        //A scan from the key reads every SSTable
+        let mut iterator = keyspace.scan_from_key_with_transaction(&transaction, &key(15), true);
+        assert!(iterator.next());
+        assert_eq!(n_sstables_read(&sstables), sstables.len());
+    }
+
+    #[test]
+    fn describe_sstable() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(0)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..20 {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+
+        let sstables = keyspace.sstables.get_sstables(0);
+        assert!(!sstables.is_empty());
+        let mut n_entries = 0;
+        for sstable in &sstables {
+            let meta = keyspace.describe_sstable(sstable.sstable_id).unwrap();
+            assert_eq!(meta.min_key, sstable.first_key.as_bytes());
+            assert_eq!(meta.max_key, sstable.last_key.as_bytes());
+            n_entries += meta.n_entries.unwrap();
+        }
+        //Keys are written in order, so the first SSTable starts with the first key
+        assert_eq!(keyspace.describe_sstable(sstables[0].sstable_id).unwrap().min_key, key(0));
+        assert!(n_entries <= 20);
+
+        assert!(matches!(keyspace.describe_sstable(1000), Err(shared::SimpleDbError::SSTableNotFound(0, 1000))));
+    }
+
+    #[test]
+    fn compaction_keeps_tombstones_readable_by_active_transactions() {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(0)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        keyspace.set_with_transaction(&transaction, key(0), &value(0)).unwrap();
+        transaction_manager.commit(&transaction).unwrap();
+        let long_running_transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        keyspace.delete_with_transaction(&transaction, key(0)).unwrap();
+        transaction_manager.commit(&transaction).unwrap();
+        //Flushes the tombstone
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 1..30 {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+
+        //The long running transaction reads the value hidden by the tombstone
+        keyspace.compaction.compact(CompactionTask::Full(FullCompactionTask { level: 1 })).unwrap();
+        assert_eq!(n_versions_in_sstables(&keyspace, &key(0)), 2);
+        assert_eq!(keyspace.get_with_transaction(&long_running_transaction, &key(0)).unwrap(), Some(Bytes::from(value(0))));
+
+        transaction_manager.commit(&long_running_transaction).unwrap();
+        keyspace.compaction.compact(CompactionTask::Full(FullCompactionTask { level: 1 })).unwrap();
+        assert_eq!(n_versions_in_sstables(&keyspace, &key(0)), 0);
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        assert_eq!(keyspace.get_with_transaction(&transaction, &key(0)).unwrap(), None);
+        assert_eq!(keyspace.get_with_transaction(&transaction, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+    }
+
+    #[test]
+    fn parallel_compaction() {
+        let (entries, n_split_keys) = simple_leveled_compaction_entries(1);
+        let (entries_parallel, n_split_keys_parallel) = simple_leveled_compaction_entries(2);
+
+        assert_eq!(n_split_keys, 0);
+        assert_eq!(n_split_keys_parallel, 1);
+        assert!(entries.len() > 500);
+        assert!(entries == entries_parallel);
+    }
+
+    //Returns the entries of level 1 (key, txn id, is tombstone, value) & the number of split keys of the compaction
+    fn simple_leveled_compaction_entries(compaction_threads: usize) -> (Vec<(Bytes, shared::TxnId, bool, Bytes)>, usize) {
+        let file_system = FaultyFileSystem::create();
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(1024)
+            .max_memtables_inactive(0)
+            .sst_size_bytes(4096)
+            .compaction_threads_per_keyspace(compaction_threads)
+            .build_arc();
+        Logger::init(options.clone());
+        let transaction_manager = Arc::new(TransactionManager::create_mock(options.clone()));
+        let keyspace = Keyspace::create_new(0, transaction_manager.clone(), options.clone(), 0, Arc::new(CompactionRateLimiter::create(&options)), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..500 {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in (0..500).step_by(5) {
+            keyspace.set_with_transaction(&transaction, key(i), &value(i + 1)).unwrap();
+        }
+        for i in (0..500).step_by(7) {
+            keyspace.delete_with_transaction(&transaction, key(i)).unwrap();
+        }
+        transaction_manager.commit(&transaction).unwrap();
+
+        let n_split_keys = keyspace.sstables.get_split_keys_in_levels(&[0, 1], compaction_threads).len();
+        keyspace.compaction.compact(CompactionTask::SimpleLeveled(SimpleLeveledCompactionTask { level: 0 })).unwrap();
+        assert!(keyspace.sstables.get_sstables_id(0).is_empty());
+
+        let mut iterator = keyspace.sstables.scan_from_level(&vec![1]);
+        let mut entries = Vec::new();
+        while iterator.next() {
+            let key = iterator.key();
+            entries.push((key.as_bytes().clone(), key.txn_id(), key.is_tombstone(), Bytes::copy_from_slice(iterator.value())));
+        }
+        (entries, n_split_keys)
+    }
+
+    fn n_versions_in_sstables(keyspace: &Arc<Keyspace>, key: &Bytes) -> usize {
+        let mut iterator = keyspace.sstables.scan_from_level(&keyspace.sstables.get_non_empty_levels());
+        let mut n_versions = 0;
+        while iterator.next() {
+            if iterator.key().as_bytes() == key {
+                n_versions += 1;
+            }
+        }
+        n_versions
+    }
+
+    fn n_sstables_read(sstables: &[Arc<SSTable>]) -> usize {
+        sstables.iter()
+            .filter(|sstable| sstable.n_blocks_read.load(Ordering::Relaxed) > 0)
+            .count()
+    }
+
+    fn assert_keys_readable(keyspace: &Arc<Keyspace>, transaction_manager: &Arc<TransactionManager>) {
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        for i in 0..30 {
+            assert_eq!(keyspace.get_with_transaction(&transaction, &key(i)).unwrap(), Some(Bytes::from(value(i))));
+        }
+    }
+
+    fn key(i: usize) -> Bytes {
+        Bytes::from(format!("key-{:03}", i))
+    }
+
+    fn value(i: usize) -> Vec<u8> {
+        format!("value-{}", i).into_bytes()
+    }
+}
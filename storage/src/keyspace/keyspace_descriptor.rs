@@ -1,42 +1,116 @@
-use bytes::Buf;
-use shared::SimpleDbError::{CannotCreateKeyspaceDescriptorFile, CannotOpenKeyspaceDescriptorFile, CannotReadKeyspaceDescriptorFile};
-use shared::{Flag, KeyspaceId, SimpleDbError, SimpleDbFile, SimpleDbFileMode};
+use bytes::{Buf, BufMut};
+use shared::SimpleDbError::{CannotCreateKeyspaceDescriptorFile, CannotOpenKeyspaceDescriptorFile, CannotReadKeyspaceDescriptorFile, CannotWriteKeyspaceDescriptorFile};
+use shared::{CompactionStrategy, Flag, KeyspaceId, SimpleDbError, SimpleDbFile, SimpleDbFileMode, SimpleDbOptions};
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Mutex, RwLock};
 
+//Flags (8 bytes), read only (1 byte), compaction strategy (1 byte).
+//Descriptors written before read only keyspaces existed only contain the flags
 pub struct KeyspaceDescriptor {
     pub(crate) flags: Flag,
+    //Writes to read only keyspaces are rejected, and they are not compacted
+    read_only: AtomicBool,
+    //Set when the keyspace compaction strategy is migrated. If None, SimpleDbOptions::compaction_strategy is used
+    compaction_strategy: RwLock<Option<CompactionStrategy>>,
+
+    file: Mutex<SimpleDbFile>,
+    keyspace_id: KeyspaceId,
 }
 
 impl KeyspaceDescriptor {
     pub fn create(
+        options: &Arc<SimpleDbOptions>,
         flags: Flag,
         keyspace_path: PathBuf,
         keyspace_id: KeyspaceId
     ) -> Result<KeyspaceDescriptor, SimpleDbError> {
         let keyspace_path = Self::to_keyspace_path(keyspace_path);
-        SimpleDbFile::create(keyspace_path.as_path(), &flags.to_le_bytes().to_vec(), SimpleDbFileMode::RandomWrites)
+        let file = SimpleDbFile::create_in(&options.file_system, keyspace_path.as_path(), &Self::serialize(flags, false, None), SimpleDbFileMode::RandomWrites)
+            .and_then(|file| file.fsync().map(|_| file))
             .map_err(|e| CannotCreateKeyspaceDescriptorFile(keyspace_id, e))?;
-        Ok(KeyspaceDescriptor{ flags })
+        Ok(KeyspaceDescriptor {
+            read_only: AtomicBool::new(false),
+            compaction_strategy: RwLock::new(None),
+            file: Mutex::new(file),
+            keyspace_id,
+            flags,
+        })
     }
 
     pub fn load_from_disk(
+        options: &Arc<SimpleDbOptions>,
         keyspace_id: KeyspaceId,
         keyspace_path: PathBuf,
     )  -> Result<KeyspaceDescriptor, SimpleDbError> {
         let path = Self::to_keyspace_path(keyspace_path);
-        let keyspace_file = SimpleDbFile::open(path.as_path(), SimpleDbFileMode::RandomWrites)
+        let keyspace_file = SimpleDbFile::open_in(&options.file_system, path.as_path(), SimpleDbFileMode::RandomWrites)
             .map_err(|e| CannotReadKeyspaceDescriptorFile(keyspace_id, e))?;
         let keyspace_desc_bytes = keyspace_file.read_all()
             .map_err(|e| CannotOpenKeyspaceDescriptorFile(keyspace_id, e))?;
-        Ok(Self::deserialize(keyspace_desc_bytes))
+        let (flags, read_only, compaction_strategy) = Self::deserialize(keyspace_desc_bytes);
+
+        Ok(KeyspaceDescriptor {
+            read_only: AtomicBool::new(read_only),
+            compaction_strategy: RwLock::new(compaction_strategy),
+            file: Mutex::new(keyspace_file),
+            keyspace_id,
+            flags,
+        })
     }
 
-    pub fn deserialize(bytes: Vec<u8>) -> KeyspaceDescriptor {
-        let bytes_ptr = &mut bytes.as_slice();
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Relaxed)
+    }
 
-        KeyspaceDescriptor {
-            flags: bytes_ptr.get_u64_le()
-        }
+    pub fn set_read_only(&self, read_only: bool) -> Result<(), SimpleDbError> {
+        let mut file = self.file.lock().unwrap();
+        file.safe_replace(&Self::serialize(self.flags, read_only, self.compaction_strategy()))
+            .map_err(|e| CannotWriteKeyspaceDescriptorFile(self.keyspace_id, e))?;
+        self.read_only.store(read_only, Relaxed);
+        Ok(())
+    }
+
+    pub fn compaction_strategy(&self) -> Option<CompactionStrategy> {
+        *self.compaction_strategy.read().unwrap()
+    }
+
+    pub fn set_compaction_strategy(&self, compaction_strategy: CompactionStrategy) -> Result<(), SimpleDbError> {
+        let mut file = self.file.lock().unwrap();
+        file.safe_replace(&Self::serialize(self.flags, self.is_read_only(), Some(compaction_strategy)))
+            .map_err(|e| CannotWriteKeyspaceDescriptorFile(self.keyspace_id, e))?;
+        *self.compaction_strategy.write().unwrap() = Some(compaction_strategy);
+        Ok(())
+    }
+
+    fn serialize(flags: Flag, read_only: bool, compaction_strategy: Option<CompactionStrategy>) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        serialized.put_u64_le(flags);
+        serialized.put_u8(read_only as u8);
+        serialized.put_u8(match compaction_strategy {
+            None => 0,
+            Some(CompactionStrategy::SimpleLeveled) => 1,
+            Some(CompactionStrategy::Tiered) => 2,
+            Some(CompactionStrategy::Leveled) => 3,
+            Some(CompactionStrategy::None) => 4,
+        });
+        serialized
+    }
+
+    //Flags, read only, compaction strategy
+    fn deserialize(bytes: Vec<u8>) -> (Flag, bool, Option<CompactionStrategy>) {
+        let bytes_ptr = &mut bytes.as_slice();
+        let flags = bytes_ptr.get_u64_le();
+        let read_only = bytes_ptr.has_remaining() && bytes_ptr.get_u8() != 0;
+        let compaction_strategy = match bytes_ptr.has_remaining().then(|| bytes_ptr.get_u8()) {
+            Some(1) => Some(CompactionStrategy::SimpleLeveled),
+            Some(2) => Some(CompactionStrategy::Tiered),
+            Some(3) => Some(CompactionStrategy::Leveled),
+            Some(4) => Some(CompactionStrategy::None),
+            _ => None,
+        };
+        (flags, read_only, compaction_strategy)
     }
 
     fn to_keyspace_path(mut keyspace_path: PathBuf) -> PathBuf {
@@ -0,0 +1,14 @@
+use shared::{Flag, KeyspaceId};
+
+//Introspection of a keyspace, returned by Storage::list_keyspaces
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyspaceInfo {
+    pub keyspace_id: KeyspaceId,
+    pub flags: Flag,
+    //Indexed by level
+    pub n_sstables_per_level: Vec<usize>,
+    //Size of all the SSTables files
+    pub sstables_bytes: usize,
+    //Active memtable plus the ones waiting to be flushed
+    pub n_memtables: usize,
+}
@@ -1,20 +1,32 @@
+use crate::compaction::compaction_rate_limiter::CompactionRateLimiter;
+use crate::sst::block_cache::BlockCache;
 use crate::keyspace::keyspace::Keyspace;
+use crate::keyspace::keyspace_info::KeyspaceInfo;
+use crate::keyspace::runtime_options::RuntimeOptions;
 use crate::transactions::transaction_manager::TransactionManager;
 use crossbeam_skiplist::SkipMap;
 use std::cmp::max;
-use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
 use std::sync::Arc;
 use shared::Flag;
+use shared::logger::logger;
+use shared::logger::SimpleDbLayer::StorageKeyspace;
+
+const DROPPED_KEYSPACE_EXTENSION: &str = "dropped";
 
 pub struct Keyspaces {
     keyspaces: SkipMap<shared::KeyspaceId, Arc<Keyspace>>,
     next_keyspace_id: AtomicUsize,
 
     transaction_manager: Arc<TransactionManager>,
-    options: Arc<shared::SimpleDbOptions>
+    options: Arc<shared::SimpleDbOptions>,
+    //Shared by the compactions of all keyspaces
+    compaction_rate_limiter: Arc<CompactionRateLimiter>,
+    //Shared by the SSTables of all keyspaces
+    block_cache: Arc<BlockCache>,
+    runtime_options: RuntimeOptions,
 }
 
 impl Keyspaces {
@@ -23,6 +35,9 @@ impl Keyspaces {
             keyspaces: SkipMap::new(),
             next_keyspace_id: AtomicUsize::new(0),
             transaction_manager: Arc::new(TransactionManager::create_mock(options.clone())),
+            compaction_rate_limiter: Arc::new(CompactionRateLimiter::create(&options)),
+            block_cache: Arc::new(BlockCache::create(&options)),
+            runtime_options: RuntimeOptions::create(&options),
             options
         }
     }
@@ -35,17 +50,21 @@ impl Keyspaces {
         let path = PathBuf::from(options.base_path.as_str());
         let path = path.as_path();
         let mut max_keyspace_id = 0;
+        let compaction_rate_limiter = Arc::new(CompactionRateLimiter::create(&options));
+        let block_cache = Arc::new(BlockCache::create(&options));
 
-        for file in fs::read_dir(path).map_err(|e| shared::SimpleDbError::CannotReadKeyspacesDirectories(e))? {
-            let file = file.unwrap();
-            if let Ok(keyspace_id) = file.file_name().to_str().unwrap().parse::<usize>() {
+        for file in options.file_system.list(path).map_err(|e| shared::SimpleDbError::CannotReadKeyspacesDirectories(e))? {
+            let file_name = file.file_name().unwrap().to_str().unwrap();
+            //Keyspace dropped, but we crashed before all of its files were removed
+            if file_name.ends_with(DROPPED_KEYSPACE_EXTENSION) {
+                let _ = options.file_system.remove_dir_all(file.as_path());
+                continue;
+            }
+            if let Ok(keyspace_id) = file_name.parse::<usize>() {
                 let keyspace_id = keyspace_id as shared::KeyspaceId;
-                let is_keyspace = file.metadata()
-                    .map_err(|e| shared::SimpleDbError::CannotReadKeyspaceFile(keyspace_id, e))?
-                    .is_dir();
-                if is_keyspace {
+                if options.file_system.is_dir(file.as_path()) {
                     let keyspace = Keyspace::create_and_load(
-                        keyspace_id, transaction_manager.clone(), options.clone()
+                        keyspace_id, transaction_manager.clone(), options.clone(), compaction_rate_limiter.clone(), block_cache.clone()
                     )?;
                     keyspaces.insert(keyspace_id, keyspace);
                     max_keyspace_id = max(max_keyspace_id, keyspace_id);
@@ -55,6 +74,9 @@ impl Keyspaces {
 
         Ok(Keyspaces{
             next_keyspace_id: AtomicUsize::new(max_keyspace_id + 1),
+            runtime_options: RuntimeOptions::create(&options),
+            compaction_rate_limiter,
+            block_cache,
             transaction_manager,
             options,
             keyspaces
@@ -71,6 +93,13 @@ impl Keyspaces {
         keyspaces
     }
 
+    //Sorted by keyspace ID
+    pub fn list_keyspaces(&self) -> Vec<KeyspaceInfo> {
+        self.keyspaces.iter()
+            .map(|entry| entry.value().info())
+            .collect()
+    }
+
     pub fn get_keyspace(&self, keyspace_id: shared::KeyspaceId) -> Result<Arc<Keyspace>, shared::SimpleDbError> {
         match self.keyspaces.get(&keyspace_id) {
             Some(entry) => Ok(entry.value().clone()),
@@ -81,12 +110,68 @@ impl Keyspaces {
     pub fn create_keyspace(&self, flags: Flag) -> Result<Arc<Keyspace>, shared::SimpleDbError> {
         let keyspace_id = self.next_keyspace_id.fetch_add(1, Relaxed) as shared::KeyspaceId;
         let keyspace = Keyspace::create_new(
-            keyspace_id, self.transaction_manager.clone(), self.options.clone(), flags
+            keyspace_id, self.transaction_manager.clone(), self.options.clone(), flags, self.compaction_rate_limiter.clone(), self.block_cache.clone()
+        )?;
+        self.keyspaces.insert(keyspace_id, keyspace.clone());
+        //Applied once it is inserted, so that it doesn't miss the options changed meanwhile
+        keyspace.apply_runtime_options(&self.runtime_options);
+        Ok(keyspace)
+    }
+
+    //Used to restore keyspaces from a snapshot, so that they keep their IDs
+    pub fn create_keyspace_with_id(&self, keyspace_id: shared::KeyspaceId, flags: Flag) -> Result<Arc<Keyspace>, shared::SimpleDbError> {
+        self.next_keyspace_id.fetch_max(keyspace_id + 1, Relaxed);
+        let keyspace = Keyspace::create_new(
+            keyspace_id, self.transaction_manager.clone(), self.options.clone(), flags, self.compaction_rate_limiter.clone(), self.block_cache.clone()
         )?;
         self.keyspaces.insert(keyspace_id, keyspace.clone());
+        keyspace.apply_runtime_options(&self.runtime_options);
         Ok(keyspace)
     }
 
+    pub fn drop_keyspace(&self, keyspace_id: shared::KeyspaceId) -> Result<(), shared::SimpleDbError> {
+        let keyspace = self.get_keyspace(keyspace_id)?;
+        keyspace.stop_compaction_thread();
+        self.keyspaces.remove(&keyspace_id);
+
+        //Renaming is atomic, so once it is done, the keyspace won't be loaded again at startup,
+        //even if we crash before all of its files are removed
+        let path = shared::get_directory_usize(&self.options.base_path, keyspace_id);
+        let dropped_path = path.with_extension(DROPPED_KEYSPACE_EXTENSION);
+        self.options.file_system.rename(&path, &dropped_path)
+            .map_err(|e| shared::SimpleDbError::CannotDropKeyspace(keyspace_id, e))?;
+
+        if let Err(error) = self.options.file_system.remove_dir_all(&dropped_path) {
+            logger().error(StorageKeyspace(keyspace_id), &format!(
+                "Cannot remove dropped keyspace files: {}. They will be removed at the next startup", error
+            ));
+        }
+
+        Ok(())
+    }
+
+    //Only the options of RuntimeOptions & compaction_max_bytes_per_sec can be changed
+    pub fn set_option(&self, option_name: &str, value: usize) -> Result<(), shared::SimpleDbError> {
+        match option_name {
+            "compaction_task_frequency_ms" => {
+                if value == 0 {
+                    return Err(shared::SimpleDbError::InvalidOption(option_name.to_string(), String::from("it must be greater than 0")));
+                }
+                self.runtime_options.set_compaction_task_frequency_ms(value);
+            },
+            "max_memtables_inactive" => self.runtime_options.set_max_memtables_inactive(value),
+            //Shared by the compactions of all keyspaces
+            "compaction_max_bytes_per_sec" => self.compaction_rate_limiter.set_max_bytes_per_sec(value),
+            _ => return Err(shared::SimpleDbError::InvalidOption(option_name.to_string(), String::from("it cannot be changed while running"))),
+        }
+
+        for keyspace in self.keyspaces.iter() {
+            keyspace.value().apply_runtime_options(&self.runtime_options);
+        }
+
+        Ok(())
+    }
+
     pub fn start_keyspaces_compaction_threads(&self) {
         for keyspace in self.keyspaces.iter() {
             let keyspace = keyspace.value();
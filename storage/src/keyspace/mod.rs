@@ -1,3 +1,5 @@
 pub mod keyspace;
 pub mod keyspaces;
+pub mod keyspace_info;
 mod keyspace_descriptor;
+mod runtime_options;
@@ -0,0 +1,34 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+
+//Options of the keyspaces that can be changed with Storage::set_option while the storage engine is running.
+//When an option is changed, it is applied to every keyspace. Keyspaces created afterward also use the current values
+pub(crate) struct RuntimeOptions {
+    compaction_task_frequency_ms: AtomicUsize,
+    max_memtables_inactive: AtomicUsize,
+}
+
+impl RuntimeOptions {
+    pub(crate) fn create(options: &shared::SimpleDbOptions) -> RuntimeOptions {
+        RuntimeOptions {
+            compaction_task_frequency_ms: AtomicUsize::new(options.compaction_task_frequency_ms),
+            max_memtables_inactive: AtomicUsize::new(options.max_memtables_inactive),
+        }
+    }
+
+    pub(crate) fn compaction_task_frequency_ms(&self) -> usize {
+        self.compaction_task_frequency_ms.load(Relaxed)
+    }
+
+    pub(crate) fn set_compaction_task_frequency_ms(&self, value: usize) {
+        self.compaction_task_frequency_ms.store(value, Relaxed);
+    }
+
+    pub(crate) fn max_memtables_inactive(&self) -> usize {
+        self.max_memtables_inactive.load(Relaxed)
+    }
+
+    pub(crate) fn set_max_memtables_inactive(&self, value: usize) {
+        self.max_memtables_inactive.store(value, Relaxed);
+    }
+}
@@ -3,11 +3,24 @@ mod sst;
 mod compaction;
 mod manifest;
 mod keyspace;
+mod snapshot;
 
 pub mod transactions;
 pub mod storage;
 pub mod utils;
+#[cfg(feature = "async")]
+pub mod async_storage;
 
 pub use shared::iterators::storage_iterator::StorageIterator;
 pub use shared::iterators::mock_iterator::MockIterator;
 pub use storage::*;
+pub use compaction::compaction_stats::CompactionStats;
+pub use memtables::memtable_stats::MemtableStats;
+pub use keyspace::keyspace_info::KeyspaceInfo;
+pub use sst::sstable_meta::SSTableMeta;
+pub use transactions::transaction_info::TxnInfo;
+pub use manifest::manifest::{Manifest, ManifestOperationContent, MemtableFlushManifestOperation, OperationId};
+#[cfg(feature = "object-store")]
+pub use sst::object_store_file_system::ObjectStoreFileSystem;
+#[cfg(feature = "async")]
+pub use async_storage::{AsyncStorage, ScanStream};
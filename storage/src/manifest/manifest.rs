@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::AtomicUsize;
@@ -7,9 +7,13 @@ use bytes::{Buf, BufMut};
 use serde::{Deserialize, Serialize};
 use crate::compaction::compaction::CompactionTask;
 
+pub type OperationId = usize;
+
 pub struct Manifest {
     file: Mutex<shared::SimpleDbFile>,
     last_manifest_record_id: AtomicUsize,
+    //Operations written since the last fsync, used by ManifestFsyncPolicy::Batched
+    n_unsynced_operations: AtomicUsize,
     options: Arc<shared::SimpleDbOptions>,
     keyspace_id: shared::KeyspaceId
 }
@@ -38,9 +42,10 @@ impl Manifest {
         options: Arc<shared::SimpleDbOptions>,
         keyspace_id: shared::KeyspaceId
     ) -> Result<Manifest, shared::SimpleDbError> {
-        match shared::SimpleDbFile::open(Self::manifest_path(&options, keyspace_id).as_path(), shared::SimpleDbFileMode::AppendOnly) {
+        match shared::SimpleDbFile::open_in(&options.file_system, Self::manifest_path(&options, keyspace_id).as_path(), shared::SimpleDbFileMode::AppendOnly) {
             Ok(file) => Ok(Manifest {
                 last_manifest_record_id: AtomicUsize::new(0),
+                n_unsynced_operations: AtomicUsize::new(0),
                 file: Mutex::new(file),
                 keyspace_id,
                 options
@@ -57,6 +62,25 @@ impl Manifest {
         Ok(uncompleted_operations)
     }
 
+    //Returns the operations in the order they were appended, along with whether they have been marked as completed.
+    //Completed records are not returned by themselves.
+    pub fn iter_operations(&self) -> Result<impl Iterator<Item=(OperationId, ManifestOperationContent, bool)>, shared::SimpleDbError> {
+        let all_operations = self.read_all_operations_from_disk()?;
+        let completed_operations_id: HashSet<OperationId> = all_operations.iter()
+            .filter_map(|operation| match operation.content {
+                ManifestOperationContent::Completed(operation_id) => Some(operation_id),
+                _ => None
+            })
+            .collect();
+
+        Ok(all_operations.into_iter()
+            .filter(|operation| !matches!(operation.content, ManifestOperationContent::Completed(_)))
+            .map(move |operation| {
+                let is_completed = completed_operations_id.contains(&operation.manifest_operation_id);
+                (operation.manifest_operation_id, operation.content, is_completed)
+            }))
+    }
+
     fn rewrite_manifest(&self, uncompleted_operations: &Vec<ManifestOperationContent>) -> Result<(), shared::SimpleDbError> {
         self.clear_manifest()?;
 
@@ -69,7 +93,7 @@ impl Manifest {
 
     fn clear_manifest(&self) -> Result<(), shared::SimpleDbError> {
         let path = Self::manifest_path(&self.options, self.keyspace_id);
-        let mut file = shared::SimpleDbFile::open(path.as_path(), shared::SimpleDbFileMode::RandomWrites)
+        let mut file = shared::SimpleDbFile::open_in(&self.options.file_system, path.as_path(), shared::SimpleDbFileMode::RandomWrites)
             .map_err(|e| shared::SimpleDbError::CannotResetManifest(self.keyspace_id, e))?;
 
         file.clear()
@@ -102,19 +126,26 @@ impl Manifest {
     }
 
     fn read_all_operations_from_disk(&self) -> Result<Vec<ManifestOperation>, shared::SimpleDbError> {
-        let mut file_lock_result = self.file.lock();
-        let file = file_lock_result
-            .as_mut()
-            .unwrap();
-        let records_bytes = file.read_all()
+        //The appending file handle might not be positioned at the start, so the records are read using a new one
+        let _file_lock = self.file.lock();
+        let path = Self::manifest_path(&self.options, self.keyspace_id);
+        let records_bytes = shared::SimpleDbFile::open_in(&self.options.file_system, path.as_path(), shared::SimpleDbFileMode::ReadOnly)
+            .and_then(|file| file.read_all())
             .map_err(|e| shared::SimpleDbError::CannotReadManifestOperations(self.keyspace_id, e))?;
         let mut records_bytes_ptr = records_bytes.as_slice();
         let mut all_records: Vec<ManifestOperation> = Vec::new();
         let mut current_offset = 0;
 
         while records_bytes_ptr.has_remaining() {
+            //A crash in the middle of append_operation might leave a torn record at the end of the file
+            if records_bytes_ptr.remaining() < 8 {
+                break;
+            }
             let json_length = records_bytes_ptr.get_u32_le() as usize;
             let expected_crc = records_bytes_ptr.get_u32_le();
+            if records_bytes_ptr.remaining() < json_length {
+                break;
+            }
             let json_record_bytes = &records_bytes_ptr[..json_length];
             let actual_crc = crc32fast::hash(json_record_bytes);
 
@@ -162,7 +193,7 @@ impl Manifest {
 
                 file.write(&serialized)
                     .map_err(|e| shared::SimpleDbError::CannotWriteManifestOperation(self.keyspace_id, e))?;
-                let _ = file.fsync(); //We dont care if it fails to fysnc
+                self.fsync_written_operation(file);
                 Ok(manifest_record_id)
             }
             //This won't happen since manifest_record does not contain a map with non string keys
@@ -171,7 +202,78 @@ impl Manifest {
         }
     }
 
+    //We dont care if it fails to fysnc. Losing the last operations of the manifest only makes
+    //completed operations to be restarted, see Keyspace::recover_from_manifest
+    fn fsync_written_operation(&self, file: &shared::SimpleDbFile) {
+        match self.options.manifest_fsync_policy {
+            shared::ManifestFsyncPolicy::EveryOperation => {
+                let _ = file.fsync();
+            },
+            shared::ManifestFsyncPolicy::Batched(n_operations) => {
+                if self.n_unsynced_operations.fetch_add(1, Relaxed) + 1 >= n_operations {
+                    self.n_unsynced_operations.store(0, Relaxed);
+                    let _ = file.fsync();
+                }
+            },
+        }
+    }
+
+    //Fsyncs the operations that haven't been fsynced yet
+    pub fn sync(&self) -> Result<(), shared::SimpleDbError> {
+        let file_lock_result = self.file.lock();
+        let file = file_lock_result.as_ref().unwrap();
+        self.n_unsynced_operations.store(0, Relaxed);
+        file.fsync()
+            .map_err(|e| shared::SimpleDbError::CannotWriteManifestOperation(self.keyspace_id, e))
+    }
+
     fn manifest_path(options: &Arc<shared::SimpleDbOptions>, keyspace_id: shared::KeyspaceId) -> PathBuf {
         shared::get_file_usize(&options.base_path, keyspace_id, "MANIFEST")
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::sync::Arc;
+    use shared::{FileSystem, InMemoryFileSystem, SimpleDbFileMode};
+    use crate::manifest::manifest::{Manifest, ManifestOperationContent, MemtableFlushManifestOperation};
+
+    #[test]
+    fn iter_operations() {
+        let file_system = Arc::new(InMemoryFileSystem::create());
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .build_arc();
+        let manifest = Manifest::create(options, 1).unwrap();
+
+        let first = manifest.append_operation(flush(1, 1)).unwrap();
+        let second = manifest.append_operation(flush(2, 2)).unwrap();
+        manifest.mark_as_completed(first).unwrap();
+        let third = manifest.append_operation(flush(3, 3)).unwrap();
+
+        assert_operations(&manifest, &[(first, 1, true), (second, 2, false), (third, 3, false)]);
+
+        //Torn trailing record
+        let mut file = file_system.open(Path::new("/simpledb/1/MANIFEST"), &SimpleDbFileMode::AppendOnly).unwrap();
+        file.write_all(&[0x20, 0x00, 0x00, 0x00, 0x01, 0x02, b'{']).unwrap();
+
+        assert_operations(&manifest, &[(first, 1, true), (second, 2, false), (third, 3, false)]);
+    }
+
+    fn assert_operations(manifest: &Manifest, expected: &[(usize, usize, bool)]) {
+        let operations: Vec<(usize, usize, bool)> = manifest.iter_operations().unwrap()
+            .map(|(operation_id, content, is_completed)| match content {
+                ManifestOperationContent::MemtableFlush(flush) => (operation_id, flush.memtable_id, is_completed),
+                _ => panic!("Unexpected manifest operation"),
+            })
+            .collect();
+
+        assert_eq!(operations, expected);
+    }
+
+    fn flush(memtable_id: usize, sstable_id: usize) -> ManifestOperationContent {
+        ManifestOperationContent::MemtableFlush(MemtableFlushManifestOperation { memtable_id, sstable_id })
+    }
+}
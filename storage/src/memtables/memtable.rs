@@ -4,7 +4,8 @@ use crate::memtables::wal::Wal;
 use crate::sst::sstable_builder::SSTableBuilder;
 use crate::transactions::transaction::Transaction;
 use crate::transactions::transaction_manager::TransactionManager;
-use crate::utils::tombstone::TOMBSTONE;
+use crate::utils::tombstone::{merge_values, RangeTombstone, TOMBSTONE};
+use crate::utils::ttl;
 use bytes::{Bytes};
 use crossbeam_skiplist::{SkipMap, SkipSet};
 use shared::iterators::storage_iterator::StorageIterator;
@@ -15,7 +16,7 @@ use std::cell::UnsafeCell;
 use std::ops::Bound::Excluded;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 pub struct MemTable {
     pub(crate) data: Arc<SkipMap<Key, Bytes>>,
@@ -26,6 +27,7 @@ pub struct MemTable {
     pub(crate) wal: UnsafeCell<Wal>,
     pub(crate) options: Arc<shared::SimpleDbOptions>,
     pub(crate) txn_ids_written: SkipSet<shared::TxnId>,
+    pub(crate) range_tombstones: RwLock<Vec<RangeTombstone>>,
     pub(crate) keyspace_id: shared::KeyspaceId,
     pub(crate) keyspace_flags: Flag,
 }
@@ -53,6 +55,7 @@ impl MemTable {
             state: UnsafeCell::new(MemtableState::New),
             data: Arc::new(SkipMap::new()),
             txn_ids_written: SkipSet::new(),
+            range_tombstones: RwLock::new(Vec::new()),
             keyspace_flags,
             keyspace_id,
             memtable_id,
@@ -74,6 +77,7 @@ impl MemTable {
             data: Arc::new(SkipMap::new()),
             wal: UnsafeCell::new(wal),
             txn_ids_written: SkipSet::new(),
+            range_tombstones: RwLock::new(Vec::new()),
             keyspace_flags,
             keyspace_id,
             memtable_id,
@@ -97,6 +101,7 @@ impl MemTable {
             state: UnsafeCell::new(MemtableState::Active),
             data: Arc::new(SkipMap::new()),
             txn_ids_written: SkipSet::new(),
+            range_tombstones: RwLock::new(Vec::new()),
             keyspace_id: 0,
             keyspace_flags,
             memtable_id,
@@ -127,7 +132,12 @@ impl MemTable {
     pub fn set_flushed(&self) {
         unsafe {
             (* self.state.get()) = Flushed;
-            (*self.wal.get()).delete_wal().expect("Cannot delete WAL");
+            //The memtable is already persisted in an SSTable, the WAL will be replayed again at the next startup
+            if let Err(error) = (*self.wal.get()).delete_wal() {
+                logger().error(SimpleDbLayer::StorageKeyspace(self.keyspace_id), &format!(
+                    "Cannot delete WAL of flushed Memtable ID {}: {}", self.memtable_id, error
+                ));
+            }
         }
     }
 
@@ -135,16 +145,17 @@ impl MemTable {
         self.memtable_id
     }
 
-    pub fn get(&self, key_lookup: &Bytes, transaction: &Transaction) -> Option<Bytes> {
-        let mut current_key = Key::create(key_lookup.clone(), transaction.txn_id + 1);
+    //Returns the newest version readable by the transaction. Tombstones are also returned, see Key::is_tombstone
+    pub fn get(&self, key_lookup: &Bytes, transaction: &Transaction) -> Option<(Key, Bytes)> {
+        let mut current_key = Key::create(key_lookup.clone(), transaction.max_readable_txn_id() + 1);
 
         loop {
             if let Some(entry) = self.data.upper_bound(Excluded(&current_key)) {
                 if !entry.key().bytes_eq_bytes(&key_lookup) {
                     return None;
                 }
-                if transaction.can_read(entry.key()) {
-                    return Some(entry.value().clone());
+                if transaction.can_read(entry.key()) && !ttl::is_expired(entry.key(), entry.value()) {
+                    return Some((entry.key().clone(), entry.value().clone()));
                 }
 
                 current_key = entry.key().clone();
@@ -158,33 +169,77 @@ impl MemTable {
         self.write(
             &Key::create(key, transaction.txn_id),
             Bytes::copy_from_slice(value),
-            transaction.txn_id
+            transaction.txn_id,
+            false
+        )
+    }
+
+    //expire_at is in millis since the epoch, it is stored at the start of the value
+    pub fn set_with_ttl(&self, transaction: &Transaction, key: Bytes, value: &[u8], expire_at: u64) -> Result<(), shared::SimpleDbError> {
+        self.write(
+            &Key::create(key, transaction.txn_id).with_ttl(true),
+            ttl::encode_value(expire_at, value),
+            transaction.txn_id,
+            false
         )
     }
 
     pub fn delete(&self, transaction: &Transaction, key: Bytes) -> Result<(), shared::SimpleDbError> {
         self.write(
-            &Key::create(key, transaction.txn_id),
+            &Key::create_tombstone(key, transaction.txn_id),
             TOMBSTONE,
-            transaction.txn_id
+            transaction.txn_id,
+            false
+        )
+    }
+
+    //Deletes the keys in [start, end) written by older transactions
+    pub fn delete_range(&self, transaction: &Transaction, start: Bytes, end: Bytes) -> Result<(), shared::SimpleDbError> {
+        self.write(
+            &Key::create_range_tombstone(start, transaction.txn_id),
+            end,
+            transaction.txn_id,
+            false
         )
     }
 
-    fn write(&self, key: &Key, value: Bytes, txn_id: shared::TxnId) -> Result<(), shared::SimpleDbError> {
+    pub fn get_range_tombstones(&self, transaction: &Transaction) -> Vec<RangeTombstone> {
+        self.range_tombstones.read().unwrap().iter()
+            .filter(|range_tombstone| transaction.can_read(&range_tombstone.start))
+            .cloned()
+            .collect()
+    }
+
+    //Unlike set, the value is not merged with the value previously written by the transaction.
+    //If value is None, the key is deleted
+    pub fn replace(&self, transaction: &Transaction, key: Bytes, value: Option<&[u8]>) -> Result<(), shared::SimpleDbError> {
+        match value {
+            Some(value) => self.write(&Key::create(key, transaction.txn_id), Bytes::copy_from_slice(value), transaction.txn_id, true),
+            None => self.write(&Key::create_tombstone(key, transaction.txn_id), TOMBSTONE, transaction.txn_id, true),
+        }
+    }
+
+    fn write(&self, key: &Key, value: Bytes, txn_id: shared::TxnId, replace: bool) -> Result<(), shared::SimpleDbError> {
         if !self.can_memtable_be_written() {
             return Ok(());
         }
-        if self.current_size_bytes.load(Relaxed) >= self.max_size_bytes {
+        if self.is_full() {
             return Err(shared::SimpleDbError::Internal);
         }
 
-        self.write_wal(&key, &value)?;
+        self.write_wal(&key, &value, replace)?;
 
         self.txn_ids_written.insert(txn_id);
 
         self.current_size_bytes.fetch_add(key.len() + value.len(), Relaxed);
 
-        self.write_into_skiplist(key, value);
+        if key.is_range_tombstone() {
+            self.range_tombstones.write().unwrap().push(RangeTombstone { start: key.clone(), end: value });
+        } else if replace {
+            self.data.insert(key.clone(), value);
+        } else {
+            self.write_into_skiplist(key, value);
+        }
 
         Ok(())
     }
@@ -203,30 +258,31 @@ impl MemTable {
             Some(present_entry) => {
                 let merger_fn = self.options.storage_value_merger.unwrap();
 
-                match merger_fn(present_entry.value(), &value, self.keyspace_flags) {
+                match merge_values(merger_fn, (present_entry.key(), present_entry.value()), (key, &value), self.keyspace_flags) {
                     StorageValueMergeResult::Ok(merged_value) => { self.data.insert(key.clone(), merged_value); }
                     StorageValueMergeResult::DiscardPreviousKeepNew => { self.data.insert(key.clone(), value); }
-                    StorageValueMergeResult::DiscardPreviousAndNew => {}
+                    StorageValueMergeResult::DiscardPreviousAndNew => { self.data.remove(key); }
+                    StorageValueMergeResult::Delete => { self.data.insert(key.clone().with_tombstone(true), TOMBSTONE); }
                 };
             }
             None => { self.data.insert(key.clone(), value); },
         };
     }
 
-    fn write_wal(&self, key: &Key, value: &Bytes) -> Result<(), shared::SimpleDbError> {
+    fn write_wal(&self, key: &Key, value: &Bytes, replace: bool) -> Result<(), shared::SimpleDbError> {
         //Multiple threads can write to the WAL concurrently, since the kernel already makes sure
         //that there won't be race conditions when multiple threads are writing to an append only file
         //https://nullprogram.com/blog/2016/08/03/
         let wal: &mut Wal = unsafe { &mut *self.wal.get() };
 
         if self.can_memtable_wal_be_written() {
-            wal.add_entry(key, value)
+            wal.add_entry(key, value, replace)
         } else {
             Ok(())
         }
     }
 
-    pub fn to_sst(self: &Arc<MemTable>, transaction_manager: &Arc<TransactionManager>) -> SSTableBuilder {
+    pub fn to_sst(self: &Arc<MemTable>, transaction_manager: &Arc<TransactionManager>) -> Result<SSTableBuilder, shared::SimpleDbError> {
         let mut memtable_iterator = MemtableIterator::create(&self, &Transaction::none());
         let mut sstable_builder = SSTableBuilder::create(self.options.clone(), self.keyspace_id, 0);
         sstable_builder.set_memtable_id(self.memtable_id);
@@ -236,25 +292,32 @@ impl MemTable {
             let key = memtable_iterator.key();
 
             match transaction_manager.on_write_key(key) {
-                Ok(_) => sstable_builder.add_entry(key.clone(), Bytes::copy_from_slice(value)),
+                Ok(_) => sstable_builder.add_entry(key.clone(), Bytes::copy_from_slice(value))?,
                 Err(_) => {}
             };
         }
 
-        sstable_builder
+        for range_tombstone in self.range_tombstones.read().unwrap().iter() {
+            if transaction_manager.on_write_key(&range_tombstone.start).is_ok() {
+                sstable_builder.add_range_tombstone(range_tombstone.clone());
+            }
+        }
+
+        Ok(sstable_builder)
     }
 
     fn recover_from_wal(&mut self) -> Result<(), shared::SimpleDbError> {
         self.set_recovering_from_wal();
         let wal: &Wal = unsafe { &*self.wal.get() };
-        let mut entries = wal.read_entries()?;
+        let entries = wal.read_entries()?;
 
         logger().info(SimpleDbLayer::StorageKeyspace(self.keyspace_id), &format!(
             "Applying {} operations from WAL to memtable with ID: {}", entries.len(), wal.get_memtable_id())
         );
 
-        while let Some(entry) = entries.pop() {
-            self.write(&entry.key, entry.value, entry.key.txn_id())?;
+        //Entries are applied in the same order as they were written, so that later writes of a key win
+        for entry in entries {
+            self.write(&entry.key, entry.value, entry.key.txn_id(), entry.replace)?;
         }
 
         self.set_active();
@@ -262,6 +325,18 @@ impl MemTable {
         Ok(())
     }
 
+    pub fn size_bytes(&self) -> usize {
+        self.current_size_bytes.load(Relaxed)
+    }
+
+    pub fn n_entries(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.current_size_bytes.load(Relaxed) >= self.max_size_bytes
+    }
+
     fn can_memtable_be_written(&self) -> bool {
         let current_state = unsafe { &*self.state.get() };
 
@@ -323,14 +398,14 @@ mod test {
 
         let to_test = memtable.get(&Bytes::from("alberto"), &transaction(2));
         assert!(to_test.is_some());
-        assert!(to_test.unwrap().eq(&vec![3]));
+        assert!(to_test.unwrap().1.eq(&vec![3]));
 
         let to_test = memtable.get(&Bytes::from("aa"), &transaction(9));
         assert!(to_test.is_none());
 
         let to_test = memtable.get(&Bytes::from("jaime"), &transaction(6));
         assert!(to_test.is_some());
-        assert!(to_test.unwrap().eq(&vec![8]));
+        assert!(to_test.unwrap().1.eq(&vec![8]));
     }
 
     fn transaction(txn_id: shared::TxnId) -> Transaction {
@@ -163,6 +163,40 @@ mod test {
         assertions::assert_empty_iterator(iterator);
     }
 
+    #[test]
+    fn count_collect_keys_for_each() {
+        let memtable = MemTable::create_mock(Arc::new(shared::SimpleDbOptions::default()), 0, 0)
+            .map(Arc::new)
+            .unwrap();
+        memtable.set_active();
+        memtable.set(&transaction(3), Bytes::from("C"), &[3]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("A"), &[1]).unwrap();
+        memtable.set(&transaction(2), Bytes::from("B"), &[2]).unwrap();
+        memtable.set(&transaction(4), Bytes::from("B"), &[4]).unwrap();
+
+        assert_eq!(MemtableIterator::create(&memtable, &Transaction::none()).count(), 4);
+        assert_eq!(MemtableIterator::create(&memtable, &Transaction::none()).collect_keys(), vec![
+            Key::create_from_str("A", 1),
+            Key::create_from_str("B", 2),
+            Key::create_from_str("B", 4),
+            Key::create_from_str("C", 3),
+        ]);
+
+        let mut values = Vec::new();
+        MemtableIterator::create(&memtable, &Transaction::none()).for_each(|_, value| values.push(value[0]));
+        assert_eq!(values, vec![1, 2, 4, 3]);
+
+        //Only the remaining entries
+        let mut iterator = MemtableIterator::create(&memtable, &Transaction::none());
+        iterator.seek(&Bytes::from("B"), false);
+        assert_eq!(iterator.count(), 1);
+
+        let empty_memtable = MemTable::create_mock(Arc::new(shared::SimpleDbOptions::default()), 0, 0)
+            .map(Arc::new)
+            .unwrap();
+        assert_eq!(MemtableIterator::create(&empty_memtable, &Transaction::none()).count(), 0);
+    }
+
     #[test]
     fn emtpy() {
         let memtable = Arc::new(MemTable::create_mock(Arc::new(shared::SimpleDbOptions::default()), 0, 0)
@@ -0,0 +1,13 @@
+//State of the memtables of a keyspace, and counters of the memtables flushed since it was loaded.
+//Useful to tune SimpleDbOptions::memtable_max_size_bytes & SimpleDbOptions::max_memtables_inactive
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemtableStats {
+    //Bytes of the keys & values written to the active memtable
+    pub active_bytes: usize,
+    pub active_entries: usize,
+    //Full memtables waiting to be flushed
+    pub inactive_count: usize,
+    pub flushes_total: usize,
+    //Bytes of the keys & values of the flushed memtables
+    pub bytes_flushed_total: usize,
+}
@@ -1,8 +1,11 @@
 use crate::memtables::memtable::MemTable;
 use crate::memtables::memtable_iterator::MemtableIterator;
+use crate::memtables::memtable_stats::MemtableStats;
 use crate::memtables::wal::Wal;
 use crate::transactions::transaction::Transaction;
+use crate::utils::tombstone::RangeTombstone;
 use bytes::Bytes;
+use shared::key::Key;
 use shared::iterators::merge_iterator::MergeIterator;
 use shared::Flag;
 use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
@@ -16,6 +19,12 @@ pub struct Memtables {
     keyspace_flags: Flag,
     next_memtable_id: AtomicUsize,
     options: Arc<shared::SimpleDbOptions>,
+    //Initially SimpleDbOptions::max_memtables_inactive
+    max_memtables_inactive: AtomicUsize,
+
+    //Since the keyspace was loaded
+    n_flushes: AtomicUsize,
+    n_bytes_flushed: AtomicUsize,
 }
 
 impl Memtables {
@@ -38,7 +47,7 @@ impl Memtables {
         MergeIterator::create(iterators)
     }
 
-    pub fn get(&self, key: &Bytes, transaction: &Transaction) -> Option<bytes::Bytes> {
+    pub fn get(&self, key: &Bytes, transaction: &Transaction) -> Option<(Key, Bytes)> {
         unsafe {
             let memtable_ref =  (*self.current_memtable.load(Acquire)).clone();
             let value = memtable_ref.get(key, transaction);
@@ -50,26 +59,153 @@ impl Memtables {
         }
     }
 
-    pub fn set(&self, key: Bytes, value: &[u8], transaction: &Transaction) -> Option<Arc<MemTable>> {
+    pub fn set(
+        &self,
+        key: Bytes,
+        value: &[u8],
+        transaction: &Transaction
+    ) -> Result<Option<Arc<MemTable>>, shared::SimpleDbError> {
+        unsafe {
+            let memtable_ref = (*self.current_memtable.load(Acquire)).clone();
+            let set_result = memtable_ref.set(transaction, key.clone(), value);
+
+            match set_result {
+                Err(_) if memtable_ref.is_full() => {
+                    let memtable_to_flush = self.set_current_memtable_as_inactive();
+                    //The memtable was full, the write is retried in the new one
+                    (*self.current_memtable.load(Acquire)).set(transaction, key, value)?;
+                    Ok(memtable_to_flush)
+                },
+                Err(e) => Err(e),
+                Ok(_) => Ok(None),
+            }
+        }
+    }
+
+    pub fn set_with_ttl(
+        &self,
+        key: Bytes,
+        value: &[u8],
+        expire_at: u64,
+        transaction: &Transaction
+    ) -> Result<Option<Arc<MemTable>>, shared::SimpleDbError> {
         unsafe {
             let memtable_ref = (*self.current_memtable.load(Acquire)).clone();
-            let set_result = memtable_ref.set(transaction, key, value);
+            let set_result = memtable_ref.set_with_ttl(transaction, key.clone(), value, expire_at);
 
             match set_result {
-                Err(_) => self.set_current_memtable_as_inactive(),
-                _ => None
+                Err(_) if memtable_ref.is_full() => {
+                    let memtable_to_flush = self.set_current_memtable_as_inactive();
+                    (*self.current_memtable.load(Acquire)).set_with_ttl(transaction, key, value, expire_at)?;
+                    Ok(memtable_to_flush)
+                },
+                Err(e) => Err(e),
+                Ok(_) => Ok(None),
+            }
+        }
+    }
+
+    pub fn delete(
+        &self,
+        key: Bytes,
+        transaction: &Transaction
+    ) -> Result<Option<Arc<MemTable>>, shared::SimpleDbError> {
+        unsafe {
+            let memtable_ref = (*self.current_memtable.load(Acquire)).clone();
+            let delete_result = memtable_ref.delete(transaction, key.clone());
+
+            match delete_result {
+                Err(_) if memtable_ref.is_full() => {
+                    let memtable_to_flush = self.set_current_memtable_as_inactive();
+                    (*self.current_memtable.load(Acquire)).delete(transaction, key)?;
+                    Ok(memtable_to_flush)
+                },
+                Err(e) => Err(e),
+                Ok(_) => Ok(None),
             }
         }
     }
 
-    pub fn delete(&self, key: Bytes, transaction: &Transaction) -> Option<Arc<MemTable>> {
+    pub fn delete_range(
+        &self,
+        start: Bytes,
+        end: Bytes,
+        transaction: &Transaction
+    ) -> Result<Option<Arc<MemTable>>, shared::SimpleDbError> {
         unsafe {
             let memtable_ref = (*self.current_memtable.load(Acquire)).clone();
-            let delete_result = memtable_ref.delete(transaction, key);
+            let delete_result = memtable_ref.delete_range(transaction, start.clone(), end.clone());
 
             match delete_result {
-                Err(_) => self.set_current_memtable_as_inactive(),
-                _ => None,
+                Err(_) if memtable_ref.is_full() => {
+                    let memtable_to_flush = self.set_current_memtable_as_inactive();
+                    (*self.current_memtable.load(Acquire)).delete_range(transaction, start, end)?;
+                    Ok(memtable_to_flush)
+                },
+                Err(e) => Err(e),
+                Ok(_) => Ok(None),
+            }
+        }
+    }
+
+    //Range tombstones of every memtable readable by the transaction
+    pub fn get_range_tombstones(&self, transaction: &Transaction) -> Vec<RangeTombstone> {
+        unsafe {
+            let mut range_tombstones = (*self.current_memtable.load(Acquire)).get_range_tombstones(transaction);
+
+            let inactive_memtables_rw_lock = &*self.inactive_memtables.load(Acquire);
+            for inactive_memtable in inactive_memtables_rw_lock.read().unwrap().iter() {
+                range_tombstones.extend(inactive_memtable.get_range_tombstones(transaction));
+            }
+
+            range_tombstones
+        }
+    }
+
+    pub fn replace(
+        &self,
+        key: Bytes,
+        value: Option<&[u8]>,
+        transaction: &Transaction
+    ) -> Result<Option<Arc<MemTable>>, shared::SimpleDbError> {
+        unsafe {
+            let memtable_ref = (*self.current_memtable.load(Acquire)).clone();
+            let replace_result = memtable_ref.replace(transaction, key.clone(), value);
+
+            match replace_result {
+                Err(_) if memtable_ref.is_full() => {
+                    let memtable_to_flush = self.set_current_memtable_as_inactive();
+                    (*self.current_memtable.load(Acquire)).replace(transaction, key, value)?;
+                    Ok(memtable_to_flush)
+                },
+                Err(e) => Err(e),
+                Ok(_) => Ok(None),
+            }
+        }
+    }
+
+    //Called once the memtable has been written to disk
+    pub fn on_memtable_flushed(&self, memtable: &MemTable) {
+        self.n_flushes.fetch_add(1, Relaxed);
+        self.n_bytes_flushed.fetch_add(memtable.size_bytes(), Relaxed);
+    }
+
+    //Used from the next memtable that becomes inactive
+    pub fn set_max_memtables_inactive(&self, max_memtables_inactive: usize) {
+        self.max_memtables_inactive.store(max_memtables_inactive, Relaxed);
+    }
+
+    pub fn stats(&self) -> MemtableStats {
+        unsafe {
+            let current_memtable = (*self.current_memtable.load(Acquire)).clone();
+            let inactive_memtables = (*self.inactive_memtables.load(Acquire)).read().unwrap();
+
+            MemtableStats {
+                active_bytes: current_memtable.size_bytes(),
+                active_entries: current_memtable.n_entries(),
+                inactive_count: inactive_memtables.len(),
+                flushes_total: self.n_flushes.load(Relaxed),
+                bytes_flushed_total: self.n_bytes_flushed.load(Relaxed),
             }
         }
     }
@@ -102,7 +238,8 @@ impl Memtables {
             let inactive_memtables_rw_lock = &*self.inactive_memtables.load(Acquire);
             let inactive_memtables_rw_result = inactive_memtables_rw_lock.read().unwrap();
 
-            for memtable in inactive_memtables_rw_result.iter() {
+            //Newest memtables go first, see MergeIterator
+            for memtable in inactive_memtables_rw_result.iter().rev() {
                 let cloned = Arc::clone(memtable);
                 memtable_iterators.push(Box::new(MemtableIterator::create(&cloned, transaction)));
             }
@@ -111,7 +248,7 @@ impl Memtables {
         }
     }
 
-    fn find_value_in_inactive_memtables(&self, key: &Bytes, transaction: &Transaction) -> Option<bytes::Bytes> {
+    fn find_value_in_inactive_memtables(&self, key: &Bytes, transaction: &Transaction) -> Option<(Key, Bytes)> {
         unsafe {
             let inactive_memtables_rw_lock = &*self.inactive_memtables.load(Acquire);
             let inactive_memtables = inactive_memtables_rw_lock.read()
@@ -158,7 +295,7 @@ impl Memtables {
 
         memtables.push((*prev_memtable).clone());
 
-        if memtables.len() > self.options.max_memtables_inactive {
+        if memtables.len() > self.max_memtables_inactive.load(Relaxed) {
             let memtable_to_flush = memtables.remove(0);
             memtable_to_flush.set_flushing();
             return Some(memtable_to_flush);
@@ -206,6 +343,9 @@ impl Memtables {
             inactive_memtables: AtomicPtr::new(Box::into_raw(Box::new(RwLock::new(inactive_memtables)))),
             current_memtable: AtomicPtr::new(Box::into_raw(Box::new(Arc::new(active_memtable)))),
             next_memtable_id: AtomicUsize::new(next_memtable_id),
+            n_flushes: AtomicUsize::new(0),
+            n_bytes_flushed: AtomicUsize::new(0),
+            max_memtables_inactive: AtomicUsize::new(options.max_memtables_inactive),
             keyspace_flags,
             keyspace_id,
             options
@@ -224,6 +364,9 @@ impl Memtables {
             inactive_memtables: AtomicPtr::new(Box::into_raw(Box::new(RwLock::new(Vec::with_capacity(options.max_memtables_inactive))))),
             current_memtable: AtomicPtr::new(Box::into_raw(Box::new(Arc::new(current_memtable)))),
             next_memtable_id: AtomicUsize::new(1),
+            n_flushes: AtomicUsize::new(0),
+            n_bytes_flushed: AtomicUsize::new(0),
+            max_memtables_inactive: AtomicUsize::new(options.max_memtables_inactive),
             keyspace_flags,
             keyspace_id,
             options
@@ -1,5 +1,6 @@
 pub mod memtables;
 pub mod memtable;
 pub mod memtable_iterator;
+pub mod memtable_stats;
 
 mod wal;
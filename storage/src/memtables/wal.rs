@@ -1,8 +1,7 @@
+use crate::transactions::group_commit::GroupCommit;
 use bytes::{Buf, BufMut, Bytes};
 use std::cmp::max;
-use std::fs;
-use std::fs::DirEntry;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use shared::key::Key;
 
@@ -11,11 +10,21 @@ pub struct Wal {
     options: Arc<shared::SimpleDbOptions>,
     memtable_id: shared::MemtableId,
     file: shared::SimpleDbFile,
+    //Some if group_commit_window_ms is enabled with DurabilityLevel::Strong. Concurrent writes to the memtable
+    //share the fsync of the WAL
+    group_commit: Option<GroupCommit>,
 }
 
+//Set in the key length of entries that replace the value of the key instead of being merged with it
+const WAL_ENTRY_REPLACE_FLAG: u32 = 1 << 31;
+//Set in the key length of entries whose key is followed by its flags. Entries written before keys had flags
+//store deletes as empty values
+const WAL_ENTRY_HAS_KEY_FLAGS: u32 = 1 << 30;
+
 pub(crate) struct WalEntry {
     pub key: Key,
-    pub value: Bytes
+    pub value: Bytes,
+    pub replace: bool,
 }
 
 impl Wal {
@@ -25,8 +34,9 @@ impl Wal {
         memtable_id: shared::MemtableId
     ) -> Result<Wal, shared::SimpleDbError> {
         Ok(Wal {
-            file: shared::SimpleDbFile::open(Self::to_wal_file_path(&options, memtable_id, keyspace_id).as_path(), shared::SimpleDbFileMode::AppendOnly)
+            file: shared::SimpleDbFile::open_in(&options.file_system, Self::to_wal_file_path(&options, memtable_id, keyspace_id).as_path(), shared::SimpleDbFileMode::AppendOnly)
                 .map_err(|e| shared::SimpleDbError::CannotCreateWal(keyspace_id, memtable_id, e))?,
+            group_commit: GroupCommit::create_from_options(&options),
             options,
             keyspace_id,
             memtable_id,
@@ -39,19 +49,27 @@ impl Wal {
     ) -> Result<Wal, shared::SimpleDbError> {
         Ok(Wal {
             file: shared::SimpleDbFile::mock(),
+            group_commit: GroupCommit::create_from_options(&options),
             keyspace_id: 0,
             options,
             memtable_id,
         })
     }
 
-    pub fn add_entry(&mut self, key: &Key, value: &[u8]) -> Result<(), shared::SimpleDbError> {
-        let encoded = self.encode(key, value);
+    pub fn add_entry(&mut self, key: &Key, value: &[u8], replace: bool) -> Result<(), shared::SimpleDbError> {
+        let encoded = self.encode(key, value, replace);
+
+        if let Some(group_commit) = &self.group_commit {
+            return group_commit.write(&mut self.file, &encoded)
+                .map_err(|e| shared::SimpleDbError::CannotWriteWalEntry(self.keyspace_id, self.memtable_id, e));
+        }
+
         self.file.write(&encoded)
             .map_err(|e| shared::SimpleDbError::CannotWriteWalEntry(self.keyspace_id, self.memtable_id, e))?;
 
         if matches!(self.options.durability_level, shared::DurabilityLevel::Strong) {
-            let _ = self.file.fsync();
+            self.file.fsync()
+                .map_err(|e| shared::SimpleDbError::CannotWriteWalEntry(self.keyspace_id, self.memtable_id, e))?;
         }
 
         Ok(())
@@ -68,14 +86,22 @@ impl Wal {
             let start_entry_ptr = current_ptr;
             let mut entry_bytes_size = 0;
 
-            let key_len = current_ptr.get_u32_le() as usize;
+            let encoded_key_len = current_ptr.get_u32_le();
+            let replace = encoded_key_len & WAL_ENTRY_REPLACE_FLAG != 0;
+            let has_key_flags = encoded_key_len & WAL_ENTRY_HAS_KEY_FLAGS != 0;
+            let key_len = (encoded_key_len & !(WAL_ENTRY_REPLACE_FLAG | WAL_ENTRY_HAS_KEY_FLAGS)) as usize;
             let key_timestmap = current_ptr.get_u64_le() as shared::TxnId;
-            entry_bytes_size = entry_bytes_size + 12;
+            entry_bytes_size += 12;
+            let key_flags = if has_key_flags {
+                entry_bytes_size += 1;
+                Some(current_ptr.get_u8())
+            } else {
+                None
+            };
 
             let key_bytes = &current_ptr[..key_len];
             current_ptr.advance(key_len);
             entry_bytes_size = entry_bytes_size + key_len;
-            let key = Key::create(Bytes::from(key_bytes.to_vec()), key_timestmap);
 
             let value_len = current_ptr.get_u32_le() as usize;
             entry_bytes_size = entry_bytes_size + 4;
@@ -83,6 +109,12 @@ impl Wal {
             current_ptr.advance(value_len);
             entry_bytes_size = entry_bytes_size + value_len;
 
+            let key = Key::create(Bytes::from(key_bytes.to_vec()), key_timestmap);
+            let key = match key_flags {
+                Some(key_flags) => key.with_flags(key_flags),
+                None => key.with_tombstone(value_len == 0),
+            };
+
             let expected_crc = current_ptr.get_u32_le();
             let actual_crc = crc32fast::hash(&start_entry_ptr[..entry_bytes_size]);
             entry_bytes_size = entry_bytes_size + 4;
@@ -97,6 +129,7 @@ impl Wal {
 
             entries.push(WalEntry{
                 value: Bytes::copy_from_slice(value_bytes),
+                replace,
                 key
             });
 
@@ -123,9 +156,7 @@ impl Wal {
         let mut max_memtable_id: usize = 0;
         let mut wals: Vec<Wal> = Vec::new();
 
-        for file in fs::read_dir(path).map_err(|e| shared::SimpleDbError::CannotReadWalFiles(keyspace_id, e))? {
-            let file = file.unwrap();
-
+        for file in options.file_system.list(path).map_err(|e| shared::SimpleDbError::CannotReadWalFiles(keyspace_id, e))? {
             if !Self::is_wal_file(&file) {
                 continue;
             }
@@ -133,8 +164,9 @@ impl Wal {
             if let Ok(memtable_id) = Self::extract_memtable_id_from_file(&file) {
                 max_memtable_id = max(max_memtable_id, memtable_id);
                 wals.push(Wal{
-                    file: shared::SimpleDbFile::open(file.path().as_path(), shared::SimpleDbFileMode::AppendOnly)
+                    file: shared::SimpleDbFile::open_in(&options.file_system, file.as_path(), shared::SimpleDbFileMode::AppendOnly)
                         .map_err(|e| shared::SimpleDbError::CannotReadWalFiles(keyspace_id, e))?,
+                    group_commit: GroupCommit::create_from_options(options),
                     options: options.clone(),
                     keyspace_id,
                     memtable_id,
@@ -145,11 +177,13 @@ impl Wal {
         Ok((wals, max_memtable_id))
     }
 
-    fn encode(&self, key: &Key, value: &[u8]) -> Vec<u8> {
+    fn encode(&self, key: &Key, value: &[u8], replace: bool) -> Vec<u8> {
         let mut encoded: Vec<u8> = Vec::new();
         //Key
-        encoded.put_u32_le(key.len() as u32);
+        let replace_flag = if replace { WAL_ENTRY_REPLACE_FLAG } else { 0 };
+        encoded.put_u32_le(key.len() as u32 | replace_flag | WAL_ENTRY_HAS_KEY_FLAGS);
         encoded.put_u64_le(key.txn_id() as u64);
+        encoded.put_u8(key.flags());
         encoded.extend(key.as_bytes());
         //Value
         encoded.put_u32_le(value.len() as u32);
@@ -160,12 +194,12 @@ impl Wal {
         encoded
     }
 
-    fn extract_memtable_id_from_file(file: &DirEntry) -> Result<shared::MemtableId, ()> {
+    fn extract_memtable_id_from_file(file: &Path) -> Result<shared::MemtableId, ()> {
         shared::extract_number_from_file_name(file, "-")
     }
 
-    fn is_wal_file(file: &DirEntry) -> bool {
-        file.file_name().to_str().unwrap().starts_with("wal-")
+    fn is_wal_file(file: &Path) -> bool {
+        file.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("wal-"))
     }
 
     fn to_wal_file_path(
@@ -176,4 +210,55 @@ impl Wal {
         let wal_file_name = format!("wal-{}", memtable_id);
         shared::get_file_usize(&options.base_path, keyspace_id, wal_file_name.as_str())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use crate::memtables::wal::Wal;
+    use bytes::{BufMut, Bytes};
+    use shared::key::Key;
+    use shared::{FileSystem, InMemoryFileSystem};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn entries_without_key_flags() {
+        let file_system = Arc::new(InMemoryFileSystem::create());
+        file_system.create_dir_all(Path::new("/simpledb/0")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system)
+            .base_path("/simpledb")
+            .build_arc();
+        let mut wal = Wal::create(options.clone(), 0, 1).unwrap();
+
+        //Entries written before keys had flags. Deletes are stored as empty values
+        wal.file.write(&encode_entry_without_key_flags(b"a", 1, b"1")).unwrap();
+        wal.file.write(&encode_entry_without_key_flags(b"b", 2, b"")).unwrap();
+        wal.add_entry(&Key::create_tombstone(Bytes::from("c"), 3), &[], false).unwrap();
+        wal.add_entry(&Key::create(Bytes::from("d"), 4), &[], false).unwrap();
+
+        wal.file.fsync().unwrap();
+
+        let (wals, _) = Wal::get_persisted_wal_id(&options, 0).unwrap();
+        let entries = wals[0].read_entries().unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].key, Key::create(Bytes::from("a"), 1));
+        assert_eq!(entries[0].value, Bytes::from("1"));
+        assert!(!entries[0].key.is_tombstone());
+        assert_eq!(entries[1].key, Key::create(Bytes::from("b"), 2));
+        assert!(entries[1].key.is_tombstone());
+        assert!(entries[2].key.is_tombstone());
+        //Empty values written with key flags aren't deletes
+        assert!(!entries[3].key.is_tombstone());
+    }
+
+    fn encode_entry_without_key_flags(key: &[u8], txn_id: shared::TxnId, value: &[u8]) -> Vec<u8> {
+        let mut encoded: Vec<u8> = Vec::new();
+        encoded.put_u32_le(key.len() as u32);
+        encoded.put_u64_le(txn_id as u64);
+        encoded.extend(key);
+        encoded.put_u32_le(value.len() as u32);
+        encoded.extend(value);
+        encoded.put_u32_le(crc32fast::hash(&encoded));
+        encoded
+    }
+}
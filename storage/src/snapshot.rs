@@ -0,0 +1,275 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use shared::{DecodeError, DecodeErrorType, FileSystem, Flag, KeyspaceId, SimpleDbError, SimpleDbFile, SimpleDbFileMode};
+use std::path::Path;
+use std::sync::Arc;
+
+//Max number of bytes buffered by SnapshotWriter & SnapshotReader, so creating and restoring a snapshot
+//doesn't load the keyspaces in memory
+const SNAPSHOT_BUFFER_SIZE: usize = 64 * 1024;
+
+const ENTRY: u8 = 0;
+const ENTRY_WITH_TTL: u8 = 1;
+const END_OF_KEYSPACE: u8 = 2;
+
+//A snapshot is a logical copy of the keyspaces, written as a single file:
+//[N keyspaces (u32)] followed by every keyspace:
+//  [Keyspace ID (u64), Flags (u64), Read only (u8)] followed by every entry:
+//    [Entry type (u8), Expire at (u64, only if it has TTL), Key size (u32), Key, Value size (u32), Value]
+//  [End of keyspace (u8)]
+//The file ends with the crc32 of all the previous bytes (u32)
+pub(crate) struct SnapshotKeyspace {
+    pub keyspace_id: KeyspaceId,
+    pub flags: Flag,
+    pub read_only: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct SnapshotEntry {
+    pub key: Bytes,
+    //Without the expiration time
+    pub value: Bytes,
+    pub expire_at: Option<u64>,
+}
+
+//Writes a snapshot while the keyspaces are being scanned. The file is only valid once finish() is called
+pub(crate) struct SnapshotWriter {
+    file: SimpleDbFile,
+    buffer: Vec<u8>,
+    crc: crc32fast::Hasher,
+}
+
+//Reads a snapshot written by SnapshotWriter. The crc is checked when it is opened,
+//so the file has to be read twice
+pub(crate) struct SnapshotReader {
+    file: SimpleDbFile,
+    //Without the crc
+    content_size: usize,
+    //Next offset of the file to read into the buffer
+    file_offset: usize,
+    buffer: Bytes,
+    n_remaining_keyspaces: usize,
+}
+
+impl SnapshotWriter {
+    pub(crate) fn create(
+        file_system: &Arc<dyn FileSystem>,
+        path: &Path,
+        n_keyspaces: usize,
+    ) -> Result<SnapshotWriter, SimpleDbError> {
+        let mut file = SimpleDbFile::open_in(file_system, path, SimpleDbFileMode::AppendOnly)
+            .map_err(SimpleDbError::CannotWriteSnapshot)?;
+        file.clear().map_err(SimpleDbError::CannotWriteSnapshot)?;
+
+        let mut writer = SnapshotWriter { file, buffer: Vec::with_capacity(SNAPSHOT_BUFFER_SIZE), crc: crc32fast::Hasher::new() };
+        writer.buffer.put_u32_le(n_keyspaces as u32);
+        Ok(writer)
+    }
+
+    pub(crate) fn start_keyspace(&mut self, keyspace: &SnapshotKeyspace) -> Result<(), SimpleDbError> {
+        self.buffer.put_u64_le(keyspace.keyspace_id as u64);
+        self.buffer.put_u64_le(keyspace.flags);
+        self.buffer.put_u8(keyspace.read_only as u8);
+        self.maybe_flush()
+    }
+
+    pub(crate) fn add_entry(&mut self, entry: &SnapshotEntry) -> Result<(), SimpleDbError> {
+        match entry.expire_at {
+            Some(expire_at) => {
+                self.buffer.put_u8(ENTRY_WITH_TTL);
+                self.buffer.put_u64_le(expire_at);
+            },
+            None => self.buffer.put_u8(ENTRY),
+        }
+        self.buffer.put_u32_le(entry.key.len() as u32);
+        self.buffer.extend_from_slice(&entry.key);
+        self.buffer.put_u32_le(entry.value.len() as u32);
+        self.buffer.extend_from_slice(&entry.value);
+        self.maybe_flush()
+    }
+
+    pub(crate) fn end_keyspace(&mut self) -> Result<(), SimpleDbError> {
+        self.buffer.put_u8(END_OF_KEYSPACE);
+        self.maybe_flush()
+    }
+
+    pub(crate) fn finish(mut self) -> Result<(), SimpleDbError> {
+        self.flush()?;
+        let crc = self.crc.clone().finalize();
+        self.file.write(&crc.to_le_bytes())
+            .and_then(|_| self.file.fsync())
+            .map_err(SimpleDbError::CannotWriteSnapshot)
+    }
+
+    fn maybe_flush(&mut self) -> Result<(), SimpleDbError> {
+        if self.buffer.len() >= SNAPSHOT_BUFFER_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SimpleDbError> {
+        self.crc.update(&self.buffer);
+        self.file.write(&self.buffer).map_err(SimpleDbError::CannotWriteSnapshot)?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl SnapshotReader {
+    pub(crate) fn open(file_system: &Arc<dyn FileSystem>, path: &Path) -> Result<SnapshotReader, SimpleDbError> {
+        let file = SimpleDbFile::open_in(file_system, path, SimpleDbFileMode::ReadOnly)
+            .map_err(SimpleDbError::CannotReadSnapshot)?;
+        if file.size() < 4 {
+            return Err(decode_error(0, DecodeErrorType::IllegalSize(4, file.size())));
+        }
+
+        let mut reader = SnapshotReader {
+            content_size: file.size() - 4,
+            buffer: Bytes::new(),
+            n_remaining_keyspaces: 0,
+            file_offset: 0,
+            file,
+        };
+        reader.check_crc()?;
+        reader.n_remaining_keyspaces = reader.read_bytes(4)?.get_u32_le() as usize;
+        Ok(reader)
+    }
+
+    //Returns None once all keyspaces have been read. The entries of the previous keyspace have to be read before
+    pub(crate) fn next_keyspace(&mut self) -> Result<Option<SnapshotKeyspace>, SimpleDbError> {
+        if self.n_remaining_keyspaces == 0 {
+            return Ok(None);
+        }
+        self.n_remaining_keyspaces -= 1;
+
+        let mut header = self.read_bytes(17)?;
+        Ok(Some(SnapshotKeyspace {
+            keyspace_id: header.get_u64_le() as KeyspaceId,
+            flags: header.get_u64_le(),
+            read_only: header.get_u8() != 0,
+        }))
+    }
+
+    //Returns None once all the entries of the current keyspace have been read
+    pub(crate) fn next_entry(&mut self) -> Result<Option<SnapshotEntry>, SimpleDbError> {
+        let entry_type_offset = self.current_offset();
+        let expire_at = match self.read_bytes(1)?.get_u8() {
+            END_OF_KEYSPACE => return Ok(None),
+            ENTRY => None,
+            ENTRY_WITH_TTL => Some(self.read_bytes(8)?.get_u64_le()),
+            entry_type => return Err(decode_error(entry_type_offset, DecodeErrorType::UnknownFlag(entry_type as usize))),
+        };
+        let key_size = self.read_bytes(4)?.get_u32_le() as usize;
+        let key = self.read_bytes(key_size)?;
+        let value_size = self.read_bytes(4)?.get_u32_le() as usize;
+        let value = self.read_bytes(value_size)?;
+
+        Ok(Some(SnapshotEntry { key, value, expire_at }))
+    }
+
+    fn check_crc(&self) -> Result<(), SimpleDbError> {
+        let mut crc = crc32fast::Hasher::new();
+        let mut offset = 0;
+        while offset < self.content_size {
+            let chunk_size = SNAPSHOT_BUFFER_SIZE.min(self.content_size - offset);
+            crc.update(&self.read_file(offset, chunk_size)?);
+            offset += chunk_size;
+        }
+
+        let expected_crc = (&self.read_file(self.content_size, 4)?[..]).get_u32_le();
+        let actual_crc = crc.finalize();
+        if expected_crc != actual_crc {
+            return Err(decode_error(self.content_size, DecodeErrorType::CorruptedCrc(expected_crc, actual_crc)));
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, n_bytes: usize) -> Result<Bytes, SimpleDbError> {
+        if self.buffer.len() < n_bytes {
+            let n_bytes_to_read = (n_bytes - self.buffer.len()).max(SNAPSHOT_BUFFER_SIZE)
+                .min(self.content_size - self.file_offset);
+            if self.buffer.len() + n_bytes_to_read < n_bytes {
+                return Err(decode_error(self.current_offset(), DecodeErrorType::IllegalSize(n_bytes, self.buffer.len() + n_bytes_to_read)));
+            }
+
+            let mut buffer = BytesMut::with_capacity(self.buffer.len() + n_bytes_to_read);
+            buffer.extend_from_slice(&self.buffer);
+            buffer.extend_from_slice(&self.read_file(self.file_offset, n_bytes_to_read)?);
+            self.file_offset += n_bytes_to_read;
+            self.buffer = buffer.freeze();
+        }
+
+        Ok(self.buffer.split_to(n_bytes))
+    }
+
+    fn read_file(&self, offset: usize, length: usize) -> Result<Vec<u8>, SimpleDbError> {
+        self.file.read(offset, length).map_err(SimpleDbError::CannotReadSnapshot)
+    }
+
+    fn current_offset(&self) -> usize {
+        self.file_offset - self.buffer.len()
+    }
+}
+
+fn decode_error(offset: usize, error_type: DecodeErrorType) -> SimpleDbError {
+    SimpleDbError::CannotDecodeSnapshot(DecodeError {
+        index: 0,
+        offset,
+        error_type,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::snapshot::{SnapshotEntry, SnapshotKeyspace, SnapshotReader, SnapshotWriter, SNAPSHOT_BUFFER_SIZE};
+    use bytes::Bytes;
+    use shared::{FileSystem, InMemoryFileSystem, SimpleDbFile, SimpleDbFileMode};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn write_read() {
+        let file_system: Arc<dyn FileSystem> = Arc::new(InMemoryFileSystem::create());
+        let path = Path::new("/snapshot");
+        //Bigger than the buffer, so it is read & written in multiple chunks
+        let big_value = Bytes::from(vec![1; SNAPSHOT_BUFFER_SIZE + 10]);
+        let entries = vec![
+            SnapshotEntry { key: Bytes::from("a"), value: Bytes::from("1"), expire_at: None },
+            SnapshotEntry { key: Bytes::from("b"), value: big_value.clone(), expire_at: None },
+            SnapshotEntry { key: Bytes::from("c"), value: Bytes::from(""), expire_at: Some(10) },
+        ];
+
+        let mut writer = SnapshotWriter::create(&file_system, path, 2).unwrap();
+        writer.start_keyspace(&SnapshotKeyspace { keyspace_id: 1, flags: 0, read_only: false }).unwrap();
+        writer.end_keyspace().unwrap();
+        writer.start_keyspace(&SnapshotKeyspace { keyspace_id: 3, flags: 2, read_only: true }).unwrap();
+        for entry in &entries {
+            writer.add_entry(entry).unwrap();
+        }
+        writer.end_keyspace().unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = SnapshotReader::open(&file_system, path).unwrap();
+        let keyspace = reader.next_keyspace().unwrap().unwrap();
+        assert_eq!((keyspace.keyspace_id, keyspace.flags, keyspace.read_only), (1, 0, false));
+        assert!(reader.next_entry().unwrap().is_none());
+        let keyspace = reader.next_keyspace().unwrap().unwrap();
+        assert_eq!((keyspace.keyspace_id, keyspace.flags, keyspace.read_only), (3, 2, true));
+        let mut entries_read = Vec::new();
+        while let Some(entry) = reader.next_entry().unwrap() {
+            entries_read.push(entry);
+        }
+        assert_eq!(entries_read, entries);
+        assert!(reader.next_keyspace().unwrap().is_none());
+
+        let encoded = SimpleDbFile::open_in(&file_system, path, SimpleDbFileMode::ReadOnly).unwrap()
+            .read_all()
+            .unwrap();
+        let mut corrupted = encoded.clone();
+        corrupted[5] ^= 1;
+        SimpleDbFile::create_in(&file_system, Path::new("/corrupted"), &corrupted, SimpleDbFileMode::RandomWrites).unwrap();
+        assert!(SnapshotReader::open(&file_system, Path::new("/corrupted")).is_err());
+        SimpleDbFile::create_in(&file_system, Path::new("/truncated"), &encoded[..2], SimpleDbFileMode::RandomWrites).unwrap();
+        assert!(SnapshotReader::open(&file_system, Path::new("/truncated")).is_err());
+    }
+}
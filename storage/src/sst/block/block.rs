@@ -1,12 +1,16 @@
 use crate::sst::block::block_decoder::decode_block;
 use crate::sst::block::block_encoder::encode_block;
 use crate::transactions::transaction::Transaction;
+use crate::utils::ttl;
 use bytes::{Bytes};
 use shared::key::Key;
 use std::sync::Arc;
 
 pub const PREFIX_COMPRESSED: u64 = 0x01;
 pub const NOT_COMPRESSED: u64 = 0x00;
+//Set in the flags of blocks whose keys are serialized with their flags. Blocks written before keys had flags
+//store deletes as empty values
+pub const BLOCK_KEYS_HAVE_FLAGS: u64 = 0x100;
 
 pub const BLOCK_FOOTER_LENGTH: usize =
     std::mem::size_of::<u16>() + //Nº Entries
@@ -40,9 +44,9 @@ impl Block {
         (inclusive && key.lt(&min_key)) || (inclusive && key.le(&min_key))
     }
 
-    pub fn get_value(&self, key_lookup: &Bytes, transaction: &Transaction) -> Option<Bytes> {
-        let (value, _) = self.binary_search_by_key_bytes(key_lookup, transaction);
-        value
+    pub fn get_value(&self, key_lookup: &Bytes, transaction: &Transaction) -> Option<(Key, Bytes)> {
+        let (entry, _) = self.binary_search_by_key_bytes(key_lookup, transaction);
+        entry
     }
 
     pub(crate) fn get_index(
@@ -75,13 +79,13 @@ impl Block {
     }
 
     //Does a binary search in the block to find an entry that has the same key and is readable by the transaction.
-    //Returns the value of the entry and the index in the block.
-    //The returned value is guaranteed to be readable by the transaction.
+    //Returns the entry and the index in the block.
+    //The returned entry is guaranteed to be readable by the transaction.
     pub(crate) fn binary_search_by_key_bytes(
         &self,
         key_lookup: &Bytes,
         transaction: &Transaction
-    ) -> (Option<Bytes>, usize) {
+    ) -> (Option<(Key, Bytes)>, usize) {
         let mut right = self.offsets.len();
         let mut left = 0;
 
@@ -107,14 +111,14 @@ impl Block {
     }
 
     //When doing a binary search we might find multiple versions exists for the same key,
-    //so we need to return the first one that is readable by the transaction.
-    //Returns the value found as an Option, and the index in the block
+    //so we need to return the newest one that is readable by the transaction.
+    //Returns the entry found as an Option, and the index in the block of the first version of the key
     fn get_value_in_multiple_key_versions(
         &self,
         transaction: &Transaction,
         key: &Bytes,
         index: usize
-    ) -> (Option<Bytes>, usize) {
+    ) -> (Option<(Key, Bytes)>, usize) {
         //We make current_index to point to the first version of a given key bytes. Example:
         //[(A, 1), (B, 1), (B, 2), (B, 3)], given key = B, index: 3, this would make current_index to
         //have value 1 (first entry of B)
@@ -122,21 +126,31 @@ impl Block {
         while current_index > 0 && self.get_key_by_index(current_index - 1).bytes_eq_bytes(key) {
             current_index = current_index - 1;
         }
+        let first_version_index = current_index;
 
-        //Now we search the first readable value by the transaction
-        while current_index < self.entries.len() {
+        //Now we search the last readable version by the transaction, versions are ordered by txn_id in ascending order
+        let mut newest_readable: Option<(Key, usize)> = None;
+        while current_index < self.offsets.len() {
             let current_key = self.get_key_by_index(current_index);
             if !current_key.bytes_eq_bytes(key) {
-                return (None, index);
+                break;
             }
-            if transaction.can_read(&current_key) {
-                return (Some(self.get_value_by_index(current_index)), current_index);
+            if transaction.can_read(&current_key) && !(current_key.has_ttl() && ttl::is_expired(&current_key, &self.get_value_by_index(current_index))) {
+                newest_readable = Some((current_key, current_index));
             }
 
             current_index += 1;
         }
 
-        (None, index)
+        match newest_readable {
+            Some((key, newest_index)) => (Some((key, self.get_value_by_index(newest_index))), first_version_index),
+            None => (None, index)
+        }
+    }
+
+    //Size of the decoded block in memory
+    pub fn size_bytes(&self) -> usize {
+        self.entries.len() + self.offsets.len() * std::mem::size_of::<u16>()
     }
 
     //Expect n_entry_index to be an index to block::offsets array
@@ -160,9 +174,9 @@ impl Block {
 
 #[cfg(test)]
 mod test {
-    use crate::sst::block::block::Block;
+    use crate::sst::block::block::{Block, NOT_COMPRESSED, PREFIX_COMPRESSED};
     use crate::sst::block::block_builder::BlockBuilder;
-    use bytes::Bytes;
+    use bytes::{BufMut, Bytes};
     use shared::key::Key;
     use std::sync::Arc;
 
@@ -198,4 +212,57 @@ mod test {
         assert_eq!(decoded_block_to_test.get_key_by_index(6).to_string(), String::from("Kia"));
         assert_eq!(decoded_block_to_test.get_value_by_index(6), vec![7]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn blocks_without_key_flags() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let entries: Vec<(&str, shared::TxnId, &[u8])> = vec![("Jaime", 1, &[1]), ("Javier", 2, &[]), ("Jose", 3, &[3])];
+
+        for prefix_compressed in [false, true] {
+            let encoded = encode_block_without_key_flags(&entries, prefix_compressed, &options);
+            let block = Block::deserialize(&encoded, &options).unwrap();
+
+            for (index, (key, txn_id, value)) in entries.iter().enumerate() {
+                assert_eq!(block.get_key_by_index(index), Key::create_from_str(key, *txn_id));
+                assert_eq!(block.get_value_by_index(index), Bytes::copy_from_slice(value));
+            }
+            //Deletes were stored as empty values
+            assert!(!block.get_key_by_index(0).is_tombstone());
+            assert!(block.get_key_by_index(1).is_tombstone());
+            assert!(!block.get_key_by_index(2).is_tombstone());
+        }
+    }
+
+    //Layout of blocks written before keys had flags
+    fn encode_block_without_key_flags(
+        entries: &[(&str, shared::TxnId, &[u8])],
+        prefix_compressed: bool,
+        options: &Arc<shared::SimpleDbOptions>
+    ) -> Vec<u8> {
+        let mut encoded: Vec<u8> = Vec::new();
+        let mut offsets: Vec<u16> = Vec::new();
+        for (key, txn_id, value) in entries {
+            offsets.push(encoded.len() as u16);
+            if prefix_compressed {
+                encoded.put_u16_le(0);
+                encoded.put_u16_le(key.len() as u16);
+                encoded.put_u64_le(*txn_id as u64);
+            } else {
+                encoded.put_u64_le(*txn_id as u64);
+                encoded.put_u16_le(key.len() as u16);
+            }
+            encoded.extend(key.as_bytes());
+            encoded.put_u16_le(value.len() as u16);
+            encoded.extend(*value);
+        }
+        let offsets_offset = encoded.len();
+        encoded.extend(shared::u16_vec_to_u8_vec(&offsets));
+
+        let flags = if prefix_compressed { PREFIX_COMPRESSED } else { NOT_COMPRESSED };
+        encoded.resize(options.block_size_bytes, 0);
+        shared::u64_to_u8_le(flags, options.block_size_bytes - 12, &mut encoded);
+        shared::u16_to_u8_le(entries.len() as u16, options.block_size_bytes - 4, &mut encoded);
+        shared::u16_to_u8_le(offsets_offset as u16, options.block_size_bytes - 2, &mut encoded);
+        encoded
+    }
+}
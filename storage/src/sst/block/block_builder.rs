@@ -60,6 +60,7 @@ impl BlockBuilder {
     fn calculate_entry_size(&self, key: &Key, value: &Bytes) -> usize {
         std::mem::size_of::<i16>() + //Key length size
             std::mem::size_of::<u64>() + //Key txn id
+            std::mem::size_of::<u8>() + //Key flags
             key.len() + //Key bytes
             std::mem::size_of::<i16>() + //Value length
             value.len() + //Value bytes
@@ -70,6 +71,7 @@ impl BlockBuilder {
 #[cfg(test)]
 mod test {
     use std::sync::Arc;
+    use crate::sst::block::block::Block;
     use crate::sst::block::block_builder::BlockBuilder;
     use bytes::Bytes;
     use shared::key::Key;
@@ -87,4 +89,23 @@ mod test {
         assert_eq!(block.get_value_by_index(1), vec![4, 5, 6]);
         assert_eq!(block.get_key_by_index(1).to_string(), String::from("Pedro"));
     }
+
+    #[test]
+    fn full_block_serialize_deserialize() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let mut block_builder = BlockBuilder::create(options.clone());
+        let mut n_entries = 0;
+        while block_builder.add_entry(Key::create_from_str(&format!("{:04}", n_entries), 1), Bytes::from(vec![1; 10])).is_ok() {
+            n_entries += 1;
+        }
+
+        let serialized = block_builder.build().serialize(&options);
+        assert_eq!(serialized.len(), options.block_size_bytes);
+
+        let block = Block::deserialize(&serialized, &options).unwrap();
+        for i in 0..n_entries {
+            assert_eq!(block.get_key_by_index(i).to_string(), format!("{:04}", i));
+            assert_eq!(block.get_value_by_index(i), vec![1; 10]);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,42 @@
+use shared::Compression;
+
+//Codec ids stored in the SSTable footer flags
+const NONE_CODEC_ID: u8 = 0;
+const LZ4_CODEC_ID: u8 = 1;
+const SNAPPY_CODEC_ID: u8 = 2;
+
+pub(crate) fn codec_id(compression: Compression) -> u8 {
+    match compression {
+        Compression::None => NONE_CODEC_ID,
+        Compression::Lz4 => LZ4_CODEC_ID,
+        Compression::Snappy => SNAPPY_CODEC_ID,
+    }
+}
+
+pub(crate) fn codec_from_id(codec_id: u8) -> Result<Compression, shared::DecodeErrorType> {
+    match codec_id {
+        NONE_CODEC_ID => Ok(Compression::None),
+        LZ4_CODEC_ID => Ok(Compression::Lz4),
+        SNAPPY_CODEC_ID => Ok(Compression::Snappy),
+        _ => Err(shared::DecodeErrorType::UnknownFlag(codec_id as usize)),
+    }
+}
+
+pub(crate) fn compress(compression: Compression, encoded_block: Vec<u8>) -> Vec<u8> {
+    match compression {
+        Compression::None => encoded_block,
+        Compression::Lz4 => lz4_flex::compress_prepend_size(&encoded_block),
+        Compression::Snappy => snap::raw::Encoder::new().compress_vec(&encoded_block)
+            .expect("Blocks are smaller than the max snappy input size"),
+    }
+}
+
+pub(crate) fn decompress(compression: Compression, compressed_block: Vec<u8>) -> Result<Vec<u8>, shared::DecodeErrorType> {
+    match compression {
+        Compression::None => Ok(compressed_block),
+        Compression::Lz4 => lz4_flex::decompress_size_prepended(&compressed_block)
+            .map_err(|error| shared::DecodeErrorType::Decompression(error.to_string())),
+        Compression::Snappy => snap::raw::Decoder::new().decompress_vec(&compressed_block)
+            .map_err(|error| shared::DecodeErrorType::Decompression(error.to_string())),
+    }
+}
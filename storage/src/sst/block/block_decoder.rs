@@ -1,6 +1,6 @@
 use std::sync::Arc;
 use bytes::{BufMut, Bytes};
-use block::{NOT_COMPRESSED, PREFIX_COMPRESSED};
+use block::{BLOCK_KEYS_HAVE_FLAGS, NOT_COMPRESSED, PREFIX_COMPRESSED};
 use shared::key::Key;
 use crate::sst::block::block;
 use crate::sst::block::block::Block;
@@ -17,9 +17,11 @@ pub(crate) fn decode_block(
     let offsets_offset: u16 = shared::u8_vec_to_u16_le(&encoded, options.block_size_bytes - 2);
     let n_entries: u16 = shared::u8_vec_to_u16_le(&encoded, options.block_size_bytes - 4);
     let offsets = decode_offsets(encoded, offsets_offset, n_entries);
-    let (entries, new_offsets) = match flag {
-        PREFIX_COMPRESSED => Ok(decode_entries_prefix_compressed(encoded, &offsets)),
-        NOT_COMPRESSED => Ok((decode_entries_not_compressed(encoded, offsets_offset), offsets)),
+    let keys_have_flags = flag & BLOCK_KEYS_HAVE_FLAGS != 0;
+    let (entries, new_offsets) = match flag & !BLOCK_KEYS_HAVE_FLAGS {
+        PREFIX_COMPRESSED => Ok(decode_entries_prefix_compressed(encoded, &offsets, keys_have_flags)),
+        NOT_COMPRESSED if keys_have_flags => Ok((decode_entries_not_compressed(encoded, offsets_offset), offsets)),
+        NOT_COMPRESSED => Ok(decode_entries_not_compressed_without_key_flags(encoded, &offsets)),
         _ => Err(shared::DecodeErrorType::UnknownFlag(flag as usize)),
     }?;
 
@@ -40,6 +42,7 @@ fn decode_offsets(
 fn decode_entries_prefix_compressed(
     encoded: &Vec<u8>,
     offsets: &Vec<u16>,
+    keys_have_flags: bool,
 ) -> (Vec<u8>, Vec<u16>) {
     let mut entries_decoded: Vec<u8> = Vec::new();
     let mut prev_key: Option<Key> = None;
@@ -56,7 +59,15 @@ fn decode_entries_prefix_compressed(
         current_index = current_index + 2;
         let key_txn_id = shared::u8_vec_to_u64_le(encoded, current_index) as shared::TxnId;
         current_index = current_index + 8;
+        let key_flags = if keys_have_flags {
+            current_index += 1;
+            Some(encoded[current_index - 1])
+        } else {
+            None
+        };
         let rest_key_u8_vec = encoded[current_index..(current_index + rest_key_size as usize)].to_vec();
+        current_index = current_index + rest_key_size as usize;
+        let value_size = shared::u8_vec_to_u16_le(encoded, current_index);
 
         let current_key = match prev_key.as_ref() {
             Some(prev_key) => {
@@ -66,12 +77,14 @@ fn decode_entries_prefix_compressed(
             },
             None => Key::create(Bytes::from(rest_key_u8_vec), key_txn_id)
         };
-        current_index = current_index + rest_key_size as usize;
+        let current_key = match key_flags {
+            Some(key_flags) => current_key.with_flags(key_flags),
+            None => current_key.with_tombstone(value_size == 0),
+        };
         entries_decoded.extend(current_key.serialize());
         prev_key = Some(current_key);
 
         //Decode value
-        let value_size = shared::u8_vec_to_u16_le(encoded, current_index);
         current_index = current_index + 2;
         let value = &encoded[current_index..(current_index + value_size as usize)];
         entries_decoded.put_u16_le(value_size);
@@ -89,4 +102,33 @@ fn decode_entries_not_compressed(
     let end_index = offsets_offset as usize;
 
     encoded[start_index..=end_index].to_vec()
-}
\ No newline at end of file
+}
+
+//Entries are serialized keys without flags, followed by the value length (u16) & the value.
+//They are decoded into the layout of Block::entries
+fn decode_entries_not_compressed_without_key_flags(
+    encoded: &Vec<u8>,
+    offsets: &Vec<u16>,
+) -> (Vec<u8>, Vec<u16>) {
+    let mut entries_decoded: Vec<u8> = Vec::new();
+    let mut new_offsets: Vec<u16> = Vec::new();
+
+    for current_offset in offsets {
+        let mut current_index: usize = *current_offset as usize;
+        new_offsets.push(entries_decoded.len() as u16);
+
+        let mut key_ptr = &encoded[current_index..];
+        let key = Key::deserialize_without_flags(&mut key_ptr);
+        current_index = encoded.len() - key_ptr.len();
+        let value_size = shared::u8_vec_to_u16_le(encoded, current_index);
+        current_index += 2;
+        let value = &encoded[current_index..(current_index + value_size as usize)];
+
+        let key = key.with_tombstone(value_size == 0);
+        entries_decoded.extend(key.serialize());
+        entries_decoded.put_u16_le(value_size);
+        entries_decoded.extend(value);
+    }
+
+    (entries_decoded, new_offsets)
+}
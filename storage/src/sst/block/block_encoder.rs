@@ -1,4 +1,4 @@
-use crate::sst::block::block::{Block, BLOCK_FOOTER_LENGTH, NOT_COMPRESSED, PREFIX_COMPRESSED};
+use crate::sst::block::block::{Block, BLOCK_FOOTER_LENGTH, BLOCK_KEYS_HAVE_FLAGS, NOT_COMPRESSED, PREFIX_COMPRESSED};
 use bytes::BufMut;
 use std::sync::Arc;
 use shared::key::Key;
@@ -59,6 +59,7 @@ fn encode_prefix_compressed_entries(
                 encoded.put_u16_le(key_overlap_size as u16);
                 encoded.put_u16_le(rest_key_size as u16);
                 encoded.put_u64_le(current_key.txn_id() as u64);
+                encoded.put_u8(current_key.flags());
                 if !rest_key.is_empty() {
                     encoded.extend(rest_key.as_bytes());
                 }
@@ -71,6 +72,7 @@ fn encode_prefix_compressed_entries(
                 encoded.put_u16_le(0);
                 encoded.put_u16_le(current_key.len() as u16);
                 encoded.put_u64_le(current_key.txn_id() as u64);
+                encoded.put_u8(current_key.flags());
                 encoded.extend(current_key.as_bytes());
                 //Value
                 encoded.put_u16_le(current_value.len() as u16);
@@ -99,7 +101,7 @@ fn encode_footer(
     options: &Arc<shared::SimpleDbOptions>
 ) {
     let n_entries: u16 = block.offsets.len() as u16;
-    shared::u64_to_u8_le(flags, options.block_size_bytes - 12, encoded);
+    shared::u64_to_u8_le(flags | BLOCK_KEYS_HAVE_FLAGS, options.block_size_bytes - 12, encoded);
     shared::u16_to_u8_le(n_entries, options.block_size_bytes - 4, encoded);
     shared::u16_to_u8_le(start_offsets_offset as u16, options.block_size_bytes - 2, encoded);
 }
\ No newline at end of file
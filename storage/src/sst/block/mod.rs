@@ -3,3 +3,4 @@ pub mod block;
 pub mod block_iterator;
 mod block_encoder;
 mod block_decoder;
+pub(crate) mod block_compression;
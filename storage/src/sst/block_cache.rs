@@ -1,129 +1,208 @@
-use std::cmp::{max, min};
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
 use crate::sst::block::block::Block;
 
+//LRU cache of the blocks read from disk, shared by the SSTables of all keyspaces. It is bounded by the size
+//of the cached blocks, so SSTables that are read often can use the space that idle SSTables don't use
 pub struct BlockCache {
-    entries: Vec<Option<BlockCacheEntry>>,
+    max_size_bytes: usize,
+    state: Mutex<BlockCacheState>,
+}
+
+struct BlockCacheState {
+    entries: HashMap<BlockCacheKey, BlockCacheEntry>,
+    //Entries by their last access. The first one is the least recently used
+    lru: BTreeMap<u64, BlockCacheKey>,
+    next_access: u64,
+    size_bytes: usize,
+}
+
+//SSTable ids are unique per keyspace
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+struct BlockCacheKey {
+    keyspace_id: shared::KeyspaceId,
+    sstable_id: shared::SSTableId,
+    block_id: usize,
 }
 
 struct BlockCacheEntry {
     block: Arc<Block>,
-    block_id: usize,
-    touched: i8,
+    size_bytes: usize,
+    last_access: u64,
 }
 
 impl BlockCache {
-    pub fn create(options: Arc<shared::SimpleDbOptions>) -> BlockCache {
-        let mut entries: Vec<Option<BlockCacheEntry>> = Vec::with_capacity(options.n_cached_blocks_per_sstable);
-        for _ in 0..options.n_cached_blocks_per_sstable {
-            entries.push(None);
+    pub fn create(options: &Arc<shared::SimpleDbOptions>) -> BlockCache {
+        BlockCache {
+            max_size_bytes: options.block_cache_size_bytes,
+            state: Mutex::new(BlockCacheState {
+                entries: HashMap::new(),
+                lru: BTreeMap::new(),
+                next_access: 0,
+                size_bytes: 0,
+            }),
         }
-
-        BlockCache { entries }
-    }
-
-    pub fn put(&mut self, block_id: usize, block: Arc<Block>) {
-        let index = self.get_index_to_put(block_id);
-
-        self.entries[index] = Some(BlockCacheEntry {
-            touched: 1,
-            block,
-            block_id,
-        });
     }
 
-    pub fn get(&mut self, block_id: usize) -> Option<Arc<Block>> {
-        let mut current_index = self.get_index(block_id);
-
-        for _ in 0..self.get_n_retries_read_cache() {
-            let cache_entry: &mut Option<BlockCacheEntry> = &mut self.entries[current_index];
-
-            if cache_entry.is_some() {
-                let cache_entry = cache_entry.as_mut().unwrap();
-                if cache_entry.block_id == block_id {
-                    cache_entry.increase_touched();
-                    return Some(cache_entry.block.clone());
-                } else {
-                    cache_entry.decrease_touched();
-                }
-            }
-
-            current_index = self.next_index(current_index);
+    //Blocks bigger than the cache are not cached
+    pub fn put(
+        &self,
+        keyspace_id: shared::KeyspaceId,
+        sstable_id: shared::SSTableId,
+        block_id: usize,
+        block: Arc<Block>
+    ) {
+        let size_bytes = block.size_bytes();
+        if size_bytes > self.max_size_bytes {
+            return;
         }
 
-        None
-    }
-
-    fn get_index_to_put(&mut self, block_id: usize) -> usize {
-        let mut current_index = self.get_index(block_id);
+        let key = BlockCacheKey { keyspace_id, sstable_id, block_id };
+        let mut state = self.state.lock().unwrap();
+        state.remove(&key);
 
-        for _ in 0..self.get_n_retries_read_cache() {
-            let current_entry = &mut self.entries[current_index];
-
-            if current_entry.is_none() || current_entry.as_ref().unwrap().touched == 0 {
-                return current_index;
-            } else {
-                let current_entry = current_entry.as_mut().unwrap();
-                current_entry.decrease_touched();
-            }
-
-            current_index = self.next_index(current_index);
+        while state.size_bytes + size_bytes > self.max_size_bytes {
+            let (_, lru_key) = state.lru.pop_first().unwrap();
+            state.remove(&lru_key);
         }
 
-        self.get_index(block_id)
+        let last_access = state.next_access();
+        state.lru.insert(last_access, key);
+        state.size_bytes += size_bytes;
+        state.entries.insert(key, BlockCacheEntry { block, size_bytes, last_access });
     }
 
-    fn next_index(&self, current_index: usize) -> usize {
-        let next_index = current_index + 1;
-        if self.entries.len() >= next_index {
-            0
-        } else {
-            next_index
-        }
+    pub fn get(
+        &self,
+        keyspace_id: shared::KeyspaceId,
+        sstable_id: shared::SSTableId,
+        block_id: usize,
+    ) -> Option<Arc<Block>> {
+        let key = BlockCacheKey { keyspace_id, sstable_id, block_id };
+        let mut state = self.state.lock().unwrap();
+        let last_access = state.next_access();
+        let entry = state.entries.get_mut(&key)?;
+        let prev_last_access = std::mem::replace(&mut entry.last_access, last_access);
+        let block = entry.block.clone();
+
+        state.lru.remove(&prev_last_access);
+        state.lru.insert(last_access, key);
+
+        Some(block)
     }
 
-    fn get_n_retries_read_cache(&self) -> usize {
-        self.entries.len() / 2
+    //Called when the SSTable is deleted
+    pub fn remove_sstable(&self, keyspace_id: shared::KeyspaceId, sstable_id: shared::SSTableId) {
+        let mut state = self.state.lock().unwrap();
+        let keys_to_remove: Vec<BlockCacheKey> = state.entries.keys()
+            .filter(|key| key.keyspace_id == keyspace_id && key.sstable_id == sstable_id)
+            .copied()
+            .collect();
+
+        for key in keys_to_remove {
+            state.remove(&key);
+        }
     }
 
-    fn get_index(&self, block_id: usize) -> usize {
-        block_id & (self.entries.len() - 1)
+    pub fn size_bytes(&self) -> usize {
+        self.state.lock().unwrap().size_bytes
     }
 }
 
-impl BlockCacheEntry {
-    pub fn increase_touched(&mut self) {
-        self.touched = min(self.touched + 1, 2);
+impl BlockCacheState {
+    fn remove(&mut self, key: &BlockCacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.last_access);
+            self.size_bytes -= entry.size_bytes;
+        }
     }
 
-    pub fn decrease_touched(&mut self) {
-        self.touched = max(self.touched - 1, 0);
+    fn next_access(&mut self) -> u64 {
+        self.next_access += 1;
+        self.next_access
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::sst::block::block::Block;
     use crate::sst::block::block_builder::BlockBuilder;
     use crate::sst::block_cache::BlockCache;
+    use bytes::Bytes;
+    use shared::key::Key;
     use std::sync::Arc;
 
     #[test]
     fn put_get() {
-        let block1 = Arc::new(BlockBuilder::create(Arc::new(shared::SimpleDbOptions::default())).build());
-        let block2 = Arc::new(BlockBuilder::create(Arc::new(shared::SimpleDbOptions::default())).build());
-        let block3 = Arc::new(BlockBuilder::create(Arc::new(shared::SimpleDbOptions::default())).build());
-        let mut cache = BlockCache::create(Arc::new(shared::SimpleDbOptions::default()));
-
-        cache.put(1, block1);
-        cache.put(2, block2);
-        cache.put(3, block3);
-
-        assert!(cache.get(1).is_some());
-        assert!(cache.get(2).is_some());
-        assert!(cache.get(3).is_some());
-        assert!(cache.get(1).is_some());
-        assert!(cache.get(2).is_some());
-        assert!(cache.get(3).is_some());
-        assert!(cache.get(4).is_none());
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let cache = BlockCache::create(&options);
+
+        cache.put(0, 1, 0, create_block(&options, 1));
+        cache.put(0, 1, 1, create_block(&options, 2));
+        cache.put(1, 1, 0, create_block(&options, 3));
+
+        assert!(cache.get(0, 1, 0).is_some());
+        assert!(cache.get(0, 1, 1).is_some());
+        assert!(cache.get(1, 1, 0).is_some());
+        assert!(cache.get(0, 2, 0).is_none());
+        assert!(cache.get(0, 1, 2).is_none());
+
+        cache.remove_sstable(0, 1);
+        assert!(cache.get(0, 1, 0).is_none());
+        assert!(cache.get(0, 1, 1).is_none());
+        assert!(cache.get(1, 1, 0).is_some());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let block_size = create_block(&Arc::new(shared::SimpleDbOptions::default()), 0).size_bytes();
+        let options = shared::start_simpledb_options_builder()
+            .block_cache_size_bytes(block_size * 4)
+            .build_arc();
+        let cache = BlockCache::create(&options);
+
+        //Blocks of many SSTables, from different keyspaces
+        for sstable_id in 0..4 {
+            cache.put(sstable_id % 2, sstable_id, 0, create_block(&options, sstable_id));
+        }
+        //SSTable 0 block becomes the most recently used
+        assert!(cache.get(0, 0, 0).is_some());
+
+        for sstable_id in 4..6 {
+            cache.put(sstable_id % 2, sstable_id, 0, create_block(&options, sstable_id));
+            assert!(cache.size_bytes() <= block_size * 4);
+        }
+
+        assert!(cache.get(0, 0, 0).is_some());
+        assert!(cache.get(1, 1, 0).is_none());
+        assert!(cache.get(0, 2, 0).is_none());
+        assert!(cache.get(1, 3, 0).is_some());
+        assert!(cache.get(0, 4, 0).is_some());
+        assert!(cache.get(1, 5, 0).is_some());
+
+        for sstable_id in 6..100 {
+            cache.put(sstable_id % 2, sstable_id, 0, create_block(&options, sstable_id));
+            assert!(cache.size_bytes() <= block_size * 4);
+        }
+        assert_eq!(cache.size_bytes(), block_size * 4);
+    }
+
+    #[test]
+    fn blocks_bigger_than_cache_are_not_cached() {
+        let options = shared::start_simpledb_options_builder()
+            .block_cache_size_bytes(0)
+            .build_arc();
+        let cache = BlockCache::create(&options);
+
+        cache.put(0, 1, 0, create_block(&options, 1));
+
+        assert!(cache.get(0, 1, 0).is_none());
+        assert_eq!(cache.size_bytes(), 0);
+    }
+
+    fn create_block(options: &Arc<shared::SimpleDbOptions>, key: usize) -> Arc<Block> {
+        let mut block_builder = BlockBuilder::create(options.clone());
+        let _ = block_builder.add_entry(Key::create_from_str(&format!("{:04}", key), 1), Bytes::from(vec![0u8; 16]));
+        Arc::new(block_builder.build())
+    }
+}
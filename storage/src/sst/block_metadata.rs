@@ -5,6 +5,9 @@ use crate::transactions::transaction::{Transaction};
 #[derive(Eq, PartialEq)]
 pub struct BlockMetadata {
     pub(crate) offset: usize,
+    //Length of the encoded block without its crc. Only stored in SSTables with page aligned blocks, where blocks
+    //are followed by padding. Otherwise, it is calculated from the offset of the next block
+    pub(crate) length: Option<usize>,
     pub(crate) first_key: Key,
     pub(crate) last_key: Key
 }
@@ -13,6 +16,7 @@ impl BlockMetadata {
     pub fn decode_all(
         bytes: &Vec<u8>,
         start_index: usize,
+        has_lengths: bool,
     ) -> Result<Vec<BlockMetadata>, shared::DecodeErrorType> {
         let expected_crc = shared::u8_vec_to_u32_le(bytes, start_index);
         let n_blocks_metadata = shared::u8_vec_to_u32_le(bytes, start_index + 4);
@@ -21,7 +25,7 @@ impl BlockMetadata {
         let start_content_index = last_index;
         let mut blocks_metadata_decoded: Vec<BlockMetadata> = Vec::with_capacity(n_blocks_metadata as usize);
         for _ in 0..n_blocks_metadata {
-            let (new_last_index, blockmetadata_decoded) = Self::decode(&bytes, last_index)?;
+            let (new_last_index, blockmetadata_decoded) = Self::decode(&bytes, last_index, has_lengths)?;
 
             last_index = new_last_index;
             blocks_metadata_decoded.push(blockmetadata_decoded);
@@ -49,7 +53,7 @@ impl BlockMetadata {
         encoded
     }
 
-    pub fn decode(bytes: &Vec<u8>, start_index: usize) -> Result<(usize, BlockMetadata), shared::DecodeErrorType> {
+    pub fn decode(bytes: &Vec<u8>, start_index: usize, has_length: bool) -> Result<(usize, BlockMetadata), shared::DecodeErrorType> {
         let mut current_index = start_index;
 
         let first_key_length = shared::u8_vec_to_u32_le(&bytes, current_index) as usize;
@@ -70,10 +74,17 @@ impl BlockMetadata {
         let offset = shared::u8_vec_to_u32_le(&bytes, current_index) as usize;
         current_index = current_index + 4;
 
+        let mut length = None;
+        if has_length {
+            length = Some(shared::u8_vec_to_u32_le(bytes, current_index) as usize);
+            current_index += 4;
+        }
+
         Ok((current_index, BlockMetadata{
             first_key: Key::create(first_key, first_key_txn_id),
             last_key: Key::create(last_key, last_key_txn_id),
-            offset
+            offset,
+            length,
         }))
     }
 
@@ -89,11 +100,14 @@ impl BlockMetadata {
         metadata_encoded.put_u64_le(self.last_key.txn_id() as u64);
         metadata_encoded.extend(self.last_key.as_bytes());
         metadata_encoded.put_u32_le(self.offset as u32);
+        if let Some(length) = self.length {
+            metadata_encoded.put_u32_le(length as u32);
+        }
         metadata_encoded
     }
 
     pub fn contains(&self, key: &Bytes, transaction: &Transaction) -> bool {
-        let key_to_be_checked = Key::create(key.clone(), transaction.txn_id);
+        let key_to_be_checked = Key::create(key.clone(), transaction.max_readable_txn_id());
         self.first_key.le(&key_to_be_checked) && self.last_key.ge(&key_to_be_checked)
     }
 }
@@ -102,6 +116,7 @@ impl Clone for BlockMetadata {
     fn clone(&self) -> Self {
         BlockMetadata{
             offset: self.offset,
+            length: self.length,
             first_key: self.first_key.clone(),
             last_key: self.last_key.clone(),
         }
@@ -116,13 +131,13 @@ mod test {
     #[test]
     fn encode_decode() {
         let metadata = vec![
-            BlockMetadata{offset: 0, first_key: Key::create_from_str("a", 1), last_key: Key::create_from_str("b", 1)},
-            BlockMetadata{offset: 1, first_key: Key::create_from_str("b", 1), last_key: Key::create_from_str("c", 1)},
-            BlockMetadata{offset: 2, first_key: Key::create_from_str("c", 1), last_key: Key::create_from_str("d", 1)},
-            BlockMetadata{offset: 3, first_key: Key::create_from_str("d", 1), last_key: Key::create_from_str("z", 1)},
+            BlockMetadata{offset: 0, length: None, first_key: Key::create_from_str("a", 1), last_key: Key::create_from_str("b", 1)},
+            BlockMetadata{offset: 1, length: None, first_key: Key::create_from_str("b", 1), last_key: Key::create_from_str("c", 1)},
+            BlockMetadata{offset: 2, length: None, first_key: Key::create_from_str("c", 1), last_key: Key::create_from_str("d", 1)},
+            BlockMetadata{offset: 3, length: None, first_key: Key::create_from_str("d", 1), last_key: Key::create_from_str("z", 1)},
         ];
         let encoded = BlockMetadata::encode_all(&metadata);
-        let decoded = BlockMetadata::decode_all(&encoded, 0);
+        let decoded = BlockMetadata::decode_all(&encoded, 0, false);
 
         assert!(decoded.is_ok());
         let decoded = decoded.unwrap();
@@ -1,8 +1,11 @@
 pub mod sstable_builder;
 pub mod sstable;
+pub mod sstable_meta;
 pub mod ssttable_iterator;
-mod block_cache;
+pub(crate) mod block_cache;
 pub mod sstables;
-mod sstables_files;
+pub(crate) mod sstables_files;
 mod block_metadata;
-mod block;
+pub(crate) mod block;
+#[cfg(feature = "object-store")]
+pub mod object_store_file_system;
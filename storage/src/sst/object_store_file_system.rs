@@ -0,0 +1,343 @@
+use crate::sst::sstables_files::is_sstable_file;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutPayload};
+use shared::{FileHandle, FileSystem, SimpleDbFileMode};
+use std::future::Future;
+use std::io::{Error, ErrorKind};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Runtime;
+
+//Stores SSTables in an object store (like S3), the rest of the files (WAL, manifest etc.) are stored in the local file system.
+//SSTables are immutable, so every SSTable file is kept in memory while it is being written,
+//and it is uploaded as a single object when it is fsynced.
+//Blocks are read with range requests, so they go through the SSTable block cache like with local files.
+//Object store calls are blocking, so this shouldn't be used from inside a tokio runtime.
+pub struct ObjectStoreFileSystem {
+    object_store: Arc<dyn ObjectStore>,
+    local: Arc<dyn FileSystem>,
+    runtime: Arc<Runtime>,
+}
+
+struct ObjectStoreFileHandle {
+    object_store: Arc<dyn ObjectStore>,
+    runtime: Arc<Runtime>,
+    location: ObjectPath,
+    append: bool,
+    //Contents of the object after being written, None if it hasn't been written since it was opened
+    written: Option<Vec<u8>>,
+    position: Mutex<usize>,
+}
+
+impl ObjectStoreFileSystem {
+    pub fn create(
+        object_store: Arc<dyn ObjectStore>,
+        local: Arc<dyn FileSystem>
+    ) -> Result<ObjectStoreFileSystem, Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(ObjectStoreFileSystem {
+            runtime: Arc::new(runtime),
+            object_store,
+            local,
+        })
+    }
+
+    fn is_remote(path: &Path) -> bool {
+        is_sstable_file(path)
+    }
+
+    fn object_exists(&self, location: &ObjectPath) -> Result<bool, Error> {
+        match block_on(&self.runtime, self.object_store.head(location)) {
+            Ok(_) => Ok(true),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn list_objects(&self, directory: &Path) -> Result<Vec<ObjectPath>, Error> {
+        let result = block_on(&self.runtime, self.object_store.list_with_delimiter(Some(&to_object_path(directory))))?;
+        Ok(result.objects.into_iter()
+            .map(|object| object.location)
+            .collect())
+    }
+}
+
+impl FileSystem for ObjectStoreFileSystem {
+    fn open(&self, path: &Path, mode: &SimpleDbFileMode) -> Result<Box<dyn FileHandle>, Error> {
+        if !Self::is_remote(path) {
+            return self.local.open(path, mode);
+        }
+
+        let location = to_object_path(path);
+        if matches!(mode, SimpleDbFileMode::ReadOnly) && !self.object_exists(&location)? {
+            return Err(Error::new(ErrorKind::NotFound, format!("{} not found", location)));
+        }
+
+        Ok(Box::new(ObjectStoreFileHandle {
+            object_store: self.object_store.clone(),
+            runtime: self.runtime.clone(),
+            append: matches!(mode, SimpleDbFileMode::AppendOnly),
+            position: Mutex::new(0),
+            written: None,
+            location,
+        }))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        if Self::is_remote(path) {
+            self.object_exists(&to_object_path(path)).unwrap_or(false)
+        } else {
+            self.local.exists(path)
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.local.is_dir(path)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        match (Self::is_remote(from), Self::is_remote(to)) {
+            (true, true) => block_on(&self.runtime, self.object_store.copy(&to_object_path(from), &to_object_path(to))),
+            (false, false) => self.local.copy(from, to),
+            _ => Err(Error::new(ErrorKind::Unsupported, "Cannot copy between local file system & object store")),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        if self.local.is_dir(from) {
+            //SSTables stored in the directory are moved as well
+            for object in self.list_objects(from)? {
+                let to_object = to_object_path(to.join(object.filename().unwrap()).as_path());
+                block_on(&self.runtime, self.object_store.rename(&object, &to_object))?;
+            }
+            return self.local.rename(from, to);
+        }
+
+        match (Self::is_remote(from), Self::is_remote(to)) {
+            (true, true) => block_on(&self.runtime, self.object_store.rename(&to_object_path(from), &to_object_path(to))),
+            (false, false) => self.local.rename(from, to),
+            _ => Err(Error::new(ErrorKind::Unsupported, "Cannot rename between local file system & object store")),
+        }
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), Error> {
+        if Self::is_remote(path) {
+            block_on(&self.runtime, self.object_store.delete(&to_object_path(path)))
+        } else {
+            self.local.remove_file(path)
+        }
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), Error> {
+        self.local.create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), Error> {
+        self.local.create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        for object in self.list_objects(path)? {
+            block_on(&self.runtime, self.object_store.delete(&object))?;
+        }
+        self.local.remove_dir_all(path)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut paths = self.local.list(path)?;
+        for object in self.list_objects(path)? {
+            paths.push(path.join(object.filename().unwrap()));
+        }
+        Ok(paths)
+    }
+}
+
+impl ObjectStoreFileHandle {
+    fn read_object(&self) -> Result<Vec<u8>, Error> {
+        match &self.written {
+            Some(written) => Ok(written.clone()),
+            None => {
+                let result = block_on(&self.runtime, self.object_store.get(&self.location))?;
+                let bytes = block_on(&self.runtime, result.bytes())?;
+                Ok(bytes.to_vec())
+            }
+        }
+    }
+
+    //Writes are done over a local copy of the object
+    fn written(&mut self) -> Result<&mut Vec<u8>, Error> {
+        if self.written.is_none() {
+            let contents = match self.read_object() {
+                Ok(contents) => contents,
+                Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+                Err(error) => return Err(error),
+            };
+            self.written = Some(contents);
+        }
+        Ok(self.written.as_mut().unwrap())
+    }
+}
+
+impl FileHandle for ObjectStoreFileHandle {
+    fn read_to_end(&self, buff: &mut Vec<u8>) -> Result<usize, Error> {
+        let contents = self.read_object()?;
+        let mut position = self.position.lock().unwrap();
+        let start = (*position).min(contents.len());
+        buff.extend_from_slice(&contents[start..]);
+        *position = contents.len();
+        Ok(contents.len() - start)
+    }
+
+    fn read_at(&self, buff: &mut [u8], offset: u64) -> Result<usize, Error> {
+        if let Some(written) = &self.written {
+            let start = (offset as usize).min(written.len());
+            let n_bytes = buff.len().min(written.len() - start);
+            buff[..n_bytes].copy_from_slice(&written[start..start + n_bytes]);
+            return Ok(n_bytes);
+        }
+
+        let size = self.size()?;
+        let end = (offset + buff.len() as u64).min(size);
+        if offset >= end {
+            return Ok(0);
+        }
+        let bytes = block_on(&self.runtime, self.object_store.get_range(&self.location, offset..end))?;
+        buff[..bytes.len()].copy_from_slice(&bytes);
+        Ok(bytes.len())
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let append = self.append;
+        let mut position = *self.position.get_mut().unwrap();
+        let written = self.written()?;
+        if append {
+            position = written.len();
+        }
+        let end = position + bytes.len();
+        if end > written.len() {
+            written.resize(end, 0);
+        }
+        written[position..end].copy_from_slice(bytes);
+        *self.position.get_mut().unwrap() = end;
+        Ok(())
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), Error> {
+        self.written()?.resize(size as usize, 0);
+        Ok(())
+    }
+
+    fn sync_all(&self) -> Result<(), Error> {
+        match &self.written {
+            Some(written) => block_on(
+                &self.runtime,
+                self.object_store.put(&self.location, PutPayload::from(written.clone()))
+            ).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        match &self.written {
+            Some(written) => Ok(written.len() as u64),
+            None => match block_on(&self.runtime, self.object_store.head(&self.location)) {
+                Ok(metadata) => Ok(metadata.size),
+                //Opened to be written, but it hasn't been uploaded yet
+                Err(error) if error.kind() == ErrorKind::NotFound => Ok(0),
+                Err(error) => Err(error),
+            }
+        }
+    }
+}
+
+fn block_on<T>(
+    runtime: &Runtime,
+    future: impl Future<Output = Result<T, object_store::Error>>
+) -> Result<T, Error> {
+    runtime.block_on(future).map_err(|error| match error {
+        object_store::Error::NotFound { .. } => Error::new(ErrorKind::NotFound, error),
+        error => Error::other(error),
+    })
+}
+
+fn to_object_path(path: &Path) -> ObjectPath {
+    ObjectPath::from_iter(path.components().filter_map(|component| match component {
+        Component::Normal(part) => part.to_str(),
+        _ => None,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sst::object_store_file_system::ObjectStoreFileSystem;
+    use crate::Storage;
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use object_store::path::Path as ObjectPath;
+    use object_store::ObjectStore;
+    use shared::logger::Logger;
+    use shared::{FileSystem, InMemoryFileSystem};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn write_read_sstables() {
+        let object_store = Arc::new(InMemory::new());
+        let local = Arc::new(InMemoryFileSystem::create());
+        local.create_dir_all(Path::new("/simpledb")).unwrap();
+        let file_system = Arc::new(ObjectStoreFileSystem::create(object_store.clone(), local.clone()).unwrap());
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system)
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let transaction = storage.start_transaction();
+        for i in 0..50 {
+            storage.set_with_transaction(keyspace_id, &transaction, key(i), &value(i)).unwrap();
+        }
+        storage.commit_transaction(&transaction).unwrap();
+
+        //SSTables are only stored in the object store
+        let objects = object_store.runtime_list(&format!("simpledb/{}", keyspace_id));
+        assert!(!objects.is_empty());
+        assert!(objects.iter().all(|object| object.filename().unwrap().starts_with("sst-")));
+        assert!(local.list_files().iter().all(|file| !file.file_name().unwrap().to_str().unwrap().starts_with("sst-")));
+
+        let storage = Storage::create(options).unwrap();
+        for i in 0..50 {
+            assert_eq!(storage.get(keyspace_id, &key(i)).unwrap(), Some(Bytes::from(value(i))));
+        }
+    }
+
+    trait RuntimeList {
+        fn runtime_list(&self, prefix: &str) -> Vec<ObjectPath>;
+    }
+
+    impl RuntimeList for InMemory {
+        fn runtime_list(&self, prefix: &str) -> Vec<ObjectPath> {
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            runtime.block_on(self.list_with_delimiter(Some(&ObjectPath::from(prefix))))
+                .unwrap()
+                .objects
+                .into_iter()
+                .map(|object| object.location)
+                .collect()
+        }
+    }
+
+    fn key(i: usize) -> Bytes {
+        Bytes::from(format!("key-{:03}", i))
+    }
+
+    fn value(i: usize) -> Vec<u8> {
+        format!("value-{}", i).into_bytes()
+    }
+}
@@ -1,31 +1,71 @@
 use crate::sst::block::block::Block;
+use crate::sst::block::block_compression;
 use crate::sst::block_cache::BlockCache;
 use crate::sst::block_metadata::BlockMetadata;
+use crate::sst::sstable_meta::SSTableMeta;
 use crate::transactions::transaction::Transaction;
 use crate::utils::bloom_filter::BloomFilter;
+use crate::utils::tombstone::RangeTombstone;
 use bytes::Bytes;
 use shared::key::Key;
-use shared::{SimpleDbFile, SimpleDbFileWrapper};
+use shared::{Compression, SimpleDbFile, SimpleDbFileWrapper};
 use std::cell::UnsafeCell;
+use std::cmp::min;
 use std::path::Path;
-use std::sync::atomic::AtomicU8;
-use std::sync::atomic::Ordering::Release;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU8, AtomicUsize};
+use std::sync::atomic::Ordering::{Relaxed, Release};
+use std::sync::Arc;
 
 pub const SSTABLE_DELETED: u8 = 2;
 pub const SSTABLE_ACTIVE: u8 = 1;
 
+//Footer flags
+pub const SSTABLE_INDEX_CHECKSUMMED: u8 = 1;
+pub const SSTABLE_HAS_RANGE_TOMBSTONES: u8 = 2;
+//The bloom filter stores its number of hash functions. Not set in SSTables written before bloom filters were
+//sized from the configured false positive rate
+pub const SSTABLE_BLOOM_HAS_N_HASHES: u8 = 4;
+//Blocks start at SSTABLE_PAGE_SIZE boundaries and the blocks metadata stores the length of every block
+pub const SSTABLE_PAGE_ALIGNED_BLOCKS: u8 = 8;
+//Bits 4 to 6 of the flags contain the codec id used to compress the blocks. Blocks are stored
+//without compression when it is 0, like in SSTables written before blocks were compressed
+pub const SSTABLE_CODEC_SHIFT: u8 = 4;
+pub const SSTABLE_CODEC_MASK: u8 = 0x07;
+//Blocks are followed by their crc32c. Blocks of SSTables written before it are followed by their crc32
+pub const SSTABLE_BLOCKS_CRC32C: u8 = 128;
+
+pub const SSTABLE_PAGE_SIZE: usize = 4096;
+
+//State (1 byte), flags (1 byte), index crc (4 bytes), level (4 bytes), bloom offset (4 bytes), meta offset (4 bytes),
+//magic (4 bytes)
+const SSTABLE_FOOTER_LENGTH: usize = 22;
+//Footer of SSTables written before the footer had flags: state (1 byte), level (4 bytes), bloom offset (4 bytes),
+//meta offset (4 bytes). They are read with no flags set
+const SSTABLE_LEGACY_FOOTER_LENGTH: usize = 13;
+//Last bytes of SSTables with flags in the footer. Legacy SSTables end with the meta offset, which is always lower
+pub const SSTABLE_FOOTER_MAGIC: u32 = 0xF00D_55B1;
+
 pub struct SSTable {
     pub(crate) sstable_id: shared::SSTableId,
     pub(crate) bloom_filter: BloomFilter,
     pub(crate) file: SimpleDbFileWrapper,
-    pub(crate) block_cache: Mutex<BlockCache>,
+    pub(crate) block_cache: Arc<BlockCache>,
     pub(crate) block_metadata: Vec<BlockMetadata>,
     pub(crate) options: Arc<shared::SimpleDbOptions>,
     pub(crate) level: u32,
     pub(crate) state: AtomicU8,
     pub(crate) first_key: Key,
     pub(crate) last_key: Key,
+    pub(crate) range_tombstones: Vec<RangeTombstone>,
+    pub(crate) compression: Compression,
+    //Checksum algorithm of the crc placed after every block, see SSTABLE_BLOCKS_CRC32C
+    pub(crate) blocks_crc32c: bool,
+    //Every block is followed by its crc. The last block ends at this offset
+    pub(crate) blocks_end_offset: usize,
+    //Includes every version of the keys. None in SSTables written before it was stored
+    pub(crate) n_entries: Option<usize>,
+    //Blocks loaded by readers, including the ones served by the block cache
+    pub(crate) n_blocks_read: AtomicUsize,
 
     pub(crate) keyspace_id: shared::KeyspaceId,
 }
@@ -41,15 +81,27 @@ impl SSTable {
         level: u32,
         sstable_id: shared::SSTableId,
         state: u8,
-        keyspace_id: shared::KeyspaceId
+        keyspace_id: shared::KeyspaceId,
+        range_tombstones: Vec<RangeTombstone>,
+        compression: Compression,
+        blocks_crc32c: bool,
+        blocks_end_offset: usize,
+        n_entries: Option<usize>,
+        block_cache: Arc<BlockCache>,
     ) -> SSTable {
         SSTable {
-            block_cache: Mutex::new(BlockCache::create(options.clone())),
+            block_cache,
             state: AtomicU8::new(state),
             file: SimpleDbFileWrapper {file: UnsafeCell::new(file)},
             block_metadata,
             bloom_filter,
             options,
+            range_tombstones,
+            compression,
+            blocks_crc32c,
+            blocks_end_offset,
+            n_entries,
+            n_blocks_read: AtomicUsize::new(0),
             first_key,
             last_key,
             level,
@@ -62,14 +114,15 @@ impl SSTable {
         sstable_id: shared::SSTableId,
         keyspace_id: shared::KeyspaceId,
         path: &Path,
-        options: Arc<shared::SimpleDbOptions>
+        options: Arc<shared::SimpleDbOptions>,
+        block_cache: Arc<BlockCache>,
     ) -> Result<Arc<SSTable>, shared::SimpleDbError> {
-        let sst_file = shared::SimpleDbFile::open(path, shared::SimpleDbFileMode::RandomWrites)
+        let sst_file = shared::SimpleDbFile::open_in(&options.file_system, path, shared::SimpleDbFileMode::RandomWrites)
             .map_err(|e| shared::SimpleDbError::CannotOpenSSTableFile(keyspace_id, sstable_id, e))?;
         let sst_bytes = sst_file.read_all()
             .map_err(|e| shared::SimpleDbError::CannotOpenSSTableFile(keyspace_id, sstable_id, e))?;
 
-        Self::deserialize(&sst_bytes, sstable_id, keyspace_id, options, sst_file)
+        Self::deserialize(&sst_bytes, sstable_id, keyspace_id, options, sst_file, block_cache)
     }
 
     fn deserialize(
@@ -78,13 +131,61 @@ impl SSTable {
         keyspace_id: shared::KeyspaceId,
         options: Arc<shared::SimpleDbOptions>,
         file: shared::SimpleDbFile,
+        block_cache: Arc<BlockCache>,
     ) -> Result<Arc<SSTable>, shared::SimpleDbError> {
-        let meta_offset = shared::u8_vec_to_u32_le(bytes, bytes.len() - 4);
-        let bloom_offset = shared::u8_vec_to_u32_le(bytes, bytes.len() - 8);
-        let level = shared::u8_vec_to_u32_le(bytes, bytes.len() - 12);
-        let state = bytes[bytes.len() - 13];
+        let has_footer_flags = bytes.len() >= SSTABLE_FOOTER_LENGTH &&
+            shared::u8_vec_to_u32_le(bytes, bytes.len() - 4) == SSTABLE_FOOTER_MAGIC;
+        let footer_length = if has_footer_flags { SSTABLE_FOOTER_LENGTH } else { SSTABLE_LEGACY_FOOTER_LENGTH };
+        let footer_offset = bytes.len().saturating_sub(footer_length);
+        let decode_footer_error = |error_type| shared::SimpleDbError::CannotDecodeSSTable(
+            keyspace_id,
+            sstable_id,
+            shared::SSTableCorruptedPart::Footer,
+            shared::DecodeError {
+                offset: footer_offset,
+                error_type,
+                index: 0,
+            }
+        );
+        if bytes.len() < footer_length {
+            return Err(decode_footer_error(shared::DecodeErrorType::IllegalSize(footer_length, bytes.len())));
+        }
+
+        //Level, bloom offset & meta offset are the last fields of both footers, before the magic
+        let offsets_end = if has_footer_flags { bytes.len() - 4 } else { bytes.len() };
+        let meta_offset = shared::u8_vec_to_u32_le(bytes, offsets_end - 4);
+        let bloom_offset = shared::u8_vec_to_u32_le(bytes, offsets_end - 8);
+        let level = shared::u8_vec_to_u32_le(bytes, offsets_end - 12);
+        let state = bytes[footer_offset];
+        let (flags, expected_index_crc) = if has_footer_flags {
+            (bytes[footer_offset + 1], shared::u8_vec_to_u32_le(bytes, footer_offset + 2))
+        } else {
+            (0, 0)
+        };
+
+        //Offsets are checked before the crc, so that a corrupted offset doesn't make us read out of bounds
+        if meta_offset > bloom_offset || bloom_offset as usize > footer_offset {
+            return Err(decode_footer_error(shared::DecodeErrorType::IllegalSize(footer_offset, bloom_offset as usize)));
+        }
+        //SSTables written before the number of entries was stored end their bloom filter at the footer
+        let n_entries_offset = if flags & SSTABLE_BLOOM_HAS_N_HASHES != 0 && bloom_offset as usize + 8 <= footer_offset {
+            let bloom_n_bytes = shared::u8_vec_to_u32_le(bytes, bloom_offset as usize + 4) as usize;
+            min(bloom_offset as usize + 12 + bloom_n_bytes, footer_offset)
+        } else {
+            footer_offset
+        };
+        if flags & SSTABLE_INDEX_CHECKSUMMED != 0 && options.sst_index_checksum {
+            let actual_index_crc = Self::index_crc(
+                &bytes[meta_offset as usize..bloom_offset as usize],
+                &bytes[n_entries_offset..footer_offset],
+                &bytes[offsets_end - 12..offsets_end]
+            );
+            if actual_index_crc != expected_index_crc {
+                return Err(decode_footer_error(shared::DecodeErrorType::CorruptedCrc(expected_index_crc, actual_index_crc)));
+            }
+        }
 
-        let block_metadata = BlockMetadata::decode_all(bytes, meta_offset as usize)
+        let block_metadata = BlockMetadata::decode_all(bytes, meta_offset as usize, flags & SSTABLE_PAGE_ALIGNED_BLOCKS != 0)
             .map_err(|error_type| shared::SimpleDbError::CannotDecodeSSTable(
                 keyspace_id,
                 sstable_id,
@@ -96,7 +197,7 @@ impl SSTable {
                 }
             ))?;
 
-        let bloom_filter = BloomFilter::decode(bytes, bloom_offset as usize)
+        let bloom_filter = BloomFilter::decode(bytes, bloom_offset as usize, flags & SSTABLE_BLOOM_HAS_N_HASHES != 0)
             .map_err(|error_type| shared::SimpleDbError::CannotDecodeSSTable(
                 keyspace_id,
                 sstable_id,
@@ -108,6 +209,36 @@ impl SSTable {
                 }
             ))?;
 
+        let compression = block_compression::codec_from_id((flags >> SSTABLE_CODEC_SHIFT) & SSTABLE_CODEC_MASK)
+            .map_err(decode_footer_error)?;
+
+        let range_tombstones = if flags & SSTABLE_HAS_RANGE_TOMBSTONES != 0 {
+            Self::decode_range_tombstones(bytes, meta_offset as usize)
+                .map_err(|error_type| shared::SimpleDbError::CannotDecodeSSTable(
+                    keyspace_id,
+                    sstable_id,
+                    shared::SSTableCorruptedPart::RangeTombstones,
+                    shared::DecodeError {
+                        offset: meta_offset as usize,
+                        error_type,
+                        index: 0,
+                    }
+                ))?
+        } else {
+            Vec::new()
+        };
+
+        //The range tombstones section was checked when it was decoded
+        let blocks_end_offset = if flags & SSTABLE_HAS_RANGE_TOMBSTONES != 0 {
+            meta_offset as usize - 8 - shared::u8_vec_to_u32_le(bytes, meta_offset as usize - 4) as usize
+        } else {
+            meta_offset as usize
+        };
+        let n_entries = if footer_offset - n_entries_offset == 8 {
+            Some(shared::u8_vec_to_u64_le(bytes, n_entries_offset) as usize)
+        } else {
+            None
+        };
         let first_key = Self::get_first_key(&block_metadata);
         let last_key = Self::get_last_key(&block_metadata);
 
@@ -121,16 +252,63 @@ impl SSTable {
             level,
             sstable_id,
             state,
-            keyspace_id
+            keyspace_id,
+            range_tombstones,
+            compression,
+            flags & SSTABLE_BLOCKS_CRC32C != 0,
+            blocks_end_offset,
+            n_entries,
+            block_cache,
         )))
     }
 
+    //Range tombstones are followed by their crc (u32) & their encoded length (u32), and they end at end_offset
+    fn decode_range_tombstones(bytes: &Vec<u8>, end_offset: usize) -> Result<Vec<RangeTombstone>, shared::DecodeErrorType> {
+        if end_offset < 8 {
+            return Err(shared::DecodeErrorType::IllegalSize(8, end_offset));
+        }
+        let length = shared::u8_vec_to_u32_le(bytes, end_offset - 4) as usize;
+        let expected_crc = shared::u8_vec_to_u32_le(bytes, end_offset - 8);
+        if length > end_offset - 8 {
+            return Err(shared::DecodeErrorType::IllegalSize(length, end_offset - 8));
+        }
+        let encoded = &bytes[end_offset - 8 - length..end_offset - 8];
+        let actual_crc = crc32fast::hash(encoded);
+        if actual_crc != expected_crc {
+            return Err(shared::DecodeErrorType::CorruptedCrc(expected_crc, actual_crc));
+        }
+
+        let mut ptr = encoded;
+        let mut range_tombstones = Vec::new();
+        while !ptr.is_empty() {
+            range_tombstones.push(RangeTombstone::deserialize(&mut ptr)?);
+        }
+        Ok(range_tombstones)
+    }
+
+    pub(crate) fn get_range_tombstones(&self, transaction: &Transaction) -> Vec<RangeTombstone> {
+        self.range_tombstones.iter()
+            .filter(|range_tombstone| transaction.can_read(&range_tombstone.start))
+            .cloned()
+            .collect()
+    }
+
+    //Covers the encoded blocks metadata, the number of entries & the footer offsets (level, bloom offset & meta offset)
+    pub(crate) fn index_crc(encoded_blocks_metadata: &[u8], encoded_n_entries: &[u8], encoded_footer_offsets: &[u8]) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(encoded_blocks_metadata);
+        hasher.update(encoded_n_entries);
+        hasher.update(encoded_footer_offsets);
+        hasher.finalize()
+    }
+
+    //SSTables that only contain range tombstones have no blocks
     fn get_last_key(block_metadata: &Vec<BlockMetadata>) -> Key {
-        block_metadata.get(block_metadata.len() - 1).unwrap().last_key.clone()
+        block_metadata.last().map(|metadata| metadata.last_key.clone()).unwrap_or_default()
     }
 
     fn get_first_key(block_metadata: &Vec<BlockMetadata>) -> Key {
-        block_metadata.get(0).unwrap().first_key.clone()
+        block_metadata.first().map(|metadata| metadata.first_key.clone()).unwrap_or_default()
     }
 
     pub fn key_greater(&self, key: &Key) -> bool {
@@ -151,67 +329,129 @@ impl SSTable {
 
     pub fn delete(&self) -> Result<(), shared::SimpleDbError> {
         self.state.store(SSTABLE_DELETED, Release);
+        self.block_cache.remove_sstable(self.keyspace_id, self.sstable_id);
         let file: &mut SimpleDbFile = unsafe { &mut *self.file.file.get() };
         file.delete()
             .map_err(|e| shared::SimpleDbError::CannotDeleteSSTable(self.keyspace_id, self.sstable_id, e))
     }
 
+    pub fn describe(&self) -> SSTableMeta {
+        SSTableMeta {
+            sstable_id: self.sstable_id,
+            keyspace_id: self.keyspace_id,
+            level: self.level,
+            min_key: self.first_key.as_bytes().clone(),
+            max_key: self.last_key.as_bytes().clone(),
+            n_entries: self.n_entries,
+            n_blocks: self.block_metadata.len(),
+            n_range_tombstones: self.range_tombstones.len(),
+            bloom_filter_n_bits: self.bloom_filter.n_bits(),
+            bloom_filter_n_hashes: self.bloom_filter.n_hashes(),
+            size_bytes: self.size(),
+        }
+    }
+
     pub fn size(&self) -> shared::SSTableId {
         let file: &mut SimpleDbFile = unsafe { &mut *self.file.file.get() };
         file.size()
     }
 
     pub fn load_block(&self, block_id: shared::SSTableId) -> Result<Arc<Block>, shared::SimpleDbError> {
-        {
+        self.load_block_read_ahead(block_id, 0)
+    }
+
+    //If the block is not cached, the block and the next n_read_ahead_blocks blocks are read from disk with a single read,
+    //and they are stored in the block cache. Read-ahead stops at the first block that is already cached
+    pub fn load_block_read_ahead(
+        &self,
+        block_id: shared::SSTableId,
+        n_read_ahead_blocks: usize
+    ) -> Result<Arc<Block>, shared::SimpleDbError> {
+        self.n_blocks_read.fetch_add(1, Relaxed);
+        let last_block_id = {
             //Try read from cache
-            let mut block_cache = self.block_cache.lock()
-                .unwrap();
-            let block_entry_from_cache = block_cache.get(block_id);
+            let block_entry_from_cache = self.block_cache.get(self.keyspace_id, self.sstable_id, block_id);
 
             if block_entry_from_cache.is_some() {
                 return Ok::<Arc<Block>, shared::SimpleDbError>(block_entry_from_cache.unwrap());
             }
-        }
 
-        //Read from disk
-        let metadata: &BlockMetadata = &self.block_metadata[block_id];
+            let max_block_id = min(block_id + n_read_ahead_blocks, self.block_metadata.len() - 1);
+            (block_id + 1..=max_block_id)
+                .find(|read_ahead_block_id| self.block_cache.get(self.keyspace_id, self.sstable_id, *read_ahead_block_id).is_some())
+                .map(|cached_block_id| cached_block_id - 1)
+                .unwrap_or(max_block_id)
+        };
+
+        //Read from disk. Blocks are stored contiguously, each one followed by its crc
+        let first_offset = self.block_metadata[block_id].offset;
+        let read_length = self.block_metadata[last_block_id].offset - first_offset + self.encoded_block_length(last_block_id) + 4;
         let file: &mut SimpleDbFile = unsafe { &mut *self.file.file.get() };
-        let encoded_block = file.read(metadata.offset, self.options.block_size_bytes)
+        let encoded_blocks = file.read(first_offset, read_length)
             .map_err(|e| shared::SimpleDbError::CannotReadSSTableFile(self.keyspace_id, self.sstable_id, e))?;
 
-        let block = Block::deserialize(&encoded_block, &self.options)
+        let mut blocks = Vec::with_capacity(last_block_id - block_id + 1);
+        for current_block_id in block_id..=last_block_id {
+            let start = min(self.block_metadata[current_block_id].offset - first_offset, encoded_blocks.len());
+            let end = min(start + self.encoded_block_length(current_block_id), encoded_blocks.len());
+            if self.options.sst_block_checksum && !self.block_crc_matches(&encoded_blocks, start, end) {
+                return Err(shared::SimpleDbError::CorruptedBlock(
+                    self.keyspace_id, self.sstable_id, self.block_metadata[current_block_id].offset
+                ));
+            }
+            blocks.push(Arc::new(self.decode_block(current_block_id, &encoded_blocks[start..end])?));
+        }
+
+        for (index, block) in blocks.iter().enumerate() {
+            self.block_cache.put(self.keyspace_id, self.sstable_id, block_id + index, block.clone());
+        }
+
+        Ok(blocks.swap_remove(0))
+    }
+
+    //The crc is placed after the block. A truncated read doesn't match
+    fn block_crc_matches(&self, encoded_blocks: &[u8], block_start: usize, block_end: usize) -> bool {
+        if block_end + 4 > encoded_blocks.len() {
+            return false;
+        }
+
+        let encoded_block = &encoded_blocks[block_start..block_end];
+        let actual_crc = if self.blocks_crc32c { crc32c::crc32c(encoded_block) } else { crc32fast::hash(encoded_block) };
+        actual_crc == u32::from_le_bytes(encoded_blocks[block_end..block_end + 4].try_into().unwrap())
+    }
+
+    //Length of the block stored on disk, without its crc & padding. Compressed blocks have different lengths
+    fn encoded_block_length(&self, block_id: shared::SSTableId) -> usize {
+        if let Some(length) = self.block_metadata[block_id].length {
+            return length;
+        }
+        let next_block_offset = self.block_metadata.get(block_id + 1)
+            .map(|next_block_metadata| next_block_metadata.offset)
+            .unwrap_or(self.blocks_end_offset);
+        next_block_offset - self.block_metadata[block_id].offset - 4
+    }
+
+    fn decode_block(&self, block_id: shared::SSTableId, encoded_block: &[u8]) -> Result<Block, shared::SimpleDbError> {
+        block_compression::decompress(self.compression, encoded_block.to_vec())
+            .and_then(|decompressed_block| Block::deserialize(&decompressed_block, &self.options))
             .map_err(|error_type| shared::SimpleDbError::CannotDecodeSSTable(
                 self.keyspace_id,
                 self.sstable_id,
                 shared::SSTableCorruptedPart::Block(block_id),
                 shared::DecodeError {
-                    offset: metadata.offset,
+                    offset: self.block_metadata[block_id].offset,
                     error_type,
                     index: 0,
                 }
-            ))?;
-
-        let block = Arc::new(block);
-
-        {
-            //Write to cache
-            let mut block_cache = self.block_cache.lock()
-                .unwrap();
-            block_cache.put(block_id, block.clone());
-        }
-
-        Ok(block)
+            ))
     }
     
-    pub fn get(&self, key: &Bytes, transaction: &Transaction) -> Result<Option<bytes::Bytes>, shared::SimpleDbError> {
-        if self.first_key.bytes_gt_bytes(key) || self.last_key.bytes_lt_bytes(key) {
-            return Ok(None);
-        }
-        if !self.bloom_filter.may_contain(shared::hash(key.as_ref())) {
+    pub fn get(&self, key: &Bytes, transaction: &Transaction) -> Result<Option<(Key, Bytes)>, shared::SimpleDbError> {
+        if !self.may_contain(key) {
             return Ok(None);
         }
 
-        match self.get_blocks_metadata(key, transaction) {
+        match self.get_blocks_metadata(key) {
             Some(block_metadata_index) => {
                 let block = self.load_block(block_metadata_index)?;
                 Ok(block.get_value(key, transaction))
@@ -220,27 +460,299 @@ impl SSTable {
         }
     }
 
-    fn get_blocks_metadata(&self, key: &Bytes, transaction: &Transaction) -> Option<usize> {
-        let lookup_key = Key::create(key.clone(), transaction.txn_id);
-        let mut right = self.block_metadata.len() - 1;
-        let mut left = 0;
+    //False if the key is out of the SSTable key range or rejected by the bloom filter
+    pub(crate) fn may_contain(&self, key: &Bytes) -> bool {
+        !self.first_key.bytes_gt_bytes(key) && !self.last_key.bytes_lt_bytes(key) &&
+            self.bloom_filter.may_contain(shared::hash(key.as_ref()))
+    }
 
-        loop {
-            let current_index = (left + right) / 2;
-            let current_block_metadata = &self.block_metadata[current_index];
+    //Returns the index of the block whose key range contains the key
+    fn get_blocks_metadata(&self, key: &Bytes) -> Option<usize> {
+        let index = self.block_metadata.partition_point(|block_metadata| block_metadata.last_key.bytes_lt_bytes(key));
 
-            if left == right {
-                return None;
-            }
-            if current_block_metadata.contains(key, &transaction) {
-                return Some(current_index);
+        match self.block_metadata.get(index) {
+            Some(block_metadata) if !block_metadata.first_key.bytes_gt_bytes(key) => Some(index),
+            _ => None
+        }
+    }
+}
+#[cfg(test)]
+mod test {
+    use crate::sst::block_cache::BlockCache;
+    use crate::sst::block_metadata::BlockMetadata;
+    use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE, SSTABLE_BLOCKS_CRC32C, SSTABLE_PAGE_SIZE};
+    use crate::sst::sstable_builder::SSTableBuilder;
+    use crate::transactions::transaction::Transaction;
+    use bytes::{BufMut, Bytes};
+    use shared::key::Key;
+    use shared::{start_simpledb_options_builder_from, Compression, InMemoryFileSystem, SimpleDbOptions};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn compressed_blocks() {
+        for compression in [Compression::None, Compression::Lz4, Compression::Snappy] {
+            let options = in_memory_options(compression);
+            let sstable = build_sstable(&options, "/1.sst");
+            assert!(sstable.block_metadata.len() > 1);
+            if compression != Compression::None {
+                assert!(sstable.size() < sstable.block_metadata.len() * options.block_size_bytes);
             }
-            if current_block_metadata.first_key.gt(&lookup_key) {
-                right = current_index;
+
+            assert_entries_readable(&SSTable::from_file(1, 0, Path::new("/1.sst"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap());
+        }
+    }
+
+    #[test]
+    fn uncompressed_sstable_readable_after_enabling_compression() {
+        let options = in_memory_options(Compression::None);
+        build_sstable(&options, "/1.sst");
+
+        let options = start_simpledb_options_builder_from(&options)
+            .compression(Compression::Lz4)
+            .build_arc();
+        build_sstable(&options, "/2.sst");
+
+        let uncompressed_sstable = SSTable::from_file(1, 0, Path::new("/1.sst"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+        let compressed_sstable = SSTable::from_file(2, 0, Path::new("/2.sst"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+        assert_eq!(uncompressed_sstable.compression, Compression::None);
+        assert_eq!(compressed_sstable.compression, Compression::Lz4);
+        assert_entries_readable(&uncompressed_sstable);
+        assert_entries_readable(&compressed_sstable);
+    }
+
+    #[test]
+    fn page_aligned_blocks() {
+        for compression in [Compression::None, Compression::Lz4] {
+            let options = start_simpledb_options_builder_from(&in_memory_options(compression))
+                .sst_page_aligned_blocks(true)
+                .build_arc();
+            let sstable = build_sstable(&options, "/1.sst");
+            assert!(sstable.block_metadata.len() > 1);
+            for block_metadata in &sstable.block_metadata {
+                assert_eq!(block_metadata.offset % SSTABLE_PAGE_SIZE, 0);
             }
-            if current_block_metadata.last_key.lt(&lookup_key) {
-                left = current_index;
+
+            let sstable = SSTable::from_file(1, 0, Path::new("/1.sst"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+            assert!(sstable.block_metadata.iter().all(|block_metadata| block_metadata.length.is_some()));
+            //Read-ahead reads several blocks with their padding
+            sstable.load_block_read_ahead(0, 2).unwrap();
+            assert_entries_readable(&sstable);
+        }
+    }
+
+    #[test]
+    fn blocks_with_crc32() {
+        let options = in_memory_options(Compression::None);
+        let sstable = build_sstable(&options, "/1.sst");
+        assert!(sstable.blocks_crc32c);
+
+        //Layout of SSTables written before blocks were checksummed with crc32c
+        let mut bytes = shared::SimpleDbFile::open_in(&options.file_system, Path::new("/1.sst"), shared::SimpleDbFileMode::ReadOnly)
+            .unwrap()
+            .read_all()
+            .unwrap();
+        let flags_offset = bytes.len() - 21;
+        bytes[flags_offset] &= !SSTABLE_BLOCKS_CRC32C;
+        shared::SimpleDbFile::create_in(&options.file_system, Path::new("/2.sst"), &bytes, shared::SimpleDbFileMode::RandomWrites)
+            .unwrap();
+        //Blocks are still followed by their crc32c
+        let sstable_with_wrong_crcs = SSTable::from_file(2, 0, Path::new("/2.sst"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+        assert!(matches!(sstable_with_wrong_crcs.load_block(0), Err(shared::SimpleDbError::CorruptedBlock(0, 2, 0))));
+
+        for block_id in 0..sstable.block_metadata.len() {
+            let block_start = sstable.block_metadata[block_id].offset;
+            let block_end = block_start + sstable.encoded_block_length(block_id);
+            let crc32 = crc32fast::hash(&bytes[block_start..block_end]);
+            bytes[block_end..block_end + 4].copy_from_slice(&crc32.to_le_bytes());
+        }
+        shared::SimpleDbFile::create_in(&options.file_system, Path::new("/3.sst"), &bytes, shared::SimpleDbFileMode::RandomWrites)
+            .unwrap();
+        let sstable = SSTable::from_file(3, 0, Path::new("/3.sst"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+        assert!(!sstable.blocks_crc32c);
+        assert_entries_readable(&sstable);
+    }
+
+    fn build_sstable(options: &Arc<SimpleDbOptions>, path: &str) -> SSTable {
+        let mut builder = SSTableBuilder::create(options.clone(), 0, 0);
+        for i in 0..500 {
+            builder.add_entry(Key::create(key(i), 1), value(i)).unwrap();
+        }
+        builder.build(1, Path::new(path), Arc::new(BlockCache::create(options))).unwrap()
+    }
+
+    fn assert_entries_readable(sstable: &SSTable) {
+        for i in 0..500 {
+            let (_, value_read) = sstable.get(&key(i), &Transaction::none()).unwrap().unwrap();
+            assert_eq!(value_read, value(i));
+        }
+    }
+
+    fn in_memory_options(compression: Compression) -> Arc<SimpleDbOptions> {
+        shared::start_simpledb_options_builder()
+            .file_system(Arc::new(InMemoryFileSystem::create()))
+            .compression(compression)
+            .build_arc()
+    }
+
+    fn key(i: usize) -> Bytes {
+        Bytes::from(format!("key-{:04}", i))
+    }
+
+    fn value(i: usize) -> Bytes {
+        Bytes::from(format!("value-{}", i))
+    }
+
+    #[test]
+    fn corrupted_index() {
+        let options = shared::start_simpledb_options_builder()
+            .sst_index_checksum(true)
+            .build_arc();
+        let mut builder = SSTableBuilder::create(options.clone(), 0, 0);
+        builder.add_entry(Key::create_from_str("a", 1), Bytes::from(vec![1])).unwrap();
+        builder.add_entry(Key::create_from_str("b", 1), Bytes::from(vec![2])).unwrap();
+        let (mut bytes, _) = builder.encode();
+
+        let decoded = SSTable::deserialize(&bytes, 1, 0, options.clone(), shared::SimpleDbFile::mock(), Arc::new(BlockCache::create(&options)));
+        assert!(decoded.is_ok());
+
+        //Flip a byte of the first key of the block metadata
+        let meta_offset = shared::u8_vec_to_u32_le(&bytes, bytes.len() - 8) as usize;
+        bytes[meta_offset + 8 + 4 + 8] ^= 0xFF;
+
+        let decoded = SSTable::deserialize(&bytes, 1, 0, options.clone(), shared::SimpleDbFile::mock(), Arc::new(BlockCache::create(&options)));
+        assert!(matches!(decoded, Err(shared::SimpleDbError::CannotDecodeSSTable(_, _, shared::SSTableCorruptedPart::Footer, _))));
+    }
+
+    #[test]
+    fn describe() {
+        let options = in_memory_options(Compression::Lz4);
+        let mut builder = SSTableBuilder::create(options.clone(), 0, 2);
+        for i in 10..110 {
+            builder.add_entry(Key::create(key(i), 1), value(i)).unwrap();
+            if i % 10 == 0 {
+                builder.add_entry(Key::create(key(i), 2), value(i)).unwrap();
             }
         }
+        builder.build(1, Path::new("/1.sst"), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let sstable = SSTable::from_file(1, 0, Path::new("/1.sst"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+        let meta = sstable.describe();
+        assert_eq!(meta.sstable_id, 1);
+        assert_eq!(meta.level, 2);
+        assert_eq!(meta.min_key, key(10));
+        assert_eq!(meta.max_key, key(109));
+        assert_eq!(meta.n_entries, Some(110));
+        assert_eq!(meta.n_blocks, sstable.block_metadata.len());
+        assert_eq!(meta.size_bytes, sstable.size());
+        assert!(meta.bloom_filter_n_bits > 0 && meta.bloom_filter_n_hashes > 0);
+        //No data block is read
+        assert_eq!(sstable.n_blocks_read.load(std::sync::atomic::Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn sstable_without_n_entries() {
+        let options = Arc::new(SimpleDbOptions::default());
+        let mut builder = SSTableBuilder::create(options.clone(), 0, 0);
+        builder.add_entry(Key::create_from_str("a", 1), Bytes::from(vec![1])).unwrap();
+        builder.add_entry(Key::create_from_str("b", 1), Bytes::from(vec![2])).unwrap();
+        let (mut bytes, _) = builder.encode();
+
+        //Layout of SSTables written before the number of entries was stored
+        let footer_offset = bytes.len() - 22;
+        bytes.drain(footer_offset - 8..footer_offset);
+        let meta_offset = shared::u8_vec_to_u32_le(&bytes, bytes.len() - 8) as usize;
+        let bloom_offset = shared::u8_vec_to_u32_le(&bytes, bytes.len() - 12) as usize;
+        let index_crc = SSTable::index_crc(&bytes[meta_offset..bloom_offset], &[], &bytes[bytes.len() - 16..bytes.len() - 4]);
+        let crc_offset = bytes.len() - 20;
+        bytes[crc_offset..crc_offset + 4].copy_from_slice(&index_crc.to_le_bytes());
+
+        let sstable = SSTable::deserialize(&bytes, 1, 0, options.clone(), shared::SimpleDbFile::mock(), Arc::new(BlockCache::create(&options))).unwrap();
+        assert_eq!(sstable.n_entries, None);
+        assert_eq!(sstable.last_key.as_bytes(), &Bytes::from("b"));
+    }
+
+    #[test]
+    fn legacy_sstable() {
+        let options = in_memory_options(Compression::None);
+        let entries: Vec<(&str, shared::TxnId, &[u8])> = vec![("a", 1, b"1"), ("b", 2, b""), ("c", 3, b"3")];
+
+        //Layout of SSTables written before the footer had flags: one block whose keys have no flags followed by
+        //its crc32, blocks metadata without lengths, bloom filter without number of hashes & 13 bytes footer
+        let mut block: Vec<u8> = Vec::new();
+        let mut offsets: Vec<u16> = Vec::new();
+        for (key, txn_id, value) in &entries {
+            offsets.push(block.len() as u16);
+            block.put_u64_le(*txn_id as u64);
+            block.put_u16_le(key.len() as u16);
+            block.extend(key.as_bytes());
+            block.put_u16_le(value.len() as u16);
+            block.extend(*value);
+        }
+        let offsets_offset = block.len();
+        block.extend(shared::u16_vec_to_u8_vec(&offsets));
+        block.resize(options.block_size_bytes, 0);
+        shared::u16_to_u8_le(entries.len() as u16, options.block_size_bytes - 4, &mut block);
+        shared::u16_to_u8_le(offsets_offset as u16, options.block_size_bytes - 2, &mut block);
+
+        let mut bytes = block.clone();
+        bytes.put_u32_le(crc32fast::hash(&block));
+        let meta_offset = bytes.len();
+        bytes.extend(BlockMetadata::encode_all(&vec![BlockMetadata {
+            first_key: Key::create_from_str("a", 1),
+            last_key: Key::create_from_str("c", 3),
+            length: None,
+            offset: 0,
+        }]));
+        let bloom_offset = bytes.len();
+        let bitmap = vec![0x01; 64];
+        bytes.put_u32_le(crc32fast::hash(&bitmap));
+        bytes.put_u32_le(bitmap.len() as u32);
+        bytes.extend(&bitmap);
+        bytes.push(SSTABLE_ACTIVE);
+        bytes.put_u32_le(3);
+        bytes.put_u32_le(bloom_offset as u32);
+        bytes.put_u32_le(meta_offset as u32);
+
+        shared::SimpleDbFile::create_in(&options.file_system, Path::new("/1.sst"), &bytes, shared::SimpleDbFileMode::RandomWrites)
+            .unwrap();
+
+        let sstable = SSTable::from_file(1, 0, Path::new("/1.sst"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+        assert_eq!(sstable.level, 3);
+        assert_eq!(sstable.n_entries, None);
+        assert!(!sstable.blocks_crc32c);
+        assert!(matches!(sstable.compression, Compression::None));
+        assert_eq!(sstable.bloom_filter.n_hashes(), 0);
+        assert_eq!(sstable.first_key, Key::create_from_str("a", 1));
+        assert_eq!(sstable.last_key, Key::create_from_str("c", 3));
+        let (key_a, value_a) = sstable.get(&Bytes::from("a"), &Transaction::none()).unwrap().unwrap();
+        assert!(!key_a.is_tombstone());
+        assert_eq!(value_a, Bytes::from("1"));
+        //Deletes were stored as empty values
+        let (key_b, _) = sstable.get(&Bytes::from("b"), &Transaction::none()).unwrap().unwrap();
+        assert!(key_b.is_tombstone());
+        let (_, value_c) = sstable.get(&Bytes::from("c"), &Transaction::none()).unwrap().unwrap();
+        assert_eq!(value_c, Bytes::from("3"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unsorted_input() {
+        let options = Arc::new(SimpleDbOptions::default());
+        let mut builder = SSTableBuilder::create(options.clone(), 0, 0);
+        builder.add_entry(Key::create_from_str("b", 1), Bytes::from(vec![1])).unwrap();
+
+        assert!(matches!(
+            builder.add_entry(Key::create_from_str("a", 1), Bytes::from(vec![2])),
+            Err(shared::SimpleDbError::UnsortedSSTableInput(_, _, _))
+        ));
+        //Same key & same transaction
+        assert!(matches!(
+            builder.add_entry(Key::create_from_str("b", 1), Bytes::from(vec![2])),
+            Err(shared::SimpleDbError::UnsortedSSTableInput(_, _, _))
+        ));
+        //Newer version of the same key
+        assert!(builder.add_entry(Key::create_from_str("b", 2), Bytes::from(vec![3])).is_ok());
+        assert!(builder.add_entry(Key::create_from_str("c", 1), Bytes::from(vec![4])).is_ok());
+        assert_eq!(builder.n_entries(), 3);
+    }
+}
@@ -1,7 +1,11 @@
 use crate::sst::block::block_builder::BlockBuilder;
+use crate::sst::block::block_compression;
+use crate::sst::block_cache::BlockCache;
 use crate::sst::block_metadata::BlockMetadata;
-use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE};
+use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE, SSTABLE_BLOCKS_CRC32C, SSTABLE_BLOOM_HAS_N_HASHES, SSTABLE_CODEC_SHIFT, SSTABLE_FOOTER_MAGIC, SSTABLE_HAS_RANGE_TOMBSTONES, SSTABLE_INDEX_CHECKSUMMED, SSTABLE_PAGE_ALIGNED_BLOCKS, SSTABLE_PAGE_SIZE};
+use crate::sst::sstables_files::to_temporary_sstable_file_path;
 use crate::utils::bloom_filter::BloomFilter;
+use crate::utils::tombstone::RangeTombstone;
 use bytes::{BufMut, Bytes};
 use shared::key::Key;
 use std::path::Path;
@@ -20,6 +24,8 @@ pub struct SSTableBuilder {
 
     key_hashes: Vec<u32>,
 
+    range_tombstones: Vec<RangeTombstone>,
+
     options: Arc<shared::SimpleDbOptions>,
     level: u32,
 
@@ -39,6 +45,7 @@ impl SSTableBuilder {
             level,
             keyspace_id,
             key_hashes: Vec::new(),
+            range_tombstones: Vec::new(),
             builded_block_metadata: Vec::new(),
             builded_encoded_blocks: Vec::new(),
             first_key_current_block: None,
@@ -58,23 +65,40 @@ impl SSTableBuilder {
         self.memtable_id = Some(memtable_id);
     }
 
-    pub fn add_entry(&mut self, key: Key, value: Bytes) {
+    pub fn add_entry(&mut self, key: Key, value: Bytes) -> Result<(), shared::SimpleDbError> {
+        if cfg!(test) || self.options.sst_verify_sorted_input {
+            if let Some(last_key) = self.last_key.as_ref().filter(|last_key| key <= **last_key) {
+                return Err(shared::SimpleDbError::UnsortedSSTableInput(self.keyspace_id, last_key.clone(), key));
+            }
+        }
+
         if self.first_key.is_none() {
             self.first_key = Some(key.clone());
         }
         self.last_key = Some(key.clone());
 
+        self.key_hashes.push(shared::hash(key.as_bytes()));
+
+        //The current block is full, the entry goes to a new block
+        if self.current_block_builder.add_entry(key.clone(), value.clone()).is_err() {
+            self.build_current_block();
+            let _ = self.current_block_builder.add_entry(key.clone(), value);
+        }
+
         self.last_key_current_block = Some(key.clone());
         if self.first_key_current_block.is_none() {
-            self.first_key_current_block = Some(key.clone());
+            self.first_key_current_block = Some(key);
         }
 
-        self.key_hashes.push(shared::hash(key.as_bytes()));
+        Ok(())
+    }
 
-        match self.current_block_builder.add_entry(key, value) {
-            Err(_) => self.build_current_block(),
-            Ok(_) => {}
-        };
+    pub(crate) fn add_range_tombstone(&mut self, range_tombstone: RangeTombstone) {
+        self.range_tombstones.push(range_tombstone);
+    }
+
+    pub fn has_range_tombstones(&self) -> bool {
+        !self.range_tombstones.is_empty()
     }
 
     pub fn n_entries(&self) -> usize {
@@ -88,16 +112,60 @@ impl SSTableBuilder {
     pub fn build(
         mut self,
         id: usize,
-        path: &Path
+        path: &Path,
+        block_cache: Arc<BlockCache>,
     ) -> Result<SSTable, shared::SimpleDbError> {
+        //Blocks are placed at the start of the SSTable
+        self.build_current_block();
+        let blocks_end_offset = self.builded_encoded_blocks.len();
+        let n_entries = self.n_entries();
+        let (encoded, bloom_filter) = self.encode();
+
+        match self.write_sstable_file(path, &encoded) {
+            //SSTables might only contain range tombstones
+            Ok(lsm_file) => Ok(SSTable::create(self.builded_block_metadata, self.options.clone(), bloom_filter, self.first_key.unwrap_or_default(),
+                                               self.last_key.unwrap_or_default(), lsm_file, self.level, id, SSTABLE_ACTIVE, self.keyspace_id,
+                                               self.range_tombstones, self.options.compression, true, blocks_end_offset, Some(n_entries), block_cache,
+            )),
+            Err(e) => Err(shared::SimpleDbError::   CannotCreateSSTableFile(self.keyspace_id, id, e))
+        }
+    }
+
+    //The SSTable is written to a temporary file which is renamed once it is persisted, so that after a crash,
+    //we never load a partially written SSTable. Temporary files are removed when the SSTables are loaded
+    fn write_sstable_file(&self, path: &Path, encoded: &[u8]) -> Result<shared::SimpleDbFile, std::io::Error> {
+        let file_system = &self.options.file_system;
+        let temporary_path = to_temporary_sstable_file_path(path);
+        let temporary_file = shared::SimpleDbFile::create_in(
+            file_system, temporary_path.as_path(), encoded, shared::SimpleDbFileMode::RandomWrites
+        )?;
+        temporary_file.fsync()?;
+        file_system.rename(temporary_path.as_path(), path)?;
+        shared::SimpleDbFile::open_in(file_system, path, shared::SimpleDbFileMode::ReadOnly)
+    }
+
+    //Returns the SSTable bytes that will be written to disk & its bloom filter
+    pub(crate) fn encode(&mut self) -> (Vec<u8>, BloomFilter) {
         self.build_current_block();
 
         let bloom_filter: BloomFilter = BloomFilter::create(
             &self.key_hashes,
-            self.options.bloom_filter_n_entries
+            self.options.bloom_filter_false_positive_rate
         );
 
-        let mut encoded = self.builded_encoded_blocks;
+        let mut encoded = std::mem::take(&mut self.builded_encoded_blocks);
+
+        //Range tombstones, crc & length of the encoded range tombstones. They are placed just before the blocks metadata
+        let mut flags = 0;
+        if !self.range_tombstones.is_empty() {
+            let range_tombstones_encoded: Vec<u8> = self.range_tombstones.iter()
+                .flat_map(|range_tombstone| range_tombstone.serialize())
+                .collect();
+            encoded.extend(&range_tombstones_encoded);
+            encoded.put_u32_le(crc32fast::hash(&range_tombstones_encoded));
+            encoded.put_u32_le(range_tombstones_encoded.len() as u32);
+            flags |= SSTABLE_HAS_RANGE_TOMBSTONES;
+        }
 
         //Blocks metadata
         let meta_offset = encoded.len();
@@ -109,18 +177,38 @@ impl SSTableBuilder {
         let bloom_encoded = bloom_filter.encode();
         encoded.extend(bloom_encoded);
 
-        //Bloom & blocks metadata offsets, state
-        encoded.push(SSTABLE_ACTIVE);
-        encoded.put_u32_le(self.level);
-        encoded.put_u32_le(bloom_offset as u32);
-        encoded.put_u32_le(meta_offset as u32);
+        //Number of entries, placed between the bloom filter and the footer
+        let mut n_entries_encoded: Vec<u8> = Vec::new();
+        n_entries_encoded.put_u64_le(self.n_entries() as u64);
+        encoded.extend(&n_entries_encoded);
 
-        match shared::SimpleDbFile::create(path, &encoded, shared::SimpleDbFileMode::ReadOnly) {
-            Ok(lsm_file) => Ok(SSTable::create(self.builded_block_metadata, self.options, bloom_filter, self.first_key.unwrap(),
-                                               self.last_key.unwrap(), lsm_file, self.level, id, SSTABLE_ACTIVE, self.keyspace_id,
-            )),
-            Err(e) => Err(shared::SimpleDbError::   CannotCreateSSTableFile(self.keyspace_id, id, e))
+        flags |= SSTABLE_BLOOM_HAS_N_HASHES;
+        if self.options.sst_page_aligned_blocks {
+            flags |= SSTABLE_PAGE_ALIGNED_BLOCKS;
         }
+        flags |= block_compression::codec_id(self.options.compression) << SSTABLE_CODEC_SHIFT;
+        flags |= SSTABLE_BLOCKS_CRC32C;
+
+        //Bloom & blocks metadata offsets, state, index checksum & magic
+        let mut footer: Vec<u8> = Vec::new();
+        footer.put_u32_le(self.level);
+        footer.put_u32_le(bloom_offset as u32);
+        footer.put_u32_le(meta_offset as u32);
+
+        let index_crc = if self.options.sst_index_checksum {
+            flags |= SSTABLE_INDEX_CHECKSUMMED;
+            SSTable::index_crc(&encoded[meta_offset..bloom_offset], &n_entries_encoded, &footer)
+        } else {
+            0
+        };
+
+        encoded.push(SSTABLE_ACTIVE);
+        encoded.push(flags);
+        encoded.put_u32_le(index_crc);
+        encoded.extend(footer);
+        encoded.put_u32_le(SSTABLE_FOOTER_MAGIC);
+
+        (encoded, bloom_filter)
     }
 
     pub fn estimated_size_bytes(&self) -> usize {
@@ -133,18 +221,28 @@ impl SSTableBuilder {
             return
         }
 
-        let encoded_block: Vec<u8> = self.current_block_builder.build()
-            .serialize(&self.options);
+        let encoded_block: Vec<u8> = block_compression::compress(
+            self.options.compression,
+            self.current_block_builder.build().serialize(&self.options)
+        );
         self.current_block_builder = BlockBuilder::create(self.options.clone());
 
+        let page_aligned = self.options.sst_page_aligned_blocks;
         self.builded_block_metadata.push(BlockMetadata {
             first_key: self.first_key_current_block.take().unwrap(),
             last_key: self.last_key_current_block.take().unwrap(),
             offset: self.builded_encoded_blocks.len(),
+            length: if page_aligned { Some(encoded_block.len()) } else { None },
         });
 
-        let crc = crc32fast::hash(&encoded_block);
+        let crc = crc32c::crc32c(&encoded_block);
         self.builded_encoded_blocks.extend(encoded_block);
         self.builded_encoded_blocks.put_u32_le(crc);
+
+        //The next block will start at the next page boundary
+        if page_aligned {
+            let padded_length = self.builded_encoded_blocks.len().next_multiple_of(SSTABLE_PAGE_SIZE);
+            self.builded_encoded_blocks.resize(padded_length, 0);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+use bytes::Bytes;
+use shared::{KeyspaceId, SSTableId};
+
+//Introspection of an SSTable, returned by Storage::describe_sstable. It is built from the blocks metadata, the bloom
+//filter & the footer, no data block is read
+#[derive(Debug, Clone, PartialEq)]
+pub struct SSTableMeta {
+    pub sstable_id: SSTableId,
+    pub keyspace_id: KeyspaceId,
+    pub level: u32,
+    //User keys. Empty if the SSTable only contains range tombstones
+    pub min_key: Bytes,
+    pub max_key: Bytes,
+    //Includes every version of the keys. None in SSTables written before it was stored
+    pub n_entries: Option<usize>,
+    pub n_blocks: usize,
+    pub n_range_tombstones: usize,
+    pub bloom_filter_n_bits: usize,
+    pub bloom_filter_n_hashes: u32,
+    //Size of the SSTable file
+    pub size_bytes: usize,
+}
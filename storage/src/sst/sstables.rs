@@ -1,15 +1,17 @@
 use crate::manifest::manifest::{Manifest, ManifestOperationContent, MemtableFlushManifestOperation};
+use crate::sst::block_cache::BlockCache;
 use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE};
 use crate::sst::sstable_builder::SSTableBuilder;
-use crate::sst::sstables_files::{extract_sstable_id_from_file, is_sstable_file, to_sstable_file_name};
+use crate::sst::sstables_files::{extract_sstable_id_from_file, is_sstable_file, is_temporary_sstable_file, to_sstable_file_name};
 use crate::sst::ssttable_iterator::SSTableIterator;
 use crate::transactions::transaction::Transaction;
+use crate::utils::tombstone::RangeTombstone;
 use bytes::Bytes;
+use shared::key::Key;
 use shared::iterators::merge_iterator::MergeIterator;
 use shared::logger::logger;
 use shared::logger::SimpleDbLayer::StorageKeyspace;
 use std::cmp::max;
-use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::{Acquire, Relaxed};
@@ -22,34 +24,41 @@ pub struct SSTables {
     next_sstable_id: AtomicUsize,
     options: Arc<shared::SimpleDbOptions>,
     manifest: Arc<Manifest>,
+    block_cache: Arc<BlockCache>,
     n_current_levels: usize,
+    //Key ranges [start, end) that leveled compaction tries to keep in upper levels. They are not persisted
+    hot_ranges: RwLock<Vec<(Bytes, Bytes)>>,
 }
 
 impl SSTables {
     pub fn open(
         options: Arc<shared::SimpleDbOptions>,
         keyspace_id: shared::KeyspaceId,
-        manifest: Arc<Manifest>
+        manifest: Arc<Manifest>,
+        block_cache: Arc<BlockCache>,
     ) -> Result<SSTables, shared::SimpleDbError> {
         let mut levels: Vec<RwLock<Vec<Arc<SSTable>>>> = Vec::with_capacity(64);
         for _ in 0..64 {
             levels.push(RwLock::new(Vec::new()));
         }
-        let (sstables, max_ssatble_id) = Self::load_sstables(&options, keyspace_id)?;
+        let (sstables, max_ssatble_id) = Self::load_sstables(&options, keyspace_id, &block_cache)?;
 
         Ok(SSTables {
             keyspace_id,
             next_sstable_id: AtomicUsize::new(max_ssatble_id + 1),
             n_current_levels: 0,
+            hot_ranges: RwLock::new(Vec::new()),
             options,
             sstables,
             manifest,
+            block_cache,
         })
     }
 
     fn load_sstables(
         options: &Arc<shared::SimpleDbOptions>,
-        keyspace_id: shared::KeyspaceId
+        keyspace_id: shared::KeyspaceId,
+        block_cache: &Arc<BlockCache>,
     ) -> Result<(Vec<RwLock<Vec<Arc<SSTable>>>>, shared::SSTableId), shared::SimpleDbError> {
         logger().info(StorageKeyspace(keyspace_id), &format!("Loading SSTables"));
 
@@ -62,18 +71,21 @@ impl SSTables {
         let path = path.as_path();
         let mut max_sstable_id: shared::SSTableId = 0;
 
-        for file in fs::read_dir(path).map_err(|e| shared::SimpleDbError::CannotReadSSTablesFiles(keyspace_id, e))? {
-            let file = file.unwrap();
-
+        for file in options.file_system.list(path).map_err(|e| shared::SimpleDbError::CannotReadSSTablesFiles(keyspace_id, e))? {
             if !is_sstable_file(&file) {
                 continue;
             }
+            //SSTable flush or compaction that didn't complete
+            if is_temporary_sstable_file(&file) {
+                let _ = options.file_system.remove_file(file.as_path());
+                continue;
+            }
 
             if let Ok(sstable_id) = extract_sstable_id_from_file(&file) {
                 logger().info(StorageKeyspace(keyspace_id), &format!("Loading SSTable ID: {}", sstable_id));
 
                 let sstable = SSTable::from_file(
-                    sstable_id, keyspace_id, file.path().as_path(), options.clone()
+                    sstable_id, keyspace_id, file.as_path(), options.clone(), block_cache.clone()
                 )?;
 
                 if sstable.state.load(Acquire) != SSTABLE_ACTIVE {
@@ -88,6 +100,11 @@ impl SSTables {
             }
         }
 
+        //Files are not listed in any particular order, SSTables in a level are kept from oldest to newest
+        for level in levels.iter() {
+            level.write().unwrap().sort_by_key(|sstable| sstable.sstable_id);
+        }
+
         logger().info(StorageKeyspace(keyspace_id), &format!("Loaded {} levels of SSTables", levels.len()));
 
         Ok((levels, max_sstable_id))
@@ -100,7 +117,7 @@ impl SSTables {
             let lock = self.sstables[*level_id].read();
             let sstables_in_level = lock.as_ref().unwrap();
 
-            for sstable in sstables_in_level.iter() {
+            for sstable in sstables_in_level.iter().rev() {
                 iterators.push(Box::new(SSTableIterator::create(sstable.clone(), &Transaction::none())))
             }
         }
@@ -109,33 +126,106 @@ impl SSTables {
     }
 
     pub fn scan_all(&self, transaction: &Transaction) -> MergeIterator<SSTableIterator> {
-        let iterators = self.create_iterators(transaction);
+        let iterators = self.create_iterators(transaction, None);
+        MergeIterator::create(iterators)
+    }
+
+    //Only the SSTables that may contain the key are scanned, see SSTable::may_contain
+    pub fn scan_key(&self, key: &Bytes, transaction: &Transaction) -> MergeIterator<SSTableIterator> {
+        let iterators = self.create_iterators(transaction, Some(key));
         MergeIterator::create(iterators)
     }
 
-    fn create_iterators(&self, transaction: &Transaction) -> Vec<Box<SSTableIterator>> {
+    //Range tombstones readable by the transaction
+    pub(crate) fn get_range_tombstones(&self, transaction: &Transaction) -> Vec<RangeTombstone> {
+        let mut range_tombstones = Vec::new();
+
+        for sstables_in_level_lock in self.sstables.iter() {
+            for sstable in sstables_in_level_lock.read().unwrap().iter() {
+                range_tombstones.extend(sstable.get_range_tombstones(transaction));
+            }
+        }
+
+        range_tombstones
+    }
+
+    pub(crate) fn get_range_tombstones_in_levels(&self, levels_id: &Vec<usize>) -> Vec<RangeTombstone> {
+        let mut range_tombstones = Vec::new();
+
+        for level_id in levels_id {
+            for sstable in self.sstables[*level_id].read().unwrap().iter() {
+                range_tombstones.extend(sstable.range_tombstones.iter().cloned());
+            }
+        }
+
+        range_tombstones
+    }
+
+    //Returns at most n_chunks - 1 sorted keys, that split the keys stored in the sstables in chunks of similar size
+    //Keys are taken from the first key of each block, so the returned keys might not be visible to a transaction
+    pub fn get_split_keys(&self, n_chunks: usize) -> Vec<Bytes> {
+        let levels_id: Vec<usize> = (0..self.sstables.len()).collect();
+        self.get_split_keys_in_levels(&levels_id, n_chunks)
+    }
+
+    //Same as get_split_keys, but only the keys stored in the SSTables of levels_id are split
+    pub fn get_split_keys_in_levels(&self, levels_id: &[usize], n_chunks: usize) -> Vec<Bytes> {
+        let mut blocks_first_keys: Vec<Bytes> = Vec::new();
+
+        for level_id in levels_id {
+            let lock_result = self.sstables[*level_id].read();
+            let sstable_in_level = lock_result.as_ref().unwrap();
+
+            for sstable in sstable_in_level.iter() {
+                for block_metadata in &sstable.block_metadata {
+                    blocks_first_keys.push(block_metadata.first_key.as_bytes().clone());
+                }
+            }
+        }
+
+        blocks_first_keys.sort();
+        blocks_first_keys.dedup();
+
+        if n_chunks <= 1 || blocks_first_keys.len() < n_chunks {
+            return blocks_first_keys.into_iter()
+                .skip(1)
+                .take(n_chunks.saturating_sub(1))
+                .collect();
+        }
+
+        let chunk_size = blocks_first_keys.len() / n_chunks;
+        (1..n_chunks)
+            .map(|chunk_index| blocks_first_keys[chunk_index * chunk_size].clone())
+            .collect()
+    }
+
+    fn create_iterators(&self, transaction: &Transaction, key: Option<&Bytes>) -> Vec<Box<SSTableIterator>> {
         let mut iterators: Vec<Box<SSTableIterator>> = Vec::with_capacity(self.sstables.len());
 
         for sstables_in_level_lock in self.sstables.iter() {
             let lock_result = sstables_in_level_lock.read();
             let sstable_in_level = lock_result.as_ref().unwrap();
 
-            for sstable in sstable_in_level.iter() {
-                iterators.push(Box::new(SSTableIterator::create(sstable.clone(), transaction)));
+            //Newest SSTables go first, see MergeIterator
+            for sstable in sstable_in_level.iter().rev() {
+                if key.is_none_or(|key| sstable.may_contain(key)) {
+                    iterators.push(Box::new(SSTableIterator::create(sstable.clone(), transaction)));
+                }
             }
         }
 
         iterators
     }
 
-    pub fn get(&self, key: &Bytes, transaction: &Transaction) -> Result<Option<bytes::Bytes>, shared::SimpleDbError> {
+    //Tombstones are also returned, see Key::is_tombstone
+    pub fn get(&self, key: &Bytes, transaction: &Transaction) -> Result<Option<(Key, Bytes)>, shared::SimpleDbError> {
         for sstables_in_level_lock in self.sstables.iter() {
             let lock_result = sstables_in_level_lock.read();
             let sstable_in_level = lock_result.as_ref().unwrap();
 
-            for sstable in sstable_in_level.iter() {
+            for sstable in sstable_in_level.iter().rev() {
                 match sstable.get(key, transaction)? {
-                    Some(value) => return Ok(Some(value)),
+                    Some(entry) => return Ok(Some(entry)),
                     None => continue
                 }
             }
@@ -168,6 +258,12 @@ impl SSTables {
         false
     }
 
+    pub fn get_sstable(&self, sstable_id: shared::SSTableId) -> Option<Arc<SSTable>> {
+        self.sstables.iter()
+            .flat_map(|sstables_level| sstables_level.read().unwrap().clone())
+            .find(|sstable| sstable.sstable_id == sstable_id)
+    }
+
     pub fn delete_sstables(&self, level: usize, sstables_id: Vec<shared::SSTableId>) -> Result<(), shared::SimpleDbError> {
         match self.sstables.get(level) {
             Some(sstables_lock) => {
@@ -218,10 +314,40 @@ impl SSTables {
         }
     }
 
+    //Indexed by level
+    pub fn get_n_sstables_per_level(&self) -> Vec<usize> {
+        (0..self.sstables.len())
+            .map(|level| self.get_n_sstables(level))
+            .collect()
+    }
+
+    pub fn get_sstables_size(&self) -> usize {
+        self.sstables.iter()
+            .map(|level_lock| level_lock.read().unwrap().iter()
+                .map(|sstable| sstable.size())
+                .sum::<usize>())
+            .sum()
+    }
+
+    pub fn get_non_empty_levels(&self) -> Vec<usize> {
+        (0..self.sstables.len())
+            .filter(|level| self.get_n_sstables(*level) > 0)
+            .collect()
+    }
+
     pub fn get_n_levels(&self) -> usize {
         self.n_current_levels
     }
 
+    pub fn pin_hot_range(&self, start: Bytes, end: Bytes) {
+        self.hot_ranges.write().unwrap().push((start, end));
+    }
+
+    pub fn contains_hot_keys(&self, sstable: &SSTable) -> bool {
+        self.hot_ranges.read().unwrap().iter()
+            .any(|(start, end)| sstable.first_key.as_bytes() < end && sstable.last_key.as_bytes() >= start)
+    }
+
     pub fn is_last_level(&self, level: usize) -> bool {
         level + 1  >= self.sstables.len()
     }
@@ -249,6 +375,7 @@ impl SSTables {
         let sstable_build_result = sstable_builder.build(
             sstable_id,
             self.to_sstable_file_path(sstable_id, self.keyspace_id).as_path(),
+            self.block_cache.clone(),
         );
 
         match sstable_build_result {
@@ -1,7 +1,17 @@
-use std::fs::DirEntry;
+use std::path::{Path, PathBuf};
 
-pub(crate) fn is_sstable_file(file: &DirEntry) -> bool {
-    file.file_name().to_str().unwrap().starts_with("sst-")
+const TEMPORARY_SSTABLE_FILE_EXTENSION: &str = "tmp";
+
+pub(crate) fn is_sstable_file(file: &Path) -> bool {
+    file.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("sst-"))
+}
+
+pub(crate) fn is_temporary_sstable_file(file: &Path) -> bool {
+    is_sstable_file(file) && file.extension().is_some_and(|extension| extension == TEMPORARY_SSTABLE_FILE_EXTENSION)
+}
+
+pub(crate) fn to_temporary_sstable_file_path(path: &Path) -> PathBuf {
+    path.with_extension(TEMPORARY_SSTABLE_FILE_EXTENSION)
 }
 
 pub(crate) fn to_sstable_file_name(sstable_id: shared::SSTableId) -> String {
@@ -9,6 +19,6 @@ pub(crate) fn to_sstable_file_name(sstable_id: shared::SSTableId) -> String {
     result
 }
 
-pub(crate) fn extract_sstable_id_from_file(file: &DirEntry) -> Result<shared::SSTableId, ()> {
+pub(crate) fn extract_sstable_id_from_file(file: &Path) -> Result<shared::SSTableId, ()> {
     shared::extract_number_from_file_name(file, "-")
 }
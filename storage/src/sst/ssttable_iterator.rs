@@ -21,7 +21,10 @@ pub struct SSTableIterator {
     pending_blocks: Vec<BlockMetadata>,
     current_block_metadata: Option<BlockMetadata>,
     current_block_iterator: Option<BlockIterator>,
-    current_block_id: i32 //Index to SSTable block_metadata
+    current_block_id: i32, //Index to SSTable block_metadata
+
+    //If a block cannot be loaded, the iterator finishes and the error is kept here, see StorageIterator::take_error
+    error: Option<shared::SimpleDbError>,
 }
 
 impl SSTableIterator {
@@ -32,6 +35,7 @@ impl SSTableIterator {
             current_block_iterator: None,
             current_block_metadata: None,
             current_block_id: -1,
+            error: None,
             sstable,
         }
     }
@@ -74,14 +78,29 @@ impl SSTableIterator {
     }
 
     fn set_iterating_block(&mut self, block_metadata: BlockMetadata) {
-        let block = self.load_block(self.current_block_id as usize);
-        self.current_block_metadata = Some(block_metadata);
-        self.current_block_iterator = Some(BlockIterator::create(block));
+        //Blocks are iterated sequentially, so the next blocks are read ahead
+        match self.sstable.load_block_read_ahead(self.current_block_id as usize, self.sstable.options.scan_read_ahead_blocks) {
+            Ok(block) => {
+                self.current_block_metadata = Some(block_metadata);
+                self.current_block_iterator = Some(BlockIterator::create(block));
+            },
+            Err(error) => self.finish_iterator_with_error(error),
+        }
+    }
+
+    fn load_block(&mut self, block_id: usize) -> Option<Arc<Block>> {
+        match self.sstable.load_block(block_id) {
+            Ok(block) => Some(block),
+            Err(error) => {
+                self.finish_iterator_with_error(error);
+                None
+            }
+        }
     }
 
-    fn load_block(&mut self, block_id: usize) -> Arc<Block> {
-        self.sstable.load_block(block_id)
-            .expect("Cannot load block")
+    fn finish_iterator_with_error(&mut self, error: shared::SimpleDbError) {
+        self.error = Some(error);
+        self.finish_iterator();
     }
 
     fn finish_iterator(&mut self) {
@@ -143,7 +162,9 @@ impl StorageIterator for SSTableIterator {
             self.current_block_id += 1;
 
             if current_block_metadata.contains(key_bytes, &self.transaction) {
-                let current_block = self.load_block(self.current_block_id as usize);
+                let Some(current_block) = self.load_block(self.current_block_id as usize) else {
+                    return;
+                };
                 let mut current_block_iterator = BlockIterator::create(current_block);
 
                 current_block_iterator.seek(key_bytes, inclusive);
@@ -159,6 +180,10 @@ impl StorageIterator for SSTableIterator {
             self.finish_iterator();
         }
     }
+
+    fn take_error(&mut self) -> Option<shared::SimpleDbError> {
+        self.error.take()
+    }
 }
 
 #[cfg(test)]
@@ -168,14 +193,16 @@ mod test {
     use crate::sst::block_cache::BlockCache;
     use crate::sst::block_metadata::BlockMetadata;
     use crate::sst::sstable::{SSTable, SSTABLE_ACTIVE};
+    use crate::sst::sstable_builder::SSTableBuilder;
     use crate::sst::ssttable_iterator::SSTableIterator;
+    use crate::utils::faulty_file_system::FaultyFileSystem;
+    use std::path::Path;
     use crate::transactions::transaction::Transaction;
     use crate::utils::bloom_filter::BloomFilter;
     use shared::iterators::storage_iterator::StorageIterator;
     use bytes::Bytes;
-    use crossbeam_skiplist::SkipSet;
-    use std::sync::atomic::AtomicU8;
-    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicU8, AtomicUsize};
+    use std::sync::Arc;
     use shared::{assertions, SimpleDbFileWrapper};
     use shared::key::Key;
 
@@ -264,6 +291,85 @@ mod test {
         );
     }
 
+    #[test]
+    fn read_ahead() {
+        let (n_reads_without_read_ahead, keys_without_read_ahead) = scan_counting_reads(0);
+        let (n_reads_with_read_ahead, keys_with_read_ahead) = scan_counting_reads(3);
+
+        assert_eq!(keys_without_read_ahead.len(), 40);
+        assert_eq!(keys_without_read_ahead, keys_with_read_ahead);
+        assert!(n_reads_with_read_ahead < n_reads_without_read_ahead);
+    }
+
+    #[test]
+    fn corrupted_block() {
+        let file_system = FaultyFileSystem::create();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .build_arc();
+        let mut builder = SSTableBuilder::create(options.clone(), 0, 0);
+        for i in 0..40 {
+            builder.add_entry(Key::create(Bytes::from(format!("key-{:03}", i)), 0), Bytes::from(vec![0; 1000])).unwrap();
+        }
+        let sstable = builder.build(1, Path::new("/sst-1"), Arc::new(BlockCache::create(&options))).unwrap();
+        let second_block_offset = sstable.block_metadata[1].offset;
+
+        //Flip a byte of the value of the first entry in the second block
+        let mut bytes = shared::SimpleDbFile::open_in(&options.file_system, Path::new("/sst-1"), shared::SimpleDbFileMode::ReadOnly)
+            .unwrap()
+            .read_all()
+            .unwrap();
+        bytes[second_block_offset + 100] ^= 0xFF;
+        shared::SimpleDbFile::create_in(&options.file_system, Path::new("/sst-2"), &bytes, shared::SimpleDbFileMode::RandomWrites)
+            .unwrap();
+        let sstable = SSTable::from_file(2, 0, Path::new("/sst-2"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+
+        let mut iterator = SSTableIterator::create(sstable.clone(), &Transaction::none());
+        let mut n_keys = 0;
+        while iterator.next() {
+            n_keys += 1;
+        }
+        assert!(n_keys < 40);
+        assert!(matches!(iterator.take_error(), Some(shared::SimpleDbError::CorruptedBlock(0, 2, offset)) if offset == second_block_offset));
+
+        //Checksums are not verified, the corrupted value is returned
+        let options = shared::start_simpledb_options_builder_from(&options)
+            .sst_block_checksum(false)
+            .build_arc();
+        let sstable = SSTable::from_file(2, 0, Path::new("/sst-2"), options.clone(), Arc::new(BlockCache::create(&options))).unwrap();
+        let mut iterator = SSTableIterator::create(sstable, &Transaction::none());
+        let mut n_keys = 0;
+        while iterator.next() {
+            n_keys += 1;
+        }
+        assert_eq!(n_keys, 40);
+        assert!(iterator.take_error().is_none());
+    }
+
+    //Returns the number of reads done by the scan & the scanned keys
+    fn scan_counting_reads(scan_read_ahead_blocks: usize) -> (usize, Vec<Bytes>) {
+        let file_system = FaultyFileSystem::create();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .scan_read_ahead_blocks(scan_read_ahead_blocks)
+            .build_arc();
+        let mut builder = SSTableBuilder::create(options.clone(), 0, 0);
+        for i in 0..40 {
+            builder.add_entry(Key::create(Bytes::from(format!("key-{:03}", i)), 0), Bytes::from(vec![0; 1000])).unwrap();
+        }
+        let sstable = Arc::new(builder.build(1, Path::new("/sst-1"), Arc::new(BlockCache::create(&options))).unwrap());
+        assert!(sstable.block_metadata.len() > 4);
+
+        let n_reads_before_scan = file_system.n_reads();
+        let mut iterator = SSTableIterator::create(sstable, &Transaction::none());
+        let mut keys = Vec::new();
+        while iterator.next() {
+            keys.push(Bytes::copy_from_slice(iterator.key().as_bytes()));
+        }
+
+        (file_system.n_reads() - n_reads_before_scan, keys)
+    }
+
     fn build_sstable_iterator() -> SSTableIterator {
         let mut block1 = BlockBuilder::create(Arc::new(shared::SimpleDbOptions::default()));
         block1.add_entry(Key::create_from_str("Alberto", 0), Bytes::from(vec![1]));
@@ -281,28 +387,33 @@ mod test {
         block3.add_entry(Key::create_from_str("Zi", 0), Bytes::from(vec![1]));
         let block3 = Arc::new(block3.build());
 
-        let mut block_cache = BlockCache::create(Arc::new(shared::SimpleDbOptions::default()));
-        block_cache.put(0, block1);
-        block_cache.put(1, block2);
-        block_cache.put(2, block3);
+        let block_cache = BlockCache::create(&Arc::new(shared::SimpleDbOptions::default()));
+        block_cache.put(0, 1, 0, block1);
+        block_cache.put(0, 1, 1, block2);
+        block_cache.put(0, 1, 2, block3);
 
         let sstable = Arc::new(SSTable{
             keyspace_id: 0,
-            active_txn_ids_written: SkipSet::new(),
             sstable_id: 1,
-            bloom_filter: BloomFilter::create(&Vec::new(), 8),
+            bloom_filter: BloomFilter::create(&[], 0.01),
             file: SimpleDbFileWrapper{ file: UnsafeCell::new(shared::SimpleDbFile::mock()) },
-            block_cache: Mutex::new(block_cache),
+            block_cache: Arc::new(block_cache),
             block_metadata: vec![
-                BlockMetadata{offset: 0, first_key: Key::create_from_str("Alberto", 0), last_key: Key::create_from_str("Berto", 0)},
-                BlockMetadata{offset: 8, first_key: Key::create_from_str("Cigu", 0), last_key: Key::create_from_str("De", 0)},
-                BlockMetadata{offset: 16, first_key: Key::create_from_str("Estonia", 0), last_key: Key::create_from_str("Zi", 0)},
+                BlockMetadata{offset: 0, length: None, first_key: Key::create_from_str("Alberto", 0), last_key: Key::create_from_str("Berto", 0)},
+                BlockMetadata{offset: 8, length: None, first_key: Key::create_from_str("Cigu", 0), last_key: Key::create_from_str("De", 0)},
+                BlockMetadata{offset: 16, length: None, first_key: Key::create_from_str("Estonia", 0), last_key: Key::create_from_str("Zi", 0)},
             ],
             options: Arc::new(shared::SimpleDbOptions::default()),
             level: 0,
             state: AtomicU8::new(SSTABLE_ACTIVE),
             first_key: Key::create_from_str("Alberto", 1),
             last_key: Key::create_from_str("Zi", 1),
+            range_tombstones: Vec::new(),
+            compression: shared::Compression::None,
+            blocks_crc32c: true,
+            blocks_end_offset: 28,
+            n_entries: None,
+            n_blocks_read: AtomicUsize::new(0),
         });
 
         SSTableIterator::create(sstable, &Transaction::none())
@@ -1,20 +1,38 @@
+use crate::compaction::compaction_stats::CompactionStats;
+use crate::keyspace::keyspace::Keyspace;
 use crate::keyspace::keyspaces::Keyspaces;
+use crate::keyspace::keyspace_info::KeyspaceInfo;
 use crate::memtables::memtable_iterator::MemtableIterator;
+use crate::memtables::memtable_stats::MemtableStats;
 use crate::sst::ssttable_iterator::SSTableIterator;
+use crate::sst::sstable_meta::SSTableMeta;
 use crate::transactions::transaction::Transaction;
+use crate::transactions::transaction_info::TxnInfo;
 use crate::transactions::transaction_manager::{IsolationLevel, TransactionManager};
 use shared::iterators::merge_iterator::MergeIterator;
 use crate::utils::storage_engine_iterator::StorageEngineIterator;
+use crate::snapshot::{SnapshotEntry, SnapshotKeyspace, SnapshotReader, SnapshotWriter};
+use crate::utils::ttl;
+use crate::utils::backup_iterator::BackupIterator;
+use shared::iterators::storage_iterator::StorageIterator;
+use std::path::{Path, PathBuf};
 use shared::iterators::two_merge_iterators::TwoMergeIterator;
 use bytes::Bytes;
-use shared::{Flag, KeyspaceId, SimpleDbError, SimpleDbOptions};
+use shared::{CompactionStrategy, Flag, KeyspaceId, SSTableId, SimpleDbError, SimpleDbOptions};
 use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::Duration;
 use shared::logger::{logger, SimpleDbLayer};
 
+//Max number of entries written by each transaction of Storage::restore_snapshot
+const SNAPSHOT_RESTORE_BATCH_SIZE: usize = 1024;
+//Storage::restore_snapshot restores the snapshot into <base_path>.restoring before renaming it to base_path
+const RESTORING_SNAPSHOT_EXTENSION: &str = "restoring";
+
 pub struct Storage {
     transaction_manager: Arc<TransactionManager>,
     keyspaces: Keyspaces,
+    options: Arc<SimpleDbOptions>,
 }
 
 //Key value
@@ -30,6 +48,7 @@ pub type SimpleDbStorageIterator = StorageEngineIterator<
 impl Storage {
     pub fn create(options: Arc<SimpleDbOptions>) -> Result<Storage, SimpleDbError> {
         logger().info(SimpleDbLayer::Storage, "Starting storage engine!");
+        options.validate()?;
 
         let transaction_manager = Arc::new(
             TransactionManager::create_recover_from_log(options.clone())?
@@ -41,6 +60,7 @@ impl Storage {
         let mut storage = Storage {
             transaction_manager,
             keyspaces,
+            options,
         };
 
         storage.keyspaces.recover_from_manifest();
@@ -55,6 +75,7 @@ impl Storage {
         Storage {
             transaction_manager: Arc::new(TransactionManager::create_mock(simple_db_options.clone())),
             keyspaces: Keyspaces::mock(simple_db_options.clone()),
+            options: simple_db_options.clone(),
         }
     }
 
@@ -65,6 +86,12 @@ impl Storage {
         Ok(iterator)
     }
 
+    //Returns the live entries of the keyspace, see BackupIterator. All entries are read with the same transaction,
+    //so writes done after the iterator was created are not returned. The transaction is committed when it is dropped
+    pub fn backup_iterator(&self, keyspace_id: KeyspaceId) -> Result<BackupIterator<SimpleDbStorageIterator>, SimpleDbError> {
+        Ok(BackupIterator::create(self.scan_all(keyspace_id)?))
+    }
+
     pub fn scan_from(
         &self,
         keyspace_id: KeyspaceId,
@@ -88,6 +115,18 @@ impl Storage {
         Ok(keyspace.scan_from_key_with_transaction(transaction, key, inclusive))
     }
 
+    //Returns an iterator starting at the key, which only reads the SSTables whose bloom filter may contain the key.
+    //Entries after the key might be missing, see Keyspace::scan_key_with_transaction
+    pub fn scan_key_with_transaction(
+        &self,
+        transaction: &Transaction,
+        keyspace_id: KeyspaceId,
+        key: &Bytes,
+    ) -> Result<SimpleDbStorageIterator, SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        Ok(keyspace.scan_key_with_transaction(transaction, key))
+    }
+
     pub fn scan_all_with_transaction(
         &self,
         transaction: &Transaction,
@@ -97,13 +136,52 @@ impl Storage {
         Ok(keyspace.scan_all_with_transaction(transaction))
     }
 
+    //Keys that split the keyspace in at most n_chunks ranges of similar size, see SSTables::get_split_keys
+    pub fn get_split_keys(
+        &self,
+        keyspace_id: KeyspaceId,
+        n_chunks: usize
+    ) -> Result<Vec<Bytes>, SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        Ok(keyspace.get_split_keys(n_chunks))
+    }
+
     pub fn get(
         &self,
         keyspace_id: KeyspaceId,
         key: &Bytes
     ) -> Result<Option<Bytes>, SimpleDbError> {
         let transaction = self.transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
-        self.get_with_transaction(keyspace_id, &transaction, key)
+        let value = self.get_with_transaction(keyspace_id, &transaction, key)?;
+        self.transaction_manager.commit(&transaction)?;
+        Ok(value)
+    }
+
+    //Gets all the keys with the same transaction. The returned values are in the same order as keys.
+    //Keys are looked up in sorted order, so that consecutive lookups are likely to hit the same cached blocks
+    pub fn multi_get<K: AsRef<[u8]>>(
+        &self,
+        keyspace_id: KeyspaceId,
+        keys: &[K]
+    ) -> Result<Vec<Option<Bytes>>, SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        let mut sorted_keys_index: Vec<usize> = (0..keys.len()).collect();
+        sorted_keys_index.sort_by(|a, b| keys[*a].as_ref().cmp(keys[*b].as_ref()));
+
+        self.run_with_transaction(IsolationLevel::SnapshotIsolation, |transaction| {
+            let mut values: Vec<Option<Bytes>> = vec![None; keys.len()];
+            let mut prev_key_index: Option<usize> = None;
+            for key_index in sorted_keys_index {
+                let key = keys[key_index].as_ref();
+                values[key_index] = match prev_key_index {
+                    //Duplicated key
+                    Some(prev_key_index) if keys[prev_key_index].as_ref() == key => values[prev_key_index].clone(),
+                    _ => keyspace.get_with_transaction(transaction, &Bytes::copy_from_slice(key))?,
+                };
+                prev_key_index = Some(key_index);
+            }
+            Ok(values)
+        })
     }
 
     pub fn get_with_transaction(
@@ -123,7 +201,8 @@ impl Storage {
         value: &[u8]
     ) -> Result<(), SimpleDbError> {
         let transaction = self.transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
-        self.set_with_transaction(keyspace_id, &transaction, key, value)
+        self.set_with_transaction(keyspace_id, &transaction, key, value)?;
+        self.transaction_manager.commit(&transaction)
     }
 
     pub fn set_with_transaction(
@@ -137,13 +216,41 @@ impl Storage {
         keyspace.set_with_transaction(transaction, key, value)
     }
 
+    //The key is treated as absent once ttl has elapsed
+    pub fn set_with_ttl(
+        &self,
+        keyspace_id: KeyspaceId,
+        key: Bytes,
+        value: &[u8],
+        ttl: Duration,
+    ) -> Result<(), SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        self.run_with_transaction(IsolationLevel::SnapshotIsolation, |transaction| {
+            keyspace.set_with_ttl_with_transaction(transaction, key, value, ttl)
+        })
+    }
+
     pub fn delete(
         &self,
         keyspace_id: KeyspaceId,
         key: Bytes
     ) -> Result<(), SimpleDbError> {
         let transaction = self.transaction_manager.start_transaction(IsolationLevel::ReadUncommited);
-        self.delete_with_transaction(keyspace_id, &transaction, key)
+        self.delete_with_transaction(keyspace_id, &transaction, key)?;
+        self.transaction_manager.commit(&transaction)
+    }
+
+    //Deletes the keys in [start, end)
+    pub fn delete_range(
+        &self,
+        keyspace_id: KeyspaceId,
+        start: Bytes,
+        end: Bytes,
+    ) -> Result<(), SimpleDbError> {
+        let transaction = self.transaction_manager.start_transaction(IsolationLevel::ReadUncommited);
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        keyspace.delete_range_with_transaction(&transaction, start, end)?;
+        self.transaction_manager.commit(&transaction)
     }
 
     pub fn delete_with_transaction(
@@ -171,7 +278,7 @@ impl Storage {
             };
         }
 
-        Ok(())
+        self.transaction_manager.commit(&transaction)
     }
 
     pub fn get_flags(&self, keyspace_id: KeyspaceId) -> Result<Flag, SimpleDbError> {
@@ -179,6 +286,51 @@ impl Storage {
         Ok(keyspace.flags())
     }
 
+    //Writes to read only keyspaces return SimpleDbError::ReadOnlyKeyspace and they are not compacted.
+    //It is persisted in the keyspace descriptor
+    pub fn set_keyspace_read_only(&self, keyspace_id: KeyspaceId, read_only: bool) -> Result<(), SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        keyspace.set_read_only(read_only)
+    }
+
+    pub fn is_keyspace_read_only(&self, keyspace_id: KeyspaceId) -> Result<bool, SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        Ok(keyspace.is_read_only())
+    }
+
+    //Flush counters are since the storage was started
+    pub fn memtable_stats(&self, keyspace_id: KeyspaceId) -> Result<MemtableStats, SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        Ok(keyspace.memtable_stats())
+    }
+
+    //Counters of the compactions run in the keyspace since the storage was started
+    pub fn compaction_stats(&self, keyspace_id: KeyspaceId) -> Result<CompactionStats, SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        Ok(keyspace.compaction_stats())
+    }
+
+    //Only reads the SSTable metadata, which is kept in memory
+    pub fn describe_sstable(&self, keyspace_id: KeyspaceId, sstable_id: SSTableId) -> Result<SSTableMeta, SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        keyspace.describe_sstable(sstable_id)
+    }
+
+    //Hints compaction to keep the keys in [start, end) in upper levels, so that they are read faster. Only used by
+    //leveled compaction. Hot ranges are kept in memory, they have to be pinned again after a restart
+    pub fn pin_hot_range(&self, keyspace_id: KeyspaceId, start: Bytes, end: Bytes) -> Result<(), SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        keyspace.pin_hot_range(start, end);
+        Ok(())
+    }
+
+    //Compacts all the SSTables of the keyspace into the layout expected by the new strategy, which is used by the
+    //next compactions of the keyspace. The new strategy overrides SimpleDbOptions::compaction_strategy, also after restarts
+    pub fn migrate_compaction_strategy(&self, keyspace_id: KeyspaceId, new_strategy: CompactionStrategy) -> Result<(), SimpleDbError> {
+        let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+        keyspace.migrate_compaction_strategy(new_strategy)
+    }
+
     pub fn start_transaction_with_isolation(&self, isolation_level: IsolationLevel) -> Transaction {
         self.transaction_manager.start_transaction(isolation_level)
     }
@@ -195,13 +347,1134 @@ impl Storage {
         self.transaction_manager.rollback(transaction)
     }
 
+    //Active transactions, ordered by transaction ID
+    pub fn transaction_info(&self) -> Vec<TxnInfo> {
+        self.transaction_manager.transaction_info()
+    }
+
+    //Discards the writes done by the transaction after the savepoint was created, see Transaction::savepoint
+    pub fn rollback_to_savepoint(&self, transaction: &Transaction, name: &str) -> Result<(), SimpleDbError> {
+        for write_to_undo in self.transaction_manager.rollback_to_savepoint(transaction, name)? {
+            let keyspace = self.keyspaces.get_keyspace(write_to_undo.keyspace_id)?;
+            keyspace.restore(transaction, write_to_undo.key, write_to_undo.previous_value)?;
+        }
+
+        Ok(())
+    }
+
     pub fn create_keyspace(&self, flag: Flag) -> Result<KeyspaceId, SimpleDbError> {
         let keyspace = self.keyspaces.create_keyspace(flag)?;
         keyspace.start_compaction_thread();
         Ok(keyspace.keyspace_id())
     }
 
+    //Removes the keyspace and all of its data
+    pub fn drop_keyspace(&self, keyspace_id: KeyspaceId) -> Result<(), SimpleDbError> {
+        self.keyspaces.drop_keyspace(keyspace_id)
+    }
+
     pub fn get_keyspaces_id(&self) -> Vec<KeyspaceId> {
         self.keyspaces.get_keyspaces_id()
     }
-}
\ No newline at end of file
+
+    //Changes an option while running. Supported options: compaction_task_frequency_ms, which is used from the next
+    //compaction cycle, max_memtables_inactive & compaction_max_bytes_per_sec. The change is not persisted
+    pub fn set_option(&self, option_name: &str, value: usize) -> Result<(), SimpleDbError> {
+        self.keyspaces.set_option(option_name, value)
+    }
+
+    //Sorted by keyspace ID
+    pub fn list_keyspaces(&self) -> Vec<KeyspaceInfo> {
+        self.keyspaces.list_keyspaces()
+    }
+
+    //Writes all the keyspaces to a single file, which can be restored with Storage::restore_snapshot.
+    //All keyspaces are read with the same transaction, so the snapshot only contains the writes of the transactions
+    //committed before it was started. Compaction strategies set with migrate_compaction_strategy are not included.
+    //Entries are written while they are scanned, so the keyspaces are not loaded in memory
+    pub fn create_snapshot(&self, path: &Path) -> Result<(), SimpleDbError> {
+        let transaction = self.transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        let result = self.write_snapshot(path, &transaction);
+        self.transaction_manager.commit(&transaction)?;
+        result
+    }
+
+    //Creates a new storage in base_path with the data of a snapshot written by Storage::create_snapshot.
+    //The rest of the options are taken from options. Keyspaces keep their IDs. base_path must not exist or be empty.
+    //The snapshot is restored into a temporary directory, which is renamed to base_path once all the entries
+    //have been written, so base_path never contains a partially restored storage.
+    //Entries are written in transactions of at most SNAPSHOT_RESTORE_BATCH_SIZE entries
+    pub fn restore_snapshot(
+        path: &Path,
+        base_path: &str,
+        options: &Arc<SimpleDbOptions>,
+    ) -> Result<Storage, SimpleDbError> {
+        let file_system = &options.file_system;
+        let base_path_buf = PathBuf::from(base_path);
+        if file_system.exists(&base_path_buf) && !file_system.list(&base_path_buf).map_err(SimpleDbError::CannotWriteSnapshot)?.is_empty() {
+            return Err(SimpleDbError::CannotWriteSnapshot(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists, format!("{} is not empty", base_path)
+            )));
+        }
+
+        //Left by a previous restore that failed
+        let restoring_path = base_path_buf.with_extension(RESTORING_SNAPSHOT_EXTENSION);
+        if file_system.exists(&restoring_path) {
+            file_system.remove_dir_all(&restoring_path).map_err(SimpleDbError::CannotWriteSnapshot)?;
+        }
+        file_system.create_dir_all(&restoring_path).map_err(SimpleDbError::CannotWriteSnapshot)?;
+
+        if let Err(error) = Self::restore_snapshot_into(path, &restoring_path, options) {
+            let _ = file_system.remove_dir_all(&restoring_path);
+            return Err(error);
+        }
+
+        if file_system.exists(&base_path_buf) {
+            file_system.remove_dir_all(&base_path_buf).map_err(SimpleDbError::CannotWriteSnapshot)?;
+        }
+        file_system.rename(&restoring_path, &base_path_buf).map_err(SimpleDbError::CannotWriteSnapshot)?;
+
+        Storage::create(shared::start_simpledb_options_builder_from(options)
+            .base_path(base_path)
+            .build_arc())
+    }
+
+    //Compaction threads are not started, so no files are written once it returns
+    fn restore_snapshot_into(
+        path: &Path,
+        restoring_path: &Path,
+        options: &Arc<SimpleDbOptions>,
+    ) -> Result<(), SimpleDbError> {
+        let mut snapshot_reader = SnapshotReader::open(&options.file_system, path)?;
+
+        let options = shared::start_simpledb_options_builder_from(options)
+            .base_path(restoring_path.to_str().unwrap())
+            .build_arc();
+        let storage = Storage::create(options)?;
+
+        while let Some(snapshot_keyspace) = snapshot_reader.next_keyspace()? {
+            let keyspace = storage.keyspaces.create_keyspace_with_id(snapshot_keyspace.keyspace_id, snapshot_keyspace.flags)?;
+
+            loop {
+                let transaction = storage.transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+                let has_more_entries = match storage.restore_snapshot_batch(&keyspace, &mut snapshot_reader, &transaction) {
+                    Ok(has_more_entries) => has_more_entries,
+                    Err(error) => {
+                        let _ = storage.transaction_manager.rollback(&transaction);
+                        return Err(error);
+                    }
+                };
+                storage.transaction_manager.commit(&transaction)?;
+                if !has_more_entries {
+                    break;
+                }
+            }
+
+            //Done after the entries have been written, as read only keyspaces reject writes
+            if snapshot_keyspace.read_only {
+                storage.set_keyspace_read_only(snapshot_keyspace.keyspace_id, true)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    //Writes at most SNAPSHOT_RESTORE_BATCH_SIZE entries of the current keyspace of the snapshot.
+    //Returns false once all the entries of the keyspace have been written
+    fn restore_snapshot_batch(
+        &self,
+        keyspace: &Arc<Keyspace>,
+        snapshot_reader: &mut SnapshotReader,
+        transaction: &Transaction,
+    ) -> Result<bool, SimpleDbError> {
+        for _ in 0..SNAPSHOT_RESTORE_BATCH_SIZE {
+            let entry = match snapshot_reader.next_entry()? {
+                Some(entry) => entry,
+                None => return Ok(false),
+            };
+            match entry.expire_at {
+                Some(expire_at) => keyspace.set_with_expire_at_with_transaction(transaction, entry.key, &entry.value, expire_at)?,
+                None => keyspace.set_with_transaction(transaction, entry.key, &entry.value)?,
+            }
+        }
+
+        Ok(true)
+    }
+
+    //The transaction is committed if function succeeds, and rolled back if it fails
+    fn run_with_transaction<T>(
+        &self,
+        isolation_level: IsolationLevel,
+        function: impl FnOnce(&Transaction) -> Result<T, SimpleDbError>,
+    ) -> Result<T, SimpleDbError> {
+        let transaction = self.transaction_manager.start_transaction(isolation_level);
+        match function(&transaction) {
+            Ok(result) => {
+                self.transaction_manager.commit(&transaction)?;
+                Ok(result)
+            },
+            Err(error) => {
+                let _ = self.transaction_manager.rollback(&transaction);
+                Err(error)
+            }
+        }
+    }
+
+    //If it fails, the file is left without crc, so it cannot be restored
+    fn write_snapshot(&self, path: &Path, transaction: &Transaction) -> Result<(), SimpleDbError> {
+        let keyspaces_id = self.keyspaces.get_keyspaces_id();
+        let mut snapshot_writer = SnapshotWriter::create(&self.options.file_system, path, keyspaces_id.len())?;
+
+        for keyspace_id in keyspaces_id {
+            let keyspace = self.keyspaces.get_keyspace(keyspace_id)?;
+            snapshot_writer.start_keyspace(&SnapshotKeyspace {
+                read_only: keyspace.is_read_only(),
+                flags: keyspace.flags(),
+                keyspace_id,
+            })?;
+
+            let mut iterator = keyspace.scan_all_with_transaction(transaction);
+            iterator.keep_expiration_time();
+            let mut iterator = BackupIterator::create(iterator);
+            while iterator.next() {
+                let key = iterator.key();
+                let value = Bytes::copy_from_slice(iterator.value());
+                snapshot_writer.add_entry(&SnapshotEntry {
+                    expire_at: ttl::expire_at(key, &value),
+                    value: ttl::user_value(key, value),
+                    key: key.as_bytes().clone(),
+                })?;
+            }
+            if let Some(error) = iterator.take_error() {
+                return Err(error);
+            }
+
+            snapshot_writer.end_keyspace()?;
+        }
+
+        snapshot_writer.finish()
+    }
+}
+#[cfg(test)]
+mod test {
+    use crate::transactions::transaction_manager::IsolationLevel;
+    use crate::utils::faulty_file_system::FaultyFileSystem;
+    use crate::storage::SNAPSHOT_RESTORE_BATCH_SIZE;
+    use crate::Storage;
+    use bytes::Bytes;
+    use shared::logger::Logger;
+    use shared::iterators::storage_iterator::StorageIterator;
+    use shared::{start_simpledb_options_builder_from, FileSystem, InMemoryFileSystem, KeyspaceId, ManifestFsyncPolicy, SimpleDbError, SimpleDbOptions, SimpleLeveledCompactionOptions};
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn in_memory_file_system() {
+        let file_system = Arc::new(InMemoryFileSystem::create());
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(256)
+            .max_memtables_inactive(1)
+            .build_arc();
+        Logger::init(options.clone());
+
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let transaction = storage.start_transaction();
+        for i in 0..100 {
+            let key = Bytes::from(format!("key-{:03}", i));
+            storage.set_with_transaction(keyspace_id, &transaction, key, format!("value-{}", i).as_bytes()).unwrap();
+        }
+        storage.commit_transaction(&transaction).unwrap();
+
+        let keyspace_files = file_system.list(Path::new(&format!("/simpledb/{}", keyspace_id))).unwrap();
+        assert!(keyspace_files.iter().any(|file| file.file_name().unwrap().to_str().unwrap().starts_with("sst-")));
+        assert!(!Path::new(&format!("/simpledb/{}", keyspace_id)).exists());
+
+        for i in 0..100 {
+            assert_eq!(
+                storage.get(keyspace_id, &Bytes::from(format!("key-{:03}", i))).unwrap(),
+                Some(Bytes::from(format!("value-{}", i)))
+            );
+        }
+
+        //Data is recovered from the SSTables & WAL stored in memory
+        let storage = Storage::create(options).unwrap();
+        for i in 0..100 {
+            assert_eq!(
+                storage.get(keyspace_id, &Bytes::from(format!("key-{:03}", i))).unwrap(),
+                Some(Bytes::from(format!("value-{}", i)))
+            );
+        }
+    }
+
+    #[test]
+    fn crash_at_every_write() {
+        let n_writes = count_workload_writes();
+
+        for n_writes_before_crash in 1..=n_writes {
+            let file_system = FaultyFileSystem::create();
+            let options = faulty_file_system_options(&file_system);
+            let storage = Storage::create(options.clone()).unwrap();
+            let keyspace_id = storage.create_keyspace(0).unwrap();
+
+            file_system.crash_after_writes(n_writes_before_crash);
+            let committed = write_keys(&storage, keyspace_id);
+            file_system.crash();
+
+            assert_committed_keys_recovered(&options, keyspace_id, &committed);
+        }
+    }
+
+    #[test]
+    fn failed_write_at_every_write() {
+        let n_writes = count_workload_writes();
+
+        for n_failed_write in 1..=n_writes {
+            let file_system = FaultyFileSystem::create();
+            let options = faulty_file_system_options(&file_system);
+            let storage = Storage::create(options.clone()).unwrap();
+            let keyspace_id = storage.create_keyspace(0).unwrap();
+
+            file_system.fail_write(n_failed_write);
+            let committed = write_keys(&storage, keyspace_id);
+            assert!(committed.len() < N_KEYS);
+
+            assert_committed_keys_recovered(&options, keyspace_id, &committed);
+        }
+    }
+
+    #[test]
+    fn short_reads() {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let committed = write_keys(&storage, keyspace_id);
+        assert_eq!(committed.len(), N_KEYS);
+
+        file_system.short_reads(true);
+        assert_committed_keys_recovered(&options, keyspace_id, &committed);
+    }
+
+    #[test]
+    fn multi_get() {
+        let storage = Storage::create(in_memory_options()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        storage.set(keyspace_id, Bytes::from("b"), &[2]).unwrap();
+        storage.set(keyspace_id, Bytes::from("d"), &[4]).unwrap();
+        storage.set(keyspace_id, Bytes::from("a"), &[1]).unwrap();
+        storage.delete(keyspace_id, Bytes::from("a")).unwrap();
+
+        let values = storage.multi_get(keyspace_id, &["d", "c", "b", "a"]).unwrap();
+        assert_eq!(values, vec![Some(Bytes::from(vec![4])), None, Some(Bytes::from(vec![2])), None]);
+
+        let values = storage.multi_get(keyspace_id, &["b", "d", "b", "e", "e"]).unwrap();
+        assert_eq!(values, vec![
+            Some(Bytes::from(vec![2])), Some(Bytes::from(vec![4])), Some(Bytes::from(vec![2])), None, None
+        ]);
+        assert!(storage.multi_get::<&str>(keyspace_id, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_committed_non_repeatable_read() {
+        let options = in_memory_options();
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, key(1), &value(1)).unwrap();
+        storage.commit_transaction(&transaction).unwrap();
+
+        let reader = storage.start_transaction_with_isolation(IsolationLevel::ReadCommitted);
+        let snapshot_reader = storage.start_transaction_with_isolation(IsolationLevel::SnapshotIsolation);
+        assert_eq!(storage.get_with_transaction(keyspace_id, &reader, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+
+        let writer = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &writer, key(1), &value(2)).unwrap();
+        storage.set_with_transaction(keyspace_id, &writer, key(2), &value(2)).unwrap();
+        //Uncommitted writes are not visible
+        assert_eq!(storage.get_with_transaction(keyspace_id, &reader, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+        storage.commit_transaction(&writer).unwrap();
+
+        //The second read sees the write committed after the reader started
+        assert_eq!(storage.get_with_transaction(keyspace_id, &reader, &key(1)).unwrap(), Some(Bytes::from(value(2))));
+        assert_eq!(storage.get_with_transaction(keyspace_id, &snapshot_reader, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+        let mut iterator = storage.scan_all_with_transaction(&reader, keyspace_id).unwrap();
+        let mut keys = Vec::new();
+        while iterator.next() {
+            keys.push(Bytes::copy_from_slice(iterator.key().as_bytes()));
+        }
+        keys.dedup();
+        assert_eq!(keys, vec![key(1), key(2)]);
+
+        //Own writes are visible
+        storage.set_with_transaction(keyspace_id, &reader, key(3), &value(3)).unwrap();
+        assert_eq!(storage.get_with_transaction(keyspace_id, &reader, &key(3)).unwrap(), Some(Bytes::from(value(3))));
+    }
+
+    #[test]
+    fn serializable_read_write_conflict() {
+        let options = in_memory_options();
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, key(1), &value(1)).unwrap();
+        storage.commit_transaction(&transaction).unwrap();
+
+        let reader = storage.start_transaction_with_isolation(IsolationLevel::Serializable);
+        let writer = storage.start_transaction_with_isolation(IsolationLevel::Serializable);
+        assert_eq!(storage.get_with_transaction(keyspace_id, &reader, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+        storage.set_with_transaction(keyspace_id, &writer, key(1), &value(2)).unwrap();
+        storage.set_with_transaction(keyspace_id, &reader, key(2), &value(2)).unwrap();
+        storage.commit_transaction(&writer).unwrap();
+
+        //The reader read key 1, which has been modified by the writer, so it cannot be serialized after the writer
+        assert!(matches!(storage.commit_transaction(&reader), Err(SimpleDbError::SerializationFailure(_))));
+        assert_eq!(storage.get(keyspace_id, &key(1)).unwrap(), Some(Bytes::from(value(2))));
+        assert_eq!(storage.get(keyspace_id, &key(2)).unwrap(), None);
+
+        //Retry
+        let reader = storage.start_transaction_with_isolation(IsolationLevel::Serializable);
+        assert_eq!(storage.get_with_transaction(keyspace_id, &reader, &key(1)).unwrap(), Some(Bytes::from(value(2))));
+        storage.set_with_transaction(keyspace_id, &reader, key(2), &value(2)).unwrap();
+        storage.commit_transaction(&reader).unwrap();
+        assert_eq!(storage.get(keyspace_id, &key(2)).unwrap(), Some(Bytes::from(value(2))));
+    }
+
+    #[test]
+    fn serializable_disjoint_keys() {
+        let options = in_memory_options();
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+
+        let first = storage.start_transaction_with_isolation(IsolationLevel::Serializable);
+        let second = storage.start_transaction_with_isolation(IsolationLevel::Serializable);
+        storage.get_with_transaction(keyspace_id, &first, &key(1)).unwrap();
+        storage.get_with_transaction(keyspace_id, &second, &key(2)).unwrap();
+        storage.set_with_transaction(keyspace_id, &first, key(3), &value(3)).unwrap();
+        storage.set_with_transaction(keyspace_id, &second, key(4), &value(4)).unwrap();
+
+        storage.commit_transaction(&second).unwrap();
+        storage.commit_transaction(&first).unwrap();
+    }
+
+    #[test]
+    fn rollback_to_savepoint() {
+        let options = in_memory_options();
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, key(1), &value(1)).unwrap();
+        storage.set_with_transaction(keyspace_id, &transaction, key(2), &value(2)).unwrap();
+
+        transaction.savepoint("a");
+        storage.set_with_transaction(keyspace_id, &transaction, key(1), &value(10)).unwrap();
+        storage.delete_with_transaction(keyspace_id, &transaction, key(2)).unwrap();
+        storage.set_with_transaction(keyspace_id, &transaction, key(3), &value(3)).unwrap();
+        transaction.savepoint("b");
+        storage.set_with_transaction(keyspace_id, &transaction, key(4), &value(4)).unwrap();
+
+        storage.rollback_to_savepoint(&transaction, "a").unwrap();
+        assert_eq!(storage.get_with_transaction(keyspace_id, &transaction, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+        assert_eq!(storage.get_with_transaction(keyspace_id, &transaction, &key(2)).unwrap(), Some(Bytes::from(value(2))));
+        assert_eq!(storage.get_with_transaction(keyspace_id, &transaction, &key(3)).unwrap(), None);
+        assert_eq!(storage.get_with_transaction(keyspace_id, &transaction, &key(4)).unwrap(), None);
+        //Savepoints created after "a" are removed
+        assert!(matches!(storage.rollback_to_savepoint(&transaction, "b"), Err(SimpleDbError::SavepointNotFound(_))));
+
+        //"a" is kept
+        storage.set_with_transaction(keyspace_id, &transaction, key(5), &value(5)).unwrap();
+        storage.rollback_to_savepoint(&transaction, "a").unwrap();
+        storage.set_with_transaction(keyspace_id, &transaction, key(6), &value(6)).unwrap();
+        storage.commit_transaction(&transaction).unwrap();
+
+        assert_eq!(storage.get(keyspace_id, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+        assert_eq!(storage.get(keyspace_id, &key(2)).unwrap(), Some(Bytes::from(value(2))));
+        assert_eq!(storage.get(keyspace_id, &key(5)).unwrap(), None);
+        assert_eq!(storage.get(keyspace_id, &key(6)).unwrap(), Some(Bytes::from(value(6))));
+    }
+
+    #[test]
+    fn values_are_not_mistaken_for_tombstones() {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+
+        storage.set(keyspace_id, key(0), &[10]).unwrap();
+        storage.set(keyspace_id, key(1), &[]).unwrap();
+        storage.set(keyspace_id, key(2), &value(2)).unwrap();
+        storage.delete(keyspace_id, key(2)).unwrap();
+        let assert_entries = |storage: &Storage| {
+            assert_eq!(storage.get(keyspace_id, &key(0)).unwrap(), Some(Bytes::from(vec![10])));
+            assert_eq!(storage.get(keyspace_id, &key(1)).unwrap(), Some(Bytes::new()));
+            assert_eq!(storage.get(keyspace_id, &key(2)).unwrap(), None);
+        };
+        assert_entries(&storage);
+
+        //Memtables are flushed to SSTables
+        for i in 3..20 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        assert_entries(&storage);
+
+        drop(storage);
+        assert_entries(&Storage::create(options).unwrap());
+    }
+
+    #[test]
+    fn batched_manifest_fsyncs() {
+        assert!(count_workload_syncs(ManifestFsyncPolicy::Batched(16)) < count_workload_syncs(ManifestFsyncPolicy::EveryOperation));
+
+        //The manifest operations that weren't fsynced are lost
+        let file_system = FaultyFileSystem::create();
+        let options = start_simpledb_options_builder_from(&faulty_file_system_options(&file_system))
+            .manifest_fsync_policy(ManifestFsyncPolicy::Batched(16))
+            .build_arc();
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let committed = write_keys(&storage, keyspace_id);
+        drop(storage);
+        file_system.crash();
+
+        assert_committed_keys_recovered(&options, keyspace_id, &committed);
+    }
+
+    #[test]
+    fn ttl() {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+
+        storage.set_with_ttl(keyspace_id, key(0), &value(0), Duration::ZERO).unwrap();
+        storage.set_with_ttl(keyspace_id, key(1), &value(1), Duration::from_secs(3600)).unwrap();
+        //The expired version doesn't hide the previous one
+        storage.set(keyspace_id, key(2), &value(2)).unwrap();
+        storage.set_with_ttl(keyspace_id, key(2), &value(100), Duration::ZERO).unwrap();
+        let assert_entries = |storage: &Storage| {
+            assert_eq!(storage.get(keyspace_id, &key(0)).unwrap(), None);
+            assert_eq!(storage.get(keyspace_id, &key(1)).unwrap(), Some(Bytes::from(value(1))));
+            assert_eq!(storage.get(keyspace_id, &key(2)).unwrap(), Some(Bytes::from(value(2))));
+
+            let mut iterator = storage.scan_all(keyspace_id).unwrap();
+            let mut scanned = Vec::new();
+            while iterator.next() && iterator.key().bytes_lt_bytes(&key(3)) {
+                scanned.push((iterator.key().as_bytes().clone(), Bytes::copy_from_slice(iterator.value())));
+            }
+            assert_eq!(scanned, vec![(key(1), Bytes::from(value(1))), (key(2), Bytes::from(value(2)))]);
+        };
+        assert_entries(&storage);
+
+        //Memtables are flushed to SSTables
+        for i in 3..20 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        assert_entries(&storage);
+
+        drop(storage);
+        assert_entries(&Storage::create(options).unwrap());
+    }
+
+    #[test]
+    fn delete_range() {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+
+        for i in 0..10 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        storage.delete_range(keyspace_id, key(3), key(7)).unwrap();
+        //Written after the range tombstone, so it is not deleted
+        storage.set(keyspace_id, key(5), &value(105)).unwrap();
+
+        let assert_entries = |storage: &Storage| {
+            for i in [0, 2, 7, 9] {
+                assert_eq!(storage.get(keyspace_id, &key(i)).unwrap(), Some(Bytes::from(value(i))));
+            }
+            for i in [3, 4, 6] {
+                assert_eq!(storage.get(keyspace_id, &key(i)).unwrap(), None);
+            }
+            assert_eq!(storage.get(keyspace_id, &key(5)).unwrap(), Some(Bytes::from(value(105))));
+
+            let mut iterator = storage.scan_all(keyspace_id).unwrap();
+            let mut scanned = Vec::new();
+            while iterator.next() && iterator.key().bytes_lt_bytes(&key(10)) {
+                scanned.push(iterator.key().as_bytes().clone());
+            }
+            assert_eq!(scanned, vec![key(0), key(1), key(2), key(5), key(7), key(8), key(9)]);
+        };
+        assert_entries(&storage);
+
+        //Memtables are flushed to SSTables
+        for i in 10..30 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        assert_entries(&storage);
+
+        drop(storage);
+        assert_entries(&Storage::create(options).unwrap());
+    }
+
+    #[test]
+    fn rollback_to_savepoint_flushed_writes() {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let transaction = storage.start_transaction();
+        for i in 0..N_KEYS {
+            storage.set_with_transaction(keyspace_id, &transaction, key(i), &value(i)).unwrap();
+        }
+
+        //Memtables are small, so the writes after the savepoint are flushed to SSTables
+        transaction.savepoint("a");
+        for i in 0..N_KEYS {
+            storage.set_with_transaction(keyspace_id, &transaction, key(i), &value(i + 100)).unwrap();
+        }
+        storage.rollback_to_savepoint(&transaction, "a").unwrap();
+        storage.commit_transaction(&transaction).unwrap();
+
+        for i in 0..N_KEYS {
+            assert_eq!(storage.get(keyspace_id, &key(i)).unwrap(), Some(Bytes::from(value(i))));
+        }
+        //Replaced values are recovered from the WAL & SSTables
+        assert_committed_keys_recovered(&options, keyspace_id, &(0..N_KEYS).collect());
+    }
+
+    #[test]
+    fn read_only_keyspace() {
+        let options = in_memory_options();
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        storage.set(keyspace_id, Bytes::from("a"), &[1]).unwrap();
+
+        storage.set_keyspace_read_only(keyspace_id, true).unwrap();
+
+        assert!(storage.is_keyspace_read_only(keyspace_id).unwrap());
+        assert!(matches!(storage.set(keyspace_id, Bytes::from("b"), &[2]), Err(SimpleDbError::ReadOnlyKeyspace(_))));
+        assert!(matches!(storage.delete(keyspace_id, Bytes::from("a")), Err(SimpleDbError::ReadOnlyKeyspace(_))));
+        let n_active_transactions = storage.transaction_info().len();
+        assert!(matches!(
+            storage.set_with_ttl(keyspace_id, Bytes::from("b"), &[2], Duration::from_secs(3600)),
+            Err(SimpleDbError::ReadOnlyKeyspace(_))
+        ));
+        //The transaction of the failed write is rolled back
+        assert_eq!(storage.transaction_info().len(), n_active_transactions);
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("a")).unwrap(), Some(Bytes::from(vec![1])));
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("b")).unwrap(), None);
+
+        //Persisted in the keyspace descriptor
+        let storage = Storage::create(options).unwrap();
+        assert!(storage.is_keyspace_read_only(keyspace_id).unwrap());
+        assert!(matches!(storage.set(keyspace_id, Bytes::from("b"), &[2]), Err(SimpleDbError::ReadOnlyKeyspace(_))));
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("a")).unwrap(), Some(Bytes::from(vec![1])));
+
+        storage.set_keyspace_read_only(keyspace_id, false).unwrap();
+        storage.set(keyspace_id, Bytes::from("b"), &[2]).unwrap();
+        assert_eq!(storage.get(keyspace_id, &Bytes::from("b")).unwrap(), Some(Bytes::from(vec![2])));
+    }
+
+    #[test]
+    fn memtable_stats() {
+        let options = start_simpledb_options_builder_from(&in_memory_options())
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(0)
+            .build_arc();
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+
+        let stats = storage.memtable_stats(keyspace_id).unwrap();
+        assert_eq!(stats.active_bytes, 0);
+        assert_eq!(stats.flushes_total, 0);
+
+        let mut prev_active_bytes = 0;
+        let mut n_keys_written = 0;
+        while storage.memtable_stats(keyspace_id).unwrap().flushes_total == 0 {
+            let stats = storage.memtable_stats(keyspace_id).unwrap();
+            assert_eq!(stats.active_entries, n_keys_written);
+            assert!(n_keys_written == 0 || stats.active_bytes > prev_active_bytes);
+            prev_active_bytes = stats.active_bytes;
+
+            storage.set(keyspace_id, Bytes::from(format!("key-{:03}", n_keys_written)), &[0; 16]).unwrap();
+            n_keys_written += 1;
+        }
+
+        //The write that didn't fit in the full memtable went to the new one
+        let stats = storage.memtable_stats(keyspace_id).unwrap();
+        assert_eq!(stats.flushes_total, 1);
+        assert_eq!(stats.active_entries, 1);
+        assert!(stats.active_bytes < prev_active_bytes);
+        assert_eq!(stats.inactive_count, 0);
+        assert!(stats.bytes_flushed_total >= 128);
+    }
+
+    #[test]
+    fn snapshot() {
+        let options = in_memory_options();
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let read_only_keyspace_id = storage.create_keyspace(0).unwrap();
+        for i in 0..20 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        storage.set_with_ttl(keyspace_id, key(20), &value(20), Duration::from_secs(3600)).unwrap();
+        storage.set_with_ttl(keyspace_id, key(21), &value(21), Duration::ZERO).unwrap();
+        storage.delete(keyspace_id, key(5)).unwrap();
+        storage.set(read_only_keyspace_id, key(0), &value(0)).unwrap();
+        storage.set_keyspace_read_only(read_only_keyspace_id, true).unwrap();
+        //Not committed when the snapshot is taken
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, key(30), &value(30)).unwrap();
+
+        storage.create_snapshot(Path::new("/snapshot")).unwrap();
+
+        storage.commit_transaction(&transaction).unwrap();
+        storage.set(keyspace_id, key(0), &value(100)).unwrap();
+        storage.delete(keyspace_id, key(1)).unwrap();
+        storage.set(keyspace_id, key(31), &value(31)).unwrap();
+        let new_keyspace_id = storage.create_keyspace(0).unwrap();
+
+        options.file_system.create_dir_all(Path::new("/restored")).unwrap();
+        let restored = Storage::restore_snapshot(Path::new("/snapshot"), "/restored", &options).unwrap();
+        let assert_snapshot_point = |restored: &Storage| {
+            let mut keyspaces_id = restored.get_keyspaces_id();
+            keyspaces_id.sort();
+            assert_eq!(keyspaces_id, vec![keyspace_id, read_only_keyspace_id]);
+            assert!(restored.is_keyspace_read_only(read_only_keyspace_id).unwrap());
+            assert_eq!(restored.get(read_only_keyspace_id, &key(0)).unwrap(), Some(Bytes::from(value(0))));
+
+            let mut iterator = restored.scan_all(keyspace_id).unwrap();
+            let mut scanned = Vec::new();
+            while iterator.next() {
+                scanned.push((iterator.key().as_bytes().clone(), Bytes::copy_from_slice(iterator.value())));
+            }
+            let expected: Vec<(Bytes, Bytes)> = (0..21)
+                .filter(|i| *i != 5)
+                .map(|i| (key(i), Bytes::from(value(i))))
+                .collect();
+            assert_eq!(scanned, expected);
+        };
+        assert_snapshot_point(&restored);
+
+        drop(restored);
+        let options = start_simpledb_options_builder_from(&options)
+            .base_path("/restored")
+            .build_arc();
+        let restored = Storage::create(options).unwrap();
+        assert_snapshot_point(&restored);
+        //New keyspaces don't reuse the restored IDs
+        assert!(restored.create_keyspace(0).unwrap() >= new_keyspace_id);
+    }
+
+    #[test]
+    fn snapshot_restored_in_multiple_batches() {
+        let options = in_memory_options();
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let n_entries = SNAPSHOT_RESTORE_BATCH_SIZE * 2 + 1;
+        for i in 0..n_entries {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        storage.set_keyspace_read_only(keyspace_id, true).unwrap();
+
+        storage.create_snapshot(Path::new("/snapshot")).unwrap();
+        options.file_system.create_dir_all(Path::new("/restored")).unwrap();
+        let restored = Storage::restore_snapshot(Path::new("/snapshot"), "/restored", &options).unwrap();
+
+        assert!(restored.is_keyspace_read_only(keyspace_id).unwrap());
+        let mut iterator = restored.scan_all(keyspace_id).unwrap();
+        let mut n_scanned = 0;
+        while iterator.next() {
+            n_scanned += 1;
+        }
+        assert_eq!(n_scanned, n_entries);
+        for i in 0..n_entries {
+            assert_eq!(restored.get(keyspace_id, &key(i)).unwrap(), Some(Bytes::from(value(i))));
+        }
+    }
+
+    #[test]
+    fn snapshot_restore_failed() {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        for i in 0..SNAPSHOT_RESTORE_BATCH_SIZE * 2 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        storage.create_snapshot(Path::new("/snapshot")).unwrap();
+
+        //Fails after the first batch has been committed
+        file_system.fail_write(SNAPSHOT_RESTORE_BATCH_SIZE * 3);
+        assert!(Storage::restore_snapshot(Path::new("/snapshot"), "/restored", &options).is_err());
+        assert!(!file_system.exists(Path::new("/restored")));
+        assert!(!file_system.exists(Path::new("/restored.restoring")));
+
+        let restored = Storage::restore_snapshot(Path::new("/snapshot"), "/restored", &options).unwrap();
+        for i in 0..SNAPSHOT_RESTORE_BATCH_SIZE * 2 {
+            assert_eq!(restored.get(keyspace_id, &key(i)).unwrap(), Some(Bytes::from(value(i))));
+        }
+    }
+
+    #[test]
+    fn snapshot_restore_into_non_empty_base_path() {
+        let options = in_memory_options();
+        let storage = Storage::create(options.clone()).unwrap();
+        storage.create_keyspace(0).unwrap();
+        storage.create_snapshot(Path::new("/snapshot")).unwrap();
+
+        assert!(Storage::restore_snapshot(Path::new("/snapshot"), "/simpledb", &options).is_err());
+    }
+
+    #[test]
+    fn backup_iterator() {
+        let options = in_memory_options();
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        for i in 0..10 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        storage.set(keyspace_id, key(2), &value(102)).unwrap();
+        storage.delete(keyspace_id, key(3)).unwrap();
+
+        let mut iterator = storage.backup_iterator(keyspace_id).unwrap();
+        assert!(iterator.next());
+        assert_eq!(iterator.key().as_bytes(), &key(0));
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                storage.set(keyspace_id, key(1), &value(101)).unwrap();
+                storage.delete(keyspace_id, key(4)).unwrap();
+                storage.set(keyspace_id, key(3), &value(103)).unwrap();
+                storage.set(keyspace_id, key(20), &value(20)).unwrap();
+            });
+        });
+
+        let mut scanned = Vec::new();
+        while iterator.next() {
+            scanned.push((iterator.key().as_bytes().clone(), Bytes::copy_from_slice(iterator.value())));
+        }
+        let expected: Vec<(Bytes, Bytes)> = (1..10)
+            .filter(|i| *i != 3)
+            .map(|i| (key(i), Bytes::from(value(if i == 2 { 102 } else { i }))))
+            .collect();
+        assert_eq!(scanned, expected);
+
+        drop(iterator);
+        assert_eq!(storage.get(keyspace_id, &key(3)).unwrap(), Some(Bytes::from(value(103))));
+        assert_eq!(storage.get(keyspace_id, &key(4)).unwrap(), None);
+    }
+
+    #[test]
+    fn drop_keyspace() {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options.clone()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let other_keyspace_id = storage.create_keyspace(0).unwrap();
+        //Memtables are flushed to SSTables
+        for i in 0..20 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        storage.set(other_keyspace_id, key(0), &value(0)).unwrap();
+        let keyspace_path = shared::get_directory_usize(&options.base_path, keyspace_id);
+        assert!(file_system.list(&keyspace_path).unwrap().len() > 2);
+
+        storage.drop_keyspace(keyspace_id).unwrap();
+
+        assert!(!file_system.exists(&keyspace_path));
+        assert!(matches!(storage.get(keyspace_id, &key(0)), Err(SimpleDbError::KeyspaceNotFound(_))));
+        assert!(matches!(storage.set(keyspace_id, key(0), &value(0)), Err(SimpleDbError::KeyspaceNotFound(_))));
+        assert!(matches!(storage.drop_keyspace(keyspace_id), Err(SimpleDbError::KeyspaceNotFound(_))));
+
+        drop(storage);
+        let storage = Storage::create(options).unwrap();
+        assert_eq!(storage.get_keyspaces_id(), vec![other_keyspace_id]);
+        assert!(matches!(storage.scan_all(keyspace_id), Err(SimpleDbError::KeyspaceNotFound(_))));
+        assert_eq!(storage.get(other_keyspace_id, &key(0)).unwrap(), Some(Bytes::from(value(0))));
+    }
+
+    #[test]
+    fn list_keyspaces() {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options).unwrap();
+        let small_keyspace_id = storage.create_keyspace(0).unwrap();
+        let large_keyspace_id = storage.create_keyspace(2).unwrap();
+        storage.set(small_keyspace_id, key(0), &value(0)).unwrap();
+        //Memtables are flushed to SSTables
+        for i in 0..20 {
+            storage.set(large_keyspace_id, key(i), &value(i)).unwrap();
+        }
+
+        let keyspaces = storage.list_keyspaces();
+        assert_eq!(keyspaces.len(), 2);
+        let (small, large) = (&keyspaces[0], &keyspaces[1]);
+        assert_eq!((small.keyspace_id, small.flags), (small_keyspace_id, 0));
+        assert_eq!((large.keyspace_id, large.flags), (large_keyspace_id, 2));
+
+        assert!(small.n_sstables_per_level.iter().all(|n_sstables| *n_sstables == 0));
+        assert_eq!(small.sstables_bytes, 0);
+        assert_eq!(small.n_memtables, 1);
+
+        //Compactions are not run, so all SSTables are in level 0
+        let n_sstables = large.n_sstables_per_level[0];
+        assert!(n_sstables > 0);
+        assert_eq!(large.n_sstables_per_level.iter().sum::<usize>(), n_sstables);
+        assert!(large.sstables_bytes >= n_sstables * 128);
+        assert!(large.n_memtables >= 1 && large.n_memtables <= 2);
+    }
+
+    #[test]
+    fn set_option() {
+        let file_system = Arc::new(InMemoryFileSystem::create());
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system)
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            .compaction_task_frequency_ms(3_600_000)
+            .build_arc();
+        Logger::init(options.clone());
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        //Memtables are flushed to SSTables
+        for i in 0..100 {
+            storage.set(keyspace_id, key(i), &value(i)).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(storage.compaction_stats(keyspace_id).unwrap().n_compactions, 0);
+
+        storage.set_option("compaction_task_frequency_ms", 1).unwrap();
+        let mut waited_ms = 0;
+        while storage.compaction_stats(keyspace_id).unwrap().n_compactions == 0 && waited_ms < 5000 {
+            std::thread::sleep(Duration::from_millis(10));
+            waited_ms += 10;
+        }
+        assert!(storage.compaction_stats(keyspace_id).unwrap().n_compactions > 0);
+        for i in 0..20 {
+            assert_eq!(storage.get(keyspace_id, &key(i)).unwrap(), Some(Bytes::from(value(i))));
+        }
+
+        //Applied to keyspaces created afterward
+        storage.set_option("max_memtables_inactive", 4).unwrap();
+        let new_keyspace_id = storage.create_keyspace(0).unwrap();
+        for i in 0..20 {
+            storage.set(new_keyspace_id, key(i), &value(i)).unwrap();
+        }
+        assert!(storage.memtable_stats(new_keyspace_id).unwrap().inactive_count > 1);
+
+        assert!(matches!(storage.set_option("compaction_task_frequency_ms", 0), Err(SimpleDbError::InvalidOption(_, _))));
+        assert!(matches!(storage.set_option("memtable_max_size_bytes", 10), Err(SimpleDbError::InvalidOption(_, _))));
+    }
+
+    //Scans the whole iterator stack: memtables & SSTables merged by TwoMergeIterator, wrapped by StorageEngineIterator
+    #[test]
+    fn scan_all_single_key() {
+        //Only in the memtable
+        let storage = Storage::create(in_memory_options()).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        storage.set(keyspace_id, key(1), &value(1)).unwrap();
+        assert_eq!(scan_all_keys(&storage, keyspace_id), vec![key(1)]);
+
+        //The value fills the memtable, so the next write flushes it
+        let options = start_simpledb_options_builder_from(&in_memory_options())
+            .memtable_max_size_bytes(64)
+            .max_memtables_inactive(0)
+            .build_arc();
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        storage.set(keyspace_id, key(1), &[0; 128]).unwrap();
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, key(1), &[1; 128]).unwrap();
+        assert_eq!(storage.list_keyspaces()[0].n_sstables_per_level[0], 1);
+
+        //The only visible version is in the SSTable
+        assert_eq!(scan_all_entries(&storage, keyspace_id), vec![(key(1), Bytes::from(vec![0; 128]))]);
+        //Versions in the memtable & the SSTable are returned as a single entry, with the newest value
+        storage.commit_transaction(&transaction).unwrap();
+        assert_eq!(scan_all_entries(&storage, keyspace_id), vec![(key(1), Bytes::from(vec![1; 128]))]);
+
+        //The SSTable ends with a version that is not visible
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        storage.set(keyspace_id, key(1), &value(1)).unwrap();
+        let transaction = storage.start_transaction();
+        storage.set_with_transaction(keyspace_id, &transaction, key(1), &[1; 128]).unwrap();
+        storage.set_with_transaction(keyspace_id, &transaction, key(1), &[2; 128]).unwrap();
+        assert_eq!(storage.list_keyspaces()[1].n_sstables_per_level[0], 1);
+        assert_eq!(scan_all_keys(&storage, keyspace_id), vec![key(1)]);
+    }
+
+    fn scan_all_entries(storage: &Storage, keyspace_id: KeyspaceId) -> Vec<(Bytes, Bytes)> {
+        let mut iterator = storage.scan_all(keyspace_id).unwrap();
+        let mut entries = Vec::new();
+        while iterator.next() {
+            entries.push((iterator.key().as_bytes().clone(), Bytes::copy_from_slice(iterator.value())));
+        }
+        entries
+    }
+
+    fn scan_all_keys(storage: &Storage, keyspace_id: KeyspaceId) -> Vec<Bytes> {
+        let mut iterator = storage.scan_all(keyspace_id).unwrap();
+        let mut keys = Vec::new();
+        while iterator.next() {
+            keys.push(iterator.key().as_bytes().clone());
+        }
+        keys
+    }
+
+    #[test]
+    fn group_commit() {
+        let (n_syncs, keyspace_id, file_system) = concurrent_commits(0);
+        let (n_syncs_group_commit, keyspace_id_group_commit, file_system_group_commit) = concurrent_commits(5);
+        //Without group commit, every write to the WAL & to the transaction log is fsynced
+        assert_eq!(n_syncs.wal, N_CONCURRENT_COMMITS);
+        assert!(n_syncs.transaction_log >= N_CONCURRENT_COMMITS * 2);
+        assert!(n_syncs_group_commit.wal < n_syncs.wal);
+        assert!(n_syncs_group_commit.transaction_log < n_syncs.transaction_log);
+
+        //Committed transactions survive a crash
+        for (keyspace_id, file_system, group_commit_window_ms) in [
+            (keyspace_id, file_system, 0), (keyspace_id_group_commit, file_system_group_commit, 5)
+        ] {
+            file_system.crash();
+            let storage = Storage::create(group_commit_options(&file_system, group_commit_window_ms)).unwrap();
+            for i in 0..N_CONCURRENT_COMMITS {
+                assert_eq!(storage.get(keyspace_id, &key(i)).unwrap(), Some(Bytes::from(value(i))));
+            }
+        }
+    }
+
+    const N_CONCURRENT_COMMITS: usize = 80;
+
+    struct ConcurrentCommitsSyncs {
+        wal: usize,
+        transaction_log: usize,
+    }
+
+    //Commits N_CONCURRENT_COMMITS transactions from 8 threads. Returns the number of fsyncs done by them
+    fn concurrent_commits(group_commit_window_ms: usize) -> (ConcurrentCommitsSyncs, KeyspaceId, Arc<FaultyFileSystem>) {
+        let file_system = FaultyFileSystem::create();
+        let storage = Storage::create(group_commit_options(&file_system, group_commit_window_ms)).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let n_wal_syncs_before = file_system.n_syncs_of_files("wal-");
+        let n_transaction_log_syncs_before = file_system.n_syncs_of_files("transaction-log");
+
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let storage = &storage;
+                scope.spawn(move || {
+                    for i in (thread..N_CONCURRENT_COMMITS).step_by(8) {
+                        let transaction = storage.start_transaction();
+                        storage.set_with_transaction(keyspace_id, &transaction, key(i), &value(i)).unwrap();
+                        storage.commit_transaction(&transaction).unwrap();
+                    }
+                });
+            }
+        });
+
+        let n_syncs = ConcurrentCommitsSyncs {
+            wal: file_system.n_syncs_of_files("wal-") - n_wal_syncs_before,
+            transaction_log: file_system.n_syncs_of_files("transaction-log") - n_transaction_log_syncs_before,
+        };
+        (n_syncs, keyspace_id, file_system)
+    }
+
+    //Memtables are not flushed
+    fn group_commit_options(file_system: &Arc<FaultyFileSystem>, group_commit_window_ms: usize) -> Arc<SimpleDbOptions> {
+        start_simpledb_options_builder_from(&faulty_file_system_options(file_system))
+            .group_commit_window_ms(group_commit_window_ms)
+            .memtable_max_size_bytes(1048576)
+            .build_arc()
+    }
+
+    fn in_memory_options() -> Arc<SimpleDbOptions> {
+        let file_system = Arc::new(InMemoryFileSystem::create());
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system)
+            .base_path("/simpledb")
+            .build_arc();
+        Logger::init(options.clone());
+        options
+    }
+
+    const N_KEYS: usize = 30;
+
+    //Number of writes done by write_keys() without injecting faults
+    fn count_workload_writes() -> usize {
+        let file_system = FaultyFileSystem::create();
+        let options = faulty_file_system_options(&file_system);
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let n_writes_before = file_system.n_writes();
+        write_keys(&storage, keyspace_id);
+        file_system.n_writes() - n_writes_before
+    }
+
+    fn count_workload_syncs(manifest_fsync_policy: ManifestFsyncPolicy) -> usize {
+        let file_system = FaultyFileSystem::create();
+        let options = start_simpledb_options_builder_from(&faulty_file_system_options(&file_system))
+            .manifest_fsync_policy(manifest_fsync_policy)
+            .build_arc();
+        let storage = Storage::create(options).unwrap();
+        let keyspace_id = storage.create_keyspace(0).unwrap();
+        let n_syncs_before = file_system.n_syncs();
+        write_keys(&storage, keyspace_id);
+        file_system.n_syncs() - n_syncs_before
+    }
+
+    //Writes every key in its own transaction. Returns the keys whose transaction was committed.
+    //Memtables are small, so SSTables are flushed during the workload
+    fn write_keys(storage: &Storage, keyspace_id: KeyspaceId) -> Vec<usize> {
+        let mut committed = Vec::new();
+        for i in 0..N_KEYS {
+            let transaction = storage.start_transaction();
+            let result = storage.set_with_transaction(keyspace_id, &transaction, key(i), &value(i))
+                .and_then(|_| storage.commit_transaction(&transaction));
+            if result.is_ok() {
+                committed.push(i);
+            }
+        }
+        committed
+    }
+
+    fn assert_committed_keys_recovered(options: &Arc<SimpleDbOptions>, keyspace_id: KeyspaceId, committed: &Vec<usize>) {
+        let storage = Storage::create(options.clone()).unwrap();
+        for i in committed {
+            assert_eq!(storage.get(keyspace_id, &key(*i)).unwrap(), Some(Bytes::from(value(*i))));
+        }
+    }
+
+    fn faulty_file_system_options(file_system: &Arc<FaultyFileSystem>) -> Arc<SimpleDbOptions> {
+        file_system.create_dir_all(Path::new("/simpledb")).unwrap();
+        let options = shared::start_simpledb_options_builder()
+            .file_system(file_system.clone())
+            .base_path("/simpledb")
+            .memtable_max_size_bytes(128)
+            .max_memtables_inactive(1)
+            //Compactions are not run, so that the number of writes is deterministic
+            .simple_leveled_compaction_options(SimpleLeveledCompactionOptions {
+                level0_file_num_compaction_trigger: usize::MAX,
+                ..SimpleLeveledCompactionOptions::default()
+            })
+            .build_arc();
+        Logger::init(options.clone());
+        options
+    }
+
+    fn key(i: usize) -> Bytes {
+        Bytes::from(format!("key-{:03}", i))
+    }
+
+    fn value(i: usize) -> Vec<u8> {
+        format!("value-{}", i).into_bytes()
+    }
+}
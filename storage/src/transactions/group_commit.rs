@@ -0,0 +1,79 @@
+use shared::SimpleDbFile;
+use std::cmp::max;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+//Writes done to a file within a window of time share a single fsync. The first writer that finds no fsync in
+//progress becomes the leader: it waits for the window to end, fsyncs the file, and wakes up the writers whose
+//writes have been fsynced. Writers that arrive while the leader fsyncs wait for the next fsync
+pub(crate) struct GroupCommit {
+    window: Duration,
+    state: Mutex<GroupCommitState>,
+    fsynced: Condvar,
+}
+
+struct GroupCommitState {
+    //Number of writes done so far, used as the sequence number of the last write
+    n_writes: u64,
+    //Writes with a sequence number lower or equal to this one have been fsynced
+    n_fsynced_writes: u64,
+    leader_active: bool,
+}
+
+impl GroupCommit {
+    pub(crate) fn create(window: Duration) -> GroupCommit {
+        GroupCommit {
+            state: Mutex::new(GroupCommitState { n_writes: 0, n_fsynced_writes: 0, leader_active: false }),
+            fsynced: Condvar::new(),
+            window,
+        }
+    }
+
+    //Some if group_commit_window_ms is enabled with DurabilityLevel::Strong
+    pub(crate) fn create_from_options(options: &Arc<shared::SimpleDbOptions>) -> Option<GroupCommit> {
+        match (options.durability_level, options.group_commit_window_ms) {
+            (shared::DurabilityLevel::Strong, window_ms) if window_ms > 0 => {
+                Some(GroupCommit::create(Duration::from_millis(window_ms as u64)))
+            },
+            _ => None,
+        }
+    }
+
+    //Returns once the bytes have been written & fsynced. If the fsync fails, the error is returned to the leader,
+    //and the other writers will retry it
+    pub(crate) fn write(&self, file: &mut SimpleDbFile, bytes: &[u8]) -> Result<(), std::io::Error> {
+        let mut state = self.state.lock().unwrap();
+        file.write(bytes)?;
+        state.n_writes += 1;
+        let write_seq = state.n_writes;
+
+        loop {
+            if state.n_fsynced_writes >= write_seq {
+                return Ok(());
+            }
+            if !state.leader_active {
+                state.leader_active = true;
+                drop(state);
+                return self.fsync_as_leader(file);
+            }
+
+            state = self.fsynced.wait(state).unwrap();
+        }
+    }
+
+    fn fsync_as_leader(&self, file: &SimpleDbFile) -> Result<(), std::io::Error> {
+        std::thread::sleep(self.window);
+        //Writes done after this point might not be included in the fsync
+        let n_writes_to_fsync = self.state.lock().unwrap().n_writes;
+        let fsync_result = file.fsync();
+
+        let mut state = self.state.lock().unwrap();
+        state.leader_active = false;
+        if fsync_result.is_ok() {
+            state.n_fsynced_writes = max(state.n_fsynced_writes, n_writes_to_fsync);
+        }
+        self.fsynced.notify_all();
+
+        fsync_result
+    }
+}
@@ -1,3 +1,5 @@
 pub mod transaction_manager;
 pub mod transaction;
-mod transaction_log;
\ No newline at end of file
+mod transaction_log;
+pub mod transaction_info;
+pub(crate) mod group_commit;
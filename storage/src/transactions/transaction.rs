@@ -1,12 +1,39 @@
 use crate::transactions::transaction_manager::IsolationLevel;
+use bytes::Bytes;
 use shared::TxnId;
 use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 use shared::key::Key;
 
 pub struct Transaction {
     pub(crate) active_transactions: HashSet<TxnId>,
     pub(crate) isolation_level: IsolationLevel,
     pub(crate) txn_id: TxnId,
+    //Newest transaction that might be visible. With IsolationLevel::ReadCommitted it is updated on every statement,
+    //see TransactionManager::statement_transaction. Otherwise, it is always txn_id
+    pub(crate) read_txn_id: TxnId,
+
+    //Shared between the clones of the transaction.
+    //Read keys are only tracked with IsolationLevel::Serializable, they are used to detect conflicts when committing
+    pub(crate) read_keys: Arc<Mutex<HashSet<(shared::KeyspaceId, Bytes)>>>,
+    pub(crate) written_keys: Arc<Mutex<HashSet<(shared::KeyspaceId, Bytes)>>>,
+    pub(crate) savepoints: Arc<Mutex<Savepoints>>,
+}
+
+#[derive(Default)]
+pub(crate) struct Savepoints {
+    //Savepoint name, index in undo_log of the first write done after the savepoint
+    pub(crate) names: Vec<(String, usize)>,
+    //Writes done while there is some savepoint, in the order they were done
+    pub(crate) undo_log: Vec<UndoEntry>,
+}
+
+#[derive(Clone)]
+pub(crate) struct UndoEntry {
+    pub(crate) keyspace_id: shared::KeyspaceId,
+    pub(crate) key: Bytes,
+    //Value visible by the transaction before the write. None if it wasn't present
+    pub(crate) previous_value: Option<Bytes>,
 }
 
 impl Transaction {
@@ -14,6 +41,10 @@ impl Transaction {
         Transaction {
             isolation_level: IsolationLevel::ReadUncommited,
             active_transactions: HashSet::new(),
+            read_keys: Arc::new(Mutex::new(HashSet::new())),
+            written_keys: Arc::new(Mutex::new(HashSet::new())),
+            savepoints: Arc::new(Mutex::new(Savepoints::default())),
+            read_txn_id: 0,
             txn_id: 0
         }
     }
@@ -22,22 +53,72 @@ impl Transaction {
         Transaction {
             isolation_level: IsolationLevel::SnapshotIsolation,
             active_transactions: HashSet::new(),
+            read_keys: Arc::new(Mutex::new(HashSet::new())),
+            written_keys: Arc::new(Mutex::new(HashSet::new())),
+            savepoints: Arc::new(Mutex::new(Savepoints::default())),
+            read_txn_id: id,
             txn_id: id
         }
     }
 
     pub fn can_read(&self, key: &Key) -> bool {
         match self.isolation_level {
-            IsolationLevel::SnapshotIsolation => {
+            IsolationLevel::SnapshotIsolation |
+            IsolationLevel::Serializable => {
                 key.txn_id() <= self.txn_id && !self.active_transactions.contains(&key.txn_id())
             },
+            IsolationLevel::ReadCommitted => {
+                key.txn_id() == self.txn_id ||
+                    (key.txn_id() <= self.read_txn_id && !self.active_transactions.contains(&key.txn_id()))
+            },
             IsolationLevel::ReadUncommited => true
         }
     }
 
+    //Used as the upper bound when looking up the versions of a key
+    pub(crate) fn max_readable_txn_id(&self) -> TxnId {
+        std::cmp::max(self.txn_id, self.read_txn_id)
+    }
+
     pub fn id(&self) -> TxnId {
         self.txn_id
     }
+
+    pub fn is_serializable(&self) -> bool {
+        matches!(self.isolation_level, IsolationLevel::Serializable)
+    }
+
+    //Writes done after this call can be discarded with Storage::rollback_to_savepoint.
+    //A savepoint with the same name replaces the previous one
+    pub fn savepoint(&self, name: &str) {
+        let mut savepoints = self.savepoints.lock().unwrap();
+        savepoints.names.retain(|(savepoint_name, _)| savepoint_name != name);
+        let undo_log_index = savepoints.undo_log.len();
+        savepoints.names.push((name.to_string(), undo_log_index));
+    }
+
+    pub(crate) fn has_savepoints(&self) -> bool {
+        !self.savepoints.lock().unwrap().names.is_empty()
+    }
+
+    pub(crate) fn on_savepoint_write(&self, undo_entry: UndoEntry) {
+        self.savepoints.lock().unwrap().undo_log.push(undo_entry);
+    }
+
+    pub(crate) fn on_read(&self, keyspace_id: shared::KeyspaceId, key: &Bytes) {
+        if self.is_serializable() {
+            self.read_keys.lock().unwrap().insert((keyspace_id, key.clone()));
+        }
+    }
+
+    pub(crate) fn on_write(&self, keyspace_id: shared::KeyspaceId, key: &Bytes) {
+        self.written_keys.lock().unwrap().insert((keyspace_id, key.clone()));
+    }
+
+    //Returns true if the transaction with txn_id might have written keys not visible by this transaction
+    pub(crate) fn is_concurrent_with(&self, txn_id: TxnId) -> bool {
+        txn_id > self.txn_id || self.active_transactions.contains(&txn_id)
+    }
 }
 
 impl Clone for Transaction {
@@ -45,7 +126,11 @@ impl Clone for Transaction {
         Transaction {
             active_transactions: self.active_transactions.clone(),
             isolation_level: self.isolation_level.clone(),
+            read_keys: self.read_keys.clone(),
+            written_keys: self.written_keys.clone(),
+            savepoints: self.savepoints.clone(),
+            read_txn_id: self.read_txn_id,
             txn_id: self.txn_id,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,16 @@
+use crate::transactions::transaction_manager::IsolationLevel;
+use shared::TxnId;
+use std::time::Duration;
+
+//Introspection of an active transaction, returned by Storage::transaction_info
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxnInfo {
+    pub txn_id: TxnId,
+    pub isolation_level: IsolationLevel,
+    //Millis since the epoch
+    pub started_at: u64,
+    //Includes deletes and the writes undone by rolling back to a savepoint
+    pub n_writes: usize,
+    //Time since the transaction started
+    pub age: Duration,
+}
@@ -1,3 +1,4 @@
+use crate::transactions::group_commit::GroupCommit;
 use bytes::{Buf, BufMut};
 use shared::{SimpleDbError, SimpleDbFile, TxnId};
 use std::cell::UnsafeCell;
@@ -24,6 +25,8 @@ pub enum TransactionLogEntry {
 pub struct TransactionLog {
     //Wrapped with RwLock Because TransactionLog needs to be passed to threads. UnsafeCell doest implement Sync
     log_file: shared::SimpleDbFileWrapper,
+    //Some if group_commit_window_ms is enabled with DurabilityLevel::Strong
+    group_commit: Option<GroupCommit>,
     options: Arc<shared::SimpleDbOptions>
 }
 
@@ -31,8 +34,9 @@ impl TransactionLog {
     pub fn create(options: Arc<shared::SimpleDbOptions>) -> Result<TransactionLog, SimpleDbError> {
         Ok(TransactionLog {
             log_file: shared::SimpleDbFileWrapper {file: UnsafeCell::new(
-                SimpleDbFile::open(to_transaction_log_file_path(&options).as_path(), shared::SimpleDbFileMode::AppendOnly)
+                SimpleDbFile::open_in(&options.file_system, to_transaction_log_file_path(&options).as_path(), shared::SimpleDbFileMode::AppendOnly)
                     .map_err(|e| SimpleDbError::CannotCreateTransactionLog(e))?) },
+            group_commit: GroupCommit::create_from_options(&options),
             options
         })
     }
@@ -40,6 +44,7 @@ impl TransactionLog {
     pub fn create_mock(options: Arc<shared::SimpleDbOptions>) -> TransactionLog {
         TransactionLog {
             log_file: shared::SimpleDbFileWrapper {file: UnsafeCell::new(shared::SimpleDbFile::mock())},
+            group_commit: GroupCommit::create_from_options(&options),
             options
         }
     }
@@ -50,11 +55,17 @@ impl TransactionLog {
         //https://nullprogram.com/blog/2016/08/03/
         let log_file = unsafe { &mut *self.log_file.file.get() };
 
+        if let Some(group_commit) = &self.group_commit {
+            return group_commit.write(log_file, &entry.serialize())
+                .map_err(shared::SimpleDbError::CannotWriteTransactionLogEntry);
+        }
+
         log_file.write(&entry.serialize())
             .map_err(|e| shared::SimpleDbError::CannotWriteTransactionLogEntry(e))?;
 
         if matches!(self.options.durability_level, shared::DurabilityLevel::Strong) {
-            let _ = log_file.fsync();
+            log_file.fsync()
+                .map_err(|e| shared::SimpleDbError::CannotWriteTransactionLogEntry(e))?;
         }
         
         Ok(())
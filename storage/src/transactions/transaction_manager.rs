@@ -1,30 +1,68 @@
-use crate::transactions::transaction::Transaction;
+use crate::transactions::transaction::{Savepoints, Transaction, UndoEntry};
+use crate::transactions::transaction_info::TxnInfo;
 use crate::transactions::transaction_log::{TransactionLog, TransactionLogEntry};
-use crossbeam_skiplist::SkipMap;
+use crate::utils::clock::{Clock, SystemClock};
+use crossbeam_skiplist::{SkipMap, SkipSet};
 use shared::{SimpleDbError, TxnId};
-use std::cmp::max;
+use std::borrow::Cow;
+use std::cmp::{max, min};
 use std::collections::HashSet;
 use std::sync::atomic::Ordering::Relaxed;
 use std::sync::atomic::{AtomicU64, AtomicUsize};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use bytes::Bytes;
 use shared::key::Key;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum IsolationLevel {
     ReadUncommited,
-    SnapshotIsolation //MVCC
+    //Every statement (get or scan) sees the transactions committed before it started, plus the transaction's own writes.
+    //Two reads of the same key might return different values. Versions are still ordered by txn_id, so a write
+    //to a key that has been committed by a newer transaction is hidden by that version
+    ReadCommitted,
+    SnapshotIsolation, //MVCC
+    //Snapshot isolation + the transaction is aborted when committing if a key read by it
+    //(with get or returned by a scan) has been written by a concurrent committed transaction.
+    //Ranges are not tracked, so phantom reads might still happen
+    Serializable,
 }
 
 pub struct TransactionManager {
     rolledback_transactions: SkipMap<TxnId, AtomicUsize>,
-    active_transactions: SkipMap<TxnId, AtomicUsize>,
+    active_transactions: SkipMap<TxnId, ActiveTransaction>,
     next_txn_id: AtomicU64,
     log: TransactionLog,
+
+    //Transactions rolledback because they have been open for longer than transaction_timeout. They are kept
+    //until Storage::rollback_transaction is called, so that their writes & commits fail with TransactionAborted
+    aborted_transactions: SkipSet<TxnId>,
+    //None if transactions don't time out
+    transaction_timeout: Option<Duration>,
+    clock: Arc<dyn Clock>,
+
+    //Active serializable transactions
+    serializable_transactions: SkipMap<TxnId, Transaction>,
+    //Keys written by committed transactions that are concurrent to some active serializable transaction
+    committed_writes: SkipMap<TxnId, HashSet<(shared::KeyspaceId, Bytes)>>,
+    //Serializable transactions validation & the registration of committed writes are done one at a time.
+    //Rollbacks are also done while holding it, so that a transaction cannot be committed & aborted at the same time
+    commit_lock: Mutex<()>,
+}
+
+struct ActiveTransaction {
+    n_writes: AtomicUsize,
+    isolation_level: IsolationLevel,
+    started_at: Instant,
+    //Millis since the epoch
+    started_at_millis: u64,
+    //The transaction might not see the writes of this transaction & newer ones, see Transaction::can_read
+    oldest_unreadable_txn_id: TxnId,
 }
 
 impl TransactionManager {
     pub fn create_recover_from_log(options: Arc<shared::SimpleDbOptions>) -> Result<TransactionManager, shared::SimpleDbError> {
-        let log = TransactionLog::create(options)?;
+        let log = TransactionLog::create(options.clone())?;
         let transaction_log_entries = log.read_entries()?;
         let (active_transactions, pending_to_rollback, max_txn_id) =
             Self::get_pending_transactions(&transaction_log_entries);
@@ -38,6 +76,12 @@ impl TransactionManager {
             rolledback_transactions: Self::pending_transactions_to_txnids(&active_transactions, &pending_to_rollback),
             next_txn_id: AtomicU64::new((max_txn_id + 1) as u64),
             active_transactions: SkipMap::new(),
+            serializable_transactions: SkipMap::new(),
+            committed_writes: SkipMap::new(),
+            commit_lock: Mutex::new(()),
+            aborted_transactions: SkipSet::new(),
+            transaction_timeout: Self::transaction_timeout(&options),
+            clock: Arc::new(SystemClock {}),
             log,
         })
     }
@@ -52,36 +96,162 @@ impl TransactionManager {
         active_transactions
     }
 
+    //Ordered by transaction ID
+    pub fn transaction_info(&self) -> Vec<TxnInfo> {
+        let now = self.clock.now();
+
+        self.active_transactions.iter()
+            .map(|active_transaction| TxnInfo {
+                txn_id: *active_transaction.key(),
+                isolation_level: active_transaction.value().isolation_level.clone(),
+                started_at: active_transaction.value().started_at_millis,
+                n_writes: active_transaction.value().n_writes.load(Relaxed),
+                age: now.duration_since(active_transaction.value().started_at),
+            })
+            .collect()
+    }
+
+    //Writes of transactions with a lower ID are committed, and visible to every active & future transaction.
+    //Used by compactions to know which tombstones can be removed
+    pub fn oldest_read_txn_id(&self) -> TxnId {
+        let oldest_active = self.active_transactions.iter()
+            .map(|active_transaction| active_transaction.value().oldest_unreadable_txn_id);
+        let oldest_rolledback = self.rolledback_transactions.iter()
+            .map(|rolledback_transaction| *rolledback_transaction.key());
+
+        oldest_active.chain(oldest_rolledback)
+            .fold(self.next_txn_id.load(Relaxed) as TxnId, min)
+    }
+
     pub fn create_mock(options: Arc<shared::SimpleDbOptions>) -> TransactionManager {
+        Self::create_mock_with_clock(options, Arc::new(SystemClock {}))
+    }
+
+    pub(crate) fn create_mock_with_clock(options: Arc<shared::SimpleDbOptions>, clock: Arc<dyn Clock>) -> TransactionManager {
         TransactionManager {
+            transaction_timeout: Self::transaction_timeout(&options),
             log: TransactionLog::create_mock(options),
             rolledback_transactions: SkipMap::new(),
             active_transactions: SkipMap::new(),
             next_txn_id: AtomicU64::new(0),
+            serializable_transactions: SkipMap::new(),
+            committed_writes: SkipMap::new(),
+            commit_lock: Mutex::new(()),
+            aborted_transactions: SkipSet::new(),
+            clock,
         }
     }
 
+    //Serializable transactions that cannot be committed are rolledback, and SerializationFailure is returned
     pub fn commit(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
+        self.abort_if_timed_out(transaction.txn_id)?;
+        let commit_lock = self.commit_lock.lock().unwrap();
+        //It might have been aborted by another thread after the previous check
+        if self.aborted_transactions.contains(&transaction.txn_id) {
+            return Err(SimpleDbError::TransactionAborted(transaction.txn_id));
+        }
+
+        if transaction.is_serializable() && self.has_read_conflicts(transaction) {
+            drop(commit_lock);
+            self.rollback(transaction)?;
+            return Err(SimpleDbError::SerializationFailure(transaction.txn_id));
+        }
+
         self.active_transactions.remove(&transaction.txn_id);
+        self.serializable_transactions.remove(&transaction.txn_id);
+
+        let written_keys = transaction.written_keys.lock().unwrap().clone();
+        if !written_keys.is_empty() && !self.serializable_transactions.is_empty() {
+            self.committed_writes.insert(transaction.txn_id, written_keys);
+        }
+        self.remove_unused_committed_writes();
+
+        drop(commit_lock);
+
         self.log.add_entry(TransactionLogEntry::Commit(transaction.txn_id))
     }
 
     //Before calling this function it is expected that the written keys have been removed
     pub fn rollback(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
-        self.log.add_entry(TransactionLogEntry::StartRollback(transaction.txn_id))?;
-        let n_writes = self.active_transactions.get(&transaction.txn_id)
+        let _commit_lock = self.commit_lock.lock().unwrap();
+        //Aborted transactions have already been rolledback
+        if self.aborted_transactions.remove(&transaction.txn_id).is_some() {
+            return Ok(());
+        }
+
+        self.rollback_active_transaction(transaction.txn_id)
+    }
+
+    //Expects commit_lock to be held
+    fn rollback_active_transaction(&self, txn_id: TxnId) -> Result<(), SimpleDbError> {
+        self.log.add_entry(TransactionLogEntry::StartRollback(txn_id))?;
+        let n_writes = self.active_transactions.get(&txn_id)
             .unwrap()
             .value()
+            .n_writes
             .load(Relaxed);
-        self.active_transactions.remove(&transaction.txn_id);
+        self.active_transactions.remove(&txn_id);
+        self.serializable_transactions.remove(&txn_id);
 
         if n_writes > 0 {
-            self.rolledback_transactions.insert(transaction.txn_id, AtomicUsize::new(n_writes));
+            self.rolledback_transactions.insert(txn_id, AtomicUsize::new(n_writes));
         }
 
         Ok(())
     }
 
+    //Rolls back every transaction that has been open for longer than the transaction timeout.
+    //Called when transactions are started, so that idle transactions don't pin old versions forever
+    fn abort_timed_out_transactions(&self) {
+        if self.transaction_timeout.is_some() {
+            for active_transaction in self.active_transactions.iter() {
+                let _ = self.abort_if_timed_out(*active_transaction.key());
+            }
+        }
+    }
+
+    //Returns TransactionAborted if the transaction has been aborted, now or before
+    fn abort_if_timed_out(&self, txn_id: TxnId) -> Result<(), SimpleDbError> {
+        if self.aborted_transactions.contains(&txn_id) {
+            return Err(SimpleDbError::TransactionAborted(txn_id));
+        }
+        let transaction_timeout = match self.transaction_timeout {
+            Some(transaction_timeout) => transaction_timeout,
+            None => return Ok(()),
+        };
+        let is_timed_out = |txn_id: &TxnId| self.active_transactions.get(txn_id)
+            .map(|active_transaction| self.clock.now().duration_since(active_transaction.value().started_at) > transaction_timeout)
+            .unwrap_or(false);
+        if !is_timed_out(&txn_id) {
+            return Ok(());
+        }
+
+        let _commit_lock = self.commit_lock.lock().unwrap();
+        //It might have been committed or rolledback by another thread
+        if is_timed_out(&txn_id) {
+            self.rollback_active_transaction(txn_id)?;
+            self.aborted_transactions.insert(txn_id);
+        }
+
+        Err(SimpleDbError::TransactionAborted(txn_id))
+    }
+
+    //Returns the writes done after the savepoint, from the newest to the oldest, which have to be undone by the caller.
+    //Savepoints created after it are removed, the savepoint itself is kept
+    pub(crate) fn rollback_to_savepoint(&self, transaction: &Transaction, name: &str) -> Result<Vec<UndoEntry>, SimpleDbError> {
+        let mut savepoints = transaction.savepoints.lock().unwrap();
+        let savepoint_position = savepoints.names.iter()
+            .position(|(savepoint_name, _)| savepoint_name == name)
+            .ok_or_else(|| SimpleDbError::SavepointNotFound(name.to_string()))?;
+        let undo_log_index = savepoints.names[savepoint_position].1;
+
+        savepoints.names.truncate(savepoint_position + 1);
+        let mut writes_to_undo = savepoints.undo_log.split_off(undo_log_index);
+        writes_to_undo.reverse();
+
+        Ok(writes_to_undo)
+    }
+
     //This function is called when there is a memtable flush or sstable compaction
     //Returns Ok if the key with that transaction ID hasn't been rolledback
     //Returns Err if it has been rolledback
@@ -103,22 +273,56 @@ impl TransactionManager {
     }
 
     pub fn start_transaction(&self, isolation_level: IsolationLevel) -> Transaction {
+        self.abort_timed_out_transactions();
         let active_transactions = self.copy_active_transactions();
         let txn_id = self.next_txn_id.fetch_add(1, Relaxed) as shared::TxnId;
-        self.active_transactions.insert(txn_id, AtomicUsize::new(0));
-
-        Transaction {
+        self.active_transactions.insert(txn_id, ActiveTransaction {
+            oldest_unreadable_txn_id: active_transactions.iter().copied().fold(txn_id + 1, min),
+            n_writes: AtomicUsize::new(0),
+            isolation_level: isolation_level.clone(),
+            started_at: self.clock.now(),
+            started_at_millis: self.clock.now_millis(),
+        });
+
+        let transaction = Transaction {
+            read_keys: Arc::new(Mutex::new(HashSet::new())),
+            written_keys: Arc::new(Mutex::new(HashSet::new())),
+            savepoints: Arc::new(Mutex::new(Savepoints::default())),
             active_transactions,
             isolation_level,
+            read_txn_id: txn_id,
             txn_id
+        };
+
+        if transaction.is_serializable() {
+            self.serializable_transactions.insert(txn_id, transaction.clone());
+        }
+
+        transaction
+    }
+
+    //Returns the transaction to be used by a get or a scan. ReadCommitted transactions take a new snapshot
+    //of the committed transactions, the rest keep the one taken when the transaction started
+    pub(crate) fn statement_transaction<'a>(&self, transaction: &'a Transaction) -> Cow<'a, Transaction> {
+        match transaction.isolation_level {
+            IsolationLevel::ReadCommitted => {
+                let mut statement_transaction = transaction.clone();
+                statement_transaction.read_txn_id = (self.next_txn_id.load(Relaxed) as TxnId).saturating_sub(1);
+                statement_transaction.active_transactions = self.copy_active_transactions();
+                statement_transaction.active_transactions.remove(&transaction.txn_id);
+                Cow::Owned(statement_transaction)
+            },
+            _ => Cow::Borrowed(transaction),
         }
     }
 
     pub fn mark_write(&self, transaction: &Transaction) -> Result<(), SimpleDbError> {
+        self.abort_if_timed_out(transaction.txn_id)?;
+
         match self.active_transactions.get(&transaction.txn_id) {
-            Some(n_writes) => {
+            Some(active_transaction) => {
                 self.log.add_entry(TransactionLogEntry::Write(transaction.txn_id))?;
-                n_writes.value().fetch_add(1, Relaxed);
+                active_transaction.value().n_writes.fetch_add(1, Relaxed);
                 Ok(())
             }
             None => Ok(())
@@ -129,12 +333,45 @@ impl TransactionManager {
         self.active_transactions.get(&txn_id).is_some()
     }
 
+    fn transaction_timeout(options: &Arc<shared::SimpleDbOptions>) -> Option<Duration> {
+        match options.transaction_timeout_ms {
+            0 => None,
+            transaction_timeout_ms => Some(Duration::from_millis(transaction_timeout_ms as u64)),
+        }
+    }
+
+    fn has_read_conflicts(&self, transaction: &Transaction) -> bool {
+        let read_keys = transaction.read_keys.lock().unwrap();
+
+        self.committed_writes.iter()
+            .filter(|committed_writes| transaction.is_concurrent_with(*committed_writes.key()))
+            .any(|committed_writes| !committed_writes.value().is_disjoint(&read_keys))
+    }
+
+    //Writes of committed transactions are kept while some active serializable transaction is concurrent with them
+    fn remove_unused_committed_writes(&self) {
+        for committed_writes in self.committed_writes.iter() {
+            let committed_txn_id = *committed_writes.key();
+            let is_used = self.serializable_transactions.iter()
+                .any(|serializable_transaction| serializable_transaction.value().is_concurrent_with(committed_txn_id));
+
+            if !is_used {
+                committed_writes.remove();
+            }
+        }
+    }
+
+    //Rolledback transactions whose writes haven't been discarded yet by flushes or compactions
+    //are also included, so that new transactions don't see their writes
     fn copy_active_transactions(&self) -> HashSet<TxnId> {
         let mut active_transactions: HashSet<TxnId> = HashSet::new();
 
         for active_transaction in &self.active_transactions {
             active_transactions.insert(*active_transaction.key());
         }
+        for rolledback_transaction in &self.rolledback_transactions {
+            active_transactions.insert(*rolledback_transaction.key());
+        }
 
         active_transactions
     }
@@ -219,4 +456,77 @@ impl TransactionManager {
 
         entries
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::transactions::transaction_info::TxnInfo;
+    use crate::transactions::transaction_manager::{IsolationLevel, TransactionManager};
+    use crate::utils::clock::MockClock;
+    use shared::SimpleDbError;
+    use std::time::Duration;
+
+    #[test]
+    fn transaction_timeout() {
+        let clock = MockClock::create();
+        let options = shared::start_simpledb_options_builder()
+            .transaction_timeout_ms(1000)
+            .build_arc();
+        let transaction_manager = TransactionManager::create_mock_with_clock(options, clock.clone());
+
+        let transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        transaction_manager.mark_write(&transaction).unwrap();
+        clock.advance(Duration::from_millis(999));
+        transaction_manager.mark_write(&transaction).unwrap();
+
+        clock.advance(Duration::from_millis(2));
+        assert!(matches!(transaction_manager.mark_write(&transaction), Err(SimpleDbError::TransactionAborted(_))));
+        assert!(!transaction_manager.is_active(transaction.txn_id));
+        assert!(matches!(transaction_manager.commit(&transaction), Err(SimpleDbError::TransactionAborted(_))));
+        //Its writes are hidden from new transactions
+        let new_transaction = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        assert!(new_transaction.active_transactions.contains(&transaction.txn_id));
+        transaction_manager.rollback(&transaction).unwrap();
+
+        //Idle transactions are aborted when new transactions start
+        clock.advance(Duration::from_millis(1001));
+        transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        assert!(!transaction_manager.is_active(new_transaction.txn_id));
+    }
+
+    #[test]
+    fn transaction_info() {
+        let clock = MockClock::create();
+        let transaction_manager = TransactionManager::create_mock_with_clock(shared::start_simpledb_options_builder().build_arc(), clock.clone());
+
+        let read_committed = transaction_manager.start_transaction(IsolationLevel::ReadCommitted);
+        clock.advance(Duration::from_millis(100));
+        let serializable = transaction_manager.start_transaction(IsolationLevel::Serializable);
+        transaction_manager.mark_write(&serializable).unwrap();
+        transaction_manager.mark_write(&serializable).unwrap();
+        let committed = transaction_manager.start_transaction(IsolationLevel::SnapshotIsolation);
+        transaction_manager.commit(&committed).unwrap();
+        clock.advance(Duration::from_millis(50));
+
+        assert_eq!(transaction_manager.transaction_info(), vec![
+            TxnInfo {
+                txn_id: read_committed.txn_id,
+                isolation_level: IsolationLevel::ReadCommitted,
+                started_at: MockClock::START_MILLIS,
+                n_writes: 0,
+                age: Duration::from_millis(150),
+            },
+            TxnInfo {
+                txn_id: serializable.txn_id,
+                isolation_level: IsolationLevel::Serializable,
+                started_at: MockClock::START_MILLIS + 100,
+                n_writes: 2,
+                age: Duration::from_millis(50),
+            },
+        ]);
+
+        transaction_manager.rollback(&read_committed).unwrap();
+        let txn_ids: Vec<_> = transaction_manager.transaction_info().iter().map(|info| info.txn_id).collect();
+        assert_eq!(txn_ids, vec![serializable.txn_id]);
+    }
+}
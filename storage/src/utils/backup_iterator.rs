@@ -0,0 +1,82 @@
+use bytes::Bytes;
+use shared::iterators::storage_iterator::StorageIterator;
+use shared::key::Key;
+
+//Returns the live entries of the inner iterator: only the newest version of each key is returned, and deleted keys
+//are skipped. Expects the inner iterator to return the versions of a key in ascending order, like StorageEngineIterator
+pub struct BackupIterator<I: StorageIterator> {
+    inner_iterator: I,
+
+    //Entry of the inner iterator that belongs to the next key
+    pending: Option<(Key, Bytes)>,
+
+    current_key: Option<Key>,
+    current_value: Option<Bytes>,
+}
+
+impl<I: StorageIterator> BackupIterator<I> {
+    pub fn create(inner_iterator: I) -> BackupIterator<I> {
+        BackupIterator {
+            current_value: None,
+            current_key: None,
+            pending: None,
+            inner_iterator,
+        }
+    }
+
+    fn next_inner_entry(&mut self) -> Option<(Key, Bytes)> {
+        if self.inner_iterator.next() {
+            Some((self.inner_iterator.key().clone(), Bytes::copy_from_slice(self.inner_iterator.value())))
+        } else {
+            None
+        }
+    }
+}
+
+impl<I: StorageIterator> StorageIterator for BackupIterator<I> {
+    fn next(&mut self) -> bool {
+        loop {
+            let mut newest = match self.pending.take().or_else(|| self.next_inner_entry()) {
+                Some(entry) => entry,
+                None => return false,
+            };
+
+            while let Some(entry) = self.next_inner_entry() {
+                if entry.0.bytes_eq_bytes(newest.0.as_bytes()) {
+                    newest = entry;
+                } else {
+                    self.pending = Some(entry);
+                    break;
+                }
+            }
+
+            if !newest.0.is_tombstone() {
+                self.current_key = Some(newest.0);
+                self.current_value = Some(newest.1);
+                return true;
+            }
+        }
+    }
+
+    //The pending entry might belong to a deleted key
+    fn has_next(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn key(&self) -> &Key {
+        self.current_key.as_ref().unwrap()
+    }
+
+    fn value(&self) -> &[u8] {
+        self.current_value.as_ref().unwrap()
+    }
+
+    fn seek(&mut self, key: &Bytes, inclusive: bool) {
+        self.pending = None;
+        self.inner_iterator.seek(key, inclusive);
+    }
+
+    fn take_error(&mut self) -> Option<shared::SimpleDbError> {
+        self.inner_iterator.take_error()
+    }
+}
@@ -1,75 +1,135 @@
 use bytes::BufMut;
 
+//Bits of the bitmap are probed with double hashing: probe i is at (hash + i * delta) % n bits
 pub struct BloomFilter {
     bitmap: Vec<u8>,
+    //0 for bloom filters of SSTables written before the number of hashes was stored. They only probe
+    //the first bit of the byte hash & (bitmap.len() - 1)
+    n_hashes: u32,
 }
 
+//Probing more bits makes lookups slower without reducing much the false positive rate
+const MAX_HASHES: u32 = 30;
+
 impl BloomFilter {
     pub fn may_contain(&self, hash: u32) -> bool {
+        if self.n_hashes == 0 {
+            return self.may_contain_legacy(hash);
+        }
+
+        let n_bits = self.bitmap.len() as u32 * 8;
+        let delta = hash.rotate_left(15);
+        let mut current_hash = hash;
+
+        for _ in 0..self.n_hashes {
+            let bit_index = current_hash % n_bits;
+            if self.bitmap[(bit_index / 8) as usize] & (0x01 << (bit_index % 8)) == 0 {
+                return false;
+            }
+            current_hash = current_hash.wrapping_add(delta);
+        }
+
+        true
+    }
+
+    fn may_contain_legacy(&self, hash: u32) -> bool {
         let slot_index = hash & (self.bitmap.len() - 1) as u32;
-        let bit_index_in_slot = hash & (std::mem::size_of::<u8>() - 1) as u32;
-        let slot: u8 = self.bitmap[slot_index as usize];
+        self.bitmap[slot_index as usize] & 0x01 == 0x01
+    }
 
-        slot >> bit_index_in_slot & 0x01 == 0x01
+    pub fn n_hashes(&self) -> u32 {
+        self.n_hashes
     }
 
+    pub fn n_bits(&self) -> usize {
+        self.bitmap.len() * 8
+    }
+
+    //Crc (4 bytes), n bytes (4 bytes), n hashes (4 bytes), bitmap. The crc covers the number of hashes & the bitmap
     pub fn encode(&self) -> Vec<u8> {
-        let crc = crc32fast::hash(&self.bitmap);
-        let mut encoded: Vec<u8> = Vec::with_capacity(self.bitmap.len() + 8);
-        encoded.put_u32_le(crc);
+        let mut encoded_params_bitmap: Vec<u8> = Vec::with_capacity(self.bitmap.len() + 4);
+        encoded_params_bitmap.put_u32_le(self.n_hashes);
+        encoded_params_bitmap.extend(&self.bitmap);
+
+        let mut encoded: Vec<u8> = Vec::with_capacity(self.bitmap.len() + 12);
+        encoded.put_u32_le(crc32fast::hash(&encoded_params_bitmap));
         encoded.put_u32_le(self.bitmap.len() as u32);
-        encoded.extend(&self.bitmap);
+        encoded.extend(encoded_params_bitmap);
         encoded
     }
 
-    pub fn decode(bytes: &Vec<u8>, start_offset: usize) -> Result<BloomFilter, shared::DecodeErrorType> {
+    //Bloom filters of SSTables written before the number of hashes was stored are decoded with has_n_hashes false.
+    //Their layout is: crc (4 bytes), n bytes (4 bytes), bitmap
+    pub fn decode(
+        bytes: &Vec<u8>,
+        start_offset: usize,
+        has_n_hashes: bool,
+    ) -> Result<BloomFilter, shared::DecodeErrorType> {
         let expected_crc = shared::u8_vec_to_u32_le(bytes, start_offset);
-        let n_bytes = shared::u8_vec_to_u32_le(bytes, start_offset + 4);
+        let n_bytes = shared::u8_vec_to_u32_le(bytes, start_offset + 4) as usize;
 
-        let bitmap_start_index = start_offset + 8;
-        let bitmap_end_index = start_offset + 8 + n_bytes as usize;
-        let bloom_bitmap = bytes[bitmap_start_index..bitmap_end_index].to_vec();
-        let actual_crc = crc32fast::hash(&bloom_bitmap);
+        let crc_start_index = start_offset + 8;
+        let bitmap_start_index = if has_n_hashes { crc_start_index + 4 } else { crc_start_index };
+        let bitmap_end_index = bitmap_start_index + n_bytes;
+        if bitmap_end_index > bytes.len() {
+            return Err(shared::DecodeErrorType::IllegalSize(bitmap_end_index, bytes.len()));
+        }
 
+        let actual_crc = crc32fast::hash(&bytes[crc_start_index..bitmap_end_index]);
         if actual_crc != expected_crc {
             return Err(shared::DecodeErrorType::CorruptedCrc(expected_crc, actual_crc));
         }
 
-        Ok(Self::from_botmap(bloom_bitmap))
-    }
+        let n_hashes = if has_n_hashes { shared::u8_vec_to_u32_le(bytes, crc_start_index) } else { 0 };
+        if n_bytes == 0 || (has_n_hashes && n_hashes == 0) {
+            return Err(shared::DecodeErrorType::IllegalSize(1, 0));
+        }
 
-    pub fn from_botmap(bitmap: Vec<u8>) -> BloomFilter {
-        BloomFilter{ bitmap }
+        Ok(BloomFilter {
+            bitmap: bytes[bitmap_start_index..bitmap_end_index].to_vec(),
+            n_hashes,
+        })
     }
 
+    //The number of bits & hashes are the ones that minimize the size of the filter for the given
+    //number of keys & false positive rate: bits per key = -ln(rate) / ln(2)^2, n hashes = bits per key * ln(2)
     pub fn create(
-        hashes: &Vec<u32>,
-        n_entries: usize //Expect power of 2
+        hashes: &[u32],
+        false_positive_rate: f64, //Expect (0, 1)
     ) -> BloomFilter {
-        let n_vec_slots = (n_entries / 8) as u32;
-        let mut bitmap: Vec<u8> = Vec::with_capacity(n_vec_slots as usize);
-        shared::fill_vec(&mut bitmap, n_vec_slots as usize, 0);
+        let bits_per_key = -false_positive_rate.ln() / (std::f64::consts::LN_2 * std::f64::consts::LN_2);
+        let n_hashes = ((bits_per_key * std::f64::consts::LN_2).round() as u32).clamp(1, MAX_HASHES);
+        //At least 64 bits, so that small SSTables don't have a very high false positive rate
+        let n_bits = ((hashes.len() as f64 * bits_per_key).ceil() as usize).max(64);
+        let n_bytes = n_bits.div_ceil(8);
+
+        let mut bloom_filter = BloomFilter { bitmap: vec![0; n_bytes], n_hashes };
+        let n_bits = n_bytes as u32 * 8;
 
         for hash in hashes {
-            let slot_index = hash & (n_vec_slots - 1);
-            let bit_index_in_slot = hash & (std::mem::size_of::<u8>() - 1) as u32;
-            let slot: u8 = bitmap[slot_index as usize];
-            let updated_slot: u8 = slot | (0x01 << bit_index_in_slot);
-            bitmap[slot_index as usize] = updated_slot;
+            let delta = hash.rotate_left(15);
+            let mut current_hash = *hash;
+
+            for _ in 0..n_hashes {
+                let bit_index = current_hash % n_bits;
+                bloom_filter.bitmap[(bit_index / 8) as usize] |= 0x01 << (bit_index % 8);
+                current_hash = current_hash.wrapping_add(delta);
+            }
         }
 
-        BloomFilter { bitmap }
+        bloom_filter
     }
 }
 
 #[cfg(test)]
 mod test {
+    use bytes::BufMut;
     use crate::utils::bloom_filter::BloomFilter;
 
     #[test]
     fn may_contain() {
         let hashes = vec![101212, 1389172819, 182971, 12, 1729187291];
-        let bloom = BloomFilter::create(&hashes, 64);
+        let bloom = BloomFilter::create(&hashes, 0.01);
 
         assert!(bloom.may_contain(101212));
         assert!(bloom.may_contain(1389172819));
@@ -82,17 +142,59 @@ mod test {
 
     #[test]
     fn decode_encode() {
-        let encoded = BloomFilter::create(&vec![101212, 1389172819, 182971, 12, 1729187291], 64)
+        let encoded = BloomFilter::create(&[101212, 1389172819, 182971, 12, 1729187291], 0.01)
             .encode();
-        let decoded_result = BloomFilter::decode(&encoded, 0);
+        let decoded_result = BloomFilter::decode(&encoded, 0, true);
 
         assert!(decoded_result.is_ok());
         let decoded_result = decoded_result.unwrap();
 
+        assert_eq!(decoded_result.n_hashes(), 7);
         assert!(decoded_result.may_contain(101212));
         assert!(decoded_result.may_contain(1389172819));
         assert!(decoded_result.may_contain(182971));
         assert!(decoded_result.may_contain(12));
         assert!(decoded_result.may_contain(1729187291));
     }
+
+    #[test]
+    fn decode_legacy() {
+        let mut bitmap = vec![0u8; 8];
+        bitmap[12 & 7] = 0x01;
+        let mut encoded = Vec::new();
+        encoded.put_u32_le(crc32fast::hash(&bitmap));
+        encoded.put_u32_le(bitmap.len() as u32);
+        encoded.extend(&bitmap);
+
+        let decoded = BloomFilter::decode(&encoded, 0, false).unwrap();
+
+        assert_eq!(decoded.n_hashes(), 0);
+        assert!(decoded.may_contain(12));
+        assert!(!decoded.may_contain(13));
+    }
+
+    #[test]
+    fn false_positive_rate() {
+        let n_keys = 10000;
+        let n_absent_keys = 100000;
+
+        for false_positive_rate in [0.1, 0.01, 0.001] {
+            let hashes: Vec<u32> = (0..n_keys)
+                .map(|i| shared::hash(format!("key{}", i).as_bytes()))
+                .collect();
+            let bloom = BloomFilter::create(&hashes, false_positive_rate);
+
+            assert!(hashes.iter().all(|hash| bloom.may_contain(*hash)));
+
+            let n_false_positives = (0..n_absent_keys)
+                .filter(|i| bloom.may_contain(shared::hash(format!("absent{}", i).as_bytes())))
+                .count();
+            let observed_rate = n_false_positives as f64 / n_absent_keys as f64;
+
+            assert!(
+                (observed_rate - false_positive_rate).abs() <= false_positive_rate * 0.5,
+                "Expected false positive rate {} Observed {}", false_positive_rate, observed_rate
+            );
+        }
+    }
 }
@@ -0,0 +1,73 @@
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+//Allows tests to control the passage of time
+pub(crate) trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    //Wall clock time, in millis since the epoch
+    fn now_millis(&self) -> u64;
+
+    fn sleep(&self, duration: Duration);
+}
+
+pub(crate) struct SystemClock {}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or(0)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+//Time only advances when sleep() or advance() are called. The wall clock starts at MockClock::START_MILLIS
+#[cfg(test)]
+pub(crate) struct MockClock {
+    start: Instant,
+    elapsed: Mutex<Duration>,
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub(crate) const START_MILLIS: u64 = 1_000_000;
+
+    pub(crate) fn create() -> Arc<MockClock> {
+        Arc::new(MockClock { start: Instant::now(), elapsed: Mutex::new(Duration::ZERO), sleeps: Mutex::new(Vec::new()) })
+    }
+
+    pub(crate) fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    pub(crate) fn take_sleeps(&self) -> Vec<Duration> {
+        std::mem::take(&mut *self.sleeps.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed.lock().unwrap()
+    }
+
+    fn now_millis(&self) -> u64 {
+        Self::START_MILLIS + self.elapsed.lock().unwrap().as_millis() as u64
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.sleeps.lock().unwrap().push(duration);
+        self.advance(duration);
+    }
+}
@@ -0,0 +1,254 @@
+use shared::{FileHandle, FileSystem, InMemoryFileSystem, SimpleDbFileMode};
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+//Only used for testing. In memory file system that counts reads & writes, and that can be configured to:
+// - Fail a write
+// - Return short reads
+// - Lose the written data: written bytes are replaced with zeros
+// - Simulate a crash: after a number of writes, every write, fsync, rename & delete fails, as if the process
+//   had died. Calling crash() drops all the data that was not fsynced, so the storage engine can be reopened
+//   to check its recovery. Creating, renaming & deleting files are considered to be durable once they are done.
+pub(crate) struct FaultyFileSystem {
+    inner: Arc<InMemoryFileSystem>,
+    state: Arc<Mutex<FaultState>>,
+}
+
+#[derive(Default)]
+struct FaultState {
+    n_writes: usize,
+    n_reads: usize,
+    n_syncs: usize,
+    n_syncs_by_file: HashMap<PathBuf, usize>,
+    fail_write: Option<usize>,
+    crash_after_write: Option<usize>,
+    crashed: bool,
+    short_reads: bool,
+    zero_writes: bool,
+    //Contents of the files at their last fsync
+    synced: HashMap<PathBuf, Vec<u8>>,
+}
+
+struct FaultyFileHandle {
+    inner: Box<dyn FileHandle>,
+    path: PathBuf,
+    state: Arc<Mutex<FaultState>>,
+}
+
+impl FaultyFileSystem {
+    pub(crate) fn create() -> Arc<FaultyFileSystem> {
+        Arc::new(FaultyFileSystem {
+            inner: Arc::new(InMemoryFileSystem::create()),
+            state: Arc::new(Mutex::new(FaultState::default())),
+        })
+    }
+
+    //Number of writes done so far
+    pub(crate) fn n_writes(&self) -> usize {
+        self.state.lock().unwrap().n_writes
+    }
+
+    //Number of reads done so far
+    pub(crate) fn n_reads(&self) -> usize {
+        self.state.lock().unwrap().n_reads
+    }
+
+    //Number of fsyncs done so far
+    pub(crate) fn n_syncs(&self) -> usize {
+        self.state.lock().unwrap().n_syncs
+    }
+
+    //Number of fsyncs done so far to the files whose name starts with file_name_prefix
+    pub(crate) fn n_syncs_of_files(&self, file_name_prefix: &str) -> usize {
+        self.state.lock().unwrap().n_syncs_by_file.iter()
+            .filter(|(path, _)| path.file_name()
+                .map(|file_name| file_name.to_string_lossy().starts_with(file_name_prefix))
+                .unwrap_or(false))
+            .map(|(_, n_syncs)| *n_syncs)
+            .sum()
+    }
+
+    //The n_write th write from now will fail
+    pub(crate) fn fail_write(&self, n_write: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.fail_write = Some(state.n_writes + n_write);
+    }
+
+    //After n_writes from now have been done, the process is considered to be dead
+    pub(crate) fn crash_after_writes(&self, n_writes: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.crash_after_write = Some(state.n_writes + n_writes);
+    }
+
+    pub(crate) fn short_reads(&self, value: bool) {
+        self.state.lock().unwrap().short_reads = value;
+    }
+
+    pub(crate) fn zero_writes(&self, value: bool) {
+        self.state.lock().unwrap().zero_writes = value;
+    }
+
+    //Removes all the data that hasn't been fsynced & clears the injected faults
+    pub(crate) fn crash(&self) {
+        let mut state = self.state.lock().unwrap();
+        for path in self.inner.list_files() {
+            let synced = state.synced.get(&path).cloned().unwrap_or_default();
+            let mut file = self.inner.open(path.as_path(), &SimpleDbFileMode::RandomWrites).unwrap();
+            file.set_len(0).unwrap();
+            file.write_all(&synced).unwrap();
+        }
+
+        state.crash_after_write = None;
+        state.fail_write = None;
+        state.crashed = false;
+    }
+
+    fn check_not_crashed(&self) -> Result<(), Error> {
+        check_not_crashed(&self.state.lock().unwrap())
+    }
+}
+
+fn check_not_crashed(state: &FaultState) -> Result<(), Error> {
+    if state.crashed {
+        return Err(Error::other("Simulated crash"));
+    }
+    Ok(())
+}
+
+impl FileSystem for FaultyFileSystem {
+    fn open(&self, path: &Path, mode: &SimpleDbFileMode) -> Result<Box<dyn FileHandle>, Error> {
+        if !self.inner.exists(path) {
+            self.check_not_crashed()?;
+        }
+
+        Ok(Box::new(FaultyFileHandle {
+            inner: self.inner.open(path, mode)?,
+            path: path.to_path_buf(),
+            state: self.state.clone(),
+        }))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.inner.is_dir(path)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        self.check_not_crashed()?;
+        self.inner.copy(from, to)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        check_not_crashed(&state)?;
+        self.inner.rename(from, to)?;
+
+        let renamed: Vec<PathBuf> = state.synced.keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for path in renamed {
+            let synced = state.synced.remove(&path).unwrap();
+            state.synced.insert(to.join(path.strip_prefix(from).unwrap()), synced);
+        }
+
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        check_not_crashed(&state)?;
+        state.synced.remove(path);
+        self.inner.remove_file(path)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<(), Error> {
+        self.check_not_crashed()?;
+        self.inner.create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), Error> {
+        self.check_not_crashed()?;
+        self.inner.create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        check_not_crashed(&state)?;
+        state.synced.retain(|synced_path, _| !synced_path.starts_with(path));
+        self.inner.remove_dir_all(path)
+    }
+
+    fn list(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        self.inner.list(path)
+    }
+}
+
+impl FileHandle for FaultyFileHandle {
+    fn read_to_end(&self, buff: &mut Vec<u8>) -> Result<usize, Error> {
+        self.state.lock().unwrap().n_reads += 1;
+        self.inner.read_to_end(buff)
+    }
+
+    fn read_at(&self, buff: &mut [u8], offset: u64) -> Result<usize, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.n_reads += 1;
+        let short_reads = state.short_reads;
+        drop(state);
+
+        if short_reads && buff.len() > 1 {
+            let half = buff.len() / 2;
+            return self.inner.read_at(&mut buff[..half], offset);
+        }
+        self.inner.read_at(buff, offset)
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        check_not_crashed(&state)?;
+        state.n_writes += 1;
+
+        if state.fail_write == Some(state.n_writes) {
+            return Err(Error::other("Injected write failure"));
+        }
+
+        if state.zero_writes {
+            self.inner.write_all(&vec![0; bytes.len()])?;
+        } else {
+            self.inner.write_all(bytes)?;
+        }
+
+        if state.crash_after_write == Some(state.n_writes) {
+            state.crashed = true;
+        }
+
+        Ok(())
+    }
+
+    fn set_len(&mut self, size: u64) -> Result<(), Error> {
+        check_not_crashed(&self.state.lock().unwrap())?;
+        self.inner.set_len(size)
+    }
+
+    fn sync_all(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        check_not_crashed(&state)?;
+
+        let mut contents = vec![0; self.inner.size()? as usize];
+        self.inner.read_at(&mut contents, 0)?;
+        state.synced.insert(self.path.clone(), contents);
+        state.n_syncs += 1;
+        *state.n_syncs_by_file.entry(self.path.clone()).or_insert(0) += 1;
+
+        self.inner.sync_all()
+    }
+
+    fn size(&self) -> Result<u64, Error> {
+        self.inner.size()
+    }
+}
@@ -1,3 +1,8 @@
+pub mod backup_iterator;
 pub mod bloom_filter;
+pub(crate) mod clock;
+#[cfg(test)]
+pub(crate) mod faulty_file_system;
 pub mod storage_engine_iterator;
 pub mod tombstone;
+pub mod ttl;
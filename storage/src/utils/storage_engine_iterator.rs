@@ -1,6 +1,7 @@
 use crate::transactions::transaction::Transaction;
 use crate::transactions::transaction_manager::TransactionManager;
-use crate::utils::tombstone::TOMBSTONE;
+use crate::utils::tombstone::{is_range_deleted, merge_values, RangeTombstone, TOMBSTONE};
+use crate::utils::ttl;
 use bytes::Bytes;
 use shared::iterators::storage_iterator::StorageIterator;
 use shared::key::Key;
@@ -14,6 +15,11 @@ use std::sync::Arc;
 //This iterator merges the values by the merger function defined in SimpleDbOptions
 //And commits the transaction when the iterator is dropped if the iterator was created in "standalone" mode
 // which means when the transaction was created only for the iterator, (for example: call to Storage::scan_from or Storage::scan_all)
+//
+//Versions of the same key visible by the transaction are read ordered by txn_id in ascending order (see Key ordering).
+//For each key, only the newest visible version (the highest txn_id <= transaction's txn_id) is returned, and the key
+//is skipped if it is a tombstone. When merging the versions with the merger function, the newest visible version is
+//always passed last, and its key is the one returned. Compactions keep every version, see keep_all_versions
 pub struct StorageEngineIterator<I: StorageIterator> {
     options: Arc<shared::SimpleDbOptions>,
     inner_iterator: I,
@@ -25,12 +31,25 @@ pub struct StorageEngineIterator<I: StorageIterator> {
 
     transaction_manager: Option<Arc<TransactionManager>>,
     transaction: Option<Transaction>,
+    //Serializable transaction whose read keys are tracked, see Transaction::on_read
+    reads_transaction: Option<(Transaction, shared::KeyspaceId)>,
 
     is_finished: bool,
 
     keyspace_flags: Flag,
 
     first_iteration: bool,
+
+    //Expired entries are never returned. If false, the values of keys with Key::has_ttl are returned with
+    //their expiration time, so that they can be rewritten by compactions
+    strip_expiration_time: bool,
+
+    //Entries covered by these range tombstones are not returned
+    range_tombstones: Vec<RangeTombstone>,
+
+    //If true, every visible version of the keys is returned when there is no merger function, including
+    //tombstones, so that compactions don't drop versions that older transactions can still read
+    keep_all_versions: bool,
 }
 
 impl<I: StorageIterator> StorageEngineIterator<I> {
@@ -39,12 +58,6 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
         options: &Arc<shared::SimpleDbOptions>,
         iterator: I,
     ) -> StorageEngineIterator<I> {
-        let mut is_finished = false;
-
-        if !iterator.has_next() {
-            is_finished = true
-        }
-
         StorageEngineIterator {
             entries_to_return: VecDeque::new(),
             transaction_manager: None,
@@ -54,8 +67,12 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
             current_value: None,
             current_key: None,
             transaction: None,
+            reads_transaction: None,
+            strip_expiration_time: true,
+            range_tombstones: Vec::new(),
+            keep_all_versions: false,
+            is_finished: false,
             keyspace_flags,
-            is_finished,
         }
     }
 
@@ -68,38 +85,55 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
         self.transaction = Some(transaction);
     }
 
+    pub fn keep_expiration_time(&mut self) {
+        self.strip_expiration_time = false;
+    }
+
+    pub(crate) fn keep_all_versions(&mut self) {
+        self.keep_all_versions = true;
+    }
+
+    pub(crate) fn set_range_tombstones(&mut self, range_tombstones: Vec<RangeTombstone>) {
+        self.range_tombstones = range_tombstones;
+    }
+
+    pub fn track_reads(&mut self, transaction: &Transaction, keyspace_id: shared::KeyspaceId) {
+        self.reads_transaction = Some((transaction.clone(), keyspace_id));
+    }
+
+    //Returns up to n entries, the same ones that n calls to next() would return. The last returned entry
+    //is the current one, see StorageIterator::key & StorageIterator::value
+    pub fn next_batch(&mut self, n: usize) -> Vec<(Key, Bytes)> {
+        let mut batch = Vec::with_capacity(n);
+        while batch.len() < n && self.next() {
+            batch.push((self.current_key.clone().unwrap(), self.current_value.clone().unwrap()));
+        }
+
+        batch
+    }
+
     fn find_entries(&mut self) -> bool {
         loop {
             if self.is_finished {
                 return false;
             }
 
-            self.entries_to_return.push_back((
-                self.inner_iterator.key().clone(),
-                Bytes::copy_from_slice(self.inner_iterator.value()))
-            );
+            self.push_entry_to_return();
 
             let current_key_bytes = Bytes::copy_from_slice(self.inner_iterator.key().as_bytes());
 
-            let mut has_next = self.inner_iterator.has_next();
-
-            while has_next {
-                self.inner_iterator.next();
-                let next_key = self.inner_iterator.key();
-
-                if next_key.bytes_eq_bytes(&current_key_bytes) {
-                    self.entries_to_return.push_back((
-                        self.inner_iterator.key().clone(),
-                        Bytes::copy_from_slice(self.inner_iterator.value()))
-                    );
-                    has_next = self.inner_iterator.has_next();
-                } else {
-                    break
+            //has_next() of the inner iterator might return true even if the remaining entries are not visible,
+            //so only the result of next() is used
+            loop {
+                if !self.inner_iterator.next() {
+                    self.is_finished = true;
+                    break;
+                }
+                if !self.inner_iterator.key().bytes_eq_bytes(&current_key_bytes) {
+                    break;
                 }
-            }
 
-            if !has_next {
-                self.is_finished = true;
+                self.push_entry_to_return();
             }
 
             if self.merge_entry_values() {
@@ -108,8 +142,20 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
         }
     }
 
+    fn push_entry_to_return(&mut self) {
+        let key = self.inner_iterator.key();
+        let value = self.inner_iterator.value();
+
+        if !ttl::is_expired(key, value) && !is_range_deleted(&self.range_tombstones, key) {
+            self.entries_to_return.push_back((key.clone(), Bytes::copy_from_slice(value)));
+        }
+    }
+
     //Returns true if it merged a value that can be returned to the user of the iterator
     fn merge_entry_values(&mut self) -> bool {
+        if self.options.storage_value_merger.is_none() && !self.keep_all_versions {
+            return self.keep_newest_entry();
+        }
         if self.options.storage_value_merger.is_none() || self.entries_to_return.len() <= 1 {
             return self.check_some_keys_in_entries_to_return_readable();
         }
@@ -119,11 +165,13 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
 
         while let Some((next_key, next_value)) = self.entries_to_return.pop_front() {
             match prev_merged_value.take() {
-                Some((_, previous_merged_value)) => {
-                    match merge_fn(&previous_merged_value, &next_value, self.keyspace_flags) {
+                Some((previous_merged_key, previous_merged_value)) => {
+                    match merge_values(merge_fn, (&previous_merged_key, &previous_merged_value), (&next_key, &next_value), self.keyspace_flags) {
                         StorageValueMergeResult::Ok(merged_value) => prev_merged_value = Some((next_key, merged_value)),
                         StorageValueMergeResult::DiscardPreviousKeepNew => prev_merged_value = Some((next_key, next_value)),
                         StorageValueMergeResult::DiscardPreviousAndNew => {}
+                        //Tombstones are not returned, see check_some_keys_in_entries_to_return_readable
+                        StorageValueMergeResult::Delete => prev_merged_value = Some((next_key.with_tombstone(true), TOMBSTONE)),
                     }
                 },
                 None => {
@@ -132,31 +180,43 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
             }
         }
 
-        let (final_key, final_value) = prev_merged_value.take().unwrap();
-        self.entries_to_return.push_front((final_key, final_value));
-        self.check_some_keys_in_entries_to_return_readable()
+        match prev_merged_value.take() {
+            Some((final_key, final_value)) => {
+                self.entries_to_return.push_front((final_key, final_value));
+                self.check_some_keys_in_entries_to_return_readable()
+            },
+            //The last merge discarded both values
+            None => false
+        }
     }
 
-    fn check_some_keys_in_entries_to_return_readable(&self) -> bool {
-        for (_, value) in &self.entries_to_return {
-            if !value.eq(&TOMBSTONE) {
-                return true;
-            }
+    //Versions are ordered by txn_id, so the newest visible one is the last
+    fn keep_newest_entry(&mut self) -> bool {
+        let newest_entry = self.entries_to_return.pop_back();
+        self.entries_to_return.clear();
+
+        match newest_entry {
+            Some(newest_entry) if !newest_entry.0.is_tombstone() => {
+                self.entries_to_return.push_back(newest_entry);
+                true
+            },
+            _ => false,
         }
+    }
 
-        false
+    fn check_some_keys_in_entries_to_return_readable(&self) -> bool {
+        self.entries_to_return.iter()
+            .any(|(key, _)| !key.is_tombstone())
     }
 
     fn do_do_next(&mut self) -> bool {
-        if self.is_finished {
-            return false;
-        }
+        //Entries of the last key might still need to be returned, even if the inner iterator is finished
         if self.entries_to_return.is_empty() && !self.find_entries() {
             return false;
         }
 
         let (next_key, next_value) = self.entries_to_return.pop_front().unwrap();
-        self.current_value = Some(next_value);
+        self.current_value = if self.strip_expiration_time { Some(ttl::user_value(&next_key, next_value)) } else { Some(next_value) };
         self.current_key = Some(next_key);
 
         true
@@ -164,17 +224,34 @@ impl<I: StorageIterator> StorageEngineIterator<I> {
 }
 
 impl<I: StorageIterator> StorageIterator for StorageEngineIterator<I> {
+    //Like the rest of the iterators, it doesn't point to any entry until next() is called for the first time
     fn next(&mut self) -> bool {
         if self.first_iteration {
-            self.inner_iterator.next();
+            //The inner iterator might have no entries after a seek
+            if !self.inner_iterator.next() {
+                self.is_finished = true;
+            }
             self.first_iteration = false;
         }
 
-        self.do_do_next()
+        let advanced = self.do_do_next();
+        if advanced {
+            if let Some((transaction, keyspace_id)) = &self.reads_transaction {
+                transaction.on_read(*keyspace_id, self.current_key.as_ref().unwrap().as_bytes());
+            }
+        }
+
+        advanced
     }
 
+    //It might return true if the remaining entries are not visible or have been deleted
     fn has_next(&self) -> bool {
-        todo!()
+        if self.first_iteration {
+            self.inner_iterator.has_next()
+        } else {
+            //If it is not finished, the inner iterator points to an entry that hasn't been returned yet
+            !self.entries_to_return.is_empty() || !self.is_finished
+        }
     }
 
     fn key(&self) -> &Key {
@@ -187,6 +264,13 @@ impl<I: StorageIterator> StorageIterator for StorageEngineIterator<I> {
 
     fn seek(&mut self, key: &Bytes, inclusive: bool) {
         self.inner_iterator.seek(key, inclusive);
+        self.entries_to_return.clear();
+        self.first_iteration = true;
+        self.is_finished = false;
+    }
+
+    fn take_error(&mut self) -> Option<shared::SimpleDbError> {
+        self.inner_iterator.take_error()
     }
 }
 
@@ -203,6 +287,7 @@ mod test {
     use crate::memtables::memtable::MemTable;
     use crate::memtables::memtable_iterator::MemtableIterator;
     use crate::transactions::transaction::Transaction;
+    use crate::transactions::transaction_manager::IsolationLevel;
     use crate::utils::storage_engine_iterator::StorageEngineIterator;
     use bytes::Bytes;
     use shared::iterators::storage_iterator::StorageIterator;
@@ -267,11 +352,10 @@ mod test {
         assert!(iterator.next());
         assert!(iterator.key().eq(&Key::create_from_str("aa", 10)));
 
-        assert!(iterator.next());
-        assert!(iterator.key().eq(&Key::create_from_str("alberto", 1)));
-
+        //Only the newest version is returned
         assert!(iterator.next());
         assert!(iterator.key().eq(&Key::create_from_str("alberto", 3)));
+        assert!(iterator.value().eq(&vec![4]));
 
         assert!(iterator.next());
         assert!(iterator.key().eq(&Key::create_from_str("gonchi", 1)));
@@ -288,17 +372,42 @@ mod test {
         assert!(!iterator.next());
     }
 
-    fn merge_values(a: &Bytes, b: &Bytes) -> StorageValueMergeResult {
-        let a_vec = a.to_vec();
-        let b_vec = b.to_vec();
-
-        if b_vec[0] == 10 {
-            StorageValueMergeResult::DiscardPreviousKeepNew
-        } else if a_vec[0] == 10 {
-            StorageValueMergeResult::Ok(b.clone())
-        } else {
-            StorageValueMergeResult::Ok(Bytes::from(vec![a_vec[0] + b_vec[0]]))
+    #[test]
+    fn next_batch() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, 0).unwrap());
+        memtable.set(&transaction(1), Bytes::from("a"), &[1]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("b"), &[2]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("c"), &[3]).unwrap();
+        memtable.delete(&transaction(2), Bytes::from("c")).unwrap();
+        memtable.set(&transaction(1), Bytes::from("d"), &[4]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("e"), &[5]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("f"), &[6]).unwrap();
+
+        let mut iterator = StorageEngineIterator::create(0, &options, MemtableIterator::create(&memtable, &Transaction::none()));
+        let mut expected = Vec::new();
+        while iterator.next() {
+            expected.push((iterator.key().clone(), Bytes::copy_from_slice(iterator.value())));
         }
+
+        let mut batch_iterator = StorageEngineIterator::create(0, &options, MemtableIterator::create(&memtable, &Transaction::none()));
+        let batch = batch_iterator.next_batch(3);
+        assert_eq!(batch, expected[..3].to_vec());
+        assert!(batch_iterator.key().eq(&batch[2].0));
+        assert_eq!(batch_iterator.next_batch(100), expected[3..].to_vec());
+        assert!(batch_iterator.next_batch(3).is_empty());
+
+        //Starts from the seeked key
+        let mut seeked_iterator = StorageEngineIterator::create(0, &options, MemtableIterator::create(&memtable, &Transaction::none()));
+        seeked_iterator.seek(&Bytes::from("d"), true);
+        let batch = seeked_iterator.next_batch(2);
+        assert_eq!(batch.len(), 2);
+        assert!(batch[0].0.eq(&Key::create_from_str("d", 1)));
+        assert!(batch[1].0.eq(&Key::create_from_str("e", 1)));
+    }
+
+    fn merge_values(a: &Bytes, b: &Bytes) -> StorageValueMergeResult {
+        StorageValueMergeResult::Ok(Bytes::from(vec![a[0] + b[0]]))
     }
 
     #[test]
@@ -316,7 +425,8 @@ mod test {
         memtable.set(&transaction(5), Bytes::from("javier"), &vec![1]);
         memtable.set(&transaction(5), Bytes::from("jaime"), &vec![1]);
         memtable.set(&transaction(1), Bytes::from("wili"), &vec![1]);
-        memtable.set(&transaction(1), Bytes::from("wili"), &vec![10]); //10 Equivalent of tombstone
+        memtable.delete(&transaction(1), Bytes::from("wili")).unwrap();
+        memtable.set(&transaction(1), Bytes::from("wili"), &vec![10]); //Stored as data, not as a tombstone
         memtable.set(&transaction(1), Bytes::from("wili"), &vec![2]);
 
         let mut iterator = StorageEngineIterator::create(
@@ -343,11 +453,140 @@ mod test {
 
         assert!(iterator.next());
         assert!(iterator.key().eq(&Key::create_from_str("wili", 1)));
+        assert!(iterator.value().eq(&vec![12]));
+
+        assert!(!iterator.next());
+    }
+
+    //Values are counter increments, the key is deleted when the counter reaches 0
+    fn merge_counter(a: &Bytes, b: &Bytes) -> StorageValueMergeResult {
+        let counter = a[0] as i8 + b[0] as i8;
+        if counter == 0 {
+            StorageValueMergeResult::Delete
+        } else {
+            StorageValueMergeResult::Ok(Bytes::from(vec![counter as u8]))
+        }
+    }
+
+    #[test]
+    fn iterator_merger_fn_delete() {
+        let options = shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .storage_value_merger(|a, b, _| merge_counter(a, b))
+            .build_arc();
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, 0).unwrap());
+        memtable.set(&transaction(1), Bytes::from("alberto"), &[2]).unwrap();
+        memtable.set(&transaction(2), Bytes::from("alberto"), &[-2i8 as u8]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("jaime"), &[1]).unwrap();
+        memtable.set(&transaction(2), Bytes::from("jaime"), &[-1i8 as u8]).unwrap();
+        memtable.set(&transaction(3), Bytes::from("jaime"), &[5]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("wili"), &[3]).unwrap();
+        memtable.set(&transaction(1), Bytes::from("wili"), &[-3i8 as u8]).unwrap();
+
+        let mut iterator = StorageEngineIterator::create(
+            0,
+            &options,
+            MemtableIterator::create(&memtable, &Transaction::none()),
+        );
+
+        //The counter starts over after being deleted
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("jaime", 3)));
+        assert!(iterator.value().eq(&vec![5]));
+        assert!(!iterator.next());
+    }
+
+    #[test]
+    fn iterator_newest_visible_version() {
+        let options = shared::start_simpledb_options_builder_from(&shared::SimpleDbOptions::default())
+            .storage_value_merger(|_, _, _| StorageValueMergeResult::DiscardPreviousKeepNew)
+            .build_arc();
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, 0).unwrap());
+        memtable.set(&transaction(1), Bytes::from("alberto"), &vec![1]);
+        memtable.set(&transaction(3), Bytes::from("alberto"), &vec![3]);
+        memtable.set(&transaction(5), Bytes::from("alberto"), &vec![5]);
+
+        let mut iterator = StorageEngineIterator::create(
+            0,
+            &options,
+            MemtableIterator::create(&memtable, &snapshot_transaction(4)),
+        );
+
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("alberto", 3)));
+        assert!(iterator.value().eq(&vec![3]));
+        assert!(!iterator.next());
+    }
+
+    #[test]
+    fn iterator_visible_versions_order() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, 0).unwrap());
+        memtable.set(&transaction(5), Bytes::from("alberto"), &vec![5]);
+        memtable.set(&transaction(3), Bytes::from("alberto"), &vec![3]);
+        memtable.set(&transaction(1), Bytes::from("alberto"), &vec![1]);
+
+        let mut iterator = StorageEngineIterator::create(
+            0,
+            &options,
+            MemtableIterator::create(&memtable, &snapshot_transaction(4)),
+        );
+
+        //Without a merger function, only the newest visible version is returned
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("alberto", 3)));
+        assert!(iterator.value().eq(&vec![3]));
+        assert!(!iterator.next());
+
+        //Compactions read every visible version in ascending order
+        let mut iterator = StorageEngineIterator::create(
+            0,
+            &options,
+            MemtableIterator::create(&memtable, &snapshot_transaction(4)),
+        );
+        iterator.keep_all_versions();
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("alberto", 1)));
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("alberto", 3)));
+        assert!(!iterator.next());
+    }
+
+    #[test]
+    fn iterator_newest_visible_version_deleted() {
+        let options = Arc::new(shared::SimpleDbOptions::default());
+        let memtable = Arc::new(MemTable::create_mock(options.clone(), 0, 0).unwrap());
+        memtable.set(&transaction(1), Bytes::from("alberto"), &[1]).unwrap();
+        memtable.delete(&transaction(2), Bytes::from("alberto")).unwrap();
+        memtable.delete(&transaction(1), Bytes::from("jaime")).unwrap();
+        memtable.set(&transaction(2), Bytes::from("jaime"), &[2]).unwrap();
+
+        let mut iterator = StorageEngineIterator::create(
+            0,
+            &options,
+            MemtableIterator::create(&memtable, &snapshot_transaction(3)),
+        );
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("jaime", 2)));
         assert!(iterator.value().eq(&vec![2]));
+        assert!(!iterator.next());
 
+        //The delete is not visible
+        let mut iterator = StorageEngineIterator::create(
+            0,
+            &options,
+            MemtableIterator::create(&memtable, &snapshot_transaction(1)),
+        );
+        assert!(iterator.next());
+        assert!(iterator.key().eq(&Key::create_from_str("alberto", 1)));
         assert!(!iterator.next());
     }
 
+    fn snapshot_transaction(txn_id: shared::TxnId) -> Transaction {
+        let mut transaction = transaction(txn_id);
+        transaction.isolation_level = IsolationLevel::SnapshotIsolation;
+        transaction
+    }
+
     fn transaction(txn_id: shared::TxnId) -> Transaction {
         let mut transaction = Transaction::none();
         transaction.txn_id = txn_id;
@@ -1,3 +1,71 @@
-use bytes::Bytes;
+use bytes::{Buf, BufMut, Bytes};
+use shared::key::Key;
+use shared::{Flag, StorageValueMergeResult, StorageValueMergerFn};
 
+//Value stored with tombstone keys. Whether a key has been deleted is given by Key::is_tombstone, not by its value
 pub const TOMBSTONE: Bytes = Bytes::new();
+
+//Tombstones are never passed to the merger function. A deleted key starts over with the new value,
+//and a delete always replaces the previous value. Values that expire are not merged either, they are replaced
+pub(crate) fn merge_values(
+    merger_fn: StorageValueMergerFn,
+    (prev_key, prev_value): (&Key, &Bytes),
+    (new_key, new_value): (&Key, &Bytes),
+    keyspace_flags: Flag
+) -> StorageValueMergeResult {
+    if prev_key.is_tombstone() || new_key.is_tombstone() || prev_key.has_ttl() || new_key.has_ttl() {
+        StorageValueMergeResult::DiscardPreviousKeepNew
+    } else {
+        merger_fn(prev_value, new_value, keyspace_flags)
+    }
+}
+
+//Written by Storage::delete_range. Hides the versions of the keys in [start, end) written by transactions older than
+//the range tombstone's transaction. Range tombstones are not stored with the rest of the keys: memtables keep them in a
+//separate list, and SSTables store them in their own section
+#[derive(Clone)]
+pub struct RangeTombstone {
+    //Contains the start of the range & the txn_id of the transaction that wrote it
+    pub(crate) start: Key,
+    pub(crate) end: Bytes,
+}
+
+impl RangeTombstone {
+    pub(crate) fn covers(&self, key: &Key) -> bool {
+        key.txn_id() < self.start.txn_id() &&
+            key.bytes_ge_bytes(self.start.as_bytes()) &&
+            key.bytes_lt_bytes(&self.end)
+    }
+
+    //Start key (see Key::serialize), end length (u16), end bytes
+    pub(crate) fn serialize(&self) -> Vec<u8> {
+        let mut serialized = self.start.serialize();
+        serialized.put_u16_le(self.end.len() as u16);
+        serialized.extend(self.end.as_ref());
+        serialized
+    }
+
+    pub(crate) fn deserialize(ptr: &mut &[u8]) -> Result<RangeTombstone, shared::DecodeErrorType> {
+        //Txn id (u64), flags (u8) & key length (u16)
+        if ptr.len() < 11 {
+            return Err(shared::DecodeErrorType::IllegalSize(11, ptr.len()));
+        }
+        let start_key_size = Key::serialized_key_size(&mut &ptr[..]);
+        if ptr.len() < start_key_size + 2 {
+            return Err(shared::DecodeErrorType::IllegalSize(start_key_size + 2, ptr.len()));
+        }
+        let start = Key::deserialize(ptr);
+        let end_length = ptr.get_u16_le() as usize;
+        if ptr.len() < end_length {
+            return Err(shared::DecodeErrorType::IllegalSize(end_length, ptr.len()));
+        }
+        let end = Bytes::copy_from_slice(&ptr[..end_length]);
+        ptr.advance(end_length);
+
+        Ok(RangeTombstone { start, end })
+    }
+}
+
+pub(crate) fn is_range_deleted(range_tombstones: &[RangeTombstone], key: &Key) -> bool {
+    range_tombstones.iter().any(|range_tombstone| range_tombstone.covers(key))
+}
@@ -0,0 +1,48 @@
+use bytes::{Buf, BufMut, Bytes};
+use shared::key::Key;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+//Values of keys with Key::has_ttl start with the time they expire at, in millis since the epoch (u64 little endian)
+const EXPIRE_AT_SIZE: usize = 8;
+
+pub(crate) fn expire_at_from_ttl(ttl: Duration) -> u64 {
+    now_millis().saturating_add(ttl.as_millis() as u64)
+}
+
+pub(crate) fn encode_value(expire_at: u64, value: &[u8]) -> Bytes {
+    let mut encoded = Vec::with_capacity(EXPIRE_AT_SIZE + value.len());
+    encoded.put_u64_le(expire_at);
+    encoded.extend_from_slice(value);
+    Bytes::from(encoded)
+}
+
+//Expired versions are treated as if they had never been written,
+//so an expired version doesn't hide older versions of the key that haven't expired yet
+pub(crate) fn is_expired(key: &Key, value: &[u8]) -> bool {
+    key.has_ttl() && value.len() >= EXPIRE_AT_SIZE && (&value[..EXPIRE_AT_SIZE]).get_u64_le() <= now_millis()
+}
+
+//Returns None if the key has no ttl
+pub(crate) fn expire_at(key: &Key, value: &[u8]) -> Option<u64> {
+    if key.has_ttl() && value.len() >= EXPIRE_AT_SIZE {
+        Some((&value[..EXPIRE_AT_SIZE]).get_u64_le())
+    } else {
+        None
+    }
+}
+
+//Returns the value written by the user, without its expiration time
+pub(crate) fn user_value(key: &Key, value: Bytes) -> Bytes {
+    if key.has_ttl() && value.len() >= EXPIRE_AT_SIZE {
+        value.slice(EXPIRE_AT_SIZE..)
+    } else {
+        value
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}